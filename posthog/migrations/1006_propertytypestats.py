@@ -0,0 +1,78 @@
+# Generated by Django 4.2.28 on 2026-10-18 13:00
+
+import django.db.models.deletion
+import django.db.models.functions.comparison
+from django.db import migrations, models
+
+import posthog.models.utils
+
+
+class Migration(migrations.Migration):
+    dependencies = [
+        ("posthog", "1005_propertyvaluestats"),
+    ]
+
+    operations = [
+        migrations.CreateModel(
+            name="PropertyTypeStats",
+            fields=[
+                (
+                    "id",
+                    models.UUIDField(
+                        default=posthog.models.utils.uuid7,
+                        editable=False,
+                        primary_key=True,
+                        serialize=False,
+                    ),
+                ),
+                ("name", models.CharField(max_length=400)),
+                (
+                    "type",
+                    models.PositiveSmallIntegerField(
+                        choices=[(1, "event"), (2, "person"), (3, "group"), (4, "session")],
+                        default=1,
+                    ),
+                ),
+                ("group_type_index", models.PositiveSmallIntegerField(null=True)),
+                ("observations", models.JSONField(default=list)),
+                ("updated_at", models.DateTimeField()),
+                (
+                    "project",
+                    models.ForeignKey(
+                        null=True,
+                        on_delete=django.db.models.deletion.CASCADE,
+                        to="posthog.project",
+                    ),
+                ),
+                (
+                    "team",
+                    models.ForeignKey(
+                        on_delete=django.db.models.deletion.CASCADE,
+                        related_name="property_type_stats",
+                        to="posthog.team",
+                    ),
+                ),
+            ],
+            options={
+                "indexes": [
+                    models.Index(
+                        django.db.models.functions.comparison.Coalesce(models.F("project_id"), models.F("team_id")),
+                        models.F("type"),
+                        django.db.models.functions.comparison.Coalesce(models.F("group_type_index"), -1),
+                        models.F("name"),
+                        name="posthog_proptypestats_proj",
+                    )
+                ],
+            },
+        ),
+        migrations.AddConstraint(
+            model_name="propertytypestats",
+            constraint=models.UniqueConstraint(
+                models.F("team"),
+                models.F("type"),
+                django.db.models.functions.comparison.Coalesce(models.F("group_type_index"), -1),
+                models.F("name"),
+                name="posthog_proptypestats_uniq",
+            ),
+        ),
+    ]
@@ -75,7 +75,7 @@ from .plugin import Plugin, PluginAttachment, PluginConfig, PluginLogEntry, Plug
 from .product_intent import ProductIntent
 from .project import Project
 from .property import Property
-from .property_definition import PropertyDefinition
+from .property_definition import PropertyDefinition, PropertyTypeStats, PropertyValueStats
 from .proxy_record import ProxyRecord
 from .quick_filter import QuickFilter
 from .remote_config import RemoteConfig
@@ -198,6 +198,8 @@ __all__ = [
     "Project",
     "Property",
     "PropertyDefinition",
+    "PropertyTypeStats",
+    "PropertyValueStats",
     "ProxyRecord",
     "QuickFilter",
     "RetentionFilter",
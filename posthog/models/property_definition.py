@@ -5,7 +5,7 @@ from django.db.models.functions import Coalesce
 
 from posthog.clickhouse.table_engines import ReplacingMergeTree, ReplicationScheme
 from posthog.models.team import Team
-from posthog.models.utils import UniqueConstraintByExpression, UUIDTModel
+from posthog.models.utils import UniqueConstraintByExpression, UUIDModel, UUIDTModel
 from posthog.settings.data_stores import CLICKHOUSE_DATABASE
 
 
@@ -131,6 +131,87 @@ class PropertyDefinition(UUIDTModel):
         return None
 
 
+class PropertyValueStats(UUIDModel):
+    """
+    Recently seen distinct values and approximate most common values of a property, maintained by
+    property-defs-rs to serve filter autocomplete without querying ClickHouse.
+    """
+
+    team = models.ForeignKey(Team, on_delete=models.CASCADE, related_name="property_value_stats")
+    project = models.ForeignKey("Project", on_delete=models.CASCADE, null=True)
+    name = models.CharField(max_length=400)
+    type = models.PositiveSmallIntegerField(
+        default=PropertyDefinition.Type.EVENT, choices=PropertyDefinition.Type.choices
+    )
+    # Only set for group properties, which are tracked per group type
+    group_type_index = models.PositiveSmallIntegerField(null=True)
+    # Most recently seen first, e.g. ["Chrome", "Safari"]
+    sample_values = models.JSONField(default=list)
+    # Highest count first, e.g. [{"value": "Chrome", "count": 120}]
+    top_values = models.JSONField(default=list)
+    updated_at = models.DateTimeField()
+
+    class Meta:
+        constraints = [
+            models.UniqueConstraint(
+                F("team"),
+                F("type"),
+                Coalesce(F("group_type_index"), -1),
+                F("name"),
+                name="posthog_propvaluestats_uniq",
+            ),
+        ]
+        indexes = [
+            models.Index(
+                Coalesce(F("project_id"), F("team_id")),
+                F("type"),
+                Coalesce(F("group_type_index"), -1),
+                F("name"),
+                name="posthog_propvaluestats_proj",
+            ),
+        ]
+
+
+class PropertyTypeStats(UUIDModel):
+    """
+    How often a property's values are detected as each type, maintained by property-defs-rs for
+    properties seen with more than one type, or a type other than their definition's.
+    """
+
+    team = models.ForeignKey(Team, on_delete=models.CASCADE, related_name="property_type_stats")
+    project = models.ForeignKey("Project", on_delete=models.CASCADE, null=True)
+    name = models.CharField(max_length=400)
+    type = models.PositiveSmallIntegerField(
+        default=PropertyDefinition.Type.EVENT, choices=PropertyDefinition.Type.choices
+    )
+    # Only set for group properties, which are tracked per group type
+    group_type_index = models.PositiveSmallIntegerField(null=True)
+    # Most common type first, e.g. [{"property_type": "Numeric", "count": 80, "first_seen_at": ...,
+    # "last_seen_at": ..., "sample_values": ["42"]}, {"property_type": "String", "count": 20, ...}]
+    observations = models.JSONField(default=list)
+    updated_at = models.DateTimeField()
+
+    class Meta:
+        constraints = [
+            models.UniqueConstraint(
+                F("team"),
+                F("type"),
+                Coalesce(F("group_type_index"), -1),
+                F("name"),
+                name="posthog_proptypestats_uniq",
+            ),
+        ]
+        indexes = [
+            models.Index(
+                Coalesce(F("project_id"), F("team_id")),
+                F("type"),
+                Coalesce(F("group_type_index"), -1),
+                F("name"),
+                name="posthog_proptypestats_proj",
+            ),
+        ]
+
+
 # ClickHouse Table DDL
 
 PROPERTY_DEFINITIONS_TABLE_SQL = (
@@ -16,6 +16,51 @@ RUST_HOGVM_STL = [
     "notEmpty",
     "match",
     "JSONExtract",
+    "multiSearchAnyCaseInsensitive",
+    "lower",
+    "upper",
+    "reverse",
+    "concat",
+    "like",
+    "ilike",
+    "notLike",
+    "notILike",
+    "replaceOne",
+    "replaceAll",
+    "position",
+    "positionCaseInsensitive",
+    "trim",
+    "trimLeft",
+    "trimRight",
+    "splitByString",
+    "startsWith",
+    "substring",
+    "empty",
+    "isNull",
+    "isNotNull",
+    "toInt",
+    "toFloat",
+    "base64Encode",
+    "base64Decode",
+    "encodeURLComponent",
+    "decodeURLComponent",
+    "round",
+    "floor",
+    "now",
+    "toDateTime",
+    "toDate",
+    "fromUnixTimestamp",
+    "fromUnixTimestampMilli",
+    "toUnixTimestamp",
+    "toUnixTimestampMilli",
+    "toTimeZone",
+    "formatDateTime",
+    "md5Hex",
+    "md5",
+    "sha256Hex",
+    "sha256",
+    "sha256HmacChainHex",
+    "sha256HmacChain",
     "arrayReduce",
     "arrayCount",
     "arrayFilter",
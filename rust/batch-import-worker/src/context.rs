@@ -40,20 +40,14 @@ impl AppContext {
             "Using in-memory cache for identify events (capacity: {}, TTL: {}s)",
             config.identify_memory_cache_capacity, config.identify_memory_cache_ttl_seconds
         );
-        let identify_cache: Arc<dyn IdentifyCache> = Arc::new(MemoryIdentifyCache::new(
-            config.identify_memory_cache_capacity,
-            Duration::from_secs(config.identify_memory_cache_ttl_seconds),
-        ));
+        let identify_cache = Self::new_identify_cache(config);
 
         // Initialize the group cache - memory-only implementation
         info!(
             "Using in-memory cache for group events (capacity: {}, TTL: {}s)",
             config.group_memory_cache_capacity, config.group_memory_cache_ttl_seconds
         );
-        let group_cache: Arc<dyn GroupCache> = Arc::new(MemoryGroupCache::new(
-            config.group_memory_cache_capacity,
-            Duration::from_secs(config.group_memory_cache_ttl_seconds),
-        ));
+        let group_cache = Self::new_group_cache(config);
 
         let person_processing_filter =
             PersonProcessingFilter::new(&config.force_disable_person_processing);
@@ -77,6 +71,20 @@ impl AppContext {
         Ok(ctx)
     }
 
+    pub fn new_identify_cache(config: &Config) -> Arc<dyn IdentifyCache> {
+        Arc::new(MemoryIdentifyCache::new(
+            config.identify_memory_cache_capacity,
+            Duration::from_secs(config.identify_memory_cache_ttl_seconds),
+        ))
+    }
+
+    pub fn new_group_cache(config: &Config) -> Arc<dyn GroupCache> {
+        Arc::new(MemoryGroupCache::new(
+            config.group_memory_cache_capacity,
+            Duration::from_secs(config.group_memory_cache_ttl_seconds),
+        ))
+    }
+
     pub async fn get_token_for_team_id(&self, team_id: i32) -> Result<String, Error> {
         Ok(
             sqlx::query_scalar!("SELECT api_token FROM posthog_team WHERE id = $1", team_id)
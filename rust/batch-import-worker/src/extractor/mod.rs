@@ -5,6 +5,8 @@ use std::{path::Path, path::PathBuf, sync::Arc};
 use tokio::{fs::File, io::AsyncWriteExt};
 use tracing::debug;
 
+use crate::error::ToUserError;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ExtractorType {
@@ -181,77 +183,176 @@ impl PartExtractor for PlainGzipExtractor {
         file_path: &Path,
         temp_dir: &Path,
     ) -> Result<ExtractedPartData, Error> {
-        let data_file_path = temp_dir.join(format!("{}.data", key.replace(':', "_")));
-        let mut output_file = File::create(&data_file_path)
-            .await
-            .with_context(|| format!("Failed to create data file for key: {key}"))?;
+        decompress_to_seekable_file(key, file_path, temp_dir, "Gzip", |file| {
+            Ok(Box::new(flate2::read::GzDecoder::new(file)))
+        })
+        .await
+    }
+}
 
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<Result<Vec<u8>, Error>>(16);
+pub struct ZstdExtractor;
 
-        let decompress_handle = tokio::task::spawn_blocking({
-            let file_path = file_path.to_path_buf();
+#[async_trait]
+impl PartExtractor for ZstdExtractor {
+    async fn extract_compressed_to_seekable_file(
+        &self,
+        key: &str,
+        file_path: &Path,
+        temp_dir: &Path,
+    ) -> Result<ExtractedPartData, Error> {
+        decompress_to_seekable_file(key, file_path, temp_dir, "Zstd", |file| {
+            Ok(Box::new(zstd::stream::read::Decoder::new(file)?))
+        })
+        .await
+    }
+}
 
-            move || -> Result<bool, Error> {
-                use flate2::read::GzDecoder;
-                use std::fs::File as StdFile;
-                use std::io::Read;
-
-                let input_file = StdFile::open(file_path)
-                    .context("Failed to open gzip file for decompression")?;
-                let mut decoder = GzDecoder::new(input_file);
-                let mut buffer = [0u8; 8192];
-                let mut last_byte = None;
-
-                loop {
-                    let bytes_read = decoder.read(&mut buffer)?;
-                    if bytes_read == 0 {
-                        break;
-                    }
+type DecoderFn = fn(std::fs::File) -> Result<Box<dyn std::io::Read + Send>, std::io::Error>;
+
+// Streams a single compressed file through a decoder into a data file, adding a trailing newline
+// if the decompressed data doesn't end with one
+async fn decompress_to_seekable_file(
+    key: &str,
+    file_path: &Path,
+    temp_dir: &Path,
+    compression: &'static str,
+    decoder: DecoderFn,
+) -> Result<ExtractedPartData, Error> {
+    let data_file_path = temp_dir.join(format!("{}.data", key.replace(':', "_")));
+    let mut output_file = File::create(&data_file_path)
+        .await
+        .with_context(|| format!("Failed to create data file for key: {key}"))?;
 
-                    if bytes_read > 0 {
-                        last_byte = Some(buffer[bytes_read - 1]);
-                    }
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<Result<Vec<u8>, Error>>(16);
 
-                    if tx.blocking_send(Ok(buffer[..bytes_read].to_vec())).is_err() {
-                        break;
-                    }
+    let decompress_handle = tokio::task::spawn_blocking({
+        let file_path = file_path.to_path_buf();
+
+        move || -> Result<bool, Error> {
+            use std::fs::File as StdFile;
+            use std::io::Read;
+
+            let input_file = StdFile::open(file_path)
+                .with_context(|| format!("Failed to open {compression} file for decompression"))?;
+            let mut decoder = decoder(input_file)
+                .with_context(|| format!("Failed to start {compression} decompression"))?;
+            let mut buffer = [0u8; 8192];
+            let mut last_byte = None;
+
+            loop {
+                let bytes_read = decoder.read(&mut buffer)?;
+                if bytes_read == 0 {
+                    break;
                 }
 
-                Ok(last_byte != Some(b'\n'))
+                if bytes_read > 0 {
+                    last_byte = Some(buffer[bytes_read - 1]);
+                }
+
+                if tx.blocking_send(Ok(buffer[..bytes_read].to_vec())).is_err() {
+                    break;
+                }
             }
-        });
 
-        let mut total_size = 0usize;
-        while let Some(chunk_result) = rx.recv().await {
-            let chunk = chunk_result?;
-            output_file
-                .write_all(&chunk)
-                .await
-                .with_context(|| "Failed to write decompressed data to file")?;
-            total_size += chunk.len();
+            Ok(last_byte != Some(b'\n'))
         }
+    });
 
-        let needs_newline = decompress_handle
+    let mut total_size = 0usize;
+    while let Some(chunk_result) = rx.recv().await {
+        let chunk = chunk_result?;
+        output_file
+            .write_all(&chunk)
             .await
-            .context("Gzip decompression task panicked")??;
-        let final_size = if needs_newline && total_size > 0 {
-            output_file
-                .write_all(b"\n")
-                .await
-                .with_context(|| "Failed to write newline to file")?;
-            total_size + 1
-        } else {
-            total_size
-        };
+            .with_context(|| "Failed to write decompressed data to file")?;
+        total_size += chunk.len();
+    }
 
+    let needs_newline = decompress_handle
+        .await
+        .with_context(|| format!("{compression} decompression task panicked"))??;
+    let final_size = if needs_newline && total_size > 0 {
         output_file
-            .sync_all()
+            .write_all(b"\n")
             .await
-            .with_context(|| format!("Failed to sync output file to disk for key: {key}"))?;
+            .with_context(|| "Failed to write newline to file")?;
+        total_size + 1
+    } else {
+        total_size
+    };
+
+    output_file
+        .sync_all()
+        .await
+        .with_context(|| format!("Failed to sync output file to disk for key: {key}"))?;
+
+    Ok(ExtractedPartData {
+        data_file_path,
+        data_file_size: final_size,
+    })
+}
+
+// Converts a parquet file to json lines, one object per row, keyed by column name
+pub struct ParquetExtractor;
+
+#[async_trait]
+impl PartExtractor for ParquetExtractor {
+    async fn extract_compressed_to_seekable_file(
+        &self,
+        key: &str,
+        file_path: &Path,
+        temp_dir: &Path,
+    ) -> Result<ExtractedPartData, Error> {
+        let data_file_path = temp_dir.join(format!("{}.data", key.replace(':', "_")));
+
+        let data_file_size = tokio::task::spawn_blocking({
+            let file_path = file_path.to_path_buf();
+            let data_file_path = data_file_path.clone();
+            let key = key.to_string();
+
+            move || -> Result<usize, Error> {
+                use parquet::file::reader::{FileReader, SerializedFileReader};
+                use std::fs::File as StdFile;
+                use std::io::{BufWriter, Write};
+
+                let input_file =
+                    StdFile::open(file_path).context("Failed to open parquet file")?;
+                let reader = SerializedFileReader::new(input_file).user_error(format!(
+                    "'{key}' isn't a valid Parquet file. Check that every file in the source is Parquet."
+                ))?;
+                let mut output = BufWriter::new(
+                    StdFile::create(&data_file_path)
+                        .with_context(|| format!("Failed to create data file for key: {key}"))?,
+                );
+
+                let mut total_size = 0usize;
+                for row in reader
+                    .get_row_iter(None)
+                    .context("Failed to read parquet rows")?
+                {
+                    let row = row.user_error(format!("Failed to read a row of Parquet file '{key}'."))?;
+                    let mut line = serde_json::to_vec(&row.to_json_value())?;
+                    line.push(b'\n');
+                    output
+                        .write_all(&line)
+                        .context("Failed to write converted row to file")?;
+                    total_size += line.len();
+                }
+
+                output
+                    .into_inner()
+                    .map_err(|e| e.into_error())
+                    .and_then(|file| file.sync_all())
+                    .with_context(|| format!("Failed to sync output file to disk for key: {key}"))?;
+                Ok(total_size)
+            }
+        })
+        .await
+        .context("Parquet conversion task panicked")??;
 
         Ok(ExtractedPartData {
             data_file_path,
-            data_file_size: final_size,
+            data_file_size,
         })
     }
 }
@@ -363,6 +464,26 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_zstd_extractor_adds_newline_when_missing() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let temp_path = temp_dir.path();
+
+        let zstd_file = temp_path.join("test.zst");
+        let test_content = "line1\nline2\nline3"; // No trailing newline
+        std::fs::write(&zstd_file, zstd::encode_all(test_content.as_bytes(), 3)?)?;
+
+        let result = ZstdExtractor
+            .extract_compressed_to_seekable_file("test_key", &zstd_file, temp_path)
+            .await?;
+
+        let extracted_content = fs::read_to_string(&result.data_file_path).await?;
+        assert_eq!(extracted_content, format!("{test_content}\n"));
+        assert_eq!(result.data_file_size, test_content.len() + 1);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_zip_gzip_json_extractor_single_file() -> Result<()> {
         let temp_dir = TempDir::new()?;
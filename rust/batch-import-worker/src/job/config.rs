@@ -2,7 +2,10 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use anyhow::Error;
 use aws_config::{retry::RetryConfig, timeout::TimeoutConfig, BehaviorVersion, Region};
-use base64::{prelude::BASE64_URL_SAFE, Engine};
+use base64::{
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE},
+    Engine,
+};
 use chrono::{DateTime, Utc};
 use fernet::MultiFernet;
 use serde::{Deserialize, Serialize};
@@ -11,19 +14,24 @@ use serde_json::Value;
 use crate::{
     context::AppContext,
     emit::{kafka::KafkaEmitter, Emitter, FileEmitter, NoOpEmitter, StdoutEmitter},
+    error::ToUserError,
     extractor::ExtractorType,
     parse::format::FormatConfig,
     source::{
+        azure_blob::{AzureBlobAuth, AzureBlobSource},
+        compressed::CompressedSource,
         date_range_export::{AuthConfig, DateRangeExportSource},
         folder::FolderSource,
+        gcs::{GcsSource, ServiceAccountKey, DEFAULT_GCS_ENDPOINT},
         s3::S3Source,
         s3_gzip::GzipS3Source,
         url_list::UrlList,
         DataSource,
     },
+    transformation::TransformationConfig,
 };
 
-use super::model::JobModel;
+use super::{model::JobModel, preview::PreviewConfig};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -38,6 +46,12 @@ pub struct JobConfig {
     pub generate_identify_events: bool,
     #[serde(default = "JobConfig::default_generate_group_identify_events")]
     pub generate_group_identify_events: bool,
+    // An optional Hog program run against every event before it's emitted
+    #[serde(default)]
+    pub transformation: Option<TransformationConfig>,
+    // If set, the job is previewed rather than run, and paused once the preview is done
+    #[serde(default)]
+    pub preview: Option<PreviewConfig>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,6 +62,8 @@ pub enum SourceConfig {
     S3(S3SourceConfig),
     S3Gzip(S3SourceConfig),
     DateRangeExport(DateRangeExportSourceConfig),
+    Gcs(GcsSourceConfig),
+    AzureBlob(AzureBlobSourceConfig),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,6 +94,33 @@ pub struct S3SourceConfig {
     endpoint_url: Option<String>,
 }
 
+// Without a service account key, GCS requests are unauthenticated, e.g. against fake-gcs-server
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GcsSourceConfig {
+    bucket: String,
+    #[serde(default)]
+    prefix: String,
+    #[serde(default)]
+    service_account_key_key: Option<String>,
+    #[serde(default)]
+    endpoint_url: Option<String>,
+}
+
+// The account key takes precedence over the SAS token, if both are given
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AzureBlobSourceConfig {
+    account_name: String,
+    container: String,
+    #[serde(default)]
+    prefix: String,
+    #[serde(default)]
+    account_key_key: Option<String>,
+    #[serde(default)]
+    sas_token_key: Option<String>,
+    #[serde(default)]
+    endpoint_url: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DateRangeExportSourceConfig {
     base_url: String,
@@ -195,6 +238,13 @@ impl SourceConfig {
             SourceConfig::DateRangeExport(config) => {
                 Ok(Box::new(config.create_source(secrets).await?))
             }
+            // Object store parts may be gzip or zstd compressed, so we decompress them as they're prepared
+            SourceConfig::Gcs(config) => Ok(Box::new(CompressedSource::new(Box::new(
+                config.create_source(secrets)?,
+            )))),
+            SourceConfig::AzureBlob(config) => Ok(Box::new(CompressedSource::new(Box::new(
+                config.create_source(secrets)?,
+            )))),
         }
     }
 }
@@ -390,6 +440,91 @@ impl S3SourceConfig {
         ))
     }
 }
+impl GcsSourceConfig {
+    pub fn create_source(&self, secrets: &JobSecrets) -> Result<GcsSource, Error> {
+        let service_account = match &self.service_account_key_key {
+            Some(key) => {
+                let service_account_key = secrets.secrets.get(key).ok_or(Error::msg(format!(
+                    "Missing service account key as key {key}"
+                )))?;
+                // Keys can be stored as the JSON object GCP hands out, or as a string of it
+                let service_account_key: ServiceAccountKey = match service_account_key.as_str() {
+                    Some(s) => serde_json::from_str(s),
+                    None => serde_json::from_value(service_account_key.clone()),
+                }
+                .user_error("The GCS service account key isn't a valid JSON key file")?;
+                Some(service_account_key)
+            }
+            None => None,
+        };
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        Ok(GcsSource::new(
+            client,
+            self.endpoint_url
+                .clone()
+                .unwrap_or(DEFAULT_GCS_ENDPOINT.to_string()),
+            self.bucket.clone(),
+            self.prefix.clone(),
+            service_account,
+        ))
+    }
+}
+
+impl AzureBlobSourceConfig {
+    pub fn create_source(&self, secrets: &JobSecrets) -> Result<AzureBlobSource, Error> {
+        let auth = if let Some(key) = &self.account_key_key {
+            let account_key = secrets
+                .secrets
+                .get(key)
+                .ok_or(Error::msg(format!("Missing account key as key {key}")))?
+                .as_str()
+                .ok_or(Error::msg(format!(
+                    "Account key as key {key} is not a string"
+                )))?;
+            let account_key = BASE64_STANDARD
+                .decode(account_key.trim())
+                .user_error("The Azure storage account key isn't valid base64")?;
+            AzureBlobAuth::SharedKey(account_key)
+        } else if let Some(key) = &self.sas_token_key {
+            let sas_token = secrets
+                .secrets
+                .get(key)
+                .ok_or(Error::msg(format!("Missing SAS token as key {key}")))?
+                .as_str()
+                .ok_or(Error::msg(format!(
+                    "SAS token as key {key} is not a string"
+                )))?;
+            AzureBlobAuth::Sas(sas_token.to_string())
+        } else {
+            AzureBlobAuth::Anonymous
+        };
+
+        let endpoint = match &self.endpoint_url {
+            Some(url) => url.clone(),
+            None => format!("https://{}.blob.core.windows.net", self.account_name),
+        };
+        let endpoint = reqwest::Url::parse(&endpoint)
+            .user_error(format!("Invalid Azure endpoint URL: {endpoint}"))?;
+
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        AzureBlobSource::new(
+            client,
+            endpoint,
+            self.account_name.clone(),
+            self.container.clone(),
+            self.prefix.clone(),
+            auth,
+        )
+    }
+}
+
 impl DateRangeExportSourceConfig {
     pub async fn create_source(
         &self,
@@ -539,6 +674,8 @@ mod tests {
             import_events: true,
             generate_identify_events: false,
             generate_group_identify_events: false,
+            transformation: None,
+            preview: None,
         }
     }
 
@@ -652,6 +789,8 @@ mod tests {
             import_events: false,
             generate_identify_events: true,
             generate_group_identify_events: false,
+            transformation: None,
+            preview: None,
         };
 
         // Test serialization works with different source types
@@ -681,6 +820,8 @@ mod tests {
             import_events: true,
             generate_identify_events: false,
             generate_group_identify_events: false,
+            transformation: None,
+            preview: None,
         };
 
         let serialized = serde_json::to_string(&stdout_config).unwrap();
@@ -712,6 +853,8 @@ mod tests {
                 import_events,
                 generate_identify_events,
                 generate_group_identify_events: false,
+                transformation: None,
+                preview: None,
             };
 
             // Test serialization/deserialization
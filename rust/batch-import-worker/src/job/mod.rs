@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::{atomic::Ordering, Arc};
 
 use anyhow::{Context, Error};
@@ -11,17 +12,22 @@ use tracing::{debug, error, info, warn};
 
 use crate::{
     context::AppContext,
-    emit::Emitter,
+    emit::{Emitter, NoOpEmitter},
     error::{extract_retry_after_from_error, get_user_message, is_rate_limited_error, UserError},
     job::backoff::format_backoff_messages,
-    parse::{format::ParserFn, Parsed},
+    parse::{
+        format::{csv_header_len, ParserFn},
+        Parsed,
+    },
     source::DataSource,
     spawn_liveness_loop,
+    transformation::{HogTransformation, TransformationStats},
 };
 
 pub mod backoff;
 pub mod config;
 pub mod model;
+pub mod preview;
 
 #[derive(Debug, PartialEq)]
 enum ErrorHandlingDecision {
@@ -99,6 +105,11 @@ pub struct Job {
 
     pub source: Box<dyn DataSource>,
     pub transform: Arc<ParserFn>,
+    // The job's Hog transformation, run against every event after parsing
+    pub transformation: Option<Arc<HogTransformation>>,
+
+    // For formats with a header row, the header of each part, which is prepended to every chunk of it
+    part_headers: Option<Mutex<HashMap<String, Vec<u8>>>>,
 
     // We keep a mutex here so we can mutably borrow this and the job state at the same time
     pub sink: Mutex<Box<dyn Emitter>>,
@@ -111,6 +122,7 @@ pub struct Job {
 struct Checkpoint {
     key: String,
     data: Parsed<Vec<InternallyCapturedEvent>>,
+    transformation: TransformationStats,
 }
 
 impl Job {
@@ -123,6 +135,7 @@ impl Job {
             .construct(&model.secrets, context.clone(), is_restarting)
             .await
             .with_context(|| "Failed to construct data source for job".to_string())?;
+        let source = model.import_config.data_format.wrap_source(source);
 
         // Some sources need to prepare for the job before we can start processing it
         source.prepare_for_job().await?;
@@ -135,18 +148,23 @@ impl Job {
                 .await?,
         );
 
-        let sink = model
-            .import_config
-            .sink
-            .construct(context.clone(), &model)
-            .await
-            .with_context(|| format!("Failed to construct sink for job {}", model.id))?;
+        let transformation = match &model.import_config.transformation {
+            Some(config) => Some(Arc::new(HogTransformation::new(config)?)),
+            None => None,
+        };
 
-        let mut state = model
-            .state
-            .as_ref()
-            .cloned()
-            .unwrap_or_else(|| JobState { parts: vec![] });
+        // Previews never emit anything, so they don't need the real sink
+        let sink: Box<dyn Emitter> = match &model.import_config.preview {
+            Some(_) => Box::new(NoOpEmitter),
+            None => model
+                .import_config
+                .sink
+                .construct(context.clone(), &model)
+                .await
+                .with_context(|| format!("Failed to construct sink for job {}", model.id))?,
+        };
+
+        let mut state = model.state.as_ref().cloned().unwrap_or_default();
 
         if state.parts.is_empty() {
             info!(job_id = %model.id, "Found job with no parts, initializing parts list");
@@ -175,6 +193,11 @@ impl Job {
         }
 
         let job_id = model.id;
+        let part_headers = model
+            .import_config
+            .data_format
+            .has_header()
+            .then(|| Mutex::new(HashMap::new()));
 
         Ok(Self {
             context,
@@ -183,12 +206,19 @@ impl Job {
             state: Mutex::new(state),
             source,
             transform: Arc::new(transform),
+            transformation,
+            part_headers,
             sink: Mutex::new(sink),
             checkpoint: Mutex::new(None),
         })
     }
 
     pub async fn process(self) -> Result<Option<Self>, Error> {
+        let preview = self.model.lock().await.import_config.preview.clone();
+        if let Some(preview) = preview {
+            return self.run_preview(preview).await;
+        }
+
         let next_chunk_fut = self.get_next_chunk();
         let next_commit_fut = self.do_commit();
 
@@ -310,10 +340,7 @@ impl Job {
         };
 
         let mut checkpoint = self.checkpoint.lock().await;
-        *checkpoint = Some(Checkpoint {
-            key: next.0,
-            data: next.1,
-        });
+        *checkpoint = Some(next);
 
         drop(checkpoint);
 
@@ -321,9 +348,7 @@ impl Job {
         Ok(Some(self))
     }
 
-    async fn get_next_chunk(
-        &self,
-    ) -> Result<Option<(String, Parsed<Vec<InternallyCapturedEvent>>)>, Error> {
+    async fn get_next_chunk(&self) -> Result<Option<Checkpoint>, Error> {
         let mut state = self.state.lock().await;
 
         let Some(next_part) = state.parts.iter_mut().find(|p| !p.is_done()) else {
@@ -357,49 +382,82 @@ impl Job {
                         key
                     );
                     next_part.current_offset = actual_size;
-                    return Ok(Some((
-                        key.clone(),
-                        Parsed {
+                    return Ok(Some(Checkpoint {
+                        key: key.clone(),
+                        data: Parsed {
                             consumed: 0,
                             data: vec![],
                         },
-                    )));
+                        transformation: TransformationStats::default(),
+                    }));
                 }
             }
         }
 
+        let header = match &self.part_headers {
+            Some(headers) => self.get_part_header(headers, &key).await?,
+            None => Vec::new(),
+        };
+        // The first chunk is fetched from after the header, so every chunk is parsed the same way
+        let fetch_offset = next_part.current_offset.max(header.len() as u64);
+
         info!(job_id = %self.job_id, "Fetching part chunk {:?}", next_part);
 
-        let next_chunk = self
-            .source
-            .get_chunk(
-                &next_part.key,
-                next_part.current_offset,
-                self.context.config.chunk_size as u64,
-            )
-            .await
-            .context(format!("Fetching part chunk {next_part:?}"))?;
+        let fetched = get_chunk_within(
+            self.source.as_ref(),
+            &next_part.key,
+            fetch_offset,
+            self.context.config.chunk_size as u64,
+            next_part.total_size,
+        )
+        .await
+        .context(format!("Fetching part chunk {next_part:?}"))?;
+
+        // How many bytes from the current offset the chunk covers, including any of the header
+        let chunk_bytes = (fetch_offset - next_part.current_offset) as usize + fetched.len();
 
         let is_last_chunk = match next_part.total_size {
-            Some(total_size) => next_part.current_offset + next_chunk.len() as u64 > total_size,
+            Some(total_size) => {
+                fetch_offset >= total_size
+                    || next_part.current_offset + chunk_bytes as u64 > total_size
+            }
             None => false,
         };
 
-        let chunk_bytes = next_chunk.len();
+        let header_len = header.len();
+        let next_chunk = if header.is_empty() {
+            fetched
+        } else {
+            let mut chunk = header;
+            chunk.extend_from_slice(&fetched);
+            chunk
+        };
 
         info!(job_id = %self.job_id, "Fetched part chunk {:?}", next_part);
         let m_tf = self.transform.clone();
+        let transformation = self.transformation.clone();
         let key_for_error = key.clone();
         // This is computationally expensive, so we run it in a blocking task
-        let parsed = tokio::task::spawn_blocking(move || (m_tf)(next_chunk))
-            .await?
-            .map_err(|e| {
+        let (mut parsed, transformation_stats) = tokio::task::spawn_blocking(move || {
+            let mut parsed = (m_tf)(next_chunk).map_err(|e| {
                 let inner_msg = get_user_message(&e);
                 e.context(UserError::new(format!(
                     "Parsing data in file '{key_for_error}' failed: {inner_msg}"
                 )))
-            })
-            .context(format!("Processing part chunk {next_part:?}"))?;
+            })?;
+            let Some(transformation) = transformation else {
+                return Ok((parsed, TransformationStats::default()));
+            };
+            let (events, stats) = transformation.apply(std::mem::take(&mut parsed.data))?;
+            parsed.data = events;
+            Ok::<_, Error>((parsed, stats))
+        })
+        .await?
+        .context(format!("Processing part chunk {next_part:?}"))?;
+
+        // The parser counts the prepended header as consumed, but we count from the current offset
+        parsed.consumed = (parsed.consumed + (fetch_offset - next_part.current_offset) as usize)
+            .saturating_sub(header_len);
 
         info!(
             job_id = %self.job_id,
@@ -435,7 +493,39 @@ impl Job {
             reset_backoff_after_success(self.context.clone(), &mut model).await?;
         }
 
-        Ok(Some((ret_key, parsed)))
+        Ok(Some(Checkpoint {
+            key: ret_key,
+            data: parsed,
+            transformation: transformation_stats,
+        }))
+    }
+
+    async fn get_part_header(
+        &self,
+        headers: &Mutex<HashMap<String, Vec<u8>>>,
+        key: &str,
+    ) -> Result<Vec<u8>, Error> {
+        let mut headers = headers.lock().await;
+        if let Some(header) = headers.get(key) {
+            return Ok(header.clone());
+        }
+
+        let delimiter = self
+            .model
+            .lock()
+            .await
+            .import_config
+            .data_format
+            .csv_delimiter()?;
+        let header = read_part_header(
+            self.source.as_ref(),
+            key,
+            self.context.config.chunk_size,
+            delimiter,
+        )
+        .await?;
+        headers.insert(key.to_string(), header.clone());
+        Ok(header)
     }
 
     async fn do_commit(&self) -> Result<(), Error> {
@@ -448,7 +538,8 @@ impl Job {
             return Ok(()); // We've got no checkpointed data to commit, so we're done
         };
 
-        let (key, parsed) = (checkpoint.key, checkpoint.data);
+        let (key, parsed, transformation_stats) =
+            (checkpoint.key, checkpoint.data, checkpoint.transformation);
 
         info!(job_id = %self.job_id, "Committing part {} consumed {} bytes", key, parsed.consumed);
         info!(job_id = %self.job_id, "Committing {} events", parsed.data.len());
@@ -469,7 +560,8 @@ impl Job {
         // looking at logs, or both). The jobs status message is set to enable this kind of debugging.
         self.shutdown_guard()?; // This is the last time we call this during the commit - if we get this far, we want to commit fully if at all possible
         info!(job_id = %self.job_id, "Beginning PG part commit");
-        self.begin_part_commit(&key, parsed.consumed).await?;
+        self.begin_part_commit(&key, parsed.consumed, &transformation_stats)
+            .await?;
         info!(job_id = %self.job_id, "Beginning emitter part commit");
 
         let to_sleep = txn.commit_write().await?;
@@ -494,7 +586,12 @@ impl Job {
 
     // Writes the new partstate to the DB, and sets the job status to paused, such that if there's an issue with the sink commit, the job
     // will be paused, and manual intervention will be required to resume it
-    async fn begin_part_commit(&self, key: &str, consumed: usize) -> Result<(), Error> {
+    async fn begin_part_commit(
+        &self,
+        key: &str,
+        consumed: usize,
+        transformation_stats: &TransformationStats,
+    ) -> Result<(), Error> {
         let mut model = self.model.lock().await;
         let Some(model_state) = &mut model.state else {
             return Err(Error::msg("No model state found"));
@@ -512,6 +609,10 @@ impl Job {
             key, part.current_offset, consumed
         );
 
+        // The job's transformation totals are committed along with the chunk they came from
+        model_state.transformation.add(transformation_stats);
+        metric_emit::transformation_stats(transformation_stats);
+
         model
             .pause(
                 self.context.clone(),
@@ -540,6 +641,54 @@ impl Job {
     }
 }
 
+// Fetches a chunk of a part from `offset`, or nothing if that's already the end of the part. Sources that
+// read by byte range reject a range starting at the end of the object, which is where the rows of a part
+// holding only its header row would start.
+async fn get_chunk_within(
+    source: &dyn DataSource,
+    key: &str,
+    offset: u64,
+    size: u64,
+    total_size: Option<u64>,
+) -> Result<Vec<u8>, Error> {
+    if total_size.is_some_and(|total_size| offset >= total_size) {
+        return Ok(Vec::new());
+    }
+    source.get_chunk(key, offset, size).await
+}
+
+// Reads the header row from the start of a CSV part. Some sources clean a part up once it's been read
+// to the end, which for a part smaller than a chunk is this read, so we prepare it again for its rows.
+async fn read_part_header(
+    source: &dyn DataSource,
+    key: &str,
+    chunk_size: usize,
+    delimiter: u8,
+) -> Result<Vec<u8>, Error> {
+    let part_start = source
+        .get_chunk(key, 0, chunk_size as u64)
+        .await
+        .context(format!("Fetching header of part {key}"))?;
+
+    let header_len = match csv_header_len(&part_start, delimiter) {
+        Some(len) => len,
+        // A part with no newline is a header with no rows
+        None if part_start.len() < chunk_size => part_start.len(),
+        None => {
+            return Err(Error::msg(format!(
+                "No header row found in the first {chunk_size} bytes of part {key}"
+            ))
+            .context(UserError::new(format!(
+                "The header row of '{key}' is too long. Check that the file is CSV."
+            ))))
+        }
+    };
+
+    source.prepare_key(key).await?;
+
+    Ok(part_start[..header_len].to_vec())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -622,6 +771,100 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_header_of_small_compressed_csv_part_leaves_it_readable() {
+        use crate::source::{compressed::CompressedSource, folder::FolderSource};
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        const HEADER: &str = "event,distinct_id\n";
+        const ROWS: &str = "signup,user-1\npurchase,user-2\n";
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(HEADER.as_bytes()).unwrap();
+        encoder.write_all(ROWS.as_bytes()).unwrap();
+        std::fs::write(temp_dir.path().join("a.csv.gz"), encoder.finish().unwrap()).unwrap();
+
+        let inner = FolderSource::new(temp_dir.path().to_str().unwrap().to_string())
+            .await
+            .unwrap();
+        let source = CompressedSource::new(Box::new(inner));
+        source.prepare_for_job().await.unwrap();
+        source.prepare_key("a.csv.gz").await.unwrap();
+
+        // The part is smaller than a chunk, so reading the header reads all of it
+        let chunk_size = 1024;
+        let header = read_part_header(&source, "a.csv.gz", chunk_size, b',')
+            .await
+            .unwrap();
+        assert_eq!(header, HEADER.as_bytes());
+
+        let rows = source
+            .get_chunk("a.csv.gz", header.len() as u64, chunk_size as u64)
+            .await
+            .unwrap();
+        assert_eq!(rows, ROWS.as_bytes());
+    }
+
+    // Like the S3 and URL list sources, rejects reads starting at the end of the data
+    struct RangeSource {
+        data: Vec<u8>,
+    }
+
+    #[async_trait]
+    impl DataSource for RangeSource {
+        async fn keys(&self) -> Result<Vec<String>, Error> {
+            Ok(vec!["a.csv".to_string()])
+        }
+
+        async fn size(&self, _key: &str) -> Result<Option<u64>, Error> {
+            Ok(Some(self.data.len() as u64))
+        }
+
+        async fn get_chunk(&self, _key: &str, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+            let offset = offset as usize;
+            if offset >= self.data.len() {
+                return Err(Error::msg("416 Range Not Satisfiable"));
+            }
+            let end = (offset + size as usize).min(self.data.len());
+            Ok(self.data[offset..end].to_vec())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_header_only_csv_part_is_read_without_fetching_past_its_end() {
+        use crate::parse::format::csv_rows;
+
+        let source = RangeSource {
+            data: b"event,distinct_id\n".to_vec(),
+        };
+        let chunk_size = 1024;
+
+        let header = read_part_header(&source, "a.csv", chunk_size, b',')
+            .await
+            .unwrap();
+        let total_size = source.size("a.csv").await.unwrap();
+        let rows = get_chunk_within(
+            &source,
+            "a.csv",
+            header.len() as u64,
+            chunk_size as u64,
+            total_size,
+        )
+        .await
+        .unwrap();
+        assert!(rows.is_empty());
+
+        let parsed =
+            csv_rows::<common_types::RawEvent>(b',', None, Default::default(), chunk_size)(
+                header.clone(),
+            )
+            .unwrap();
+        assert!(parsed.data.is_empty());
+        assert_eq!(parsed.consumed, header.len());
+    }
+
     #[test]
     fn test_error_message_includes_date_range_when_available() {
         let mock_source = MockDataSource::new();
@@ -781,7 +1024,7 @@ mod tests {
             status: super::model::JobStatus::Running,
             status_message: None,
             display_status_message: None,
-            state: Some(JobState { parts: vec![] }),
+            state: Some(JobState::default()),
             import_config: super::config::JobConfig {
                 // Construct a trivially valid config that won't be used by this test
                 source: super::config::SourceConfig::Folder(super::config::FolderSourceConfig {
@@ -795,6 +1038,8 @@ mod tests {
                 import_events: true,
                 generate_identify_events: false,
                 generate_group_identify_events: false,
+                transformation: None,
+                preview: None,
             },
             secrets: super::config::JobSecrets {
                 secrets: std::collections::HashMap::new(),
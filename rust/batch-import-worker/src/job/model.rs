@@ -8,9 +8,12 @@ use sqlx::{postgres::PgQueryResult, PgPool, Row};
 use tracing::{info, warn};
 use uuid::Uuid;
 
-use crate::context::AppContext;
+use crate::{context::AppContext, transformation::TransformationStats};
 
-use super::config::{JobConfig, JobSecrets};
+use super::{
+    config::{JobConfig, JobSecrets},
+    preview::PreviewReport,
+};
 
 #[derive(Debug, Clone)]
 pub struct JobModel {
@@ -49,12 +52,18 @@ pub enum JobStatus {
     Completed,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub struct JobState {
     // Parts are sorted, and we iterate through them in order, to let us import
     // from oldest to newest
     pub parts: Vec<PartState>,
+    // What the job's transformation, if it has one, has done to the events committed so far
+    #[serde(default)]
+    pub transformation: TransformationStats,
+    // The report of a preview run of the job, if it's been previewed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview: Option<PreviewReport>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -380,7 +389,7 @@ impl TryFrom<(JobRow, &[String], String)> for JobModel {
         let (row, keys, lease_id) = input;
         let state = match row.state {
             Some(s) => serde_json::from_value(s).context("Parsing state")?,
-            None => JobState { parts: vec![] },
+            None => JobState::default(),
         };
 
         let import_config = serde_json::from_value(row.import_config).context("Parsing config")?;
@@ -454,6 +463,8 @@ mod tests {
                 import_events: true,
                 generate_identify_events: false,
                 generate_group_identify_events: false,
+                transformation: None,
+                preview: None,
             },
             secrets: crate::job::config::JobSecrets {
                 secrets: std::collections::HashMap::new(),
@@ -0,0 +1,481 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use common_types::InternallyCapturedEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{info, warn};
+
+use crate::{
+    error::get_user_message,
+    parse::format::{FormatConfig, ParserFn},
+    transformation::{HogTransformation, TransformationStats},
+};
+
+use super::{get_chunk_within, model::JobState, Job};
+
+// We keep at most this many parse errors in a report, so a broken file doesn't bloat the job state
+const MAX_PREVIEW_ERRORS: usize = 100;
+// And at most this much of each offending record
+const MAX_RAW_RECORD_BYTES: usize = 1024;
+
+// Rather than importing anything, a job with this set reads the start of its first few parts, runs them
+// through the job's parser and transformation, and writes what it found to the job state, then pauses.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PreviewConfig {
+    /// How many of the source's keys to read from
+    #[serde(default = "PreviewConfig::default_max_keys")]
+    pub max_keys: usize,
+    /// How many records to read from the start of each key
+    #[serde(default = "PreviewConfig::default_max_records")]
+    pub max_records: usize,
+    /// How many of the resulting events to include in the report
+    #[serde(default = "PreviewConfig::default_max_samples")]
+    pub max_samples: usize,
+}
+
+impl PreviewConfig {
+    fn default_max_keys() -> usize {
+        5
+    }
+
+    fn default_max_records() -> usize {
+        100
+    }
+
+    fn default_max_samples() -> usize {
+        10
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PreviewReport {
+    pub keys: Vec<String>,
+    pub records_read: u64,
+    pub events: u64,
+    pub event_counts: BTreeMap<String, u64>,
+    pub identify_events: u64,
+    pub group_identify_events: u64,
+    pub earliest_timestamp: Option<DateTime<Utc>>,
+    pub latest_timestamp: Option<DateTime<Utc>>,
+    /// The first events produced, as they'd be sent to capture
+    pub sample_events: Vec<Value>,
+    /// The total number of records that failed to parse, of which the first are in `parse_errors`
+    pub parse_error_count: u64,
+    pub parse_errors: Vec<PreviewParseError>,
+    pub transformation: TransformationStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct PreviewParseError {
+    pub key: String,
+    /// The line of the key the record starts on, if records could be parsed one at a time
+    pub line: Option<usize>,
+    pub raw: String,
+    /// The user facing error message
+    pub message: String,
+    /// The full error chain
+    pub detail: String,
+}
+
+impl PreviewReport {
+    fn add_events(&mut self, events: &[InternallyCapturedEvent], max_samples: usize) {
+        for event in events {
+            let event = &event.inner;
+            self.events += 1;
+            *self.event_counts.entry(event.event.clone()).or_default() += 1;
+            match event.event.as_str() {
+                "$identify" => self.identify_events += 1,
+                "$groupidentify" => self.group_identify_events += 1,
+                _ => {}
+            }
+
+            self.earliest_timestamp = Some(
+                self.earliest_timestamp
+                    .map_or(event.timestamp, |t| t.min(event.timestamp)),
+            );
+            self.latest_timestamp = Some(
+                self.latest_timestamp
+                    .map_or(event.timestamp, |t| t.max(event.timestamp)),
+            );
+
+            if self.sample_events.len() < max_samples {
+                // The event data is what's sent to capture, so that's what we show
+                let data = serde_json::from_str(&event.data)
+                    .unwrap_or_else(|_| Value::String(event.data.clone()));
+                self.sample_events.push(data);
+            }
+        }
+    }
+
+    fn add_error(&mut self, key: &str, line: Option<usize>, raw: &[u8], err: &Error) {
+        self.parse_error_count += 1;
+        if self.parse_errors.len() >= MAX_PREVIEW_ERRORS {
+            return;
+        }
+
+        let raw = &raw[..raw.len().min(MAX_RAW_RECORD_BYTES)];
+        self.parse_errors.push(PreviewParseError {
+            key: key.to_string(),
+            line,
+            raw: String::from_utf8_lossy(raw).trim_end().to_string(),
+            message: get_user_message(err),
+            detail: format!("{err:#}"),
+        });
+    }
+}
+
+// Runs the start of a part through a job's parsing and transformation
+pub struct Previewer {
+    pub config: PreviewConfig,
+    pub format: FormatConfig,
+    pub parser: Arc<ParserFn>,
+    pub transformation: Option<Arc<HogTransformation>>,
+}
+
+impl Previewer {
+    /// Adds the first records of `data`, read from the start of `key` after its header (if it
+    /// has one), to the report. Where the format allows, records are parsed one at a time, so
+    /// an error can be tied to the record that caused it.
+    pub fn preview_part(
+        &self,
+        report: &mut PreviewReport,
+        key: &str,
+        header: &[u8],
+        data: &[u8],
+        is_part_end: bool,
+    ) {
+        report.keys.push(key.to_string());
+
+        let Some(records) = self.format.split_records(data, is_part_end) else {
+            match self.run(data.to_vec(), report) {
+                Ok(mut events) => {
+                    events.truncate(self.config.max_records);
+                    report.records_read += events.len() as u64;
+                    report.add_events(&events, self.config.max_samples);
+                }
+                Err(e) => report.add_error(key, None, data, &e),
+            }
+            return;
+        };
+
+        let mut line = 1 + header.iter().filter(|b| **b == b'\n').count();
+        let mut records_read = 0;
+        for record in records {
+            let record_line = line;
+            line += record.iter().filter(|b| **b == b'\n').count();
+            if record.trim_ascii().is_empty() {
+                continue;
+            }
+            if records_read == self.config.max_records {
+                break;
+            }
+            records_read += 1;
+
+            let mut input = header.to_vec();
+            input.extend_from_slice(record);
+            if !record.ends_with(b"\n") {
+                input.push(b'\n');
+            }
+
+            match self.run(input, report) {
+                Ok(events) => report.add_events(&events, self.config.max_samples),
+                Err(e) => report.add_error(key, Some(record_line), record, &e),
+            }
+        }
+        report.records_read += records_read as u64;
+    }
+
+    fn run(
+        &self,
+        input: Vec<u8>,
+        report: &mut PreviewReport,
+    ) -> Result<Vec<InternallyCapturedEvent>, Error> {
+        let parsed = (self.parser)(input)?;
+        let Some(transformation) = &self.transformation else {
+            return Ok(parsed.data);
+        };
+        let (events, stats) = transformation.apply(parsed.data)?;
+        report.transformation.add(&stats);
+        Ok(events)
+    }
+}
+
+impl Job {
+    // Previews the job instead of running it, then pauses it so the report can be reviewed
+    pub(super) async fn run_preview(self, config: PreviewConfig) -> Result<Option<Self>, Error> {
+        let keys: Vec<String> = {
+            let state = self.state.lock().await;
+            state
+                .parts
+                .iter()
+                .take(config.max_keys)
+                .map(|p| p.key.clone())
+                .collect()
+        };
+
+        let format = self.model.lock().await.import_config.data_format.clone();
+        let previewer = Arc::new(Previewer {
+            config,
+            format,
+            parser: self.transform.clone(),
+            transformation: self.transformation.clone(),
+        });
+
+        let mut report = PreviewReport::default();
+        for key in keys {
+            report = match self
+                .preview_key(previewer.clone(), report.clone(), &key)
+                .await
+            {
+                Ok(report) => report,
+                Err(e) => {
+                    // A part we can't read is reported like one we can't parse, so the rest can still be previewed
+                    warn!(job_id = %self.job_id, "Failed to preview part {key}: {e:?}");
+                    report.add_error(&key, None, &[], &e);
+                    report
+                }
+            };
+            if let Err(e) = self.source.cleanup_key(&key).await {
+                warn!(job_id = %self.job_id, "Failed to cleanup key {key}: {e:?}");
+            }
+        }
+
+        if let Err(e) = self.source.cleanup_after_job().await {
+            warn!(job_id = %self.job_id, "Failed to cleanup after job: {e:?}");
+        }
+
+        let status_message = format!(
+            "Preview complete: {} events from {} records in {} files, {} records failed to parse",
+            report.events,
+            report.records_read,
+            report.keys.len(),
+            report.parse_error_count
+        );
+        info!(job_id = %self.job_id, "{}", status_message);
+
+        let mut model = self.model.lock().await;
+        model.state.get_or_insert_with(JobState::default).preview = Some(report);
+        model
+            .pause(
+                self.context.clone(),
+                status_message,
+                Some("Preview complete. Review it before running the import.".to_string()),
+            )
+            .await?;
+
+        Ok(None)
+    }
+
+    async fn preview_key(
+        &self,
+        previewer: Arc<Previewer>,
+        mut report: PreviewReport,
+        key: &str,
+    ) -> Result<PreviewReport, Error> {
+        self.source.prepare_key(key).await?;
+
+        let header = match &self.part_headers {
+            Some(headers) => self.get_part_header(headers, key).await?,
+            None => Vec::new(),
+        };
+
+        let chunk_size = self.context.config.chunk_size;
+        let size = self.source.size(key).await?;
+        let data = get_chunk_within(
+            self.source.as_ref(),
+            key,
+            header.len() as u64,
+            chunk_size as u64,
+            size,
+        )
+        .await
+        .with_context(|| format!("Fetching start of part {key}"))?;
+        let is_part_end = data.len() < chunk_size;
+
+        let key = key.to_string();
+        // Parsing is computationally expensive, so we run it in a blocking task
+        tokio::task::spawn_blocking(move || {
+            previewer.preview_part(&mut report, &key, &header, &data, is_part_end);
+            report
+        })
+        .await
+        .context("Preview task failed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::UserError;
+    use crate::parse::{content::ContentType, format::newline_delim};
+    use crate::transformation::TransformationConfig;
+    use common_types::{CapturedEvent, RawEvent};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    // Parses each line as a captured event, like the `captured` content type
+    fn parser() -> Arc<ParserFn> {
+        let parse = newline_delim(true, |line| {
+            let raw: RawEvent = serde_json::from_str(line)
+                .map_err(|e| Error::from(e).context(UserError::new("Invalid event")))?;
+            let timestamp: DateTime<Utc> = raw
+                .timestamp
+                .as_deref()
+                .unwrap_or("2024-01-01T00:00:00Z")
+                .parse()?;
+            Ok(InternallyCapturedEvent {
+                team_id: 1,
+                inner: CapturedEvent {
+                    uuid: Uuid::now_v7(),
+                    distinct_id: "user-1".to_string(),
+                    session_id: None,
+                    ip: "".to_string(),
+                    data: serde_json::to_string(&raw)?,
+                    now: "".to_string(),
+                    sent_at: None,
+                    token: "token".to_string(),
+                    event: raw.event.clone(),
+                    timestamp,
+                    is_cookieless_mode: false,
+                    historical_migration: true,
+                },
+            })
+        });
+        Arc::new(Box::new(parse))
+    }
+
+    fn previewer(max_records: usize, max_samples: usize) -> Previewer {
+        Previewer {
+            config: PreviewConfig {
+                max_keys: 5,
+                max_records,
+                max_samples,
+            },
+            format: FormatConfig::JsonLines {
+                skip_blanks: true,
+                content: ContentType::Captured,
+            },
+            parser: parser(),
+            transformation: None,
+        }
+    }
+
+    const LINES: &str = r#"{"event": "signup", "timestamp": "2024-01-02T00:00:00Z"}
+{"event": "$identify", "timestamp": "2024-01-01T00:00:00Z"}
+
+not json
+{"event": "$groupidentify", "timestamp": "2024-01-03T00:00:00Z"}
+{"event": "signup", "timestamp": "2024-01-04T00:00:00Z"}
+"#;
+
+    #[test]
+    fn test_preview_summarises_events() {
+        let mut report = PreviewReport::default();
+
+        previewer(100, 2).preview_part(&mut report, "part-1", b"", LINES.as_bytes(), true);
+
+        assert_eq!(report.keys, vec!["part-1".to_string()]);
+        assert_eq!(report.records_read, 5);
+        assert_eq!(report.events, 4);
+        assert_eq!(
+            report.event_counts,
+            BTreeMap::from([
+                ("signup".to_string(), 2),
+                ("$identify".to_string(), 1),
+                ("$groupidentify".to_string(), 1),
+            ])
+        );
+        assert_eq!(report.identify_events, 1);
+        assert_eq!(report.group_identify_events, 1);
+        assert_eq!(
+            report.earliest_timestamp,
+            Some("2024-01-01T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            report.latest_timestamp,
+            Some("2024-01-04T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(report.sample_events.len(), 2);
+        assert_eq!(report.sample_events[0]["event"], "signup");
+    }
+
+    #[test]
+    fn test_preview_reports_offending_records() {
+        let mut report = PreviewReport::default();
+
+        previewer(100, 10).preview_part(&mut report, "part-1", b"", LINES.as_bytes(), true);
+
+        assert_eq!(report.parse_error_count, 1);
+        let error = &report.parse_errors[0];
+        assert_eq!(error.key, "part-1");
+        assert_eq!(error.line, Some(4));
+        assert_eq!(error.raw, "not json");
+        assert_eq!(error.message, "Invalid event");
+    }
+
+    #[test]
+    fn test_preview_reads_at_most_max_records() {
+        let mut report = PreviewReport::default();
+
+        previewer(2, 10).preview_part(&mut report, "part-1", b"", LINES.as_bytes(), true);
+
+        assert_eq!(report.records_read, 2);
+        assert_eq!(report.events, 2);
+        assert_eq!(report.parse_error_count, 0);
+    }
+
+    #[test]
+    fn test_preview_leaves_out_partial_record() {
+        let mut report = PreviewReport::default();
+        let data = &LINES.as_bytes()[..LINES.len() - 10];
+
+        previewer(100, 10).preview_part(&mut report, "part-1", b"", data, false);
+
+        assert_eq!(report.records_read, 4);
+        assert_eq!(report.parse_error_count, 1);
+    }
+
+    #[test]
+    fn test_preview_runs_transformation() {
+        let mut previewer = previewer(100, 10);
+        // if (event.event == 'signup') { return null } return event
+        let transformation: TransformationConfig = serde_json::from_value(json!({
+            "bytecode": [
+                "_H", 1, 32, "event", 32, "event", 1, 2, 32, "signup", 11, 40, 2, 31, 38, 32,
+                "event", 1, 1, 38
+            ]
+        }))
+        .unwrap();
+        previewer.transformation = Some(Arc::new(HogTransformation::new(&transformation).unwrap()));
+        let mut report = PreviewReport::default();
+
+        previewer.preview_part(&mut report, "part-1", b"", LINES.as_bytes(), true);
+
+        assert_eq!(report.events, 2);
+        assert_eq!(report.event_counts.get("signup"), None);
+        assert_eq!(report.transformation.dropped, 2);
+    }
+
+    #[test]
+    fn test_preview_counts_header_lines() {
+        let mut previewer = previewer(100, 10);
+        previewer.format = FormatConfig::Csv {
+            delimiter: ',',
+            column_names: None,
+            columns: Default::default(),
+            content: ContentType::Captured,
+        };
+        // The test parser ignores the header, so every row fails to parse
+        let mut report = PreviewReport::default();
+
+        previewer.preview_part(&mut report, "part-1", b"event\n", b"a\nb\n", true);
+
+        let lines: Vec<_> = report.parse_errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![Some(2), Some(3)]);
+    }
+}
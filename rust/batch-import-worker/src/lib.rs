@@ -19,6 +19,7 @@ pub mod metrics;
 pub mod parse;
 pub mod person_processing_filter;
 pub mod source;
+pub mod transformation;
 
 // During job init, we can hang for a long time initialising sinks or sources, so we kick off a task to
 // report that we're alive while we do it.
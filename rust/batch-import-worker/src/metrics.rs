@@ -1,9 +1,14 @@
 pub const BACKOFF_EVENTS_TOTAL: &str = "batch_import_backoff_events_total";
 pub const BACKOFF_DELAY_SECONDS: &str = "batch_import_backoff_delay_seconds";
 pub const UNPAUSE_TOTAL: &str = "batch_import_unpause_total";
+pub const TRANSFORMATION_DROPPED_TOTAL: &str = "batch_import_transformation_dropped_total";
+pub const TRANSFORMATION_MODIFIED_TOTAL: &str = "batch_import_transformation_modified_total";
+pub const TRANSFORMATION_ADDED_TOTAL: &str = "batch_import_transformation_added_total";
 
 use metrics::{counter, histogram};
 
+use crate::transformation::TransformationStats;
+
 pub fn backoff_event(delay_secs: f64) {
     counter!(BACKOFF_EVENTS_TOTAL).increment(1);
     histogram!(BACKOFF_DELAY_SECONDS).record(delay_secs);
@@ -12,3 +17,9 @@ pub fn backoff_event(delay_secs: f64) {
 pub fn unpause_event() {
     counter!(UNPAUSE_TOTAL).increment(1);
 }
+
+pub fn transformation_stats(stats: &TransformationStats) {
+    counter!(TRANSFORMATION_DROPPED_TOTAL).increment(stats.dropped);
+    counter!(TRANSFORMATION_MODIFIED_TOTAL).increment(stats.modified);
+    counter!(TRANSFORMATION_ADDED_TOTAL).increment(stats.added);
+}
@@ -0,0 +1,227 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Context, Error};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::error::UserError;
+
+use super::format::UserFacingParseError;
+
+// How the columns of tabular data (CSV, Parquet) become the fields of the records a content
+// type expects. With the default mapping, each column becomes a top-level field of the same name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    /// The field each column goes in, keyed by column name. Nested fields are separated by `.`,
+    /// e.g. `properties.plan`. Columns without an entry keep their own name.
+    #[serde(default)]
+    pub fields: HashMap<String, String>,
+    /// Columns holding JSON text, e.g. a `properties` column exported from a warehouse, which
+    /// are parsed rather than imported as strings
+    #[serde(default)]
+    pub json_columns: HashSet<String>,
+    /// Drop columns without an entry in `fields`, rather than keeping them under their own name
+    #[serde(default)]
+    pub drop_unmapped: bool,
+}
+
+impl ColumnMapping {
+    /// Builds a record from a row's columns. Null and empty values are left out, so they read
+    /// as missing fields rather than empty strings.
+    pub fn map_row<'a>(
+        &self,
+        row: impl IntoIterator<Item = (&'a str, Value)>,
+    ) -> Result<Map<String, Value>, Error> {
+        let mut record = Map::new();
+
+        for (column, value) in row {
+            if value.is_null() || value.as_str().is_some_and(str::is_empty) {
+                continue;
+            }
+
+            let path = match self.fields.get(column) {
+                Some(path) => path.as_str(),
+                None if self.drop_unmapped => continue,
+                None => column,
+            };
+
+            let value = match value {
+                Value::String(text) if self.json_columns.contains(column) => {
+                    serde_json::from_str(&text).map_err(|e| {
+                        anyhow::Error::from(e).context(UserError::new(format!(
+                            "Column '{column}' is configured as JSON, but contains a value that isn't valid JSON."
+                        )))
+                    })?
+                }
+                value => value,
+            };
+
+            insert_at_path(&mut record, path, value)?;
+        }
+
+        Ok(record)
+    }
+
+    /// Builds a record from a row's columns and deserializes it as `T`
+    pub fn record<'a, T>(&self, row: impl IntoIterator<Item = (&'a str, Value)>) -> Result<T, Error>
+    where
+        T: DeserializeOwned + UserFacingParseError,
+    {
+        let record = self.map_row(row)?;
+        serde_json::from_value(Value::Object(record))
+            .map_err(|e| {
+                let user_msg = T::user_facing_schema_error(&e);
+                anyhow::Error::from(e).context(UserError::new(user_msg))
+            })
+            .context("Failed to map columns to record")
+    }
+}
+
+fn insert_at_path(record: &mut Map<String, Value>, path: &str, value: Value) -> Result<(), Error> {
+    let mut parts = path.split('.');
+    // split always returns at least one part
+    let field = parts.next_back().unwrap_or(path);
+
+    let mut current = record;
+    for part in parts {
+        current = current
+            .entry(part)
+            .or_insert_with(|| Value::Object(Map::new()))
+            .as_object_mut()
+            .ok_or_else(|| {
+                Error::msg(format!("Field {part} is not an object")).context(UserError::new(
+                    format!(
+                        "Can't map a column to '{path}', as another column is mapped to '{part}'."
+                    ),
+                ))
+            })?;
+    }
+
+    current.insert(field.to_string(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::get_user_message;
+    use serde_json::json;
+
+    fn row(columns: &[(&'static str, &str)]) -> Vec<(&'static str, Value)> {
+        columns
+            .iter()
+            .map(|(column, value)| (*column, Value::String(value.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_unmapped_columns_keep_their_names() {
+        let mapping = ColumnMapping::default();
+
+        let record = mapping
+            .map_row(row(&[("event", "signup"), ("distinct_id", "user-1")]))
+            .unwrap();
+
+        assert_eq!(
+            Value::Object(record),
+            json!({"event": "signup", "distinct_id": "user-1"})
+        );
+    }
+
+    #[test]
+    fn test_columns_are_mapped_to_nested_fields() {
+        let mapping = ColumnMapping {
+            fields: HashMap::from([
+                ("user".to_string(), "distinct_id".to_string()),
+                ("plan".to_string(), "properties.plan".to_string()),
+                ("seats".to_string(), "properties.billing.seats".to_string()),
+            ]),
+            json_columns: HashSet::from(["seats".to_string()]),
+            drop_unmapped: true,
+        };
+
+        let record = mapping
+            .map_row(row(&[
+                ("user", "user-1"),
+                ("plan", "pro"),
+                ("seats", "5"),
+                ("internal_id", "abc"),
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            Value::Object(record),
+            json!({
+                "distinct_id": "user-1",
+                "properties": {"plan": "pro", "billing": {"seats": 5}}
+            })
+        );
+    }
+
+    #[test]
+    fn test_json_columns_are_merged_with_mapped_fields() {
+        let mapping = ColumnMapping {
+            fields: HashMap::from([("plan".to_string(), "properties.plan".to_string())]),
+            json_columns: HashSet::from(["properties".to_string()]),
+            drop_unmapped: false,
+        };
+
+        let record = mapping
+            .map_row(row(&[
+                ("properties", r#"{"$browser": "Chrome"}"#),
+                ("plan", "pro"),
+            ]))
+            .unwrap();
+
+        assert_eq!(
+            Value::Object(record),
+            json!({"properties": {"$browser": "Chrome", "plan": "pro"}})
+        );
+    }
+
+    #[test]
+    fn test_empty_and_null_values_are_left_out() {
+        let mapping = ColumnMapping::default();
+
+        let record = mapping
+            .map_row([
+                ("event", json!("signup")),
+                ("uuid", json!("")),
+                ("ip", Value::Null),
+            ])
+            .unwrap();
+
+        assert_eq!(Value::Object(record), json!({"event": "signup"}));
+    }
+
+    #[test]
+    fn test_invalid_json_column_has_user_facing_error() {
+        let mapping = ColumnMapping {
+            json_columns: HashSet::from(["properties".to_string()]),
+            ..Default::default()
+        };
+
+        let err = mapping
+            .map_row(row(&[("properties", "{not json")]))
+            .unwrap_err();
+
+        assert!(get_user_message(&err).contains("Column 'properties'"));
+    }
+
+    #[test]
+    fn test_conflicting_paths_have_user_facing_error() {
+        let mapping = ColumnMapping {
+            fields: HashMap::from([
+                ("a".to_string(), "properties".to_string()),
+                ("b".to_string(), "properties.plan".to_string()),
+            ]),
+            ..Default::default()
+        };
+
+        let err = mapping
+            .map_row(row(&[("a", "x"), ("b", "pro")]))
+            .unwrap_err();
+
+        assert!(get_user_message(&err).contains("'properties.plan'"));
+    }
+}
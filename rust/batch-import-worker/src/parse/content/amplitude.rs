@@ -8,12 +8,11 @@ use serde_json::Value;
 use tracing::error;
 use uuid::Uuid;
 
+use super::identify::create_identify_event;
 use super::TransformContext;
 use crate::cache::{group_cache::GroupChanges, GroupCache};
 use crate::parse::format::{extract_between, extract_field_name, UserFacingParseError};
 
-mod identify;
-
 /// Represents a group that has changed properties
 #[derive(Debug, Clone)]
 pub struct ChangedGroup {
@@ -596,9 +595,10 @@ impl AmplitudeEvent {
                             if !has_seen {
                                 // Create and inject $identify event
                                 let identify_uuid = Uuid::now_v7();
-                                let identify_event = identify::create_identify_event(
+                                let identify_event = create_identify_event(
                                     team_id,
                                     &token,
+                                    "amplitude",
                                     user_id,
                                     device_id,
                                     identify_uuid,
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+use chrono::{DateTime, Utc};
+use common_types::{CapturedEvent, InternallyCapturedEvent, RawEvent};
+use serde_json::Value;
+use tracing::error;
+use uuid::Uuid;
+
+use super::TransformContext;
+use crate::cache::GroupChanges;
+
+/// A PostHog event mapped from a third-party call, before it's turned into an
+/// `InternallyCapturedEvent`. Shared by the content types whose sources carry their own
+/// identify and group calls.
+#[derive(Debug, Clone)]
+pub struct MappedEvent {
+    pub event: String,
+    pub distinct_id: String,
+    pub uuid: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub ip: Option<String>,
+    pub properties: HashMap<String, Value>,
+    pub set: HashMap<String, Value>,
+    pub set_once: HashMap<String, Value>,
+}
+
+impl MappedEvent {
+    pub fn new(
+        event: impl Into<String>,
+        distinct_id: impl Into<String>,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            event: event.into(),
+            distinct_id: distinct_id.into(),
+            uuid: Uuid::now_v7(),
+            timestamp,
+            ip: None,
+            properties: HashMap::new(),
+            set: HashMap::new(),
+            set_once: HashMap::new(),
+        }
+    }
+
+    /// A `$groupidentify` event applying a group's property changes
+    pub fn group_identify(
+        distinct_id: &str,
+        group_type: &str,
+        group_key: &str,
+        changes: GroupChanges,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let mut event = Self::new("$groupidentify", distinct_id, timestamp);
+        event.properties.insert(
+            "$group_type".to_string(),
+            Value::String(group_type.to_string()),
+        );
+        event.properties.insert(
+            "$group_key".to_string(),
+            Value::String(group_key.to_string()),
+        );
+        // Always included, even if empty, so the group gets created
+        event.properties.insert(
+            "$group_set".to_string(),
+            Value::Object(changes.set.into_iter().collect()),
+        );
+        if !changes.unset.is_empty() {
+            event.properties.insert(
+                "$group_unset".to_string(),
+                Value::Array(changes.unset.into_iter().map(Value::String).collect()),
+            );
+        }
+        event
+    }
+
+    /// Tag the event as a historical import from `source`, run it through `event_transform`
+    /// and build the event to emit
+    pub fn into_captured(
+        mut self,
+        context: &TransformContext,
+        source: &str,
+        event_transform: &impl Fn(RawEvent) -> Result<Option<RawEvent>, Error>,
+    ) -> Result<Option<InternallyCapturedEvent>, Error> {
+        self.properties
+            .insert("historical_migration".to_string(), Value::Bool(true));
+        self.properties.insert(
+            "analytics_source".to_string(),
+            Value::String(source.to_string()),
+        );
+        self.properties.insert(
+            "$import_job_id".to_string(),
+            Value::String(context.job_id.to_string()),
+        );
+
+        let raw_event = RawEvent {
+            token: Some(context.token.clone()),
+            distinct_id: Some(Value::String(self.distinct_id.clone())),
+            uuid: Some(self.uuid),
+            event: self.event,
+            properties: self.properties,
+            timestamp: Some(self.timestamp.to_rfc3339()),
+            set: (!self.set.is_empty()).then_some(self.set),
+            set_once: (!self.set_once.is_empty()).then_some(self.set_once),
+            offset: None,
+        };
+
+        let Some(raw_event) = event_transform(raw_event)? else {
+            return Ok(None);
+        };
+
+        let inner = CapturedEvent {
+            uuid: self.uuid,
+            distinct_id: self.distinct_id,
+            session_id: None,
+            ip: self.ip.unwrap_or_else(|| "127.0.0.1".to_string()),
+            data: serde_json::to_string(&raw_event)?,
+            now: Utc::now().to_rfc3339(),
+            sent_at: None,
+            token: context.token.clone(),
+            event: raw_event.event.clone(),
+            timestamp: self.timestamp,
+            is_cookieless_mode: false,
+            historical_migration: true,
+        };
+
+        Ok(Some(InternallyCapturedEvent {
+            team_id: context.team_id,
+            inner,
+        }))
+    }
+}
+
+/// Whether this is the first time the job has seen `user_id` together with
+/// `anon_distinct_id`, marking the pair as seen. Cache failures are logged and treated as
+/// already seen, so they never generate duplicate merges.
+pub fn is_new_identity(context: &TransformContext, user_id: &str, anon_distinct_id: &str) -> bool {
+    let team_id = context.team_id;
+    match context
+        .identify_cache
+        .has_seen_user_device(team_id, user_id, anon_distinct_id)
+    {
+        Ok(true) => false,
+        Ok(false) => {
+            if let Err(e) =
+                context
+                    .identify_cache
+                    .mark_seen_user_device(team_id, user_id, anon_distinct_id)
+            {
+                error!(
+                    "Failed to mark seen in identify cache for team {} user {} anon id {}: {}",
+                    team_id, user_id, anon_distinct_id, e
+                );
+            }
+            true
+        }
+        Err(e) => {
+            error!(
+                "Failed to check identify cache for team {} user {} anon id {}: {}",
+                team_id, user_id, anon_distinct_id, e
+            );
+            false
+        }
+    }
+}
+
+/// Changes to a group's properties since the job last saw it, if any, marking the new
+/// properties as seen
+pub fn group_changes(
+    context: &TransformContext,
+    group_type: &str,
+    group_key: &str,
+    properties: &HashMap<String, Value>,
+) -> Option<GroupChanges> {
+    let team_id = context.team_id;
+    let changes = context
+        .group_cache
+        .get_group_changes(team_id, group_type, group_key, properties)?;
+
+    if let Err(e) = context
+        .group_cache
+        .mark_group_seen(team_id, group_type, group_key, properties)
+    {
+        error!(
+            "Failed to mark group seen for team {} group {}/{}: {}",
+            team_id, group_type, group_key, e
+        );
+    }
+    Some(changes)
+}
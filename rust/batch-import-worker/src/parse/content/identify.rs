@@ -5,10 +5,12 @@ use serde_json::Value;
 use uuid::Uuid;
 
 /// Creates a PostHog $identify event that links a user_id to a device_id
-/// This is used when we first encounter a user_id + device_id combination from Amplitude
+/// This is used when we first encounter a user_id + device_id combination from `source`,
+/// e.g. an Amplitude device id or a Segment anonymous id
 pub fn create_identify_event(
     team_id: i32,
     token: &str,
+    source: &str,
     user_id: &str,
     device_id: &str,
     event_uuid: Uuid,
@@ -34,13 +36,13 @@ pub fn create_identify_event(
         Value::String(device_id.to_string()),
     );
 
-    // Add Amplitude-specific metadata
+    // Add source-specific metadata, e.g. $amplitude_user_id
     properties.insert(
-        "$amplitude_user_id".to_string(),
+        format!("${source}_user_id"),
         Value::String(user_id.to_string()),
     );
     properties.insert(
-        "$amplitude_device_id".to_string(),
+        format!("${source}_device_id"),
         Value::String(device_id.to_string()),
     );
 
@@ -48,7 +50,7 @@ pub fn create_identify_event(
     properties.insert("historical_migration".to_string(), Value::Bool(true));
     properties.insert(
         "analytics_source".to_string(),
-        Value::String("amplitude".to_string()),
+        Value::String(source.to_string()),
     );
 
     // Create the raw event
@@ -91,6 +93,8 @@ mod tests {
     use super::*;
     use serde_json::{json, Value};
 
+    const SOURCE: &str = "amplitude";
+
     #[test]
     fn test_create_identify_event() {
         let team_id = 123;
@@ -100,9 +104,10 @@ mod tests {
         let event_uuid = Uuid::now_v7();
 
         let timestamp = Utc::now();
-        let result =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp)
-                .unwrap();
+        let result = create_identify_event(
+            team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+        )
+        .unwrap();
 
         assert_eq!(result.team_id, team_id);
         assert_eq!(result.inner.token, token);
@@ -145,9 +150,10 @@ mod tests {
         let event_uuid = Uuid::now_v7();
 
         let timestamp = Utc::now();
-        let result =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp)
-                .unwrap();
+        let result = create_identify_event(
+            team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+        )
+        .unwrap();
 
         // Verify the event has all required fields
         assert!(!result.inner.data.is_empty());
@@ -173,9 +179,10 @@ mod tests {
         let event_uuid = Uuid::now_v7();
 
         let timestamp = Utc::now();
-        let result =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp)
-                .unwrap();
+        let result = create_identify_event(
+            team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+        )
+        .unwrap();
 
         // Verify basic structure
         assert_eq!(result.team_id, team_id);
@@ -208,7 +215,7 @@ mod tests {
 
         // Test with empty strings (should fail)
         let timestamp = Utc::now();
-        let result = create_identify_event(team_id, token, "", "", event_uuid, timestamp);
+        let result = create_identify_event(team_id, token, SOURCE, "", "", event_uuid, timestamp);
         assert!(result.is_err(), "Should reject empty user_id");
         assert!(result
             .unwrap_err()
@@ -216,15 +223,24 @@ mod tests {
             .contains("user_id cannot be empty"));
 
         // Test with whitespace-only strings (should fail)
-        let result =
-            create_identify_event(team_id, token, "   ", "device123", event_uuid, timestamp);
+        let result = create_identify_event(
+            team_id,
+            token,
+            SOURCE,
+            "   ",
+            "device123",
+            event_uuid,
+            timestamp,
+        );
         assert!(result.is_err(), "Should reject whitespace-only user_id");
         assert!(result
             .unwrap_err()
             .to_string()
             .contains("user_id cannot be empty"));
 
-        let result = create_identify_event(team_id, token, "user123", "   ", event_uuid, timestamp);
+        let result = create_identify_event(
+            team_id, token, SOURCE, "user123", "   ", event_uuid, timestamp,
+        );
         assert!(result.is_err(), "Should reject whitespace-only device_id");
         assert!(result
             .unwrap_err()
@@ -237,6 +253,7 @@ mod tests {
         let result = create_identify_event(
             team_id,
             token,
+            SOURCE,
             &long_user_id,
             &long_device_id,
             event_uuid,
@@ -250,6 +267,7 @@ mod tests {
         let result = create_identify_event(
             team_id,
             token,
+            SOURCE,
             unicode_user_id,
             unicode_device_id,
             event_uuid,
@@ -278,9 +296,10 @@ mod tests {
         let event_uuid = Uuid::now_v7();
 
         let timestamp = Utc::now();
-        let result =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp)
-                .unwrap();
+        let result = create_identify_event(
+            team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+        )
+        .unwrap();
         let data: RawEvent = serde_json::from_str(&result.inner.data).unwrap();
 
         // Verify exact JSON structure
@@ -318,12 +337,26 @@ mod tests {
         let event_uuid2 = Uuid::now_v7();
 
         let timestamp = Utc::now();
-        let result1 =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid1, timestamp)
-                .unwrap();
-        let result2 =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid2, timestamp)
-                .unwrap();
+        let result1 = create_identify_event(
+            team_id,
+            token,
+            SOURCE,
+            user_id,
+            device_id,
+            event_uuid1,
+            timestamp,
+        )
+        .unwrap();
+        let result2 = create_identify_event(
+            team_id,
+            token,
+            SOURCE,
+            user_id,
+            device_id,
+            event_uuid2,
+            timestamp,
+        )
+        .unwrap();
 
         // UUIDs should be preserved
         assert_eq!(result1.inner.uuid, event_uuid1);
@@ -347,9 +380,10 @@ mod tests {
         let event_uuid = Uuid::now_v7();
 
         let timestamp = Utc::now();
-        let result =
-            create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp)
-                .unwrap();
+        let result = create_identify_event(
+            team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+        )
+        .unwrap();
 
         // Verify CapturedEvent structure
         assert_eq!(result.inner.uuid, event_uuid);
@@ -380,6 +414,7 @@ mod tests {
         let result = create_identify_event(
             team_id,
             token,
+            SOURCE,
             user_id,
             device_id,
             event_uuid,
@@ -410,6 +445,7 @@ mod tests {
         let result = create_identify_event(
             team_id,
             token,
+            SOURCE,
             user_id,
             device_id,
             event_uuid,
@@ -465,8 +501,9 @@ mod tests {
 
         let timestamp = Utc::now();
         for (user_id, device_id) in failing_cases {
-            let result =
-                create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp);
+            let result = create_identify_event(
+                team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+            );
             assert!(
                 result.is_err(),
                 "Should reject invalid case: user_id='{user_id}', device_id='{device_id}'"
@@ -484,8 +521,9 @@ mod tests {
         ];
 
         for (user_id, device_id) in valid_cases {
-            let result =
-                create_identify_event(team_id, token, user_id, device_id, event_uuid, timestamp);
+            let result = create_identify_event(
+                team_id, token, SOURCE, user_id, device_id, event_uuid, timestamp,
+            );
             assert!(
                 result.is_ok(),
                 "Should accept valid case: user_id='{user_id}', device_id='{device_id}'"
@@ -1,13 +1,18 @@
 use std::sync::Arc;
 
 use mixpanel::MixpanelContentConfig;
+use segment::SegmentContentConfig;
 use serde::{Deserialize, Serialize};
 
 use crate::cache::{GroupCache, IdentifyCache};
 
 pub mod amplitude;
 pub mod captured;
+pub mod events;
+pub mod identify;
 pub mod mixpanel;
+pub mod segment;
+pub mod snowplow;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
@@ -15,6 +20,8 @@ pub enum ContentType {
     Mixpanel(MixpanelContentConfig), // From a mixpanel export
     Amplitude,
     Captured, // Each json object structured as if it was going to be sent to the capture endpoint
+    Segment(SegmentContentConfig), // Segment tracking API calls, from a warehouse or S3 export
+    Snowplow, // Snowplow enriched events, one tab separated event per line
 }
 
 // All /extra/ information needed to go from any input format to an InternallyCapturedEvent,
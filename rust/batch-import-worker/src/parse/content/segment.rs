@@ -0,0 +1,720 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+use chrono::{DateTime, Utc};
+use common_types::{InternallyCapturedEvent, RawEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+use super::events::{group_changes, is_new_identity, MappedEvent};
+use super::identify::create_identify_event;
+use super::TransformContext;
+use crate::parse::format::{extract_between, extract_field_name, UserFacingParseError};
+
+/// UUID namespace for generating deterministic UUIDs from Segment messageId values, so
+/// re-importing the same export doesn't duplicate events
+const SEGMENT_MESSAGE_ID_NAMESPACE: Uuid = Uuid::from_bytes(*b"posthog_segment_");
+
+const SOURCE: &str = "segment";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct SegmentContentConfig {
+    /// Segment groups have no type, so all group calls are imported as this group type
+    #[serde(default = "default_group_type")]
+    pub group_type: String,
+}
+
+fn default_group_type() -> String {
+    "company".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentCallType {
+    Track,
+    Identify,
+    Group,
+    Page,
+    Screen,
+    Alias,
+}
+
+/// A call in Segment's tracking API format, as found in Segment warehouse and S3 exports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentEvent {
+    #[serde(rename = "type")]
+    pub call_type: SegmentCallType,
+    // Ids are strings in the spec, but some exports write numeric user ids as numbers
+    pub user_id: Option<Value>,
+    pub anonymous_id: Option<Value>,
+    pub group_id: Option<Value>,
+    pub previous_id: Option<Value>,
+    pub message_id: Option<String>,
+    /// Event name of track calls
+    pub event: Option<String>,
+    /// Page or screen name of page and screen calls
+    pub name: Option<String>,
+    #[serde(default)]
+    pub properties: Option<Map<String, Value>>,
+    #[serde(default)]
+    pub traits: Option<Map<String, Value>>,
+    #[serde(default)]
+    pub context: Option<SegmentContext>,
+    pub timestamp: Option<String>,
+    pub original_timestamp: Option<String>,
+    pub received_at: Option<String>,
+    pub sent_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentContext {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub locale: Option<String>,
+    pub timezone: Option<String>,
+    pub group_id: Option<Value>,
+    pub page: Option<Map<String, Value>>,
+    pub campaign: Option<Map<String, Value>>,
+    pub os: Option<Map<String, Value>>,
+    pub device: Option<Map<String, Value>>,
+    pub app: Option<Map<String, Value>>,
+    pub library: Option<Map<String, Value>>,
+    pub screen: Option<Map<String, Value>>,
+}
+
+/// Implement schema-specific error messages for SegmentEvent
+/// That we can surface to the user to let them know what's wrong with the
+/// data set they are trying to import
+impl UserFacingParseError for SegmentEvent {
+    fn user_facing_schema_error(err: &serde_json::Error) -> String {
+        let err_str = err.to_string();
+
+        if err_str.contains("missing field") {
+            if let Some(field_name) = extract_field_name(&err_str, "missing field `", "`") {
+                return match field_name.as_str() {
+                    "type" => "Missing required field 'type'. Each Segment call must have a 'type' of track, identify, group, page, screen or alias.".to_string(),
+                    _ => format!("Missing required field '{field_name}'. Please check that your Segment export includes this field."),
+                };
+            }
+        }
+
+        if err_str.contains("unknown variant") {
+            if let Some(call_type) = extract_field_name(&err_str, "unknown variant `", "`") {
+                return format!(
+                    "Unknown Segment call type '{call_type}'. Supported types are track, identify, group, page, screen and alias."
+                );
+            }
+        }
+
+        if err_str.contains("invalid type:") {
+            if let Some(got) = extract_between(&err_str, "invalid type: ", ", expected") {
+                if err_str.contains("map") {
+                    return format!(
+                        "Expected a JSON object but got {got}. The 'properties', 'traits' and 'context' fields must be JSON objects like {{\"key\": \"value\"}}."
+                    );
+                }
+                return format!(
+                    "Unexpected {got}. The 'event', 'name', 'messageId' and timestamp fields must be strings."
+                );
+            }
+        }
+
+        // Fallback to generic message
+        "The JSON structure doesn't match the expected Segment call format. Each line should be a Segment tracking API call with a 'type' and a 'userId' or 'anonymousId'.".to_string()
+    }
+}
+
+impl SegmentEvent {
+    pub fn parse_fn(
+        context: TransformContext,
+        config: SegmentContentConfig,
+        event_transform: impl Fn(RawEvent) -> Result<Option<RawEvent>, Error>,
+    ) -> impl Fn(Self) -> Result<Vec<InternallyCapturedEvent>, Error> {
+        move |seg| {
+            let user_id = seg.user_id.as_ref().and_then(id_string);
+            let anonymous_id = seg.anonymous_id.as_ref().and_then(id_string);
+
+            // Every Segment call has one or the other, calls without either can't be
+            // attributed to anyone
+            let Some(distinct_id) = user_id.clone().or_else(|| anonymous_id.clone()) else {
+                return Ok(vec![]);
+            };
+
+            let timestamp = parse_timestamp(&seg);
+            let event_uuid = seg
+                .message_id
+                .as_deref()
+                .map(|id| Uuid::new_v5(&SEGMENT_MESSAGE_ID_NAMESPACE, id.as_bytes()))
+                .unwrap_or_else(Uuid::now_v7);
+            let seg_context = seg.context.clone().unwrap_or_default();
+
+            let mut events = Vec::new();
+            let mut mapped = Vec::new();
+
+            match seg.call_type {
+                SegmentCallType::Track | SegmentCallType::Page | SegmentCallType::Screen => {
+                    // Link the anonymous and identified ids the first time we see them
+                    // together, as we do for Amplitude user and device ids
+                    if context.generate_identify_events {
+                        if let (Some(user_id), Some(anonymous_id)) = (&user_id, &anonymous_id) {
+                            if is_new_identity(&context, user_id, anonymous_id) {
+                                events.push(create_identify_event(
+                                    context.team_id,
+                                    &context.token,
+                                    SOURCE,
+                                    user_id,
+                                    anonymous_id,
+                                    Uuid::now_v7(),
+                                    timestamp,
+                                )?);
+                            }
+                        }
+                    }
+
+                    // Track calls without an event name are skipped
+                    let event_name = match seg.call_type {
+                        SegmentCallType::Page => Some("$pageview".to_string()),
+                        SegmentCallType::Screen => Some("$screen".to_string()),
+                        _ => seg.event.clone().filter(|event| !event.is_empty()),
+                    };
+
+                    if let Some(event_name) = event_name.filter(|_| context.import_events) {
+                        let mut event = MappedEvent::new(event_name, distinct_id, timestamp);
+                        event.uuid = event_uuid;
+                        event.properties = seg
+                            .properties
+                            .clone()
+                            .unwrap_or_default()
+                            .into_iter()
+                            .collect();
+                        add_context_properties(&mut event, &seg_context);
+
+                        match seg.call_type {
+                            SegmentCallType::Page => add_page_properties(&mut event, &seg),
+                            SegmentCallType::Screen => {
+                                if let Some(name) = &seg.name {
+                                    event.properties.insert(
+                                        "$screen_name".to_string(),
+                                        Value::String(name.clone()),
+                                    );
+                                }
+                            }
+                            _ => {}
+                        }
+
+                        if let Some(group_id) = seg_context.group_id.as_ref().and_then(id_string) {
+                            let mut groups = Map::new();
+                            groups.insert(config.group_type.clone(), Value::String(group_id));
+                            event
+                                .properties
+                                .insert("$groups".to_string(), Value::Object(groups));
+                        }
+
+                        mapped.push(event);
+                    }
+                }
+                SegmentCallType::Identify if context.generate_identify_events => {
+                    // Merge the anonymous id the first time it's identified
+                    if let (Some(user_id), Some(anonymous_id)) = (&user_id, &anonymous_id) {
+                        if is_new_identity(&context, user_id, anonymous_id) {
+                            events.push(create_identify_event(
+                                context.team_id,
+                                &context.token,
+                                SOURCE,
+                                user_id,
+                                anonymous_id,
+                                Uuid::now_v7(),
+                                timestamp,
+                            )?);
+                        }
+                    }
+
+                    // Traits update the person's properties
+                    let traits: HashMap<String, Value> =
+                        seg.traits.clone().unwrap_or_default().into_iter().collect();
+                    if !traits.is_empty() {
+                        let mut event = MappedEvent::new("$set", distinct_id, timestamp);
+                        event.uuid = event_uuid;
+                        event.set = traits;
+                        add_context_properties(&mut event, &seg_context);
+                        mapped.push(event);
+                    }
+                }
+                SegmentCallType::Group if context.generate_group_identify_events => {
+                    let Some(group_id) = seg.group_id.as_ref().and_then(id_string) else {
+                        return Ok(vec![]);
+                    };
+                    let traits: HashMap<String, Value> =
+                        seg.traits.clone().unwrap_or_default().into_iter().collect();
+
+                    // Only emitted when the group is new or its traits changed
+                    if let Some(changes) =
+                        group_changes(&context, &config.group_type, &group_id, &traits)
+                    {
+                        let mut event = MappedEvent::group_identify(
+                            &distinct_id,
+                            &config.group_type,
+                            &group_id,
+                            changes,
+                            timestamp,
+                        );
+                        event.uuid = event_uuid;
+                        mapped.push(event);
+                    }
+                }
+                SegmentCallType::Alias if context.generate_identify_events => {
+                    let (Some(user_id), Some(previous_id)) =
+                        (&user_id, seg.previous_id.as_ref().and_then(id_string))
+                    else {
+                        return Ok(vec![]);
+                    };
+                    let mut event = MappedEvent::new("$create_alias", user_id.clone(), timestamp);
+                    event.uuid = event_uuid;
+                    event
+                        .properties
+                        .insert("alias".to_string(), Value::String(previous_id));
+                    mapped.push(event);
+                }
+                SegmentCallType::Identify | SegmentCallType::Group | SegmentCallType::Alias => {}
+            }
+
+            for event in mapped {
+                if let Some(event) = event.into_captured(&context, SOURCE, &event_transform)? {
+                    events.push(event);
+                }
+            }
+            Ok(events)
+        }
+    }
+}
+
+fn id_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn parse_timestamp(seg: &SegmentEvent) -> DateTime<Utc> {
+    [
+        &seg.timestamp,
+        &seg.original_timestamp,
+        &seg.received_at,
+        &seg.sent_at,
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|ts| DateTime::parse_from_rfc3339(ts).ok())
+    .map(|ts| ts.with_timezone(&Utc))
+    // If all timestamp parsing fails, use current time as last resort
+    .unwrap_or_else(Utc::now)
+}
+
+/// Copy the fields of a context object onto the event, under their PostHog property names
+fn copy_fields(
+    event: &mut MappedEvent,
+    object: &Option<Map<String, Value>>,
+    mapping: &[(&str, &str)],
+) {
+    let Some(object) = object else {
+        return;
+    };
+    for (from, to) in mapping {
+        if let Some(value) = object.get(*from).filter(|v| !v.is_null()) {
+            event.properties.insert(to.to_string(), value.clone());
+        }
+    }
+}
+
+fn add_context_properties(event: &mut MappedEvent, context: &SegmentContext) {
+    if let Some(ip) = &context.ip {
+        event
+            .properties
+            .insert("$ip".to_string(), Value::String(ip.clone()));
+        event.ip = Some(ip.clone());
+    }
+    if let Some(user_agent) = &context.user_agent {
+        event.properties.insert(
+            "$raw_user_agent".to_string(),
+            Value::String(user_agent.clone()),
+        );
+    }
+    if let Some(locale) = &context.locale {
+        event
+            .properties
+            .insert("$locale".to_string(), Value::String(locale.clone()));
+    }
+    if let Some(timezone) = &context.timezone {
+        event
+            .properties
+            .insert("$timezone".to_string(), Value::String(timezone.clone()));
+    }
+
+    copy_fields(
+        event,
+        &context.page,
+        &[
+            ("url", "$current_url"),
+            ("path", "$pathname"),
+            ("referrer", "$referrer"),
+            ("title", "$title"),
+        ],
+    );
+    copy_fields(
+        event,
+        &context.campaign,
+        &[
+            ("name", "utm_campaign"),
+            ("source", "utm_source"),
+            ("medium", "utm_medium"),
+            ("term", "utm_term"),
+            ("content", "utm_content"),
+        ],
+    );
+    copy_fields(
+        event,
+        &context.os,
+        &[("name", "$os"), ("version", "$os_version")],
+    );
+    copy_fields(
+        event,
+        &context.device,
+        &[
+            ("id", "$device_id"),
+            ("manufacturer", "$device_manufacturer"),
+            ("model", "$device_model"),
+        ],
+    );
+    copy_fields(
+        event,
+        &context.app,
+        &[
+            ("name", "$app_name"),
+            ("version", "$app_version"),
+            ("build", "$app_build"),
+        ],
+    );
+    copy_fields(
+        event,
+        &context.library,
+        &[("name", "$lib"), ("version", "$lib_version")],
+    );
+    copy_fields(
+        event,
+        &context.screen,
+        &[("width", "$screen_width"), ("height", "$screen_height")],
+    );
+}
+
+/// Page calls carry the page in their properties, which take precedence over the context
+fn add_page_properties(event: &mut MappedEvent, seg: &SegmentEvent) {
+    copy_fields(
+        event,
+        &seg.properties,
+        &[
+            ("url", "$current_url"),
+            ("path", "$pathname"),
+            ("referrer", "$referrer"),
+            ("title", "$title"),
+        ],
+    );
+    if let Some(name) = &seg.name {
+        event
+            .properties
+            .entry("name".to_string())
+            .or_insert_with(|| Value::String(name.clone()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{MockGroupCache, MockIdentifyCache};
+    use crate::error::get_user_message;
+    use crate::parse::format::{json_nd, skip_geoip};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/segment.jsonl");
+
+    fn fixture(call_type: &str) -> SegmentEvent {
+        FIXTURE
+            .lines()
+            .map(|line| serde_json::from_str::<SegmentEvent>(line).unwrap())
+            .find(|seg| serde_json::to_value(seg.call_type).unwrap() == json!(call_type))
+            .unwrap()
+    }
+
+    fn create_test_context() -> TransformContext {
+        TransformContext {
+            team_id: 123,
+            token: "test_token".to_string(),
+            job_id: Uuid::now_v7(),
+            identify_cache: Arc::new(MockIdentifyCache::new()),
+            group_cache: Arc::new(MockGroupCache::new()),
+            import_events: true,
+            generate_identify_events: false,
+            generate_group_identify_events: false,
+        }
+    }
+
+    fn config() -> SegmentContentConfig {
+        SegmentContentConfig {
+            group_type: default_group_type(),
+        }
+    }
+
+    fn parse(
+        context: TransformContext,
+        seg: SegmentEvent,
+    ) -> Vec<(InternallyCapturedEvent, RawEvent)> {
+        let parser = SegmentEvent::parse_fn(context, config(), skip_geoip());
+        parser(seg)
+            .unwrap()
+            .into_iter()
+            .map(|event| {
+                let raw: RawEvent = serde_json::from_str(&event.inner.data).unwrap();
+                (event, raw)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fixture_parses_as_json_lines() {
+        let parsed = json_nd::<SegmentEvent>(true)(FIXTURE.as_bytes().to_vec()).unwrap();
+
+        assert_eq!(parsed.data.len(), 6);
+        assert_eq!(parsed.consumed, FIXTURE.len());
+    }
+
+    #[test]
+    fn test_track_call() {
+        let events = parse(create_test_context(), fixture("track"));
+
+        assert_eq!(events.len(), 1);
+        let (event, raw) = &events[0];
+        assert_eq!(event.team_id, 123);
+        assert_eq!(event.inner.distinct_id, "user-1");
+        assert_eq!(event.inner.ip, "203.0.113.7");
+        assert_eq!(
+            event.inner.uuid,
+            Uuid::new_v5(&SEGMENT_MESSAGE_ID_NAMESPACE, b"msg-track-1")
+        );
+        assert_eq!(
+            event.inner.timestamp.to_rfc3339(),
+            "2024-01-15T10:00:00+00:00"
+        );
+        assert_eq!(raw.event, "Order Completed");
+        assert_eq!(raw.properties["revenue"], json!(42.5));
+        assert_eq!(
+            raw.properties["$current_url"],
+            json!("https://example.com/checkout")
+        );
+        assert_eq!(raw.properties["$referrer"], json!("https://google.com"));
+        assert_eq!(raw.properties["utm_campaign"], json!("winter"));
+        assert_eq!(raw.properties["$lib"], json!("analytics.js"));
+        assert_eq!(raw.properties["$groups"], json!({"company": "acme"}));
+        assert_eq!(raw.properties["analytics_source"], json!("segment"));
+        assert_eq!(raw.properties["historical_migration"], json!(true));
+    }
+
+    #[test]
+    fn test_track_call_links_anonymous_id_once() {
+        let mut context = create_test_context();
+        context.generate_identify_events = true;
+
+        let first = parse(context.clone(), fixture("track"));
+        let second = parse(context, fixture("track"));
+
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].1.event, "$identify");
+        assert_eq!(first[0].1.properties["$anon_distinct_id"], json!("anon-1"));
+        assert_eq!(first[1].1.event, "Order Completed");
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].1.event, "Order Completed");
+    }
+
+    #[test]
+    fn test_identify_call() {
+        let mut context = create_test_context();
+        context.generate_identify_events = true;
+
+        let first = parse(context.clone(), fixture("identify"));
+
+        // The anonymous id is merged first, then the traits are set on the person
+        assert_eq!(first.len(), 2);
+        let (event, raw) = &first[0];
+        assert_eq!(raw.event, "$identify");
+        assert_eq!(event.inner.distinct_id, "user-1");
+        assert_eq!(raw.properties["$anon_distinct_id"], json!("anon-1"));
+        assert_eq!(raw.properties["analytics_source"], json!("segment"));
+        let (event, raw) = &first[1];
+        assert_eq!(raw.event, "$set");
+        assert_eq!(event.inner.distinct_id, "user-1");
+        assert_eq!(
+            raw.set.as_ref().unwrap()["email"],
+            json!("user-1@example.com")
+        );
+
+        // Once linked, later identify calls only update the person's properties
+        let second = parse(context, fixture("identify"));
+
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].1.event, "$set");
+        assert_eq!(second[0].1.set.as_ref().unwrap()["plan"], json!("pro"));
+    }
+
+    #[test]
+    fn test_identify_call_without_traits_is_deduplicated() {
+        let mut context = create_test_context();
+        context.generate_identify_events = true;
+        let mut seg = fixture("identify");
+        seg.traits = None;
+
+        assert_eq!(parse(context.clone(), seg.clone()).len(), 1);
+        assert!(parse(context, seg).is_empty());
+    }
+
+    #[test]
+    fn test_identify_call_skipped_without_identify_events() {
+        assert!(parse(create_test_context(), fixture("identify")).is_empty());
+    }
+
+    #[test]
+    fn test_group_call() {
+        let mut context = create_test_context();
+        context.generate_group_identify_events = true;
+
+        let first = parse(context.clone(), fixture("group"));
+
+        assert_eq!(first.len(), 1);
+        let (event, raw) = &first[0];
+        assert_eq!(raw.event, "$groupidentify");
+        assert_eq!(event.inner.distinct_id, "user-1");
+        assert_eq!(raw.properties["$group_type"], json!("company"));
+        assert_eq!(raw.properties["$group_key"], json!("acme"));
+        assert_eq!(
+            raw.properties["$group_set"],
+            json!({"name": "Acme Inc", "employees": 50})
+        );
+
+        // Unchanged traits don't produce another $groupidentify
+        assert!(parse(context.clone(), fixture("group")).is_empty());
+
+        let mut changed = fixture("group");
+        changed.traits = Some(
+            json!({"name": "Acme Inc", "employees": 60})
+                .as_object()
+                .unwrap()
+                .clone(),
+        );
+        let third = parse(context, changed);
+        assert_eq!(
+            third[0].1.properties["$group_set"],
+            json!({"employees": 60})
+        );
+    }
+
+    #[test]
+    fn test_group_call_uses_configured_group_type() {
+        let mut context = create_test_context();
+        context.generate_group_identify_events = true;
+        let parser = SegmentEvent::parse_fn(
+            context,
+            SegmentContentConfig {
+                group_type: "organization".to_string(),
+            },
+            skip_geoip(),
+        );
+
+        let events = parser(fixture("group")).unwrap();
+        let raw: RawEvent = serde_json::from_str(&events[0].inner.data).unwrap();
+
+        assert_eq!(raw.properties["$group_type"], json!("organization"));
+    }
+
+    #[test]
+    fn test_page_call() {
+        let events = parse(create_test_context(), fixture("page"));
+
+        assert_eq!(events.len(), 1);
+        let (event, raw) = &events[0];
+        assert_eq!(raw.event, "$pageview");
+        assert_eq!(event.inner.distinct_id, "anon-2");
+        assert_eq!(
+            raw.properties["$current_url"],
+            json!("https://example.com/pricing")
+        );
+        assert_eq!(raw.properties["$pathname"], json!("/pricing"));
+        assert_eq!(raw.properties["$title"], json!("Pricing"));
+        assert_eq!(raw.properties["name"], json!("Pricing"));
+    }
+
+    #[test]
+    fn test_screen_call() {
+        let events = parse(create_test_context(), fixture("screen"));
+
+        assert_eq!(events.len(), 1);
+        let (event, raw) = &events[0];
+        assert_eq!(raw.event, "$screen");
+        // Numeric user ids are imported as strings
+        assert_eq!(event.inner.distinct_id, "42");
+        assert_eq!(
+            event.inner.timestamp.to_rfc3339(),
+            "2024-01-15T10:03:00+00:00"
+        );
+        assert_eq!(raw.properties["$screen_name"], json!("Home"));
+        assert_eq!(raw.properties["$os"], json!("iOS"));
+        assert_eq!(raw.properties["$os_version"], json!("17.2"));
+        assert_eq!(raw.properties["$device_model"], json!("iPhone15,2"));
+        assert_eq!(raw.properties["$app_version"], json!("2.3.0"));
+    }
+
+    #[test]
+    fn test_alias_call() {
+        let mut context = create_test_context();
+        context.generate_identify_events = true;
+
+        let events = parse(context, fixture("alias"));
+
+        assert_eq!(events.len(), 1);
+        let (event, raw) = &events[0];
+        assert_eq!(raw.event, "$create_alias");
+        assert_eq!(event.inner.distinct_id, "user-1");
+        assert_eq!(raw.properties["alias"], json!("legacy-1"));
+    }
+
+    #[test]
+    fn test_track_call_skipped_without_import_events() {
+        let mut context = create_test_context();
+        context.import_events = false;
+
+        assert!(parse(context, fixture("track")).is_empty());
+    }
+
+    #[test]
+    fn test_call_without_any_id_is_skipped() {
+        let mut seg = fixture("page");
+        seg.anonymous_id = None;
+
+        assert!(parse(create_test_context(), seg).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_call_type_has_user_facing_error() {
+        let line = br#"{"type": "flush", "userId": "user-1"}
+"#;
+        let err = json_nd::<SegmentEvent>(false)(line.to_vec()).unwrap_err();
+
+        let msg = get_user_message(&err);
+        assert!(
+            msg.contains("Unknown Segment call type 'flush'"),
+            "Expected the call type in the error, got: {msg}"
+        );
+    }
+}
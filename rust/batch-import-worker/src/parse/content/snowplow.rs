@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Error};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use common_types::{InternallyCapturedEvent, RawEvent};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::events::{is_new_identity, MappedEvent};
+use super::identify::create_identify_event;
+use super::TransformContext;
+use crate::error::UserError;
+
+const SOURCE: &str = "snowplow";
+
+/// Columns of a Snowplow enriched event, in the order they appear in the TSV
+const FIELDS: [&str; 131] = [
+    "app_id",
+    "platform",
+    "etl_tstamp",
+    "collector_tstamp",
+    "dvce_created_tstamp",
+    "event",
+    "event_id",
+    "txn_id",
+    "name_tracker",
+    "v_tracker",
+    "v_collector",
+    "v_etl",
+    "user_id",
+    "user_ipaddress",
+    "user_fingerprint",
+    "domain_userid",
+    "domain_sessionidx",
+    "network_userid",
+    "geo_country",
+    "geo_region",
+    "geo_city",
+    "geo_zipcode",
+    "geo_latitude",
+    "geo_longitude",
+    "geo_region_name",
+    "ip_isp",
+    "ip_organization",
+    "ip_domain",
+    "ip_netspeed",
+    "page_url",
+    "page_title",
+    "page_referrer",
+    "page_urlscheme",
+    "page_urlhost",
+    "page_urlport",
+    "page_urlpath",
+    "page_urlquery",
+    "page_urlfragment",
+    "refr_urlscheme",
+    "refr_urlhost",
+    "refr_urlport",
+    "refr_urlpath",
+    "refr_urlquery",
+    "refr_urlfragment",
+    "refr_medium",
+    "refr_source",
+    "refr_term",
+    "mkt_medium",
+    "mkt_source",
+    "mkt_term",
+    "mkt_content",
+    "mkt_campaign",
+    "contexts",
+    "se_category",
+    "se_action",
+    "se_label",
+    "se_property",
+    "se_value",
+    "unstruct_event",
+    "tr_orderid",
+    "tr_affiliation",
+    "tr_total",
+    "tr_tax",
+    "tr_shipping",
+    "tr_city",
+    "tr_state",
+    "tr_country",
+    "ti_orderid",
+    "ti_sku",
+    "ti_name",
+    "ti_category",
+    "ti_price",
+    "ti_quantity",
+    "pp_xoffset_min",
+    "pp_xoffset_max",
+    "pp_yoffset_min",
+    "pp_yoffset_max",
+    "useragent",
+    "br_name",
+    "br_family",
+    "br_version",
+    "br_type",
+    "br_renderengine",
+    "br_lang",
+    "br_features_pdf",
+    "br_features_flash",
+    "br_features_java",
+    "br_features_director",
+    "br_features_quicktime",
+    "br_features_realplayer",
+    "br_features_windowsmedia",
+    "br_features_gears",
+    "br_features_silverlight",
+    "br_cookies",
+    "br_colordepth",
+    "br_viewwidth",
+    "br_viewheight",
+    "os_name",
+    "os_family",
+    "os_manufacturer",
+    "os_timezone",
+    "dvce_type",
+    "dvce_ismobile",
+    "dvce_screenwidth",
+    "dvce_screenheight",
+    "doc_charset",
+    "doc_width",
+    "doc_height",
+    "tr_currency",
+    "tr_total_base",
+    "tr_tax_base",
+    "tr_shipping_base",
+    "ti_currency",
+    "ti_price_base",
+    "base_currency",
+    "geo_timezone",
+    "mkt_clickid",
+    "mkt_network",
+    "etl_tags",
+    "dvce_sent_tstamp",
+    "refr_domain_userid",
+    "refr_dvce_tstamp",
+    "derived_contexts",
+    "domain_sessionid",
+    "derived_tstamp",
+    "event_vendor",
+    "event_name",
+    "event_format",
+    "event_version",
+    "event_fingerprint",
+    "true_tstamp",
+];
+
+/// Columns copied onto every event, under their PostHog property names
+const PROPERTY_FIELDS: &[(&str, &str)] = &[
+    ("page_url", "$current_url"),
+    ("page_urlhost", "$host"),
+    ("page_urlpath", "$pathname"),
+    ("page_title", "$title"),
+    ("page_referrer", "$referrer"),
+    ("refr_urlhost", "$referring_domain"),
+    ("mkt_source", "utm_source"),
+    ("mkt_medium", "utm_medium"),
+    ("mkt_campaign", "utm_campaign"),
+    ("mkt_term", "utm_term"),
+    ("mkt_content", "utm_content"),
+    ("user_ipaddress", "$ip"),
+    ("geo_country", "$geoip_country_code"),
+    ("geo_region_name", "$geoip_subdivision_1_name"),
+    ("geo_city", "$geoip_city_name"),
+    ("geo_zipcode", "$geoip_postal_code"),
+    ("geo_timezone", "$geoip_time_zone"),
+    ("useragent", "$raw_user_agent"),
+    ("br_family", "$browser"),
+    ("br_version", "$browser_version"),
+    ("br_lang", "$browser_language"),
+    ("os_family", "$os"),
+    ("domain_userid", "$device_id"),
+    ("domain_sessionid", "$snowplow_session_id"),
+    ("app_id", "$snowplow_app_id"),
+    ("platform", "$snowplow_platform"),
+];
+
+/// Numeric columns copied onto every event
+const NUMERIC_PROPERTY_FIELDS: &[(&str, &str)] = &[
+    ("geo_latitude", "$geoip_latitude"),
+    ("geo_longitude", "$geoip_longitude"),
+    ("br_viewwidth", "$viewport_width"),
+    ("br_viewheight", "$viewport_height"),
+    ("dvce_screenwidth", "$screen_width"),
+    ("dvce_screenheight", "$screen_height"),
+];
+
+/// Transaction columns, copied as-is onto the events that have them
+const TRANSACTION_FIELDS: &[&str] = &[
+    "tr_orderid",
+    "tr_affiliation",
+    "tr_total",
+    "tr_tax",
+    "tr_shipping",
+    "tr_city",
+    "tr_state",
+    "tr_country",
+    "tr_currency",
+    "ti_orderid",
+    "ti_sku",
+    "ti_name",
+    "ti_category",
+    "ti_price",
+    "ti_quantity",
+    "ti_currency",
+];
+
+/// A Snowplow enriched event, as written by the enrich step to S3 or GCS: one event per
+/// line, with tab separated columns in a fixed order
+#[derive(Debug, Clone)]
+pub struct SnowplowEvent {
+    columns: Vec<String>,
+}
+
+impl SnowplowEvent {
+    /// Parse one enriched event line. Anything other than the full set of columns is
+    /// rejected, which also keeps lines cut off at the end of a chunk from being consumed.
+    pub fn from_tsv(line: &str) -> Result<Self, Error> {
+        let line = line.trim_matches(|c| c == '\r' || c == '\n');
+        let columns: Vec<String> = line.split('\t').map(String::from).collect();
+
+        if columns.len() != FIELDS.len() {
+            return Err(Error::msg(format!(
+                "Expected {} tab separated columns, got {}",
+                FIELDS.len(),
+                columns.len()
+            )))
+            .context(UserError::new(format!(
+                "Each line must be a Snowplow enriched event with {} tab separated columns, but a line had {}. Check that the export contains enriched events, not raw collector payloads.",
+                FIELDS.len(),
+                columns.len()
+            )))
+            .context("Failed to parse Snowplow enriched event");
+        }
+
+        Ok(Self { columns })
+    }
+
+    /// The value of a column, if it's not empty
+    pub fn get(&self, field: &str) -> Option<&str> {
+        let index = FIELDS.iter().position(|f| *f == field)?;
+        self.columns
+            .get(index)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn parse_fn(
+        context: TransformContext,
+        event_transform: impl Fn(RawEvent) -> Result<Option<RawEvent>, Error>,
+    ) -> impl Fn(Self) -> Result<Vec<InternallyCapturedEvent>, Error> {
+        move |sp| {
+            let Some(event_type) = sp.get("event") else {
+                return Ok(vec![]);
+            };
+
+            let event_name = match event_type {
+                // Page pings are heartbeats while a page is open. PostHog derives time on page
+                // from timestamps, so they have no meaning and are filtered.
+                "page_ping" => return Ok(vec![]),
+                "page_view" => "$pageview".to_string(),
+                "struct" => sp
+                    .get("se_action")
+                    .or(sp.get("se_category"))
+                    .unwrap_or("struct")
+                    .to_string(),
+                // Self-describing events are named by their schema, e.g. link_click
+                _ => sp.get("event_name").unwrap_or(event_type).to_string(),
+            };
+
+            let user_id = sp.get("user_id");
+            let device_id = sp.get("domain_userid").or(sp.get("network_userid"));
+            // Events without either can't be attributed to anyone
+            let Some(distinct_id) = user_id.or(device_id).map(String::from) else {
+                return Ok(vec![]);
+            };
+            let timestamp = parse_timestamp(&sp);
+
+            let mut events = Vec::new();
+            let mut mapped = Vec::new();
+
+            if context.generate_identify_events {
+                if let (Some(user_id), Some(device_id)) = (user_id, device_id) {
+                    if is_new_identity(&context, user_id, device_id) {
+                        events.push(create_identify_event(
+                            context.team_id,
+                            &context.token,
+                            SOURCE,
+                            user_id,
+                            device_id,
+                            Uuid::now_v7(),
+                            timestamp,
+                        )?);
+                    }
+                }
+            }
+
+            if context.import_events {
+                let mut event = MappedEvent::new(event_name, distinct_id, timestamp);
+                if let Some(event_id) = sp.get("event_id").and_then(|id| Uuid::parse_str(id).ok()) {
+                    event.uuid = event_id;
+                }
+                event.ip = sp.get("user_ipaddress").map(String::from);
+                event.properties = sp.properties();
+                mapped.push(event);
+            }
+
+            for event in mapped {
+                if let Some(event) = event.into_captured(&context, SOURCE, &event_transform)? {
+                    events.push(event);
+                }
+            }
+            Ok(events)
+        }
+    }
+
+    fn properties(&self) -> HashMap<String, Value> {
+        // Self-describing events wrap their data as {"schema": ..., "data": {"schema": ..., "data": {...}}}.
+        // It goes in first, so the columns we map below win over any keys it shares with them.
+        let mut properties: HashMap<String, Value> = match self
+            .get("unstruct_event")
+            .and_then(|json| serde_json::from_str::<Value>(json).ok())
+            .and_then(|event| event.pointer("/data/data").cloned())
+        {
+            Some(Value::Object(data)) => data.into_iter().collect(),
+            _ => HashMap::new(),
+        };
+
+        for (field, property) in PROPERTY_FIELDS {
+            if let Some(value) = self.get(field) {
+                properties.insert(property.to_string(), Value::String(value.to_string()));
+            }
+        }
+        for (field, property) in NUMERIC_PROPERTY_FIELDS {
+            if let Some(value) = self.get(field).and_then(|v| v.parse::<f64>().ok()) {
+                properties.insert(property.to_string(), Value::from(value));
+            }
+        }
+        for field in TRANSACTION_FIELDS {
+            if let Some(value) = self.get(field) {
+                properties.insert(field.to_string(), Value::String(value.to_string()));
+            }
+        }
+
+        if let Some(device_type) = self.get("dvce_type") {
+            let device_type = match device_type {
+                "Computer" => "Desktop",
+                other => other,
+            };
+            properties.insert(
+                "$device_type".to_string(),
+                Value::String(device_type.to_string()),
+            );
+        }
+
+        // Structured events carry their data in fixed columns
+        for (field, property) in [
+            ("se_category", "category"),
+            ("se_action", "action"),
+            ("se_label", "label"),
+            ("se_property", "property"),
+        ] {
+            if let Some(value) = self.get(field) {
+                properties.insert(property.to_string(), Value::String(value.to_string()));
+            }
+        }
+        if let Some(value) = self.get("se_value").and_then(|v| v.parse::<f64>().ok()) {
+            properties.insert("value".to_string(), Value::from(value));
+        }
+
+        if let Some(contexts) = self
+            .get("contexts")
+            .and_then(|json| serde_json::from_str::<Value>(json).ok())
+            .and_then(|contexts| contexts.get("data").cloned())
+        {
+            properties.insert("$snowplow_contexts".to_string(), contexts);
+        }
+
+        properties
+    }
+}
+
+fn parse_timestamp(sp: &SnowplowEvent) -> DateTime<Utc> {
+    // The derived timestamp corrects the device clock with the collector's
+    ["derived_tstamp", "dvce_created_tstamp", "collector_tstamp"]
+        .into_iter()
+        .filter_map(|field| sp.get(field))
+        .find_map(|ts| NaiveDateTime::parse_from_str(ts, "%Y-%m-%d %H:%M:%S%.f").ok())
+        .map(|ts| ts.and_utc())
+        // If all timestamp parsing fails, use current time as last resort
+        .unwrap_or_else(Utc::now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{MockGroupCache, MockIdentifyCache};
+    use crate::error::get_user_message;
+    use crate::parse::format::{newline_delim, skip_geoip};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const FIXTURE: &str = include_str!("../../../tests/fixtures/snowplow_enriched.tsv");
+
+    fn fixture(event: &str) -> SnowplowEvent {
+        FIXTURE
+            .lines()
+            .map(|line| SnowplowEvent::from_tsv(line).unwrap())
+            .find(|sp| sp.get("event") == Some(event))
+            .unwrap()
+    }
+
+    fn create_test_context() -> TransformContext {
+        TransformContext {
+            team_id: 123,
+            token: "test_token".to_string(),
+            job_id: Uuid::now_v7(),
+            identify_cache: Arc::new(MockIdentifyCache::new()),
+            group_cache: Arc::new(MockGroupCache::new()),
+            import_events: true,
+            generate_identify_events: false,
+            generate_group_identify_events: false,
+        }
+    }
+
+    fn parse(
+        context: TransformContext,
+        sp: SnowplowEvent,
+    ) -> Vec<(InternallyCapturedEvent, RawEvent)> {
+        let parser = SnowplowEvent::parse_fn(context, skip_geoip());
+        parser(sp)
+            .unwrap()
+            .into_iter()
+            .map(|event| {
+                let raw: RawEvent = serde_json::from_str(&event.inner.data).unwrap();
+                (event, raw)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_page_view() {
+        let events = parse(create_test_context(), fixture("page_view"));
+
+        assert_eq!(events.len(), 1);
+        let (event, raw) = &events[0];
+        assert_eq!(raw.event, "$pageview");
+        assert_eq!(event.inner.distinct_id, "user-1");
+        assert_eq!(
+            event.inner.uuid.to_string(),
+            "5f3d4e2a-1b2c-4d5e-8f90-0a1b2c3d4e5f"
+        );
+        assert_eq!(event.inner.ip, "198.51.100.4");
+        // The derived timestamp wins over the device and collector ones
+        assert_eq!(
+            event.inner.timestamp.to_rfc3339(),
+            "2024-02-01T12:00:00.500+00:00"
+        );
+        assert_eq!(
+            raw.properties["$current_url"],
+            json!("https://shop.example.com/products?id=1")
+        );
+        assert_eq!(raw.properties["$pathname"], json!("/products"));
+        assert_eq!(raw.properties["utm_source"], json!("google"));
+        assert_eq!(raw.properties["$geoip_country_code"], json!("DE"));
+        assert_eq!(raw.properties["$geoip_latitude"], json!(52.52));
+        assert_eq!(raw.properties["$device_type"], json!("Desktop"));
+        assert_eq!(raw.properties["$device_id"], json!("dom-1"));
+        assert_eq!(raw.properties["$viewport_width"], json!(1440.0));
+        assert_eq!(
+            raw.properties["$snowplow_contexts"][0]["data"],
+            json!({"id": "page-1"})
+        );
+        assert_eq!(raw.properties["analytics_source"], json!("snowplow"));
+    }
+
+    #[test]
+    fn test_structured_event() {
+        let events = parse(create_test_context(), fixture("struct"));
+
+        let (_, raw) = &events[0];
+        assert_eq!(raw.event, "add_to_cart");
+        assert_eq!(raw.properties["category"], json!("checkout"));
+        assert_eq!(raw.properties["label"], json!("sku-1"));
+        assert_eq!(raw.properties["value"], json!(19.99));
+    }
+
+    #[test]
+    fn test_self_describing_event() {
+        let events = parse(create_test_context(), fixture("unstruct"));
+
+        let (event, raw) = &events[0];
+        assert_eq!(raw.event, "link_click");
+        // Without a user id, the cookie id is the distinct id
+        assert_eq!(event.inner.distinct_id, "dom-1");
+        assert_eq!(
+            raw.properties["targetUrl"],
+            json!("https://example.com/docs")
+        );
+        assert_eq!(raw.properties["elementId"], json!("docs-link"));
+    }
+
+    #[test]
+    fn test_self_describing_data_does_not_overwrite_mapped_properties() {
+        let mut sp = fixture("unstruct");
+        let index = FIELDS.iter().position(|f| *f == "unstruct_event").unwrap();
+        sp.columns[index] = json!({
+            "schema": "iglu:com.snowplowanalytics.snowplow/unstruct_event/jsonschema/1-0-0",
+            "data": {
+                "schema": "iglu:com.snowplowanalytics.snowplow/link_click/jsonschema/1-0-1",
+                "data": {"elementId": "docs-link", "$device_id": "spoofed"}
+            }
+        })
+        .to_string();
+
+        let events = parse(create_test_context(), sp);
+
+        let (_, raw) = &events[0];
+        assert_eq!(raw.properties["elementId"], json!("docs-link"));
+        assert_eq!(raw.properties["$device_id"], json!("dom-1"));
+    }
+
+    #[test]
+    fn test_events_without_user_or_cookie_ids_are_skipped() {
+        let mut sp = fixture("page_view");
+        for field in ["user_id", "domain_userid", "network_userid"] {
+            let index = FIELDS.iter().position(|f| *f == field).unwrap();
+            sp.columns[index] = String::new();
+        }
+
+        assert!(parse(create_test_context(), sp).is_empty());
+    }
+
+    #[test]
+    fn test_page_pings_are_filtered() {
+        assert!(parse(create_test_context(), fixture("page_ping")).is_empty());
+    }
+
+    #[test]
+    fn test_user_and_cookie_ids_are_linked_once() {
+        let mut context = create_test_context();
+        context.generate_identify_events = true;
+
+        let first = parse(context.clone(), fixture("page_view"));
+        let second = parse(context, fixture("struct"));
+
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0].1.event, "$identify");
+        assert_eq!(first[0].0.inner.distinct_id, "user-1");
+        assert_eq!(first[0].1.properties["$anon_distinct_id"], json!("dom-1"));
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].1.event, "add_to_cart");
+    }
+
+    #[test]
+    fn test_line_with_wrong_column_count_has_user_facing_error() {
+        let err = SnowplowEvent::from_tsv("shop\tweb\t2024-02-01").unwrap_err();
+
+        let msg = get_user_message(&err);
+        assert!(
+            msg.contains("131 tab separated columns"),
+            "Expected the column count in the error, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn test_line_cut_off_at_chunk_end_is_not_consumed() {
+        let first_line_len = FIXTURE.find('\n').unwrap() + 1;
+        let chunk = FIXTURE.as_bytes()[..first_line_len + 100].to_vec();
+
+        let parsed = newline_delim(false, SnowplowEvent::from_tsv)(chunk).unwrap();
+
+        assert_eq!(parsed.data.len(), 1);
+        assert_eq!(parsed.consumed, first_line_len);
+    }
+
+    #[test]
+    fn test_rows_with_empty_first_and_last_columns_keep_their_columns() {
+        let mut columns = vec![""; FIELDS.len()];
+        columns[5] = "page_view";
+        let chunk = format!("{}\n", columns.join("\t")).into_bytes();
+
+        let parsed = newline_delim(false, SnowplowEvent::from_tsv)(chunk).unwrap();
+
+        assert_eq!(parsed.data.len(), 1);
+        assert_eq!(parsed.data[0].get("event"), Some("page_view"));
+        assert_eq!(parsed.data[0].get("app_id"), None);
+    }
+}
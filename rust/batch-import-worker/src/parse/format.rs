@@ -6,14 +6,17 @@ use common_types::{InternallyCapturedEvent, RawEvent};
 use rayon::iter::IntoParallelIterator;
 use rayon::prelude::*;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-use crate::error::UserError;
+use crate::error::{ToUserError, UserError};
+use crate::source::{parquet::ParquetSource, DataSource};
 use crate::{context::AppContext, job::model::JobModel};
 
 use super::{
+    columns::ColumnMapping,
     content::{
         amplitude::AmplitudeEvent, captured::captured_parse_fn, mixpanel::MixpanelEvent,
-        ContentType, TransformContext,
+        segment::SegmentEvent, snowplow::SnowplowEvent, ContentType, TransformContext,
     },
     Parsed,
 };
@@ -25,37 +28,63 @@ pub enum FormatConfig {
         skip_blanks: bool,
         content: ContentType,
     },
+    // A single json array of objects, as exported by some legacy tools
+    JsonArray {
+        content: ContentType,
+    },
+    Csv {
+        #[serde(default = "FormatConfig::default_csv_delimiter")]
+        delimiter: char,
+        // Names of the columns, for files without a header row. If unset, the first row of
+        // each file names the columns.
+        #[serde(default)]
+        column_names: Option<Vec<String>>,
+        #[serde(default)]
+        columns: ColumnMapping,
+        content: ContentType,
+    },
+    Parquet {
+        #[serde(default)]
+        columns: ColumnMapping,
+        content: ContentType,
+    },
 }
 
 pub type ParserFn =
     Box<dyn Fn(Vec<u8>) -> Result<Parsed<Vec<InternallyCapturedEvent>>, Error> + Send + Sync>;
 
+type RecordsFn<T> = Box<dyn Fn(Vec<u8>) -> Result<Parsed<Vec<T>>, Error> + Send + Sync>;
+
 impl FormatConfig {
     pub async fn get_parser(
         &self,
         model: &JobModel,
         context: Arc<AppContext>,
     ) -> Result<ParserFn, Error> {
-        // Only support json-lines for now
-        let Self::JsonLines {
-            skip_blanks,
-            content,
-        } = self;
-
+        // The caches record which identities and groups have been seen, so the real run would skip
+        // anything a preview sharing them had marked. Previews get their own, dropped with the parser.
+        let (identify_cache, group_cache) = match &model.import_config.preview {
+            Some(_) => (
+                AppContext::new_identify_cache(&context.config),
+                AppContext::new_group_cache(&context.config),
+            ),
+            None => (context.identify_cache.clone(), context.group_cache.clone()),
+        };
         let transform_context = TransformContext {
             team_id: model.team_id,
             token: context.get_token_for_team_id(model.team_id).await?,
             job_id: model.id,
-            identify_cache: context.identify_cache.clone(),
-            group_cache: context.group_cache.clone(),
+            identify_cache,
+            group_cache,
             import_events: model.import_config.import_events,
             generate_identify_events: model.import_config.generate_identify_events,
             generate_group_identify_events: model.import_config.generate_group_identify_events,
         };
+        let chunk_size = context.config.chunk_size;
 
-        match content {
+        match self.content() {
             ContentType::Mixpanel(config) => {
-                let format_parse = json_nd(*skip_blanks);
+                let format_parse = self.records::<MixpanelEvent>(chunk_size)?;
 
                 let event_transform = MixpanelEvent::parse_fn(
                     transform_context,
@@ -68,7 +97,7 @@ impl FormatConfig {
                 );
 
                 let parser = move |data| {
-                    let parsed: Parsed<Vec<MixpanelEvent>> = format_parse(data)?;
+                    let parsed = format_parse(data)?;
                     let consumed = parsed.consumed;
                     let result: Result<_, Error> = parsed
                         .data
@@ -86,10 +115,10 @@ impl FormatConfig {
                 Ok(Box::new(parser))
             }
             ContentType::Amplitude => {
-                let format_parse = json_nd(*skip_blanks);
+                let format_parse = self.records::<AmplitudeEvent>(chunk_size)?;
                 let event_transform = AmplitudeEvent::parse_fn(transform_context, skip_geoip());
                 let parser = move |data| {
-                    let parsed: Parsed<Vec<AmplitudeEvent>> = format_parse(data)?;
+                    let parsed = format_parse(data)?;
                     let consumed = parsed.consumed;
                     let result: Vec<_> = parsed
                         .data
@@ -108,10 +137,10 @@ impl FormatConfig {
                 Ok(Box::new(parser))
             }
             ContentType::Captured => {
-                let format_parse = json_nd(*skip_blanks);
+                let format_parse = self.records::<RawEvent>(chunk_size)?;
                 let event_transform = captured_parse_fn(transform_context, skip_geoip());
                 let parser = move |data| {
-                    let parsed: Parsed<Vec<RawEvent>> = format_parse(data)?;
+                    let parsed = format_parse(data)?;
                     let consumed = parsed.consumed;
                     let result: Result<_, Error> = parsed
                         .data
@@ -128,8 +157,166 @@ impl FormatConfig {
 
                 Ok(Box::new(parser))
             }
+            ContentType::Segment(config) => {
+                let format_parse = self.records::<SegmentEvent>(chunk_size)?;
+                let event_transform =
+                    SegmentEvent::parse_fn(transform_context, config.clone(), skip_geoip());
+                let parser = move |data| {
+                    let parsed = format_parse(data)?;
+                    let consumed = parsed.consumed;
+                    let result: Result<Vec<_>, Error> =
+                        parsed.data.into_par_iter().map(&event_transform).collect();
+
+                    Ok(Parsed {
+                        data: result?.into_iter().flatten().collect(),
+                        consumed,
+                    })
+                };
+
+                Ok(Box::new(parser))
+            }
+            ContentType::Snowplow => {
+                let Self::JsonLines { skip_blanks, .. } = self else {
+                    return Err(Error::msg("Unsupported format for Snowplow content").context(
+                        UserError::new("Snowplow enriched events can only be imported as tab separated lines, using the json_lines format."),
+                    ));
+                };
+
+                // Enriched events are tab separated rather than json, but still one per line
+                let format_parse = newline_delim(*skip_blanks, SnowplowEvent::from_tsv);
+                let event_transform = SnowplowEvent::parse_fn(transform_context, skip_geoip());
+                let parser = move |data| {
+                    let parsed: Parsed<Vec<SnowplowEvent>> = format_parse(data)?;
+                    let consumed = parsed.consumed;
+                    let result: Result<Vec<_>, Error> =
+                        parsed.data.into_par_iter().map(&event_transform).collect();
+
+                    Ok(Parsed {
+                        data: result?.into_iter().flatten().collect(),
+                        consumed,
+                    })
+                };
+
+                Ok(Box::new(parser))
+            }
+        }
+    }
+
+    pub fn content(&self) -> &ContentType {
+        match self {
+            Self::JsonLines { content, .. }
+            | Self::JsonArray { content }
+            | Self::Csv { content, .. }
+            | Self::Parquet { content, .. } => content,
+        }
+    }
+
+    // Whether the first row of each part names its columns. Every chunk of the part needs that
+    // row to be parsed, so the job prepends it to chunks after the first.
+    pub fn has_header(&self) -> bool {
+        matches!(
+            self,
+            Self::Csv {
+                column_names: None,
+                ..
+            }
+        )
+    }
+
+    // Parquet files can only be read from their footer, not a chunk at a time, so sources are
+    // wrapped to convert each part to json lines before it's read
+    pub fn wrap_source(&self, source: Box<dyn DataSource>) -> Box<dyn DataSource> {
+        match self {
+            Self::Parquet { .. } => Box::new(ParquetSource::new(source)),
+            _ => source,
         }
     }
+
+    // Splits data read from a part (after any header row) into its raw records, each including
+    // its trailing newline, so they can be parsed one at a time. Unless the data runs to the end
+    // of the part, a trailing partial record is left out. Returns None for formats whose records
+    // can't be found without parsing them.
+    pub fn split_records<'a>(&self, data: &'a [u8], is_part_end: bool) -> Option<Vec<&'a [u8]>> {
+        let (ends, ends_in_quotes) = match self {
+            Self::JsonArray { .. } => return None,
+            Self::Csv { .. } => csv_record_ends(data, self.csv_delimiter().ok()?),
+            Self::JsonLines { .. } | Self::Parquet { .. } => {
+                let ends = data
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, byte)| **byte == NEWLINE_DELIM)
+                    .map(|(idx, _)| idx + 1)
+                    .collect();
+                (ends, false)
+            }
+        };
+
+        let mut records = Vec::with_capacity(ends.len() + 1);
+        let mut start = 0;
+        for end in ends {
+            records.push(&data[start..end]);
+            start = end;
+        }
+        if is_part_end && !ends_in_quotes && start < data.len() {
+            records.push(&data[start..]);
+        }
+        Some(records)
+    }
+
+    // Splits a chunk into the records a content type parses events from
+    fn records<T>(&self, chunk_size: usize) -> Result<RecordsFn<T>, Error>
+    where
+        T: DeserializeOwned + Send + UserFacingParseError + 'static,
+    {
+        match self {
+            Self::JsonLines { skip_blanks, .. } => Ok(Box::new(json_nd(*skip_blanks))),
+            Self::JsonArray { .. } => Ok(Box::new(json_array())),
+            Self::Csv {
+                column_names,
+                columns,
+                ..
+            } => {
+                let delimiter = self.csv_delimiter()?;
+                Ok(Box::new(csv_rows(
+                    delimiter,
+                    column_names.clone(),
+                    columns.clone(),
+                    chunk_size,
+                )))
+            }
+            Self::Parquet { columns, .. } => {
+                // The source has already converted each row to a json object
+                let columns = columns.clone();
+                Ok(Box::new(newline_delim(true, move |line| {
+                    let row: Map<String, Value> = serde_json::from_str(line)
+                        .context("Failed to read converted parquet row")?;
+                    columns.record(
+                        row.iter()
+                            .map(|(column, value)| (column.as_str(), value.clone())),
+                    )
+                })))
+            }
+        }
+    }
+
+    // The delimiter separating CSV fields, as the single byte the CSV reader expects
+    pub fn csv_delimiter(&self) -> Result<u8, Error> {
+        let Self::Csv { delimiter, .. } = self else {
+            return Err(Error::msg("Only CSV data has a delimiter"));
+        };
+        u8::try_from(*delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or_else(|| {
+                Error::msg(format!("Invalid CSV delimiter {delimiter:?}")).context(UserError::new(
+                    "The CSV delimiter must be a single ASCII character.",
+                ))
+            })
+    }
+
+    fn default_csv_delimiter() -> char {
+        ','
+    }
 }
 
 const NEWLINE_DELIM: u8 = b'\n';
@@ -158,7 +345,7 @@ pub fn newline_delim<T: Send>(
                 let line = std::str::from_utf8(&data[last_consumed_byte..cursor])
                     .context("Failed to parse line as utf8")?;
                 if !skip_blank_lines || !line.trim().is_empty() {
-                    lines.push((cursor, line.trim()));
+                    lines.push((cursor, trim_line(line)));
                 }
                 last_consumed_byte = cursor;
             }
@@ -213,6 +400,12 @@ pub fn newline_delim<T: Send>(
     }
 }
 
+/// Trim whitespace around a line, except tabs, which are column separators in tab
+/// separated lines and can't be dropped even when the first or last column is empty
+fn trim_line(line: &str) -> &str {
+    line.trim_matches(|c: char| c.is_whitespace() && c != '\t')
+}
+
 /// Trait for types that can provide user-facing JSON parse error messages.
 /// Each event type (RawEvent, MixpanelEvent, AmplitudeEvent) implements this
 /// to provide format-specific error messages that help users fix their data.
@@ -341,6 +534,246 @@ where
     })
 }
 
+pub fn json_array<T>() -> impl Fn(Vec<u8>) -> Result<Parsed<Vec<T>>, Error>
+where
+    T: DeserializeOwned + Send + UserFacingParseError,
+{
+    move |data: Vec<u8>| {
+        let mut objects = Vec::new();
+        let mut consumed = 0;
+
+        // Rather than parsing the whole array, which might not fit in a chunk, we find where each of its
+        // objects starts and ends, tracking strings so braces inside them aren't counted. The chunk can start
+        // and end partway through the array, so anything between objects (the array's brackets, commas and
+        // whitespace) is skipped, and an object cut off at the end of the chunk is left for the next one.
+        let mut depth = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut object_start = 0;
+        for (idx, &byte) in data.iter().enumerate() {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if byte == b'\\' {
+                    escaped = true;
+                } else if byte == b'"' {
+                    in_string = false;
+                }
+                continue;
+            }
+
+            if depth > 0 {
+                match byte {
+                    b'"' => in_string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            objects.push((object_start, idx + 1));
+                            consumed = idx + 1;
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match byte {
+                b'{' => {
+                    depth = 1;
+                    object_start = idx;
+                }
+                b'[' | b']' | b',' => consumed = idx + 1,
+                byte if byte.is_ascii_whitespace() => consumed = idx + 1,
+                byte => {
+                    return Err(Error::msg(format!(
+                        "Unexpected byte {byte:#x} between array elements, at byte {idx} of current chunk"
+                    ))
+                    .context(UserError::new(
+                        "The file must be a JSON array of objects, like [{\"event\": ...}, {\"event\": ...}].",
+                    )));
+                }
+            }
+        }
+
+        let parsed: Result<Vec<T>, Error> = objects
+            .into_par_iter()
+            .map(|(start, end)| {
+                serde_json::from_slice(&data[start..end])
+                    .map_err(|e| {
+                        let user_msg = T::user_facing_parse_error(&e);
+                        anyhow::Error::from(e).context(UserError::new(user_msg))
+                    })
+                    .context(format!(
+                        "Failed to json parse array element starting at byte {start} of current chunk"
+                    ))
+            })
+            .collect();
+
+        Ok(Parsed {
+            data: parsed?,
+            consumed,
+        })
+    }
+}
+
+// Byte offsets just past the end of each complete CSV record in `data`, and whether `data`
+// ends partway through a quoted field. Quoted fields can contain newlines, so records can't
+// just be split by line. As in the CSV reader, a quote only opens a quoted field at the start
+// of the field, and is an ordinary character anywhere else.
+fn csv_record_ends(data: &[u8], delimiter: u8) -> (Vec<usize>, bool) {
+    #[derive(PartialEq)]
+    enum State {
+        FieldStart,
+        InField,
+        InQuotes,
+        // Just after a quote inside a quoted field, which either closes it or, if followed by
+        // another quote, is escaped
+        QuoteInQuotes,
+    }
+
+    let mut ends = Vec::new();
+    let mut state = State::FieldStart;
+    for (idx, &byte) in data.iter().enumerate() {
+        state = match state {
+            State::InQuotes if byte == b'"' => State::QuoteInQuotes,
+            State::InQuotes => State::InQuotes,
+            State::QuoteInQuotes if byte == b'"' => State::InQuotes,
+            State::FieldStart if byte == b'"' => State::InQuotes,
+            _ if byte == delimiter => State::FieldStart,
+            _ if byte == NEWLINE_DELIM => {
+                ends.push(idx + 1);
+                State::FieldStart
+            }
+            _ => State::InField,
+        };
+    }
+    (ends, state == State::InQuotes)
+}
+
+// The length of the header row at the start of a CSV part, if it's complete
+pub fn csv_header_len(part_start: &[u8], delimiter: u8) -> Option<usize> {
+    csv_record_ends(part_start, delimiter).0.first().copied()
+}
+
+// Parses chunks of CSV. Unless `column_names` is given, each chunk must start with the header row,
+// followed by at most `chunk_size` bytes of the part, as a chunk shorter than that is the end of the
+// part, and its last row is complete even without a trailing newline.
+pub fn csv_rows<T>(
+    delimiter: u8,
+    column_names: Option<Vec<String>>,
+    columns: ColumnMapping,
+    chunk_size: usize,
+) -> impl Fn(Vec<u8>) -> Result<Parsed<Vec<T>>, Error>
+where
+    T: DeserializeOwned + Send + UserFacingParseError,
+{
+    move |data: Vec<u8>| {
+        let (ends, ends_in_quotes) = csv_record_ends(&data, delimiter);
+        let reader = |bytes| {
+            csv::ReaderBuilder::new()
+                .has_headers(false)
+                .flexible(true)
+                .delimiter(delimiter)
+                .from_reader(bytes)
+        };
+
+        let (header, header_len) = match &column_names {
+            Some(names) => (names.clone(), 0),
+            None => {
+                let Some(&header_len) = ends.first() else {
+                    // With no newline at all, this is either a header with no rows after it, or
+                    // the start of a header too long for the chunk
+                    let consumed = if data.len() < chunk_size {
+                        data.len()
+                    } else {
+                        0
+                    };
+                    return Ok(Parsed {
+                        data: vec![],
+                        consumed,
+                    });
+                };
+                let header = reader(&data[..header_len])
+                    .records()
+                    .next()
+                    .transpose()
+                    .user_error("The header row of the CSV file couldn't be read.")?
+                    .map(|record| {
+                        record
+                            .iter()
+                            // Spreadsheet tools often start files with a byte order mark
+                            .map(|name| name.trim_start_matches('\u{feff}').trim().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (header, header_len)
+            }
+        };
+
+        let mut complete = ends.last().copied().unwrap_or(0).max(header_len);
+        let is_part_end = data.len() - header_len < chunk_size;
+        if is_part_end && ends_in_quotes {
+            return Err(Error::msg(format!(
+                "Quoted field opened after byte {complete} of current chunk is never closed"
+            ))
+            .context(UserError::new(
+                "A quoted value in the CSV file is never closed. Check that quotes inside values are doubled.",
+            )));
+        }
+        if is_part_end {
+            complete = data.len();
+        } else if complete == header_len {
+            // A full chunk without the end of a single row would leave the job stuck on it
+            return Err(Error::msg(format!(
+                "No complete row in {chunk_size} bytes after byte {header_len} of current chunk"
+            ))
+            .context(UserError::new(format!(
+                "A row of the CSV file is longer than {chunk_size} bytes. Check that quotes inside values are doubled."
+            ))));
+        }
+
+        let mut rows = Vec::new();
+        for record in reader(&data[header_len..complete]).records() {
+            let record = record.user_error(
+                "A row of the CSV file couldn't be read. Check that it's valid UTF-8.",
+            )?;
+            let start = header_len + record.position().map_or(0, |p| p.byte() as usize);
+            if record.len() != header.len() {
+                return Err(Error::msg(format!(
+                    "Row starting at byte {start} of current chunk has {} columns, expected {}",
+                    record.len(),
+                    header.len()
+                ))
+                .context(UserError::new(format!(
+                    "A row of the CSV file has {} columns, but the file has {}. Check that values containing the delimiter are quoted.",
+                    record.len(),
+                    header.len()
+                ))));
+            }
+            rows.push((start, record));
+        }
+
+        let parsed: Result<Vec<T>, Error> = rows
+            .into_par_iter()
+            .map(|(start, record)| {
+                let row = header
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(column, value)| (column.as_str(), Value::String(value.to_string())));
+                columns.record(row).context(format!(
+                    "Failed to parse row starting at byte {start} of current chunk"
+                ))
+            })
+            .collect();
+
+        Ok(Parsed {
+            data: parsed?,
+            consumed: complete,
+        })
+    }
+}
+
 pub fn skip_geoip() -> impl Fn(RawEvent) -> Result<Option<RawEvent>, Error> {
     move |mut event| {
         event
@@ -509,4 +942,262 @@ mod tests {
             "Missing comma should be mentioned: {msg}"
         );
     }
+
+    #[test]
+    fn test_json_array_parsing() {
+        let data = br#"[
+  {"id": 1, "name": "test1"},
+  {"id": 2, "name": "te}st2"},
+  {"id": 3, "name": "test\"3"}
+]
+"#
+        .to_vec();
+        let data_len = data.len();
+
+        let parsed = json_array::<TestData>()(data).unwrap();
+
+        assert_eq!(parsed.data.len(), 3);
+        assert_eq!(parsed.data[1].name, "te}st2");
+        assert_eq!(parsed.data[2].name, "test\"3");
+        assert_eq!(parsed.consumed, data_len);
+    }
+
+    #[test]
+    fn test_json_array_split_across_chunks() {
+        let data = br#"[{"id": 1, "name": "test1"}, {"id": 2, "name": "test2"}]"#;
+        // Cut partway through the second object
+        let first_chunk = data[..40].to_vec();
+
+        let first = json_array::<TestData>()(first_chunk).unwrap();
+
+        assert_eq!(first.data.len(), 1);
+        // The first object, and the comma and space after it
+        assert_eq!(first.consumed, 29);
+
+        let second = json_array::<TestData>()(data[first.consumed..].to_vec()).unwrap();
+
+        assert_eq!(second.data.len(), 1);
+        assert_eq!(second.data[0].id, 2);
+        assert_eq!(first.consumed + second.consumed, data.len());
+    }
+
+    #[test]
+    fn test_json_array_of_non_objects_has_user_facing_error() {
+        use crate::error::get_user_message;
+
+        let err = json_array::<TestData>()(b"[1, 2, 3]".to_vec()).unwrap_err();
+
+        let msg = get_user_message(&err);
+        assert!(
+            msg.contains("JSON array of objects"),
+            "Expected guidance on the expected shape, got: {msg}"
+        );
+    }
+
+    fn csv_columns() -> ColumnMapping {
+        ColumnMapping {
+            json_columns: ["id".to_string()].into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_csv_parsing() {
+        let data = b"id,name\r\n1,test1\r\n\r\n2,\"te,\"\"st\"\"\n2\"\r\n".to_vec();
+        let data_len = data.len();
+
+        let parsed = csv_rows::<TestData>(b',', None, csv_columns(), 100)(data).unwrap();
+
+        assert_eq!(
+            parsed.data,
+            vec![
+                TestData {
+                    id: 1,
+                    name: "test1".to_string()
+                },
+                TestData {
+                    id: 2,
+                    name: "te,\"st\"\n2".to_string()
+                },
+            ]
+        );
+        assert_eq!(parsed.consumed, data_len);
+    }
+
+    #[test]
+    fn test_csv_row_cut_off_at_chunk_end_is_left_for_next_chunk() {
+        // A full chunk might end partway through a row, even outside a quoted field
+        let parsed =
+            csv_rows::<TestData>(b',', None, csv_columns(), 12)(b"id,name\n1,test1\n2,te".to_vec())
+                .unwrap();
+        assert_eq!(parsed.data.len(), 1);
+        assert_eq!(parsed.consumed, 16);
+
+        // Even at the end of the part, a row ending in an open quoted field isn't complete
+        let parsed = csv_rows::<TestData>(b',', None, csv_columns(), 100)(
+            b"id,name\n1,test1\n2,\"test\n2".to_vec(),
+        )
+        .unwrap();
+        assert_eq!(parsed.data.len(), 1);
+        assert_eq!(parsed.consumed, 16);
+    }
+
+    #[test]
+    fn test_csv_last_row_without_newline_is_parsed_at_end_of_part() {
+        let data = b"id,name\n1,test1\n2,test2".to_vec();
+        let data_len = data.len();
+
+        let parsed = csv_rows::<TestData>(b',', None, csv_columns(), 100)(data).unwrap();
+
+        assert_eq!(parsed.data.len(), 2);
+        assert_eq!(parsed.data[1].name, "test2");
+        assert_eq!(parsed.consumed, data_len);
+    }
+
+    #[test]
+    fn test_csv_chunks_with_prepended_header() {
+        let part = b"id,name\n1,test1\n2,test2\n3,test3\n";
+        let chunk_size = 12;
+        let header_len = csv_header_len(part, b',').unwrap();
+        let parse = csv_rows::<TestData>(b',', None, csv_columns(), chunk_size);
+
+        // Mirrors how the job fetches chunks, starting after the header and prepending it to each
+        let mut offset = header_len;
+        let mut rows = Vec::new();
+        while offset < part.len() {
+            let end = (offset + chunk_size).min(part.len());
+            let mut chunk = part[..header_len].to_vec();
+            chunk.extend_from_slice(&part[offset..end]);
+
+            let parsed = parse(chunk).unwrap();
+            assert!(parsed.consumed > header_len);
+            offset += parsed.consumed - header_len;
+            rows.extend(parsed.data);
+        }
+
+        assert_eq!(
+            rows.iter().map(|row| row.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_csv_with_configured_column_names() {
+        let data = b"1;test1\n2;test2\n".to_vec();
+
+        let parsed = csv_rows::<TestData>(
+            b';',
+            Some(vec!["id".to_string(), "name".to_string()]),
+            csv_columns(),
+            100,
+        )(data)
+        .unwrap();
+
+        assert_eq!(parsed.data.len(), 2);
+        assert_eq!(parsed.data[0].name, "test1");
+    }
+
+    #[test]
+    fn test_csv_header_only_part() {
+        let parsed =
+            csv_rows::<TestData>(b',', None, csv_columns(), 100)(b"id,name".to_vec()).unwrap();
+
+        assert!(parsed.data.is_empty());
+        assert_eq!(parsed.consumed, 7);
+    }
+
+    #[test]
+    fn test_csv_row_with_wrong_column_count_has_user_facing_error() {
+        use crate::error::get_user_message;
+
+        let data = b"id,name\n1,test,1\n".to_vec();
+
+        let err = csv_rows::<TestData>(b',', None, csv_columns(), 100)(data).unwrap_err();
+
+        let msg = get_user_message(&err);
+        assert!(
+            msg.contains("has 3 columns, but the file has 2"),
+            "Expected the column counts in the error, got: {msg}"
+        );
+    }
+
+    #[test]
+    fn test_csv_quotes_only_open_fields_at_their_start() {
+        // The quote in 5" is part of an unquoted value, so doesn't swallow the next row
+        let data = b"1,5\" screen\n2,\"quoted, \"\"value\"\"\"\n3,a;b\n";
+        assert_eq!(csv_record_ends(data, b','), (vec![12, 34, 40], false));
+
+        // With a different delimiter, a quote after a comma is mid-field
+        assert_eq!(csv_record_ends(b"1,\"a\n2\n", b';'), (vec![5, 7], false));
+        assert_eq!(csv_record_ends(b"1;\"a\n2\n", b';'), (vec![], true));
+    }
+
+    #[test]
+    fn test_csv_rows_that_never_end_have_user_facing_errors() {
+        use crate::error::get_user_message;
+
+        let parse = csv_rows::<TestData>(b',', None, csv_columns(), 12);
+
+        let err = parse(b"id,name\n1,\"unclosed\n2,test\n".to_vec()).unwrap_err();
+        let msg = get_user_message(&err);
+        assert!(msg.contains("is longer than 12 bytes"), "Got: {msg}");
+
+        let err = parse(b"id,name\n1,\"test\n".to_vec()).unwrap_err();
+        let msg = get_user_message(&err);
+        assert!(msg.contains("is never closed"), "Got: {msg}");
+    }
+
+    #[test]
+    fn test_split_records() {
+        let json_lines = FormatConfig::JsonLines {
+            skip_blanks: true,
+            content: ContentType::Captured,
+        };
+        let csv = FormatConfig::Csv {
+            delimiter: ',',
+            column_names: None,
+            columns: csv_columns(),
+            content: ContentType::Captured,
+        };
+        let json_array = FormatConfig::JsonArray {
+            content: ContentType::Captured,
+        };
+
+        let lines = b"{\"a\": 1}\n{\"a\": 2}\n{\"a\"";
+        assert_eq!(
+            json_lines.split_records(lines, false).unwrap(),
+            vec![&b"{\"a\": 1}\n"[..], &b"{\"a\": 2}\n"[..]]
+        );
+        assert_eq!(json_lines.split_records(lines, true).unwrap().len(), 3);
+
+        // Quoted newlines don't end a record
+        let rows = b"1,\"multi\nline\"\n2,test";
+        assert_eq!(
+            csv.split_records(rows, true).unwrap(),
+            vec![&b"1,\"multi\nline\"\n"[..], &b"2,test"[..]]
+        );
+
+        assert!(json_array.split_records(b"[{}]", true).is_none());
+    }
+
+    #[test]
+    fn test_csv_format_config_defaults() {
+        let config: FormatConfig = serde_json::from_value(serde_json::json!({
+            "type": "csv",
+            "content": {"type": "captured"}
+        }))
+        .unwrap();
+
+        let FormatConfig::Csv {
+            delimiter,
+            column_names,
+            ..
+        } = &config
+        else {
+            panic!("Expected csv format");
+        };
+        assert_eq!(*delimiter, ',');
+        assert!(column_names.is_none());
+        assert!(config.has_header());
+    }
 }
@@ -1,3 +1,4 @@
+pub mod columns;
 pub mod content;
 pub mod format;
 pub mod serialization;
@@ -0,0 +1,585 @@
+use std::time::SystemTime;
+
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, AUTHORIZATION},
+    Client, Method, Response, StatusCode, Url,
+};
+use sha2::Sha256;
+use tracing::debug;
+use xmlparser::{ElementEnd, Token, Tokenizer};
+
+use crate::error::{ToUserError, UserError};
+
+use super::DataSource;
+
+const API_VERSION: &str = "2021-08-06";
+
+// The headers, in order, that make up the start of a Shared Key string-to-sign
+const SIGNED_HEADERS: [&str; 11] = [
+    "content-encoding",
+    "content-language",
+    "content-length",
+    "content-md5",
+    "content-type",
+    "date",
+    "if-modified-since",
+    "if-match",
+    "if-none-match",
+    "if-unmodified-since",
+    "range",
+];
+
+pub enum AzureBlobAuth {
+    // The decoded storage account key
+    SharedKey(Vec<u8>),
+    // A SAS token, as the query string it's handed out as
+    Sas(String),
+    Anonymous,
+}
+
+// Reads blobs from an Azure Blob Storage container through its REST API. The endpoint is the account's
+// blob endpoint, e.g. https://account.blob.core.windows.net, or http://127.0.0.1:10000/devstoreaccount1
+// for Azurite.
+pub struct AzureBlobSource {
+    client: Client,
+    container_url: Url,
+    account_name: String,
+    container: String,
+    prefix: String,
+    auth: AzureBlobAuth,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct ListBlobsPage {
+    names: Vec<String>,
+    next_marker: Option<String>,
+}
+
+impl AzureBlobSource {
+    pub fn new(
+        client: Client,
+        endpoint: Url,
+        account_name: String,
+        container: String,
+        prefix: String,
+        auth: AzureBlobAuth,
+    ) -> Result<Self, Error> {
+        let mut container_url = endpoint;
+        container_url
+            .path_segments_mut()
+            .map_err(|_| Error::msg("Azure endpoint URL cannot be a base"))?
+            .pop_if_empty()
+            .push(&container);
+
+        Ok(Self {
+            client,
+            container_url,
+            account_name,
+            container,
+            prefix,
+            auth,
+        })
+    }
+
+    fn blob_url(&self, key: &str) -> Result<Url, Error> {
+        let mut url = self.container_url.clone();
+        url.path_segments_mut()
+            .map_err(|_| Error::msg("Azure container URL cannot be a base"))?
+            .extend(key.split('/'));
+        Ok(url)
+    }
+
+    async fn execute(
+        &self,
+        method: Method,
+        mut url: Url,
+        headers: HeaderMap,
+        operation: &str,
+    ) -> Result<Response, Error> {
+        if let AzureBlobAuth::Sas(token) = &self.auth {
+            let sas = Url::parse(&format!("http://sas/?{}", token.trim_start_matches('?')))
+                .user_error("The Azure SAS token isn't a valid query string")?;
+            url.query_pairs_mut().extend_pairs(sas.query_pairs());
+        }
+
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("x-ms-version", API_VERSION)
+            .header("x-ms-date", httpdate::fmt_http_date(SystemTime::now()))
+            .headers(headers)
+            .build()
+            .with_context(|| format!("Failed to build Azure {operation} request"))?;
+
+        if let AzureBlobAuth::SharedKey(key) = &self.auth {
+            let to_sign = string_to_sign(
+                &self.account_name,
+                request.method(),
+                request.url(),
+                request.headers(),
+            );
+            let mut mac = Hmac::<Sha256>::new_from_slice(key)
+                .context("Failed to create Azure request signer")?;
+            mac.update(to_sign.as_bytes());
+            let signature = BASE64_STANDARD.encode(mac.finalize().into_bytes());
+            let authorization = format!("SharedKey {}:{signature}", self.account_name);
+            request
+                .headers_mut()
+                .insert(AUTHORIZATION, HeaderValue::from_str(&authorization)?);
+        }
+
+        self.client.execute(request).await.user_error(format!(
+            "Azure {operation} failed - check your network connection and endpoint"
+        ))
+    }
+
+    async fn error_for_status(
+        &self,
+        response: Response,
+        operation: &str,
+    ) -> Result<Response, Error> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let error = Error::msg(format!("Azure {operation} failed with {status}: {body}"));
+        Err(error.context(UserError::new(user_friendly_error(
+            status,
+            &self.container,
+            operation,
+        ))))
+    }
+}
+
+pub(crate) fn user_friendly_error(status: StatusCode, container: &str, operation: &str) -> String {
+    match status {
+        StatusCode::FORBIDDEN => format!(
+            "Access denied to Azure container '{container}' - check your account key or SAS token and its permissions"
+        ),
+        StatusCode::NOT_FOUND => {
+            format!("Azure container '{container}' or the blob being read does not exist")
+        }
+        StatusCode::SERVICE_UNAVAILABLE | StatusCode::TOO_MANY_REQUESTS => {
+            format!("Azure {operation} was throttled")
+        }
+        _ => format!(
+            "Azure {operation} failed - check your credentials, container name, and permissions"
+        ),
+    }
+}
+
+// Builds the string signed for Shared Key authorization, as described in
+// https://learn.microsoft.com/en-us/rest/api/storageservices/authorize-with-shared-key
+fn string_to_sign(account_name: &str, method: &Method, url: &Url, headers: &HeaderMap) -> String {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+    };
+
+    let mut lines = vec![method.as_str().to_string()];
+    for name in SIGNED_HEADERS {
+        let value = header(name);
+        // A zero content length is signed as an empty string
+        if name == "content-length" && value == "0" {
+            lines.push(String::new());
+        } else {
+            lines.push(value.to_string());
+        }
+    }
+
+    let mut ms_headers: Vec<_> = headers
+        .keys()
+        .map(|name| name.as_str())
+        .filter(|name| name.starts_with("x-ms-"))
+        .collect();
+    ms_headers.sort_unstable();
+    ms_headers.dedup();
+    for name in ms_headers {
+        lines.push(format!("{name}:{}", header(name).trim()));
+    }
+
+    let mut resource = format!("/{account_name}{}", url.path());
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(k, v)| (k.to_lowercase(), v.into_owned()))
+        .collect();
+    params.sort();
+    let mut names: Vec<&str> = params.iter().map(|(k, _)| k.as_str()).collect();
+    names.dedup();
+    for name in names {
+        let values: Vec<&str> = params
+            .iter()
+            .filter(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+            .collect();
+        resource.push_str(&format!("\n{name}:{}", values.join(",")));
+    }
+    lines.push(resource);
+
+    lines.join("\n")
+}
+
+// Pulls the blob names and continuation marker out of a List Blobs response
+fn parse_list_blobs(xml: &str) -> Result<ListBlobsPage, Error> {
+    let mut page = ListBlobsPage::default();
+    let mut path: Vec<&str> = Vec::new();
+
+    for token in Tokenizer::from(xml) {
+        match token.context("Failed to parse Azure list blobs response")? {
+            Token::ElementStart { local, .. } => path.push(local.as_str()),
+            Token::ElementEnd { end, .. } => match end {
+                ElementEnd::Open => {}
+                ElementEnd::Close(..) | ElementEnd::Empty => {
+                    path.pop();
+                }
+            },
+            Token::Text { text } => {
+                let text = unescape(text.as_str());
+                match path.as_slice() {
+                    ["EnumerationResults", "Blobs", "Blob", "Name"] => page.names.push(text),
+                    ["EnumerationResults", "NextMarker"] if !text.is_empty() => {
+                        page.next_marker = Some(text)
+                    }
+                    _ => {}
+                }
+            }
+            Token::Cdata { text, .. }
+                if path.as_slice() == ["EnumerationResults", "Blobs", "Blob", "Name"] =>
+            {
+                page.names.push(text.as_str().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    Ok(page)
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let decoded = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[async_trait]
+impl DataSource for AzureBlobSource {
+    async fn keys(&self) -> Result<Vec<String>, Error> {
+        debug!(
+            "Listing blobs in container {} with prefix {}",
+            self.container, self.prefix
+        );
+
+        let mut keys = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let mut url = self.container_url.clone();
+            {
+                let mut query = url.query_pairs_mut();
+                query
+                    .append_pair("restype", "container")
+                    .append_pair("comp", "list")
+                    .append_pair("prefix", &self.prefix);
+                if let Some(marker) = &marker {
+                    query.append_pair("marker", marker);
+                }
+            }
+
+            let response = self
+                .execute(Method::GET, url, HeaderMap::new(), "list blobs")
+                .await?;
+            let body = self
+                .error_for_status(response, "list blobs")
+                .await?
+                .text()
+                .await
+                .context("Failed to read Azure list blobs response")?;
+            let page = parse_list_blobs(&body)?;
+
+            // Accounts with a hierarchical namespace list directories as blobs ending in /
+            keys.extend(page.names.into_iter().filter(|name| !name.ends_with('/')));
+            match page.next_marker {
+                Some(next) => marker = Some(next),
+                None => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, Error> {
+        let response = self
+            .execute(
+                Method::HEAD,
+                self.blob_url(key)?,
+                HeaderMap::new(),
+                "get blob properties",
+            )
+            .await?;
+        let response = self
+            .error_for_status(response, "get blob properties")
+            .await?;
+
+        // The body of a HEAD response is always empty, so the length has to come from the header
+        let size = response
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .ok_or_else(|| Error::msg(format!("Azure blob {key} has no content length")))?
+            .to_str()?
+            .parse()
+            .with_context(|| format!("Invalid content length for Azure blob {key}"))?;
+        Ok(Some(size))
+    }
+
+    async fn get_chunk(&self, key: &str, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+        // Ranges are inclusive, so an empty one can't be requested
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ms-range",
+            HeaderValue::from_str(&format!("bytes={offset}-{}", offset + size - 1))?,
+        );
+
+        let response = self
+            .execute(Method::GET, self.blob_url(key)?, headers, "get blob chunk")
+            .await?;
+
+        // Reading from the end of a blob is out of range, but there's just nothing left to read
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(Vec::new());
+        }
+        let response = self.error_for_status(response, "get blob chunk").await?;
+
+        let data = response
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to read body data from Azure blob {key}"))?;
+        Ok(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::get_user_message;
+    use httpmock::{
+        Method::{GET, HEAD},
+        MockServer,
+    };
+
+    const ACCOUNT: &str = "devstoreaccount1";
+
+    fn source(server: &MockServer, auth: AzureBlobAuth) -> AzureBlobSource {
+        let endpoint = Url::parse(&server.url(format!("/{ACCOUNT}"))).unwrap();
+        AzureBlobSource::new(
+            Client::new(),
+            endpoint,
+            ACCOUNT.to_string(),
+            "imports".to_string(),
+            "2024/".to_string(),
+            auth,
+        )
+        .unwrap()
+    }
+
+    fn list_response(names: &[&str], next_marker: &str) -> String {
+        let blobs: String = names
+            .iter()
+            .map(|name| {
+                format!("<Blob><Name>{name}</Name><Properties><Content-Length>10</Content-Length></Properties></Blob>")
+            })
+            .collect();
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?><EnumerationResults ContainerName=\"imports\"><Prefix>2024/</Prefix><Blobs>{blobs}</Blobs><NextMarker>{next_marker}</NextMarker></EnumerationResults>"
+        )
+    }
+
+    #[test]
+    fn test_string_to_sign_is_canonical() {
+        let url = Url::parse(
+            "http://127.0.0.1:10000/devstoreaccount1/imports?restype=container&comp=list&prefix=2024%2F",
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ms-version", HeaderValue::from_static(API_VERSION));
+        headers.insert(
+            "x-ms-date",
+            HeaderValue::from_static("Sun, 18 Oct 2026 00:00:00 GMT"),
+        );
+        headers.insert("content-length", HeaderValue::from_static("0"));
+
+        let to_sign = string_to_sign(ACCOUNT, &Method::GET, &url, &headers);
+
+        assert_eq!(
+            to_sign,
+            [
+                "GET",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "",
+                "x-ms-date:Sun, 18 Oct 2026 00:00:00 GMT",
+                "x-ms-version:2021-08-06",
+                "/devstoreaccount1/devstoreaccount1/imports\ncomp:list\nprefix:2024/\nrestype:container",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_list_blobs() {
+        let xml = list_response(&["2024/a&amp;b.jsonl", "2024/dir/"], "");
+
+        let page = parse_list_blobs(&xml).unwrap();
+
+        assert_eq!(
+            page,
+            ListBlobsPage {
+                names: vec!["2024/a&b.jsonl".to_string(), "2024/dir/".to_string()],
+                next_marker: None,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_listed_across_pages_with_shared_key() {
+        let server = MockServer::start();
+        let first_page = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/{ACCOUNT}/imports"))
+                .query_param("comp", "list")
+                .query_param("prefix", "2024/")
+                .matches(|req| {
+                    req.query_params
+                        .as_ref()
+                        .is_none_or(|params| params.iter().all(|(k, _)| k != "marker"))
+                })
+                .header_exists("x-ms-date")
+                .matches(|req| {
+                    req.headers.as_ref().is_some_and(|headers| {
+                        headers.iter().any(|(k, v)| {
+                            k.eq_ignore_ascii_case("authorization")
+                                && v.starts_with("SharedKey devstoreaccount1:")
+                        })
+                    })
+                });
+            then.status(200)
+                .body(list_response(&["2024/a.jsonl", "2024/dir/"], "marker-2"));
+        });
+        let second_page = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/{ACCOUNT}/imports"))
+                .query_param("marker", "marker-2");
+            then.status(200)
+                .body(list_response(&["2024/b.jsonl.zst"], ""));
+        });
+
+        let auth = AzureBlobAuth::SharedKey(b"not-a-real-key".to_vec());
+        let keys = source(&server, auth).keys().await.unwrap();
+
+        first_page.assert();
+        second_page.assert();
+        assert_eq!(keys, vec!["2024/a.jsonl", "2024/b.jsonl.zst"]);
+    }
+
+    #[tokio::test]
+    async fn test_size_and_ranged_reads_with_sas() {
+        let server = MockServer::start();
+        let properties = server.mock(|when, then| {
+            when.method(HEAD)
+                .path(format!("/{ACCOUNT}/imports/2024/a.jsonl"))
+                .query_param("sig", "abc/=");
+            then.status(200).header("Content-Length", "1234");
+        });
+        let chunk = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("/{ACCOUNT}/imports/2024/a.jsonl"))
+                .query_param("sv", "2021-08-06")
+                .header("x-ms-range", "bytes=10-19");
+            then.status(206).body("0123456789");
+        });
+        let source = source(
+            &server,
+            AzureBlobAuth::Sas("?sv=2021-08-06&sig=abc%2F%3D".to_string()),
+        );
+
+        let size = source.size("2024/a.jsonl").await;
+        let data = source.get_chunk("2024/a.jsonl", 10, 10).await.unwrap();
+
+        properties.assert();
+        chunk.assert();
+        assert_eq!(size.unwrap(), Some(1234));
+        assert_eq!(data, b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_access_denied_has_user_facing_error() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/{ACCOUNT}/imports"));
+            then.status(403)
+                .body("<Error><Code>AuthenticationFailed</Code></Error>");
+        });
+
+        let err = source(&server, AzureBlobAuth::Anonymous)
+            .keys()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            get_user_message(&err),
+            "Access denied to Azure container 'imports' - check your account key or SAS token and its permissions"
+        );
+    }
+}
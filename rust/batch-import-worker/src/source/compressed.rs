@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::extractor::{ExtractedPartData, PartExtractor, PlainGzipExtractor, ZstdExtractor};
+
+use super::{download_to_file, read_prepared_chunk, DataSource};
+
+fn sanitize_key_for_path(key: &str) -> String {
+    key.replace(['/', ':'], "_")
+}
+
+// Compressed parts are recognised by their extension, as object stores don't reliably record it
+fn extractor_for_key(key: &str) -> Option<Arc<dyn PartExtractor>> {
+    if key.ends_with(".gz") {
+        Some(Arc::new(PlainGzipExtractor))
+    } else if key.ends_with(".zst") || key.ends_with(".zstd") {
+        Some(Arc::new(ZstdExtractor))
+    } else {
+        None
+    }
+}
+
+// Wraps a source, so that gzip and zstd compressed parts are downloaded in full and decompressed when
+// they're prepared, then served a chunk at a time from the decompressed file. Other parts are read from
+// the inner source as they are.
+pub struct CompressedSource {
+    inner: Box<dyn DataSource>,
+    temp_dir: Mutex<Option<TempDir>>,
+    prepared_keys: Mutex<HashMap<String, ExtractedPartData>>,
+}
+
+impl CompressedSource {
+    pub fn new(inner: Box<dyn DataSource>) -> Self {
+        Self {
+            inner,
+            temp_dir: Mutex::new(None),
+            prepared_keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn get_temp_dir_path(&self) -> Result<PathBuf, Error> {
+        let guard = self.temp_dir.lock().await;
+        Ok(guard
+            .as_ref()
+            .ok_or_else(|| Error::msg("Temp directory not initialized"))?
+            .path()
+            .to_path_buf())
+    }
+}
+
+#[async_trait]
+impl DataSource for CompressedSource {
+    async fn keys(&self) -> Result<Vec<String>, Error> {
+        self.inner.keys().await
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, Error> {
+        if extractor_for_key(key).is_none() {
+            return self.inner.size(key).await;
+        }
+
+        // The size of a decompressed part isn't known until it's been prepared
+        let prepared_keys = self.prepared_keys.lock().await;
+        Ok(prepared_keys
+            .get(key)
+            .map(|part| part.data_file_size as u64))
+    }
+
+    async fn get_chunk(&self, key: &str, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+        if extractor_for_key(key).is_none() {
+            return self.inner.get_chunk(key, offset, size).await;
+        }
+
+        let part = {
+            let prepared_keys = self.prepared_keys.lock().await;
+            prepared_keys
+                .get(key)
+                .ok_or_else(|| Error::msg(format!("Key not prepared: {key}")))?
+                .clone()
+        };
+
+        let buffer = read_prepared_chunk(&part, key, offset, size).await?;
+
+        if offset + buffer.len() as u64 >= part.data_file_size as u64 {
+            if let Err(e) = self.cleanup_key(key).await {
+                warn!("Failed to cleanup key {key}: {e:?}");
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    async fn prepare_key(&self, key: &str) -> Result<(), Error> {
+        self.inner.prepare_key(key).await?;
+
+        let Some(extractor) = extractor_for_key(key) else {
+            return Ok(());
+        };
+
+        {
+            let prepared_keys = self.prepared_keys.lock().await;
+            if prepared_keys.contains_key(key) {
+                return Ok(());
+            }
+        }
+
+        let temp_dir = self.get_temp_dir_path().await?;
+        let safe_key = sanitize_key_for_path(key);
+        let raw_file_path = temp_dir.join(format!("{safe_key}.raw"));
+        let downloaded = download_to_file(self.inner.as_ref(), key, &raw_file_path).await?;
+        self.inner.cleanup_key(key).await?;
+
+        let part = extractor
+            .extract_compressed_to_seekable_file(&safe_key, &raw_file_path, &temp_dir)
+            .await
+            .with_context(|| format!("Failed to decompress {key}"))?;
+
+        if let Err(e) = tokio::fs::remove_file(&raw_file_path).await {
+            warn!(
+                "Failed to remove raw file {}: {e:?}",
+                raw_file_path.display()
+            );
+        }
+
+        info!(
+            "Prepared key {} ({} compressed bytes, {} decompressed)",
+            key, downloaded, part.data_file_size
+        );
+        let mut prepared_keys = self.prepared_keys.lock().await;
+        prepared_keys.insert(key.to_string(), part);
+        Ok(())
+    }
+
+    async fn cleanup_key(&self, key: &str) -> Result<(), Error> {
+        let part = {
+            let mut prepared_keys = self.prepared_keys.lock().await;
+            prepared_keys.remove(key)
+        };
+
+        match part {
+            Some(part) => tokio::fs::remove_file(&part.data_file_path)
+                .await
+                .with_context(|| format!("Failed to remove decompressed file for key: {key}")),
+            None => self.inner.cleanup_key(key).await,
+        }
+    }
+
+    async fn prepare_for_job(&self) -> Result<(), Error> {
+        self.inner.prepare_for_job().await?;
+
+        let temp_dir =
+            tempfile::tempdir().with_context(|| "Failed to create temp directory for job")?;
+        debug!("Created temp directory for job: {:?}", temp_dir.path());
+        *self.temp_dir.lock().await = Some(temp_dir);
+        Ok(())
+    }
+
+    async fn cleanup_after_job(&self) -> Result<(), Error> {
+        self.prepared_keys.lock().await.clear();
+        if let Some(temp_dir) = self.temp_dir.lock().await.take() {
+            drop(temp_dir);
+            debug!("Cleaned up temp directory");
+        }
+        self.inner.cleanup_after_job().await
+    }
+
+    fn get_date_range_for_key(&self, key: &str) -> Option<String> {
+        self.inner.get_date_range_for_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::folder::FolderSource;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    const CONTENT: &str = "{\"event\": \"signup\"}\n{\"event\": \"purchase\"}\n";
+
+    async fn setup_source() -> (TempDir, CompressedSource) {
+        let temp_dir = TempDir::new().unwrap();
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CONTENT.as_bytes()).unwrap();
+        std::fs::write(
+            temp_dir.path().join("a.jsonl.gz"),
+            encoder.finish().unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            temp_dir.path().join("b.jsonl.zst"),
+            zstd::encode_all(CONTENT.as_bytes(), 3).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("c.jsonl"), CONTENT).unwrap();
+
+        let inner = FolderSource::new(temp_dir.path().to_str().unwrap().to_string())
+            .await
+            .unwrap();
+        let source = CompressedSource::new(Box::new(inner));
+        source.prepare_for_job().await.unwrap();
+        (temp_dir, source)
+    }
+
+    #[tokio::test]
+    async fn test_compressed_parts_are_decompressed() {
+        let (_temp_dir, source) = setup_source().await;
+
+        for key in ["a.jsonl.gz", "b.jsonl.zst"] {
+            assert_eq!(source.size(key).await.unwrap(), None);
+            source.prepare_key(key).await.unwrap();
+            let size = source.size(key).await.unwrap().unwrap();
+            assert_eq!(size, CONTENT.len() as u64);
+
+            let first = source.get_chunk(key, 0, 10).await.unwrap();
+            let rest = source.get_chunk(key, 10, size).await.unwrap();
+
+            assert_eq!([first, rest].concat(), CONTENT.as_bytes());
+            // Reading the end of the part cleans it up
+            assert_eq!(source.size(key).await.unwrap(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uncompressed_parts_are_passed_through() {
+        let (_temp_dir, source) = setup_source().await;
+        let key = "c.jsonl";
+
+        source.prepare_key(key).await.unwrap();
+
+        assert_eq!(source.size(key).await.unwrap(), Some(CONTENT.len() as u64));
+        assert_eq!(
+            source.get_chunk(key, 0, 100).await.unwrap(),
+            CONTENT.as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_compressed_part_fails_to_prepare() {
+        let (temp_dir, source) = setup_source().await;
+        std::fs::write(temp_dir.path().join("d.jsonl.gz"), "not gzip").unwrap();
+
+        assert!(source.prepare_key("d.jsonl.gz").await.is_err());
+    }
+}
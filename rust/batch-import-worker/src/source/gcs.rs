@@ -0,0 +1,467 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use crate::error::{ToUserError, UserError};
+
+use super::DataSource;
+
+pub const DEFAULT_GCS_ENDPOINT: &str = "https://storage.googleapis.com";
+const READ_ONLY_SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+// Tokens are refreshed this long before they expire, so a request never goes out with a stale one
+const TOKEN_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+// The parts of a service account's JSON key we need to get access tokens
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    #[serde(default = "ServiceAccountKey::default_token_uri")]
+    pub token_uri: String,
+}
+
+impl ServiceAccountKey {
+    fn default_token_uri() -> String {
+        "https://oauth2.googleapis.com/token".to_string()
+    }
+}
+
+#[derive(Serialize)]
+struct TokenClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+struct AccessToken {
+    token: String,
+    expires_at: SystemTime,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListObjectsResponse {
+    #[serde(default)]
+    items: Vec<ObjectMetadata>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ObjectMetadata {
+    name: String,
+    // The JSON API returns sizes as strings, as they can exceed what javascript numbers can hold
+    size: String,
+}
+
+// Reads objects from a Google Cloud Storage bucket through its JSON API. Without a service account
+// key, requests are unauthenticated, which is only useful against an emulator like fake-gcs-server.
+pub struct GcsSource {
+    client: Client,
+    endpoint: String,
+    bucket: String,
+    prefix: String,
+    service_account: Option<ServiceAccountKey>,
+    token: Mutex<Option<AccessToken>>,
+}
+
+impl GcsSource {
+    pub fn new(
+        client: Client,
+        endpoint: String,
+        bucket: String,
+        prefix: String,
+        service_account: Option<ServiceAccountKey>,
+    ) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            bucket,
+            prefix,
+            service_account,
+            token: Mutex::new(None),
+        }
+    }
+
+    fn object_url(&self, key: &str) -> String {
+        format!(
+            "{}/storage/v1/b/{}/o/{}",
+            self.endpoint,
+            urlencoding::encode(&self.bucket),
+            urlencoding::encode(key)
+        )
+    }
+
+    async fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, Error> {
+        let Some(service_account) = &self.service_account else {
+            return Ok(request);
+        };
+
+        let mut token = self.token.lock().await;
+        let now = SystemTime::now();
+        let is_fresh = token
+            .as_ref()
+            .is_some_and(|t| t.expires_at > now + TOKEN_EXPIRY_MARGIN);
+        if !is_fresh {
+            *token = Some(self.fetch_token(service_account, now).await?);
+        }
+
+        let token = token.as_ref().map(|t| t.token.as_str()).unwrap_or_default();
+        Ok(request.bearer_auth(token))
+    }
+
+    // Exchanges a JWT signed with the service account's key for an access token
+    async fn fetch_token(
+        &self,
+        service_account: &ServiceAccountKey,
+        now: SystemTime,
+    ) -> Result<AccessToken, Error> {
+        let iat = now.duration_since(UNIX_EPOCH)?.as_secs();
+        let claims = TokenClaims {
+            iss: &service_account.client_email,
+            scope: READ_ONLY_SCOPE,
+            aud: &service_account.token_uri,
+            iat,
+            exp: iat + 3600,
+        };
+        let key = EncodingKey::from_rsa_pem(service_account.private_key.as_bytes())
+            .user_error("The GCS service account key's private key isn't valid")?;
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)
+            .context("Failed to sign GCS token request")?;
+
+        let body = format!(
+            "grant_type={}&assertion={}",
+            urlencoding::encode("urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            assertion
+        );
+        let response = self
+            .client
+            .post(&service_account.token_uri)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .context("Failed to request GCS access token")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(
+                Error::msg(format!("GCS token request failed with {status}: {body}")).context(
+                    UserError::new(
+                        "Failed to authenticate with Google Cloud - check the service account key",
+                    ),
+                ),
+            );
+        }
+
+        let token: TokenResponse = response
+            .json()
+            .await
+            .context("Failed to parse GCS access token response")?;
+        Ok(AccessToken {
+            token: token.access_token,
+            expires_at: now + Duration::from_secs(token.expires_in),
+        })
+    }
+
+    async fn send(&self, request: RequestBuilder, operation: &str) -> Result<Response, Error> {
+        let response = self
+            .authorize(request)
+            .await?
+            .send()
+            .await
+            .user_error(format!(
+                "GCS {operation} failed - check your network connection and endpoint"
+            ))?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let error = Error::msg(format!("GCS {operation} failed with {status}: {body}"));
+        Err(error.context(UserError::new(user_friendly_error(
+            status,
+            &self.bucket,
+            operation,
+        ))))
+    }
+}
+
+pub(crate) fn user_friendly_error(status: StatusCode, bucket: &str, operation: &str) -> String {
+    match status {
+        StatusCode::UNAUTHORIZED => {
+            "Invalid Google Cloud credentials - please check your service account key".to_string()
+        }
+        StatusCode::FORBIDDEN => {
+            format!(
+                "Access denied to GCS bucket '{bucket}' - check your service account's permissions"
+            )
+        }
+        StatusCode::NOT_FOUND => {
+            format!("GCS bucket '{bucket}' or the object being read does not exist")
+        }
+        StatusCode::TOO_MANY_REQUESTS => format!("GCS {operation} was rate limited"),
+        _ => {
+            format!("GCS {operation} failed - check your credentials, bucket name, and permissions")
+        }
+    }
+}
+
+#[async_trait]
+impl DataSource for GcsSource {
+    async fn keys(&self) -> Result<Vec<String>, Error> {
+        debug!(
+            "Listing objects in bucket {} with prefix {}",
+            self.bucket, self.prefix
+        );
+        let url = format!(
+            "{}/storage/v1/b/{}/o",
+            self.endpoint,
+            urlencoding::encode(&self.bucket)
+        );
+
+        let mut keys = Vec::new();
+        let mut page_token = None;
+        loop {
+            let mut query = vec![
+                ("prefix", self.prefix.clone()),
+                ("fields", "items(name,size),nextPageToken".to_string()),
+            ];
+            if let Some(token) = page_token {
+                query.push(("pageToken", token));
+            }
+
+            let response = self
+                .send(self.client.get(&url).query(&query), "list objects")
+                .await?;
+            let page: ListObjectsResponse = response
+                .json()
+                .await
+                .context("Failed to parse GCS list objects response")?;
+
+            // Consoles create empty objects ending in / to stand in for folders
+            keys.extend(
+                page.items
+                    .into_iter()
+                    .map(|o| o.name)
+                    .filter(|name| !name.ends_with('/')),
+            );
+            match page.next_page_token {
+                Some(token) => page_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, Error> {
+        let request = self
+            .client
+            .get(self.object_url(key))
+            .query(&[("fields", "name,size")]);
+        let metadata: ObjectMetadata = self
+            .send(request, "get object metadata")
+            .await?
+            .json()
+            .await
+            .context("Failed to parse GCS object metadata")?;
+
+        let size = metadata
+            .size
+            .parse()
+            .with_context(|| format!("Invalid size for GCS object {key}: {}", metadata.size))?;
+        Ok(Some(size))
+    }
+
+    async fn get_chunk(&self, key: &str, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+        // Ranges are inclusive, so an empty one can't be requested
+        if size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let request = self
+            .client
+            .get(self.object_url(key))
+            .query(&[("alt", "media")])
+            .header("Range", format!("bytes={offset}-{}", offset + size - 1));
+
+        let response = self.authorize(request).await?.send().await.user_error(
+            "GCS get object chunk failed - check your network connection and endpoint",
+        )?;
+
+        // Reading from the end of an object is out of range, but there's just nothing left to read
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            return Ok(Vec::new());
+        }
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(
+                Error::msg(format!("GCS get object chunk failed with {status}: {body}")).context(
+                    UserError::new(user_friendly_error(
+                        status,
+                        &self.bucket,
+                        "get object chunk",
+                    )),
+                ),
+            );
+        }
+
+        let data = response.bytes().await.with_context(|| {
+            format!(
+                "Failed to read body data from GCS object gs://{}/{key}",
+                self.bucket
+            )
+        })?;
+        Ok(data.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::get_user_message;
+    use httpmock::{Method::GET, MockServer};
+    use serde_json::json;
+
+    fn source(server: &MockServer) -> GcsSource {
+        GcsSource::new(
+            Client::new(),
+            server.base_url(),
+            "my-bucket".to_string(),
+            "exports/".to_string(),
+            None,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_listed_across_pages() {
+        let server = MockServer::start();
+        let first_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/storage/v1/b/my-bucket/o")
+                .query_param("prefix", "exports/")
+                .matches(|req| {
+                    req.query_params
+                        .as_ref()
+                        .is_none_or(|params| params.iter().all(|(k, _)| k != "pageToken"))
+                });
+            then.status(200).json_body(json!({
+                "items": [
+                    {"name": "exports/", "size": "0"},
+                    {"name": "exports/a.jsonl", "size": "10"}
+                ],
+                "nextPageToken": "page-2"
+            }));
+        });
+        let second_page = server.mock(|when, then| {
+            when.method(GET)
+                .path("/storage/v1/b/my-bucket/o")
+                .query_param("pageToken", "page-2");
+            then.status(200)
+                .json_body(json!({"items": [{"name": "exports/b.jsonl.gz", "size": "20"}]}));
+        });
+
+        let keys = source(&server).keys().await.unwrap();
+
+        first_page.assert();
+        second_page.assert();
+        assert_eq!(keys, vec!["exports/a.jsonl", "exports/b.jsonl.gz"]);
+    }
+
+    #[tokio::test]
+    async fn test_size_and_ranged_reads() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/storage/v1/b/my-bucket/o/exports%2Fa.jsonl")
+                .matches(|req| {
+                    req.query_params
+                        .as_ref()
+                        .is_none_or(|params| params.iter().all(|(k, _)| k != "alt"))
+                });
+            then.status(200)
+                .json_body(json!({"name": "exports/a.jsonl", "size": "1234"}));
+        });
+        let chunk = server.mock(|when, then| {
+            when.method(GET)
+                .path("/storage/v1/b/my-bucket/o/exports%2Fa.jsonl")
+                .query_param("alt", "media")
+                .header("Range", "bytes=10-19");
+            then.status(206).body("0123456789");
+        });
+        let source = source(&server);
+
+        assert_eq!(source.size("exports/a.jsonl").await.unwrap(), Some(1234));
+        let data = source.get_chunk("exports/a.jsonl", 10, 10).await.unwrap();
+
+        chunk.assert();
+        assert_eq!(data, b"0123456789");
+    }
+
+    #[tokio::test]
+    async fn test_read_past_end_is_empty() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET)
+                .path("/storage/v1/b/my-bucket/o/exports%2Fa.jsonl");
+            then.status(416);
+        });
+
+        let data = source(&server)
+            .get_chunk("exports/a.jsonl", 1234, 10)
+            .await
+            .unwrap();
+
+        assert!(data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_empty_read_makes_no_request() {
+        let server = MockServer::start();
+        let chunk = server.mock(|when, then| {
+            when.method(GET);
+            then.status(500);
+        });
+
+        let data = source(&server)
+            .get_chunk("exports/a.jsonl", 10, 0)
+            .await
+            .unwrap();
+
+        chunk.assert_hits(0);
+        assert!(data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_access_denied_has_user_facing_error() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(GET).path("/storage/v1/b/my-bucket/o");
+            then.status(403).body("denied");
+        });
+
+        let err = source(&server).keys().await.unwrap_err();
+
+        assert_eq!(
+            get_user_message(&err),
+            "Access denied to GCS bucket 'my-bucket' - check your service account's permissions"
+        );
+    }
+}
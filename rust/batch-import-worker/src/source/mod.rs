@@ -1,12 +1,24 @@
-use anyhow::Error;
+use std::path::Path;
+
+use anyhow::{Context, Error};
 use async_trait::async_trait;
+use tokio::{fs::File, io::AsyncReadExt, io::AsyncSeekExt, io::AsyncWriteExt};
+
+use crate::extractor::ExtractedPartData;
 
+pub mod azure_blob;
+pub mod compressed;
 pub mod date_range_export;
 pub mod folder;
+pub mod gcs;
+pub mod parquet;
 pub mod s3;
 pub mod s3_gzip;
 pub mod url_list;
 
+// How much of a part to download at a time, when downloading it in full
+const DOWNLOAD_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
 #[async_trait]
 pub trait DataSource: Sync + Send {
     async fn keys(&self) -> Result<Vec<String>, Error>;
@@ -34,3 +46,63 @@ pub trait DataSource: Sync + Send {
         None
     }
 }
+
+// Downloads the whole of a part to a file, for parts that have to be converted or decompressed
+// before they can be read a chunk at a time. Returns the number of bytes downloaded.
+pub(crate) async fn download_to_file(
+    source: &dyn DataSource,
+    key: &str,
+    path: &Path,
+) -> Result<u64, Error> {
+    let mut file = File::create(path)
+        .await
+        .with_context(|| format!("Failed to create raw file: {}", path.display()))?;
+
+    let mut offset = 0;
+    loop {
+        let chunk = source
+            .get_chunk(key, offset, DOWNLOAD_CHUNK_SIZE)
+            .await
+            .with_context(|| format!("Failed to download {key} at offset {offset}"))?;
+        file.write_all(&chunk)
+            .await
+            .with_context(|| format!("Failed to write raw file: {}", path.display()))?;
+        offset += chunk.len() as u64;
+        if (chunk.len() as u64) < DOWNLOAD_CHUNK_SIZE {
+            break;
+        }
+    }
+
+    file.sync_all()
+        .await
+        .with_context(|| format!("Failed to sync raw file: {}", path.display()))?;
+    Ok(offset)
+}
+
+// Reads a chunk of a part that's been converted or decompressed to a local file
+pub(crate) async fn read_prepared_chunk(
+    part: &ExtractedPartData,
+    key: &str,
+    offset: u64,
+    size: u64,
+) -> Result<Vec<u8>, Error> {
+    let total_size = part.data_file_size as u64;
+    if offset >= total_size {
+        return Ok(Vec::new());
+    }
+
+    let end_offset = std::cmp::min(offset + size, total_size);
+    let mut buffer = vec![0u8; (end_offset - offset) as usize];
+
+    let mut file = File::open(&part.data_file_path)
+        .await
+        .with_context(|| format!("Failed to open prepared data file for key: {key}"))?;
+    file.seek(std::io::SeekFrom::Start(offset))
+        .await
+        .with_context(|| format!("Failed to seek to offset {offset} for key: {key}"))?;
+    file.read_exact(&mut buffer)
+        .await
+        .with_context(|| format!("Failed to read prepared data file for key: {key}"))?;
+
+    Ok(buffer)
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::extractor::{ExtractedPartData, ParquetExtractor, PartExtractor};
+
+use super::{download_to_file, read_prepared_chunk, DataSource};
+
+fn sanitize_key_for_path(key: &str) -> String {
+    key.replace(['/', ':'], "_")
+}
+
+// Hadoop and Spark write marker and checksum files like `_SUCCESS` alongside the data
+fn is_data_key(key: &str) -> bool {
+    let file_name = key.rsplit('/').next().unwrap_or(key);
+    !file_name.starts_with('_') && !file_name.starts_with('.')
+}
+
+// Parquet files can't be read a chunk at a time, as their metadata is at the end of the file. This
+// wraps another source, downloading each part in full when it's prepared and converting it to json
+// lines, which are then served a chunk at a time like any other part.
+pub struct ParquetSource {
+    inner: Box<dyn DataSource>,
+    temp_dir: Mutex<Option<TempDir>>,
+    prepared_keys: Mutex<HashMap<String, ExtractedPartData>>,
+}
+
+impl ParquetSource {
+    pub fn new(inner: Box<dyn DataSource>) -> Self {
+        Self {
+            inner,
+            temp_dir: Mutex::new(None),
+            prepared_keys: Mutex::new(HashMap::new()),
+        }
+    }
+
+    async fn get_temp_dir_path(&self) -> Result<PathBuf, Error> {
+        let guard = self.temp_dir.lock().await;
+        Ok(guard
+            .as_ref()
+            .ok_or_else(|| Error::msg("Temp directory not initialized"))?
+            .path()
+            .to_path_buf())
+    }
+}
+
+#[async_trait]
+impl DataSource for ParquetSource {
+    async fn keys(&self) -> Result<Vec<String>, Error> {
+        let keys = self.inner.keys().await?;
+        Ok(keys.into_iter().filter(|k| is_data_key(k)).collect())
+    }
+
+    async fn size(&self, key: &str) -> Result<Option<u64>, Error> {
+        // The size of the converted part isn't known until it's been prepared
+        let prepared_keys = self.prepared_keys.lock().await;
+        Ok(prepared_keys
+            .get(key)
+            .map(|part| part.data_file_size as u64))
+    }
+
+    async fn get_chunk(&self, key: &str, offset: u64, size: u64) -> Result<Vec<u8>, Error> {
+        let part = {
+            let prepared_keys = self.prepared_keys.lock().await;
+            prepared_keys
+                .get(key)
+                .ok_or_else(|| Error::msg(format!("Key not prepared: {key}")))?
+                .clone()
+        };
+
+        let buffer = read_prepared_chunk(&part, key, offset, size).await?;
+
+        if offset + buffer.len() as u64 >= part.data_file_size as u64 {
+            if let Err(e) = self.cleanup_key(key).await {
+                warn!("Failed to cleanup key {key}: {e:?}");
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    async fn prepare_key(&self, key: &str) -> Result<(), Error> {
+        {
+            let prepared_keys = self.prepared_keys.lock().await;
+            if prepared_keys.contains_key(key) {
+                return Ok(());
+            }
+        }
+
+        self.inner.prepare_key(key).await?;
+
+        let temp_dir = self.get_temp_dir_path().await?;
+        let safe_key = sanitize_key_for_path(key);
+        let raw_file_path = temp_dir.join(format!("{safe_key}.parquet"));
+        download_to_file(self.inner.as_ref(), key, &raw_file_path).await?;
+        self.inner.cleanup_key(key).await?;
+
+        let part = ParquetExtractor
+            .extract_compressed_to_seekable_file(&safe_key, &raw_file_path, &temp_dir)
+            .await?;
+
+        if let Err(e) = tokio::fs::remove_file(&raw_file_path).await {
+            warn!(
+                "Failed to remove raw file {}: {e:?}",
+                raw_file_path.display()
+            );
+        }
+
+        info!(
+            "Prepared key {} ({} bytes of converted rows)",
+            key, part.data_file_size
+        );
+        let mut prepared_keys = self.prepared_keys.lock().await;
+        prepared_keys.insert(key.to_string(), part);
+        Ok(())
+    }
+
+    async fn cleanup_key(&self, key: &str) -> Result<(), Error> {
+        let part = {
+            let mut prepared_keys = self.prepared_keys.lock().await;
+            prepared_keys.remove(key)
+        };
+
+        if let Some(part) = part {
+            tokio::fs::remove_file(&part.data_file_path)
+                .await
+                .with_context(|| format!("Failed to remove converted data file for key: {key}"))?;
+        }
+        Ok(())
+    }
+
+    async fn prepare_for_job(&self) -> Result<(), Error> {
+        self.inner.prepare_for_job().await?;
+
+        let temp_dir =
+            tempfile::tempdir().with_context(|| "Failed to create temp directory for job")?;
+        debug!("Created temp directory for job: {:?}", temp_dir.path());
+        *self.temp_dir.lock().await = Some(temp_dir);
+        Ok(())
+    }
+
+    async fn cleanup_after_job(&self) -> Result<(), Error> {
+        self.prepared_keys.lock().await.clear();
+        if let Some(temp_dir) = self.temp_dir.lock().await.take() {
+            drop(temp_dir);
+            debug!("Cleaned up temp directory");
+        }
+        self.inner.cleanup_after_job().await
+    }
+
+    fn get_date_range_for_key(&self, key: &str) -> Option<String> {
+        self.inner.get_date_range_for_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::folder::FolderSource;
+    use parquet::data_type::{ByteArray, ByteArrayType, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn write_parquet(
+        path: &std::path::Path,
+        events: &[&str],
+        distinct_ids: &[&str],
+        counts: &[i64],
+    ) {
+        let schema = Arc::new(
+            parse_message_type(
+                "message schema {
+                    REQUIRED BYTE_ARRAY event (UTF8);
+                    REQUIRED BYTE_ARRAY distinct_id (UTF8);
+                    OPTIONAL INT64 count;
+                }",
+            )
+            .unwrap(),
+        );
+        let file = std::fs::File::create(path).unwrap();
+        let mut writer =
+            SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::default())).unwrap();
+
+        let mut row_group = writer.next_row_group().unwrap();
+        for values in [events, distinct_ids] {
+            let values: Vec<ByteArray> = values.iter().map(|v| ByteArray::from(*v)).collect();
+            let mut column = row_group.next_column().unwrap().unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&values, None, None)
+                .unwrap();
+            column.close().unwrap();
+        }
+        // The last row has no count
+        let def_levels: Vec<i16> = (0..events.len())
+            .map(|i| i16::from(i < counts.len()))
+            .collect();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column
+            .typed::<Int64Type>()
+            .write_batch(counts, Some(&def_levels), None)
+            .unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+    }
+
+    async fn setup_source() -> (TempDir, ParquetSource) {
+        let temp_dir = TempDir::new().unwrap();
+        write_parquet(
+            &temp_dir.path().join("part-0000.snappy.parquet"),
+            &["signup", "purchase", "logout"],
+            &["user-1", "user-2", "user-1"],
+            &[1, 2],
+        );
+        std::fs::write(temp_dir.path().join("_SUCCESS"), "").unwrap();
+
+        let inner = FolderSource::new(temp_dir.path().to_str().unwrap().to_string())
+            .await
+            .unwrap();
+        let source = ParquetSource::new(Box::new(inner));
+        source.prepare_for_job().await.unwrap();
+        (temp_dir, source)
+    }
+
+    #[tokio::test]
+    async fn test_marker_files_are_skipped() {
+        let (_temp_dir, source) = setup_source().await;
+
+        let keys = source.keys().await.unwrap();
+
+        assert_eq!(keys, vec!["part-0000.snappy.parquet".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rows_are_converted_to_json_lines() {
+        let (_temp_dir, source) = setup_source().await;
+        let key = "part-0000.snappy.parquet";
+
+        assert_eq!(source.size(key).await.unwrap(), None);
+        source.prepare_key(key).await.unwrap();
+        let size = source.size(key).await.unwrap().unwrap();
+
+        let data = source.get_chunk(key, 0, size).await.unwrap();
+        let rows: Vec<Value> = String::from_utf8(data)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(
+            rows,
+            vec![
+                json!({"event": "signup", "distinct_id": "user-1", "count": 1}),
+                json!({"event": "purchase", "distinct_id": "user-2", "count": 2}),
+                json!({"event": "logout", "distinct_id": "user-1", "count": null}),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_converted_part_is_served_in_chunks() {
+        let (_temp_dir, source) = setup_source().await;
+        let key = "part-0000.snappy.parquet";
+        source.prepare_key(key).await.unwrap();
+        let size = source.size(key).await.unwrap().unwrap();
+
+        let first = source.get_chunk(key, 0, 10).await.unwrap();
+        let rest = source.get_chunk(key, 10, size).await.unwrap();
+
+        assert_eq!(first.len(), 10);
+        assert_eq!(first.len() + rest.len(), size as usize);
+        // Reading the end of the part cleans it up
+        assert_eq!(source.size(key).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_parquet_has_user_facing_error() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(temp_dir.path().join("events.parquet"), "not parquet").unwrap();
+        let inner = FolderSource::new(temp_dir.path().to_str().unwrap().to_string())
+            .await
+            .unwrap();
+        let source = ParquetSource::new(Box::new(inner));
+        source.prepare_for_job().await.unwrap();
+
+        let err = source.prepare_key("events.parquet").await.unwrap_err();
+
+        assert!(crate::error::get_user_message(&err).contains("isn't a valid Parquet file"));
+    }
+}
@@ -0,0 +1,406 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use common_types::{InternallyCapturedEvent, RawEvent};
+use hogvm::{sync_execute, ExecutionContext, Program};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use uuid::Uuid;
+
+use crate::error::{ToUserError, UserError};
+
+// Fields an event returned by a transformation keeps from the original event if it leaves them out
+const PRESERVED_FIELDS: [&str; 5] = ["event", "distinct_id", "uuid", "timestamp", "token"];
+
+// A compiled Hog program, run against every event after it's been parsed. The program gets the
+// event as the `event` global, and returns either the (possibly modified) event, null to drop it,
+// or an array of events to replace it with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct TransformationConfig {
+    pub bytecode: Vec<Value>,
+    #[serde(default = "TransformationConfig::default_max_steps")]
+    pub max_steps: usize,
+    #[serde(default = "TransformationConfig::default_max_heap_size")]
+    pub max_heap_size: usize,
+    #[serde(default = "TransformationConfig::default_max_stack_depth")]
+    pub max_stack_depth: usize,
+}
+
+impl TransformationConfig {
+    fn default_max_steps() -> usize {
+        10_000
+    }
+
+    fn default_max_heap_size() -> usize {
+        1024 * 1024
+    }
+
+    fn default_max_stack_depth() -> usize {
+        128
+    }
+}
+
+// Running totals of what a job's transformation has done to its events, persisted in the job state
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub struct TransformationStats {
+    /// Events the transformation returned null (or an empty array) for
+    pub dropped: u64,
+    /// Events the transformation returned a changed copy of
+    pub modified: u64,
+    /// Extra events produced by the transformation returning more than one event
+    pub added: u64,
+}
+
+impl TransformationStats {
+    pub fn add(&mut self, other: &TransformationStats) {
+        self.dropped += other.dropped;
+        self.modified += other.modified;
+        self.added += other.added;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == TransformationStats::default()
+    }
+}
+
+pub struct HogTransformation {
+    config: TransformationConfig,
+}
+
+impl HogTransformation {
+    pub fn new(config: &TransformationConfig) -> Result<Self, Error> {
+        // Programs aren't Clone or Sync, so we build one per event, but check the bytecode is valid up front
+        Program::new(config.bytecode.clone())
+            .user_error("The job's transformation isn't a valid compiled Hog program.")?;
+
+        Ok(Self {
+            config: config.clone(),
+        })
+    }
+
+    /// Runs the transformation over a chunk's events, returning the events to emit in place of them
+    pub fn apply(
+        &self,
+        events: Vec<InternallyCapturedEvent>,
+    ) -> Result<(Vec<InternallyCapturedEvent>, TransformationStats), Error> {
+        let results: Vec<(Vec<InternallyCapturedEvent>, TransformationStats)> = events
+            .into_par_iter()
+            .map(|event| self.transform_event(event))
+            .collect::<Result<_, Error>>()?;
+
+        let mut stats = TransformationStats::default();
+        let mut transformed = Vec::with_capacity(results.len());
+        for (events, event_stats) in results {
+            transformed.extend(events);
+            stats.add(&event_stats);
+        }
+
+        Ok((transformed, stats))
+    }
+
+    fn transform_event(
+        &self,
+        event: InternallyCapturedEvent,
+    ) -> Result<(Vec<InternallyCapturedEvent>, TransformationStats), Error> {
+        let uuid = event.inner.uuid;
+        let input: Value = serde_json::from_str(&event.inner.data)
+            .with_context(|| format!("Failed to read event {uuid} for transformation"))?;
+
+        let output = self.run(&input).map_err(|e| {
+            let msg = format!("The job's transformation failed on event {uuid}: {e}");
+            e.context(UserError::new(msg))
+        })?;
+
+        let outputs = match output {
+            Value::Null => Vec::new(),
+            Value::Object(object) => vec![object],
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    Value::Object(object) => Ok(object),
+                    other => Err(invalid_output(uuid, &other)),
+                })
+                .collect::<Result<_, _>>()?,
+            other => return Err(invalid_output(uuid, &other)),
+        };
+
+        let mut stats = TransformationStats::default();
+        match outputs.len() {
+            0 => stats.dropped = 1,
+            n => stats.added = n as u64 - 1,
+        }
+
+        let mut events = Vec::with_capacity(outputs.len());
+        for (index, output) in outputs.into_iter().enumerate() {
+            let output = with_preserved_fields(output, &input);
+            if output == input && index == 0 {
+                events.push(event.clone());
+                continue;
+            }
+            if output != input {
+                stats.modified += 1;
+            }
+            events.push(rebuild_event(&event, index, output)?);
+        }
+
+        Ok((events, stats))
+    }
+
+    fn run(&self, input: &Value) -> Result<Value, Error> {
+        let program = Program::new(self.config.bytecode.clone())?;
+        let context = ExecutionContext::with_defaults(program)
+            .with_globals(json!({ "event": input }))
+            .with_max_steps(self.config.max_steps)
+            .with_max_heap_size(self.config.max_heap_size)
+            .with_max_stack_depth(self.config.max_stack_depth);
+
+        sync_execute(&context, false).map_err(|failure| {
+            Error::msg(format!(
+                "{} (at instruction {}, step {})",
+                failure.error, failure.ip, failure.step
+            ))
+        })
+    }
+}
+
+fn invalid_output(uuid: Uuid, output: &Value) -> Error {
+    Error::msg(format!("Transformation returned {output}")).context(UserError::new(format!(
+        "The job's transformation returned something other than an event, null or a list of events for event {uuid}."
+    )))
+}
+
+fn with_preserved_fields(mut output: Map<String, Value>, input: &Value) -> Value {
+    for field in PRESERVED_FIELDS {
+        if let Some(value) = input.get(field) {
+            output.entry(field).or_insert_with(|| value.clone());
+        }
+    }
+    Value::Object(output)
+}
+
+// Builds the event to emit from what the transformation returned. When an event is fanned out, every
+// copy after the first that kept the original uuid gets one derived from it, so re-running a chunk
+// produces the same uuids.
+fn rebuild_event(
+    original: &InternallyCapturedEvent,
+    index: usize,
+    output: Value,
+) -> Result<InternallyCapturedEvent, Error> {
+    let original_uuid = original.inner.uuid;
+    let mut raw: RawEvent = serde_json::from_value(output).user_error(format!(
+        "The job's transformation returned an invalid event for event {original_uuid}."
+    ))?;
+
+    let mut uuid = raw.uuid.unwrap_or(original_uuid);
+    if index > 0 && uuid == original_uuid {
+        uuid = Uuid::new_v5(&original_uuid, index.to_string().as_bytes());
+    }
+    raw.uuid = Some(uuid);
+
+    let distinct_id = match &raw.distinct_id {
+        Some(Value::String(distinct_id)) => distinct_id.clone(),
+        Some(Value::Null) | None => original.inner.distinct_id.clone(),
+        Some(other) => other.to_string(),
+    };
+
+    let timestamp = match &raw.timestamp {
+        Some(timestamp) => DateTime::parse_from_rfc3339(timestamp)
+            .map(|t| t.with_timezone(&Utc))
+            .user_error(format!(
+                "The job's transformation returned an invalid timestamp '{timestamp}' for event {original_uuid}."
+            ))?,
+        None => original.inner.timestamp,
+    };
+
+    let mut event = original.clone();
+    event.inner.uuid = uuid;
+    event.inner.event = raw.event.clone();
+    event.inner.distinct_id = distinct_id;
+    event.inner.timestamp = timestamp;
+    event.inner.data = serde_json::to_string(&raw)?;
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::get_user_message;
+    use common_types::CapturedEvent;
+
+    fn config(bytecode: Value) -> TransformationConfig {
+        serde_json::from_value(json!({ "bytecode": bytecode })).unwrap()
+    }
+
+    fn event(name: &str) -> InternallyCapturedEvent {
+        let uuid = Uuid::now_v7();
+        let timestamp: DateTime<Utc> = "2024-01-01T00:00:00Z".parse().unwrap();
+        let data = json!({
+            "event": name,
+            "distinct_id": "user-1",
+            "uuid": uuid,
+            "timestamp": timestamp.to_rfc3339(),
+            "properties": {"plan": "free"},
+        });
+        InternallyCapturedEvent {
+            team_id: 1,
+            inner: CapturedEvent {
+                uuid,
+                distinct_id: "user-1".to_string(),
+                session_id: None,
+                ip: "".to_string(),
+                data: data.to_string(),
+                now: "2024-01-01 00:00:00.000".to_string(),
+                sent_at: None,
+                token: "token".to_string(),
+                event: name.to_string(),
+                timestamp,
+                is_cookieless_mode: false,
+                historical_migration: true,
+            },
+        }
+    }
+
+    fn data(event: &InternallyCapturedEvent) -> Value {
+        serde_json::from_str(&event.inner.data).unwrap()
+    }
+
+    #[test]
+    fn test_returning_event_leaves_it_unchanged() {
+        // return event
+        let transformation =
+            HogTransformation::new(&config(json!(["_H", 1, 32, "event", 1, 1, 38]))).unwrap();
+        let input = event("signup");
+
+        let (output, stats) = transformation.apply(vec![input.clone()]).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].inner, input.inner);
+        assert!(stats.is_empty());
+    }
+
+    #[test]
+    fn test_events_are_dropped() {
+        // if (event.event == 'internal') { return null } return event
+        let transformation = HogTransformation::new(&config(json!([
+            "_H", 1, 32, "event", 32, "event", 1, 2, 32, "internal", 11, 40, 2, 31, 38, 32,
+            "event", 1, 1, 38
+        ])))
+        .unwrap();
+
+        let (output, stats) = transformation
+            .apply(vec![event("internal"), event("signup"), event("internal")])
+            .unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].inner.event, "signup");
+        assert_eq!(
+            stats,
+            TransformationStats {
+                dropped: 2,
+                modified: 0,
+                added: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_events_are_modified() {
+        // return {'event': 'renamed', 'properties': event.properties}
+        let transformation = HogTransformation::new(&config(json!([
+            "_H",
+            1,
+            32,
+            "event",
+            32,
+            "renamed",
+            32,
+            "properties",
+            32,
+            "properties",
+            32,
+            "event",
+            1,
+            2,
+            42,
+            2,
+            38
+        ])))
+        .unwrap();
+        let input = event("signup");
+
+        let (output, stats) = transformation.apply(vec![input.clone()]).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].inner.event, "renamed");
+        // Fields the program left out are kept from the original event
+        assert_eq!(output[0].inner.uuid, input.inner.uuid);
+        assert_eq!(output[0].inner.distinct_id, "user-1");
+        assert_eq!(output[0].inner.timestamp, input.inner.timestamp);
+        let output_data = data(&output[0]);
+        assert_eq!(output_data["event"], "renamed");
+        assert_eq!(output_data["distinct_id"], "user-1");
+        assert_eq!(output_data["properties"], json!({"plan": "free"}));
+        assert_eq!(stats.modified, 1);
+    }
+
+    #[test]
+    fn test_events_are_fanned_out_with_stable_uuids() {
+        // return [event, event]
+        let transformation = HogTransformation::new(&config(json!([
+            "_H", 1, 32, "event", 1, 1, 32, "event", 1, 1, 43, 2, 38
+        ])))
+        .unwrap();
+        let input = event("signup");
+
+        let (first_run, stats) = transformation.apply(vec![input.clone()]).unwrap();
+        let (second_run, _) = transformation.apply(vec![input.clone()]).unwrap();
+
+        assert_eq!(first_run.len(), 2);
+        assert_eq!(first_run[0].inner.uuid, input.inner.uuid);
+        assert_ne!(first_run[1].inner.uuid, input.inner.uuid);
+        assert_eq!(first_run[1].inner.uuid, second_run[1].inner.uuid);
+        assert_eq!(
+            data(&first_run[1])["uuid"],
+            json!(first_run[1].inner.uuid.to_string())
+        );
+        assert_eq!(
+            stats,
+            TransformationStats {
+                dropped: 0,
+                modified: 0,
+                added: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_non_event_output_has_user_facing_error() {
+        // return 1
+        let transformation = HogTransformation::new(&config(json!(["_H", 1, 33, 1, 38]))).unwrap();
+
+        let err = transformation.apply(vec![event("signup")]).unwrap_err();
+
+        assert!(get_user_message(&err).contains("something other than an event"));
+    }
+
+    #[test]
+    fn test_step_limit_fails_event() {
+        // while (true) {}
+        let mut config = config(json!(["_H", 1, 39, -2]));
+        config.max_steps = 100;
+        let transformation = HogTransformation::new(&config).unwrap();
+
+        let err = transformation.apply(vec![event("signup")]).unwrap_err();
+
+        assert!(get_user_message(&err).contains("The job's transformation failed on event"));
+    }
+
+    #[test]
+    fn test_invalid_bytecode_is_rejected() {
+        let result = HogTransformation::new(&config(json!(["not", "hog"])));
+
+        assert!(get_user_message(&result.err().unwrap()).contains("isn't a valid compiled Hog"));
+    }
+}
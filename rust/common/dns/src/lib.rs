@@ -84,9 +84,27 @@ impl Resolve for PublicIPv4Resolver {
     }
 }
 
+/// Resolve every address of a host, without any filtering.
+///
+/// This is meant for discovering internal services, e.g. all pods behind a headless
+/// Kubernetes service, so unlike [`PublicIPv4Resolver`] it keeps private addresses.
+/// Never use it on user-provided hostnames.
+pub async fn resolve_all(host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let host = host.to_string();
+    let mut addrs: Vec<SocketAddr> =
+        spawn_blocking(move || (host.as_str(), port).to_socket_addrs())
+            .await
+            .map_err(io::Error::from)??
+            .collect();
+    // Resolvers may return addresses in any order, sort them so callers can diff results
+    addrs.sort();
+    addrs.dedup();
+    Ok(addrs)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{NoPublicIPv4Error, PublicIPv4Resolver};
+    use crate::{resolve_all, NoPublicIPv4Error, PublicIPv4Resolver};
     use reqwest::dns::{Name, Resolve};
     use std::str::FromStr;
 
@@ -121,6 +139,17 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn it_resolves_all_addresses_including_private_ones() {
+        let addrs = resolve_all("localhost", 8080)
+            .await
+            .expect("lookup has failed");
+        assert!(!addrs.is_empty(), "empty address list");
+        assert!(addrs
+            .iter()
+            .all(|addr| addr.ip().is_loopback() && addr.port() == 8080));
+    }
+
     #[tokio::test]
     async fn it_bubbles_up_resolution_error() {
         let resolver: PublicIPv4Resolver = PublicIPv4Resolver {};
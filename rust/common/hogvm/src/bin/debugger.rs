@@ -0,0 +1,539 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    time::{Duration, Instant},
+};
+
+use hogvm::{ExecutionContext, HogVM, HogValue, Operation, Program, StepOutcome, Symbol, VmError};
+use serde_json::Value;
+
+// Invoked like (when inside the common/hogvm folder):
+//   cargo run --bin debugger -- path/to/bytecode.json [--globals globals.json] [--break <spec>]... [--script commands.txt] [--profile]
+//
+// The bytecode file is a JSON array, as produced by the hog compiler, and the globals file a JSON object.
+// Without --script, commands are read interactively from stdin. With --profile, the program is run to
+// completion and a report of step counts and time spent per operation and per native function is printed.
+// Instruction pointers are absolute indexes into the bytecode array, as in serialized VM states.
+const HELP: &str = "Commands:
+  s, step [n]         execute the next n steps (default 1)
+  c, continue         run until a breakpoint is hit or the program finishes
+  b, break <spec>     add a breakpoint. <spec> is one of:
+                        <ip>               an index into the top level bytecode, header included
+                        <module>/<fn>      entry into a module function
+                        <module>/<fn>:<ip> an index into a module function's bytecode
+                        <name>             a call to a global or native function
+  d, delete <n>       remove breakpoint n
+  breakpoints         list breakpoints
+  w, where            show the current position and next operation
+  stack               print the whole stack
+  locals              print the stack values of the current call frame
+  heap                print heap and stack usage against their limits
+  q, quit             exit the debugger";
+
+#[derive(Debug, PartialEq)]
+enum Breakpoint {
+    Ip(Option<Symbol>, usize),
+    Entry(Symbol),
+    Call(String),
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Breakpoint::Ip(None, ip) => write!(f, "ip {ip}"),
+            Breakpoint::Ip(Some(symbol), ip) => write!(f, "ip {symbol}:{ip}"),
+            Breakpoint::Entry(symbol) => write!(f, "entry to {symbol}"),
+            Breakpoint::Call(name) => write!(f, "call to {name}"),
+        }
+    }
+}
+
+fn parse_breakpoint(spec: &str) -> Result<Breakpoint, String> {
+    let parse_ip = |ip: &str| {
+        ip.parse::<usize>()
+            .map_err(|_| format!("invalid instruction pointer: {ip}"))
+    };
+    if let Ok(ip) = spec.parse::<usize>() {
+        return Ok(Breakpoint::Ip(None, ip));
+    }
+    let Some((module, name)) = spec.split_once('/') else {
+        return Ok(Breakpoint::Call(spec.to_string()));
+    };
+    match name.split_once(':') {
+        Some((name, ip)) => Ok(Breakpoint::Ip(Some(symbol(module, name)), parse_ip(ip)?)),
+        None => Ok(Breakpoint::Entry(symbol(module, name))),
+    }
+}
+
+fn symbol(module: &str, name: &str) -> Symbol {
+    Symbol {
+        module: module.to_string(),
+        name: name.to_string(),
+    }
+}
+
+#[derive(Default)]
+struct Timing {
+    count: usize,
+    total: Duration,
+}
+
+impl Timing {
+    fn record(&mut self, elapsed: Duration) {
+        self.count += 1;
+        self.total += elapsed;
+    }
+}
+
+#[derive(Default)]
+struct Profile {
+    ops: HashMap<String, Timing>,
+    natives: HashMap<String, Timing>,
+    peak_heap: usize,
+    peak_stack: usize,
+}
+
+struct Debugger<'a> {
+    context: &'a ExecutionContext,
+    vm: HogVM<'a>,
+    breakpoints: Vec<Breakpoint>,
+    steps: usize,
+    result: Option<Result<Value, VmError>>,
+    profile: Option<Profile>,
+}
+
+impl<'a> Debugger<'a> {
+    fn new(context: &'a ExecutionContext, profile: bool) -> Result<Self, VmError> {
+        Ok(Self {
+            context,
+            vm: context.to_vm()?,
+            breakpoints: Vec::new(),
+            steps: 0,
+            result: None,
+            profile: profile.then(Profile::default),
+        })
+    }
+
+    fn finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    // The name of the global function the next step will call, if it calls one
+    fn pending_call(&self) -> Option<&str> {
+        if !matches!(self.vm.peek_op(), Ok(Operation::CallGlobal)) {
+            return None;
+        }
+        let symbol = self.vm.current_symbol().cloned();
+        self.context
+            .get_bytecode(self.vm.ip() + 1, &symbol)
+            .ok()
+            .and_then(Value::as_str)
+    }
+
+    // Returns the breakpoint matching the current vm position, if any. Entry breakpoints only
+    // match on the step a call lands in the function, not on returning to it.
+    fn hit_breakpoint(&self, entered: bool) -> Option<usize> {
+        let symbol = self.vm.current_symbol();
+        let call = self.pending_call();
+        self.breakpoints.iter().position(|bp| match bp {
+            Breakpoint::Ip(s, ip) => s.as_ref() == symbol && *ip == self.vm.absolute_ip(),
+            Breakpoint::Entry(s) => entered && Some(s) == symbol,
+            Breakpoint::Call(name) => call == Some(name.as_str()),
+        })
+    }
+
+    fn step(&mut self) {
+        if self.finished() {
+            return;
+        }
+        if self.steps >= self.context.max_steps {
+            self.result = Some(Err(VmError::OutOfResource("steps".to_string())));
+            return;
+        }
+        self.steps += 1;
+
+        let op = self.vm.peek_op().map(|op| format!("{op:?}"));
+        let start = Instant::now();
+        let outcome = self.vm.step();
+        if let (Some(profile), Ok(op)) = (self.profile.as_mut(), op) {
+            profile.ops.entry(op).or_default().record(start.elapsed());
+        }
+
+        match outcome {
+            Ok(StepOutcome::Finished(res)) => self.result = Some(Ok(res)),
+            Ok(StepOutcome::NativeCall(name, args)) => {
+                let start = Instant::now();
+                let res = self
+                    .context
+                    .execute_native_function_call(&mut self.vm, &name, args);
+                if let Some(profile) = self.profile.as_mut() {
+                    profile
+                        .natives
+                        .entry(name)
+                        .or_default()
+                        .record(start.elapsed());
+                }
+                if let Err(e) = res {
+                    self.result = Some(Err(e));
+                }
+            }
+            Ok(StepOutcome::Continue) => {}
+            Err(e) => self.result = Some(Err(e)),
+        }
+
+        if let Some(profile) = self.profile.as_mut() {
+            profile.peak_heap = profile.peak_heap.max(self.vm.heap.used_bytes());
+            profile.peak_stack = profile.peak_stack.max(self.vm.stack().len());
+        }
+    }
+
+    // Run until a breakpoint is hit or the program finishes. We always take at least one step, so
+    // that continuing from a breakpoint doesn't immediately stop at it again.
+    fn run(&mut self) {
+        loop {
+            let depth = self.vm.call_depth();
+            self.step();
+            if self.finished() {
+                return;
+            }
+            if let Some(i) = self.hit_breakpoint(self.vm.call_depth() > depth) {
+                println!("Breakpoint {i} hit: {}", self.breakpoints[i]);
+                self.print_where();
+                return;
+            }
+        }
+    }
+
+    fn print_result(&self) {
+        match &self.result {
+            Some(Ok(res)) => println!("Finished after {} steps: {res}", self.steps),
+            Some(Err(e)) => println!("Failed after {} steps: {e}", self.steps),
+            None => {}
+        }
+    }
+
+    fn print_where(&self) {
+        if self.finished() {
+            self.print_result();
+            return;
+        }
+        let location = match self.vm.current_symbol() {
+            Some(symbol) => format!("{symbol}:{}", self.vm.absolute_ip()),
+            None => format!("{}", self.vm.absolute_ip()),
+        };
+        let op = match self.vm.peek_op() {
+            Ok(Operation::CallGlobal) => {
+                format!("CallGlobal {}", self.pending_call().unwrap_or("?"))
+            }
+            Ok(op) => format!("{op:?}"),
+            Err(e) => format!("<{e}>"),
+        };
+        println!(
+            "[{location}] next: {op} (step {}, call depth {})",
+            self.steps,
+            self.vm.call_depth()
+        );
+    }
+
+    fn print_values(&self, values: &[HogValue], offset: usize) {
+        if values.is_empty() {
+            println!("  (empty)");
+        }
+        for (i, value) in values.iter().enumerate() {
+            println!("  {:>4}: {}", i + offset, self.show(value));
+        }
+    }
+
+    fn show(&self, value: &HogValue) -> String {
+        match self.vm.hog_to_json(value) {
+            Ok(json) => json.to_string(),
+            // Closures and callables have no json representation
+            Err(_) => match value.deref(&self.vm.heap) {
+                Ok(literal) => format!("{literal:?}"),
+                Err(e) => format!("<{e}>"),
+            },
+        }
+    }
+
+    fn print_heap(&self) {
+        let used = self.vm.heap.used_bytes();
+        let max = self.context.max_heap_size;
+        println!(
+            "heap: {used} / {max} bytes ({:.1}%)",
+            used as f64 / max as f64 * 100.0
+        );
+        println!(
+            "stack: {} / {} values, call depth {}",
+            self.vm.stack().len(),
+            self.context.max_stack_depth,
+            self.vm.call_depth()
+        );
+    }
+
+    // Returns false if the debugger should exit
+    fn command(&mut self, line: &str) -> bool {
+        let mut parts = line.split_whitespace();
+        let Some(cmd) = parts.next() else {
+            return true;
+        };
+        let arg = parts.next();
+        match cmd {
+            "s" | "step" => {
+                let Some(n) = arg.map_or(Some(1), |n| n.parse::<usize>().ok()) else {
+                    println!("invalid step count: {}", arg.unwrap_or_default());
+                    return true;
+                };
+                for _ in 0..n {
+                    self.step();
+                }
+                self.print_where();
+            }
+            "c" | "continue" => {
+                self.run();
+                self.print_result();
+            }
+            "b" | "break" => match arg.map(parse_breakpoint) {
+                Some(Ok(bp)) => {
+                    println!("Breakpoint {}: {bp}", self.breakpoints.len());
+                    self.breakpoints.push(bp);
+                }
+                Some(Err(e)) => println!("{e}"),
+                None => println!("usage: break <spec>"),
+            },
+            "d" | "delete" => match arg.and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if n < self.breakpoints.len() => {
+                    println!("Deleted breakpoint {n}: {}", self.breakpoints.remove(n));
+                }
+                _ => println!("usage: delete <n>, where n is listed by `breakpoints`"),
+            },
+            "breakpoints" => {
+                for (i, bp) in self.breakpoints.iter().enumerate() {
+                    println!("  {i}: {bp}");
+                }
+            }
+            "w" | "where" => self.print_where(),
+            "stack" => self.print_values(self.vm.stack(), 0),
+            "locals" => {
+                let locals = self.vm.locals();
+                self.print_values(locals, self.vm.stack().len() - locals.len());
+            }
+            "heap" => self.print_heap(),
+            "h" | "help" => println!("{HELP}"),
+            "q" | "quit" => return false,
+            _ => println!("unknown command: {cmd}, try `help`"),
+        }
+        true
+    }
+}
+
+fn print_timings(title: &str, timings: &HashMap<String, Timing>) {
+    let mut rows: Vec<_> = timings.iter().collect();
+    rows.sort_by_key(|(_, timing)| std::cmp::Reverse(timing.total));
+    println!(
+        "{title:<24} {:>10} {:>12} {:>10}",
+        "count", "total µs", "mean ns"
+    );
+    for (name, timing) in rows {
+        println!(
+            "{name:<24} {:>10} {:>12.1} {:>10}",
+            timing.count,
+            timing.total.as_secs_f64() * 1_000_000.0,
+            timing.total.as_nanos() / timing.count as u128
+        );
+    }
+}
+
+fn print_profile(debugger: &Debugger) {
+    let Some(profile) = &debugger.profile else {
+        return;
+    };
+    debugger.print_result();
+    println!(
+        "peak heap: {} / {} bytes, peak stack: {} / {} values\n",
+        profile.peak_heap,
+        debugger.context.max_heap_size,
+        profile.peak_stack,
+        debugger.context.max_stack_depth
+    );
+    print_timings("operation", &profile.ops);
+    println!();
+    print_timings("native function", &profile.natives);
+}
+
+fn usage() -> ! {
+    eprintln!("usage: debugger <bytecode.json> [--globals <file>] [--break <spec>]... [--script <file>] [--profile]\n\n{HELP}");
+    std::process::exit(1)
+}
+
+fn read_json(path: &str) -> Value {
+    let data =
+        std::fs::read_to_string(path).unwrap_or_else(|e| panic!("Failed to read {path}: {e}"));
+    serde_json::from_str(&data).unwrap_or_else(|e| panic!("Failed to parse {path}: {e}"))
+}
+
+pub fn main() {
+    // nosemgrep: rust.lang.security.args.args
+    let mut args = std::env::args().skip(1);
+    let mut bytecode_file = None;
+    let mut globals_file = None;
+    let mut script_file = None;
+    let mut breakpoints = Vec::new();
+    let mut profile = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--globals" => globals_file = Some(args.next().unwrap_or_else(|| usage())),
+            "--script" => script_file = Some(args.next().unwrap_or_else(|| usage())),
+            "--break" => breakpoints.push(args.next().unwrap_or_else(|| usage())),
+            "--profile" => profile = true,
+            _ if arg.starts_with("--") || bytecode_file.is_some() => usage(),
+            _ => bytecode_file = Some(arg),
+        }
+    }
+    let Some(bytecode_file) = bytecode_file else {
+        usage()
+    };
+    // Without a script, profiling runs straight to completion, so breakpoints would never stop it
+    if profile && script_file.is_none() && !breakpoints.is_empty() {
+        eprintln!("--break needs --script when used with --profile");
+        std::process::exit(1)
+    }
+
+    let Value::Array(bytecode) = read_json(&bytecode_file) else {
+        panic!("Bytecode file must contain a JSON array");
+    };
+    let program = Program::new(bytecode).expect("Invalid program");
+    let mut context = ExecutionContext::with_defaults(program);
+    if let Some(globals_file) = globals_file {
+        context = context.with_globals(read_json(&globals_file));
+    }
+
+    let mut debugger = Debugger::new(&context, profile).expect("Failed to construct vm");
+    for spec in breakpoints {
+        match parse_breakpoint(&spec) {
+            Ok(bp) => debugger.breakpoints.push(bp),
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    if profile && script_file.is_none() {
+        while !debugger.finished() {
+            debugger.step();
+        }
+        print_profile(&debugger);
+        return;
+    }
+
+    if let Some(script_file) = script_file {
+        let script = std::fs::read_to_string(&script_file)
+            .unwrap_or_else(|e| panic!("Failed to read {script_file}: {e}"));
+        for line in script.lines() {
+            println!("> {line}");
+            if !debugger.command(line) {
+                break;
+            }
+        }
+        print_profile(&debugger);
+        return;
+    }
+
+    debugger.print_where();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(hogdb) ");
+        std::io::stdout().flush().unwrap();
+        let Some(Ok(line)) = lines.next() else {
+            break;
+        };
+        if !debugger.command(&line) {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context(bytecode: Value) -> ExecutionContext {
+        let Value::Array(bytecode) = bytecode else {
+            panic!("bytecode must be an array");
+        };
+        ExecutionContext::with_defaults(Program::new(bytecode).unwrap())
+    }
+
+    fn result(debugger: &Debugger) -> Value {
+        match &debugger.result {
+            Some(Ok(res)) => res.clone(),
+            Some(Err(e)) => panic!("program failed: {e}"),
+            None => panic!("program hasn't finished"),
+        }
+    }
+
+    #[test]
+    fn test_parse_breakpoint() {
+        assert_eq!(parse_breakpoint("12"), Ok(Breakpoint::Ip(None, 12)));
+        assert_eq!(
+            parse_breakpoint("math/double"),
+            Ok(Breakpoint::Entry(symbol("math", "double")))
+        );
+        assert_eq!(
+            parse_breakpoint("math/double:3"),
+            Ok(Breakpoint::Ip(Some(symbol("math", "double")), 3))
+        );
+        assert_eq!(
+            parse_breakpoint("toString"),
+            Ok(Breakpoint::Call("toString".to_string()))
+        );
+        assert!(parse_breakpoint("math/double:x").is_err());
+    }
+
+    #[test]
+    fn test_stepping() {
+        // return 1 + 2
+        let context = context(json!(["_H", 1, 33, 1, 33, 2, 6, 38]));
+        let mut debugger = Debugger::new(&context, false).unwrap();
+
+        // Instruction pointers count the header
+        assert_eq!(debugger.vm.absolute_ip(), 2);
+        debugger.command("step 2");
+        assert_eq!(debugger.vm.absolute_ip(), 6);
+        assert_eq!(debugger.vm.stack().len(), 2);
+        assert!(!debugger.finished());
+
+        debugger.command("step 2");
+        assert_eq!(result(&debugger), json!(3));
+        assert_eq!(debugger.steps, 4);
+    }
+
+    #[test]
+    fn test_breaking_on_a_breakpoint() {
+        let context = context(json!(["_H", 1, 33, 1, 33, 2, 6, 38]));
+        let mut debugger = Debugger::new(&context, false).unwrap();
+
+        debugger.command("break 6");
+        debugger.command("continue");
+        assert_eq!(debugger.vm.absolute_ip(), 6);
+        assert_eq!(debugger.steps, 2);
+
+        // Continuing steps past the breakpoint we're stopped at
+        debugger.command("continue");
+        assert_eq!(result(&debugger), json!(3));
+    }
+
+    #[test]
+    fn test_breaking_on_a_call() {
+        // return toString(1)
+        let context = context(json!(["_H", 1, 33, 1, 2, "toString", 1, 38]));
+        let mut debugger = Debugger::new(&context, false).unwrap();
+
+        debugger.command("break toString");
+        debugger.command("continue");
+        assert_eq!(debugger.pending_call(), Some("toString"));
+        assert_eq!(debugger.vm.absolute_ip(), 4);
+
+        debugger.command("continue");
+        assert_eq!(result(&debugger), json!("1"));
+    }
+}
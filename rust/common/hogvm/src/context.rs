@@ -1,5 +1,5 @@
 use serde_json::{json, Value as JsonValue};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     error::VmError,
@@ -9,6 +9,11 @@ use crate::{
     HogLiteral, HogValue,
 };
 
+/// Resolves cohort membership for the InCohort and NotInCohort operations. It's passed the value
+/// on the left hand side of the operation (generally a person id) and the cohort on the right hand
+/// side (an id or name), and returns whether the former is a member of the latter.
+pub type CohortResolver = Box<dyn Fn(&HogVM, &HogValue, &HogValue) -> Result<bool, VmError>>;
+
 /// The read-only context for the virtual machine.
 pub struct ExecutionContext {
     program: Program,
@@ -18,8 +23,11 @@ pub struct ExecutionContext {
     pub max_stack_depth: usize,
     pub max_heap_size: usize,
     pub max_steps: usize,
+    pub max_async_steps: usize,
     native_fns: HashMap<String, NativeFunction>,
+    async_fns: HashSet<String>, // Native calls to these suspend execution, rather than being run by the context
     symbol_table: HashMap<Symbol, ExportedFunction>, // Flattened symbol table of all imported hog modules
+    cohort_resolver: Option<CohortResolver>,
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
@@ -44,8 +52,11 @@ impl ExecutionContext {
             max_stack_depth,
             max_heap_size,
             max_steps,
+            max_async_steps: 100,
             native_fns,
+            async_fns: HashSet::new(),
             symbol_table: HashMap::new(),
+            cohort_resolver: None,
         }
         .with_modules(&modules)
     }
@@ -98,8 +109,24 @@ impl ExecutionContext {
         self
     }
 
+    pub fn with_cohort_resolver(mut self, resolver: CohortResolver) -> Self {
+        self.cohort_resolver = Some(resolver);
+        self
+    }
+
+    // The number of async functions a program can call before it's stopped
+    pub fn with_max_async_steps(mut self, max_async_steps: usize) -> Self {
+        self.max_async_steps = max_async_steps;
+        self
+    }
+
+    pub fn with_async_fns(mut self, names: impl IntoIterator<Item = String>) -> Self {
+        self.async_fns.extend(names);
+        self
+    }
+
+    // Adds to, rather than replacing, the set of importable modules, so the hog stl stays available
     pub fn with_modules(mut self, modules: &HashMap<String, Module>) -> Self {
-        self.symbol_table.clear();
         for (name, module) in modules.iter() {
             self = self.add_module(name.clone(), module);
         }
@@ -118,6 +145,18 @@ impl ExecutionContext {
         HogVM::new(self)
     }
 
+    pub fn is_async_fn(&self, name: &str) -> bool {
+        self.async_fns.contains(name)
+    }
+
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    pub(crate) fn symbols(&self) -> impl Iterator<Item = (&Symbol, &ExportedFunction)> {
+        self.symbol_table.iter()
+    }
+
     pub fn has_symbol(&self, symbol: &Symbol) -> bool {
         self.symbol_table.contains_key(symbol)
     }
@@ -128,6 +167,29 @@ impl ExecutionContext {
             .ok_or(VmError::UnknownSymbol(symbol.to_string()))
     }
 
+    // Returns every function a module exports, keyed by name, or None if no module with that name
+    // has been added
+    pub fn module_exports(&self, module: &str) -> Option<Vec<(&Symbol, &ExportedFunction)>> {
+        let exports: Vec<_> = self
+            .symbol_table
+            .iter()
+            .filter(|(symbol, _)| symbol.module == module)
+            .collect();
+        (!exports.is_empty()).then_some(exports)
+    }
+
+    pub fn in_cohort(
+        &self,
+        vm: &HogVM,
+        subject: &HogValue,
+        cohort: &HogValue,
+    ) -> Result<bool, VmError> {
+        let Some(resolver) = &self.cohort_resolver else {
+            return Err(VmError::NoCohortResolver);
+        };
+        resolver(vm, subject, cohort)
+    }
+
     pub fn execute_native_function_call(
         &self,
         vm: &mut HogVM,
@@ -71,6 +71,10 @@ pub enum VmError {
     IntegerOverflow,
     #[error("Unknown symbol {0}")]
     UnknownSymbol(String),
+    #[error("Unknown module {0}")]
+    UnknownModule(String),
+    #[error("No cohort resolver configured, cannot check cohort membership")]
+    NoCohortResolver,
     #[error("{0}")]
     Other(String),
 }
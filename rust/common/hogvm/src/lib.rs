@@ -3,25 +3,41 @@ mod error;
 mod memory;
 mod ops;
 mod program;
+mod state;
 mod stl;
 mod util;
 mod values;
 mod vm;
 
 // Execution context
+pub use context::CohortResolver;
 pub use context::ExecutionContext;
+pub use context::Symbol;
 
 // Programs and modules
 pub use program::ExportedFunction;
 pub use program::Module;
 pub use program::Program;
 
+// Operations, for inspecting bytecode
+pub use ops::Operation;
+
 // VM, and helpers
+pub use vm::execute;
 pub use vm::sync_execute;
+pub use vm::ExecOutcome;
 pub use vm::HogVM;
 pub use vm::StepOutcome;
 pub use vm::VmFailure;
 
+// Serializable VM state, for suspending and resuming programs
+pub use state::BytecodeEntry;
+pub use state::CallFrameState;
+pub use state::ThrowFrameState;
+pub use state::UpValue;
+pub use state::VmState;
+pub use state::VM_STATE_VERSION;
+
 // STL - again, we expose a lot, because we want to make it easy to extend this
 pub use stl::hog_stl;
 pub use stl::native_func;
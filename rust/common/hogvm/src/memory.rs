@@ -35,6 +35,10 @@ impl VmHeap {
         }
     }
 
+    pub fn used_bytes(&self) -> usize {
+        self.current_bytes
+    }
+
     fn assert_can_allocate(&self, new_bytes: usize) -> Result<(), VmError> {
         if self.current_bytes.saturating_add(new_bytes) > self.max_bytes {
             Err(VmError::OutOfResource("Heap Memory".to_string()))
@@ -76,6 +76,16 @@ impl Program {
     pub fn version(&self) -> u64 {
         self.version
     }
+
+    // The full bytecode, including the header
+    pub fn bytecode(&self) -> &[JsonValue] {
+        &self.bytecode
+    }
+
+    // The length of the header, which instruction pointers into the program are relative to
+    pub fn start_offset(&self) -> usize {
+        self.program_start_offset
+    }
 }
 
 impl Module {
@@ -109,4 +119,8 @@ impl ExportedFunction {
     pub fn get(&self, idx: usize) -> Option<&JsonValue> {
         self.body.get(idx)
     }
+
+    pub fn bytecode(&self) -> &[JsonValue] {
+        &self.body
+    }
 }
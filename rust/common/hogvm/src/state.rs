@@ -0,0 +1,567 @@
+use std::{collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    context::{ExecutionContext, Symbol},
+    error::VmError,
+    memory::HeapReference,
+    program::Program,
+    values::{Callable, Closure, HogLiteral, HogValue, LocalCallable},
+    vm::{CallFrame, DeclaredFunction, HogVM, ThrowFrame, MAX_JSON_SERDE_DEPTH},
+};
+
+/// The version of the serialized state format this VM produces. States without a version were produced
+/// by the Node VM, and are treated as version 1, which this format is compatible with.
+pub const VM_STATE_VERSION: u64 = 1;
+
+const ROOT_CHUNK: &str = "root";
+
+/// A snapshot of a suspended VM. This has the same shape as the Node VM's `VMState`, and follows the same
+/// conventions (absolute instruction pointers, "root" and "module/function" chunk names, and upvalues
+/// referenced by id), so a program suspended by one VM can be resumed by the other.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VmState {
+    #[serde(default = "legacy_version")]
+    pub version: u64,
+    pub bytecodes: HashMap<String, BytecodeEntry>,
+    // The Node VM's legacy single-program format, still found in some in-flight jobs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytecode: Option<Vec<JsonValue>>,
+    pub stack: Vec<JsonValue>,
+    pub upvalues: Vec<UpValue>,
+    pub call_stack: Vec<CallFrameState>,
+    pub throw_stack: Vec<ThrowFrameState>,
+    pub declared_functions: HashMap<String, (usize, usize)>, // Name to (ip, arg count)
+    pub ops: usize,
+    pub async_steps: usize,
+    pub sync_duration: u64, // Milliseconds
+    pub max_mem_used: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telemetry: Option<JsonValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BytecodeEntry {
+    pub bytecode: Vec<JsonValue>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub globals: Option<JsonValue>,
+}
+
+/// A captured variable. Open upvalues still live on the stack, at `location`, while closed ones have
+/// outlived the frame that declared them, and carry their own value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpValue {
+    #[serde(rename = "__hogUpValue__", default = "marker")]
+    pub marker: bool,
+    pub id: usize,
+    pub location: i64,
+    pub closed: bool,
+    #[serde(default)]
+    pub value: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrameState {
+    pub closure: JsonValue,
+    pub ip: usize,
+    pub chunk: String,
+    pub stack_start: usize,
+    pub arg_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThrowFrameState {
+    pub call_stack_len: usize,
+    pub stack_len: usize,
+    pub catch_ip: usize,
+}
+
+fn legacy_version() -> u64 {
+    1
+}
+
+fn marker() -> bool {
+    true
+}
+
+impl VmState {
+    /// The program this state was suspended in, for constructing the context to resume it with.
+    pub fn program(&self) -> Result<Program, VmError> {
+        let bytecode = match (self.bytecodes.get(ROOT_CHUNK), &self.bytecode) {
+            (Some(root), _) => root.bytecode.clone(),
+            (None, Some(legacy)) => legacy.clone(),
+            (None, None) => {
+                return Err(VmError::InvalidBytecode(
+                    "VM state has no root bytecode".to_string(),
+                ))
+            }
+        };
+        Program::new(bytecode)
+    }
+
+    /// Push the result of the async function call the VM suspended on, ready for it to be resumed.
+    pub fn push_result(&mut self, result: JsonValue) {
+        self.stack.push(result);
+    }
+}
+
+fn chunk_name(symbol: &Option<Symbol>) -> String {
+    match symbol {
+        Some(symbol) => symbol.to_string(),
+        None => ROOT_CHUNK.to_string(),
+    }
+}
+
+fn parse_chunk(chunk: &str) -> Result<Option<Symbol>, VmError> {
+    if chunk == ROOT_CHUNK {
+        return Ok(None);
+    }
+    let Some((module, name)) = chunk.split_once('/') else {
+        return Err(VmError::UnknownSymbol(chunk.to_string()));
+    };
+    Ok(Some(Symbol::new(module, name)))
+}
+
+impl<'a> HogVM<'a> {
+    // Instruction pointers into the root program are relative to the end of its header, but the
+    // serialized format uses absolute ones, as the Node VM does
+    pub(crate) fn chunk_offset(&self, symbol: &Option<Symbol>) -> usize {
+        match symbol {
+            Some(_) => 0,
+            None => self.context.program().start_offset(),
+        }
+    }
+
+    /// Serialize the VM's full state - its stack, heap, call and throw frames - so that it can be
+    /// resumed later, by either this VM or the Node one.
+    pub fn to_state(&self) -> Result<VmState, VmError> {
+        let mut writer = StateWriter {
+            vm: self,
+            ids: HashMap::new(),
+            captured: Vec::new(),
+        };
+
+        let stack = self
+            .stack
+            .iter()
+            .map(|value| writer.value(value, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // The Node VM has an explicit frame for the root program, which we don't, and stores the
+        // instruction pointer of each frame in that frame, where we store it in the frame called from it
+        let position = |depth: usize| match self.stack_frames.get(depth) {
+            Some(callee) => (&callee.ret_symbol, callee.ret_ptr),
+            None => (&self.current_symbol, self.ip),
+        };
+        let mut call_stack = Vec::with_capacity(self.stack_frames.len() + 1);
+        let (symbol, ip) = position(0);
+        call_stack.push(CallFrameState {
+            closure: writer.closure(&None, 0, &[]),
+            ip: ip + self.chunk_offset(symbol),
+            chunk: chunk_name(symbol),
+            stack_start: 0,
+            arg_count: 0,
+        });
+        for (i, frame) in self.stack_frames.iter().enumerate() {
+            let (symbol, ip) = position(i + 1);
+            call_stack.push(CallFrameState {
+                closure: writer.closure(symbol, frame.arg_count, &frame.captures),
+                ip: ip + self.chunk_offset(symbol),
+                chunk: chunk_name(symbol),
+                stack_start: frame.stack_start,
+                arg_count: frame.arg_count,
+            });
+        }
+
+        let throw_stack = self
+            .throw_frames
+            .iter()
+            .map(|frame| ThrowFrameState {
+                call_stack_len: frame.call_depth + 1,
+                stack_len: frame.stack_start,
+                catch_ip: frame.catch_ptr + self.chunk_offset(&frame.catch_symbol),
+            })
+            .collect();
+
+        // Captured values which are still on the stack are open upvalues, and the rest are closed. Closed
+        // values can themselves hold closures, capturing more values, so we walk until we run out.
+        let mut open_locations = HashMap::new();
+        for (location, value) in self.stack.iter().enumerate().rev() {
+            if let HogValue::Ref(ptr) = value {
+                open_locations.insert(*ptr, location);
+            }
+        }
+        let mut upvalues = Vec::new();
+        let mut next = 0;
+        while let Some(ptr) = writer.captured.get(next).copied() {
+            next += 1;
+            let id = next;
+            let upvalue = match open_locations.get(&ptr) {
+                Some(location) => UpValue {
+                    marker: true,
+                    id,
+                    location: *location as i64,
+                    closed: false,
+                    value: JsonValue::Null,
+                },
+                // Closed upvalues never match a stack location again, so we give them an impossible one
+                None => UpValue {
+                    marker: true,
+                    id,
+                    location: -1,
+                    closed: true,
+                    value: writer.value(&ptr.into(), 0)?,
+                },
+            };
+            upvalues.push(upvalue);
+        }
+        upvalues.sort_by_key(|upvalue| upvalue.location);
+
+        let mut bytecodes = HashMap::new();
+        bytecodes.insert(
+            ROOT_CHUNK.to_string(),
+            BytecodeEntry {
+                bytecode: self.context.program().bytecode().to_vec(),
+                globals: None,
+            },
+        );
+        // The Node VM has its own copy of the hog stl, but any other modules have to travel with the state
+        for (symbol, function) in self.context.symbols() {
+            if symbol.module != "stl" {
+                let entry = BytecodeEntry {
+                    bytecode: function.bytecode().to_vec(),
+                    globals: None,
+                };
+                bytecodes.insert(symbol.to_string(), entry);
+            }
+        }
+
+        let root_offset = self.chunk_offset(&None);
+        let declared_functions = self
+            .declared_functions
+            .iter()
+            .map(|(name, f)| (name.clone(), (f.ip + root_offset, f.arg_count)))
+            .collect();
+
+        Ok(VmState {
+            version: VM_STATE_VERSION,
+            bytecodes,
+            bytecode: None,
+            stack,
+            upvalues,
+            call_stack,
+            throw_stack,
+            declared_functions,
+            ops: self.ops,
+            async_steps: self.async_steps,
+            sync_duration: self.sync_duration.as_millis() as u64,
+            max_mem_used: self.max_mem_used.max(self.heap.current_bytes),
+            telemetry: None,
+        })
+    }
+
+    /// Reconstruct a VM from a serialized state. The context should be constructed from the same program
+    /// the state was suspended in (see `VmState::program`).
+    pub fn from_state(context: &'a ExecutionContext, state: VmState) -> Result<Self, VmError> {
+        if state.version > VM_STATE_VERSION {
+            return Err(VmError::InvalidBytecode(format!(
+                "Unsupported VM state version {}",
+                state.version
+            )));
+        }
+        if let Ok(program) = state.program() {
+            if program.bytecode() != context.program().bytecode() {
+                return Err(VmError::InvalidBytecode(
+                    "VM state was suspended in a different program".to_string(),
+                ));
+            }
+        }
+
+        let mut vm = HogVM::new(context)?;
+
+        // Every upvalue gets a heap slot up front, so closures anywhere in the state can refer to them
+        // before we know their values
+        let mut upvalues = HashMap::new();
+        for upvalue in &state.upvalues {
+            upvalues.insert(upvalue.id, vm.heap.emplace(HogLiteral::Null)?);
+        }
+
+        let stack = state
+            .stack
+            .into_iter()
+            .map(|value| vm.value_from_state(value, &upvalues, 0))
+            .collect::<Result<Vec<_>, _>>()?;
+        vm.stack = stack;
+
+        for upvalue in state.upvalues {
+            let ptr = upvalues[&upvalue.id];
+            let value = if upvalue.closed {
+                vm.value_from_state(upvalue.value, &upvalues, 0)?
+            } else {
+                // Open upvalues are hoisted locals - the stack slot points to the captured heap slot
+                let location = usize::try_from(upvalue.location)
+                    .map_err(|_| VmError::StackIndexOutOfBounds)?;
+                let slot = vm
+                    .stack
+                    .get_mut(location)
+                    .ok_or(VmError::StackIndexOutOfBounds)?;
+                std::mem::replace(slot, ptr.into())
+            };
+            let value = value.deref(&vm.heap)?.clone();
+            *vm.heap.get_mut(ptr)? = value;
+        }
+
+        let positions = state
+            .call_stack
+            .iter()
+            .map(|frame| {
+                let symbol = parse_chunk(&frame.chunk)?;
+                let ip = frame.ip.saturating_sub(vm.chunk_offset(&symbol));
+                Ok((symbol, ip))
+            })
+            .collect::<Result<Vec<_>, VmError>>()?;
+
+        for (i, frame) in state.call_stack.iter().enumerate().skip(1) {
+            let (ret_symbol, ret_ptr) = positions[i - 1].clone();
+            let captures = vm.closure_captures(&frame.closure, &upvalues)?;
+            vm.stack_frames.push(CallFrame {
+                ret_ptr,
+                ret_symbol,
+                stack_start: frame.stack_start,
+                arg_count: frame.arg_count,
+                captures,
+            });
+        }
+        if let Some((symbol, ip)) = positions.last().cloned() {
+            vm.current_symbol = symbol;
+            vm.ip = ip;
+        }
+
+        for frame in &state.throw_stack {
+            let (catch_symbol, _) = positions
+                .get(frame.call_stack_len.saturating_sub(1))
+                .cloned()
+                .ok_or(VmError::NoFrame)?;
+            vm.throw_frames.push(ThrowFrame {
+                catch_ptr: frame
+                    .catch_ip
+                    .saturating_sub(vm.chunk_offset(&catch_symbol)),
+                catch_symbol,
+                stack_start: frame.stack_len,
+                call_depth: frame.call_stack_len.saturating_sub(1),
+            });
+        }
+
+        // The Node VM doesn't record which chunk a function was declared in, so we assume the root program
+        let root_offset = vm.chunk_offset(&None);
+        for (name, (ip, arg_count)) in state.declared_functions {
+            let declared = DeclaredFunction {
+                ip: ip.saturating_sub(root_offset),
+                symbol: None,
+                arg_count,
+            };
+            vm.declared_functions.insert(name, declared);
+        }
+
+        vm.ops = state.ops;
+        vm.async_steps = state.async_steps;
+        vm.sync_duration = Duration::from_millis(state.sync_duration);
+        vm.max_mem_used = state.max_mem_used;
+
+        Ok(vm)
+    }
+
+    // As json_to_hog, but understanding the serialized forms of callables and closures
+    fn value_from_state(
+        &mut self,
+        value: JsonValue,
+        upvalues: &HashMap<usize, HeapReference>,
+        depth: usize,
+    ) -> Result<HogValue, VmError> {
+        if depth > MAX_JSON_SERDE_DEPTH {
+            return Err(VmError::OutOfResource(
+                "state deserialization depth".to_string(),
+            ));
+        }
+
+        match value {
+            JsonValue::Array(items) => {
+                let items = items
+                    .into_iter()
+                    .map(|item| self.value_from_state(item, upvalues, depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(self.heap.emplace(HogLiteral::Array(items))?.into())
+            }
+            JsonValue::Object(map) if map.contains_key("__hogClosure__") => {
+                let value = JsonValue::Object(map);
+                let callable = self.callable_from_state(&value["callable"])?;
+                let captures = self.closure_captures(&value, upvalues)?;
+                Ok(HogLiteral::Closure(Closure { callable, captures }).into())
+            }
+            JsonValue::Object(map) if map.contains_key("__hogCallable__") => {
+                let callable = self.callable_from_state(&JsonValue::Object(map))?;
+                Ok(HogLiteral::Callable(callable).into())
+            }
+            JsonValue::Object(map) => {
+                let map = map
+                    .into_iter()
+                    .map(|(k, v)| Ok((k, self.value_from_state(v, upvalues, depth + 1)?)))
+                    .collect::<Result<HashMap<_, _>, VmError>>()?;
+                Ok(self.heap.emplace(HogLiteral::Object(map))?.into())
+            }
+            other => self.json_to_hog(other),
+        }
+    }
+
+    fn callable_from_state(&self, value: &JsonValue) -> Result<Callable, VmError> {
+        let kind = value["__hogCallable__"].as_str().unwrap_or_default();
+        if kind != "local" {
+            // The Node VM can reference stl and async functions as values, but we can't
+            return Err(VmError::NotImplemented(format!(
+                "Deserializing {kind} callables"
+            )));
+        }
+        let field = |key: &str| {
+            value[key]
+                .as_u64()
+                .map(|v| v as usize)
+                .ok_or_else(|| VmError::InvalidValue(format!("callable.{key}"), "usize".into()))
+        };
+        let symbol = parse_chunk(value["chunk"].as_str().unwrap_or(ROOT_CHUNK))?;
+        Ok(LocalCallable {
+            name: value["name"].as_str().unwrap_or_default().to_string(),
+            stack_arg_count: field("argCount")?,
+            capture_count: field("upvalueCount")?,
+            ip: field("ip")?.saturating_sub(self.chunk_offset(&symbol)),
+            symbol,
+        }
+        .into())
+    }
+
+    fn closure_captures(
+        &self,
+        closure: &JsonValue,
+        upvalues: &HashMap<usize, HeapReference>,
+    ) -> Result<Vec<HeapReference>, VmError> {
+        let Some(ids) = closure["upvalues"].as_array() else {
+            return Ok(Vec::new());
+        };
+        ids.iter()
+            .map(|id| {
+                id.as_u64()
+                    .and_then(|id| upvalues.get(&(id as usize)))
+                    .copied()
+                    .ok_or_else(|| VmError::UnknownSymbol(format!("upvalue {id}")))
+            })
+            .collect()
+    }
+}
+
+// Serializes values, assigning ids to the heap slots captured by closures along the way
+struct StateWriter<'a, 'b> {
+    vm: &'a HogVM<'b>,
+    ids: HashMap<HeapReference, usize>,
+    captured: Vec<HeapReference>,
+}
+
+impl StateWriter<'_, '_> {
+    fn upvalue_id(&mut self, ptr: HeapReference) -> usize {
+        if let Some(id) = self.ids.get(&ptr) {
+            return *id;
+        }
+        self.captured.push(ptr);
+        // Ids start at 1, as in the Node VM
+        let id = self.captured.len();
+        self.ids.insert(ptr, id);
+        id
+    }
+
+    fn callable(
+        &self,
+        symbol: &Option<Symbol>,
+        name: &str,
+        arg_count: usize,
+        capture_count: usize,
+        ip: usize,
+    ) -> JsonValue {
+        json!({
+            "__hogCallable__": "local",
+            "name": name,
+            "argCount": arg_count,
+            "upvalueCount": capture_count,
+            "ip": ip + self.vm.chunk_offset(symbol),
+            "chunk": chunk_name(symbol),
+        })
+    }
+
+    // Call frames don't record the callable they're executing, only what it captured, so the closure we
+    // write for each frame is only good for resolving upvalues, which is all the Node VM uses it for
+    fn closure(
+        &mut self,
+        symbol: &Option<Symbol>,
+        arg_count: usize,
+        captures: &[HeapReference],
+    ) -> JsonValue {
+        let upvalues: Vec<usize> = captures.iter().map(|ptr| self.upvalue_id(*ptr)).collect();
+        json!({
+            "__hogClosure__": true,
+            "callable": self.callable(symbol, "", arg_count, captures.len(), 0),
+            "upvalues": upvalues,
+        })
+    }
+
+    fn value(&mut self, value: &HogValue, depth: usize) -> Result<JsonValue, VmError> {
+        if depth > MAX_JSON_SERDE_DEPTH {
+            return Err(VmError::OutOfResource(
+                "state serialization depth".to_string(),
+            ));
+        }
+
+        match value.deref(&self.vm.heap)? {
+            HogLiteral::Array(items) => Ok(JsonValue::Array(
+                items
+                    .iter()
+                    .map(|item| self.value(item, depth + 1))
+                    .collect::<Result<_, _>>()?,
+            )),
+            HogLiteral::Object(map) => Ok(JsonValue::Object(
+                map.iter()
+                    .map(|(k, v)| Ok((k.clone(), self.value(v, depth + 1)?)))
+                    .collect::<Result<_, VmError>>()?,
+            )),
+            HogLiteral::Callable(Callable::Local(callable)) => Ok(self.callable(
+                &callable.symbol,
+                &callable.name,
+                callable.stack_arg_count,
+                callable.capture_count,
+                callable.ip,
+            )),
+            HogLiteral::Closure(closure) => {
+                let Callable::Local(callable) = &closure.callable;
+                let callable_json = self.callable(
+                    &callable.symbol,
+                    &callable.name,
+                    callable.stack_arg_count,
+                    callable.capture_count,
+                    callable.ip,
+                );
+                let upvalues: Vec<usize> = closure
+                    .captures
+                    .iter()
+                    .map(|ptr| self.upvalue_id(*ptr))
+                    .collect();
+                Ok(json!({
+                    "__hogClosure__": true,
+                    "callable": callable_json,
+                    "upvalues": upvalues,
+                }))
+            }
+            _ => self.vm.hog_to_json(value),
+        }
+    }
+}
@@ -1,7 +1,20 @@
 use core::str;
 use std::collections::HashMap;
 
+use base64::{
+    alphabet,
+    engine::{
+        general_purpose::{STANDARD as BASE64_STANDARD, URL_SAFE_NO_PAD as BASE64_URL_SAFE_NO_PAD},
+        DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig,
+    },
+    Engine,
+};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use hmac::{Hmac, Mac};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
 
 use crate::{
     construct_free_standing,
@@ -49,6 +62,12 @@ pub fn stl() -> Vec<(String, NativeFunction)> {
                 let arg = args[0].deref(&vm.heap)?;
                 // TODO - tuples, dates, datetimes, errors are all just duck-typed "objects" or "arrays", but we should
                 // still support them I guess
+                if as_datetime(&vm.heap, arg)?.is_some() {
+                    return Ok(HogLiteral::String("datetime".to_string()).into());
+                }
+                if as_date(&vm.heap, arg)?.is_some() {
+                    return Ok(HogLiteral::String("date".to_string()).into());
+                }
                 match arg {
                     HogLiteral::Number(_) => Ok(HogLiteral::String("number".to_string()).into()),
                     HogLiteral::Boolean(_) => Ok(HogLiteral::String("boolean".to_string()).into()),
@@ -297,16 +316,7 @@ pub fn stl() -> Vec<(String, NativeFunction)> {
             "notEmpty",
             native_func(|vm, args| {
                 assert_argc(&args, 1, "notEmpty")?;
-                let val = &args[0];
-                match val.deref(&vm.heap)? {
-                    HogLiteral::Array(a) => Ok(HogLiteral::Boolean(!a.is_empty()).into()),
-                    HogLiteral::String(s) => Ok(HogLiteral::Boolean(!s.is_empty()).into()),
-                    HogLiteral::Object(o) => Ok(HogLiteral::Boolean(!o.is_empty()).into()),
-                    _ => Err(VmError::NativeCallFailed(format!(
-                        "{} not supported by notEmpty",
-                        val.type_name()
-                    ))),
-                }
+                Ok((!is_empty(args[0].deref(&vm.heap)?)).into())
             }),
         ),
         (
@@ -378,6 +388,494 @@ pub fn stl() -> Vec<(String, NativeFunction)> {
                 Ok(HogLiteral::Number(0i64.into()).into())
             }),
         ),
+        // Strings
+        (
+            "lower",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "lower")?;
+                Ok(nullable_str(&vm.heap, &args[0])?
+                    .map_or(HogLiteral::Null, |s| s.to_lowercase().into())
+                    .into())
+            }),
+        ),
+        (
+            "upper",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "upper")?;
+                Ok(nullable_str(&vm.heap, &args[0])?
+                    .map_or(HogLiteral::Null, |s| s.to_uppercase().into())
+                    .into())
+            }),
+        ),
+        (
+            "reverse",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "reverse")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                Ok(HogLiteral::String(s.chars().rev().collect()).into())
+            }),
+        ),
+        (
+            "concat",
+            native_func(|vm, args| {
+                let mut res = String::new();
+                for arg in &args {
+                    if *arg.deref(&vm.heap)? != HogLiteral::Null {
+                        res.push_str(&to_string(&vm.heap, arg, 0)?);
+                    }
+                }
+                Ok(HogLiteral::String(res).into())
+            }),
+        ),
+        (
+            "like",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "like")?;
+                let (value, pattern) = str_args(&vm.heap, &args)?;
+                Ok(like_search(value, pattern, true)?.into())
+            }),
+        ),
+        (
+            "ilike",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "ilike")?;
+                let (value, pattern) = str_args(&vm.heap, &args)?;
+                Ok(like_search(value, pattern, false)?.into())
+            }),
+        ),
+        (
+            "notLike",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "notLike")?;
+                let (value, pattern) = str_args(&vm.heap, &args)?;
+                Ok((!like_search(value, pattern, true)?).into())
+            }),
+        ),
+        (
+            "notILike",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "notILike")?;
+                let (value, pattern) = str_args(&vm.heap, &args)?;
+                Ok((!like_search(value, pattern, false)?).into())
+            }),
+        ),
+        (
+            "replaceOne",
+            native_func(|vm, args| {
+                assert_argc(&args, 3, "replaceOne")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let from = args[1].deref(&vm.heap)?.try_as::<str>()?;
+                let to = args[2].deref(&vm.heap)?.try_as::<str>()?;
+                Ok(HogLiteral::String(s.replacen(from, to, 1)).into())
+            }),
+        ),
+        (
+            "replaceAll",
+            native_func(|vm, args| {
+                assert_argc(&args, 3, "replaceAll")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let from = args[1].deref(&vm.heap)?.try_as::<str>()?;
+                let to = args[2].deref(&vm.heap)?.try_as::<str>()?;
+                Ok(HogLiteral::String(s.replace(from, to)).into())
+            }),
+        ),
+        (
+            "position",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "position")?;
+                let HogLiteral::String(haystack) = args[0].deref(&vm.heap)? else {
+                    return Ok(0i64.into());
+                };
+                let needle = to_string(&vm.heap, &args[1], 0)?;
+                Ok(char_position(haystack, &needle).into())
+            }),
+        ),
+        (
+            "positionCaseInsensitive",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "positionCaseInsensitive")?;
+                let HogLiteral::String(haystack) = args[0].deref(&vm.heap)? else {
+                    return Ok(0i64.into());
+                };
+                let needle = to_string(&vm.heap, &args[1], 0)?.to_lowercase();
+                Ok(char_position(&haystack.to_lowercase(), &needle).into())
+            }),
+        ),
+        (
+            "trim",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "trim")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let Some(c) = trim_char(&vm.heap, &args)? else {
+                    return Ok(HogLiteral::String(String::new()).into());
+                };
+                Ok(HogLiteral::String(s.trim_matches(c).to_string()).into())
+            }),
+        ),
+        (
+            "trimLeft",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "trimLeft")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let Some(c) = trim_char(&vm.heap, &args)? else {
+                    return Ok(HogLiteral::String(String::new()).into());
+                };
+                Ok(HogLiteral::String(s.trim_start_matches(c).to_string()).into())
+            }),
+        ),
+        (
+            "trimRight",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "trimRight")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let Some(c) = trim_char(&vm.heap, &args)? else {
+                    return Ok(HogLiteral::String(String::new()).into());
+                };
+                Ok(HogLiteral::String(s.trim_end_matches(c).to_string()).into())
+            }),
+        ),
+        (
+            "splitByString",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 2, 3, "splitByString")?;
+                let separator = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let s = args[1].deref(&vm.heap)?.try_as::<str>()?;
+                let limit = match args.get(2).map(|a| a.deref(&vm.heap)).transpose()? {
+                    None | Some(HogLiteral::Null) => usize::MAX,
+                    Some(limit) => limit.try_as::<Num>()?.to_integer().max(0) as usize,
+                };
+                // An empty separator splits the string into its characters, as in JS
+                let parts: Vec<HogValue> = if separator.is_empty() {
+                    s.chars()
+                        .take(limit)
+                        .map(|c| HogLiteral::String(c.to_string()).into())
+                        .collect()
+                } else {
+                    s.split(separator)
+                        .take(limit)
+                        .map(|p| HogLiteral::String(p.to_string()).into())
+                        .collect()
+                };
+                Ok(HogLiteral::Array(parts).into())
+            }),
+        ),
+        (
+            "startsWith",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "startsWith")?;
+                match (args[0].deref(&vm.heap)?, args[1].deref(&vm.heap)?) {
+                    (HogLiteral::String(s), HogLiteral::String(prefix)) => {
+                        Ok(s.starts_with(prefix.as_str()).into())
+                    }
+                    _ => Ok(false.into()),
+                }
+            }),
+        ),
+        (
+            "substring",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 2, 3, "substring")?;
+                let HogLiteral::String(s) = args[0].deref(&vm.heap)? else {
+                    return Ok(HogLiteral::String(String::new()).into());
+                };
+                // Start is 1-based, and both start and length count characters, not bytes
+                let start = args[1].deref(&vm.heap)?.try_as::<Num>()?.to_integer() - 1;
+                let char_count = s.chars().count() as i64;
+                let length = match args.get(2).map(|a| a.deref(&vm.heap)).transpose()? {
+                    Some(HogLiteral::Number(n)) => n.to_integer(),
+                    _ => char_count - start,
+                };
+                if start < 0 || length < 0 || start >= char_count {
+                    return Ok(HogLiteral::String(String::new()).into());
+                }
+                let res = s.chars().skip(start as usize).take(length as usize);
+                Ok(HogLiteral::String(res.collect()).into())
+            }),
+        ),
+        (
+            "empty",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "empty")?;
+                Ok(is_empty(args[0].deref(&vm.heap)?).into())
+            }),
+        ),
+        (
+            "isNull",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "isNull")?;
+                Ok((*args[0].deref(&vm.heap)? == HogLiteral::Null).into())
+            }),
+        ),
+        (
+            "isNotNull",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "isNotNull")?;
+                Ok((*args[0].deref(&vm.heap)? != HogLiteral::Null).into())
+            }),
+        ),
+        (
+            "toInt",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "toInt")?;
+                let arg = args[0].deref(&vm.heap)?;
+                if let Some(datetime) = as_datetime(&vm.heap, arg)? {
+                    return Ok((datetime.dt.floor() as i64).into());
+                }
+                if let Some(date) = as_date(&vm.heap, arg)? {
+                    return Ok(days_since_epoch(date).into());
+                }
+                match arg {
+                    HogLiteral::Number(n) => Ok((n.to_float().trunc() as i64).into()),
+                    HogLiteral::String(s) => Ok(parse_int_prefix(s)
+                        .map_or(HogLiteral::Null, HogLiteral::from)
+                        .into()),
+                    _ => Ok(HogLiteral::Null.into()),
+                }
+            }),
+        ),
+        (
+            "toFloat",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "toFloat")?;
+                let arg = args[0].deref(&vm.heap)?;
+                if let Some(datetime) = as_datetime(&vm.heap, arg)? {
+                    return Ok(datetime.dt.into());
+                }
+                if let Some(date) = as_date(&vm.heap, arg)? {
+                    return Ok((days_since_epoch(date) as f64).into());
+                }
+                match arg {
+                    HogLiteral::Number(n) => Ok(n.to_float().into()),
+                    HogLiteral::String(s) => Ok(parse_float_prefix(s)
+                        .map_or(HogLiteral::Null, HogLiteral::from)
+                        .into()),
+                    _ => Ok(HogLiteral::Null.into()),
+                }
+            }),
+        ),
+        (
+            "base64Encode",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "base64Encode")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                Ok(HogLiteral::String(BASE64_STANDARD.encode(s)).into())
+            }),
+        ),
+        (
+            "base64Decode",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "base64Decode")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let bytes = BASE64_LENIENT
+                    .decode(s.trim_end_matches('='))
+                    .or_else(|_| BASE64_URL_SAFE_NO_PAD.decode(s.trim_end_matches('=')))
+                    .map_err(|e| VmError::NativeCallFailed(format!("Invalid base64: {e}")))?;
+                Ok(HogLiteral::String(String::from_utf8_lossy(&bytes).into_owned()).into())
+            }),
+        ),
+        (
+            "encodeURLComponent",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "encodeURLComponent")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                Ok(HogLiteral::String(utf8_percent_encode(s, URL_COMPONENT).to_string()).into())
+            }),
+        ),
+        (
+            "decodeURLComponent",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "decodeURLComponent")?;
+                let s = args[0].deref(&vm.heap)?.try_as::<str>()?;
+                let decoded = percent_decode_str(s)
+                    .decode_utf8()
+                    .map_err(|_| VmError::NativeCallFailed("URI malformed".to_string()))?;
+                Ok(HogLiteral::String(decoded.into_owned()).into())
+            }),
+        ),
+        // Math
+        (
+            "round",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "round")?;
+                match args[0].deref(&vm.heap)?.try_as::<Num>()? {
+                    Num::Integer(i) => Ok((*i).into()),
+                    // Rounds half up, like JS's Math.round, rather than half to even
+                    Num::Float(f) => Ok(float_to_num((f + 0.5).floor()).into()),
+                }
+            }),
+        ),
+        (
+            "floor",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "floor")?;
+                match args[0].deref(&vm.heap)?.try_as::<Num>()? {
+                    Num::Integer(i) => Ok((*i).into()),
+                    Num::Float(f) => Ok(float_to_num(f.floor()).into()),
+                }
+            }),
+        ),
+        // Dates
+        (
+            "now",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 0, 1, "now")?;
+                let zone = optional_zone(&vm.heap, &args, 0)?;
+                let dt = Utc::now().timestamp_millis() as f64 / 1000.0;
+                Ok(HogDateTime::new(dt, zone).into())
+            }),
+        ),
+        (
+            "toDateTime",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "toDateTime")?;
+                let zone = optional_zone(&vm.heap, &args, 1)?;
+                let dt = match args[0].deref(&vm.heap)? {
+                    HogLiteral::Number(n) => n.to_float(),
+                    HogLiteral::String(s) => parse_iso_timestamp(s, &parse_tz(&zone)?)?,
+                    other => {
+                        return Err(VmError::NativeCallFailed(format!(
+                            "toDateTime() expects a string or number, got {}",
+                            other.type_name()
+                        )))
+                    }
+                };
+                Ok(HogDateTime::new(dt, zone).into())
+            }),
+        ),
+        (
+            "toDate",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "toDate")?;
+                let date = match args[0].deref(&vm.heap)? {
+                    HogLiteral::Number(n) => {
+                        timestamp_to_datetime(n.to_float(), &Tz::UTC)?.date_naive()
+                    }
+                    HogLiteral::String(s) => parse_iso_date(s)?,
+                    other => {
+                        return Err(VmError::NativeCallFailed(format!(
+                            "toDate() expects a string or number, got {}",
+                            other.type_name()
+                        )))
+                    }
+                };
+                Ok(hog_date(date).into())
+            }),
+        ),
+        (
+            "fromUnixTimestamp",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "fromUnixTimestamp")?;
+                let dt = args[0].deref(&vm.heap)?.try_as::<Num>()?.to_float();
+                Ok(HogDateTime::new(dt, "UTC".to_string()).into())
+            }),
+        ),
+        (
+            "fromUnixTimestampMilli",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "fromUnixTimestampMilli")?;
+                let millis = args[0].deref(&vm.heap)?.try_as::<Num>()?.to_float();
+                Ok(HogDateTime::new(millis / 1000.0, "UTC".to_string()).into())
+            }),
+        ),
+        (
+            "toUnixTimestamp",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "toUnixTimestamp")?;
+                Ok(to_unix_timestamp(&vm.heap, &args)?.into())
+            }),
+        ),
+        (
+            "toUnixTimestampMilli",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "toUnixTimestampMilli")?;
+                let secs = to_unix_timestamp(&vm.heap, &args)?;
+                Ok(((secs * 1000.0).round() as i64).into())
+            }),
+        ),
+        (
+            "toTimeZone",
+            native_func(|vm, args| {
+                assert_argc(&args, 2, "toTimeZone")?;
+                let Some(datetime) = as_datetime(&vm.heap, args[0].deref(&vm.heap)?)? else {
+                    return Err(VmError::NativeCallFailed(
+                        "toTimeZone() expects a DateTime".to_string(),
+                    ));
+                };
+                let zone = args[1].deref(&vm.heap)?.try_as::<str>()?;
+                parse_tz(zone)?;
+                Ok(HogDateTime::new(datetime.dt, zone.to_string()).into())
+            }),
+        ),
+        (
+            "formatDateTime",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 2, 3, "formatDateTime")?;
+                let Some(datetime) = as_datetime(&vm.heap, args[0].deref(&vm.heap)?)? else {
+                    return Err(VmError::NativeCallFailed(
+                        "formatDateTime() expects a DateTime".to_string(),
+                    ));
+                };
+                let format = args[1].deref(&vm.heap)?.try_as::<str>()?;
+                let zone = match args.get(2).map(|a| a.deref(&vm.heap)).transpose()? {
+                    None | Some(HogLiteral::Null) => datetime.zone.clone(),
+                    Some(zone) => zone.try_as::<str>()?.to_string(),
+                };
+                let dt = timestamp_to_datetime(datetime.dt, &parse_tz(&zone)?)?;
+                Ok(HogLiteral::String(format_datetime(&dt, format)).into())
+            }),
+        ),
+        // Crypto
+        (
+            "md5Hex",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "md5Hex")?;
+                hash_nullable(&vm.heap, &args, "hex", |data| md5::compute(data).0.to_vec())
+            }),
+        ),
+        (
+            "md5",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "md5")?;
+                let encoding = digest_encoding(&vm.heap, &args, 1)?;
+                hash_nullable(&vm.heap, &args, encoding, |data| {
+                    md5::compute(data).0.to_vec()
+                })
+            }),
+        ),
+        (
+            "sha256Hex",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "sha256Hex")?;
+                hash_nullable(&vm.heap, &args, "hex", |data| Sha256::digest(data).to_vec())
+            }),
+        ),
+        (
+            "sha256",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "sha256")?;
+                let encoding = digest_encoding(&vm.heap, &args, 1)?;
+                hash_nullable(&vm.heap, &args, encoding, |data| {
+                    Sha256::digest(data).to_vec()
+                })
+            }),
+        ),
+        (
+            "sha256HmacChainHex",
+            native_func(|vm, args| {
+                assert_argc(&args, 1, "sha256HmacChainHex")?;
+                let digest = sha256_hmac_chain(&vm.heap, &args[0])?;
+                encode_digest(&digest, "hex").map(|s| HogLiteral::String(s).into())
+            }),
+        ),
+        (
+            "sha256HmacChain",
+            native_func(|vm, args| {
+                assert_argc_range(&args, 1, 2, "sha256HmacChain")?;
+                let encoding = digest_encoding(&vm.heap, &args, 1)?;
+                let digest = sha256_hmac_chain(&vm.heap, &args[0])?;
+                encode_digest(&digest, encoding).map(|s| HogLiteral::String(s).into())
+            }),
+        ),
     ]
     .into_iter()
     .map(|(name, func)| (name.to_string(), func))
@@ -433,6 +931,12 @@ fn to_string(heap: &VmHeap, val: &HogValue, depth: usize) -> Result<String, VmEr
                 .join(", ")
         )),
         HogLiteral::Object(hash_map) => {
+            if let Some(datetime) = as_datetime(heap, val)? {
+                return datetime_to_iso(&datetime);
+            }
+            if let Some(date) = as_date(heap, val)? {
+                return Ok(date.format("%Y-%m-%d").to_string());
+            }
             let mut entries = Vec::new();
             for (key, value) in hash_map {
                 entries.push(format!("{}: {}", key, to_string(heap, value, depth + 1)?));
@@ -463,6 +967,23 @@ fn assert_argc(args: &[HogValue], count: usize, name: impl AsRef<str>) -> Result
     )
 }
 
+fn assert_argc_range(
+    args: &[HogValue],
+    min: usize,
+    max: usize,
+    name: impl AsRef<str>,
+) -> Result<(), VmError> {
+    assert(
+        (min..=max).contains(&args.len()),
+        format!(
+            "{} takes between {} and {} arguments",
+            name.as_ref(),
+            min,
+            max
+        ),
+    )
+}
+
 fn err_to_null(
     func: impl Fn(&HogVM, Vec<HogValue>) -> Result<HogValue, VmError>,
 ) -> impl Fn(&HogVM, Vec<HogValue>) -> Result<HogValue, VmError> {
@@ -476,3 +997,477 @@ where
 {
     Box::new(func)
 }
+
+// String functions in the reference implementations pass nulls through, rather than failing
+fn nullable_str<'a>(heap: &'a VmHeap, val: &'a HogValue) -> Result<Option<&'a str>, VmError> {
+    match val.deref(heap)? {
+        HogLiteral::Null => Ok(None),
+        other => other.try_as::<str>().map(Some),
+    }
+}
+
+fn str_args<'a>(heap: &'a VmHeap, args: &'a [HogValue]) -> Result<(&'a str, &'a str), VmError> {
+    Ok((
+        args[0].deref(heap)?.try_as::<str>()?,
+        args[1].deref(heap)?.try_as::<str>()?,
+    ))
+}
+
+fn is_empty(val: &HogLiteral) -> bool {
+    match val {
+        HogLiteral::Null => true,
+        HogLiteral::String(s) => s.is_empty(),
+        HogLiteral::Array(a) => a.is_empty(),
+        HogLiteral::Object(o) => o.is_empty(),
+        _ => false,
+    }
+}
+
+// 1-based character (not byte) position of needle in haystack, or 0 if it isn't found
+fn char_position(haystack: &str, needle: &str) -> i64 {
+    haystack
+        .find(needle)
+        .map_or(0, |i| haystack[..i].chars().count() as i64 + 1)
+}
+
+// Matches the characters to trim: the one the caller passed, a space if they passed null, or any
+// whitespace if they passed nothing. Returns None if the caller passed something other than a
+// single character, in which case the result is an empty string.
+fn trim_char(heap: &VmHeap, args: &[HogValue]) -> Result<Option<impl Fn(char) -> bool>, VmError> {
+    let trimmed = match args.get(1).map(|a| a.deref(heap)).transpose()? {
+        None => None,
+        Some(HogLiteral::Null) => Some(' '),
+        Some(c) => {
+            let mut chars = c.try_as::<str>()?.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => return Ok(None),
+            }
+        }
+    };
+    Ok(Some(move |c: char| match trimmed {
+        Some(trimmed) => c == trimmed,
+        None => c.is_whitespace(),
+    }))
+}
+
+// Parses the leading integer of a string, ignoring anything after it, like JS's parseInt
+fn parse_int_prefix(s: &str) -> Option<i64> {
+    let s = s.trim_start();
+    let end = s
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && (c == '-' || c == '+'))))
+        .map_or(s.len(), |(i, _)| i);
+    s[..end].parse().ok()
+}
+
+// As above, for floats, like JS's parseFloat
+fn parse_float_prefix(s: &str) -> Option<f64> {
+    let s = s.trim_start();
+    s.char_indices()
+        .map(|(i, c)| i + c.len_utf8())
+        .rev()
+        .find_map(|end| s[..end].parse::<f64>().ok().filter(|f| f.is_finite()))
+}
+
+fn float_to_num(f: f64) -> HogLiteral {
+    if f.is_finite() && f.abs() < i64::MAX as f64 {
+        (f as i64).into()
+    } else {
+        f.into()
+    }
+}
+
+fn like_search(value: &str, pattern: &str, case_sensitive: bool) -> Result<bool, VmError> {
+    // Unlike the LIKE operator, the reference implementations of these functions match anywhere in
+    // the string, rather than requiring the whole string to match
+    let pattern = regex::escape(pattern).replace('%', ".*").replace('_', ".");
+    regex_match(value, pattern, case_sensitive)
+}
+
+// Matches JS's encodeURIComponent, which leaves A-Z a-z 0-9 - _ . ! ~ * ' ( ) unescaped
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'!')
+    .remove(b'~')
+    .remove(b'*')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')');
+
+// Accepts input with or without padding, as node's Buffer does
+const BASE64_LENIENT: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+// Dates and datetimes are represented as objects, with a marker key, so they can be passed in and
+// out of the VM as json, in the same shape as the other implementations use.
+const DATETIME_MARKER: &str = "__hogDateTime__";
+const DATE_MARKER: &str = "__hogDate__";
+
+struct HogDateTime {
+    dt: f64, // Seconds since the epoch
+    zone: String,
+}
+
+impl HogDateTime {
+    fn new(dt: f64, zone: String) -> Self {
+        Self { dt, zone }
+    }
+}
+
+impl From<HogDateTime> for HogValue {
+    fn from(datetime: HogDateTime) -> Self {
+        let mut map = HashMap::new();
+        map.insert(DATETIME_MARKER.to_string(), true.into());
+        map.insert("dt".to_string(), datetime.dt.into());
+        map.insert("zone".to_string(), HogLiteral::String(datetime.zone).into());
+        HogLiteral::Object(map).into()
+    }
+}
+
+fn hog_date(date: NaiveDate) -> HogLiteral {
+    let mut map = HashMap::new();
+    map.insert(DATE_MARKER.to_string(), true.into());
+    map.insert("year".to_string(), (date.year() as i64).into());
+    map.insert("month".to_string(), (date.month() as i64).into());
+    map.insert("day".to_string(), (date.day() as i64).into());
+    HogLiteral::Object(map)
+}
+
+fn has_marker(heap: &VmHeap, map: &HashMap<String, HogValue>, marker: &str) -> bool {
+    map.get(marker)
+        .and_then(|v| v.deref(heap).ok())
+        .is_some_and(|v| *v == HogLiteral::Boolean(true))
+}
+
+fn as_datetime(heap: &VmHeap, val: &HogLiteral) -> Result<Option<HogDateTime>, VmError> {
+    let HogLiteral::Object(map) = val else {
+        return Ok(None);
+    };
+    if !has_marker(heap, map, DATETIME_MARKER) {
+        return Ok(None);
+    }
+    let dt = match map.get("dt") {
+        Some(dt) => dt.deref(heap)?.try_as::<Num>()?.to_float(),
+        None => {
+            return Err(VmError::NativeCallFailed(
+                "DateTime is missing dt".to_string(),
+            ))
+        }
+    };
+    let zone = match map.get("zone").map(|z| z.deref(heap)).transpose()? {
+        Some(HogLiteral::String(zone)) => zone.clone(),
+        _ => "UTC".to_string(),
+    };
+    Ok(Some(HogDateTime::new(dt, zone)))
+}
+
+fn as_date(heap: &VmHeap, val: &HogLiteral) -> Result<Option<NaiveDate>, VmError> {
+    let HogLiteral::Object(map) = val else {
+        return Ok(None);
+    };
+    if !has_marker(heap, map, DATE_MARKER) {
+        return Ok(None);
+    }
+    let part = |key: &str| -> Result<i64, VmError> {
+        match map.get(key) {
+            Some(v) => Ok(v.deref(heap)?.try_as::<Num>()?.to_integer()),
+            None => Err(VmError::NativeCallFailed(format!("Date is missing {key}"))),
+        }
+    };
+    let (year, month, day) = (part("year")?, part("month")?, part("day")?);
+    NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32)
+        .map(Some)
+        .ok_or_else(|| VmError::NativeCallFailed(format!("Invalid date {year}-{month}-{day}")))
+}
+
+fn days_since_epoch(date: NaiveDate) -> i64 {
+    (date - NaiveDate::default()).num_days()
+}
+
+fn parse_tz(zone: &str) -> Result<Tz, VmError> {
+    zone.parse()
+        .map_err(|_| VmError::NativeCallFailed(format!("Unknown time zone {zone}")))
+}
+
+fn optional_zone(heap: &VmHeap, args: &[HogValue], index: usize) -> Result<String, VmError> {
+    match args.get(index).map(|a| a.deref(heap)).transpose()? {
+        None | Some(HogLiteral::Null) => Ok("UTC".to_string()),
+        Some(zone) => {
+            let zone = zone.try_as::<str>()?;
+            parse_tz(zone)?;
+            Ok(zone.to_string())
+        }
+    }
+}
+
+// Timestamps are handled at millisecond precision, as they are in the other implementations
+fn timestamp_to_datetime(dt: f64, tz: &Tz) -> Result<DateTime<Tz>, VmError> {
+    DateTime::from_timestamp_millis((dt * 1000.0).round() as i64)
+        .map(|dt| dt.with_timezone(tz))
+        .ok_or_else(|| VmError::NativeCallFailed(format!("Timestamp {dt} out of range")))
+}
+
+fn datetime_to_iso(datetime: &HogDateTime) -> Result<String, VmError> {
+    let dt = timestamp_to_datetime(datetime.dt, &parse_tz(&datetime.zone)?)?;
+    let format = if datetime.zone == "UTC" {
+        "%Y-%m-%dT%H:%M:%S%.3fZ"
+    } else {
+        "%Y-%m-%dT%H:%M:%S%.3f%:z"
+    };
+    Ok(dt.format(format).to_string())
+}
+
+const NAIVE_DATETIME_FORMATS: [&str; 3] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S%.f",
+];
+
+// Parses an ISO 8601 string into seconds since the epoch. Strings without an offset are taken to
+// be in the given time zone.
+fn parse_iso_timestamp(s: &str, tz: &Tz) -> Result<f64, VmError> {
+    let to_seconds = |millis: i64| millis as f64 / 1000.0;
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(to_seconds(dt.timestamp_millis()));
+    }
+    let naive = NAIVE_DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(NaiveTime::MIN))
+        })
+        .ok_or_else(|| VmError::NativeCallFailed(format!("Invalid ISO 8601 date {s}")))?;
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| to_seconds(dt.timestamp_millis()))
+        .ok_or_else(|| VmError::NativeCallFailed(format!("{s} does not exist in {tz}")))
+}
+
+// The calendar date of an ISO 8601 string, as written
+fn parse_iso_date(s: &str) -> Result<NaiveDate, VmError> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Ok(dt.date_naive());
+    }
+    NAIVE_DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .map(|dt| dt.date())
+        .or_else(|| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok())
+        .ok_or_else(|| VmError::NativeCallFailed(format!("Invalid ISO 8601 date {s}")))
+}
+
+fn to_unix_timestamp(heap: &VmHeap, args: &[HogValue]) -> Result<f64, VmError> {
+    let zone = optional_zone(heap, args, 1)?;
+    let arg = args[0].deref(heap)?;
+    if let Some(datetime) = as_datetime(heap, arg)? {
+        return Ok(datetime.dt);
+    }
+    if let Some(date) = as_date(heap, arg)? {
+        let midnight = date.and_time(NaiveTime::MIN);
+        return parse_tz(&zone)?
+            .from_local_datetime(&midnight)
+            .earliest()
+            .map(|dt| dt.timestamp() as f64)
+            .ok_or_else(|| VmError::NativeCallFailed(format!("{date} does not exist in {zone}")));
+    }
+    parse_iso_timestamp(arg.try_as::<str>()?, &parse_tz(&zone)?)
+}
+
+// Formats a datetime using ClickHouse's formatDateTime syntax. Where the reference implementations
+// disagree on a token (%e, %f, %w), we follow the typescript one.
+fn format_datetime(dt: &DateTime<Tz>, format: &str) -> String {
+    let mut res = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue;
+        }
+        let Some(token) = chars.next() else {
+            break;
+        };
+        let spec = match token {
+            'a' => "%a",
+            'b' => "%b",
+            'c' | 'm' => "%m",
+            'C' | 'g' | 'y' => "%y",
+            'd' => "%d",
+            'D' => "%m/%d/%y",
+            'F' => "%Y-%m-%d",
+            'G' | 'Y' => "%Y",
+            'h' | 'I' | 'l' => "%I",
+            'H' | 'k' => "%H",
+            'i' => "%M",
+            'j' => "%j",
+            'M' => "%B",
+            'n' => "%n",
+            'p' => "%p",
+            'r' => "%I:%M %p",
+            'R' => "%H:%M",
+            's' | 'S' => "%S",
+            't' => "%t",
+            'T' => "%H:%M:%S",
+            'u' | 'w' => "%u",
+            'V' => "%V",
+            'W' => "%A",
+            'z' => "%z",
+            '%' => "%%",
+            'e' => {
+                res.push_str(&dt.day().to_string());
+                continue;
+            }
+            'f' => {
+                res.push_str(&format!("{:03}", dt.timestamp_subsec_millis()));
+                continue;
+            }
+            'Q' => {
+                res.push_str(&dt.month0().div_euclid(3).saturating_add(1).to_string());
+                continue;
+            }
+            // Unknown tokens are dropped, as they are in the reference implementations
+            _ => continue,
+        };
+        res.push_str(&dt.format(spec).to_string());
+    }
+    res
+}
+
+fn digest_encoding<'a>(
+    heap: &'a VmHeap,
+    args: &'a [HogValue],
+    index: usize,
+) -> Result<&'a str, VmError> {
+    match args.get(index).map(|a| a.deref(heap)).transpose()? {
+        None | Some(HogLiteral::Null) => Ok("hex"),
+        Some(encoding) => encoding.try_as::<str>(),
+    }
+}
+
+fn encode_digest(digest: &[u8], encoding: &str) -> Result<String, VmError> {
+    match encoding {
+        "hex" => Ok(digest.iter().map(|b| format!("{b:02x}")).collect()),
+        "base64" => Ok(BASE64_STANDARD.encode(digest)),
+        "base64url" => Ok(BASE64_URL_SAFE_NO_PAD.encode(digest)),
+        // Each byte as the latin-1 character with that code point, as node does
+        "binary" => Ok(digest.iter().map(|b| *b as char).collect()),
+        _ => Err(VmError::NativeCallFailed(format!(
+            "Unsupported digest encoding {encoding}"
+        ))),
+    }
+}
+
+fn hash_nullable(
+    heap: &VmHeap,
+    args: &[HogValue],
+    encoding: &str,
+    hash: impl Fn(&[u8]) -> Vec<u8>,
+) -> Result<HogValue, VmError> {
+    let Some(data) = nullable_str(heap, &args[0])? else {
+        return Ok(HogLiteral::Null.into());
+    };
+    encode_digest(&hash(data.as_bytes()), encoding).map(|s| HogLiteral::String(s).into())
+}
+
+// HMACs the second element with the first as the key, then each following element with the
+// previous digest as the key
+fn sha256_hmac_chain(heap: &VmHeap, data: &HogValue) -> Result<Vec<u8>, VmError> {
+    let HogLiteral::Array(items) = data.deref(heap)? else {
+        return Err(VmError::NativeCallFailed(
+            "sha256HmacChain() expects an array".to_string(),
+        ));
+    };
+    assert(
+        items.len() >= 2,
+        "sha256HmacChain() requires at least two elements",
+    )?;
+
+    let hmac = |key: &[u8], item: &HogValue| -> Result<Vec<u8>, VmError> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(item.deref(heap)?.try_as::<str>()?.as_bytes());
+        Ok(mac.finalize().into_bytes().to_vec())
+    };
+
+    let key = items[0].deref(heap)?.try_as::<str>()?;
+    let mut digest = hmac(key.as_bytes(), &items[1])?;
+    for item in &items[2..] {
+        digest = hmac(&digest, item)?;
+    }
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prefixes() {
+        assert_eq!(parse_int_prefix("42abc"), Some(42));
+        assert_eq!(parse_int_prefix("  -7.9"), Some(-7));
+        assert_eq!(parse_int_prefix("abc"), None);
+        assert_eq!(parse_float_prefix("3.5e2xyz"), Some(350.0));
+        assert_eq!(parse_float_prefix("-.5"), Some(-0.5));
+        assert_eq!(parse_float_prefix("inf"), None);
+    }
+
+    #[test]
+    fn test_char_position() {
+        assert_eq!(char_position("héllo", "llo"), 3);
+        assert_eq!(char_position("hello", "x"), 0);
+        assert_eq!(char_position("hello", ""), 1);
+    }
+
+    #[test]
+    fn test_like_search_is_unanchored() {
+        assert!(like_search("hello world", "lo w", true).unwrap());
+        assert!(like_search("hello world", "h_llo%", true).unwrap());
+        assert!(!like_search("hello world", "HELLO", true).unwrap());
+        assert!(like_search("hello world", "HELLO", false).unwrap());
+        assert!(!like_search("hello", "h.llo", true).unwrap());
+    }
+
+    #[test]
+    fn test_format_datetime_follows_typescript() {
+        // 2024-01-05T03:04:05.123Z, a Friday
+        let dt = timestamp_to_datetime(1704423845.123, &Tz::UTC).unwrap();
+        assert_eq!(format_datetime(&dt, "%e|%f|%w|%u|%Q"), "5|123|5|5|1");
+        assert_eq!(
+            format_datetime(&dt, "%Y-%m-%d %H:%i:%S"),
+            "2024-01-05 03:04:05"
+        );
+        assert_eq!(format_datetime(&dt, "100%% %Xdone"), "100% done");
+    }
+
+    #[test]
+    fn test_encode_digest() {
+        let digest = [0x00, 0xff, 0x10];
+        assert_eq!(encode_digest(&digest, "hex").unwrap(), "00ff10");
+        assert_eq!(encode_digest(&digest, "base64").unwrap(), "AP8Q");
+        assert_eq!(
+            encode_digest(&digest, "binary").unwrap(),
+            "\u{0}\u{ff}\u{10}"
+        );
+        assert!(encode_digest(&digest, "latin1").is_err());
+    }
+
+    #[test]
+    fn test_datetime_round_trip() {
+        let datetime = HogDateTime::new(1704423845.5, "Europe/London".to_string());
+        assert_eq!(
+            datetime_to_iso(&datetime).unwrap(),
+            "2024-01-05T03:04:05.500+00:00"
+        );
+        let tz = parse_tz("America/New_York").unwrap();
+        assert_eq!(
+            parse_iso_timestamp("2024-01-04 22:04:05.5", &tz).unwrap(),
+            1704423845.5
+        );
+        assert!(parse_tz("Mars/Olympus_Mons").is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use std::{any::Any, collections::HashMap};
+use std::{
+    any::Any,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use serde::de::DeserializeOwned;
 use serde_json::Value as JsonValue;
@@ -8,6 +12,7 @@ use crate::{
     error::VmError,
     memory::{HeapReference, VmHeap},
     ops::Operation,
+    state::VmState,
     util::{get_json_nested, like, regex_match},
     values::{Callable, Closure, FromHogLiteral, HogLiteral, HogValue, LocalCallable, Num, NumOp},
 };
@@ -40,31 +45,46 @@ pub struct HogVM<'a> {
     /// The heap of the virtual machine. Generally used for `HogValue::deref`, to allow you to access
     /// `HogLiteral` values for native function implementation.
     pub heap: VmHeap, // Needs to be pub to allow users to write their own extension native functions
-    stack: Vec<HogValue>,
+    pub(crate) stack: Vec<HogValue>,
 
-    stack_frames: Vec<CallFrame>,
-    throw_frames: Vec<ThrowFrame>,
-    ip: usize,
+    pub(crate) stack_frames: Vec<CallFrame>,
+    pub(crate) throw_frames: Vec<ThrowFrame>,
+    pub(crate) ip: usize,
 
-    context: &'a ExecutionContext,
+    pub(crate) context: &'a ExecutionContext,
     // The base program is None, but calling into e.g. hog standard library functions involves changing the "module"
     // the pointer is currently pointing into to e.g. "arrayExists", as part of the function call that branches into
     // that function.
-    current_symbol: Option<Symbol>,
+    pub(crate) current_symbol: Option<Symbol>,
+    // Functions declared with the legacy DeclareFn operation, which are called by name via CallGlobal
+    pub(crate) declared_functions: HashMap<String, DeclaredFunction>,
+
+    // Execution statistics, carried across suspensions as part of the serialized VM state
+    pub(crate) ops: usize,
+    pub(crate) async_steps: usize,
+    pub(crate) sync_duration: Duration,
+    pub(crate) max_mem_used: usize,
+}
+
+pub(crate) struct DeclaredFunction {
+    pub(crate) ip: usize,
+    pub(crate) symbol: Option<Symbol>,
+    pub(crate) arg_count: usize,
 }
 
-struct CallFrame {
-    ret_ptr: usize,               // Where to jump back to when we're done
-    ret_symbol: Option<Symbol>,   // The module to return to when we're done
-    stack_start: usize,           // Point in the stack the frame values start
-    captures: Vec<HeapReference>, // Values captured from the parent scope/frame
+pub(crate) struct CallFrame {
+    pub(crate) ret_ptr: usize, // Where to jump back to when we're done
+    pub(crate) ret_symbol: Option<Symbol>, // The module to return to when we're done
+    pub(crate) stack_start: usize, // Point in the stack the frame values start
+    pub(crate) arg_count: usize, // The number of arguments the called function takes
+    pub(crate) captures: Vec<HeapReference>, // Values captured from the parent scope/frame
 }
 
-struct ThrowFrame {
-    catch_ptr: usize,             // The ptr to jump to if we throw
-    catch_symbol: Option<Symbol>, // The module to return to if we throw
-    stack_start: usize,           // The stack size when we entered the try
-    call_depth: usize,            // The depth of the call stack when we entered the try
+pub(crate) struct ThrowFrame {
+    pub(crate) catch_ptr: usize,             // The ptr to jump to if we throw
+    pub(crate) catch_symbol: Option<Symbol>, // The module to return to if we throw
+    pub(crate) stack_start: usize,           // The stack size when we entered the try
+    pub(crate) call_depth: usize,            // The depth of the call stack when we entered the try
 }
 
 impl<'a> HogVM<'a> {
@@ -75,11 +95,51 @@ impl<'a> HogVM<'a> {
             throw_frames: Vec::new(),
             ip: 0,
             current_symbol: None,
+            declared_functions: HashMap::new(),
             context,
             heap: VmHeap::new(context.max_heap_size),
+            ops: 0,
+            async_steps: 0,
+            sync_duration: Duration::ZERO,
+            max_mem_used: 0,
         })
     }
 
+    /// The instruction pointer, relative to the start of the executing module function or program body.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// The instruction pointer as an index into the executing chunk's full bytecode, including the
+    /// top level program's header. This is the convention serialized VM states use.
+    pub fn absolute_ip(&self) -> usize {
+        self.ip + self.chunk_offset(&self.current_symbol)
+    }
+
+    /// The module function currently executing, or None for the top level program.
+    pub fn current_symbol(&self) -> Option<&Symbol> {
+        self.current_symbol.as_ref()
+    }
+
+    pub fn stack(&self) -> &[HogValue] {
+        &self.stack
+    }
+
+    /// The stack values belonging to the current call frame - its arguments, followed by its locals.
+    pub fn locals(&self) -> &[HogValue] {
+        &self.stack[self.current_frame_base().min(self.stack.len())..]
+    }
+
+    pub fn call_depth(&self) -> usize {
+        self.stack_frames.len()
+    }
+
+    /// The operation the next step will execute, without executing it.
+    pub fn peek_op(&self) -> Result<Operation, VmError> {
+        let next = self.context.get_bytecode(self.ip, &self.current_symbol)?;
+        Operation::try_from(next.clone())
+    }
+
     /// Step the virtual machine, writing some debug information to the provided output function.
     pub fn debug_step(&mut self, output: &dyn Fn(String)) -> Result<StepOutcome, VmError> {
         let op: Operation = self.next()?;
@@ -102,6 +162,7 @@ impl<'a> HogVM<'a> {
     /// Step the virtual machine one cycle.
     pub fn step(&mut self) -> Result<StepOutcome, VmError> {
         let op: Operation = self.next()?;
+        self.ops += 1;
 
         match op {
             Operation::GetGlobal => {
@@ -133,22 +194,48 @@ impl<'a> HogVM<'a> {
                     return Err(VmError::UnknownGlobal(format!("{chain:?}")));
                 }
             }
-            // We don't implement DeclareFn, because it's not used in the current compiler - it uses
-            // "callables" constructed on the stack, which are then called by constructing a "closure",
-            // which is basically a function call that wraps a "callable" and populates it's argument list
-            Operation::DeclareFn => return Err(VmError::NotImplemented("DeclareFn".to_string())),
+            // DeclareFn is the legacy way of declaring a function - the current compiler uses "callables"
+            // constructed on the stack, which are then called by constructing a "closure", but older
+            // bytecode still declares functions by name, and calls them with CallGlobal
+            Operation::DeclareFn => {
+                let name: String = self.next()?;
+                let arg_count: usize = self.next()?;
+                let body_length: usize = self.next()?;
+                let declared = DeclaredFunction {
+                    ip: self.ip,
+                    symbol: self.current_symbol.clone(),
+                    arg_count,
+                };
+                self.declared_functions.insert(name, declared);
+                self.ip = self
+                    .ip
+                    .checked_add(body_length)
+                    .ok_or(VmError::IntegerOverflow)?;
+            }
             Operation::CallGlobal => {
                 // The TS impl here has a bunch of special case handling for functions with particular names.
                 // I'm hoping I can simplify that here by unifying the native call interface a bit
                 let name: String = self.next()?;
                 let arg_count: usize = self.next()?;
-                // NOTE - the TS implementation has a clause here that looks for the name in the
-                // "declared functions" - basically, the legacy way of declaring a function before Callable
-                // and closures were introduced. We leave it out because, as above, DeclareFn isn't supported
                 let available_args = self.stack.len() - self.current_frame_base();
                 if available_args < arg_count {
                     return Err(VmError::NotEnoughArguments(name, available_args, arg_count));
                 }
+                // As in the TS impl, declared functions shadow everything but toString
+                if name != "toString" && self.declared_functions.contains_key(&name) {
+                    return self.prep_declared_call(&name, arg_count);
+                }
+                if name == "import" {
+                    if arg_count != 1 {
+                        return Err(VmError::InvalidCall(
+                            "import requires exactly 1 argument".to_string(),
+                        ));
+                    }
+                    let module: String = self.pop_stack_as()?;
+                    let exports = self.import(&module)?;
+                    self.push_stack(exports)?;
+                    return Ok(StepOutcome::Continue);
+                }
                 let symbol = Symbol::new("stl", &name);
                 if self.context.has_symbol(&symbol) {
                     // Cross module calls are done in a manner very similar to CallLocal, just with some
@@ -280,9 +367,17 @@ impl<'a> HogVM<'a> {
                 let (val, pat): (String, String) = (self.pop_stack_as()?, self.pop_stack_as()?);
                 self.push_stack(!regex_match(val, pat, false)?)?;
             }
-            Operation::InCohort => return Err(VmError::NotImplemented("InCohort".to_string())),
+            // Cohort membership can't be determined from the program or its globals, so we defer to
+            // whatever resolver the caller configured on the context
+            Operation::InCohort => {
+                let (subject, cohort) = (self.pop_stack()?, self.pop_stack()?);
+                let res = self.context.in_cohort(self, &subject, &cohort)?;
+                self.push_stack(res)?;
+            }
             Operation::NotInCohort => {
-                return Err(VmError::NotImplemented("NotInCohort".to_string()))
+                let (subject, cohort) = (self.pop_stack()?, self.pop_stack()?);
+                let res = self.context.in_cohort(self, &subject, &cohort)?;
+                self.push_stack(!res)?;
             }
             Operation::True => {
                 self.push_stack(true)?;
@@ -489,9 +584,10 @@ impl<'a> HogVM<'a> {
             Operation::Try => {
                 // i32 to permit setting a catch offset lower than the IP
                 let catch_offset: i32 = self.next()?;
-                // +1 to skip the POP_TRY that follows the try'd operations
-                let catch_ip = (self.ip as i64)
-                    .checked_add(catch_offset as i64 + 1)
+                // As in the other impls, the offset is relative to the offset operand itself, rather than
+                // to the following operation
+                let catch_ip = (self.ip as i64 - 1)
+                    .checked_add(catch_offset as i64)
                     .ok_or(VmError::IntegerOverflow)? as usize;
                 let frame = ThrowFrame {
                     catch_ptr: catch_ip,
@@ -614,6 +710,7 @@ impl<'a> HogVM<'a> {
                     ret_ptr: self.ip,
                     ret_symbol: self.current_symbol.clone(),
                     stack_start: self.stack.len().saturating_sub(callable.stack_arg_count),
+                    arg_count: callable.stack_arg_count,
                     captures: closure.captures,
                 };
                 self.stack_frames.push(frame);
@@ -763,10 +860,38 @@ impl<'a> HogVM<'a> {
         Ok(())
     }
 
-    // TODO - we don't support function imports right now - most trivial programs don't need them,
-    // and filters are generally trivial
-    fn get_fn_reference(&self, _chain: &[HogValue]) -> Result<HogLiteral, VmError> {
-        Err(VmError::NotImplemented("imports".to_string()))
+    // Returns a closure referencing a hog module function, for e.g. passing a hog stl function as an
+    // argument. A single element chain refers to the hog stl, and a two element one to a function in
+    // a module added to the context. Native functions can't be referenced this way.
+    fn get_fn_reference(&self, chain: &[HogValue]) -> Result<HogLiteral, VmError> {
+        let names = chain
+            .iter()
+            .map(|v| v.deref(&self.heap)?.try_as::<str>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let symbol = match names.as_slice() {
+            [name] => Symbol::new("stl", name),
+            [module, name] => Symbol::new(module, name),
+            _ => return Err(VmError::UnknownSymbol(names.join("."))),
+        };
+        let function = self.context.get_symbol(&symbol)?;
+        Ok(module_fn_closure(symbol, function.arg_count()))
+    }
+
+    // Importing a module constructs an object mapping each of its exported function names to a closure
+    // that calls into the module, so `import('module').fn(args)` is just a property lookup and local call
+    fn import(&mut self, module: &str) -> Result<HeapReference, VmError> {
+        let exports = self
+            .context
+            .module_exports(module)
+            .ok_or_else(|| VmError::UnknownModule(module.to_string()))?;
+        let exports = exports
+            .into_iter()
+            .map(|(symbol, function)| {
+                let closure = module_fn_closure(symbol.clone(), function.arg_count());
+                (symbol.name.clone(), closure.into())
+            })
+            .collect();
+        self.heap.emplace(HogLiteral::Object(exports))
     }
 
     fn clone_stack_item(&self, idx: usize) -> Result<HogValue, VmError> {
@@ -800,6 +925,7 @@ impl<'a> HogVM<'a> {
             ret_ptr: self.ip,
             ret_symbol: self.current_symbol.clone(),
             stack_start: self.stack.len().saturating_sub(to_call.arg_count()),
+            arg_count: to_call.arg_count(),
             captures: Vec::new(), // Cross module calls never involve captures
         };
 
@@ -810,6 +936,35 @@ impl<'a> HogVM<'a> {
         Ok(StepOutcome::Continue)
     }
 
+    fn prep_declared_call(&mut self, name: &str, arg_count: usize) -> Result<StepOutcome, VmError> {
+        // Declared functions are called just like local callables, and never have captures
+        let Some(declared) = self.declared_functions.get(name) else {
+            return Err(VmError::UnknownFunction(name.to_string()));
+        };
+        let (ip, symbol, expected_args) =
+            (declared.ip, declared.symbol.clone(), declared.arg_count);
+        if arg_count > expected_args {
+            return Err(VmError::InvalidCall(format!(
+                "Too many args - expected {expected_args}, got {arg_count}"
+            )));
+        }
+        for _ in 0..expected_args.saturating_sub(arg_count) {
+            self.push_stack(HogLiteral::Null)?;
+        }
+        let frame = CallFrame {
+            ret_ptr: self.ip,
+            ret_symbol: self.current_symbol.clone(),
+            stack_start: self.stack.len().saturating_sub(expected_args),
+            arg_count: expected_args,
+            captures: Vec::new(),
+        };
+        self.stack_frames.push(frame);
+        self.current_symbol = symbol;
+        self.ip = ip;
+
+        Ok(StepOutcome::Continue)
+    }
+
     // Construct a hog value from a Json object. If the json object would be heap allocated
     // as a HogValue (e.g. if it's an array or object), then it will be allocated onto the heap,
     // and a reference will be returned. Nested json objects are flattened during allocation,
@@ -899,24 +1054,54 @@ impl<'a> HogVM<'a> {
     }
 }
 
-// Helper function to simply run a program until it either finishes or returns an error
-pub fn sync_execute(context: &ExecutionContext, print_debug: bool) -> Result<JsonValue, VmFailure> {
-    let fail = |e, vm: Option<&HogVM>, step: usize| VmFailure {
-        error: e,
+fn module_fn_closure(symbol: Symbol, arg_count: usize) -> HogLiteral {
+    let callable = LocalCallable {
+        name: symbol.name.clone(),
+        stack_arg_count: arg_count,
+        capture_count: 0,
+        ip: 0,
+        symbol: Some(symbol),
+    };
+    HogLiteral::Closure(Closure {
+        callable: callable.into(),
+        captures: Vec::new(),
+    })
+}
+
+/// The outcome of running a program until it either finishes, or suspends on an async function call.
+#[derive(Debug, Clone)]
+pub enum ExecOutcome {
+    /// The program has completed, returning a value
+    Finished(JsonValue),
+    /// The program has called an async function. The caller should run it, push its result onto the
+    /// state with `VmState::push_result`, and then resume execution by passing the state back in.
+    Suspended {
+        function: String,
+        args: Vec<JsonValue>,
+        state: Box<VmState>,
+    },
+}
+
+fn failure(error: VmError, vm: Option<&HogVM>, step: usize) -> VmFailure {
+    VmFailure {
+        error,
         ip: vm.map_or(0, |vm| vm.ip),
         stack: vm.map_or(Vec::new(), |vm| vm.stack.clone()),
         step,
-    };
+    }
+}
 
-    let mut vm = HogVM::new(context).map_err(|e| fail(e, None, 0))?;
+// Helper function to simply run a program until it either finishes or returns an error
+pub fn sync_execute(context: &ExecutionContext, print_debug: bool) -> Result<JsonValue, VmFailure> {
+    let mut vm = HogVM::new(context).map_err(|e| failure(e, None, 0))?;
 
     let mut i = 0;
     while i < context.max_steps {
         let res = if print_debug {
             vm.debug_step(&|s| println!("{s}"))
-                .map_err(|e| fail(e, Some(&vm), i))?
+                .map_err(|e| failure(e, Some(&vm), i))?
         } else {
-            vm.step().map_err(|e| fail(e, Some(&vm), i))?
+            vm.step().map_err(|e| failure(e, Some(&vm), i))?
         };
 
         match res {
@@ -925,7 +1110,7 @@ pub fn sync_execute(context: &ExecutionContext, print_debug: bool) -> Result<Jso
             StepOutcome::NativeCall(name, args) => {
                 match context.execute_native_function_call(&mut vm, &name, args) {
                     Ok(_) => {}
-                    Err(err) => return Err(fail(err, Some(&vm), i)),
+                    Err(err) => return Err(failure(err, Some(&vm), i)),
                 };
             }
         }
@@ -934,7 +1119,57 @@ pub fn sync_execute(context: &ExecutionContext, print_debug: bool) -> Result<Jso
 
     let err = VmError::OutOfResource("steps".to_string());
 
-    Err(fail(err, Some(&vm), i))
+    Err(failure(err, Some(&vm), i))
+}
+
+/// Run a program, or resume a suspended one from its state, until it either finishes or calls one of
+/// the context's async functions. Unlike `sync_execute`, the step limit applies across all of a
+/// program's resumptions, not each one.
+pub fn execute(
+    context: &ExecutionContext,
+    state: Option<VmState>,
+) -> Result<ExecOutcome, VmFailure> {
+    let started = Instant::now();
+    let mut vm = match state {
+        Some(state) => HogVM::from_state(context, state),
+        None => HogVM::new(context),
+    }
+    .map_err(|e| failure(e, None, 0))?;
+
+    while vm.ops < context.max_steps {
+        let res = vm.step().map_err(|e| failure(e, Some(&vm), vm.ops))?;
+        match res {
+            StepOutcome::Continue => {}
+            StepOutcome::Finished(res) => return Ok(ExecOutcome::Finished(res)),
+            StepOutcome::NativeCall(name, args) if context.is_async_fn(&name) => {
+                if vm.async_steps >= context.max_async_steps {
+                    let err = VmError::OutOfResource("async steps".to_string());
+                    return Err(failure(err, Some(&vm), vm.ops));
+                }
+                let args = args
+                    .iter()
+                    .map(|arg| vm.hog_to_json(arg))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| failure(e, Some(&vm), vm.ops))?;
+                vm.async_steps += 1;
+                vm.sync_duration += started.elapsed();
+                let state = vm.to_state().map_err(|e| failure(e, Some(&vm), vm.ops))?;
+                return Ok(ExecOutcome::Suspended {
+                    function: name,
+                    args,
+                    state: Box::new(state),
+                });
+            }
+            StepOutcome::NativeCall(name, args) => {
+                if let Err(err) = context.execute_native_function_call(&mut vm, &name, args) {
+                    return Err(failure(err, Some(&vm), vm.ops));
+                }
+            }
+        }
+    }
+
+    let err = VmError::OutOfResource("steps".to_string());
+    Err(failure(err, Some(&vm), vm.ops))
 }
 
 fn next_type_name(next: &JsonValue) -> String {
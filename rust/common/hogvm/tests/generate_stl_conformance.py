@@ -0,0 +1,195 @@
+#!/usr/bin/env python3
+"""
+Regenerates tests/static/stl_conformance.jsonl by running each case below through the reference (python)
+HogVM standard library. Run from the repo root:
+
+    python3 rust/common/hogvm/tests/generate_stl_conformance.py
+
+Only cases where the python and typescript implementations agree belong here - where they differ, the rust
+VM follows typescript, and the behaviour is covered by unit tests in src/stl.rs instead.
+"""
+
+import os
+import sys
+import json
+
+sys.path.insert(0, os.getcwd())
+
+from common.hogvm.python.stl import STL  # noqa: E402
+
+DT = {"__hogDateTime__": True, "dt": 1704164645.123, "zone": "UTC"}  # 2024-01-02T03:04:05.123Z
+DT_LONDON_SUMMER = {"__hogDateTime__": True, "dt": 1720000000.0, "zone": "Europe/London"}
+DATE = {"__hogDate__": True, "year": 2024, "month": 3, "day": 9}
+
+CASES = [
+    # Strings
+    ("lower", ["Hello World"]),
+    ("lower", ["ÀÉÎ"]),
+    ("lower", [None]),
+    ("upper", ["Hello World"]),
+    ("reverse", ["hello"]),
+    ("reverse", ["añb"]),
+    ("concat", ["a", "b", "c"]),
+    ("concat", ["a", None, "c"]),
+    ("concat", ["n: ", 1, " ", True]),
+    ("like", ["banana", "N"]),
+    ("like", ["banana", "n"]),
+    ("like", ["banana", "naan"]),
+    ("like", ["banana", "b%a"]),
+    ("like", ["banana", "b_n"]),
+    ("like", ["a.c", "a.c"]),
+    ("like", ["abc", "a.c"]),
+    ("ilike", ["banana", "N"]),
+    ("ilike", ["BANANA", "%nan%"]),
+    ("notLike", ["banana", "N"]),
+    ("notILike", ["banana", "NO"]),
+    ("replaceOne", ["hello hello", "hello", "bye"]),
+    ("replaceOne", ["hello", "x", "y"]),
+    ("replaceAll", ["hello hello", "hello", "bye"]),
+    ("replaceAll", ["a.b.c", ".", "-"]),
+    ("position", ["abc", "a"]),
+    ("position", ["abc", "c"]),
+    ("position", ["abc", "d"]),
+    ("position", ["añbc", "b"]),
+    ("position", ["a1b", 1]),
+    ("positionCaseInsensitive", ["AbC", "b"]),
+    ("positionCaseInsensitive", ["AbC", "d"]),
+    ("trim", ["xxhixx", "x"]),
+    ("trim", ["xxxx  hello  world  xx", "x"]),
+    ("trim", ["xx", "x"]),
+    ("trim", ["  hi  ", "xy"]),
+    ("trimLeft", ["xxxx  hello  world  xx", "x"]),
+    ("trimRight", ["xxxx  hello  world  xx", "x"]),
+    ("trim", ["  hello  world  "]),
+    ("trimLeft", ["  hello world  "]),
+    ("trimRight", ["  hello world  "]),
+    ("trim", ["\t\n hello world \r\n"]),
+    ("trimLeft", ["\n\thello world\n"]),
+    ("trimRight", ["\thello world \n"]),
+    ("trim", ["  hi\n ", None]),
+    ("splitByString", [" ", "hello world and more"]),
+    ("splitByString", [" ", "hello world and more", 1]),
+    ("splitByString", [" ", "hello world and more", 2]),
+    ("splitByString", [" ", "hello world and more", 10]),
+    ("splitByString", [", ", "a, b, c"]),
+    ("splitByString", [",", "abc"]),
+    ("startsWith", ["hello", "he"]),
+    ("startsWith", ["hello", "lo"]),
+    ("startsWith", [1, "1"]),
+    ("substring", ["hello world", 1, 5]),
+    ("substring", ["hello world", 7]),
+    ("substring", ["hello world", 7, 100]),
+    ("substring", ["hello", 0, 2]),
+    ("substring", ["hello", 10, 2]),
+    ("substring", ["añbc", 2, 2]),
+    ("substring", [None, 1, 2]),
+    ("empty", [""]),
+    ("empty", ["a"]),
+    ("empty", [None]),
+    ("empty", [[]]),
+    ("empty", [[1]]),
+    ("empty", [{}]),
+    ("empty", [0]),
+    ("empty", [False]),
+    ("notEmpty", [""]),
+    ("notEmpty", ["a"]),
+    ("notEmpty", [0]),
+    ("isNull", [None]),
+    ("isNull", ["a"]),
+    ("isNotNull", [None]),
+    ("isNotNull", [0]),
+    ("toInt", ["42"]),
+    ("toInt", [" 42"]),
+    ("toInt", ["-7"]),
+    ("toInt", ["nope"]),
+    ("toInt", [3.9]),
+    ("toInt", [-3.9]),
+    ("toInt", [DT]),
+    ("toInt", [DATE]),
+    ("toFloat", ["1.5"]),
+    ("toFloat", ["-2"]),
+    ("toFloat", ["nope"]),
+    ("toFloat", [3]),
+    ("toFloat", [DT]),
+    ("toString", [DT]),
+    ("toString", [DT_LONDON_SUMMER]),
+    ("toString", [DATE]),
+    ("base64Encode", ["hello world"]),
+    ("base64Encode", ["añb"]),
+    ("base64Encode", [""]),
+    ("base64Decode", ["aGVsbG8gd29ybGQ="]),
+    ("base64Decode", ["YcOxYg=="]),
+    ("encodeURLComponent", ["hello world"]),
+    ("encodeURLComponent", ["a=b&c=d/e?f"]),
+    ("encodeURLComponent", ["añb"]),
+    ("encodeURLComponent", ["AZaz09-_.~"]),
+    ("decodeURLComponent", ["hello%20world"]),
+    ("decodeURLComponent", ["a%3Db%26c%3Dd%2Fe%3Ff"]),
+    ("decodeURLComponent", ["a%C3%B1b"]),
+    # Numbers
+    ("round", [1.4]),
+    ("round", [1.6]),
+    ("round", [-1.6]),
+    ("round", [3]),
+    ("floor", [1.9]),
+    ("floor", [-1.1]),
+    ("floor", [7]),
+    # Dates
+    ("toDateTime", ["2024-01-02T03:04:05Z"]),
+    ("toDateTime", ["2024-01-02T03:04:05.5Z"]),
+    ("toDateTime", ["2024-01-02T03:04:05+02:00"]),
+    ("toDateTime", [1704164645]),
+    ("toDateTime", [1704164645.5]),
+    ("toDate", ["2024-03-09"]),
+    ("toDate", ["2024-03-09T23:00:00"]),
+    ("fromUnixTimestamp", [1704164645]),
+    ("fromUnixTimestamp", [1704164645.123]),
+    ("fromUnixTimestampMilli", [1704164645123]),
+    ("toUnixTimestamp", [DT]),
+    ("toUnixTimestamp", ["2024-01-02T03:04:05Z"]),
+    ("toUnixTimestamp", ["2024-01-02T03:04:05+02:00"]),
+    ("toUnixTimestampMilli", [{"__hogDateTime__": True, "dt": 1704164645.5, "zone": "UTC"}]),
+    ("toUnixTimestampMilli", ["2024-01-02T03:04:05Z"]),
+    ("toTimeZone", [DT, "America/New_York"]),
+    ("formatDateTime", [DT, "%Y-%m-%d %H:%i:%S"]),
+    ("formatDateTime", [DT, "%F %T"]),
+    ("formatDateTime", [DT, "%D %R"]),
+    ("formatDateTime", [DT, "%a %b %d, %W %M"]),
+    ("formatDateTime", [DT, "%I:%i %p (%h %l) %r"]),
+    ("formatDateTime", [DT, "%y %C %g %G %j %u %V"]),
+    ("formatDateTime", [DT, "%k %s %z %%"]),
+    ("formatDateTime", [DT, "%H:%i", "America/New_York"]),
+    ("formatDateTime", [DT_LONDON_SUMMER, "%Y-%m-%d %H:%i:%S %z"]),
+    ("formatDateTime", [DT, "at %H:%i sharp"]),
+    # Crypto
+    ("md5Hex", ["this is a secure string"]),
+    ("md5Hex", [None]),
+    ("md5", ["this is a secure string", "hex"]),
+    ("md5", ["this is a secure string", "base64"]),
+    ("md5", ["this is a secure string", "base64url"]),
+    ("md5", ["this is a secure string", "binary"]),
+    ("md5", [None, "hex"]),
+    ("sha256Hex", ["this is a secure string"]),
+    ("sha256Hex", [None]),
+    ("sha256", ["this is a secure string", "hex"]),
+    ("sha256", ["this is a secure string", "base64"]),
+    ("sha256", ["this is a secure string", "base64url"]),
+    ("sha256", ["this is a secure string", "binary"]),
+    ("sha256HmacChainHex", [["1", "string", "more", "keys"]]),
+    ("sha256HmacChain", [["1", "string", "more", "keys"], "hex"]),
+    ("sha256HmacChain", [["1", "string", "more", "keys"], "base64"]),
+    ("sha256HmacChain", [["key", "value"], "base64url"]),
+]
+
+
+def main():
+    out_path = os.path.join(os.path.dirname(__file__), "static", "stl_conformance.jsonl")
+    with open(out_path, "w") as out:
+        for name, args in CASES:
+            result = STL[name].fn(json.loads(json.dumps(args)), None, [], 5)
+            out.write(json.dumps({"fn": name, "args": args, "result": result}, ensure_ascii=False) + "\n")
+    print(f"Wrote {len(CASES)} cases to {out_path}")
+
+
+if __name__ == "__main__":
+    main()
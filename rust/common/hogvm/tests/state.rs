@@ -0,0 +1,152 @@
+use hogvm::{execute, ExecOutcome, ExecutionContext, Program, VmError, VmState};
+use serde_json::{json, Value};
+
+fn context(bytecode: Value) -> ExecutionContext {
+    let program = Program::new(serde_json::from_value(bytecode).unwrap()).unwrap();
+    ExecutionContext::with_defaults(program).with_async_fns(["fetch".to_string()])
+}
+
+fn suspend(ctx: &ExecutionContext, state: Option<VmState>) -> (String, Vec<Value>, VmState) {
+    match execute(ctx, state).unwrap() {
+        ExecOutcome::Suspended {
+            function,
+            args,
+            state,
+        } => (function, args, *state),
+        ExecOutcome::Finished(res) => panic!("Expected suspension, finished with {res}"),
+    }
+}
+
+// Round trips the state through json, as it would be stored between job runs
+fn resume(ctx: &ExecutionContext, state: VmState, result: Value) -> Value {
+    let serialized = serde_json::to_string(&state).unwrap();
+    let mut state: VmState = serde_json::from_str(&serialized).unwrap();
+    state.push_result(result);
+    match execute(ctx, Some(state)).unwrap() {
+        ExecOutcome::Finished(res) => res,
+        ExecOutcome::Suspended { function, .. } => panic!("Unexpected suspension on {function}"),
+    }
+}
+
+#[test]
+pub fn test_suspend_and_resume() {
+    // let a := 1; let r := fetch('x'); return r + a
+    let bytecode = json!(["_H", 1, 33, 1, 32, "x", 2, "fetch", 1, 36, 1, 36, 0, 6, 38]);
+    let ctx = context(bytecode.clone());
+
+    let (function, args, state) = suspend(&ctx, None);
+    assert_eq!(function, "fetch");
+    assert_eq!(args, vec![json!("x")]);
+    assert_eq!(state.stack, vec![json!(1)]);
+    assert_eq!(state.async_steps, 1);
+    // Instruction pointers are absolute, including the header, as in the Node VM
+    assert_eq!(state.call_stack.len(), 1);
+    assert_eq!(state.call_stack[0].chunk, "root");
+    assert_eq!(state.call_stack[0].ip, 9);
+    assert_eq!(
+        state.program().unwrap().bytecode(),
+        bytecode.as_array().unwrap()
+    );
+
+    assert_eq!(resume(&ctx, state, json!(41)), json!(42));
+}
+
+#[test]
+pub fn test_resume_with_captured_values() {
+    // let x := 1; fn inc() { x := x + 1 }; fetch(); inc(); return x
+    let bytecode = json!([
+        "_H", 1, 33, 1, 52, "inc", 0, 1, 9, 55, 0, 33, 1, 6, 56, 0, 31, 38, 53, 1, true, 0, 2,
+        "fetch", 0, 36, 1, 54, 0, 35, 36, 0, 38
+    ]);
+    let ctx = context(bytecode);
+
+    let (_, _, state) = suspend(&ctx, None);
+    assert_eq!(state.upvalues.len(), 1);
+    assert_eq!(state.upvalues[0].location, 0);
+    assert!(!state.upvalues[0].closed);
+    assert_eq!(state.stack[1]["__hogClosure__"], json!(true));
+    assert_eq!(state.stack[1]["upvalues"], json!([state.upvalues[0].id]));
+    assert_eq!(state.stack[1]["callable"]["ip"], json!(9));
+
+    // The closure still writes through to the captured local after resuming
+    assert_eq!(resume(&ctx, state, Value::Null), json!(2));
+}
+
+#[test]
+pub fn test_suspend_in_function_call() {
+    // fn f(a) { return fetch(a) + 1 }; return f(5) * 2
+    let bytecode = json!([
+        "_H", 1, 52, "f", 1, 0, 9, 36, 0, 2, "fetch", 1, 33, 1, 6, 38, 53, 0, 33, 5, 36, 0, 54, 1,
+        33, 2, 8, 38
+    ]);
+    let ctx = context(bytecode);
+
+    let (_, args, state) = suspend(&ctx, None);
+    assert_eq!(args, vec![json!(5)]);
+    // Each frame records where it's executing - the root frame is waiting on the return from f
+    assert_eq!(state.call_stack.len(), 2);
+    assert_eq!(state.call_stack[0].ip, 24);
+    assert_eq!(state.call_stack[1].ip, 12);
+    assert_eq!(state.call_stack[1].stack_start, 1);
+    assert_eq!(state.call_stack[1].arg_count, 1);
+
+    assert_eq!(resume(&ctx, state, json!(10)), json!(22));
+}
+
+#[test]
+pub fn test_resume_node_state() {
+    // A state as the Node VM would write it, suspended on the fetch in test_suspend_and_resume
+    let bytecode = json!(["_H", 1, 33, 1, 32, "x", 2, "fetch", 1, 36, 1, 36, 0, 6, 38]);
+    let state = json!({
+        "bytecodes": { "root": { "bytecode": bytecode } },
+        "stack": [1],
+        "upvalues": [],
+        "callStack": [{
+            "ip": 9,
+            "chunk": "root",
+            "stackStart": 0,
+            "argCount": 0,
+            "closure": {
+                "__hogClosure__": true,
+                "callable": {
+                    "__hogCallable__": "local",
+                    "name": "",
+                    "argCount": 0,
+                    "upvalueCount": 0,
+                    "ip": 1,
+                    "chunk": "root"
+                },
+                "upvalues": []
+            }
+        }],
+        "throwStack": [],
+        "declaredFunctions": {},
+        "ops": 4,
+        "asyncSteps": 1,
+        "syncDuration": 3,
+        "maxMemUsed": 64
+    });
+    let state: VmState = serde_json::from_value(state).unwrap();
+    let ctx = context(bytecode);
+    assert_eq!(resume(&ctx, state, json!(41)), json!(42));
+}
+
+#[test]
+pub fn test_async_step_limit() {
+    // fetch(); fetch()
+    let bytecode = json!(["_H", 1, 2, "fetch", 0, 35, 2, "fetch", 0, 38]);
+    let ctx = context(bytecode).with_max_async_steps(1);
+
+    let (_, _, mut state) = suspend(&ctx, None);
+    state.push_result(Value::Null);
+    let err = execute(&ctx, Some(state)).unwrap_err();
+    assert!(matches!(err.error, VmError::OutOfResource(r) if r == "async steps"));
+}
+
+#[test]
+pub fn test_rejects_state_from_other_program() {
+    let (_, _, state) = suspend(&context(json!(["_H", 1, 2, "fetch", 0, 38])), None);
+    let other = context(json!(["_H", 1, 2, "fetch", 0, 35, 29, 38]));
+    let err = execute(&other, Some(state)).unwrap_err();
+    assert!(matches!(err.error, VmError::InvalidBytecode(_)));
+}
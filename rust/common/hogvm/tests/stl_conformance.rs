@@ -0,0 +1,74 @@
+use hogvm::{sync_execute, ExecutionContext, Program};
+use serde_json::{json, Value};
+
+// Cases are generated from the python reference implementation by generate_stl_conformance.py.
+// Each one calls a single stl function with literal arguments, and records the result.
+fn load_cases() -> Vec<(String, Vec<Value>, Value)> {
+    let path = std::env::current_dir()
+        .unwrap()
+        .join("tests/static/stl_conformance.jsonl");
+    std::fs::read_to_string(path)
+        .expect("Could read conformance cases")
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let case: Value = serde_json::from_str(line).unwrap();
+            let name = case["fn"].as_str().unwrap().to_string();
+            let args = case["args"].as_array().unwrap().clone();
+            (name, args, case["result"].clone())
+        })
+        .collect()
+}
+
+fn push_literal(bytecode: &mut Vec<Value>, value: &Value) {
+    match value {
+        Value::Null => bytecode.push(json!(31)),
+        Value::Bool(true) => bytecode.push(json!(29)),
+        Value::Bool(false) => bytecode.push(json!(30)),
+        Value::Number(n) if n.is_f64() => bytecode.extend([json!(34), value.clone()]),
+        Value::Number(_) => bytecode.extend([json!(33), value.clone()]),
+        Value::String(_) => bytecode.extend([json!(32), value.clone()]),
+        Value::Array(items) => {
+            items.iter().for_each(|item| push_literal(bytecode, item));
+            bytecode.extend([json!(43), json!(items.len())]);
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                bytecode.extend([json!(32), json!(key)]);
+                push_literal(bytecode, item);
+            }
+            bytecode.extend([json!(42), json!(map.len())]);
+        }
+    }
+}
+
+// Integers and floats are interchangeable in hog, so compare all numbers as floats
+fn normalize(value: Value) -> Value {
+    match value {
+        Value::Number(n) => json!(n.as_f64().unwrap()),
+        Value::Array(items) => Value::Array(items.into_iter().map(normalize).collect()),
+        Value::Object(map) => {
+            Value::Object(map.into_iter().map(|(k, v)| (k, normalize(v))).collect())
+        }
+        other => other,
+    }
+}
+
+#[test]
+pub fn test_stl_conformance() {
+    let mut failures = Vec::new();
+    for (name, args, expected) in load_cases() {
+        let mut bytecode = vec![json!("_H"), json!(1)];
+        args.iter().for_each(|arg| push_literal(&mut bytecode, arg));
+        bytecode.extend([json!(2), json!(name), json!(args.len()), json!(38)]);
+
+        let ctx = ExecutionContext::with_defaults(Program::new(bytecode).unwrap());
+        match sync_execute(&ctx, false) {
+            Ok(res) if normalize(res.clone()) == normalize(expected.clone()) => {}
+            res => failures.push(format!(
+                "{name}({args:?}): expected {expected}, got {res:?}"
+            )),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 
-use hogvm::{native_func, sync_execute, ExecutionContext, HogLiteral, NativeFunction, Program};
+use hogvm::{
+    native_func, sync_execute, ExecutionContext, ExportedFunction, HogLiteral, Module,
+    NativeFunction, Program, VmError,
+};
 use serde_json::{json, Value};
 
 fn stl_test_extensions() -> HashMap<String, NativeFunction> {
@@ -105,3 +108,88 @@ pub fn test_vm() {
         assert!(matches!(res, Ok(Value::Bool(true))))
     }
 }
+
+fn run(bytecode: Value, ctx: impl FnOnce(ExecutionContext) -> ExecutionContext) -> Value {
+    let program = Program::new(serde_json::from_value(bytecode).unwrap()).unwrap();
+    let ctx = ctx(ExecutionContext::with_defaults(program));
+    sync_execute(&ctx, false).unwrap()
+}
+
+#[test]
+pub fn test_declared_functions() {
+    // fn add(a, b) { return a + b }; return add(1, 2) + add(3, 4)
+    let bytecode = json!([
+        "_H", 1, 41, "add", 2, 6, 36, 0, 36, 1, 6, 38, 33, 1, 33, 2, 2, "add", 2, 33, 3, 33, 4, 2,
+        "add", 2, 6, 38
+    ]);
+    assert_eq!(run(bytecode, |ctx| ctx), json!(10));
+}
+
+#[test]
+pub fn test_module_imports() {
+    let mut math = Module::new();
+    math.add_function(
+        "double".to_string(),
+        ExportedFunction::new(
+            1,
+            serde_json::from_value(json!([36, 0, 36, 0, 6, 38])).unwrap(),
+        ),
+    );
+    let modules = HashMap::from([("math".to_string(), math)]);
+
+    // return import('math').double(21)
+    let bytecode =
+        json!(["_H", 1, 33, 21, 32, "math", 2, "import", 1, 32, "double", 45, 54, 1, 38]);
+    assert_eq!(run(bytecode, |ctx| ctx.with_modules(&modules)), json!(42));
+
+    // Referencing the function directly, rather than via an import, also works
+    let bytecode = json!(["_H", 1, 33, 21, 32, "double", 32, "math", 1, 2, 54, 1, 38]);
+    assert_eq!(run(bytecode, |ctx| ctx.with_modules(&modules)), json!(42));
+
+    // And adding modules doesn't remove the hog stl - return arrayMap(math.double, [1, 2])
+    let bytecode =
+        json!(["_H", 1, 32, "double", 32, "math", 1, 2, 33, 1, 33, 2, 43, 2, 2, "arrayMap", 2, 38]);
+    assert_eq!(
+        run(bytecode, |ctx| ctx.with_modules(&modules)),
+        json!([2, 4])
+    );
+
+    let bytecode = json!(["_H", 1, 32, "missing", 2, "import", 1, 38]);
+    let program = Program::new(serde_json::from_value(bytecode).unwrap()).unwrap();
+    let ctx = ExecutionContext::with_defaults(program).with_modules(&modules);
+    let err = sync_execute(&ctx, false).unwrap_err();
+    assert!(matches!(err.error, VmError::UnknownModule(m) if m == "missing"));
+}
+
+#[test]
+pub fn test_cohort_membership() {
+    let resolver = |ctx: ExecutionContext| {
+        ctx.with_cohort_resolver(Box::new(|vm, subject, cohort| {
+            let subject: &str = subject.deref(&vm.heap)?.try_as()?;
+            let cohort = cohort.deref(&vm.heap)?;
+            Ok(subject == "person-1" && *cohort == HogLiteral::from(7i64))
+        }))
+    };
+
+    // person_id in cohort 7, person_id not in cohort 8
+    let in_cohort = json!(["_H", 1, 33, 7, 32, "person-1", 27, 38]);
+    let not_in_cohort = json!(["_H", 1, 33, 8, 32, "person-1", 28, 38]);
+    assert_eq!(run(in_cohort.clone(), resolver), json!(true));
+    assert_eq!(run(not_in_cohort, resolver), json!(true));
+
+    // Without a resolver, cohort checks fail, rather than silently evaluating to false
+    let program = Program::new(serde_json::from_value(in_cohort).unwrap()).unwrap();
+    let ctx = ExecutionContext::with_defaults(program);
+    let err = sync_execute(&ctx, false).unwrap_err();
+    assert!(matches!(err.error, VmError::NoCohortResolver));
+}
+
+#[test]
+pub fn test_try_catch() {
+    // try { throw Error('x') } catch { return 1 }; return 2
+    let bytecode = json!([
+        "_H", 1, 50, 15, 32, "type", 32, "Error", 32, "message", 32, "x", 42, 2, 49, 51, 39, 3, 33,
+        1, 38, 33, 2, 38
+    ]);
+    assert_eq!(run(bytecode, |ctx| ctx), json!(1));
+}
@@ -19,6 +19,20 @@ pub struct EmbeddingRequest {
     pub models: Vec<EmbeddingModel>,
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub metadata: HashMap<String, Value>,
+    // If set, content longer than a chunk is split and embedded chunk by chunk, rather than truncated.
+    // Each chunk is recorded under the document id "{document_id}#chunk-{index}".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunking: Option<ChunkingStrategy>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkingStrategy {
+    // Defaults to the input window of the model being used, and is capped to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<usize>,
+    // Tokens repeated at the start of each chunk from the end of the previous one
+    #[serde(default)]
+    pub overlap_tokens: usize,
 }
 
 // Responses from an embedding request - these are written to the response
@@ -43,9 +57,17 @@ pub struct ModelResult {
 #[serde(tag = "outcome", rename_all = "snake_case")]
 pub enum EmbeddingResult {
     Success { embedding: Vec<f64> },
+    Chunked { chunks: Vec<EmbeddingChunk> },
     Failure { error: String },
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingChunk {
+    pub index: usize,
+    pub content: String,
+    pub embedding: Vec<f64>,
+}
+
 // Records the embedding worker emits, for ingestion into clickhouse
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingRecord {
@@ -63,13 +85,17 @@ pub struct EmbeddingRecord {
     pub metadata: Option<String>, // JSON object, stringified
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum EmbeddingModel {
     #[serde(rename = "text-embedding-3-small-1536")]
     #[default]
     OpenAITextEmbeddingSmall,
     #[serde(rename = "text-embedding-3-large-3072")]
     OpenAITextEmbeddingLarge,
+    // Any other model, served by an OpenAI-compatible provider configured in the embedding worker.
+    // This is the name the model is requested by, and the model_name its records are written with.
+    #[serde(untagged)]
+    Custom(String),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -79,140 +105,167 @@ pub struct ApiLimits {
 }
 
 impl EmbeddingModel {
-    pub fn name(&self) -> &'static str {
+    pub fn name(&self) -> &str {
         match self {
             EmbeddingModel::OpenAITextEmbeddingSmall => "text-embedding-3-small",
             EmbeddingModel::OpenAITextEmbeddingLarge => "text-embedding-3-large",
+            EmbeddingModel::Custom(name) => name,
         }
     }
+}
 
-    pub fn model_dimension(&self) -> usize {
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall => 1536,
-            EmbeddingModel::OpenAITextEmbeddingLarge => 3072,
-        }
-    }
-
-    pub fn model_input_window(&self) -> usize {
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall => 8192,
-            EmbeddingModel::OpenAITextEmbeddingLarge => 8192,
-        }
-    }
-
-    pub fn model_url(&self) -> &'static str {
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-                "https://api.openai.com/v1/embeddings"
-            }
-        }
-    }
+impl From<EmbeddingResponse> for Vec<EmbeddingRecord> {
+    fn from(response: EmbeddingResponse) -> Self {
+        let request = &response.request;
+        let record = |model: &EmbeddingModel,
+                      document_id: String,
+                      embedding: Vec<f64>,
+                      content: &str,
+                      metadata: &HashMap<String, Value>| EmbeddingRecord {
+            team_id: request.team_id,
+            product: request.product.clone(),
+            document_type: request.document_type.clone(),
+            model_name: model.clone(),
+            rendering: request.rendering.clone(),
+            document_id,
+            timestamp: format_ch_datetime(request.timestamp),
+            embedding,
+            content: Some(content.to_string()),
+            metadata: if metadata.is_empty() {
+                None
+            } else {
+                Some(serde_json::to_string(metadata).expect("Can serialize metadata"))
+            },
+        };
 
-    pub fn limits_key(&self) -> &'static str {
-        // Based on our openai usage limits, these are distinct buckets
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall => "openai_text_embedding_small",
-            EmbeddingModel::OpenAITextEmbeddingLarge => "openai_text_embedding_large",
-        }
-    }
+        let mut records = Vec::new();
 
-    pub fn api_limits(&self) -> ApiLimits {
-        // TODO - these are VERY conservative, but keep in mind each pod will max out at this.
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-                ApiLimits {
-                    requests_per_minute: 3_000,
-                    tokens_per_minute: 1_000_000,
+        for result in response.results {
+            match result.outcome {
+                EmbeddingResult::Success { embedding } => {
+                    records.push(record(
+                        &result.model,
+                        request.document_id.clone(),
+                        embedding,
+                        &request.content,
+                        &request.metadata,
+                    ));
                 }
+                EmbeddingResult::Chunked { chunks } => {
+                    // Every chunk is its own record, under its own document id - records are deduplicated
+                    // on the document id, so chunks sharing one would collapse into a single record
+                    let chunk_count = chunks.len();
+                    for chunk in chunks {
+                        let mut metadata = request.metadata.clone();
+                        metadata.insert("chunk_index".to_string(), chunk.index.into());
+                        metadata.insert("chunk_count".to_string(), chunk_count.into());
+                        records.push(record(
+                            &result.model,
+                            format!("{}#chunk-{}", request.document_id, chunk.index),
+                            chunk.embedding,
+                            &chunk.content,
+                            &metadata,
+                        ));
+                    }
+                }
+                EmbeddingResult::Failure { .. } => {}
             }
         }
-    }
-
-    // This takes a function ref as an argument to avoid taking a dep on reqwest
-    pub fn api_limits_from_response(
-        &self,
-        headers: &dyn Fn(&str) -> Option<String>,
-    ) -> Option<ApiLimits> {
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-                let rpm = headers("x-ratelimit-limit-requests")?.parse().ok()?;
-                let tpm = headers("x-ratelimit-limit-tokens")?.parse().ok()?;
-                Some(ApiLimits {
-                    requests_per_minute: rpm,
-                    tokens_per_minute: tpm,
-                })
-            }
-        }
-    }
-
-    pub fn construct_request_body(&self, text: &str) -> Value {
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-                let request = OAIEmbeddingRequest {
-                    input: text.to_string(),
-                    model: self.name().to_string(),
-                };
-                serde_json::to_value(request).expect("We are able to serialize the request")
-            }
-        }
-    }
 
-    pub fn extract_embedding_from_response_body(&self, response: &Value) -> Option<Vec<f64>> {
-        match self {
-            EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-                let response: OAIEmbeddingResponse =
-                    serde_json::from_value(response.clone()).ok()?;
-                response.data.into_iter().next().map(|d| d.embedding)
-            }
-        }
+        records
     }
 }
 
-#[derive(Serialize)]
-struct OAIEmbeddingRequest {
-    input: String,
-    model: String,
-}
+#[cfg(test)]
+mod test {
+    use chrono::Utc;
+    use serde_json::json;
 
-#[derive(Deserialize)]
-struct OAIEmbeddingResponse {
-    data: Vec<OAIEmbeddingData>,
-}
+    use super::*;
 
-#[derive(Deserialize)]
-struct OAIEmbeddingData {
-    embedding: Vec<f64>,
-}
+    #[test]
+    fn test_models_round_trip() {
+        for (model, name) in [
+            (
+                EmbeddingModel::OpenAITextEmbeddingSmall,
+                "text-embedding-3-small-1536",
+            ),
+            (
+                EmbeddingModel::OpenAITextEmbeddingLarge,
+                "text-embedding-3-large-3072",
+            ),
+            (EmbeddingModel::Custom("bge-small".to_string()), "bge-small"),
+        ] {
+            assert_eq!(serde_json::to_value(&model).unwrap(), json!(name));
+            assert_eq!(
+                serde_json::from_value::<EmbeddingModel>(json!(name)).unwrap(),
+                model
+            );
+        }
+    }
 
-impl From<EmbeddingResponse> for Vec<EmbeddingRecord> {
-    fn from(response: EmbeddingResponse) -> Self {
-        let mut records = Vec::new();
+    #[test]
+    fn test_chunked_results_are_a_record_per_chunk() {
+        let request: EmbeddingRequest = serde_json::from_value(json!({
+            "team_id": 1,
+            "product": "error_tracking",
+            "document_type": "fingerprint",
+            "rendering": "plain",
+            "document_id": "doc",
+            "timestamp": Utc::now(),
+            "content": "first second",
+            "models": ["text-embedding-3-small-1536", "bge-small"],
+            "metadata": {"source": "test"},
+            "chunking": {"max_tokens": 1},
+        }))
+        .unwrap();
+        assert_eq!(
+            request.chunking,
+            Some(ChunkingStrategy {
+                max_tokens: Some(1),
+                overlap_tokens: 0
+            })
+        );
 
-        for result in response.results {
-            if let EmbeddingResult::Success { embedding } = result.outcome {
-                let record = EmbeddingRecord {
-                    team_id: response.request.team_id,
-                    product: response.request.product.clone(),
-                    document_type: response.request.document_type.clone(),
-                    model_name: result.model,
-                    rendering: response.request.rendering.clone(),
-                    document_id: response.request.document_id.clone(),
-                    timestamp: format_ch_datetime(response.request.timestamp),
-                    embedding,
-                    content: Some(response.request.content.clone()),
-                    metadata: if response.request.metadata.is_empty() {
-                        None
-                    } else {
-                        Some(
-                            serde_json::to_string(&response.request.metadata)
-                                .expect("Can serialize metadata"),
-                        )
+        let response = EmbeddingResponse {
+            request,
+            results: vec![
+                ModelResult {
+                    model: EmbeddingModel::OpenAITextEmbeddingSmall,
+                    outcome: EmbeddingResult::Chunked {
+                        chunks: vec![
+                            EmbeddingChunk {
+                                index: 0,
+                                content: "first".to_string(),
+                                embedding: vec![0.1],
+                            },
+                            EmbeddingChunk {
+                                index: 1,
+                                content: " second".to_string(),
+                                embedding: vec![0.2],
+                            },
+                        ],
                     },
-                };
-                records.push(record);
-            }
-        }
+                },
+                ModelResult {
+                    model: EmbeddingModel::Custom("bge-small".to_string()),
+                    outcome: EmbeddingResult::Failure {
+                        error: "No provider configured for model bge-small".to_string(),
+                    },
+                },
+            ],
+        };
 
-        records
+        let records = Vec::<EmbeddingRecord>::from(response);
+        assert_eq!(records.len(), 2);
+        for (index, (record, content)) in records.iter().zip(["first", " second"]).enumerate() {
+            assert_eq!(record.document_id, format!("doc#chunk-{index}"));
+            assert_eq!(record.content.as_deref(), Some(content));
+            let metadata: Value = serde_json::from_str(record.metadata.as_ref().unwrap()).unwrap();
+            assert_eq!(
+                metadata,
+                json!({"source": "test", "chunk_index": index, "chunk_count": 2})
+            );
+        }
     }
 }
@@ -39,6 +39,8 @@ pub mod error_tracking;
 // Embeddings
 pub mod embedding {
     pub use crate::embeddings::ApiLimits;
+    pub use crate::embeddings::ChunkingStrategy;
+    pub use crate::embeddings::EmbeddingChunk;
     pub use crate::embeddings::EmbeddingModel;
     pub use crate::embeddings::EmbeddingRecord;
     pub use crate::embeddings::EmbeddingRequest;
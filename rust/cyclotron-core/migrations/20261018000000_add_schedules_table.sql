@@ -0,0 +1,38 @@
+---------------------------------------------------------------------
+-- Schedules table
+---------------------------------------------------------------------
+-- A schedule is a template for a recurring job. Janitors materialize due runs into
+-- cyclotron_jobs, locking the schedule row and advancing next_run in the same transaction as
+-- the job insert, so each run is enqueued exactly once no matter how many janitors are running.
+CREATE TABLE IF NOT EXISTS cyclotron_schedules (
+    id UUID PRIMARY KEY,
+    team_id INT NOT NULL,
+    function_id UUID,
+    queue_name TEXT NOT NULL,
+    priority SMALLINT NOT NULL,
+    ---------------------------------------------------------------------
+    -- When to run - exactly one of cron_expression or interval_seconds is set
+    ---------------------------------------------------------------------
+    cron_expression TEXT,
+    interval_seconds BIGINT,
+    timezone TEXT NOT NULL,
+    -- One of 'skip', 'latest' or 'all'
+    catch_up TEXT NOT NULL,
+    next_run TIMESTAMPTZ NOT NULL,
+    last_run TIMESTAMPTZ,
+    created TIMESTAMPTZ NOT NULL,
+    updated TIMESTAMPTZ NOT NULL,
+    ---------------------------------------------------------------------
+    -- Job template
+    ---------------------------------------------------------------------
+    parameters BYTEA,
+    blob BYTEA,
+    metadata BYTEA,
+    CONSTRAINT cyclotron_schedules_one_spec CHECK ((cron_expression IS NULL) <> (interval_seconds IS NULL)),
+    CONSTRAINT cyclotron_schedules_positive_interval CHECK (interval_seconds IS NULL OR interval_seconds > 0)
+);
+
+-- Janitors look for schedules with a next_run in the past
+CREATE INDEX idx_cyclotron_schedules_next_run ON cyclotron_schedules (next_run);
+
+CREATE INDEX idx_cyclotron_schedules_team_id ON cyclotron_schedules (team_id);
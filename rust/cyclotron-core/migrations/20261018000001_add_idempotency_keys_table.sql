@@ -0,0 +1,18 @@
+---------------------------------------------------------------------
+-- Idempotency keys table
+---------------------------------------------------------------------
+-- Jobs created with an idempotency key claim it here, in the same transaction as the job insert.
+-- While the claim hasn't expired, creating another job with the same key (for the same team and
+-- queue) returns the id of the job that claimed it instead. Claims outlive the jobs themselves, so
+-- a key is still honoured after its job has completed and been cleaned up by the janitor.
+CREATE TABLE IF NOT EXISTS cyclotron_idempotency_keys (
+    team_id INT NOT NULL,
+    queue_name TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    job_id UUID NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (team_id, queue_name, idempotency_key)
+);
+
+-- Janitors delete expired claims
+CREATE INDEX idx_cyclotron_idempotency_keys_expires_at ON cyclotron_idempotency_keys (expires_at);
@@ -33,6 +33,7 @@ impl PoolConfig {
 
 pub const DEFAULT_QUEUE_DEPTH_LIMIT: u64 = 1_000_000;
 pub const DEFAULT_SHARD_HEALTH_CHECK_INTERVAL: u64 = 10;
+pub const DEFAULT_IDEMPOTENCY_WINDOW_SECONDS: u64 = 3600;
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 pub struct ManagerConfig {
@@ -46,6 +47,10 @@ pub struct ManagerConfig {
     pub should_compress_vm_state: Option<bool>, // Defaults to "false" for now
     #[serde(alias = "shouldUseBulkJobCopy")]
     pub should_use_bulk_job_copy: Option<bool>, // Defaults to "false" for now
+    #[serde(alias = "idempotencyWindowSeconds")]
+    pub idempotency_window_seconds: Option<u64>, // Defaults to 1 hour - how long an idempotency key dedupes jobs for
+    #[serde(alias = "shouldNotifyWorkers")]
+    pub should_notify_workers: Option<bool>, // Defaults to "false" - NOTIFY waiting workers when jobs are created
 }
 
 #[derive(Debug, Serialize, Deserialize, Default)]
@@ -62,6 +67,8 @@ pub struct WorkerConfig {
     pub flush_loop_interval_ms: Option<u64>, // Defaults to 10
     #[serde(alias = "shouldCompressVmState")]
     pub should_compress_vm_state: Option<bool>, // Defaults to "false"
+    #[serde(alias = "listenPollIntervalMs")]
+    pub listen_poll_interval_ms: Option<u64>, // Defaults to 1000 - how often a waiting worker polls if it isn't notified
 }
 
 impl WorkerConfig {
@@ -88,4 +95,8 @@ impl WorkerConfig {
     pub fn should_compress_vm_state(&self) -> bool {
         self.should_compress_vm_state.unwrap_or(false)
     }
+
+    pub fn listen_poll_interval(&self) -> chrono::Duration {
+        chrono::Duration::milliseconds(self.listen_poll_interval_ms.unwrap_or(1000) as i64)
+    }
 }
@@ -14,6 +14,10 @@ pub enum QueueError {
     CompressionError(String),
     #[error("writing in-mem CSV buffer at {0}: {1}")]
     CsvError(&'static str, csv::Error),
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+    #[error("Unknown schedule id: {0}")]
+    UnknownScheduleId(Uuid),
 }
 
 #[derive(Debug, thiserror::Error)]
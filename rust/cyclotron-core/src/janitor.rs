@@ -4,8 +4,12 @@ use sqlx::PgPool;
 
 use crate::{
     ops::{
-        janitor::{delete_completed_and_failed_jobs, detect_poison_pills, reset_stalled_jobs},
+        janitor::{
+            delete_completed_and_failed_jobs, delete_expired_idempotency_keys, detect_poison_pills,
+            reset_stalled_jobs,
+        },
         meta::{count_total_waiting_jobs, dead_letter},
+        schedule::materialize_schedules,
     },
     types::AggregatedDelete,
     PoolConfig, QueueError,
@@ -55,6 +59,21 @@ impl Janitor {
         Ok(poison.len() as u64)
     }
 
+    pub async fn delete_expired_idempotency_keys(&self) -> Result<u64, QueueError> {
+        delete_expired_idempotency_keys(&self.pool).await
+    }
+
+    // Enqueue the due runs of up to `limit` schedules, returning the number of jobs enqueued. Safe to run
+    // from multiple janitors at once. Runs due longer than `lateness_tolerance` ago count as missed, for
+    // schedules that skip missed runs.
+    pub async fn materialize_schedules(
+        &self,
+        limit: usize,
+        lateness_tolerance: Duration,
+    ) -> Result<u64, QueueError> {
+        materialize_schedules(&self.pool, limit, lateness_tolerance).await
+    }
+
     pub async fn waiting_jobs(&self) -> Result<Vec<(u64, String)>, QueueError> {
         count_total_waiting_jobs(&self.pool).await
     }
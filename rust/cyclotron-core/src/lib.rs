@@ -6,10 +6,14 @@ mod ops;
 mod types;
 pub use types::AggregatedDelete;
 pub use types::Bytes;
+pub use types::CatchUpPolicy;
 pub use types::Job;
 pub use types::JobInit;
 pub use types::JobState;
 pub use types::JobUpdate;
+pub use types::Schedule;
+pub use types::ScheduleInit;
+pub use types::ScheduleSpec;
 
 // Errors
 mod error;
@@ -23,6 +27,7 @@ mod manager;
 pub use manager::QueueManager;
 
 // Worker
+mod listener;
 mod worker;
 // A handle to a released job update, that can be awaited to block waiting for the flush to complete
 pub use worker::FlushHandle;
@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::sync::{
+    mpsc::{self, error::TryRecvError},
+    Notify,
+};
+use tracing::error;
+
+use crate::ops::notify::queue_channel;
+
+// How long to wait before reconnecting, if the listener connection fails
+const RECONNECT_BACKOFF: std::time::Duration = std::time::Duration::from_secs(1);
+
+type Waiters = Arc<Mutex<HashMap<String, Arc<Notify>>>>;
+
+// Holds a single dedicated connection LISTENing on the channels of every queue a worker has
+// waited on, and wakes the waiters of a queue when a notification for it arrives. Notifications
+// are only ever a hint - they can be lost (e.g. while reconnecting), so waiters must still poll.
+pub struct QueueListener {
+    subscribe: mpsc::UnboundedSender<String>,
+    waiters: Waiters,
+}
+
+impl QueueListener {
+    pub fn new(pool: PgPool) -> Self {
+        let (subscribe, requests) = mpsc::unbounded_channel();
+        let waiters: Waiters = Default::default();
+        tokio::spawn(listen_loop(pool, requests, waiters.clone()));
+        Self { subscribe, waiters }
+    }
+
+    // Returns the Notify that is woken whenever jobs might have become available in the queue
+    pub fn subscribe(&self, queue: &str) -> Arc<Notify> {
+        let channel = queue_channel(queue);
+        let mut waiters = self.waiters.lock().unwrap();
+        if let Some(notify) = waiters.get(&channel) {
+            return notify.clone();
+        }
+
+        let notify = Arc::new(Notify::new());
+        waiters.insert(channel.clone(), notify.clone());
+        // The loop only exits once this sender is dropped, so this shouldn't fail
+        if let Err(e) = self.subscribe.send(channel) {
+            error!("Queue listener has stopped, not listening on {}", e.0);
+        }
+        notify
+    }
+}
+
+fn wake(waiters: &Waiters, channel: &str) {
+    if let Some(notify) = waiters.lock().unwrap().get(channel) {
+        notify.notify_waiters();
+    }
+}
+
+// Used whenever notifications might have been missed, so waiters go and check for themselves
+fn wake_all(waiters: &Waiters) {
+    for notify in waiters.lock().unwrap().values() {
+        notify.notify_waiters();
+    }
+}
+
+// Runs until the owning QueueListener is dropped
+async fn listen_loop(
+    pool: PgPool,
+    mut requests: mpsc::UnboundedReceiver<String>,
+    waiters: Waiters,
+) {
+    let mut channels: Vec<String> = vec![];
+
+    loop {
+        // Pick up any subscriptions made while we were (re)connecting
+        loop {
+            match requests.try_recv() {
+                Ok(channel) => channels.push(channel),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        let mut listener = match connect(&pool, &channels).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to start queue listener: {:?}", e);
+                wake_all(&waiters);
+                tokio::time::sleep(RECONNECT_BACKOFF).await;
+                continue;
+            }
+        };
+        // Anything sent before the LISTEN took effect was missed
+        wake_all(&waiters);
+
+        loop {
+            tokio::select! {
+                biased;
+                request = requests.recv() => {
+                    let Some(channel) = request else {
+                        return;
+                    };
+                    if let Err(e) = listener.listen(&channel).await {
+                        error!("Failed to listen on {}: {:?}", channel, e);
+                        channels.push(channel);
+                        break;
+                    }
+                    channels.push(channel);
+                }
+                notification = listener.try_recv() => match notification {
+                    Ok(Some(notification)) => wake(&waiters, notification.channel()),
+                    // The connection was lost and re-established, so we may have missed some
+                    Ok(None) => wake_all(&waiters),
+                    Err(e) => {
+                        error!("Queue listener failed: {:?}", e);
+                        break;
+                    }
+                },
+            }
+        }
+
+        wake_all(&waiters);
+        tokio::time::sleep(RECONNECT_BACKOFF).await;
+    }
+}
+
+async fn connect(pool: &PgPool, channels: &[String]) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    if !channels.is_empty() {
+        listener
+            .listen_all(channels.iter().map(String::as_str))
+            .await?;
+    }
+    Ok(listener)
+}
@@ -1,17 +1,26 @@
+use std::collections::BTreeMap;
 use std::sync::atomic::AtomicUsize;
 
 use chrono::{DateTime, Duration, Utc};
 use sqlx::PgPool;
 use tokio::sync::RwLock;
+use tracing::warn;
 use uuid::Uuid;
 
 use crate::{
-    config::{DEFAULT_QUEUE_DEPTH_LIMIT, DEFAULT_SHARD_HEALTH_CHECK_INTERVAL},
+    config::{
+        DEFAULT_IDEMPOTENCY_WINDOW_SECONDS, DEFAULT_QUEUE_DEPTH_LIMIT,
+        DEFAULT_SHARD_HEALTH_CHECK_INTERVAL,
+    },
     ops::{
-        manager::{bulk_create_jobs_copy, bulk_create_jobs_upsert, create_job},
+        manager::{
+            bulk_create_jobs_copy, bulk_create_jobs_idempotent, bulk_create_jobs_upsert, create_job,
+        },
         meta::count_total_waiting_jobs,
+        notify::{notify_jobs_available, runnable_queues},
+        schedule::{create_schedule, delete_schedule, get_schedule, update_schedule},
     },
-    JobInit, ManagerConfig, QueueError,
+    JobInit, ManagerConfig, QueueError, Schedule, ScheduleInit,
 };
 
 pub struct Shard {
@@ -21,6 +30,8 @@ pub struct Shard {
     pub depth_limit: u64,
     pub should_compress_vm_state: bool,
     pub should_use_bulk_job_copy: bool,
+    pub idempotency_window: Duration,
+    pub should_notify_workers: bool,
 }
 
 pub struct QueueManager {
@@ -41,6 +52,12 @@ impl QueueManager {
         );
         let should_compress_vm_state = config.should_compress_vm_state.unwrap_or(false);
         let should_use_bulk_job_copy = config.should_use_bulk_job_copy.unwrap_or(false);
+        let should_notify_workers = config.should_notify_workers.unwrap_or(false);
+        let idempotency_window = Duration::seconds(
+            config
+                .idempotency_window_seconds
+                .unwrap_or(DEFAULT_IDEMPOTENCY_WINDOW_SECONDS) as i64,
+        );
 
         for shard in config.shards {
             let pool = shard.connect().await.unwrap();
@@ -50,6 +67,8 @@ impl QueueManager {
                 check_interval,
                 should_compress_vm_state,
                 should_use_bulk_job_copy,
+                idempotency_window,
+                should_notify_workers,
             );
             shards.push(shard);
         }
@@ -72,6 +91,8 @@ impl QueueManager {
                 Duration::seconds(DEFAULT_SHARD_HEALTH_CHECK_INTERVAL as i64),
                 should_compress_vm_state,
                 should_use_bulk_job_copy,
+                Duration::seconds(DEFAULT_IDEMPOTENCY_WINDOW_SECONDS as i64),
+                false,
             )]),
             next_shard: AtomicUsize::new(0),
         }
@@ -79,11 +100,8 @@ impl QueueManager {
 
     pub async fn create_job(&self, init: JobInit) -> Result<Uuid, QueueError> {
         // TODO - here is where a lot of shard health and failover logic will go, eventually.
-        let next = self
-            .next_shard
-            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         let shards = self.shards.read().await;
-        let shard = &shards[next % shards.len()];
+        let shard = &shards[self.shard_for(&init, shards.len())];
         shard.create_job(init).await
     }
 
@@ -92,11 +110,8 @@ impl QueueManager {
         init: JobInit,
         timeout: Option<Duration>,
     ) -> Result<Uuid, QueueError> {
-        let next = self
-            .next_shard
-            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         let shards = self.shards.read().await;
-        let shard = &shards[next % shards.len()];
+        let shard = &shards[self.shard_for(&init, shards.len())];
         shard.create_job_blocking(init, timeout).await
     }
 
@@ -105,7 +120,17 @@ impl QueueManager {
             .next_shard
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         let shards = self.shards.read().await;
-        shards[next % shards.len()].bulk_create_jobs(inits).await
+        if !inits.iter().any(|i| i.idempotency_key.is_some()) {
+            return shards[next % shards.len()].bulk_create_jobs(inits).await;
+        }
+
+        let len = inits.len();
+        let mut ids = Vec::with_capacity(len);
+        for (shard, (positions, inits)) in route_jobs(inits, next, shards.len()) {
+            let shard_ids = shards[shard].bulk_create_jobs(inits).await?;
+            ids.extend(positions.into_iter().zip(shard_ids));
+        }
+        Ok(in_original_order(ids, len))
     }
 
     pub async fn bulk_create_jobs_blocking(
@@ -117,12 +142,125 @@ impl QueueManager {
             .next_shard
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         let shards = self.shards.read().await;
-        shards[next % shards.len()]
-            .bulk_create_jobs_blocking(inits, timeout)
-            .await
+        if !inits.iter().any(|i| i.idempotency_key.is_some()) {
+            return shards[next % shards.len()]
+                .bulk_create_jobs_blocking(inits, timeout)
+                .await;
+        }
+
+        let len = inits.len();
+        let mut ids = Vec::with_capacity(len);
+        for (shard, (positions, inits)) in route_jobs(inits, next, shards.len()) {
+            let shard_ids = shards[shard]
+                .bulk_create_jobs_blocking(inits, timeout)
+                .await?;
+            ids.extend(positions.into_iter().zip(shard_ids));
+        }
+        Ok(in_original_order(ids, len))
+    }
+
+    // Jobs with an idempotency key always go to the same shard, so the key can be checked there. Everything
+    // else is spread round-robin.
+    fn shard_for(&self, init: &JobInit, shard_count: usize) -> usize {
+        match &init.idempotency_key {
+            Some(key) => idempotency_shard(init, key, shard_count),
+            None => {
+                self.next_shard
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+                    % shard_count
+            }
+        }
+    }
+
+    // Schedules are spread across shards like jobs are, and the runs of a schedule are enqueued on the
+    // shard it lives on, by that shard's janitor
+    pub async fn create_schedule(&self, init: ScheduleInit) -> Result<Uuid, QueueError> {
+        let next = self
+            .next_shard
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let shards = self.shards.read().await;
+        create_schedule(&shards[next % shards.len()].pool, init).await
+    }
+
+    // Replaces the schedule's definition, and recomputes its next run from `init.start` (or now)
+    pub async fn update_schedule(&self, id: Uuid, init: ScheduleInit) -> Result<(), QueueError> {
+        let shards = self.shards.read().await;
+        for shard in shards.iter() {
+            if update_schedule(&shard.pool, id, init.clone()).await? {
+                return Ok(());
+            }
+        }
+        Err(QueueError::UnknownScheduleId(id))
+    }
+
+    pub async fn delete_schedule(&self, id: Uuid) -> Result<(), QueueError> {
+        let shards = self.shards.read().await;
+        for shard in shards.iter() {
+            if delete_schedule(&shard.pool, id).await? {
+                return Ok(());
+            }
+        }
+        Err(QueueError::UnknownScheduleId(id))
+    }
+
+    pub async fn get_schedule(&self, id: Uuid) -> Result<Option<Schedule>, QueueError> {
+        let shards = self.shards.read().await;
+        for shard in shards.iter() {
+            if let Some(schedule) = get_schedule(&shard.pool, id).await? {
+                return Ok(Some(schedule));
+            }
+        }
+        Ok(None)
     }
 }
 
+// The shard a job with an idempotency key lives on. This has to agree across processes and releases (unlike
+// std's hasher), so we hash with FNV-1a. Changing the number of shards moves keys, so duplicates created
+// across a resharding aren't caught.
+fn idempotency_shard(init: &JobInit, key: &str, shard_count: usize) -> usize {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut hash = FNV_OFFSET_BASIS;
+    let parts: [&[u8]; 3] = [
+        &init.team_id.to_le_bytes(),
+        init.queue_name.as_bytes(),
+        key.as_bytes(),
+    ];
+    for part in parts {
+        for byte in part.iter().chain(&[0xff]) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    (hash % shard_count as u64) as usize
+}
+
+// Group jobs by the shard they should be inserted into, remembering their position in the original vec
+fn route_jobs(
+    inits: Vec<JobInit>,
+    default_shard: usize,
+    shard_count: usize,
+) -> BTreeMap<usize, (Vec<usize>, Vec<JobInit>)> {
+    let mut routed: BTreeMap<usize, (Vec<usize>, Vec<JobInit>)> = BTreeMap::new();
+    for (position, init) in inits.into_iter().enumerate() {
+        let shard = match &init.idempotency_key {
+            Some(key) => idempotency_shard(&init, key, shard_count),
+            None => default_shard % shard_count,
+        };
+        let (positions, inits) = routed.entry(shard).or_default();
+        positions.push(position);
+        inits.push(init);
+    }
+    routed
+}
+
+fn in_original_order(mut ids: Vec<(usize, Uuid)>, len: usize) -> Vec<Uuid> {
+    debug_assert_eq!(ids.len(), len);
+    ids.sort_unstable_by_key(|(position, _)| *position);
+    ids.into_iter().map(|(_, id)| id).collect()
+}
+
 impl Shard {
     pub fn new(
         pool: PgPool,
@@ -130,6 +268,8 @@ impl Shard {
         check_interval: Duration,
         should_compress_vm_state: bool,
         should_use_bulk_job_copy: bool,
+        idempotency_window: Duration,
+        should_notify_workers: bool,
     ) -> Self {
         Self {
             pool,
@@ -138,13 +278,15 @@ impl Shard {
             depth_limit,
             should_compress_vm_state,
             should_use_bulk_job_copy,
+            idempotency_window,
+            should_notify_workers,
         }
     }
 
     // Inserts a job, failing if the shard is at capacity
     pub async fn create_job(&self, init: JobInit) -> Result<Uuid, QueueError> {
         self.insert_guard().await?;
-        create_job(&self.pool, init, self.should_compress_vm_state).await
+        self.insert_job(init).await
     }
 
     // Inserts a vec of jobs, failing if the shard is at capacity. Note "capacity" here just
@@ -152,11 +294,7 @@ impl Shard {
     // 1000, we still insert all 1000.
     pub async fn bulk_create_jobs(&self, inits: Vec<JobInit>) -> Result<Vec<Uuid>, QueueError> {
         self.insert_guard().await?;
-        if self.should_use_bulk_job_copy {
-            bulk_create_jobs_copy(&self.pool, inits, self.should_compress_vm_state).await
-        } else {
-            bulk_create_jobs_upsert(&self.pool, inits, self.should_compress_vm_state).await
-        }
+        self.insert_jobs(inits).await
     }
 
     // Inserts a job, blocking until there's capacity (or until the timeout is reached)
@@ -175,7 +313,7 @@ impl Shard {
             }
         }
 
-        create_job(&self.pool, init, self.should_compress_vm_state).await
+        self.insert_job(init).await
     }
 
     // As above, with the same caveats about what "capacity" means
@@ -194,13 +332,70 @@ impl Shard {
             }
         }
 
-        if self.should_use_bulk_job_copy {
+        self.insert_jobs(inits).await
+    }
+
+    async fn insert_job(&self, init: JobInit) -> Result<Uuid, QueueError> {
+        let queues = self.queues_to_notify([&init]);
+        let id = self.insert_job_inner(init).await?;
+        self.notify_workers(&queues).await;
+        Ok(id)
+    }
+
+    async fn insert_jobs(&self, inits: Vec<JobInit>) -> Result<Vec<Uuid>, QueueError> {
+        let queues = self.queues_to_notify(&inits);
+        let ids = self.insert_jobs_inner(inits).await?;
+        self.notify_workers(&queues).await;
+        Ok(ids)
+    }
+
+    async fn insert_job_inner(&self, init: JobInit) -> Result<Uuid, QueueError> {
+        if init.idempotency_key.is_none() {
+            return create_job(&self.pool, init, self.should_compress_vm_state).await;
+        }
+
+        let ids = bulk_create_jobs_idempotent(
+            &self.pool,
+            vec![init],
+            self.should_compress_vm_state,
+            self.idempotency_window,
+        )
+        .await?;
+        Ok(ids[0])
+    }
+
+    async fn insert_jobs_inner(&self, inits: Vec<JobInit>) -> Result<Vec<Uuid>, QueueError> {
+        if inits.iter().any(|i| i.idempotency_key.is_some()) {
+            bulk_create_jobs_idempotent(
+                &self.pool,
+                inits,
+                self.should_compress_vm_state,
+                self.idempotency_window,
+            )
+            .await
+        } else if self.should_use_bulk_job_copy {
             bulk_create_jobs_copy(&self.pool, inits, self.should_compress_vm_state).await
         } else {
             bulk_create_jobs_upsert(&self.pool, inits, self.should_compress_vm_state).await
         }
     }
 
+    fn queues_to_notify<'a>(&self, inits: impl IntoIterator<Item = &'a JobInit>) -> Vec<String> {
+        if !self.should_notify_workers {
+            return vec![];
+        }
+        runnable_queues(inits)
+    }
+
+    // The jobs are already committed at this point, so a failure here isn't surfaced to the
+    // caller - retrying the insert would create duplicates, and waiting workers fall back to
+    // polling anyway.
+    async fn notify_workers(&self, queues: &[String]) {
+        if let Err(e) = notify_jobs_available(&self.pool, queues).await {
+            warn!("Failed to notify workers of new jobs: {}", e);
+        }
+    }
+
     pub async fn insert_guard(&self) -> Result<(), QueueError> {
         if self.is_full().await? {
             return Err(QueueError::ShardFull(self.depth_limit));
@@ -236,3 +431,73 @@ impl Shard {
         Ok(is_full)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn job(team_id: i32, key: Option<&str>) -> JobInit {
+        JobInit {
+            id: None,
+            team_id,
+            queue_name: "test".to_string(),
+            priority: 0,
+            scheduled: Utc::now(),
+            function_id: None,
+            parent_run_id: None,
+            vm_state: None,
+            parameters: None,
+            blob: None,
+            metadata: None,
+            idempotency_key: key.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_idempotency_shard_is_stable() {
+        let init = job(1, Some("key"));
+        let shard = idempotency_shard(&init, "key", 16);
+        for _ in 0..10 {
+            assert_eq!(idempotency_shard(&init, "key", 16), shard);
+        }
+
+        // Different keys spread across shards
+        let shards: std::collections::HashSet<usize> = (0..100)
+            .map(|i| idempotency_shard(&init, &format!("key-{i}"), 16))
+            .collect();
+        assert!(shards.len() > 1);
+    }
+
+    #[test]
+    fn test_route_jobs_keeps_positions() {
+        let inits = vec![
+            job(1, None),
+            job(1, Some("a")),
+            job(1, None),
+            job(1, Some("a")),
+            job(2, Some("b")),
+        ];
+        let key_shard = idempotency_shard(&inits[1], "a", 4);
+
+        let routed = route_jobs(inits, 5, 4);
+
+        let (positions, inits) = &routed[&key_shard];
+        assert!(positions.contains(&1) && positions.contains(&3));
+        assert_eq!(positions.len(), inits.len());
+        // Jobs without a key go to the default shard
+        assert!(routed[&1].0.contains(&0) && routed[&1].0.contains(&2));
+        assert_eq!(routed.values().map(|(p, _)| p.len()).sum::<usize>(), 5);
+
+        let ids: Vec<(usize, Uuid)> = routed
+            .values()
+            .flat_map(|(positions, _)| positions.iter().map(|p| (*p, Uuid::from_u128(*p as u128))))
+            .collect();
+        let ordered = in_original_order(ids, 5);
+        assert_eq!(
+            ordered,
+            (0..5)
+                .map(|p| Uuid::from_u128(p as u128))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -98,3 +98,16 @@ SELECT id FROM cyclotron_jobs WHERE state = 'running' AND COALESCE(last_heartbea
 
     Ok(result)
 }
+
+// Idempotency keys stop deduplicating jobs once expired, at which point they can be deleted
+pub async fn delete_expired_idempotency_keys<'c, E>(executor: E) -> Result<u64, QueueError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query("DELETE FROM cyclotron_idempotency_keys WHERE expires_at <= NOW()")
+        .execute(executor)
+        .await
+        .map_err(QueueError::from)?;
+
+    Ok(result.rows_affected())
+}
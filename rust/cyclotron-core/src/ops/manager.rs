@@ -1,4 +1,6 @@
-use chrono::{DateTime, Utc};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{postgres::PgPoolCopyExt, Pool, Postgres};
 use uuid::Uuid;
 
@@ -203,6 +205,128 @@ FROM UNNEST(
     Ok(ids)
 }
 
+// Inserts a vec of jobs, some or all of which have an idempotency key. Keys are claimed for `window` in
+// the same transaction as the insert. Jobs whose key is already claimed aren't inserted, and get the
+// id of the job holding the claim instead - as do later jobs in the vec that repeat an earlier key.
+// Ids are returned in the same order as `jobs`.
+pub async fn bulk_create_jobs_idempotent(
+    pool: &Pool<Postgres>,
+    jobs: Vec<JobInit>,
+    should_compress_vm_state: bool,
+    window: Duration,
+) -> Result<Vec<Uuid>, QueueError> {
+    let mut ids: Vec<Uuid> = jobs
+        .iter()
+        .map(|j| j.id.unwrap_or_else(Uuid::now_v7))
+        .collect();
+
+    // The first job with a given key is the one that tries to claim it, any others just share its id
+    let mut claimants: HashMap<(i32, &str, &str), usize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    for (i, job) in jobs.iter().enumerate() {
+        let Some(key) = job.idempotency_key.as_deref() else {
+            continue;
+        };
+        match claimants.entry((job.team_id, job.queue_name.as_str(), key)) {
+            Entry::Occupied(claimant) => duplicates.push((i, *claimant.get())),
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+        }
+    }
+
+    // Claims take row locks in the order they're inserted, so sort them to keep concurrent batches
+    // with overlapping keys from locking in opposite orders and deadlocking
+    let mut ordered_claims: Vec<_> = claimants.iter().collect();
+    ordered_claims.sort_unstable_by_key(|(claim, _)| **claim);
+
+    let mut team_ids = Vec::with_capacity(claimants.len());
+    let mut queue_names = Vec::with_capacity(claimants.len());
+    let mut keys = Vec::with_capacity(claimants.len());
+    let mut claim_ids = Vec::with_capacity(claimants.len());
+    for ((team_id, queue_name, key), i) in ordered_claims {
+        team_ids.push(*team_id);
+        queue_names.push(queue_name.to_string());
+        keys.push(key.to_string());
+        claim_ids.push(ids[*i]);
+    }
+
+    let mut txn = pool.begin().await?;
+
+    // Concurrent claims of the same key block on each other here, and only one of them wins
+    let claimed: HashSet<Uuid> = sqlx::query_scalar(
+        r#"
+INSERT INTO cyclotron_idempotency_keys (team_id, queue_name, idempotency_key, job_id, expires_at)
+SELECT team_id, queue_name, idempotency_key, job_id, NOW() + $5
+FROM UNNEST($1::int[], $2::text[], $3::text[], $4::uuid[])
+    AS claims(team_id, queue_name, idempotency_key, job_id)
+ON CONFLICT (team_id, queue_name, idempotency_key) DO UPDATE
+SET job_id = EXCLUDED.job_id, expires_at = EXCLUDED.expires_at
+WHERE cyclotron_idempotency_keys.expires_at <= NOW()
+RETURNING job_id
+    "#,
+    )
+    .bind(&team_ids)
+    .bind(&queue_names)
+    .bind(&keys)
+    .bind(&claim_ids)
+    .bind(window)
+    .fetch_all(&mut *txn)
+    .await?
+    .into_iter()
+    .collect();
+
+    // Any keys we didn't claim are held by an existing job, whose id we return instead
+    if claimed.len() < claim_ids.len() {
+        let existing: Vec<(i32, String, String, Uuid)> = sqlx::query_as(
+            r#"
+SELECT k.team_id, k.queue_name, k.idempotency_key, k.job_id
+FROM cyclotron_idempotency_keys k
+JOIN UNNEST($1::int[], $2::text[], $3::text[]) AS claims(team_id, queue_name, idempotency_key)
+    USING (team_id, queue_name, idempotency_key)
+    "#,
+        )
+        .bind(&team_ids)
+        .bind(&queue_names)
+        .bind(&keys)
+        .fetch_all(&mut *txn)
+        .await?;
+
+        for (team_id, queue_name, key, job_id) in existing {
+            if let Some(i) = claimants.get(&(team_id, queue_name.as_str(), key.as_str())) {
+                ids[*i] = job_id;
+            }
+        }
+    }
+    for (i, claimant) in duplicates {
+        ids[i] = ids[claimant];
+    }
+
+    // Only jobs without a key, and jobs that claimed theirs, get inserted
+    let mut should_insert: Vec<bool> = jobs.iter().map(|j| j.idempotency_key.is_none()).collect();
+    for i in claimants.values() {
+        should_insert[*i] = claimed.contains(&ids[*i]);
+    }
+
+    let to_insert: Vec<JobInit> = jobs
+        .into_iter()
+        .zip(&ids)
+        .zip(should_insert)
+        .filter(|(_, insert)| *insert)
+        .map(|((mut job, id), _)| {
+            job.id = Some(*id);
+            job
+        })
+        .collect();
+
+    if !to_insert.is_empty() {
+        bulk_create_jobs_upsert(&mut *txn, to_insert, should_compress_vm_state).await?;
+    }
+    txn.commit().await?;
+
+    Ok(ids)
+}
+
 // wraps CSV rows to be encoded and batch written
 // to Postgres as part of a COPY FROM STDIN stmt
 #[derive(Debug, serde::Serialize)]
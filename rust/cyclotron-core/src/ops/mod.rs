@@ -2,4 +2,6 @@ pub mod compress;
 pub mod janitor;
 pub mod manager;
 pub mod meta;
+pub mod notify;
+pub mod schedule;
 pub mod worker;
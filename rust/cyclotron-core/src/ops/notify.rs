@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+
+use crate::{JobInit, QueueError};
+
+// Postgres truncates identifiers at 63 bytes, and pg_notify errors on longer channel names
+const MAX_CHANNEL_LEN: usize = 63;
+const CHANNEL_PREFIX: &str = "cyclotron_queue_";
+
+// The channel workers LISTEN on for a given queue. Queue names are free-form, so long ones
+// are replaced by a hash - collisions just mean a spurious wakeup, which is harmless.
+pub fn queue_channel(queue: &str) -> String {
+    if CHANNEL_PREFIX.len() + queue.len() <= MAX_CHANNEL_LEN {
+        return format!("{CHANNEL_PREFIX}{queue}");
+    }
+
+    // FNV-1a, so the channel name is stable across processes
+    let hash = queue.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{CHANNEL_PREFIX}{hash:016x}")
+}
+
+// The queues that have at least one job in the batch that can be dequeued right away. Jobs
+// scheduled in the future are left to the workers' polling fallback.
+pub fn runnable_queues<'a>(jobs: impl IntoIterator<Item = &'a JobInit>) -> Vec<String> {
+    let now = Utc::now();
+    let queues: HashSet<&str> = jobs
+        .into_iter()
+        .filter(|job| job.scheduled <= now)
+        .map(|job| job.queue_name.as_str())
+        .collect();
+    queues.into_iter().map(String::from).collect()
+}
+
+// Wakes any workers waiting on the given queues. Postgres only delivers notifications once
+// the surrounding transaction commits, and collapses duplicates within a transaction.
+pub async fn notify_jobs_available<'c, E>(executor: E, queues: &[String]) -> Result<(), QueueError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    if queues.is_empty() {
+        return Ok(());
+    }
+
+    let channels: Vec<String> = queues.iter().map(|q| queue_channel(q)).collect();
+    sqlx::query("SELECT pg_notify(channel, '') FROM UNNEST($1::text[]) AS channel")
+        .bind(&channels)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_queue_channel_fits_identifier_limit() {
+        assert_eq!(queue_channel("fetch"), "cyclotron_queue_fetch");
+
+        let long = "q".repeat(100);
+        let channel = queue_channel(&long);
+        assert!(channel.len() <= MAX_CHANNEL_LEN);
+        assert_eq!(channel, queue_channel(&long));
+        assert_ne!(channel, queue_channel(&"r".repeat(100)));
+    }
+}
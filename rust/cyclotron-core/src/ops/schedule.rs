@@ -0,0 +1,648 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use common_metrics::inc;
+use croner::Cron;
+use sqlx::PgPool;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    error::QueueError,
+    ops::manager::bulk_create_jobs_upsert,
+    types::{Bytes, CatchUpPolicy, JobInit, Schedule, ScheduleInit, ScheduleSpec},
+};
+
+// The most jobs a single janitor pass will enqueue for one schedule. Schedules that catch up on all
+// missed runs pick up from the first run not enqueued on the next pass.
+const MAX_RUNS_PER_SCHEDULE: usize = 100;
+// The most missed runs a single janitor pass will walk through for one schedule that only enqueues
+// recent runs. Anything missed beyond this is dropped.
+const MAX_MISSED_RUNS_SCANNED: usize = 10_000;
+// If a stored schedule can't be evaluated, we push it back this far rather than retrying it on
+// every janitor pass
+const BROKEN_SCHEDULE_BACKOFF_HOURS: i64 = 24;
+
+// Due runs that were never enqueued, because of the schedule's catch-up policy or because too many
+// were missed
+const SCHEDULE_RUNS_DROPPED: &str = "schedule_runs_dropped";
+
+const SCHEDULE_COLUMNS: &str = r#"
+    id,
+    team_id,
+    function_id,
+    queue_name,
+    priority,
+    cron_expression,
+    interval_seconds,
+    timezone,
+    catch_up,
+    next_run,
+    last_run,
+    created,
+    updated,
+    parameters,
+    blob,
+    metadata
+"#;
+
+// A parsed, ready to evaluate schedule spec
+enum Recurrence {
+    Cron(Box<Cron>, Tz),
+    Interval(Duration),
+}
+
+impl Recurrence {
+    fn new(spec: &ScheduleSpec, timezone: &str) -> Result<Self, QueueError> {
+        let tz: Tz = timezone
+            .parse()
+            .map_err(|_| QueueError::InvalidSchedule(format!("unknown timezone: {timezone}")))?;
+
+        match spec {
+            ScheduleSpec::Cron(expression) => {
+                let cron = Cron::new(expression)
+                    .with_seconds_optional()
+                    .parse()
+                    .map_err(|e| {
+                        QueueError::InvalidSchedule(format!("invalid cron expression: {e}"))
+                    })?;
+                Ok(Recurrence::Cron(Box::new(cron), tz))
+            }
+            ScheduleSpec::Interval(seconds) => {
+                let seconds = i64::try_from(*seconds)
+                    .ok()
+                    .filter(|s| *s > 0)
+                    .ok_or_else(|| {
+                        QueueError::InvalidSchedule(format!("invalid interval: {seconds}"))
+                    })?;
+                Ok(Recurrence::Interval(Duration::seconds(seconds)))
+            }
+        }
+    }
+
+    // The first run at or after `start`
+    fn first_run(&self, start: DateTime<Utc>) -> Result<DateTime<Utc>, QueueError> {
+        match self {
+            Recurrence::Cron(cron, tz) => cron_occurrence(cron, tz, start, true),
+            Recurrence::Interval(_) => Ok(start),
+        }
+    }
+
+    // The first run strictly after `previous`
+    fn next_run(&self, previous: DateTime<Utc>) -> Result<DateTime<Utc>, QueueError> {
+        match self {
+            Recurrence::Cron(cron, tz) => cron_occurrence(cron, tz, previous, false),
+            Recurrence::Interval(interval) => Ok(previous + *interval),
+        }
+    }
+
+    // Walk the runs due between `next_run` and `now`. With `CatchUpPolicy::All`, this returns the
+    // oldest due runs and picks the schedule back up from the first run after them, so a long backlog
+    // is worked through over several janitor passes. Otherwise it returns the most recent due runs
+    // and picks the schedule back up from the first run after `now`.
+    fn due_runs(
+        &self,
+        next_run: DateTime<Utc>,
+        now: DateTime<Utc>,
+        policy: CatchUpPolicy,
+    ) -> Result<DueRuns, QueueError> {
+        let mut runs = VecDeque::new();
+        let mut run = next_run;
+
+        if policy == CatchUpPolicy::All {
+            while run <= now && runs.len() < MAX_RUNS_PER_SCHEDULE {
+                runs.push_back(run);
+                run = self.next_run(run)?;
+            }
+            return Ok(DueRuns {
+                runs,
+                next_run: run,
+                passed_over: 0,
+            });
+        }
+
+        if let Recurrence::Interval(interval) = self {
+            // Intervals can jump straight to the last due run, however many were missed
+            let mut passed_over = 0;
+            if run <= now {
+                let missed = (now - run).num_seconds() / interval.num_seconds();
+                let keep = missed.min(MAX_RUNS_PER_SCHEDULE as i64 - 1);
+                run += Duration::seconds(interval.num_seconds() * (missed - keep));
+                passed_over = (missed - keep) as u64;
+                while run <= now {
+                    runs.push_back(run);
+                    run += *interval;
+                }
+            }
+            return Ok(DueRuns {
+                runs,
+                next_run: run,
+                passed_over,
+            });
+        }
+
+        let mut scanned = 0;
+        while run <= now {
+            if scanned == MAX_MISSED_RUNS_SCANNED {
+                // Give up on catching up, and pick the schedule back up from now. We don't know how
+                // many runs we didn't get to, so they're not counted as passed over.
+                let passed_over = (scanned - runs.len()) as u64;
+                return Ok(DueRuns {
+                    runs,
+                    next_run: self.next_run(now)?,
+                    passed_over,
+                });
+            }
+            if runs.len() == MAX_RUNS_PER_SCHEDULE {
+                runs.pop_front();
+            }
+            runs.push_back(run);
+            scanned += 1;
+            run = self.next_run(run)?;
+        }
+        Ok(DueRuns {
+            passed_over: (scanned - runs.len()) as u64,
+            runs,
+            next_run: run,
+        })
+    }
+}
+
+// The runs of a schedule a janitor pass found due
+struct DueRuns {
+    runs: VecDeque<DateTime<Utc>>, // Oldest first, at most MAX_RUNS_PER_SCHEDULE of them
+    next_run: DateTime<Utc>,       // Where the schedule picks back up from
+    passed_over: u64,              // Due runs before `runs` that will never be enqueued
+}
+
+fn cron_occurrence(
+    cron: &Cron,
+    tz: &Tz,
+    from: DateTime<Utc>,
+    inclusive: bool,
+) -> Result<DateTime<Utc>, QueueError> {
+    cron.find_next_occurrence(&from.with_timezone(tz), inclusive)
+        .map(|next| next.with_timezone(&Utc))
+        .map_err(|e| QueueError::InvalidSchedule(format!("no next run for cron expression: {e}")))
+}
+
+// Pick which of the due runs of a schedule to actually enqueue
+fn runs_to_enqueue(
+    due: VecDeque<DateTime<Utc>>,
+    policy: CatchUpPolicy,
+    now: DateTime<Utc>,
+    lateness_tolerance: Duration,
+) -> Vec<DateTime<Utc>> {
+    match policy {
+        CatchUpPolicy::All => due.into(),
+        CatchUpPolicy::Latest => due.back().copied().into_iter().collect(),
+        CatchUpPolicy::Skip => due
+            .into_iter()
+            .filter(|run| now - *run <= lateness_tolerance)
+            .collect(),
+    }
+}
+
+fn spec_columns(spec: &ScheduleSpec) -> (Option<&str>, Option<i64>) {
+    match spec {
+        ScheduleSpec::Cron(expression) => (Some(expression.as_str()), None),
+        // Validated to fit when the recurrence was built
+        ScheduleSpec::Interval(seconds) => (None, Some(*seconds as i64)),
+    }
+}
+
+fn catch_up_to_str(policy: CatchUpPolicy) -> &'static str {
+    match policy {
+        CatchUpPolicy::Skip => "skip",
+        CatchUpPolicy::Latest => "latest",
+        CatchUpPolicy::All => "all",
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ScheduleRow {
+    id: Uuid,
+    team_id: i32,
+    function_id: Option<Uuid>,
+    queue_name: String,
+    priority: i16,
+    cron_expression: Option<String>,
+    interval_seconds: Option<i64>,
+    timezone: String,
+    catch_up: String,
+    next_run: DateTime<Utc>,
+    last_run: Option<DateTime<Utc>>,
+    created: DateTime<Utc>,
+    updated: DateTime<Utc>,
+    parameters: Option<Bytes>,
+    blob: Option<Bytes>,
+    metadata: Option<Bytes>,
+}
+
+impl TryFrom<ScheduleRow> for Schedule {
+    type Error = QueueError;
+
+    fn try_from(row: ScheduleRow) -> Result<Self, Self::Error> {
+        let spec = match (row.cron_expression, row.interval_seconds) {
+            (Some(expression), None) => ScheduleSpec::Cron(expression),
+            (None, Some(seconds)) if seconds > 0 => ScheduleSpec::Interval(seconds as u64),
+            _ => {
+                return Err(QueueError::InvalidSchedule(format!(
+                    "schedule {} has no valid spec",
+                    row.id
+                )))
+            }
+        };
+        let catch_up = match row.catch_up.as_str() {
+            "skip" => CatchUpPolicy::Skip,
+            "latest" => CatchUpPolicy::Latest,
+            "all" => CatchUpPolicy::All,
+            other => {
+                return Err(QueueError::InvalidSchedule(format!(
+                    "schedule {} has unknown catch-up policy {other}",
+                    row.id
+                )))
+            }
+        };
+
+        Ok(Schedule {
+            id: row.id,
+            team_id: row.team_id,
+            function_id: row.function_id,
+            queue_name: row.queue_name,
+            priority: row.priority,
+            spec,
+            timezone: row.timezone,
+            catch_up,
+            next_run: row.next_run,
+            last_run: row.last_run,
+            created: row.created,
+            updated: row.updated,
+            parameters: row.parameters,
+            blob: row.blob,
+            metadata: row.metadata,
+        })
+    }
+}
+
+fn timezone_or_default(init: &ScheduleInit) -> &str {
+    init.timezone.as_deref().unwrap_or("UTC")
+}
+
+pub async fn create_schedule<'c, E>(executor: E, init: ScheduleInit) -> Result<Uuid, QueueError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    let id = init.id.unwrap_or_else(Uuid::now_v7);
+    let timezone = timezone_or_default(&init);
+    let recurrence = Recurrence::new(&init.spec, timezone)?;
+    let next_run = recurrence.first_run(init.start.unwrap_or_else(Utc::now))?;
+    let (cron_expression, interval_seconds) = spec_columns(&init.spec);
+
+    sqlx::query(
+        r#"
+INSERT INTO cyclotron_schedules
+    (
+        id,
+        team_id,
+        function_id,
+        queue_name,
+        priority,
+        cron_expression,
+        interval_seconds,
+        timezone,
+        catch_up,
+        next_run,
+        last_run,
+        created,
+        updated,
+        parameters,
+        blob,
+        metadata
+    )
+VALUES
+    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NULL, NOW(), NOW(), $11, $12, $13)
+    "#,
+    )
+    .bind(id)
+    .bind(init.team_id)
+    .bind(init.function_id)
+    .bind(&init.queue_name)
+    .bind(init.priority)
+    .bind(cron_expression)
+    .bind(interval_seconds)
+    .bind(timezone)
+    .bind(catch_up_to_str(init.catch_up))
+    .bind(next_run)
+    .bind(&init.parameters)
+    .bind(&init.blob)
+    .bind(&init.metadata)
+    .execute(executor)
+    .await?;
+
+    Ok(id)
+}
+
+// Replace the definition of a schedule, recomputing its next run. Runs that were already due but not
+// yet enqueued are dropped. Returns false if there's no schedule with this id.
+pub async fn update_schedule<'c, E>(
+    executor: E,
+    id: Uuid,
+    init: ScheduleInit,
+) -> Result<bool, QueueError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    let timezone = timezone_or_default(&init);
+    let recurrence = Recurrence::new(&init.spec, timezone)?;
+    let next_run = recurrence.first_run(init.start.unwrap_or_else(Utc::now))?;
+    let (cron_expression, interval_seconds) = spec_columns(&init.spec);
+
+    let result = sqlx::query(
+        r#"
+UPDATE cyclotron_schedules
+SET
+    team_id = $2,
+    function_id = $3,
+    queue_name = $4,
+    priority = $5,
+    cron_expression = $6,
+    interval_seconds = $7,
+    timezone = $8,
+    catch_up = $9,
+    next_run = $10,
+    updated = NOW(),
+    parameters = $11,
+    blob = $12,
+    metadata = $13
+WHERE id = $1
+    "#,
+    )
+    .bind(id)
+    .bind(init.team_id)
+    .bind(init.function_id)
+    .bind(&init.queue_name)
+    .bind(init.priority)
+    .bind(cron_expression)
+    .bind(interval_seconds)
+    .bind(timezone)
+    .bind(catch_up_to_str(init.catch_up))
+    .bind(next_run)
+    .bind(&init.parameters)
+    .bind(&init.blob)
+    .bind(&init.metadata)
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+// Returns false if there's no schedule with this id. Jobs already enqueued by the schedule are left alone.
+pub async fn delete_schedule<'c, E>(executor: E, id: Uuid) -> Result<bool, QueueError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    let result = sqlx::query("DELETE FROM cyclotron_schedules WHERE id = $1")
+        .bind(id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_schedule<'c, E>(executor: E, id: Uuid) -> Result<Option<Schedule>, QueueError>
+where
+    E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+{
+    let row: Option<ScheduleRow> = sqlx::query_as(&format!(
+        "SELECT {SCHEDULE_COLUMNS} FROM cyclotron_schedules WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(executor)
+    .await?;
+
+    row.map(Schedule::try_from).transpose()
+}
+
+// Enqueue jobs for up to `limit` schedules with due runs, returning the number of jobs enqueued.
+// Schedules are locked for the duration of the transaction (and skipped by anyone else looking
+// for due schedules), and their next run is advanced in the same transaction as the job insert,
+// so concurrent janitors never enqueue the same run twice.
+pub async fn materialize_schedules(
+    pool: &PgPool,
+    limit: usize,
+    lateness_tolerance: Duration,
+) -> Result<u64, QueueError> {
+    let mut txn = pool.begin().await?;
+    let now = Utc::now();
+
+    let rows: Vec<ScheduleRow> = sqlx::query_as(&format!(
+        r#"
+SELECT {SCHEDULE_COLUMNS}
+FROM cyclotron_schedules
+WHERE next_run <= $1
+ORDER BY next_run
+LIMIT $2
+FOR UPDATE SKIP LOCKED
+    "#
+    ))
+    .bind(now)
+    .bind(limit as i64)
+    .fetch_all(&mut *txn)
+    .await?;
+
+    let mut jobs = Vec::new();
+    for row in rows {
+        let id = row.id;
+        let (next_run, last_run) = match plan_runs(row, now, lateness_tolerance, &mut jobs) {
+            Ok(runs) => runs,
+            Err(e) => {
+                warn!("Failed to evaluate schedule {id}, backing off: {e}");
+                (now + Duration::hours(BROKEN_SCHEDULE_BACKOFF_HOURS), None)
+            }
+        };
+
+        sqlx::query(
+            "UPDATE cyclotron_schedules SET next_run = $2, last_run = COALESCE($3, last_run) WHERE id = $1",
+        )
+        .bind(id)
+        .bind(next_run)
+        .bind(last_run)
+        .execute(&mut *txn)
+        .await?;
+    }
+
+    let created = jobs.len() as u64;
+    if !jobs.is_empty() {
+        bulk_create_jobs_upsert(&mut *txn, jobs, false).await?;
+    }
+    txn.commit().await?;
+
+    Ok(created)
+}
+
+// Push the jobs for a schedule's due runs into `jobs`, returning its new next and last run
+fn plan_runs(
+    row: ScheduleRow,
+    now: DateTime<Utc>,
+    lateness_tolerance: Duration,
+    jobs: &mut Vec<JobInit>,
+) -> Result<(DateTime<Utc>, Option<DateTime<Utc>>), QueueError> {
+    let schedule = Schedule::try_from(row)?;
+    let recurrence = Recurrence::new(&schedule.spec, &schedule.timezone)?;
+    let due = recurrence.due_runs(schedule.next_run, now, schedule.catch_up)?;
+    let due_count = due.runs.len();
+    let runs = runs_to_enqueue(due.runs, schedule.catch_up, now, lateness_tolerance);
+    let last_run = runs.last().copied();
+
+    let dropped = due.passed_over + (due_count - runs.len()) as u64;
+    if dropped > 0 {
+        let labels = [(
+            "catch_up".to_string(),
+            catch_up_to_str(schedule.catch_up).to_string(),
+        )];
+        inc(SCHEDULE_RUNS_DROPPED, &labels, dropped);
+    }
+
+    jobs.extend(runs.into_iter().map(|scheduled| JobInit {
+        id: None,
+        team_id: schedule.team_id,
+        queue_name: schedule.queue_name.clone(),
+        priority: schedule.priority,
+        scheduled,
+        function_id: schedule.function_id,
+        parent_run_id: None,
+        vm_state: None,
+        parameters: schedule.parameters.clone(),
+        blob: schedule.blob.clone(),
+        metadata: schedule.metadata.clone(),
+        idempotency_key: None,
+    }));
+
+    Ok((due.next_run, last_run))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, minute, second)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_interval_due_runs() {
+        let recurrence = Recurrence::new(&ScheduleSpec::Interval(60), "UTC").unwrap();
+
+        let due = recurrence
+            .due_runs(at(0, 0, 0), at(0, 2, 30), CatchUpPolicy::Latest)
+            .unwrap();
+        assert_eq!(
+            Vec::from(due.runs),
+            vec![at(0, 0, 0), at(0, 1, 0), at(0, 2, 0)]
+        );
+        assert_eq!(due.next_run, at(0, 3, 0));
+        assert_eq!(due.passed_over, 0);
+
+        let due = recurrence
+            .due_runs(at(1, 0, 0), at(0, 2, 30), CatchUpPolicy::Latest)
+            .unwrap();
+        assert!(due.runs.is_empty());
+        assert_eq!(due.next_run, at(1, 0, 0));
+    }
+
+    #[test]
+    fn test_interval_due_runs_are_capped() {
+        let recurrence = Recurrence::new(&ScheduleSpec::Interval(1), "UTC").unwrap();
+
+        let due = recurrence
+            .due_runs(at(0, 0, 0), at(10, 0, 0), CatchUpPolicy::Latest)
+            .unwrap();
+        assert_eq!(due.runs.len(), MAX_RUNS_PER_SCHEDULE);
+        assert_eq!(due.runs.back(), Some(&at(10, 0, 0)));
+        assert_eq!(due.next_run, at(10, 0, 1));
+        assert_eq!(due.passed_over, 36_001 - MAX_RUNS_PER_SCHEDULE as u64);
+    }
+
+    #[test]
+    fn test_catching_up_on_all_runs_resumes_after_the_last_enqueued_run() {
+        let recurrence = Recurrence::new(&ScheduleSpec::Interval(1), "UTC").unwrap();
+
+        let due = recurrence
+            .due_runs(at(0, 0, 0), at(10, 0, 0), CatchUpPolicy::All)
+            .unwrap();
+        assert_eq!(due.runs.len(), MAX_RUNS_PER_SCHEDULE);
+        assert_eq!(due.runs.front(), Some(&at(0, 0, 0)));
+        assert_eq!(due.runs.back(), Some(&at(0, 1, 39)));
+        assert_eq!(due.next_run, at(0, 1, 40));
+        assert_eq!(due.passed_over, 0);
+
+        let cron = Recurrence::new(&ScheduleSpec::Cron("* * * * *".to_string()), "UTC").unwrap();
+        let due = cron
+            .due_runs(at(0, 0, 0), at(10, 0, 0), CatchUpPolicy::All)
+            .unwrap();
+        assert_eq!(due.runs.len(), MAX_RUNS_PER_SCHEDULE);
+        assert_eq!(due.runs.back(), Some(&at(1, 39, 0)));
+        assert_eq!(due.next_run, at(1, 40, 0));
+    }
+
+    #[test]
+    fn test_cron_due_runs() {
+        let recurrence =
+            Recurrence::new(&ScheduleSpec::Cron("*/15 * * * *".to_string()), "UTC").unwrap();
+
+        assert_eq!(recurrence.first_run(at(0, 7, 0)).unwrap(), at(0, 15, 0));
+        assert_eq!(recurrence.first_run(at(0, 15, 0)).unwrap(), at(0, 15, 0));
+
+        let due = recurrence
+            .due_runs(at(0, 15, 0), at(1, 0, 0), CatchUpPolicy::Latest)
+            .unwrap();
+        assert_eq!(
+            Vec::from(due.runs),
+            vec![at(0, 15, 0), at(0, 30, 0), at(0, 45, 0), at(1, 0, 0)]
+        );
+        assert_eq!(due.next_run, at(1, 15, 0));
+    }
+
+    #[test]
+    fn test_cron_uses_timezone() {
+        let recurrence = Recurrence::new(
+            &ScheduleSpec::Cron("0 9 * * *".to_string()),
+            "Europe/Amsterdam",
+        )
+        .unwrap();
+
+        // 09:00 in Amsterdam is 08:00 UTC in winter
+        assert_eq!(recurrence.first_run(at(0, 0, 0)).unwrap(), at(8, 0, 0));
+    }
+
+    #[test]
+    fn test_catch_up_policies() {
+        let due = VecDeque::from(vec![at(0, 0, 0), at(0, 1, 0), at(0, 2, 0)]);
+        let now = at(0, 2, 30);
+        let tolerance = Duration::seconds(45);
+
+        assert_eq!(
+            runs_to_enqueue(due.clone(), CatchUpPolicy::All, now, tolerance),
+            vec![at(0, 0, 0), at(0, 1, 0), at(0, 2, 0)]
+        );
+        assert_eq!(
+            runs_to_enqueue(due.clone(), CatchUpPolicy::Latest, now, tolerance),
+            vec![at(0, 2, 0)]
+        );
+        assert_eq!(
+            runs_to_enqueue(due, CatchUpPolicy::Skip, now, tolerance),
+            vec![at(0, 2, 0)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_specs() {
+        assert!(Recurrence::new(&ScheduleSpec::Interval(0), "UTC").is_err());
+        assert!(Recurrence::new(&ScheduleSpec::Interval(60), "Nowhere/Special").is_err());
+        assert!(Recurrence::new(&ScheduleSpec::Cron("* * *".to_string()), "UTC").is_err());
+    }
+}
@@ -47,6 +47,10 @@ pub struct JobInit {
     pub parameters: Option<Bytes>,
     pub blob: Option<Bytes>,
     pub metadata: Option<Bytes>,
+    // If set, creating a job with the same key, team and queue within the manager's idempotency window
+    // returns the id of the first job instead of creating a new one
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -128,3 +132,65 @@ pub struct AggregatedDelete {
     pub state: String,
     pub count: i64,
 }
+
+// How often a schedule runs
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleSpec {
+    // A cron expression, with an optional leading seconds field, evaluated in the schedule's timezone
+    Cron(String),
+    // A fixed number of seconds between runs, counted from the schedule's start
+    Interval(u64),
+}
+
+// What to do with runs that were missed, e.g. because no janitor was running when they were due
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Eq, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CatchUpPolicy {
+    // Drop missed runs, only enqueueing runs the janitor gets to within its lateness tolerance
+    Skip,
+    // Enqueue a single job for the most recent missed run
+    #[default]
+    Latest,
+    // Enqueue a job for every missed run. Each janitor pass enqueues up to 100 of them per schedule,
+    // oldest first, and the next pass carries on from there.
+    All,
+}
+
+// The chunk of data needed to create (or replace) a schedule. Every run of the schedule enqueues a
+// job built from this template, scheduled at the time the run was due.
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct ScheduleInit {
+    pub id: Option<Uuid>,
+    pub team_id: i32,
+    pub function_id: Option<Uuid>,
+    pub queue_name: String,
+    pub priority: i16,
+    pub spec: ScheduleSpec,
+    pub timezone: Option<String>, // An IANA timezone name, defaults to UTC
+    #[serde(default)]
+    pub catch_up: CatchUpPolicy,
+    pub start: Option<DateTime<Utc>>, // No runs are due before this, defaults to now
+    pub parameters: Option<Bytes>,
+    pub blob: Option<Bytes>,
+    pub metadata: Option<Bytes>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Eq, PartialEq)]
+pub struct Schedule {
+    pub id: Uuid,
+    pub team_id: i32,
+    pub function_id: Option<Uuid>,
+    pub queue_name: String,
+    pub priority: i16,
+    pub spec: ScheduleSpec,
+    pub timezone: String,
+    pub catch_up: CatchUpPolicy,
+    pub next_run: DateTime<Utc>, // The next time a job is due to be enqueued
+    pub last_run: Option<DateTime<Utc>>, // The time the most recently enqueued job was due
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+    pub parameters: Option<Bytes>,
+    pub blob: Option<Bytes>,
+    pub metadata: Option<Bytes>,
+}
@@ -1,7 +1,7 @@
 use std::{
     collections::HashMap,
     future::Future,
-    sync::{Arc, Weak},
+    sync::{Arc, OnceLock, Weak},
     task::Poll,
 };
 
@@ -16,6 +16,7 @@ use uuid::Uuid;
 use crate::{
     config::WorkerConfig,
     error::JobError,
+    listener::QueueListener,
     ops::{
         meta::dead_letter,
         worker::{dequeue_jobs, dequeue_with_vm_state, flush_job, get_vm_state, set_heartbeat},
@@ -46,11 +47,16 @@ pub struct Worker {
     // some conditions.
     flush_batch: Arc<Mutex<FlushBatch>>,
 
+    // Started the first time the worker waits for jobs, so workers that only poll don't hold
+    // an extra connection open.
+    listener: OnceLock<QueueListener>,
+
     pub heartbeat_window: Duration, // The worker will only pass one heartbeat to the DB per job every heartbeat_window
     pub linger: Duration,           // Updates will be held at most this long
     pub max_buffered: usize,        // Updates will be flushed after this many are buffered
     pub max_bytes: usize, // Updates will be flushed after the vm_state and blob sizes combined exceed this
     pub should_compress_vm_state: bool, // Compress vm_state when persisting to the DB?
+    pub listen_poll_interval: Duration, // When waiting for jobs, poll at least this often, in case a notification is missed
 }
 
 impl Worker {
@@ -71,6 +77,8 @@ impl Worker {
             max_buffered: worker_config.max_updates_buffered(),
             max_bytes: worker_config.max_bytes_buffered(),
             should_compress_vm_state: worker_config.should_compress_vm_state(),
+            listener: OnceLock::new(),
+            listen_poll_interval: worker_config.listen_poll_interval(),
         };
 
         tokio::spawn(flush_loop(
@@ -124,6 +132,72 @@ impl Worker {
         Ok(jobs)
     }
 
+    /// Dequeues jobs from the queue, waiting up to `max_wait` for some to become available if there
+    /// are none. The worker is woken by the manager's notifications (if the manager is configured to
+    /// send them), and polls every `listen_poll_interval` regardless, so jobs that become due through
+    /// other means (scheduled jobs, retries, janitor resets) are still picked up. Returns an empty vec
+    /// if no jobs became available in time.
+    pub async fn wait_for_jobs(
+        &self,
+        queue: &str,
+        limit: usize,
+        max_wait: Duration,
+    ) -> Result<Vec<Job>, QueueError> {
+        self.wait_for(queue, max_wait, || self.dequeue_jobs(queue, limit))
+            .await
+    }
+
+    /// This is the same as wait_for_jobs, but it also returns the vm_state of the job
+    pub async fn wait_for_jobs_with_vm_state(
+        &self,
+        queue: &str,
+        limit: usize,
+        max_wait: Duration,
+    ) -> Result<Vec<Job>, QueueError> {
+        self.wait_for(queue, max_wait, || self.dequeue_with_vm_state(queue, limit))
+            .await
+    }
+
+    async fn wait_for<F, Fut>(
+        &self,
+        queue: &str,
+        max_wait: Duration,
+        dequeue: F,
+    ) -> Result<Vec<Job>, QueueError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<Vec<Job>, QueueError>>,
+    {
+        let notify = self
+            .listener
+            .get_or_init(|| QueueListener::new(self.pool.clone()))
+            .subscribe(queue);
+
+        let now = tokio::time::Instant::now();
+        let deadline = now + max_wait.to_std().unwrap_or_default();
+        let poll_interval = self.listen_poll_interval.to_std().unwrap_or_default();
+
+        loop {
+            // Register interest before dequeuing, so a notification sent between an empty
+            // dequeue and us starting to wait isn't lost.
+            let notified = notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let jobs = dequeue().await?;
+            let now = tokio::time::Instant::now();
+            if !jobs.is_empty() || now >= deadline {
+                return Ok(jobs);
+            }
+
+            let poll_at = deadline.min(now + poll_interval);
+            tokio::select! {
+                _ = notified => {}
+                _ = tokio::time::sleep_until(poll_at) => {}
+            }
+        }
+    }
+
     /// Retrieve the VM state for a job, if, for example, you dequeued it and then realised you
     /// need the VM state as well.
     pub async fn get_vm_state(&self, job_id: Uuid) -> Result<Option<Bytes>, QueueError> {
@@ -17,6 +17,7 @@ pub fn create_new_job() -> JobInit {
         parameters: None,
         blob: None,
         metadata: None,
+        idempotency_key: None,
     }
 }
 
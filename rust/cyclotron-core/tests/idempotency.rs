@@ -0,0 +1,129 @@
+use chrono::Duration;
+use common::create_new_job;
+use cyclotron_core::{JobInit, QueueManager, Worker};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+mod common;
+
+fn create_keyed_job(key: &str) -> JobInit {
+    let mut job = create_new_job();
+    job.idempotency_key = Some(key.to_string());
+    job
+}
+
+async fn count_jobs(db: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM cyclotron_jobs")
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_create_job_returns_existing_id_for_key(db: PgPool) {
+    for use_bulk_job_copy in [false, true] {
+        sqlx::query("TRUNCATE cyclotron_jobs, cyclotron_idempotency_keys")
+            .execute(&db)
+            .await
+            .unwrap();
+        let manager = QueueManager::from_pool(db.clone(), false, use_bulk_job_copy);
+
+        let first = manager.create_job(create_keyed_job("a")).await.unwrap();
+        let second = manager.create_job(create_keyed_job("a")).await.unwrap();
+        let other = manager.create_job(create_keyed_job("b")).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+        assert_eq!(count_jobs(&db).await, 2);
+
+        // Keys are scoped to a team and queue
+        let mut other_team = create_keyed_job("a");
+        other_team.team_id = 2;
+        let mut other_queue = create_keyed_job("a");
+        other_queue.queue_name = "other".to_string();
+        assert_ne!(manager.create_job(other_team).await.unwrap(), first);
+        assert_ne!(manager.create_job(other_queue).await.unwrap(), first);
+        assert_eq!(count_jobs(&db).await, 4);
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_bulk_create_jobs_dedupes_keys(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, true);
+
+    let existing = manager.create_job(create_keyed_job("a")).await.unwrap();
+
+    let ids = manager
+        .bulk_create_jobs(vec![
+            create_keyed_job("a"),
+            create_new_job(),
+            create_keyed_job("b"),
+            create_keyed_job("b"),
+            create_new_job(),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(ids.len(), 5);
+    assert_eq!(ids[0], existing);
+    assert_eq!(ids[2], ids[3]);
+    assert_ne!(ids[1], ids[4]);
+    // The existing job, "b", and the two jobs without a key
+    assert_eq!(count_jobs(&db).await, 4);
+
+    let mut worker = Worker::from_pool(db.clone(), Default::default());
+    worker.max_buffered = 0;
+    let dequeued: Vec<Uuid> = worker
+        .dequeue_jobs("test", 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|job| job.id)
+        .collect();
+    for id in [ids[0], ids[1], ids[2], ids[4]] {
+        assert!(dequeued.contains(&id));
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_concurrent_creates_with_same_key(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+
+    let ids = futures::future::join_all(
+        (0..10).map(|_| manager.create_job(create_keyed_job("contended"))),
+    )
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()
+    .unwrap();
+
+    assert!(ids.iter().all(|id| *id == ids[0]));
+    assert_eq!(count_jobs(&db).await, 1);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_expired_keys_create_new_jobs(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+    let janitor = cyclotron_core::Janitor::from_pool(db.clone());
+
+    let first = manager.create_job(create_keyed_job("a")).await.unwrap();
+    assert_eq!(janitor.delete_expired_idempotency_keys().await.unwrap(), 0);
+
+    // Pretend the window has passed
+    sqlx::query("UPDATE cyclotron_idempotency_keys SET expires_at = NOW() - $1")
+        .bind(Duration::seconds(1))
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let second = manager.create_job(create_keyed_job("a")).await.unwrap();
+    assert_ne!(first, second);
+    assert_eq!(count_jobs(&db).await, 2);
+
+    sqlx::query("UPDATE cyclotron_idempotency_keys SET expires_at = NOW() - $1")
+        .bind(Duration::seconds(1))
+        .execute(&db)
+        .await
+        .unwrap();
+    assert_eq!(janitor.delete_expired_idempotency_keys().await.unwrap(), 1);
+}
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use common::create_new_job;
+use cyclotron_core::{test_support::Shard, Worker, WorkerConfig};
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+mod common;
+
+fn notifying_shard(db: PgPool) -> Shard {
+    Shard {
+        pool: db,
+        last_healthy: RwLock::new(Utc::now()),
+        check_interval: Duration::seconds(10),
+        depth_limit: 1000,
+        should_compress_vm_state: false,
+        should_use_bulk_job_copy: false,
+        idempotency_window: Duration::hours(1),
+        should_notify_workers: true,
+    }
+}
+
+// A worker that would take a minute to notice new jobs by polling alone
+fn slow_polling_worker(db: PgPool) -> Worker {
+    let config = WorkerConfig {
+        listen_poll_interval_ms: Some(60_000),
+        ..Default::default()
+    };
+    let mut worker = Worker::from_pool(db, config);
+    worker.max_buffered = 0;
+    worker
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_waiting_worker_is_notified(db: PgPool) {
+    let shard = notifying_shard(db.clone());
+    let worker = Arc::new(slow_polling_worker(db.clone()));
+
+    let waiting = {
+        let worker = worker.clone();
+        tokio::spawn(async move {
+            let start = Utc::now();
+            let jobs = worker
+                .wait_for_jobs("test", 10, Duration::seconds(30))
+                .await
+                .unwrap();
+            (jobs, Utc::now() - start)
+        })
+    };
+
+    // Give the worker a chance to start listening, and find the queue empty
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    let id = shard.create_job(create_new_job()).await.unwrap();
+
+    let (jobs, waited) = waiting.await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].id, id);
+    assert!(waited < Duration::seconds(10));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_waiting_worker_ignores_other_queues(db: PgPool) {
+    let shard = notifying_shard(db.clone());
+    let worker = slow_polling_worker(db.clone());
+
+    let mut job = create_new_job();
+    job.queue_name = "other".to_string();
+
+    let (jobs, _) = tokio::join!(
+        worker.wait_for_jobs("test", 10, Duration::seconds(2)),
+        async {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            shard.create_job(job).await.unwrap();
+        }
+    );
+    assert!(jobs.unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_waiting_worker_falls_back_to_polling(db: PgPool) {
+    // Without notifications, the worker still finds jobs on its next poll
+    let manager = cyclotron_core::QueueManager::from_pool(db.clone(), false, false);
+    let mut worker = Worker::from_pool(
+        db.clone(),
+        WorkerConfig {
+            listen_poll_interval_ms: Some(100),
+            ..Default::default()
+        },
+    );
+    worker.max_buffered = 0;
+
+    let (jobs, _) = tokio::join!(
+        worker.wait_for_jobs("test", 10, Duration::seconds(30)),
+        async {
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            manager.create_job(create_new_job()).await.unwrap();
+        }
+    );
+    assert_eq!(jobs.unwrap().len(), 1);
+
+    // And gives up once the wait is over
+    let start = Utc::now();
+    let jobs = worker
+        .wait_for_jobs("test", 10, Duration::milliseconds(300))
+        .await
+        .unwrap();
+    assert!(jobs.is_empty());
+    assert!(Utc::now() - start >= Duration::milliseconds(300));
+}
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Duration, Timelike, Utc};
+use chrono_tz::America::New_York;
+use cyclotron_core::{
+    CatchUpPolicy, Janitor, QueueError, QueueManager, ScheduleInit, ScheduleSpec, Worker,
+};
+use sqlx::PgPool;
+
+mod common;
+
+fn create_new_schedule(spec: ScheduleSpec, catch_up: CatchUpPolicy) -> ScheduleInit {
+    ScheduleInit {
+        id: None,
+        team_id: 1,
+        function_id: None,
+        queue_name: "test".to_string(),
+        priority: 0,
+        spec,
+        timezone: None,
+        catch_up,
+        start: None,
+        parameters: Some(b"parameters".to_vec()),
+        blob: None,
+        metadata: None,
+    }
+}
+
+// An every-minute schedule that started 10.5 minutes ago, so 11 runs are due, the latest 30 seconds ago
+fn create_overdue_schedule(catch_up: CatchUpPolicy) -> ScheduleInit {
+    let mut init = create_new_schedule(ScheduleSpec::Interval(60), catch_up);
+    init.start = Some(Utc::now() - Duration::seconds(630));
+    init
+}
+
+async fn count_jobs(db: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT COUNT(*) FROM cyclotron_jobs")
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_schedule_catch_up_policies(db: PgPool) {
+    let janitor = Janitor::from_pool(db.clone());
+    let tolerance = Duration::seconds(10);
+
+    let expected = [
+        (CatchUpPolicy::All, 11),
+        (CatchUpPolicy::Latest, 1),
+        (CatchUpPolicy::Skip, 0),
+    ];
+
+    for (policy, expected_jobs) in expected {
+        sqlx::query("DELETE FROM cyclotron_schedules")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("DELETE FROM cyclotron_jobs")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let manager = QueueManager::from_pool(db.clone(), false, false);
+        let id = manager
+            .create_schedule(create_overdue_schedule(policy))
+            .await
+            .unwrap();
+
+        let created = janitor.materialize_schedules(100, tolerance).await.unwrap();
+        assert_eq!(created, expected_jobs, "{policy:?}");
+        assert_eq!(count_jobs(&db).await, expected_jobs as i64, "{policy:?}");
+
+        // The next run is in the future, and nothing more is due
+        let schedule = manager.get_schedule(id).await.unwrap().unwrap();
+        assert!(schedule.next_run > Utc::now());
+        assert_eq!(
+            janitor.materialize_schedules(100, tolerance).await.unwrap(),
+            0
+        );
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_catching_up_on_all_runs_spans_janitor_passes(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+    let janitor = Janitor::from_pool(db.clone());
+
+    // An every-second schedule with 150 runs due, more than a single pass enqueues
+    let mut init = create_new_schedule(ScheduleSpec::Interval(1), CatchUpPolicy::All);
+    init.start = Some(Utc::now() - Duration::seconds(149));
+    let id = manager.create_schedule(init).await.unwrap();
+
+    let tolerance = Duration::seconds(10);
+    assert_eq!(
+        janitor.materialize_schedules(100, tolerance).await.unwrap(),
+        100
+    );
+    let schedule = manager.get_schedule(id).await.unwrap().unwrap();
+    assert!(schedule.next_run < Utc::now());
+
+    // The next pass picks up from the first run that wasn't enqueued
+    assert!(janitor.materialize_schedules(100, tolerance).await.unwrap() >= 50);
+    let schedule = manager.get_schedule(id).await.unwrap().unwrap();
+    assert!(schedule.next_run > Utc::now() - Duration::seconds(2));
+
+    let jobs: i64 = sqlx::query_scalar("SELECT COUNT(DISTINCT scheduled) FROM cyclotron_jobs")
+        .fetch_one(&db)
+        .await
+        .unwrap();
+    assert_eq!(jobs, count_jobs(&db).await);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_scheduled_jobs_match_template(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+    let janitor = Janitor::from_pool(db.clone());
+    let mut worker = Worker::from_pool(db.clone(), Default::default());
+    worker.max_buffered = 0;
+
+    let init = create_overdue_schedule(CatchUpPolicy::Latest);
+    let id = manager.create_schedule(init.clone()).await.unwrap();
+    janitor
+        .materialize_schedules(100, Duration::seconds(10))
+        .await
+        .unwrap();
+
+    let jobs = worker.dequeue_jobs("test", 10).await.unwrap();
+    assert_eq!(jobs.len(), 1);
+    let job = &jobs[0];
+    assert_eq!(job.team_id, init.team_id);
+    assert_eq!(job.parameters, init.parameters);
+
+    // Jobs are scheduled at the time their run was due
+    let schedule = manager.get_schedule(id).await.unwrap().unwrap();
+    assert!(common::dates_match(
+        &job.scheduled,
+        &schedule.last_run.unwrap()
+    ));
+    assert!(common::dates_match(
+        &(job.scheduled + Duration::seconds(60)),
+        &schedule.next_run
+    ));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_concurrent_janitors_enqueue_runs_once(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+    for _ in 0..20 {
+        manager
+            .create_schedule(create_overdue_schedule(CatchUpPolicy::All))
+            .await
+            .unwrap();
+    }
+
+    let janitors: Vec<Janitor> = (0..4).map(|_| Janitor::from_pool(db.clone())).collect();
+    let results = futures::future::join_all(janitors.iter().map(|janitor| async move {
+        let mut created = 0;
+        loop {
+            let batch = janitor
+                .materialize_schedules(3, Duration::seconds(10))
+                .await
+                .unwrap();
+            if batch == 0 {
+                return created;
+            }
+            created += batch;
+        }
+    }))
+    .await;
+
+    assert_eq!(results.iter().sum::<u64>(), 20 * 11);
+    assert_eq!(count_jobs(&db).await, 20 * 11);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_cron_schedule_uses_timezone(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+
+    let mut init = create_new_schedule(
+        ScheduleSpec::Cron("0 9 * * *".to_string()),
+        CatchUpPolicy::default(),
+    );
+    init.timezone = Some("America/New_York".to_string());
+    let id = manager.create_schedule(init).await.unwrap();
+
+    let schedule = manager.get_schedule(id).await.unwrap().unwrap();
+    let local: DateTime<_> = schedule.next_run.with_timezone(&New_York);
+    assert_eq!((local.hour(), local.minute(), local.second()), (9, 0, 0));
+    assert!(schedule.next_run > Utc::now());
+    assert!(schedule.next_run <= Utc::now() + Duration::days(1));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_update_and_delete_schedule(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+    let janitor = Janitor::from_pool(db.clone());
+
+    let id = manager
+        .create_schedule(create_overdue_schedule(CatchUpPolicy::All))
+        .await
+        .unwrap();
+
+    // Moving the start into the future means nothing is due any more
+    let mut update = create_new_schedule(ScheduleSpec::Interval(3600), CatchUpPolicy::Skip);
+    update.start = Some(Utc::now() + Duration::hours(1));
+    manager.update_schedule(id, update).await.unwrap();
+
+    let schedule = manager.get_schedule(id).await.unwrap().unwrap();
+    assert_eq!(schedule.spec, ScheduleSpec::Interval(3600));
+    assert_eq!(schedule.catch_up, CatchUpPolicy::Skip);
+    assert_eq!(
+        janitor
+            .materialize_schedules(100, Duration::seconds(10))
+            .await
+            .unwrap(),
+        0
+    );
+
+    manager.delete_schedule(id).await.unwrap();
+    assert!(manager.get_schedule(id).await.unwrap().is_none());
+    assert!(matches!(
+        manager.delete_schedule(id).await,
+        Err(QueueError::UnknownScheduleId(_))
+    ));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_invalid_schedules_are_rejected(db: PgPool) {
+    let manager = QueueManager::from_pool(db.clone(), false, false);
+
+    let invalid = [
+        create_new_schedule(
+            ScheduleSpec::Cron("not a cron".to_string()),
+            CatchUpPolicy::All,
+        ),
+        create_new_schedule(ScheduleSpec::Interval(0), CatchUpPolicy::All),
+        ScheduleInit {
+            timezone: Some("Mars/Olympus_Mons".to_string()),
+            ..create_new_schedule(ScheduleSpec::Interval(60), CatchUpPolicy::All)
+        },
+    ];
+
+    for init in invalid {
+        assert!(matches!(
+            manager.create_schedule(init).await,
+            Err(QueueError::InvalidSchedule(_))
+        ));
+    }
+}
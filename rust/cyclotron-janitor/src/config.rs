@@ -53,6 +53,12 @@ pub struct Config {
     #[envconfig(default = "60")]
     pub janitor_stall_timeout_seconds: u16,
 
+    #[envconfig(default = "100")]
+    pub schedule_batch_size: usize, // The most schedules a single janitor loop will enqueue due runs for
+
+    #[envconfig(default = "60")]
+    pub schedule_lateness_tolerance_seconds: u32, // Runs due longer ago than this are dropped by schedules that skip missed runs
+
     #[envconfig(nested = true)]
     pub kafka: KafkaConfig,
 
@@ -81,6 +87,10 @@ impl Config {
             max_touches: self.janitor_max_touches,
             id: self.janitor_id.clone(),
             shard_id: self.shard_id.clone(),
+            schedule_batch_size: self.schedule_batch_size,
+            schedule_lateness_tolerance: Duration::seconds(
+                self.schedule_lateness_tolerance_seconds as i64,
+            ),
         };
 
         JanitorConfig {
@@ -102,4 +112,6 @@ pub struct JanitorSettings {
     pub max_touches: i16,
     pub id: String,
     pub shard_id: String,
+    pub schedule_batch_size: usize,
+    pub schedule_lateness_tolerance: Duration,
 }
@@ -23,6 +23,7 @@ pub struct CleanupResult {
     pub canceled: u64,
     pub poisoned: u64,
     pub stalled: u64,
+    pub scheduled: u64,
 }
 
 pub struct Janitor {
@@ -134,6 +135,28 @@ impl Janitor {
             warn!("Reset {} stalled jobs", stalled);
         }
 
+        let scheduled = {
+            let _time = common_metrics::timing_guard(SCHEDULED_TIME, &self.metrics_labels);
+            self.inner
+                .materialize_schedules(
+                    self.settings.schedule_batch_size,
+                    self.settings.schedule_lateness_tolerance,
+                )
+                .await?
+        };
+        common_metrics::inc(SCHEDULED_COUNT, &self.metrics_labels, scheduled);
+
+        let expired_keys = {
+            let _time =
+                common_metrics::timing_guard(EXPIRED_IDEMPOTENCY_KEYS_TIME, &self.metrics_labels);
+            self.inner.delete_expired_idempotency_keys().await?
+        };
+        common_metrics::inc(
+            EXPIRED_IDEMPOTENCY_KEYS_COUNT,
+            &self.metrics_labels,
+            expired_keys,
+        );
+
         let available = {
             let _time = common_metrics::timing_guard(AVAILABLE_DEPTH_TIME, &self.metrics_labels);
             self.inner.waiting_jobs().await?
@@ -160,6 +183,7 @@ impl Janitor {
             canceled: canceled_count,
             poisoned,
             stalled,
+            scheduled,
         })
     }
 }
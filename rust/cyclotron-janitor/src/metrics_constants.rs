@@ -13,6 +13,12 @@ pub const POISONED_TIME: &str = "cyclotron_janitor_poison_pills_cleanup_ms";
 pub const STALLED_COUNT: &str = "cyclotron_janitor_stalled_jobs_reset";
 pub const STALLED_TIME: &str = "cyclotron_janitor_stalled_jobs_reset_ms";
 
+pub const SCHEDULED_COUNT: &str = "cyclotron_janitor_scheduled_jobs";
+pub const SCHEDULED_TIME: &str = "cyclotron_janitor_scheduled_jobs_ms";
+
+pub const EXPIRED_IDEMPOTENCY_KEYS_COUNT: &str = "cyclotron_janitor_expired_idempotency_keys";
+pub const EXPIRED_IDEMPOTENCY_KEYS_TIME: &str = "cyclotron_janitor_expired_idempotency_keys_ms";
+
 // The janitor should report some basic shard-level metrics
 pub const AVAILABLE_DEPTH: &str = "cyclotron_available_jobs";
 pub const AVAILABLE_DEPTH_TIME: &str = "cyclotron_available_jobs_ms";
@@ -51,6 +51,8 @@ async fn janitor_test(db: PgPool) {
         max_touches,
         id: "test_janitor".to_string(),
         shard_id: "test_shard".to_string(),
+        schedule_batch_size: 100,
+        schedule_lateness_tolerance: Duration::seconds(60),
     };
     let janitor = Janitor {
         inner: cyclotron_core::Janitor::from_pool(db.clone()),
@@ -75,6 +77,7 @@ async fn janitor_test(db: PgPool) {
         parameters: None,
         blob: None,
         metadata: None,
+        idempotency_key: None,
     };
 
     // if we mark a job as completed, the janitor will clean it up
@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 
 use cyclotron_core::{
     Job, JobInit, JobState, ManagerConfig, PoolConfig, QueueManager, Worker, WorkerConfig,
@@ -204,6 +204,8 @@ pub struct JsJob {
     pub vm_state: Option<String>,
     pub parameters: Option<String>,
     pub metadata: Option<String>,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 fn create_job(mut cx: FunctionContext) -> JsResult<JsPromise> {
@@ -514,6 +516,54 @@ fn dequeue_with_vm_state(mut cx: FunctionContext) -> JsResult<JsPromise> {
     Ok(promise)
 }
 
+fn wait_for_jobs_impl(mut cx: FunctionContext, with_vm_state: bool) -> JsResult<JsPromise> {
+    let queue_name = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    let limit = cx.argument::<JsNumber>(1)?.value(&mut cx) as usize; // TODO - I don't love this cast
+
+    let max_wait = Duration::milliseconds(cx.argument::<JsNumber>(2)?.value(&mut cx) as i64);
+
+    let (deferred, promise) = cx.promise();
+    let channel = cx.channel();
+    let runtime = runtime(&mut cx)?;
+
+    let fut = async move {
+        let worker = match WORKER.get() {
+            Some(worker) => worker,
+            None => {
+                deferred.settle_with(&channel, |mut cx| {
+                    throw_null_err(&mut cx, "worker not initialized")
+                });
+                return;
+            }
+        };
+        let jobs = if with_vm_state {
+            worker
+                .wait_for_jobs_with_vm_state(&queue_name, limit, max_wait)
+                .await
+        } else {
+            worker.wait_for_jobs(&queue_name, limit, max_wait).await
+        };
+        deferred.settle_with(&channel, move |mut cx| {
+            let jobs = jobs.or_else(|e| cx.throw_error(format!("{e}")))?;
+            let jobs = jobs_to_js_array(&mut cx, jobs)?;
+            Ok(jobs)
+        });
+    };
+
+    runtime.spawn(fut);
+
+    Ok(promise)
+}
+
+fn wait_for_jobs(cx: FunctionContext) -> JsResult<JsPromise> {
+    wait_for_jobs_impl(cx, false)
+}
+
+fn wait_for_jobs_with_vm_state(cx: FunctionContext) -> JsResult<JsPromise> {
+    wait_for_jobs_impl(cx, true)
+}
+
 fn release_job(mut cx: FunctionContext) -> JsResult<JsPromise> {
     let arg1 = cx.argument::<JsString>(0)?.value(&mut cx);
     let job_id: Uuid = arg1
@@ -896,6 +946,7 @@ impl JsJob {
             parameters: self.parameters.as_ref().map(|s| s.as_bytes().to_vec()),
             metadata: self.metadata.as_ref().map(|s| s.as_bytes().to_vec()),
             blob,
+            idempotency_key: self.idempotency_key.clone(),
         }
     }
 }
@@ -915,6 +966,8 @@ fn main(mut cx: ModuleContext) -> NeonResult<()> {
     cx.export_function("shadowBulkCreateJobs", shadow_bulk_create_jobs)?;
     cx.export_function("dequeueJobs", dequeue_jobs)?;
     cx.export_function("dequeueJobsWithVmState", dequeue_with_vm_state)?;
+    cx.export_function("waitForJobs", wait_for_jobs)?;
+    cx.export_function("waitForJobsWithVmState", wait_for_jobs_with_vm_state)?;
     cx.export_function("releaseJob", release_job)?;
     cx.export_function("forceFlush", force_flush)?;
     cx.export_function("setState", set_state)?;
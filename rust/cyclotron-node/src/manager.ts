@@ -10,6 +10,9 @@ type CyclotronManagerInternalConfig = {
     shardDepthCheckIntervalSeconds?: number
     shouldCompressVmState?: boolean
     shouldUseBulkJobCopy?: boolean
+    idempotencyWindowSeconds?: number
+    /** Whether to NOTIFY waiting workers when jobs are created. Default false */
+    shouldNotifyWorkers?: boolean
 }
 
 export type CyclotronManagerConfig = Omit<CyclotronManagerInternalConfig, 'shards'> & {
@@ -28,6 +31,8 @@ export class CyclotronManager {
             shardDepthCheckIntervalSeconds: this.config.shardDepthCheckIntervalSeconds,
             shouldCompressVmState: this.config.shouldCompressVmState,
             shouldUseBulkJobCopy: this.config.shouldUseBulkJobCopy,
+            idempotencyWindowSeconds: this.config.idempotencyWindowSeconds,
+            shouldNotifyWorkers: this.config.shouldNotifyWorkers,
         }
         return await cyclotron.maybeInitManager(JSON.stringify(config))
     }
@@ -47,6 +52,7 @@ export class CyclotronManager {
             vm_state: job.vmState ? serializeObject('vmState', job.vmState) : null,
             parameters: job.parameters ? serializeObject('parameters', job.parameters) : null,
             metadata: job.metadata ? serializeObject('metadata', job.metadata) : null,
+            idempotency_key: job.idempotencyKey,
         }
 
         const json = JSON.stringify(jobInitInternal)
@@ -68,6 +74,7 @@ export class CyclotronManager {
                 vm_state: job.vmState ? serializeObject('vmState', job.vmState) : null,
                 parameters: job.parameters ? serializeObject('parameters', job.parameters) : null,
                 metadata: job.metadata ? serializeObject('metadata', job.metadata) : null,
+                idempotency_key: job.idempotencyKey,
             }
         })
         const json = JSON.stringify(jobInitsInternal)
@@ -111,6 +118,8 @@ export class CyclotronShadowManager {
             shardDepthCheckIntervalSeconds: this.config.shardDepthCheckIntervalSeconds,
             shouldCompressVmState: this.config.shouldCompressVmState,
             shouldUseBulkJobCopy: this.config.shouldUseBulkJobCopy,
+            idempotencyWindowSeconds: this.config.idempotencyWindowSeconds,
+            shouldNotifyWorkers: this.config.shouldNotifyWorkers,
         }
         return await cyclotron.maybeInitShadowManager(JSON.stringify(config))
     }
@@ -129,6 +138,7 @@ export class CyclotronShadowManager {
             vm_state: job.vmState ? serializeObject('vmState', job.vmState) : null,
             parameters: job.parameters ? serializeObject('parameters', job.parameters) : null,
             metadata: job.metadata ? serializeObject('metadata', job.metadata) : null,
+            idempotency_key: job.idempotencyKey,
         }
 
         const json = JSON.stringify(jobInitInternal)
@@ -150,6 +160,7 @@ export class CyclotronShadowManager {
                 vm_state: job.vmState ? serializeObject('vmState', job.vmState) : null,
                 parameters: job.parameters ? serializeObject('parameters', job.parameters) : null,
                 metadata: job.metadata ? serializeObject('metadata', job.metadata) : null,
+                idempotency_key: job.idempotencyKey,
             }
         })
         const json = JSON.stringify(jobInitsInternal)
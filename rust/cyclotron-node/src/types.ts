@@ -42,7 +42,11 @@ export type CyclotronJob = {
 }
 
 export type CyclotronJobInit = Pick<CyclotronJob, 'teamId' | 'functionId' | 'queueName' | 'priority'> &
-        Pick<Partial<CyclotronJob>, 'id' | 'scheduled' | 'parentRunId' | 'vmState' | 'parameters' | 'metadata' | 'blob'>
+        Pick<Partial<CyclotronJob>, 'id' | 'scheduled' | 'parentRunId' | 'vmState' | 'parameters' | 'metadata' | 'blob'> & {
+        // Creating a job with the same key, team and queue within the manager's idempotency window returns the
+        // id of the first job instead of creating another one
+        idempotencyKey?: string | null
+    }
 
 export type CyclotronJobUpdate = Pick<
     Partial<CyclotronJob>,
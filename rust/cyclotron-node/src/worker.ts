@@ -28,6 +28,8 @@ type CyclotronWorkerNodeConfig = {
     includeVmState?: boolean
     /** Amount of delay between dequeue polls. Default: 50ms */
     pollDelayMs?: number
+    /** If set, wait up to this long for jobs to become available instead of polling every pollDelayMs. Should be set on the manager too (shouldNotifyWorkers), and kept below heartbeatTimeoutMs. Default: unset */
+    maxWaitMs?: number
     /** Heartbeat timeout. After this time without response from the worker loop the worker will be considered unhealthy. Default 30000 */
     heartbeatTimeoutMs?: number
     /** Include empty batches - useful if you want to track them. Default: false */
@@ -50,6 +52,8 @@ type CyclotronWorkerInternalConfig = {
     flushLoopIntervalMs?: number
     /** Whether to compress vmState. Default false */
     shouldCompressVmState?: boolean
+    /** While waiting for jobs, how often to poll in case a notification was missed. Default 1000 */
+    listenPollIntervalMs?: number
 }
 
 
@@ -82,6 +86,7 @@ export class CyclotronWorker {
             maxBytesBuffered: this.config.maxBytesBuffered ?? 10000000,
             flushLoopIntervalMs: this.config.flushLoopIntervalMs ?? 10,
             shouldCompressVmState: this.config.shouldCompressVmState ?? false,
+            listenPollIntervalMs: this.config.listenPollIntervalMs ?? 1000,
         }
 
         await cyclotron.maybeInitWorker(
@@ -102,19 +107,26 @@ export class CyclotronWorker {
 
             const batchMaxSize = this.config.batchMaxSize ?? 100
             const pollDelayMs = this.config.pollDelayMs ?? 50
+            const maxWaitMs = this.config.maxWaitMs
 
             while (this.isConsuming) {
                 this.lastHeartbeat = new Date()
 
                 const jobs = (
-                    this.config.includeVmState
+                    maxWaitMs !== undefined
+                        ? this.config.includeVmState
+                            ? await cyclotron.waitForJobsWithVmState(this.config.queueName, batchMaxSize, maxWaitMs)
+                            : await cyclotron.waitForJobs(this.config.queueName, batchMaxSize, maxWaitMs)
+                        : this.config.includeVmState
                         ? await cyclotron.dequeueJobsWithVmState(this.config.queueName, batchMaxSize)
                         : await cyclotron.dequeueJobs(this.config.queueName, batchMaxSize)
                 ).map(parseJob)
 
                 if (!jobs.length) {
-                    // Wait a bit before polling again
-                    await new Promise((resolve) => setTimeout(resolve, pollDelayMs))
+                    // Wait a bit before polling again, unless we already waited for jobs
+                    if (maxWaitMs === undefined) {
+                        await new Promise((resolve) => setTimeout(resolve, pollDelayMs))
+                    }
                     if (this.config.includeEmptyBatches) {
                         await processBatch(jobs)
                     }
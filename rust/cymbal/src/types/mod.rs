@@ -423,6 +423,7 @@ impl OutputErrProps {
                 EmbeddingModel::OpenAITextEmbeddingSmall,
             ],
             metadata: Default::default(),
+            chunking: None,
         }
     }
 }
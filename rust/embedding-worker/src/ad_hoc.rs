@@ -5,8 +5,8 @@ use common_types::embedding::EmbeddingModel;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    app_context::AppContext, generate_embedding, metrics_utils::RequestLabels,
-    organization::apply_ai_opt_in,
+    app_context::AppContext, chunking::count_tokens, generate_embedding,
+    metrics_utils::RequestLabels, organization::apply_ai_opt_in, providers::EmbeddingProvider,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,15 +35,16 @@ pub async fn handle_ad_hoc_request(
         return Err(anyhow::anyhow!("Organization not opted in to ai features"));
     };
 
-    let would_truncate = check_would_truncate(&request.content, &request.model);
+    let provider = context.providers.get(&request.model)?;
+    let would_truncate = check_would_truncate(&request.content, provider);
 
     if would_truncate && !request.no_truncate {
         return Err(anyhow::anyhow!("Content too long"));
     }
 
     let (embedding, token_count) = generate_embedding(
-        context.clone(),
-        request.model,
+        &context,
+        provider,
         &request.content,
         &RequestLabels::from(&request),
     )
@@ -56,17 +57,6 @@ pub async fn handle_ad_hoc_request(
     })
 }
 
-pub fn check_would_truncate(content: &str, model: &EmbeddingModel) -> bool {
-    match model {
-        EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-            let encoder = tiktoken_rs::cl100k_base().expect("We can construct the encoder");
-            let tokens: Vec<_> = encoder
-                .encode_with_special_tokens(content)
-                .into_iter()
-                .take(model.model_input_window())
-                .collect();
-            let token_count = tokens.len();
-            token_count > model.model_input_window()
-        }
-    }
+pub fn check_would_truncate(content: &str, provider: &EmbeddingProvider) -> bool {
+    count_tokens(content) > provider.input_window
 }
@@ -5,7 +5,7 @@ use common_kafka::{
     kafka_consumer::SingleTopicConsumer, kafka_producer::KafkaContext,
     transaction::TransactionalProducer,
 };
-use common_types::embedding::{ApiLimits, EmbeddingModel};
+use common_types::embedding::ApiLimits;
 use health::{HealthHandle, HealthRegistry};
 use leaky_bucket::RateLimiter;
 use metrics::{counter, gauge};
@@ -20,6 +20,7 @@ use crate::{
     config::Config,
     metrics_utils::{LIMITS_UPDATED, LIMIT_BALANCE},
     organization::Organization,
+    providers::{EmbeddingProvider, ProviderRegistry},
 };
 
 pub struct AppContext {
@@ -31,6 +32,7 @@ pub struct AppContext {
     pub config: Config,
     pub client: reqwest::Client,
     pub org_cache: Cache<i32, Option<Organization>>,
+    pub providers: ProviderRegistry,
     rate_limits: RwLock<HashMap<String, Limiter>>,
 }
 
@@ -42,6 +44,8 @@ struct Limiter {
 
 impl AppContext {
     pub async fn new(config: Config) -> Result<Self> {
+        let providers = ProviderRegistry::from_config(&config)?;
+
         let health_registry = HealthRegistry::new("liveness");
         let worker_liveness = health_registry
             .register("worker".to_string(), Duration::from_secs(60))
@@ -79,68 +83,60 @@ impl AppContext {
             config,
             client,
             org_cache,
+            providers,
             rate_limits: Default::default(),
         })
     }
 
-    pub async fn respect_rate_limits(&self, model: EmbeddingModel, tokens: usize) {
+    pub async fn respect_rate_limits(&self, provider: &EmbeddingProvider, tokens: usize) {
+        let key = provider.limits_key.as_str();
         let read = self.rate_limits.read().await;
 
-        let Some(limiter) = read.get(model.limits_key()) else {
+        let Some(limiter) = read.get(key) else {
             drop(read);
             let mut write = self.rate_limits.write().await;
-            write.insert(model.limits_key().to_string(), model.api_limits().into());
+            write.insert(key.to_string(), provider.limits.clone().into());
             drop(write);
 
             let read = self.rate_limits.read().await;
-            let limiter = read.get(model.limits_key()).expect("We just inserted this");
+            let limiter = read.get(key).expect("We just inserted this");
 
-            limiter.report_balance(model);
+            limiter.report_balance(key);
             limiter.acquire(tokens, 1).await;
             return;
         };
 
-        limiter.report_balance(model);
+        limiter.report_balance(key);
         limiter.acquire(tokens, 1).await;
     }
 
-    pub async fn update_rate_limits(&self, model: EmbeddingModel, response: &Response) {
-        let header_fn = |key: &str| {
-            response
-                .headers()
-                .get(key)
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        };
+    pub async fn update_rate_limits(&self, provider: &EmbeddingProvider, response: &Response) {
+        let key = provider.limits_key.as_str();
 
-        if let Some(new_limits) = model.api_limits_from_response(&header_fn) {
+        if let Some(new_limits) = provider.api_limits_from_response(response) {
             // Do we need to update? We do this here, rather than inside the Limiter,
             // because it lets us only take a read lock on the happy path
             let needs_update = self
                 .rate_limits
                 .read()
                 .await
-                .get(model.limits_key())
+                .get(key)
                 .map(|l| l.needs_update(&new_limits))
                 .unwrap_or(true); // If we don't find a limiter for this model, we need to add one
             if !needs_update {
                 return; // Bail early, never taking a write lock
             }
 
-            counter!(LIMITS_UPDATED, &[("key", model.limits_key())]).increment(1);
-            warn!(
-                "Updating rate limits for {}: {:?}",
-                model.limits_key(),
-                new_limits
-            );
+            counter!(LIMITS_UPDATED, &[("key", key.to_string())]).increment(1);
+            warn!("Updating rate limits for {}: {:?}", key, new_limits);
 
             let mut write = self.rate_limits.write().await;
-            match write.get_mut(model.limits_key()) {
+            match write.get_mut(key) {
                 Some(limiter) => {
                     limiter.update(new_limits).await;
                 }
                 None => {
-                    write.insert(model.limits_key().to_string(), new_limits.into());
+                    write.insert(key.to_string(), new_limits.into());
                 }
             }
         }
@@ -175,15 +171,15 @@ impl Limiter {
         self.requests.acquire(requests).await;
     }
 
-    pub fn report_balance(&self, model: EmbeddingModel) {
+    pub fn report_balance(&self, key: &str) {
         gauge!(
             LIMIT_BALANCE,
-            &[("key", model.limits_key()), ("type", "tokens")]
+            &[("key", key.to_string()), ("type", "tokens".to_string())]
         )
         .set(self.tokens.balance() as f64);
         gauge!(
             LIMIT_BALANCE,
-            &[("key", model.limits_key()), ("type", "requests")]
+            &[("key", key.to_string()), ("type", "requests".to_string())]
         )
         .set(self.requests.balance() as f64);
     }
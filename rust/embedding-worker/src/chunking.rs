@@ -0,0 +1,195 @@
+use anyhow::Result;
+use common_types::embedding::ChunkingStrategy;
+use tiktoken_rs::{CoreBPE, Rank};
+
+use crate::InvalidRequest;
+
+// A character can be encoded across several tokens, so a window of tokens can start or end
+// part-way through one. We pull the window's edges in until it decodes, which drops at most
+// the tokens of the split characters.
+const MAX_CHARACTER_TOKENS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    pub text: String,
+    pub token_count: usize,
+}
+
+// We count tokens with cl100k for every provider. It's exact for the openai models, and for
+// other models it's an estimate their configured input window should leave headroom for.
+fn encoder() -> &'static CoreBPE {
+    tiktoken_rs::cl100k_base_singleton()
+}
+
+pub fn count_tokens(content: &str) -> usize {
+    encoder().encode_with_special_tokens(content).len()
+}
+
+// Returns the longest prefix of content that fits in max_tokens
+pub fn truncate(content: &str, max_tokens: usize) -> Result<TextChunk> {
+    let tokens = encoder().encode_with_special_tokens(content);
+    if tokens.len() <= max_tokens {
+        return Ok(TextChunk {
+            text: content.to_string(),
+            token_count: tokens.len(),
+        });
+    }
+
+    let (text, start, end) = decode_window(&tokens, 0, max_tokens)?;
+    Ok(TextChunk {
+        text,
+        token_count: end - start,
+    })
+}
+
+// Splits content into chunks of at most max_tokens (capped to the input window) on token boundaries,
+// each starting with the last overlap_tokens of the chunk before it. Content that fits in a single
+// chunk is returned as-is.
+pub fn chunk(
+    content: &str,
+    input_window: usize,
+    strategy: &ChunkingStrategy,
+) -> Result<Vec<TextChunk>> {
+    let max_tokens = strategy
+        .max_tokens
+        .unwrap_or(input_window)
+        .min(input_window);
+    if strategy.overlap_tokens >= max_tokens {
+        return Err(InvalidRequest::new(
+            "invalid_chunking",
+            format!(
+                "Chunk overlap of {} tokens must be less than the chunk size of {} tokens",
+                strategy.overlap_tokens, max_tokens
+            ),
+        )
+        .into());
+    }
+
+    let tokens = encoder().encode_with_special_tokens(content);
+    if tokens.len() <= max_tokens {
+        return Ok(vec![TextChunk {
+            text: content.to_string(),
+            token_count: tokens.len(),
+        }]);
+    }
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + max_tokens).min(tokens.len());
+        let (text, chunk_start, chunk_end) = decode_window(&tokens, start, end)?;
+        chunks.push(TextChunk {
+            text,
+            token_count: chunk_end - chunk_start,
+        });
+
+        if chunk_end >= tokens.len() {
+            return Ok(chunks);
+        }
+        // Always make progress, even if the overlap would take us back to where this chunk started
+        start = chunk_end
+            .saturating_sub(strategy.overlap_tokens)
+            .max(chunk_start + 1);
+    }
+}
+
+fn decode_window(tokens: &[Rank], start: usize, end: usize) -> Result<(String, usize, usize)> {
+    for lead in 0..MAX_CHARACTER_TOKENS {
+        for trim in 0..MAX_CHARACTER_TOKENS {
+            let (start, end) = (start + lead, end.saturating_sub(trim));
+            if start >= end {
+                break;
+            }
+            if let Ok(text) = encoder().decode(tokens[start..end].to_vec()) {
+                return Ok((text, start, end));
+            }
+        }
+    }
+
+    Err(InvalidRequest::new(
+        "undecodable_content",
+        format!("Unable to decode tokens {start}..{end} into text"),
+    )
+    .into())
+}
+
+#[cfg(test)]
+mod test {
+    use common_types::embedding::ChunkingStrategy;
+
+    use super::{chunk, count_tokens, truncate};
+
+    fn strategy(max_tokens: usize, overlap_tokens: usize) -> ChunkingStrategy {
+        ChunkingStrategy {
+            max_tokens: Some(max_tokens),
+            overlap_tokens,
+        }
+    }
+
+    #[test]
+    fn test_short_content_is_a_single_chunk() {
+        let content = "a short document";
+        let chunks = chunk(content, 8192, &strategy(100, 10)).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, content);
+        assert_eq!(chunks[0].token_count, count_tokens(content));
+    }
+
+    #[test]
+    fn test_chunks_cover_content_with_overlap() {
+        let content = (0..200).map(|i| format!("word{i} ")).collect::<String>();
+        let chunks = chunk(&content, 8192, &strategy(50, 10)).unwrap();
+
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.token_count <= 50));
+        // Each chunk starts with the end of the one before it
+        for pair in chunks.windows(2) {
+            assert!(pair[0].text.contains(&pair[1].text[..16]));
+        }
+        assert!(content.starts_with(&chunks[0].text));
+        assert!(content.ends_with(&chunks.last().unwrap().text));
+
+        // Without overlap, the chunks are exactly the content
+        let chunks = chunk(&content, 8192, &strategy(50, 0)).unwrap();
+        assert_eq!(
+            chunks.iter().map(|c| c.text.as_str()).collect::<String>(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_chunk_size_is_capped_to_input_window() {
+        let content = "lorem ipsum ".repeat(100);
+        let chunks = chunk(
+            &content,
+            20,
+            &ChunkingStrategy {
+                max_tokens: None,
+                overlap_tokens: 0,
+            },
+        )
+        .unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.token_count <= 20));
+
+        assert!(chunk(&content, 20, &strategy(10, 10)).is_err());
+    }
+
+    #[test]
+    fn test_multi_token_characters_are_not_split() {
+        // Emoji encode to several tokens each, so most windows land part-way through one
+        let content = "🦀🦔🐘".repeat(30);
+        for max_tokens in 3..10 {
+            let chunks = chunk(&content, 8192, &strategy(max_tokens, 0)).unwrap();
+            assert_eq!(
+                chunks.iter().map(|c| c.text.as_str()).collect::<String>(),
+                content
+            );
+        }
+
+        let truncated = truncate(&content, 5).unwrap();
+        assert!(truncated.token_count <= 5);
+        assert!(!truncated.text.is_empty());
+        assert!(content.starts_with(&truncated.text));
+    }
+}
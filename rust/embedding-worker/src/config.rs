@@ -2,6 +2,8 @@ use common_continuous_profiling::ContinuousProfilingConfig;
 use common_kafka::config::{ConsumerConfig, KafkaConfig};
 use envconfig::Envconfig;
 
+use crate::providers::ProviderConfigs;
+
 #[derive(Envconfig, Clone)]
 pub struct Config {
     #[envconfig(nested = true)]
@@ -37,6 +39,14 @@ pub struct Config {
 
     pub openai_api_key: String,
 
+    // Point the openai models at another openai-compatible endpoint, e.g. a local stand-in
+    #[envconfig(default = "https://api.openai.com/v1")]
+    pub openai_base_url: String,
+
+    // JSON list of models served by openai-compatible endpoints, see providers::ProviderConfig
+    #[envconfig(default = "[]")]
+    pub embedding_providers: ProviderConfigs,
+
     // Rust service connect directly to postgres, not via pgbouncer, so we keep this low
     #[envconfig(default = "4")]
     pub max_pg_connections: u32,
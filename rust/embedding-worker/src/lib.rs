@@ -1,29 +1,59 @@
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use anyhow::Result;
 use common_kafka::kafka_consumer::Offset;
 use common_types::embedding::{
-    EmbeddingModel, EmbeddingRequest, EmbeddingResponse, EmbeddingResult, ModelResult,
+    ChunkingStrategy, EmbeddingChunk, EmbeddingModel, EmbeddingRequest, EmbeddingResponse,
+    EmbeddingResult, ModelResult,
 };
 use metrics::counter;
-use reqwest::{Client, Method, Request, RequestBuilder};
-use tracing::error;
+use tracing::{error, warn};
 
 use crate::{
     app_context::AppContext,
+    chunking::{chunk, truncate, TextChunk},
     metrics_utils::{
-        RequestLabels, DROPPED_REQUESTS, EMBEDDINGS_GENERATED, EMBEDDING_FAILED,
+        RequestLabels, CHUNKS_EMBEDDED, DROPPED_REQUESTS, EMBEDDINGS_GENERATED, EMBEDDING_FAILED,
         EMBEDDING_REQUEST_TIME, EMBEDDING_TOTAL_TIME, EMBEDDING_TOTAL_TOKENS, MESSAGES_RECEIVED,
-        MESSAGE_TRUNCATED,
+        MESSAGE_CHUNKED, MESSAGE_TRUNCATED,
     },
     organization::apply_ai_opt_in,
+    providers::EmbeddingProvider,
 };
 
 pub mod ad_hoc;
 pub mod app_context;
+pub mod chunking;
 pub mod config;
 pub mod metrics_utils;
 pub mod organization;
+pub mod providers;
+
+// An error caused by a request, or the provider's response to it, rather than something transient.
+// Retrying won't fix it, so it's reported back as the request's result instead of failing the batch.
+#[derive(Debug)]
+pub struct InvalidRequest {
+    // Used as the failure metric's "cause" label
+    pub cause: &'static str,
+    pub message: String,
+}
+
+impl InvalidRequest {
+    pub fn new(cause: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            cause,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for InvalidRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for InvalidRequest {}
 
 pub async fn handle_batch(
     requests: Vec<EmbeddingRequest>,
@@ -48,12 +78,7 @@ pub async fn handle_batch(
         handles.push(async move {
             let mut results = vec![];
             for model in &request.models {
-                let (model, embedding) =
-                    handle_single(ctx.clone(), *model, request.clone()).await?;
-                results.push(ModelResult {
-                    model,
-                    outcome: EmbeddingResult::Success { embedding },
-                });
+                results.push(handle_single(ctx.clone(), model.clone(), &request).await?);
             }
             Ok::<_, anyhow::Error>(EmbeddingResponse { request, results })
         });
@@ -70,44 +95,122 @@ pub async fn handle_batch(
 pub async fn handle_single(
     context: Arc<AppContext>,
     model: EmbeddingModel,
-    request: EmbeddingRequest,
-) -> Result<(EmbeddingModel, Vec<f64>)> {
-    let labels = RequestLabels::from(&request)
-        .and_model(model)
+    request: &EmbeddingRequest,
+) -> Result<ModelResult> {
+    let labels = RequestLabels::from(request)
+        .and_model(&model)
         .and([("from", "kafka")]);
 
     counter!(MESSAGES_RECEIVED, labels.render()).increment(1);
-    let (embedding, _) = match generate_embedding(context, model, &request.content, &labels).await {
+
+    let provider = match context.providers.get(&model) {
+        Ok(provider) => provider,
+        Err(e) => return failed_result(model, e, labels),
+    };
+
+    let outcome = match request.chunking {
+        Some(strategy) => {
+            generate_chunked_embeddings(&context, provider, &request.content, &strategy, &labels)
+                .await
+        }
+        None => generate_embedding(&context, provider, &request.content, &labels)
+            .await
+            .map(|(embedding, _)| EmbeddingResult::Success { embedding }),
+    };
+
+    let outcome = match outcome {
         Ok(r) => r,
+        Err(e) => return failed_result(model, e, labels),
+    };
+
+    counter!(EMBEDDINGS_GENERATED, labels.render()).increment(1);
+
+    Ok(ModelResult { model, outcome })
+}
+
+// A bad request is reported back as a failed result, so one bad message can't stop us processing
+// the batch. Anything else fails the batch.
+pub fn failed_result(
+    model: EmbeddingModel,
+    error: anyhow::Error,
+    labels: RequestLabels,
+) -> Result<ModelResult> {
+    let invalid = match error.downcast::<InvalidRequest>() {
+        Ok(invalid) => invalid,
         Err(e) => {
             counter!(EMBEDDING_FAILED, labels.render()).increment(1);
             return Err(e);
         }
     };
 
-    counter!(EMBEDDINGS_GENERATED, labels.render()).increment(1);
-
-    Ok((model, embedding))
+    warn!("Dropping embedding request: {}", invalid);
+    counter!(
+        EMBEDDING_FAILED,
+        labels.and([("cause", invalid.cause)]).render()
+    )
+    .increment(1);
+    Ok(ModelResult {
+        model,
+        outcome: EmbeddingResult::Failure {
+            error: invalid.to_string(),
+        },
+    })
 }
 
 pub async fn generate_embedding(
-    context: Arc<AppContext>,
-    model: EmbeddingModel,
+    context: &AppContext,
+    provider: &EmbeddingProvider,
     content: &str,
     labels: &RequestLabels,
 ) -> Result<(Vec<f64>, usize)> {
+    // Generate the text to actually send to the provider
+    let text = generate_embedding_text(content, provider, labels)?;
+    let embedding = embed_text(context, provider, &text, labels).await?;
+    Ok((embedding, text.token_count))
+}
+
+// Rather than truncating, embeds every chunk of the content, in order
+pub async fn generate_chunked_embeddings(
+    context: &AppContext,
+    provider: &EmbeddingProvider,
+    content: &str,
+    strategy: &ChunkingStrategy,
+    labels: &RequestLabels,
+) -> Result<EmbeddingResult> {
+    let chunks = chunk(content, provider.input_window, strategy)?;
+
+    if chunks.len() > 1 {
+        counter!(MESSAGE_CHUNKED, labels.render()).increment(1);
+    }
+
+    let mut embedded = Vec::with_capacity(chunks.len());
+    for (index, text) in chunks.into_iter().enumerate() {
+        let embedding = embed_text(context, provider, &text, labels).await?;
+        embedded.push(EmbeddingChunk {
+            index,
+            content: text.text,
+            embedding,
+        });
+    }
+
+    counter!(CHUNKS_EMBEDDED, labels.render()).increment(embedded.len() as u64);
+
+    Ok(EmbeddingResult::Chunked { chunks: embedded })
+}
+
+pub async fn embed_text(
+    context: &AppContext,
+    provider: &EmbeddingProvider,
+    text: &TextChunk,
+    labels: &RequestLabels,
+) -> Result<Vec<f64>> {
     let total_time = common_metrics::timing_guard(EMBEDDING_TOTAL_TIME, labels.render());
-    // Generate the text to actually send to OpenAI
-    let (text, token_count) = generate_embedding_text(content, &model, labels)?;
 
-    let api_req = construct_request(
-        &text,
-        model,
-        &context.config.openai_api_key,
-        context.client.clone(),
-    );
+    let api_req = provider.construct_request(context.client.clone(), &text.text)?;
 
-    context.respect_rate_limits(model, token_count).await;
+    context
+        .respect_rate_limits(provider, text.token_count)
+        .await;
 
     let request_time = common_metrics::timing_guard(EMBEDDING_REQUEST_TIME, labels.render());
     let response = context.client.execute(api_req).await?; // Unhandled - network errors etc
@@ -115,79 +218,85 @@ pub async fn generate_embedding(
     // TODO - implement 429 backoff and retry
     if !response.status().is_success() {
         error!(
-            "Failed to generate embeddings, got non-200 from openai: {}",
+            "Failed to generate embeddings, got non-200 from {}: {}",
+            provider.url,
             response.status()
         );
 
         if let Ok(error_message) = response.text().await {
-            error!("Error message from OpenAI: {}", error_message);
+            error!("Error message from {}: {}", provider.url, error_message);
         }
 
         return Err(anyhow::anyhow!("Failed to generate embeddings"));
     }
 
-    context.update_rate_limits(model, &response).await;
+    context.update_rate_limits(provider, &response).await;
 
-    let embedding = model
-        .extract_embedding_from_response_body(&response.json().await?)
-        .ok_or_else(|| anyhow::anyhow!("Failed to extract embedding"))?;
+    let embedding = provider.extract_embedding_from_response_body(response.json().await?)?;
 
     request_time.label("outcome", "success").fin();
     total_time.label("outcome", "success").fin();
 
-    counter!(EMBEDDING_TOTAL_TOKENS, labels.render()).increment(token_count as u64);
+    counter!(EMBEDDING_TOTAL_TOKENS, labels.render()).increment(text.token_count as u64);
 
-    Ok((embedding, token_count))
+    Ok(embedding)
 }
 
-// This is here, rather than on the embedding model, to avoid taking a dep on tiktoken in common/types. We
-// can reconsider it later if we want
 pub fn generate_embedding_text(
     content: &str,
-    model: &EmbeddingModel,
+    provider: &EmbeddingProvider,
     labels: &RequestLabels,
-) -> Result<(String, usize)> {
-    let (text, count) = match model {
-        EmbeddingModel::OpenAITextEmbeddingSmall | EmbeddingModel::OpenAITextEmbeddingLarge => {
-            let encoder = tiktoken_rs::cl100k_base()?;
-            let tokens: Vec<_> = encoder
-                .encode_with_special_tokens(content)
-                .into_iter()
-                .take(model.model_input_window())
-                .collect();
-            let token_count = tokens.len();
-            let text = encoder.decode(tokens)?;
-            (text, token_count)
-        }
-    };
+) -> Result<TextChunk> {
+    let text = truncate(content, provider.input_window)?;
 
-    if text.len() < content.len() {
+    if text.text.len() < content.len() {
         counter!(MESSAGE_TRUNCATED, labels.render()).increment(1);
     }
 
-    Ok((text, count))
+    Ok(text)
 }
 
-pub fn construct_request(
-    content: &str,
-    model: EmbeddingModel,
-    api_key: &str,
-    client: Client,
-) -> Request {
-    let req = Request::new(
-        Method::POST,
-        model
-            .model_url()
-            .parse()
-            .expect("The models enum only produces valid urls"),
-    );
-
-    let req = RequestBuilder::from_parts(client, req)
-        .header("Authorization", format!("Bearer {api_key}"))
-        .header("Content-Type", "application/json")
-        .json(&model.construct_request_body(content));
-
-    // This expect is fine, because we have total control over everything in the
-    // request, except the string of input content, which will serialize correctly.
-    req.build().expect("we manage to build the request")
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+    use common_types::embedding::{ChunkingStrategy, EmbeddingModel, EmbeddingResult};
+
+    use crate::{
+        ad_hoc::AdHocEmbeddingRequest, chunking::chunk, failed_result, metrics_utils::RequestLabels,
+    };
+
+    fn labels() -> RequestLabels {
+        RequestLabels::from(&AdHocEmbeddingRequest {
+            team_id: 1,
+            content: String::new(),
+            model: EmbeddingModel::OpenAITextEmbeddingSmall,
+            no_truncate: false,
+        })
+    }
+
+    #[test]
+    fn test_invalid_chunking_is_a_failed_result() {
+        for (max_tokens, overlap_tokens) in [(0, 0), (10, 10)] {
+            let strategy = ChunkingStrategy {
+                max_tokens: Some(max_tokens),
+                overlap_tokens,
+            };
+            let error = chunk("a short document", 8192, &strategy).unwrap_err();
+
+            let result =
+                failed_result(EmbeddingModel::OpenAITextEmbeddingSmall, error, labels()).unwrap();
+            assert!(matches!(
+                result.outcome,
+                EmbeddingResult::Failure { error } if error.contains("overlap")
+            ));
+        }
+
+        // Anything else, like a network error, still fails the batch
+        assert!(failed_result(
+            EmbeddingModel::OpenAITextEmbeddingSmall,
+            anyhow!("connection reset"),
+            labels()
+        )
+        .is_err());
+    }
 }
@@ -8,6 +8,8 @@ pub const LIMITS_UPDATED: &str = "embedding_worker_limits_updated";
 pub const LIMIT_BALANCE: &str = "embedding_worker_limit_balance";
 pub const DROPPED_REQUESTS: &str = "embedding_worker_dropped_requests";
 pub const MESSAGE_TRUNCATED: &str = "embedding_worker_content_truncated";
+pub const MESSAGE_CHUNKED: &str = "embedding_worker_content_chunked";
+pub const CHUNKS_EMBEDDED: &str = "embedding_worker_chunks_embedded";
 pub const EMBEDDING_FAILED: &str = "embedding_worker_embedding_failed";
 pub const EMBEDDING_TOTAL_TIME: &str = "embedding_worker_embedding_total_time";
 pub const EMBEDDING_REQUEST_TIME: &str = "embedding_worker_embedding_request_time";
@@ -32,7 +34,7 @@ impl RequestLabels {
         self
     }
 
-    pub fn and_model(self, model: EmbeddingModel) -> Self {
+    pub fn and_model(self, model: &EmbeddingModel) -> Self {
         self.and([("model", model.name())])
     }
 
@@ -64,6 +66,6 @@ impl From<&AdHocEmbeddingRequest> for RequestLabels {
         Self {
             labels: vec![("from".to_string(), "api".to_string())],
         }
-        .and_model(request.model)
+        .and_model(&request.model)
     }
 }
@@ -0,0 +1,372 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{anyhow, Result};
+use common_types::embedding::{ApiLimits, EmbeddingModel};
+use reqwest::{Client, Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{config::Config, InvalidRequest};
+
+// TODO - these are VERY conservative, but keep in mind each pod will max out at this.
+const DEFAULT_REQUESTS_PER_MINUTE: usize = 3_000;
+const DEFAULT_TOKENS_PER_MINUTE: usize = 1_000_000;
+
+// A model served by an OpenAI-compatible embeddings endpoint - a self-hosted model, another
+// vendor, or a local stand-in for tests. Configured as a JSON list in EMBEDDING_PROVIDERS.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    // The model name requests use, and records are written with. Reusing the name of
+    // a built-in model (e.g. "text-embedding-3-small-1536") replaces its provider.
+    pub name: String,
+    // e.g. "http://localhost:8080/v1", requests are sent to "{base_url}/embeddings"
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    // The model name the endpoint expects, if different from `name`
+    #[serde(default)]
+    pub remote_model: Option<String>,
+    pub dimensions: usize,
+    pub input_window: usize,
+    #[serde(default = "default_requests_per_minute")]
+    pub requests_per_minute: usize,
+    #[serde(default = "default_tokens_per_minute")]
+    pub tokens_per_minute: usize,
+}
+
+// Manual, so the api key doesn't end up in logs
+impl fmt::Debug for ProviderConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProviderConfig")
+            .field("name", &self.name)
+            .field("base_url", &self.base_url)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("remote_model", &self.remote_model)
+            .field("dimensions", &self.dimensions)
+            .field("input_window", &self.input_window)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("tokens_per_minute", &self.tokens_per_minute)
+            .finish()
+    }
+}
+
+fn default_requests_per_minute() -> usize {
+    DEFAULT_REQUESTS_PER_MINUTE
+}
+
+fn default_tokens_per_minute() -> usize {
+    DEFAULT_TOKENS_PER_MINUTE
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ProviderConfigs {
+    pub providers: Vec<ProviderConfig>,
+}
+
+impl FromStr for ProviderConfigs {
+    type Err = serde_json::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        Ok(Self {
+            providers: serde_json::from_str(s)?,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct EmbeddingProvider {
+    pub model: EmbeddingModel,
+    pub url: String,
+    pub remote_model: String,
+    pub dimensions: usize,
+    pub input_window: usize,
+    pub limits: ApiLimits,
+    // Models sharing a key share a rate limit bucket
+    pub limits_key: String,
+    api_key: Option<String>,
+}
+
+impl fmt::Debug for EmbeddingProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddingProvider")
+            .field("model", &self.model)
+            .field("url", &self.url)
+            .field("remote_model", &self.remote_model)
+            .field("dimensions", &self.dimensions)
+            .field("input_window", &self.input_window)
+            .field("limits", &self.limits)
+            .field("limits_key", &self.limits_key)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+impl EmbeddingProvider {
+    pub fn openai(model: EmbeddingModel, base_url: &str, api_key: &str) -> Result<Self> {
+        let (dimensions, limits_key) = match &model {
+            // Based on our openai usage limits, these are distinct buckets
+            EmbeddingModel::OpenAITextEmbeddingSmall => (1536, "openai_text_embedding_small"),
+            EmbeddingModel::OpenAITextEmbeddingLarge => (3072, "openai_text_embedding_large"),
+            EmbeddingModel::Custom(name) => {
+                return Err(anyhow!("{name} is not an openai model"));
+            }
+        };
+
+        Ok(Self {
+            url: embeddings_url(base_url),
+            remote_model: model.name().to_string(),
+            model,
+            dimensions,
+            input_window: 8192,
+            limits: ApiLimits {
+                requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
+                tokens_per_minute: DEFAULT_TOKENS_PER_MINUTE,
+            },
+            limits_key: limits_key.to_string(),
+            api_key: Some(api_key.to_string()),
+        })
+    }
+
+    pub fn from_config(config: &ProviderConfig) -> Result<Self> {
+        if config.dimensions == 0 || config.input_window == 0 {
+            return Err(anyhow!(
+                "Provider {} needs non-zero dimensions and input_window",
+                config.name
+            ));
+        }
+        // Built-in names deserialize to their built-in model, so configs can override them
+        let model: EmbeddingModel = serde_json::from_value(Value::String(config.name.clone()))?;
+
+        Ok(Self {
+            url: embeddings_url(&config.base_url),
+            remote_model: config
+                .remote_model
+                .clone()
+                .unwrap_or_else(|| model.name().to_string()),
+            model,
+            dimensions: config.dimensions,
+            input_window: config.input_window,
+            limits: ApiLimits {
+                requests_per_minute: config.requests_per_minute,
+                tokens_per_minute: config.tokens_per_minute,
+            },
+            limits_key: config.name.clone(),
+            api_key: config.api_key.clone(),
+        })
+    }
+
+    pub fn construct_request(&self, client: Client, content: &str) -> Result<Request> {
+        let mut req = client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .json(&OAIEmbeddingRequest {
+                input: content,
+                model: &self.remote_model,
+            });
+
+        if let Some(api_key) = &self.api_key {
+            req = req.header("Authorization", format!("Bearer {api_key}"));
+        }
+
+        // Unlike the built-in urls, configured ones can be invalid, so we don't expect here
+        Ok(req.build()?)
+    }
+
+    pub fn api_limits_from_response(&self, response: &Response) -> Option<ApiLimits> {
+        let header = |key: &str| response.headers().get(key)?.to_str().ok()?.parse().ok();
+        Some(ApiLimits {
+            requests_per_minute: header("x-ratelimit-limit-requests")?,
+            tokens_per_minute: header("x-ratelimit-limit-tokens")?,
+        })
+    }
+
+    pub fn extract_embedding_from_response_body(&self, response: Value) -> Result<Vec<f64>> {
+        let response: OAIEmbeddingResponse = serde_json::from_value(response)?;
+        let embedding = response
+            .data
+            .into_iter()
+            .next()
+            .map(|d| d.embedding)
+            .ok_or_else(|| anyhow!("No embedding in response"))?;
+
+        // Records are written to tables of a fixed dimension, so a misconfigured model mustn't slip through
+        if embedding.len() != self.dimensions {
+            return Err(InvalidRequest::new(
+                "dimension_mismatch",
+                format!(
+                    "Expected an embedding of {} dimensions from {}, got {}",
+                    self.dimensions,
+                    self.model.name(),
+                    embedding.len()
+                ),
+            )
+            .into());
+        }
+
+        Ok(embedding)
+    }
+}
+
+pub struct ProviderRegistry {
+    providers: HashMap<EmbeddingModel, EmbeddingProvider>,
+}
+
+impl ProviderRegistry {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut providers = HashMap::new();
+
+        for model in [
+            EmbeddingModel::OpenAITextEmbeddingSmall,
+            EmbeddingModel::OpenAITextEmbeddingLarge,
+        ] {
+            let provider =
+                EmbeddingProvider::openai(model, &config.openai_base_url, &config.openai_api_key)?;
+            providers.insert(provider.model.clone(), provider);
+        }
+
+        for provider_config in &config.embedding_providers.providers {
+            let provider = EmbeddingProvider::from_config(provider_config)?;
+            providers.insert(provider.model.clone(), provider);
+        }
+
+        Ok(Self { providers })
+    }
+
+    pub fn get(&self, model: &EmbeddingModel) -> Result<&EmbeddingProvider> {
+        self.providers.get(model).ok_or_else(|| {
+            InvalidRequest::new(
+                "unknown_model",
+                format!("No provider configured for model {}", model.name()),
+            )
+            .into()
+        })
+    }
+}
+
+fn embeddings_url(base_url: &str) -> String {
+    format!("{}/embeddings", base_url.trim_end_matches('/'))
+}
+
+#[derive(Serialize)]
+struct OAIEmbeddingRequest<'a> {
+    input: &'a str,
+    model: &'a str,
+}
+
+#[derive(Deserialize)]
+struct OAIEmbeddingResponse {
+    data: Vec<OAIEmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct OAIEmbeddingData {
+    embedding: Vec<f64>,
+}
+
+#[cfg(test)]
+mod test {
+    use common_types::embedding::EmbeddingModel;
+    use httpmock::MockServer;
+    use serde_json::json;
+
+    use super::{EmbeddingProvider, ProviderConfig, ProviderConfigs};
+    use crate::InvalidRequest;
+
+    fn stand_in_config(server: &MockServer) -> ProviderConfig {
+        ProviderConfig {
+            name: "bge-small".to_string(),
+            base_url: server.url("/v1/"),
+            api_key: None,
+            remote_model: Some("BAAI/bge-small-en-v1.5".to_string()),
+            dimensions: 3,
+            input_window: 512,
+            requests_per_minute: 10,
+            tokens_per_minute: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_provider_configs_parse() {
+        let configs: ProviderConfigs = r#"[
+            {"name": "bge-small", "base_url": "http://localhost:8080/v1", "dimensions": 384, "input_window": 512},
+            {"name": "text-embedding-3-small-1536", "base_url": "http://localhost:8080/v1", "dimensions": 1536, "input_window": 8192}
+        ]"#
+        .parse()
+        .unwrap();
+        assert!("".parse::<ProviderConfigs>().unwrap().providers.is_empty());
+
+        let custom = EmbeddingProvider::from_config(&configs.providers[0]).unwrap();
+        assert_eq!(
+            custom.model,
+            EmbeddingModel::Custom("bge-small".to_string())
+        );
+        assert_eq!(custom.url, "http://localhost:8080/v1/embeddings");
+        assert_eq!(custom.remote_model, "bge-small");
+        assert_eq!(custom.limits.requests_per_minute, 3_000);
+
+        // Built-in model names override the built-in provider
+        let overridden = EmbeddingProvider::from_config(&configs.providers[1]).unwrap();
+        assert_eq!(overridden.model, EmbeddingModel::OpenAITextEmbeddingSmall);
+        assert_eq!(overridden.remote_model, "text-embedding-3-small");
+    }
+
+    #[test]
+    fn test_api_keys_are_redacted() {
+        let config = ProviderConfig {
+            api_key: Some("sk-secret".to_string()),
+            ..stand_in_config(&MockServer::start())
+        };
+        let provider = EmbeddingProvider::from_config(&config).unwrap();
+        for debug in [format!("{config:?}"), format!("{provider:?}")] {
+            assert!(!debug.contains("sk-secret"));
+            assert!(debug.contains("<redacted>"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_provider() {
+        let server = MockServer::start();
+        let provider = EmbeddingProvider::from_config(&stand_in_config(&server)).unwrap();
+
+        let mock = server.mock(|when, then| {
+            when.method("POST")
+                .path("/v1/embeddings")
+                .json_body(json!({"input": "hello world", "model": "BAAI/bge-small-en-v1.5"}));
+            then.status(200)
+                .header("x-ratelimit-limit-requests", "20")
+                .header("x-ratelimit-limit-tokens", "2000")
+                .json_body(json!({"data": [{"embedding": [0.1, 0.2, 0.3]}]}));
+        });
+
+        let client = reqwest::Client::new();
+        let request = provider
+            .construct_request(client.clone(), "hello world")
+            .unwrap();
+        assert!(request.headers().get("Authorization").is_none());
+
+        let response = client.execute(request).await.unwrap();
+        mock.assert();
+
+        let limits = provider.api_limits_from_response(&response).unwrap();
+        assert_eq!(limits.requests_per_minute, 20);
+        assert_eq!(limits.tokens_per_minute, 2000);
+
+        let embedding = provider
+            .extract_embedding_from_response_body(response.json().await.unwrap())
+            .unwrap();
+        assert_eq!(embedding, vec![0.1, 0.2, 0.3]);
+
+        // An embedding of the wrong dimension is a failed request, not a record
+        let error = provider
+            .extract_embedding_from_response_body(json!({"data": [{"embedding": [0.1, 0.2]}]}))
+            .unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<InvalidRequest>().unwrap().cause,
+            "dimension_mismatch"
+        );
+    }
+}
@@ -3,9 +3,9 @@ use std::convert::Infallible;
 use axum::{extract::DefaultBodyLimit, routing, Router};
 use tower::limit::ConcurrencyLimitLayer;
 
-use hook_common::pgqueue::PgQueue;
+use hook_common::pgqueue::{JobKind, PgQueue};
 
-use super::webhook;
+use super::{jobs, webhook};
 
 pub fn add_routes(
     router: Router,
@@ -20,24 +20,40 @@ pub fn add_routes(
         .route("/_liveness", routing::get(index)); // No async loop for now, just check axum health
 
     if hog_mode {
-        router.route(
-            "/hoghook",
-            routing::post(webhook::post_hoghook)
-                .with_state(pg_pool)
-                .layer::<_, Infallible>(ConcurrencyLimitLayer::new(concurrency_limit))
-                .layer(DefaultBodyLimit::max(max_body_size)),
-        )
+        router
+            .route(
+                "/hoghook",
+                routing::post(webhook::post_hoghook)
+                    .with_state(pg_pool.clone())
+                    .layer::<_, Infallible>(ConcurrencyLimitLayer::new(concurrency_limit))
+                    .layer(DefaultBodyLimit::max(max_body_size)),
+            )
+            .nest("/hoghook/jobs", job_routes(pg_pool, JobKind::Hoghook))
     } else {
-        router.route(
-            "/webhook",
-            routing::post(webhook::post_webhook)
-                .with_state(pg_pool)
-                .layer::<_, Infallible>(ConcurrencyLimitLayer::new(concurrency_limit))
-                .layer(DefaultBodyLimit::max(max_body_size)),
-        )
+        router
+            .route(
+                "/webhook",
+                routing::post(webhook::post_webhook)
+                    .with_state(pg_pool.clone())
+                    .layer::<_, Infallible>(ConcurrencyLimitLayer::new(concurrency_limit))
+                    .layer(DefaultBodyLimit::max(max_body_size)),
+            )
+            .nest("/webhook/jobs", job_routes(pg_pool, JobKind::Webhook))
     }
 }
 
+/// Routes to inspect jobs and re-enqueue failed ones, e.g. after a receiver outage.
+fn job_routes(pg_pool: PgQueue, kind: JobKind) -> Router {
+    Router::new()
+        .route("/failed", routing::get(jobs::list_failed_jobs))
+        .route("/retry", routing::post(jobs::retry_jobs))
+        .route("/:id", routing::get(jobs::get_job))
+        .with_state(jobs::JobsState {
+            pg_queue: pg_pool,
+            kind,
+        })
+}
+
 pub async fn index() -> &'static str {
     "rusty-hook api"
 }
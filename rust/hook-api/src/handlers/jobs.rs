@@ -0,0 +1,490 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use serde_derive::Deserialize;
+use serde_json::Value;
+use tracing::error;
+
+use hook_common::pgqueue::{FailedJobsFilter, JobKind, JobStatus, JobSummary, PgQueue};
+
+const DEFAULT_LIST_LIMIT: u32 = 50;
+const MAX_LIST_LIMIT: u32 = 500;
+const MAX_RETRY_JOBS: usize = 1000;
+
+/// State for the job routes, which are mounted for either webhook or hoghook jobs.
+#[derive(Clone)]
+pub struct JobsState {
+    pub pg_queue: PgQueue,
+    pub kind: JobKind,
+}
+
+type JobsResult<T> = Result<Json<T>, (StatusCode, Json<JobsErrorResponse>)>;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct JobsErrorResponse {
+    error: String,
+}
+
+/// A job as returned by the API. The last error is passed through as stored, which for webhook
+/// jobs is a serialized `WebhookJobError`.
+#[derive(Serialize, Debug)]
+pub struct JobResponse {
+    id: i64,
+    status: JobStatus,
+    attempt: i32,
+    max_attempts: i32,
+    target: String,
+    metadata: Value,
+    created_at: chrono::DateTime<chrono::offset::Utc>,
+    attempted_at: Option<chrono::DateTime<chrono::offset::Utc>>,
+    last_attempt_finished_at: Option<chrono::DateTime<chrono::offset::Utc>>,
+    scheduled_at: chrono::DateTime<chrono::offset::Utc>,
+    last_error: Option<Value>,
+}
+
+impl From<JobSummary<Value, Value>> for JobResponse {
+    fn from(job: JobSummary<Value, Value>) -> Self {
+        Self {
+            id: job.id,
+            status: job.status,
+            attempt: job.attempt,
+            max_attempts: job.max_attempts,
+            target: job.target,
+            metadata: job.metadata.0,
+            created_at: job.created_at,
+            attempted_at: job.attempted_at,
+            last_attempt_finished_at: job.last_attempt_finished_at,
+            scheduled_at: job.scheduled_at,
+            last_error: job.last_error.map(|error| error.0),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FailedJobsQuery {
+    team_id: Option<i64>,
+    /// Filters webhook jobs.
+    plugin_config_id: Option<i64>,
+    /// Filters hoghook jobs.
+    hog_function_id: Option<String>,
+    /// The `next_cursor` returned with the previous page.
+    cursor: Option<i64>,
+    limit: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FailedJobsResponse {
+    jobs: Vec<JobResponse>,
+    /// Pass as `cursor` to fetch the next page. Unset when there are no more jobs.
+    next_cursor: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RetryJobsRequestBody {
+    ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RetryJobsResponse {
+    /// The ids of the jobs that were re-enqueued. Requested jobs that don't exist or haven't
+    /// failed are left out.
+    retried: Vec<i64>,
+}
+
+pub async fn get_job(
+    State(state): State<JobsState>,
+    Path(id): Path<i64>,
+) -> JobsResult<JobResponse> {
+    let job: JobSummary<Value, Value> = state
+        .pg_queue
+        .get_job(id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| not_found(format!("job {id} not found")))?;
+
+    Ok(Json(job.into()))
+}
+
+pub async fn list_failed_jobs(
+    State(state): State<JobsState>,
+    Query(query): Query<FailedJobsQuery>,
+) -> JobsResult<FailedJobsResponse> {
+    let (app_id_param, app_id) = match state.kind {
+        JobKind::Webhook => (
+            "plugin_config_id",
+            query.plugin_config_id.map(|id| id.to_string()),
+        ),
+        JobKind::Hoghook => ("hog_function_id", query.hog_function_id),
+    };
+    if query.team_id.is_none() && app_id.is_none() {
+        return Err(bad_request(format!(
+            "one of 'team_id' or '{app_id_param}' is required"
+        )));
+    }
+    let limit = query.limit.unwrap_or(DEFAULT_LIST_LIMIT);
+    if limit == 0 || limit > MAX_LIST_LIMIT {
+        return Err(bad_request(format!(
+            "'limit' must be between 1 and {MAX_LIST_LIMIT}"
+        )));
+    }
+
+    let filter = FailedJobsFilter {
+        kind: state.kind,
+        team_id: query.team_id,
+        app_id,
+    };
+    let jobs: Vec<JobSummary<Value, Value>> = state
+        .pg_queue
+        .list_failed_jobs(&filter, query.cursor, limit)
+        .await
+        .map_err(internal_error)?;
+
+    let next_cursor = if jobs.len() == limit as usize {
+        jobs.last().map(|job| job.id)
+    } else {
+        None
+    };
+
+    Ok(Json(FailedJobsResponse {
+        jobs: jobs.into_iter().map(JobResponse::from).collect(),
+        next_cursor,
+    }))
+}
+
+pub async fn retry_jobs(
+    State(state): State<JobsState>,
+    Json(body): Json<RetryJobsRequestBody>,
+) -> JobsResult<RetryJobsResponse> {
+    if body.ids.is_empty() || body.ids.len() > MAX_RETRY_JOBS {
+        return Err(bad_request(format!(
+            "'ids' must contain between 1 and {MAX_RETRY_JOBS} job ids"
+        )));
+    }
+
+    let retried = state
+        .pg_queue
+        .retry_failed_jobs(&body.ids)
+        .await
+        .map_err(internal_error)?;
+
+    metrics::counter!("webhook_api_jobs_retried").increment(retried.len() as u64);
+
+    Ok(Json(RetryJobsResponse { retried }))
+}
+
+fn bad_request(msg: String) -> (StatusCode, Json<JobsErrorResponse>) {
+    error!(msg);
+    (
+        StatusCode::BAD_REQUEST,
+        Json(JobsErrorResponse { error: msg }),
+    )
+}
+
+fn not_found(msg: String) -> (StatusCode, Json<JobsErrorResponse>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(JobsErrorResponse { error: msg }),
+    )
+}
+
+fn internal_error<E>(err: E) -> (StatusCode, Json<JobsErrorResponse>)
+where
+    E: std::error::Error,
+{
+    error!("internal error: {}", err);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(JobsErrorResponse {
+            error: err.to_string(),
+        }),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::{
+        body::Body,
+        http::{self, Request, StatusCode},
+        Router,
+    };
+    use hook_common::pgqueue::{NewJob, PgQueueJob, PgTransactionBatch};
+    use hook_common::webhook::{
+        HttpMethod, WebhookJobError, WebhookJobMetadata, WebhookJobParameters,
+    };
+    use http_body_util::BodyExt; // for `collect`
+    use sqlx::PgPool;
+    use std::collections;
+    use tower::ServiceExt; // for `call`, `oneshot`, and `ready`
+
+    use crate::handlers::app::add_routes;
+
+    const MAX_BODY_SIZE: usize = 1_000_000;
+    const CONCURRENCY_LIMIT: usize = 10;
+
+    async fn enqueue_failed_jobs(pg_queue: &PgQueue, team_ids: &[u32]) {
+        for team_id in team_ids {
+            let job = NewJob::new(
+                1,
+                WebhookJobMetadata {
+                    team_id: *team_id,
+                    plugin_id: 2,
+                    plugin_config_id: 3,
+                    signing_secret_ref: None,
+                },
+                WebhookJobParameters {
+                    headers: collections::HashMap::new(),
+                    method: HttpMethod::POST,
+                    url: "http://example.com/".to_owned(),
+                    body: r#"{"a": "b"}"#.to_owned(),
+                },
+                "example.com",
+            );
+            pg_queue.enqueue(job).await.expect("failed to enqueue job");
+        }
+
+        let mut batch: PgTransactionBatch<'_, WebhookJobParameters, WebhookJobMetadata> = pg_queue
+            .dequeue_tx("test-worker", team_ids.len() as u32)
+            .await
+            .expect("failed to dequeue jobs")
+            .expect("didn't find any jobs to dequeue");
+        for job in std::mem::take(&mut batch.jobs) {
+            job.fail(WebhookJobError::new_http_status(
+                500,
+                "Internal Server Error",
+            ))
+            .await
+            .expect("failed to fail job");
+        }
+        batch.commit().await.expect("failed to commit transaction");
+    }
+
+    async fn json_body(response: axum::response::Response) -> Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn list_and_get_failed_jobs(db: PgPool) {
+        let pg_queue = PgQueue::new_from_pool("test_index", db).await;
+        enqueue_failed_jobs(&pg_queue, &[1, 1, 2]).await;
+
+        let app = add_routes(
+            Router::new(),
+            pg_queue,
+            false,
+            MAX_BODY_SIZE,
+            CONCURRENCY_LIMIT,
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/webhook/jobs/failed?team_id=1&limit=1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let first_page = json_body(response).await;
+        assert_eq!(first_page["jobs"].as_array().unwrap().len(), 1);
+        let cursor = first_page["next_cursor"].as_i64().unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/webhook/jobs/failed?team_id=1&cursor={cursor}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let second_page = json_body(response).await;
+        let jobs = second_page["jobs"].as_array().unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert!(second_page["next_cursor"].is_null());
+
+        let id = jobs[0]["id"].as_i64().unwrap();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/webhook/jobs/{id}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let job = json_body(response).await;
+        assert_eq!(job["status"], "failed");
+        assert_eq!(job["attempt"], 1);
+        assert_eq!(job["metadata"]["team_id"], 1);
+        assert_eq!(job["last_error"]["type"], "Bad HTTP Status: 500");
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn list_failed_hoghook_jobs(db: PgPool) {
+        for (team_id, hog_function_id) in [(1, "abc"), (1, "def"), (2, "abc")] {
+            sqlx::query(
+                r#"
+                INSERT INTO job_queue
+                    (attempt, created_at, max_attempts, metadata, parameters, queue, scheduled_at, status, target)
+                VALUES
+                    (1, NOW(), 1, $1, '{}', 'hoghooks', NOW(), 'failed', 'example.com')
+                "#,
+            )
+            .bind(serde_json::json!({"teamId": team_id, "hogFunctionId": hog_function_id}))
+            .execute(&db)
+            .await
+            .expect("failed to insert job");
+        }
+
+        let pg_queue = PgQueue::new_from_pool("hoghooks", db).await;
+        let app = add_routes(
+            Router::new(),
+            pg_queue,
+            true,
+            MAX_BODY_SIZE,
+            CONCURRENCY_LIMIT,
+        );
+
+        for (query, expected) in [
+            ("team_id=1", 2),
+            ("hog_function_id=abc", 2),
+            ("team_id=1&hog_function_id=abc", 1),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/hoghook/jobs/failed?{query}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let body = json_body(response).await;
+            assert_eq!(body["jobs"].as_array().unwrap().len(), expected, "{query}");
+        }
+
+        // plugin_config_id only filters webhook jobs
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/hoghook/jobs/failed?plugin_config_id=3")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn list_failed_jobs_requires_filter(db: PgPool) {
+        let pg_queue = PgQueue::new_from_pool("test_index", db).await;
+        let app = add_routes(
+            Router::new(),
+            pg_queue,
+            false,
+            MAX_BODY_SIZE,
+            CONCURRENCY_LIMIT,
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/webhook/jobs/failed")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn get_missing_job(db: PgPool) {
+        let pg_queue = PgQueue::new_from_pool("test_index", db).await;
+        let app = add_routes(
+            Router::new(),
+            pg_queue,
+            false,
+            MAX_BODY_SIZE,
+            CONCURRENCY_LIMIT,
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/webhook/jobs/12345")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn retry_failed_jobs(db: PgPool) {
+        let pg_queue = PgQueue::new_from_pool("test_index", db).await;
+        enqueue_failed_jobs(&pg_queue, &[1]).await;
+        let failed: Vec<JobSummary<Value, Value>> = pg_queue
+            .list_failed_jobs(
+                &FailedJobsFilter {
+                    kind: JobKind::Webhook,
+                    team_id: Some(1),
+                    app_id: None,
+                },
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+        let id = failed[0].id;
+
+        let app = add_routes(
+            Router::new(),
+            pg_queue.clone(),
+            false,
+            MAX_BODY_SIZE,
+            CONCURRENCY_LIMIT,
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/webhook/jobs/retry")
+                    .header(http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        serde_json::to_string(&RetryJobsRequestBody {
+                            ids: vec![id, id + 1],
+                        })
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["retried"], serde_json::json!([id]));
+
+        let job: JobSummary<Value, Value> = pg_queue.get_job(id).await.unwrap().unwrap();
+        assert_eq!(job.status, JobStatus::Available);
+        assert_eq!(job.attempt, 0);
+    }
+}
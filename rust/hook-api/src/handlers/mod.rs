@@ -1,4 +1,5 @@
 mod app;
+mod jobs;
 mod webhook;
 
 pub use app::add_routes;
@@ -276,6 +276,7 @@ mod tests {
                                 team_id: 1,
                                 plugin_id: 2,
                                 plugin_config_id: 3,
+                                signing_secret_ref: None,
                             },
                             max_attempts: 1,
                         })
@@ -323,6 +324,7 @@ mod tests {
                                 team_id: 1,
                                 plugin_id: 2,
                                 plugin_config_id: 3,
+                                signing_secret_ref: None,
                             },
                             max_attempts: 1,
                         })
@@ -426,6 +428,7 @@ mod tests {
                                 team_id: 1,
                                 plugin_id: 2,
                                 plugin_config_id: 3,
+                                signing_secret_ref: None,
                             },
                             max_attempts: 1,
                         })
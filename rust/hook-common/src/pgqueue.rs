@@ -60,9 +60,10 @@ pub enum RetryError<T> {
 }
 
 /// Enumeration of possible statuses for a Job.
-#[derive(Debug, PartialEq, sqlx::Type)]
+#[derive(Debug, PartialEq, sqlx::Type, serde::Serialize)]
 #[sqlx(type_name = "job_status")]
 #[sqlx(rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum JobStatus {
     /// A job that is waiting in the queue to be picked up by a worker.
     Available,
@@ -216,6 +217,128 @@ RETURNING
     }
 }
 
+impl<J, M> Job<J, M> {
+    /// Consume `Job` to make it available again after `delay`, without counting this attempt.
+    /// Used when a job is put back without being attempted, e.g. because its target is unhealthy.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay`: The duration until the `Job` may be dequeued again. Used to set `scheduled_at`.
+    /// * `executor`: Any sqlx::Executor that can execute the UPDATE query required to reschedule this `Job`.
+    async fn reschedule<'c, E>(
+        self,
+        delay: time::Duration,
+        executor: E,
+    ) -> Result<RetriedJob, sqlx::Error>
+    where
+        E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+    {
+        let base_query = r#"
+UPDATE
+    job_queue
+SET
+    status = 'available'::job_status,
+    scheduled_at = NOW() + $3,
+    attempt = GREATEST(attempt - 1, 0)
+WHERE
+    queue = $1
+    AND id = $2
+RETURNING
+    job_queue.*
+        "#;
+
+        sqlx::query(base_query)
+            .bind(&self.queue)
+            .bind(self.id)
+            .bind(delay)
+            .execute(executor)
+            .await?;
+
+        Ok(RetriedJob {
+            id: self.id,
+            queue: self.queue,
+            retry_queue: None,
+        })
+    }
+}
+
+/// A read-only view of a `Job`, used to inspect jobs from outside of a worker.
+#[derive(sqlx::FromRow, Debug)]
+pub struct JobSummary<M, E> {
+    /// A unique id identifying a job.
+    pub id: i64,
+    /// The number of attempts made so far.
+    pub attempt: i32,
+    /// The job's number of max attempts.
+    pub max_attempts: i32,
+    /// A datetime corresponding to when the job was last attempted, if it ever was.
+    pub attempted_at: Option<chrono::DateTime<chrono::offset::Utc>>,
+    /// A datetime corresponding to when the job was created.
+    pub created_at: chrono::DateTime<chrono::offset::Utc>,
+    /// A datetime corresponding to when the last attempt of the job finished.
+    pub last_attempt_finished_at: Option<chrono::DateTime<chrono::offset::Utc>>,
+    /// A datetime corresponding to when the job may next be dequeued.
+    pub scheduled_at: chrono::DateTime<chrono::offset::Utc>,
+    /// Arbitrary job metadata stored as JSON.
+    pub metadata: JobMetadata<M>,
+    /// The current status of the job.
+    pub status: JobStatus,
+    /// The target of the job. E.g. an endpoint or service we are trying to reach.
+    pub target: String,
+    /// The error recorded by the most recent failed or retried attempt, if any.
+    pub last_error: Option<sqlx::types::Json<E>>,
+}
+
+/// The kind of jobs in a queue. Webhook and hoghook jobs store the team and app they were
+/// enqueued for under different keys in their metadata.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    #[default]
+    Webhook,
+    Hoghook,
+}
+
+impl JobKind {
+    fn team_id_key(&self) -> &'static str {
+        match self {
+            JobKind::Webhook => "team_id",
+            JobKind::Hoghook => "teamId",
+        }
+    }
+
+    fn app_id_key(&self) -> &'static str {
+        match self {
+            JobKind::Webhook => "plugin_config_id",
+            JobKind::Hoghook => "hogFunctionId",
+        }
+    }
+}
+
+/// Filters used when listing failed jobs. Both fields match against keys in the job's metadata,
+/// which depend on its `kind`.
+#[derive(Debug, Default, Clone)]
+pub struct FailedJobsFilter {
+    pub kind: JobKind,
+    pub team_id: Option<i64>,
+    /// The app the job was enqueued for: a plugin config id for webhooks, or a hog function id
+    /// for hoghooks.
+    pub app_id: Option<String>,
+}
+
+/// The columns of a `JobSummary`, shared by `job_queue` and `job_queue_dead_letter`.
+const JOB_SUMMARY_COLUMNS: &str = r#"
+    id,
+    attempt,
+    max_attempts,
+    attempted_at,
+    created_at,
+    last_attempt_finished_at,
+    scheduled_at,
+    metadata,
+    status,
+    target,
+    errors[array_upper(errors, 1)] AS last_error"#;
+
 #[async_trait]
 pub trait PgQueueJob {
     async fn complete(mut self) -> Result<CompletedJob, DatabaseError>;
@@ -231,6 +354,8 @@ pub trait PgQueueJob {
         retry_interval: time::Duration,
         queue: &str,
     ) -> Result<RetriedJob, RetryError<Box<Self>>>;
+
+    async fn reschedule(mut self, delay: time::Duration) -> Result<RetriedJob, DatabaseError>;
 }
 
 /// A Job within an open PostgreSQL transaction.
@@ -349,6 +474,23 @@ impl<'c, J: std::marker::Send, M: std::marker::Send> PgQueueJob for PgTransactio
 
         Ok(retried_job)
     }
+
+    async fn reschedule(mut self, delay: time::Duration) -> Result<RetriedJob, DatabaseError> {
+        let mut txn_guard = self.shared_txn.lock().await;
+
+        let txn_ref = txn_guard
+            .as_deref_mut()
+            .ok_or(DatabaseError::TransactionAlreadyClosedError)?;
+
+        let rescheduled_job = self.job.reschedule(delay, txn_ref).await.map_err(|error| {
+            DatabaseError::QueryError {
+                command: "UPDATE".to_owned(),
+                error,
+            }
+        })?;
+
+        Ok(rescheduled_job)
+    }
 }
 
 /// A Job that has failed but can still be enqueued into a PgQueue to be retried at a later point.
@@ -650,6 +792,241 @@ VALUES
 
         Ok(())
     }
+
+    /// Fetch a summary of the job with `id` in this PgQueue, or `None` if there is no such job.
+    /// Failed jobs are found whether or not the janitor has moved them to the dead letter table.
+    pub async fn get_job<
+        M: for<'d> serde::Deserialize<'d> + std::marker::Send + std::marker::Unpin + 'static,
+        E: for<'d> serde::Deserialize<'d> + std::marker::Send + std::marker::Unpin + 'static,
+    >(
+        &self,
+        id: i64,
+    ) -> PgQueueResult<Option<JobSummary<M, E>>> {
+        let base_query = format!(
+            r#"
+SELECT {JOB_SUMMARY_COLUMNS}
+FROM
+    job_queue
+WHERE
+    queue = $1
+    AND id = $2
+UNION ALL
+SELECT {JOB_SUMMARY_COLUMNS}
+FROM
+    job_queue_dead_letter
+WHERE
+    queue = $1
+    AND id = $2
+LIMIT 1
+        "#
+        );
+
+        sqlx::query_as(&base_query)
+            .bind(&self.name)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|error| DatabaseError::QueryError {
+                command: "SELECT".to_owned(),
+                error,
+            })
+    }
+
+    /// List failed jobs in this PgQueue matching `filter`, newest first. This includes failed jobs
+    /// the janitor has moved to the dead letter table, until they pass its retention window.
+    /// Results are paginated by id: pass the id of the last job of a page as `before_id` to
+    /// fetch the next one.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: Metadata values the jobs must match. Unset fields match any job.
+    /// * `before_id`: Only return jobs with an id lower than this one.
+    /// * `limit`: The maximum number of jobs to return.
+    pub async fn list_failed_jobs<
+        M: for<'d> serde::Deserialize<'d> + std::marker::Send + std::marker::Unpin + 'static,
+        E: for<'d> serde::Deserialize<'d> + std::marker::Send + std::marker::Unpin + 'static,
+    >(
+        &self,
+        filter: &FailedJobsFilter,
+        before_id: Option<i64>,
+        limit: u32,
+    ) -> PgQueueResult<Vec<JobSummary<M, E>>> {
+        let base_query = format!(
+            r#"
+SELECT
+    *
+FROM (
+    SELECT {JOB_SUMMARY_COLUMNS}
+    FROM
+        job_queue
+    WHERE
+        queue = $1
+        AND status = 'failed'
+    UNION ALL
+    SELECT {JOB_SUMMARY_COLUMNS}
+    FROM
+        job_queue_dead_letter
+    WHERE
+        queue = $1
+) AS failed_jobs
+WHERE
+    ($2::bigint IS NULL OR (metadata->>$3::text)::bigint = $2)
+    AND ($4::text IS NULL OR metadata->>$5::text = $4)
+    AND ($6::bigint IS NULL OR id < $6)
+ORDER BY
+    id DESC
+LIMIT $7
+        "#
+        );
+
+        sqlx::query_as(&base_query)
+            .bind(&self.name)
+            .bind(filter.team_id)
+            .bind(filter.kind.team_id_key())
+            .bind(&filter.app_id)
+            .bind(filter.kind.app_id_key())
+            .bind(before_id)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|error| DatabaseError::QueryError {
+                command: "SELECT".to_owned(),
+                error,
+            })
+    }
+
+    /// Make failed jobs in this PgQueue available again, with their attempts reset so they get
+    /// their full number of max attempts. Past errors are kept. Jobs the janitor has moved to the
+    /// dead letter table are moved back into the queue under the same id. Jobs that are not failed
+    /// are left untouched. Returns the ids of the jobs that were re-enqueued.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids`: The ids of the failed jobs to re-enqueue.
+    pub async fn retry_failed_jobs(&self, ids: &[i64]) -> PgQueueResult<Vec<i64>> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|error| DatabaseError::ConnectionError { error })?;
+
+        let retry_query = r#"
+UPDATE
+    job_queue
+SET
+    status = 'available'::job_status,
+    attempt = 0,
+    scheduled_at = NOW(),
+    last_attempt_finished_at = NULL
+WHERE
+    queue = $1
+    AND id = ANY($2)
+    AND status = 'failed'
+RETURNING
+    id
+        "#;
+
+        let mut retried: Vec<i64> = sqlx::query_scalar(retry_query)
+            .bind(&self.name)
+            .bind(ids)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|error| DatabaseError::QueryError {
+                command: "UPDATE".to_owned(),
+                error,
+            })?;
+
+        let requeue_query = r#"
+WITH dead_lettered AS (
+    DELETE FROM
+        job_queue_dead_letter
+    WHERE
+        queue = $1
+        AND id = ANY($2)
+    RETURNING
+        *
+)
+INSERT INTO job_queue
+    (id, attempt, attempted_at, attempted_by, created_at, errors, max_attempts, metadata, last_attempt_finished_at, parameters, queue, scheduled_at, status, target)
+SELECT
+    id, 0, attempted_at, attempted_by, created_at, errors, max_attempts, metadata, NULL, parameters, queue, NOW(), 'available'::job_status, target
+FROM
+    dead_lettered
+RETURNING
+    id
+        "#;
+
+        let requeued: Vec<i64> = sqlx::query_scalar(requeue_query)
+            .bind(&self.name)
+            .bind(ids)
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|error| DatabaseError::QueryError {
+                command: "INSERT".to_owned(),
+                error,
+            })?;
+
+        tx.commit()
+            .await
+            .map_err(|error| DatabaseError::TransactionError {
+                command: "COMMIT".to_owned(),
+                error,
+            })?;
+
+        retried.extend(requeued);
+        retried.sort_unstable();
+        Ok(retried)
+    }
+
+    /// Push back all available jobs in this PgQueue for `target` so they aren't dequeued
+    /// before `delay` has passed. Jobs locked by other transactions are skipped.
+    /// Returns the number of jobs rescheduled.
+    ///
+    /// # Arguments
+    ///
+    /// * `target`: The target whose jobs to reschedule. E.g. a host that is currently failing.
+    /// * `delay`: The minimum duration from now until the jobs may be dequeued.
+    pub async fn reschedule_target(
+        &self,
+        target: &str,
+        delay: time::Duration,
+    ) -> PgQueueResult<u64> {
+        let base_query = r#"
+WITH pending_for_target AS (
+    SELECT
+        id
+    FROM
+        job_queue
+    WHERE
+        queue = $1
+        AND status = 'available'
+        AND target = $2
+        AND scheduled_at < NOW() + $3
+    FOR UPDATE SKIP LOCKED
+)
+UPDATE
+    job_queue
+SET
+    scheduled_at = NOW() + $3
+FROM
+    pending_for_target
+WHERE
+    job_queue.id = pending_for_target.id
+        "#;
+
+        let result = sqlx::query(base_query)
+            .bind(&self.name)
+            .bind(target)
+            .bind(delay)
+            .execute(&self.pool)
+            .await
+            .map_err(|error| DatabaseError::QueryError {
+                command: "UPDATE".to_owned(),
+                error,
+            })?;
+
+        Ok(result.rows_affected())
+    }
 }
 
 #[cfg(test)]
@@ -952,4 +1329,248 @@ mod tests {
             .await
             .expect("failed to retry job");
     }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_can_reschedule_job_without_counting_attempt(db: PgPool) {
+        let job_target = job_target();
+        let worker_id = worker_id();
+        let new_job = NewJob::new(
+            1,
+            JobMetadata::default(),
+            JobParameters::default(),
+            &job_target,
+        );
+        let queue =
+            PgQueue::new_from_pool("test_can_reschedule_job_without_counting_attempt", db).await;
+
+        queue.enqueue(new_job).await.expect("failed to enqueue job");
+        let mut batch: PgTransactionBatch<'_, JobParameters, JobMetadata> = queue
+            .dequeue_tx(&worker_id, 1)
+            .await
+            .expect("failed to dequeue job")
+            .expect("didn't find a job to dequeue");
+        let job = batch.jobs.pop().unwrap();
+        assert_eq!(job.job.attempt, 1);
+
+        job.reschedule(time::Duration::from_secs(0))
+            .await
+            .expect("failed to reschedule job");
+        batch.commit().await.expect("failed to commit transaction");
+
+        let rescheduled_job: PgTransactionJob<JobParameters, JobMetadata> = queue
+            .dequeue_tx(&worker_id, 1)
+            .await
+            .expect("failed to dequeue job")
+            .expect("didn't find rescheduled job to dequeue")
+            .jobs
+            .pop()
+            .unwrap();
+
+        // Still the first attempt, so a single-attempt job isn't lost.
+        assert_eq!(rescheduled_job.job.attempt, 1);
+        assert_eq!(rescheduled_job.job.max_attempts, 1);
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_can_reschedule_target(db: PgPool) {
+        let worker_id = worker_id();
+        let queue = PgQueue::new_from_pool("test_can_reschedule_target", db).await;
+
+        for target in ["unhealthy-host", "unhealthy-host", "healthy-host"] {
+            queue
+                .enqueue(NewJob::new(
+                    1,
+                    JobMetadata::default(),
+                    JobParameters::default(),
+                    target,
+                ))
+                .await
+                .expect("failed to enqueue job");
+        }
+
+        let rescheduled = queue
+            .reschedule_target("unhealthy-host", time::Duration::from_secs(60))
+            .await
+            .expect("failed to reschedule target");
+        assert_eq!(rescheduled, 2);
+
+        let mut batch: PgTransactionBatch<'_, JobParameters, JobMetadata> = queue
+            .dequeue_tx(&worker_id, 10)
+            .await
+            .expect("failed to dequeue jobs")
+            .expect("didn't find any jobs to dequeue");
+
+        assert_eq!(batch.jobs.len(), 1);
+        let job = batch.jobs.pop().unwrap();
+        assert_eq!(job.job.target, "healthy-host");
+
+        job.complete().await.expect("failed to complete job");
+        batch.commit().await.expect("failed to commit transaction");
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_can_inspect_and_retry_failed_jobs(db: PgPool) {
+        let worker_id = worker_id();
+        let queue = PgQueue::new_from_pool("test_can_inspect_and_retry_failed_jobs", db).await;
+
+        for team_id in [1, 1, 2] {
+            let job_metadata = JobMetadata {
+                team_id,
+                ..JobMetadata::default()
+            };
+            queue
+                .enqueue(NewJob::new(
+                    1,
+                    job_metadata,
+                    JobParameters::default(),
+                    &job_target(),
+                ))
+                .await
+                .expect("failed to enqueue job");
+        }
+
+        let mut batch: PgTransactionBatch<'_, JobParameters, JobMetadata> = queue
+            .dequeue_tx(&worker_id, 3)
+            .await
+            .expect("failed to dequeue jobs")
+            .expect("didn't find any jobs to dequeue");
+        for job in std::mem::take(&mut batch.jobs) {
+            job.fail("a very reasonable failure reason")
+                .await
+                .expect("failed to fail job");
+        }
+        batch.commit().await.expect("failed to commit transaction");
+
+        // The janitor moves failed jobs to the dead letter table, which they're still listed from
+        let dead_lettered: i64 = sqlx::query_scalar(
+            r#"
+WITH moved AS (
+    DELETE FROM job_queue WHERE id = (SELECT MIN(id) FROM job_queue) RETURNING *
+)
+INSERT INTO job_queue_dead_letter SELECT * FROM moved RETURNING id
+            "#,
+        )
+        .fetch_one(&queue.pool)
+        .await
+        .expect("failed to dead letter job");
+
+        let filter = FailedJobsFilter {
+            kind: JobKind::Webhook,
+            team_id: Some(1),
+            app_id: None,
+        };
+        let first_page: Vec<JobSummary<JobMetadata, String>> = queue
+            .list_failed_jobs(&filter, None, 1)
+            .await
+            .expect("failed to list failed jobs");
+        assert_eq!(first_page.len(), 1);
+        let second_page: Vec<JobSummary<JobMetadata, String>> = queue
+            .list_failed_jobs(&filter, Some(first_page[0].id), 10)
+            .await
+            .expect("failed to list failed jobs");
+        assert_eq!(second_page.len(), 1);
+        assert!(second_page[0].id < first_page[0].id);
+
+        let failed_job = &second_page[0];
+        assert_eq!(failed_job.id, dead_lettered);
+        assert_eq!(failed_job.status, JobStatus::Failed);
+        assert_eq!(failed_job.attempt, 1);
+        assert_eq!(failed_job.metadata.team_id, 1);
+        assert_eq!(
+            failed_job.last_error.as_ref().map(|error| error.as_str()),
+            Some("a very reasonable failure reason")
+        );
+
+        let retried = queue
+            .retry_failed_jobs(&[failed_job.id, failed_job.id + 1000])
+            .await
+            .expect("failed to retry failed jobs");
+        assert_eq!(retried, vec![failed_job.id]);
+
+        let retried_job: JobSummary<JobMetadata, String> = queue
+            .get_job(failed_job.id)
+            .await
+            .expect("failed to get job")
+            .expect("didn't find retried job");
+        assert_eq!(retried_job.status, JobStatus::Available);
+        assert_eq!(retried_job.attempt, 0);
+        let still_dead_lettered: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM job_queue_dead_letter")
+                .fetch_one(&queue.pool)
+                .await
+                .expect("failed to count dead lettered jobs");
+        assert_eq!(still_dead_lettered, 0);
+        // Errors from previous attempts are kept around.
+        assert!(retried_job.last_error.is_some());
+
+        let remaining: Vec<JobSummary<JobMetadata, String>> = queue
+            .list_failed_jobs(&filter, None, 10)
+            .await
+            .expect("failed to list failed jobs");
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, first_page[0].id);
+
+        let missing: Option<JobSummary<JobMetadata, String>> = queue
+            .get_job(failed_job.id + 1000)
+            .await
+            .expect("failed to get job");
+        assert!(missing.is_none());
+    }
+
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_can_list_failed_hoghook_jobs(db: PgPool) {
+        let worker_id = worker_id();
+        let queue = PgQueue::new_from_pool("test_can_list_failed_hoghook_jobs", db).await;
+
+        for (team_id, hog_function_id) in [(1, "a"), (1, "b"), (2, "a")] {
+            let job_metadata = serde_json::json!({
+                "teamId": team_id,
+                "hogFunctionId": hog_function_id,
+            });
+            queue
+                .enqueue(NewJob::new(
+                    1,
+                    job_metadata,
+                    JobParameters::default(),
+                    &job_target(),
+                ))
+                .await
+                .expect("failed to enqueue job");
+        }
+
+        let mut batch: PgTransactionBatch<'_, JobParameters, serde_json::Value> = queue
+            .dequeue_tx(&worker_id, 3)
+            .await
+            .expect("failed to dequeue jobs")
+            .expect("didn't find any jobs to dequeue");
+        for job in std::mem::take(&mut batch.jobs) {
+            job.fail("a very reasonable failure reason")
+                .await
+                .expect("failed to fail job");
+        }
+        batch.commit().await.expect("failed to commit transaction");
+
+        let list = |team_id: Option<i64>, app_id: Option<&str>| {
+            let filter = FailedJobsFilter {
+                kind: JobKind::Hoghook,
+                team_id,
+                app_id: app_id.map(str::to_owned),
+            };
+            let queue = queue.clone();
+            async move {
+                let jobs: Vec<JobSummary<serde_json::Value, String>> = queue
+                    .list_failed_jobs(&filter, None, 10)
+                    .await
+                    .expect("failed to list failed jobs");
+                jobs
+            }
+        };
+
+        assert_eq!(list(Some(1), None).await.len(), 2);
+        assert_eq!(list(None, Some("a")).await.len(), 2);
+        let jobs = list(Some(1), Some("a")).await;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].metadata.0["hogFunctionId"], "a");
+        assert!(list(Some(3), None).await.is_empty());
+    }
 }
@@ -135,6 +135,9 @@ pub struct WebhookJobMetadata {
     pub team_id: u32,
     pub plugin_id: i32,
     pub plugin_config_id: i32,
+    /// Name of the secret the `WebhookWorker` signs requests with. Never the secret itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_secret_ref: Option<String>,
 }
 
 /// An error originating during a Webhook Job invocation.
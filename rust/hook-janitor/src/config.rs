@@ -29,6 +29,11 @@ pub struct Config {
     #[envconfig(default = "false")]
     pub hog_mode: bool,
 
+    // Failed jobs are moved to a dead letter table, where they can still be inspected and retried
+    // through the hook-api, until they're older than this.
+    #[envconfig(default = "604800")] // 7 days
+    pub failed_job_retention_secs: u64,
+
     #[envconfig(default = "clickhouse_app_metrics")]
     pub app_metrics_topic: String,
 
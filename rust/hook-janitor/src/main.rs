@@ -76,6 +76,7 @@ async fn main() {
                     config.app_metrics_topic.to_owned(),
                     config.app_metrics2_topic.to_owned(),
                     config.hog_mode,
+                    Duration::from_secs(config.failed_job_retention_secs),
                 )
                 .expect("unable to create webhook cleaner"),
             )
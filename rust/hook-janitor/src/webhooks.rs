@@ -45,6 +45,8 @@ pub enum WebhookCleanerError {
     KafkaProduceCanceled,
     #[error("failed to delete rows: {error}")]
     DeleteRowsError { error: sqlx::Error },
+    #[error("failed to prune dead letter rows: {error}")]
+    PruneDeadLetterRowsError { error: sqlx::Error },
     #[error("attempted to delete a different number of rows than expected")]
     DeleteConsistencyError,
     #[error("failed to rollback txn: {error}")]
@@ -61,6 +63,7 @@ pub struct WebhookCleaner {
     app_metrics_topic: String,
     app_metrics2_topic: String,
     hog_mode: bool,
+    failed_job_retention: Duration,
 }
 
 #[derive(sqlx::FromRow, Debug)]
@@ -239,6 +242,7 @@ struct CleanupStats {
     completed_agg_row_count: u64,
     failed_row_count: u64,
     failed_agg_row_count: u64,
+    dead_letter_rows_pruned: u64,
 }
 
 impl WebhookCleaner {
@@ -248,6 +252,7 @@ impl WebhookCleaner {
         app_metrics_topic: String,
         app_metrics2_topic: String,
         hog_mode: bool,
+        failed_job_retention: Duration,
     ) -> Result<Self> {
         let options = PgConnectOptions::from_str(database_url)
             .map_err(|error| WebhookCleanerError::PoolCreationError { error })?
@@ -262,6 +267,7 @@ impl WebhookCleaner {
             app_metrics_topic,
             app_metrics2_topic,
             hog_mode,
+            failed_job_retention,
         })
     }
 
@@ -272,6 +278,7 @@ impl WebhookCleaner {
         app_metrics_topic: String,
         app_metrics2_topic: String,
         hog_mode: bool,
+        failed_job_retention: Duration,
     ) -> Result<Self> {
         Ok(Self {
             pg_pool,
@@ -279,6 +286,7 @@ impl WebhookCleaner {
             app_metrics_topic,
             app_metrics2_topic,
             hog_mode,
+            failed_job_retention,
         })
     }
 
@@ -441,17 +449,44 @@ impl WebhookCleaner {
 
     async fn delete_observed_rows(&self, tx: &mut SerializableTxn<'_>) -> Result<u64> {
         // This DELETE is only safe because we are in serializable isolation mode, see the note
-        // in `start_serializable_txn`.
+        // in `start_serializable_txn`. Failed rows are moved to the dead letter table rather than
+        // dropped, so they can still be inspected and retried through the API.
         let base_query = r#"
-            DELETE FROM job_queue
-            WHERE status IN ('failed', 'completed')
+            WITH deleted AS (
+                DELETE FROM job_queue
+                WHERE status IN ('failed', 'completed')
+                RETURNING *
+            ), dead_lettered AS (
+                INSERT INTO job_queue_dead_letter
+                    (id, attempt, attempted_at, attempted_by, created_at, errors, max_attempts, metadata, last_attempt_finished_at, parameters, queue, scheduled_at, status, target)
+                SELECT
+                    id, attempt, attempted_at, attempted_by, created_at, errors, max_attempts, metadata, last_attempt_finished_at, parameters, queue, scheduled_at, status, target
+                FROM deleted
+                WHERE status = 'failed'
+            )
+            SELECT COUNT(*) FROM deleted
         "#;
 
-        let result = sqlx::query(base_query)
-            .execute(&mut *tx.0)
+        let rows_deleted: i64 = sqlx::query_scalar(base_query)
+            .fetch_one(&mut *tx.0)
             .await
             .map_err(|e| WebhookCleanerError::DeleteRowsError { error: e })?;
 
+        Ok(rows_deleted as u64)
+    }
+
+    async fn prune_dead_letter_rows(&self) -> Result<u64> {
+        let base_query = r#"
+            DELETE FROM job_queue_dead_letter
+            WHERE dead_lettered_at < NOW() - make_interval(secs => $1)
+        "#;
+
+        let result = sqlx::query(base_query)
+            .bind(self.failed_job_retention.as_secs_f64())
+            .execute(&self.pg_pool)
+            .await
+            .map_err(|e| WebhookCleanerError::PruneDeadLetterRowsError { error: e })?;
+
         Ok(result.rows_affected())
     }
 
@@ -571,12 +606,15 @@ impl WebhookCleaner {
             self.commit_txn(tx).await?;
         }
 
+        let dead_letter_rows_pruned = self.prune_dead_letter_rows().await?;
+
         Ok(CleanupStats {
             rows_processed: rows_deleted,
             completed_row_count,
             completed_agg_row_count,
             failed_row_count,
             failed_agg_row_count,
+            dead_letter_rows_pruned,
         })
     }
 }
@@ -593,6 +631,11 @@ impl Cleaner for WebhookCleaner {
                 metrics::gauge!("webhook_cleanup_last_success_timestamp",)
                     .set(get_current_timestamp_seconds());
 
+                if stats.dead_letter_rows_pruned > 0 {
+                    metrics::counter!("webhook_cleanup_dead_letter_rows_pruned",)
+                        .increment(stats.dead_letter_rows_pruned);
+                }
+
                 if stats.rows_processed > 0 {
                     let elapsed_time = start_time.elapsed().as_secs_f64();
                     metrics::histogram!("webhook_cleanup_duration").record(elapsed_time);
@@ -647,6 +690,7 @@ mod tests {
 
     const APP_METRICS_TOPIC: &str = "app_metrics";
     const APP_METRICS2_TOPIC: &str = "app_metrics2";
+    const FAILED_JOB_RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 
     fn check_app_metric_vector_equality(v1: &[AppMetric], v2: &[AppMetric]) {
         // Ignores `error_uuid`s.
@@ -682,6 +726,7 @@ mod tests {
             APP_METRICS_TOPIC.to_owned(),
             APP_METRICS2_TOPIC.to_owned(),
             hog_mode,
+            FAILED_JOB_RETENTION,
         )
         .expect("unable to create webhook cleaner");
 
@@ -693,6 +738,15 @@ mod tests {
         // Rows that are not 'completed' or 'failed' should not be processed.
         assert_eq!(cleanup_stats.rows_processed, 13);
 
+        // Failed rows are kept in the dead letter table, completed ones are gone.
+        let dead_lettered: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM job_queue_dead_letter WHERE status = 'failed'",
+        )
+        .fetch_one(&webhook_cleaner.pg_pool)
+        .await
+        .unwrap();
+        assert_eq!(dead_lettered as u64, cleanup_stats.failed_row_count);
+
         let mut received_app_metrics = Vec::new();
         for _ in 0..(cleanup_stats.completed_agg_row_count + cleanup_stats.failed_agg_row_count) {
             let kafka_msg = consumer.recv().await.unwrap();
@@ -876,6 +930,7 @@ mod tests {
             APP_METRICS_TOPIC.to_owned(),
             APP_METRICS2_TOPIC.to_owned(),
             hog_mode,
+            FAILED_JOB_RETENTION,
         )
         .expect("unable to create hoghook cleaner");
 
@@ -1018,6 +1073,7 @@ mod tests {
             APP_METRICS_TOPIC.to_owned(),
             APP_METRICS2_TOPIC.to_owned(),
             hog_mode,
+            FAILED_JOB_RETENTION,
         )
         .expect("unable to create webhook cleaner");
 
@@ -1034,6 +1090,49 @@ mod tests {
         assert_eq!(cleanup_stats.failed_agg_row_count, 0);
     }
 
+    #[sqlx::test(migrations = "../migrations")]
+    async fn test_cleanup_impl_prunes_dead_letter_rows(db: PgPool) {
+        let (_, mock_producer) = create_mock_kafka().await;
+        let hog_mode = false;
+        let webhook_cleaner = WebhookCleaner::new_from_pool(
+            db.clone(),
+            mock_producer,
+            APP_METRICS_TOPIC.to_owned(),
+            APP_METRICS2_TOPIC.to_owned(),
+            hog_mode,
+            FAILED_JOB_RETENTION,
+        )
+        .expect("unable to create webhook cleaner");
+
+        for (id, days_ago) in [(1_i64, 1), (2, 8)] {
+            sqlx::query(
+                r#"
+                INSERT INTO job_queue_dead_letter
+                    (id, attempt, created_at, max_attempts, queue, scheduled_at, status, target, dead_lettered_at)
+                VALUES
+                    ($1, 1, NOW(), 1, 'webhooks', NOW(), 'failed', 'example.com', NOW() - make_interval(days => $2))
+                "#,
+            )
+            .bind(id)
+            .bind(days_ago)
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+
+        let cleanup_stats = webhook_cleaner
+            .cleanup_impl()
+            .await
+            .expect("webbook cleanup_impl failed");
+        assert_eq!(cleanup_stats.dead_letter_rows_pruned, 1);
+
+        let remaining: Vec<i64> = sqlx::query_scalar("SELECT id FROM job_queue_dead_letter")
+            .fetch_all(&db)
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec![1]);
+    }
+
     #[sqlx::test(migrations = "../migrations", fixtures("webhook_cleanup"))]
     async fn test_serializable_isolation(db: PgPool) {
         let (_, mock_producer) = create_mock_kafka().await;
@@ -1044,6 +1143,7 @@ mod tests {
             APP_METRICS_TOPIC.to_owned(),
             APP_METRICS2_TOPIC.to_owned(),
             hog_mode,
+            FAILED_JOB_RETENTION,
         )
         .expect("unable to create webhook cleaner");
 
@@ -1102,6 +1202,7 @@ mod tests {
                 team_id: 1,
                 plugin_id: 2,
                 plugin_config_id: 3,
+                signing_secret_ref: None,
             };
             let new_job = NewJob::new(1, job_metadata, job_parameters, "target");
             queue.enqueue(new_job).await.expect("failed to enqueue job");
@@ -1130,6 +1231,7 @@ mod tests {
                 team_id: 1,
                 plugin_id: 2,
                 plugin_config_id: 3,
+                signing_secret_ref: None,
             };
             let new_job = NewJob::new(1, job_metadata, job_parameters, "target");
             queue.enqueue(new_job).await.expect("failed to enqueue job");
@@ -6,6 +6,8 @@ use envconfig::Envconfig;
 
 use common_kafka::config::KafkaConfig;
 
+use crate::signing::SigningSecrets;
+
 #[derive(Envconfig, Clone)]
 pub struct Config {
     #[envconfig(nested = true)]
@@ -47,12 +49,32 @@ pub struct Config {
     #[envconfig(default = "false")]
     pub allow_internal_ips: bool,
 
+    /// Maximum concurrent requests to a single destination host. 0 means unlimited.
+    #[envconfig(default = "64")]
+    pub max_in_flight_per_destination: usize,
+
+    /// Consecutive failures after which requests to a destination host are paused.
+    /// 0 disables the circuit breaker.
+    #[envconfig(default = "10")]
+    pub destination_failure_threshold: u32,
+
+    #[envconfig(default = "30000")]
+    pub destination_circuit_open_duration: EnvMsDuration,
+
+    #[envconfig(default = "1000")]
+    pub destination_saturated_delay: EnvMsDuration,
+
     #[envconfig(default = "false")]
     pub hog_mode: bool,
 
     #[envconfig(default = "cdp_function_callbacks")]
     pub cdp_function_callbacks_topic: String,
 
+    /// JSON object mapping signing secret references to Standard Webhooks secrets,
+    /// current secret first, e.g. `{"ref": ["whsec_...", "whsec_..."]}`.
+    #[envconfig(default = "")]
+    pub webhook_signing_secrets: SigningSecrets,
+
     #[envconfig(nested = true)]
     pub kafka: KafkaConfig,
 }
@@ -0,0 +1,504 @@
+//! Track the health of webhook destinations, keyed by job target (i.e. the destination host).
+//!
+//! Each destination gets a cap on in-flight requests and a circuit breaker. After
+//! `failure_threshold` consecutive failures the circuit opens: the destination's pending jobs in
+//! `PgQueue` are pushed back, and jobs dequeued in the meantime are rescheduled without being
+//! attempted. Once `open_duration` passes a single probe request is let through (half-open),
+//! whose outcome closes the circuit or opens it again.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time;
+
+use hook_common::pgqueue::PgQueue;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+use tracing::{error, warn};
+
+/// Forget about healthy idle destinations once we track more than this many.
+const MAX_TRACKED_DESTINATIONS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DestinationConfig {
+    /// Maximum number of concurrent requests to a single destination. 0 means unlimited.
+    pub max_in_flight: usize,
+    /// Consecutive failures after which the circuit opens. 0 disables the circuit breaker.
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects requests before letting a probe through.
+    pub open_duration: time::Duration,
+    /// How long to push back jobs for a destination that is at its in-flight cap.
+    pub saturated_delay: time::Duration,
+}
+
+/// Whether a request reached a healthy destination. Only outcomes a destination is responsible
+/// for count: e.g. a 4XX means the destination is up, while timeouts and 5XXs mean it isn't.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DestinationOutcome {
+    Success,
+    Failure,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    CircuitOpen,
+    Saturated,
+}
+
+impl RejectReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectReason::CircuitOpen => "circuit_open",
+            RejectReason::Saturated => "saturated",
+        }
+    }
+}
+
+pub enum Admission {
+    /// The request may be sent. The permit must be held until the request finishes.
+    Allowed(DestinationPermit),
+    /// The request must not be sent now, and its job should be rescheduled after `delay`.
+    Rejected {
+        reason: RejectReason,
+        delay: time::Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probing: bool },
+}
+
+impl CircuitState {
+    /// Numeric representation for the per-destination state gauge.
+    fn gauge_value(&self) -> f64 {
+        match self {
+            CircuitState::Closed => 0.0,
+            CircuitState::HalfOpen { .. } => 1.0,
+            CircuitState::Open { .. } => 2.0,
+        }
+    }
+}
+
+/// A circuit breaker state machine. Time is passed in to keep it deterministic.
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    consecutive_failures: u32,
+}
+
+impl Circuit {
+    fn new() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Decide whether a request may be sent. Returns whether it is the half-open probe,
+    /// or how long until the circuit may let a probe through.
+    fn admit(&mut self, now: Instant) -> Result<bool, time::Duration> {
+        match self.state {
+            CircuitState::Closed => Ok(false),
+            CircuitState::Open { until } if now < until => Err(until - now),
+            CircuitState::Open { .. } | CircuitState::HalfOpen { probing: false } => {
+                self.state = CircuitState::HalfOpen { probing: true };
+                Ok(true)
+            }
+            // Only one probe at a time: others wait a little and try again.
+            CircuitState::HalfOpen { probing: true } => Err(time::Duration::from_secs(1)),
+        }
+    }
+
+    /// Record an outcome. Returns true if this opened the circuit.
+    fn record(
+        &mut self,
+        outcome: DestinationOutcome,
+        now: Instant,
+        config: &DestinationConfig,
+    ) -> bool {
+        match outcome {
+            DestinationOutcome::Success => {
+                self.consecutive_failures = 0;
+                self.state = CircuitState::Closed;
+                false
+            }
+            DestinationOutcome::Failure => {
+                self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+                let should_open = match self.state {
+                    CircuitState::Closed => {
+                        config.failure_threshold > 0
+                            && self.consecutive_failures >= config.failure_threshold
+                    }
+                    // A failed probe opens the circuit again.
+                    CircuitState::HalfOpen { .. } => true,
+                    // Requests admitted before the circuit opened are still finishing.
+                    CircuitState::Open { .. } => false,
+                };
+                if should_open {
+                    self.state = CircuitState::Open {
+                        until: now + config.open_duration,
+                    };
+                }
+                should_open
+            }
+        }
+    }
+
+    /// A probe finished without an outcome, e.g. because the request couldn't be built.
+    fn abandon_probe(&mut self) {
+        if self.state == (CircuitState::HalfOpen { probing: true }) {
+            self.state = CircuitState::HalfOpen { probing: false };
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.state == CircuitState::Closed && self.consecutive_failures == 0
+    }
+}
+
+struct Destination {
+    host: String,
+    in_flight: Option<Arc<Semaphore>>,
+    circuit: Mutex<Circuit>,
+}
+
+impl Destination {
+    fn report_state(&self, state: CircuitState) {
+        metrics::gauge!("webhook_destination_circuit_state", "host" => self.host.clone())
+            .set(state.gauge_value());
+    }
+
+    fn report_in_flight(&self, max_in_flight: usize) {
+        if let Some(semaphore) = &self.in_flight {
+            metrics::gauge!("webhook_destination_in_flight", "host" => self.host.clone())
+                .set((max_in_flight - semaphore.available_permits()) as f64);
+        }
+    }
+}
+
+/// All destinations this worker has sent requests to.
+pub struct Destinations {
+    config: DestinationConfig,
+    queue: PgQueue,
+    destinations: Mutex<HashMap<String, Arc<Destination>>>,
+}
+
+impl Destinations {
+    /// # Arguments
+    ///
+    /// * `config`: Limits and circuit breaker parameters applied to every destination.
+    /// * `queue`: The queue whose pending jobs are rescheduled when a destination's circuit opens.
+    pub fn new(config: DestinationConfig, queue: PgQueue) -> Self {
+        Self {
+            config,
+            queue,
+            destinations: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn destination(&self, host: &str) -> Arc<Destination> {
+        let mut destinations = self
+            .destinations
+            .lock()
+            .expect("poisoned destinations lock");
+
+        if let Some(destination) = destinations.get(host) {
+            return destination.clone();
+        }
+
+        if destinations.len() >= MAX_TRACKED_DESTINATIONS {
+            let max_in_flight = self.config.max_in_flight;
+            destinations.retain(|_, destination| {
+                let no_requests = destination
+                    .in_flight
+                    .as_ref()
+                    .is_none_or(|s| s.available_permits() == max_in_flight);
+                let idle = destination
+                    .circuit
+                    .lock()
+                    .expect("poisoned circuit lock")
+                    .is_idle();
+                !(no_requests && idle)
+            });
+        }
+
+        let destination = Arc::new(Destination {
+            host: host.to_owned(),
+            in_flight: (self.config.max_in_flight > 0)
+                .then(|| Arc::new(Semaphore::new(self.config.max_in_flight))),
+            circuit: Mutex::new(Circuit::new()),
+        });
+        destinations.insert(host.to_owned(), destination.clone());
+        destination
+    }
+
+    /// Decide whether a request to `host` may be sent now.
+    pub fn admit(&self, host: &str) -> Admission {
+        let destination = self.destination(host);
+
+        let in_flight_permit = match &destination.in_flight {
+            Some(semaphore) => match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    return self.reject(
+                        &destination,
+                        RejectReason::Saturated,
+                        self.config.saturated_delay,
+                    )
+                }
+            },
+            None => None,
+        };
+
+        let admitted = {
+            let mut circuit = destination.circuit.lock().expect("poisoned circuit lock");
+            let admitted = circuit.admit(Instant::now());
+            if matches!(admitted, Ok(true)) {
+                destination.report_state(circuit.state);
+            }
+            admitted
+        };
+
+        match admitted {
+            Ok(probe) => {
+                destination.report_in_flight(self.config.max_in_flight);
+                Admission::Allowed(DestinationPermit {
+                    destination,
+                    queue: self.queue.clone(),
+                    config: self.config,
+                    probe,
+                    in_flight_permit,
+                })
+            }
+            Err(delay) => self.reject(&destination, RejectReason::CircuitOpen, delay),
+        }
+    }
+
+    fn reject(
+        &self,
+        destination: &Destination,
+        reason: RejectReason,
+        delay: time::Duration,
+    ) -> Admission {
+        metrics::counter!(
+            "webhook_destination_rejected_total",
+            "host" => destination.host.clone(),
+            "reason" => reason.as_str()
+        )
+        .increment(1);
+
+        Admission::Rejected { reason, delay }
+    }
+}
+
+/// Held while a request to a destination is in flight.
+pub struct DestinationPermit {
+    destination: Arc<Destination>,
+    queue: PgQueue,
+    config: DestinationConfig,
+    probe: bool,
+    in_flight_permit: Option<OwnedSemaphorePermit>,
+}
+
+impl DestinationPermit {
+    /// Record the outcome of the request and release the permit. If this opens the circuit,
+    /// the destination's pending jobs are rescheduled until the circuit may be probed again.
+    pub async fn record(mut self, outcome: DestinationOutcome) {
+        let outcome_label = match outcome {
+            DestinationOutcome::Success => "success",
+            DestinationOutcome::Failure => "failure",
+        };
+        metrics::counter!(
+            "webhook_destination_requests_total",
+            "host" => self.destination.host.clone(),
+            "outcome" => outcome_label
+        )
+        .increment(1);
+
+        let opened = {
+            let mut circuit = self
+                .destination
+                .circuit
+                .lock()
+                .expect("poisoned circuit lock");
+            let opened = circuit.record(outcome, Instant::now(), &self.config);
+            self.destination.report_state(circuit.state);
+            opened
+        };
+        // The outcome was recorded, so dropping must not abandon the probe.
+        self.probe = false;
+
+        if !opened {
+            return;
+        }
+
+        let host = &self.destination.host;
+        metrics::counter!("webhook_destination_circuit_opened_total", "host" => host.clone())
+            .increment(1);
+
+        match self
+            .queue
+            .reschedule_target(host, self.config.open_duration)
+            .await
+        {
+            Ok(rescheduled) => {
+                warn!(
+                    "opened circuit for destination {} and rescheduled {} pending jobs",
+                    host, rescheduled
+                );
+                metrics::counter!(
+                    "webhook_destination_rescheduled_jobs_total",
+                    "host" => host.clone()
+                )
+                .increment(rescheduled);
+            }
+            Err(e) => {
+                // Jobs will still be rejected and rescheduled one by one as they are dequeued.
+                error!(
+                    "error rescheduling pending jobs for destination {}: {}",
+                    host, e
+                );
+            }
+        }
+    }
+}
+
+impl Drop for DestinationPermit {
+    fn drop(&mut self) {
+        if self.probe {
+            self.destination
+                .circuit
+                .lock()
+                .expect("poisoned circuit lock")
+                .abandon_probe();
+        }
+        if self.in_flight_permit.take().is_some() {
+            self.destination.report_in_flight(self.config.max_in_flight);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DestinationConfig {
+        DestinationConfig {
+            max_in_flight: 2,
+            failure_threshold: 3,
+            open_duration: time::Duration::from_secs(30),
+            saturated_delay: time::Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn test_circuit_opens_after_consecutive_failures() {
+        let config = config();
+        let mut circuit = Circuit::new();
+        let now = Instant::now();
+
+        assert!(!circuit.record(DestinationOutcome::Failure, now, &config));
+        assert!(!circuit.record(DestinationOutcome::Failure, now, &config));
+        // A success in between resets the count.
+        assert!(!circuit.record(DestinationOutcome::Success, now, &config));
+        assert!(!circuit.record(DestinationOutcome::Failure, now, &config));
+        assert!(!circuit.record(DestinationOutcome::Failure, now, &config));
+        assert_eq!(circuit.admit(now), Ok(false));
+        assert!(circuit.record(DestinationOutcome::Failure, now, &config));
+
+        assert_eq!(circuit.admit(now), Err(config.open_duration));
+        assert_eq!(
+            circuit.admit(now + time::Duration::from_secs(10)),
+            Err(time::Duration::from_secs(20))
+        );
+    }
+
+    #[test]
+    fn test_circuit_half_open_probe() {
+        let config = config();
+        let mut circuit = Circuit::new();
+        let now = Instant::now();
+        for _ in 0..config.failure_threshold {
+            circuit.record(DestinationOutcome::Failure, now, &config);
+        }
+
+        // Once open_duration passes, a single probe is let through.
+        let later = now + config.open_duration;
+        assert_eq!(circuit.admit(later), Ok(true));
+        assert!(circuit.admit(later).is_err());
+
+        // A failed probe opens the circuit again.
+        assert!(circuit.record(DestinationOutcome::Failure, later, &config));
+        assert!(circuit.admit(later).is_err());
+
+        // A successful probe closes it.
+        let even_later = later + config.open_duration;
+        assert_eq!(circuit.admit(even_later), Ok(true));
+        assert!(!circuit.record(DestinationOutcome::Success, even_later, &config));
+        assert_eq!(circuit.admit(even_later), Ok(false));
+        assert!(circuit.is_idle());
+    }
+
+    #[test]
+    fn test_circuit_abandoned_probe_allows_another() {
+        let config = config();
+        let mut circuit = Circuit::new();
+        let now = Instant::now();
+        for _ in 0..config.failure_threshold {
+            circuit.record(DestinationOutcome::Failure, now, &config);
+        }
+
+        let later = now + config.open_duration;
+        assert_eq!(circuit.admit(later), Ok(true));
+        circuit.abandon_probe();
+        assert_eq!(circuit.admit(later), Ok(true));
+    }
+
+    #[test]
+    fn test_circuit_breaker_disabled() {
+        let config = DestinationConfig {
+            failure_threshold: 0,
+            ..config()
+        };
+        let mut circuit = Circuit::new();
+        let now = Instant::now();
+        for _ in 0..100 {
+            assert!(!circuit.record(DestinationOutcome::Failure, now, &config));
+        }
+        assert_eq!(circuit.admit(now), Ok(false));
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_cap_per_destination() {
+        let queue = PgQueue::new("test", "postgres://localhost/test", 1, "test")
+            .await
+            .expect("failed to create queue");
+        let destinations = Destinations::new(config(), queue);
+
+        let first = destinations.admit("a.example.com");
+        let second = destinations.admit("a.example.com");
+        assert!(matches!(first, Admission::Allowed(_)));
+        assert!(matches!(second, Admission::Allowed(_)));
+        assert!(matches!(
+            destinations.admit("a.example.com"),
+            Admission::Rejected {
+                reason: RejectReason::Saturated,
+                ..
+            }
+        ));
+        // Other destinations are unaffected.
+        assert!(matches!(
+            destinations.admit("b.example.com"),
+            Admission::Allowed(_)
+        ));
+
+        if let Admission::Allowed(permit) = first {
+            permit.record(DestinationOutcome::Success).await;
+        }
+        assert!(matches!(
+            destinations.admit("a.example.com"),
+            Admission::Allowed(_)
+        ));
+    }
+}
@@ -1,4 +1,6 @@
 pub mod config;
+pub mod destinations;
 pub mod error;
+pub mod signing;
 pub mod util;
 pub mod worker;
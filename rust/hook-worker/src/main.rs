@@ -10,6 +10,7 @@ use common_kafka::kafka_producer::create_kafka_producer;
 use common_metrics::{serve, setup_metrics_routes};
 use health::HealthRegistry;
 use hook_worker::config::Config;
+use hook_worker::destinations::DestinationConfig;
 use hook_worker::error::WorkerError;
 use hook_worker::worker::WebhookWorker;
 
@@ -76,6 +77,13 @@ async fn main() -> Result<(), WorkerError> {
         config.cdp_function_callbacks_topic.to_owned(),
         config.hog_mode,
         worker_liveness,
+        config.webhook_signing_secrets.clone(),
+        DestinationConfig {
+            max_in_flight: config.max_in_flight_per_destination,
+            failure_threshold: config.destination_failure_threshold,
+            open_duration: config.destination_circuit_open_duration.0,
+            saturated_delay: config.destination_saturated_delay.0,
+        },
     );
 
     let router = Router::new()
@@ -23,9 +23,13 @@ use hook_common::{
     webhook::{HttpMethod, WebhookJobError, WebhookJobParameters},
 };
 
+use crate::destinations::{
+    Admission, DestinationConfig, DestinationOutcome, DestinationPermit, Destinations, RejectReason,
+};
 use crate::error::{
     is_error_source, WebhookError, WebhookParseError, WebhookRequestError, WorkerError,
 };
+use crate::signing::{SigningSecrets, WebhookSigningError};
 use crate::util::first_n_bytes_of_response;
 use common_dns::{NoPublicIPv4Error, PublicIPv4Resolver};
 
@@ -91,6 +95,10 @@ pub struct WebhookWorker<'p> {
     hog_mode: bool,
     /// The liveness check handle, to call on a schedule to report healthy
     liveness: HealthHandle,
+    /// Secrets used to sign requests of jobs with a `signing_secret_ref` in their metadata.
+    signing_secrets: Arc<SigningSecrets>,
+    /// Health and in-flight requests of each destination host.
+    destinations: Arc<Destinations>,
 }
 
 pub fn build_http_client(
@@ -127,6 +135,8 @@ impl<'p> WebhookWorker<'p> {
         cdp_function_callbacks_topic: String,
         hog_mode: bool,
         liveness: HealthHandle,
+        signing_secrets: SigningSecrets,
+        destination_config: DestinationConfig,
     ) -> Self {
         let http_client = build_http_client(request_timeout, allow_internal_ips)
             .expect("failed to construct reqwest client for webhook worker");
@@ -143,6 +153,8 @@ impl<'p> WebhookWorker<'p> {
             cdp_function_callbacks_topic: cdp_function_callbacks_topic.leak(),
             hog_mode,
             liveness,
+            signing_secrets: Arc::new(signing_secrets),
+            destinations: Arc::new(Destinations::new(destination_config, queue.clone())),
         }
     }
 
@@ -200,6 +212,8 @@ impl<'p> WebhookWorker<'p> {
             let kafka_producer = self.kafka_producer.clone();
             let cdp_function_callbacks_topic = self.cdp_function_callbacks_topic;
             let hog_mode = self.hog_mode;
+            let signing_secrets = self.signing_secrets.clone();
+            let destinations = self.destinations.clone();
 
             tokio::spawn(async move {
                 // Move `permits` into the closure so they will be dropped when the scope ends.
@@ -212,6 +226,8 @@ impl<'p> WebhookWorker<'p> {
                     kafka_producer,
                     cdp_function_callbacks_topic,
                     hog_mode,
+                    signing_secrets,
+                    destinations,
                 )
                 .await
             });
@@ -245,9 +261,12 @@ async fn process_batch<'a>(
     kafka_producer: FutureProducer<KafkaContext>,
     cdp_function_callbacks_topic: &'static str,
     hog_mode: bool,
+    signing_secrets: Arc<SigningSecrets>,
+    destinations: Arc<Destinations>,
 ) {
     let mut futures = Vec::with_capacity(batch.jobs.len());
     let mut metadata_vec = Vec::with_capacity(batch.jobs.len());
+    let timestamp = Utc::now().timestamp();
 
     // We have to `take` the Vec of jobs from the batch to avoid a borrow checker
     // error below when we commit.
@@ -255,11 +274,38 @@ async fn process_batch<'a>(
         let http_client = http_client.clone();
         let retry_policy = retry_policy.clone();
 
+        // Sign before the metadata is taken. The job id is stable across retries, which the
+        // Standard Webhooks spec requires of `webhook-id` so receivers can deduplicate.
+        let signature_headers = signing_secrets.signature_headers(
+            &job.job().metadata,
+            &format!("msg_{}", job.job().id),
+            timestamp,
+            &job.parameters().body,
+        );
+
         metadata_vec.push(job.take_metadata());
 
+        let admission = destinations.admit(&job.job().target);
+
         let read_body = hog_mode;
-        let future =
-            async move { process_webhook_job(http_client, job, &retry_policy, read_body).await };
+        let future = async move {
+            match admission {
+                Admission::Allowed(permit) => {
+                    process_webhook_job(
+                        http_client,
+                        job,
+                        &retry_policy,
+                        read_body,
+                        signature_headers,
+                        permit,
+                    )
+                    .await
+                }
+                Admission::Rejected { reason, delay } => {
+                    reschedule_webhook_job(job, reason, delay).await
+                }
+            }
+        };
 
         futures.push(future);
     }
@@ -431,17 +477,43 @@ enum WebhookResult {
 /// * `client`: An HTTP client to execute the webhook job request.
 /// * `webhook_job`: The webhook job to process as dequeued from `hook_common::pgqueue::PgQueue`.
 /// * `retry_policy`: The retry policy used to set retry parameters if a job fails and has remaining attempts.
+/// * `signature_headers`: Standard Webhooks headers to sign the request with, if the job requested signing.
+/// * `destination_permit`: Permit to send a request to the job's destination, used to report its health.
 async fn process_webhook_job<W: WebhookJob>(
     http_client: reqwest::Client,
     webhook_job: W,
     retry_policy: &RetryPolicy,
     read_body: bool,
+    signature_headers: Result<Option<header::HeaderMap>, WebhookSigningError>,
+    destination_permit: DestinationPermit,
 ) -> Result<WebhookResult, WorkerError> {
     let parameters = webhook_job.parameters();
 
     let labels = [("queue", webhook_job.queue())];
     metrics::counter!("webhook_jobs_total", &labels).increment(1);
 
+    let signature_headers = match signature_headers {
+        Ok(signature_headers) => signature_headers,
+        Err(e) => {
+            // Retrying won't make a secret show up, and sending unsigned defeats the purpose.
+            // The error message never includes the secret or its reference.
+            webhook_job
+                .fail(WebhookJobError::new_parse(&e.to_string()))
+                .await
+                .inspect_err(|_| {
+                    metrics::counter!("webhook_jobs_database_error", &labels).increment(1)
+                })?;
+
+            metrics::counter!("webhook_jobs_signing_error", &labels).increment(1);
+            metrics::counter!("webhook_jobs_failed", &labels).increment(1);
+
+            return Ok(WebhookResult::Error(e.to_string()));
+        }
+    };
+    if signature_headers.is_some() {
+        metrics::counter!("webhook_jobs_signed", &labels).increment(1);
+    }
+
     let now = tokio::time::Instant::now();
 
     let send_result = send_webhook(
@@ -449,10 +521,16 @@ async fn process_webhook_job<W: WebhookJob>(
         &parameters.method,
         &parameters.url,
         &parameters.headers,
+        signature_headers,
         parameters.body.clone(),
     )
     .await;
 
+    match destination_outcome(&send_result) {
+        Some(outcome) => destination_permit.record(outcome).await,
+        None => drop(destination_permit),
+    }
+
     match send_result {
         Ok(response) => {
             let status = response.status();
@@ -654,6 +732,55 @@ async fn process_webhook_job<W: WebhookJob>(
     }
 }
 
+/// Put a webhook job back in the queue without attempting it, as its destination can't take
+/// requests right now. The attempt isn't counted against the job's `max_attempts`.
+///
+/// # Arguments
+///
+/// * `webhook_job`: The webhook job to reschedule as dequeued from `hook_common::pgqueue::PgQueue`.
+/// * `reason`: Why the destination can't take requests.
+/// * `delay`: How long until the job may be dequeued again.
+async fn reschedule_webhook_job<W: WebhookJob>(
+    webhook_job: W,
+    reason: RejectReason,
+    delay: time::Duration,
+) -> Result<WebhookResult, WorkerError> {
+    let labels = [("queue", webhook_job.queue())];
+    let labels_with_reason = [
+        ("queue", webhook_job.queue()),
+        ("reason", reason.as_str().to_owned()),
+    ];
+
+    webhook_job.reschedule(delay).await.inspect_err(|_| {
+        metrics::counter!("webhook_jobs_database_error", &labels).increment(1);
+    })?;
+
+    metrics::counter!("webhook_jobs_rescheduled", &labels_with_reason).increment(1);
+
+    Ok(WebhookResult::WillRetry)
+}
+
+/// Classify the result of a webhook request for destination health tracking.
+/// Returns `None` if the result says nothing about the destination's health, e.g. when the
+/// request couldn't be built or the destination resolved to a private IP.
+fn destination_outcome(
+    send_result: &Result<reqwest::Response, WebhookError>,
+) -> Option<DestinationOutcome> {
+    match send_result {
+        Ok(_) => Some(DestinationOutcome::Success),
+        // Timeouts, connection errors, 429s and 5XXs.
+        Err(WebhookError::Request(WebhookRequestError::RetryableRequestError { .. })) => {
+            Some(DestinationOutcome::Failure)
+        }
+        // Any other status means the destination is up, it just didn't like our request.
+        Err(WebhookError::Request(WebhookRequestError::NonRetryableRetryableRequestError {
+            status: Some(_),
+            ..
+        })) => Some(DestinationOutcome::Success),
+        Err(_) => None,
+    }
+}
+
 /// Make an HTTP request to a webhook endpoint.
 ///
 /// # Arguments
@@ -662,19 +789,24 @@ async fn process_webhook_job<W: WebhookJob>(
 /// * `method`: The HTTP method to use in the HTTP request.
 /// * `url`: The URL we are targetting with our request. Parsing this URL fail.
 /// * `headers`: Key, value pairs of HTTP headers in a `std::collections::HashMap`. Can fail if headers are not valid.
+/// * `signature_headers`: Optional signature headers, which take precedence over any in `headers`.
 /// * `body`: The body of the request. Ownership is required.
 async fn send_webhook(
     client: reqwest::Client,
     method: &HttpMethod,
     url: &str,
     headers: &collections::HashMap<String, String>,
+    signature_headers: Option<reqwest::header::HeaderMap>,
     body: String,
 ) -> Result<reqwest::Response, WebhookError> {
     let method: http::Method = method.into();
     let url: reqwest::Url = (url).parse().map_err(WebhookParseError::ParseUrlError)?;
-    let headers: reqwest::header::HeaderMap = (headers)
+    let mut headers: reqwest::header::HeaderMap = (headers)
         .try_into()
         .map_err(WebhookParseError::ParseHeadersError)?;
+    if let Some(signature_headers) = signature_headers {
+        headers.extend(signature_headers);
+    }
     let body = reqwest::Body::from(body);
 
     let response = client
@@ -799,6 +931,15 @@ mod tests {
         build_http_client(Duration::from_secs(1), true).expect("failed to create client")
     }
 
+    fn destination_config() -> DestinationConfig {
+        DestinationConfig {
+            max_in_flight: 10,
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            saturated_delay: Duration::from_secs(1),
+        }
+    }
+
     async fn enqueue_job(
         queue: &PgQueue,
         max_attempts: i32,
@@ -819,6 +960,54 @@ mod tests {
         assert!(is_retryable_status(http::StatusCode::INTERNAL_SERVER_ERROR));
     }
 
+    #[tokio::test]
+    async fn test_destination_outcome() {
+        use httpmock::prelude::*;
+
+        let server = MockServer::start();
+        for status in [200, 400, 429, 500] {
+            server.mock(|when, then| {
+                when.method(POST).path(format!("/{status}"));
+                then.status(status);
+            });
+        }
+
+        let mut outcomes = Vec::new();
+        for status in [200, 400, 429, 500] {
+            let send_result = send_webhook(
+                localhost_client(),
+                &HttpMethod::POST,
+                &server.url(format!("/{status}")),
+                &collections::HashMap::new(),
+                None,
+                "".to_owned(),
+            )
+            .await;
+            outcomes.push(destination_outcome(&send_result));
+        }
+        assert_eq!(
+            outcomes,
+            vec![
+                Some(DestinationOutcome::Success),
+                Some(DestinationOutcome::Success),
+                Some(DestinationOutcome::Failure),
+                Some(DestinationOutcome::Failure),
+            ]
+        );
+
+        // An invalid URL never reaches any destination.
+        let send_result = send_webhook(
+            localhost_client(),
+            &HttpMethod::POST,
+            "not a url",
+            &collections::HashMap::new(),
+            None,
+            "".to_owned(),
+        )
+        .await;
+        assert_eq!(destination_outcome(&send_result), None);
+    }
+
     #[test]
     fn test_parse_retry_after_header() {
         let mut headers = reqwest::header::HeaderMap::new();
@@ -857,6 +1046,7 @@ mod tests {
             team_id: 1,
             plugin_id: 2,
             plugin_config_id: 3,
+            signing_secret_ref: None,
         };
         let registry = HealthRegistry::new("liveness");
         let liveness = registry
@@ -889,6 +1079,8 @@ mod tests {
             "cdp_function_callbacks".to_string(),
             hog_mode,
             liveness,
+            SigningSecrets::default(),
+            destination_config(),
         );
 
         let mut batch = worker.wait_for_jobs_tx().await;
@@ -946,6 +1138,8 @@ mod tests {
             topic.to_string(),
             hog_mode,
             liveness,
+            SigningSecrets::default(),
+            destination_config(),
         );
 
         // Enqueue and run a successful job.
@@ -982,6 +1176,8 @@ mod tests {
             worker.kafka_producer.clone(),
             worker.cdp_function_callbacks_topic,
             hog_mode,
+            worker.signing_secrets.clone(),
+            worker.destinations.clone(),
         )
         .await;
 
@@ -1062,6 +1258,8 @@ mod tests {
             topic.to_string(),
             hog_mode,
             liveness,
+            SigningSecrets::default(),
+            destination_config(),
         );
 
         // Enqueue and run a job that returns a bad HTTP response.
@@ -1098,6 +1296,8 @@ mod tests {
             worker.kafka_producer.clone(),
             worker.cdp_function_callbacks_topic,
             hog_mode,
+            worker.signing_secrets.clone(),
+            worker.destinations.clone(),
         )
         .await;
 
@@ -1203,6 +1403,8 @@ mod tests {
             topic.to_string(),
             hog_mode,
             liveness,
+            SigningSecrets::default(),
+            destination_config(),
         );
 
         let batch = worker.wait_for_jobs_tx().await;
@@ -1214,6 +1416,8 @@ mod tests {
             worker.kafka_producer,
             worker.cdp_function_callbacks_topic,
             hog_mode,
+            worker.signing_secrets.clone(),
+            worker.destinations.clone(),
         )
         .await;
     }
@@ -1233,9 +1437,16 @@ mod tests {
         });
 
         let url = server.url("/echo");
-        let response = send_webhook(localhost_client(), &method, &url, &headers, body.to_owned())
-            .await
-            .expect("send_webhook failed");
+        let response = send_webhook(
+            localhost_client(),
+            &method,
+            &url,
+            &headers,
+            None,
+            body.to_owned(),
+        )
+        .await
+        .expect("send_webhook failed");
 
         assert_eq!(response.status(), StatusCode::OK);
         assert_eq!(
@@ -1244,6 +1455,56 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_send_webhook_with_signature_headers() {
+        use crate::signing::{WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER};
+        use httpmock::prelude::*;
+
+        let method = HttpMethod::POST;
+        // Signature headers must not be overridable by job parameters.
+        let headers = collections::HashMap::from([(
+            WEBHOOK_SIGNATURE_HEADER.to_owned(),
+            "v1,forged".to_owned(),
+        )]);
+        let body = "a signed request body";
+        let secrets: SigningSecrets = r#"{"destination": ["whsec_c2VjcmV0"]}"#.parse().unwrap();
+        let signature_headers = secrets
+            .signature_headers(
+                &json!({"signing_secret_ref": "destination"}),
+                "msg_1",
+                1,
+                body,
+            )
+            .unwrap();
+
+        let server = MockServer::start();
+        let mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/signed")
+                .header(WEBHOOK_ID_HEADER, "msg_1")
+                .header(
+                    WEBHOOK_SIGNATURE_HEADER,
+                    "v1,bU0FggC6CveOD+FofdkFRxuyHgN10Wb/moKJ8mJ5b3w=",
+                );
+            then.status(200);
+        });
+
+        let url = server.url("/signed");
+        let response = send_webhook(
+            localhost_client(),
+            &method,
+            &url,
+            &headers,
+            signature_headers,
+            body.to_owned(),
+        )
+        .await
+        .expect("send_webhook failed");
+
+        assert_eq!(response.status(), StatusCode::OK);
+        mock.assert();
+    }
+
     #[tokio::test]
     async fn test_error_message_contains_response_body() {
         use httpmock::prelude::*;
@@ -1259,9 +1520,16 @@ mod tests {
         });
 
         let url = server.url("/fail");
-        let err = send_webhook(localhost_client(), &method, &url, &headers, body.to_owned())
-            .await
-            .expect_err("request didn't fail when it should have failed");
+        let err = send_webhook(
+            localhost_client(),
+            &method,
+            &url,
+            &headers,
+            None,
+            body.to_owned(),
+        )
+        .await
+        .expect_err("request didn't fail when it should have failed");
 
         assert!(matches!(err, WebhookError::Request(..)));
         if let WebhookError::Request(request_error) = err {
@@ -1292,9 +1560,16 @@ mod tests {
         });
 
         let url = server.url("/fail");
-        let err = send_webhook(localhost_client(), &method, &url, &headers, body.to_owned())
-            .await
-            .expect_err("request didn't fail when it should have failed");
+        let err = send_webhook(
+            localhost_client(),
+            &method,
+            &url,
+            &headers,
+            None,
+            body.to_owned(),
+        )
+        .await
+        .expect_err("request didn't fail when it should have failed");
 
         assert!(matches!(err, WebhookError::Request(..)));
         if let WebhookError::Request(request_error) = err {
@@ -1325,9 +1600,16 @@ mod tests {
         let filtering_client =
             build_http_client(Duration::from_secs(1), false).expect("failed to create client");
 
-        let err = send_webhook(filtering_client, &method, url, &headers, body.to_owned())
-            .await
-            .expect_err("request didn't fail when it should have failed");
+        let err = send_webhook(
+            filtering_client,
+            &method,
+            url,
+            &headers,
+            None,
+            body.to_owned(),
+        )
+        .await
+        .expect_err("request didn't fail when it should have failed");
 
         assert!(matches!(err, WebhookError::Request(..)));
         if let WebhookError::Request(request_error) = err {
@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use kafka_deduplicator::checkpoint::inspect::{
+    diff_checkpoints, fetch_checkpoint, replay_dump, LocalCheckpoint,
+};
+use kafka_deduplicator::checkpoint::{
+    CheckpointDownloader, CheckpointImporter, CheckpointMetadata, CheckpointStorageBackend,
+    FilesystemCheckpointStorage, S3Downloader,
+};
+use kafka_deduplicator::config::Config;
+use tempfile::TempDir;
+use tracing_subscriber::EnvFilter;
+
+const USAGE: &str = "Usage:
+  checkpoint_inspector fetch <topic> <partition> <out_dir> [checkpoint_id]
+  checkpoint_inspector info <checkpoint>
+  checkpoint_inspector dump <checkpoint> [limit]
+  checkpoint_inspector diff <left_checkpoint> <right_checkpoint> [max_samples]
+  checkpoint_inspector replay <checkpoint> <dump_file> [batch_size]
+
+<checkpoint> is a local store directory, or remote:<topic>:<partition>[:<checkpoint_id>]
+to download the latest (or given) checkpoint attempt into a temp dir first.";
+
+const DEFAULT_DIFF_SAMPLES: usize = 100;
+const DEFAULT_REPLAY_BATCH_SIZE: usize = 1000;
+
+// Invoked like (when inside the kafka-deduplicator folder):
+//   cargo run --bin checkpoint_inspector -- info remote:events:12
+// Remote checkpoints are read with the same env config as the service
+// (CHECKPOINT_STORAGE_BACKEND, S3_BUCKET, AWS_REGION, CHECKPOINT_FILESYSTEM_DIR, ...), and
+// PIPELINE_TYPE selects how store values and replayed events are decoded. Results are written to stdout as JSON; logs go to stderr.
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(EnvFilter::from_default_env())
+        .init();
+
+    // nosemgrep: rust.lang.security.args.args
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let config = Config::init_with_defaults().context("Failed to load configuration")?;
+    // Remote checkpoints are downloaded here and removed on exit
+    let scratch = TempDir::new().context("Failed to create temp directory")?;
+
+    match args.as_slice() {
+        ["fetch", topic, partition, out_dir, rest @ ..] => {
+            let importer = build_importer(&config, Path::new(out_dir)).await?;
+            let (metadata, path) = fetch_checkpoint(
+                &importer,
+                topic,
+                parse_arg(partition, "partition")?,
+                rest.first().copied(),
+                Path::new(out_dir),
+            )
+            .await?;
+            println!("{}", metadata.to_json()?);
+            eprintln!("Checkpoint stored at {}", path.display());
+        }
+        ["info", checkpoint] => {
+            let path = resolve_checkpoint(&config, checkpoint, scratch.path()).await?;
+            let checkpoint = LocalCheckpoint::open(&path).await?;
+            let info = serde_json::json!({
+                "path": checkpoint.path,
+                "metadata": checkpoint.metadata,
+                "summary": checkpoint.summarize()?,
+            });
+            println!("{}", serde_json::to_string_pretty(&info)?);
+        }
+        ["dump", checkpoint, rest @ ..] => {
+            let limit = match rest.first() {
+                Some(limit) => parse_arg(limit, "limit")?,
+                None => usize::MAX,
+            };
+            let path = resolve_checkpoint(&config, checkpoint, scratch.path()).await?;
+            let checkpoint = LocalCheckpoint::open(&path).await?;
+            for entry in checkpoint.entries(config.pipeline_type)?.take(limit) {
+                println!("{}", serde_json::to_string(&entry?)?);
+            }
+        }
+        ["diff", left, right, rest @ ..] => {
+            let max_samples = match rest.first() {
+                Some(samples) => parse_arg(samples, "max_samples")?,
+                None => DEFAULT_DIFF_SAMPLES,
+            };
+            let left_path = resolve_checkpoint(&config, left, &scratch.path().join("left")).await?;
+            let right_path =
+                resolve_checkpoint(&config, right, &scratch.path().join("right")).await?;
+            let left = LocalCheckpoint::open(&left_path).await?;
+            let right = LocalCheckpoint::open(&right_path).await?;
+            let diff = diff_checkpoints(&left, &right, max_samples)?;
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        }
+        ["replay", checkpoint, dump_file, rest @ ..] => {
+            let batch_size = match rest.first() {
+                Some(size) => parse_arg(size, "batch_size")?,
+                None => DEFAULT_REPLAY_BATCH_SIZE,
+            };
+            let path = resolve_checkpoint(&config, checkpoint, scratch.path()).await?;
+            // Replay under the checkpoint's own coordinates when its metadata is available
+            let (topic, partition) = match CheckpointMetadata::load_from_dir(&path).await {
+                Ok(metadata) => (metadata.topic, metadata.partition),
+                Err(_) => (config.kafka_consumer_topic.clone(), 0),
+            };
+            let report = replay_dump(
+                &path,
+                &topic,
+                partition,
+                config.pipeline_type,
+                Path::new(dump_file),
+                batch_size,
+            )
+            .await?;
+            for outcome in &report.outcomes {
+                println!("{}", serde_json::to_string(outcome)?);
+            }
+            println!("{}", serde_json::json!({ "totals": report.totals }));
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_arg<T: std::str::FromStr>(value: &str, name: &str) -> Result<T> {
+    value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid {name}: {value}"))
+}
+
+async fn build_importer(config: &Config, store_base_path: &Path) -> Result<CheckpointImporter> {
+    let checkpoint_config = config.build_checkpoint_config();
+    let downloader: Box<dyn CheckpointDownloader> = match checkpoint_config.storage_backend {
+        CheckpointStorageBackend::S3 => Box::new(
+            S3Downloader::new(&checkpoint_config)
+                .await
+                .context("S3 downloader: client initialization failed")?,
+        ),
+        CheckpointStorageBackend::Filesystem => {
+            Box::new(FilesystemCheckpointStorage::new(&checkpoint_config)?)
+        }
+    };
+    Ok(CheckpointImporter::new(
+        downloader,
+        store_base_path.to_path_buf(),
+        checkpoint_config.checkpoint_import_attempt_depth,
+        checkpoint_config.checkpoint_partition_import_timeout,
+    ))
+}
+
+/// Resolve a checkpoint argument to a local store directory, downloading
+/// `remote:<topic>:<partition>[:<checkpoint_id>]` specs under `scratch_dir`
+async fn resolve_checkpoint(config: &Config, spec: &str, scratch_dir: &Path) -> Result<PathBuf> {
+    let Some(remote) = spec.strip_prefix("remote:") else {
+        return Ok(PathBuf::from(spec));
+    };
+
+    let parts: Vec<&str> = remote.split(':').collect();
+    let (topic, partition, checkpoint_id) = match parts.as_slice() {
+        [topic, partition] => (*topic, *partition, None),
+        [topic, partition, checkpoint_id] => (*topic, *partition, Some(*checkpoint_id)),
+        _ => anyhow::bail!("Invalid remote checkpoint spec: {spec}"),
+    };
+
+    let importer = build_importer(config, scratch_dir).await?;
+    let (_, path) = fetch_checkpoint(
+        &importer,
+        topic,
+        parse_arg(partition, "partition")?,
+        checkpoint_id,
+        scratch_dir,
+    )
+    .await?;
+    Ok(path)
+}
@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
+/// Remote storage backend that checkpoint attempts are exported to and imported from
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, strum_macros::EnumString,
+)]
+#[strum(serialize_all = "snake_case")]
+pub enum CheckpointStorageBackend {
+    #[default]
+    S3,
+    /// Local or NFS-mounted directory using the same key layout as S3
+    Filesystem,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CheckpointConfig {
     /// How often to trigger a checkpoint attempt for all locally-hosted partition stores
@@ -13,6 +25,17 @@ pub struct CheckpointConfig {
     /// Base directory for local checkpoints
     pub local_checkpoint_dir: String,
 
+    /// Remote storage backend for checkpoint uploads and imports
+    pub storage_backend: CheckpointStorageBackend,
+
+    /// Root directory for the filesystem storage backend (local disk or NFS mount)
+    pub filesystem_storage_dir: String,
+
+    /// Number of most recent checkpoint attempts per partition kept by the
+    /// filesystem storage backend. Older attempts are pruned after each upload.
+    /// If 0, nothing is pruned
+    pub filesystem_retained_checkpoints: usize,
+
     /// S3 bucket for checkpoint uploads
     pub s3_bucket: String,
 
@@ -90,6 +113,9 @@ impl Default for CheckpointConfig {
             checkpoint_interval: Duration::from_secs(300),
             checkpoint_full_upload_interval: 10, // create a full checkpoint every 10 attempts per partition
             local_checkpoint_dir: "./checkpoints".to_string(),
+            storage_backend: CheckpointStorageBackend::S3,
+            filesystem_storage_dir: "".to_string(),
+            filesystem_retained_checkpoints: 10,
             s3_bucket: "".to_string(),
             s3_key_prefix: "deduplication-checkpoints".to_string(),
             aws_region: None,
@@ -0,0 +1,838 @@
+//! Filesystem checkpoint storage, implementing both [`CheckpointUploader`] and
+//! [`CheckpointDownloader`] against a local or NFS-mounted directory.
+//!
+//! Remote keys map 1:1 onto paths below the root directory, so the layout matches the
+//! S3 backend exactly (including hashed object file prefixes). Every file is written to
+//! an in-progress temp name and renamed into place, and an attempt's metadata.json is
+//! only renamed into place after all of its files landed. Importers list metadata.json
+//! files, so a partially uploaded attempt is never visible to them.
+
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use futures::{stream, StreamExt};
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use super::config::CheckpointConfig;
+use super::downloader::CheckpointDownloader;
+use super::error::{DownloadCancelledError, UploadCancelledError};
+use super::metadata::{
+    hash_prefix_for_partition, join_key_prefix, CheckpointMetadata, DATE_PLUS_HOURS_ONLY_FORMAT,
+    METADATA_FILENAME,
+};
+use super::uploader::CheckpointUploader;
+use super::CheckpointPlan;
+use crate::metrics_const::{
+    CHECKPOINT_BATCH_FETCH_STORE_HISTOGRAM, CHECKPOINT_FILE_DOWNLOADS_COUNTER,
+    CHECKPOINT_FILE_UPLOADS_COUNTER, CHECKPOINT_LIST_METADATA_HISTOGRAM,
+};
+
+/// Suffix for files still being written. Never matches a RocksDB file or metadata.json
+const IN_PROGRESS_SUFFIX: &str = ".inprogress";
+
+/// Outcome of a cancellable file copy
+enum CopyOutcome {
+    Copied(u64),
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct FilesystemCheckpointStorage {
+    root: PathBuf,
+    key_prefix: String,
+    checkpoint_import_window_hours: u32,
+    retained_checkpoints: usize,
+    max_concurrent_uploads: usize,
+    max_concurrent_downloads: usize,
+}
+
+impl FilesystemCheckpointStorage {
+    pub fn new(config: &CheckpointConfig) -> Result<Self> {
+        if config.filesystem_storage_dir.is_empty() {
+            anyhow::bail!("Filesystem checkpoint storage requires a root directory");
+        }
+        let root = PathBuf::from(&config.filesystem_storage_dir);
+        std::fs::create_dir_all(&root).with_context(|| {
+            format!(
+                "Failed to create checkpoint storage directory: {}",
+                root.display()
+            )
+        })?;
+
+        info!(
+            "Filesystem checkpoint storage initialized at '{}' retaining {} checkpoints per partition",
+            root.display(),
+            config.filesystem_retained_checkpoints
+        );
+
+        Ok(Self {
+            root,
+            key_prefix: config.s3_key_prefix.clone(),
+            checkpoint_import_window_hours: config.checkpoint_import_window_hours,
+            retained_checkpoints: config.filesystem_retained_checkpoints,
+            max_concurrent_uploads: config.max_concurrent_checkpoint_file_uploads.max(1),
+            max_concurrent_downloads: config.max_concurrent_checkpoint_file_downloads.max(1),
+        })
+    }
+
+    /// Resolve a remote key to a path below the storage root, rejecting keys
+    /// that could escape it. Keys start with '/' when the key prefix is empty,
+    /// which still resolves below the root.
+    fn key_path(&self, remote_key: &str) -> Result<PathBuf> {
+        let relative = Path::new(remote_key.trim_start_matches('/'));
+        if !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            anyhow::bail!("Invalid checkpoint storage key: {remote_key}");
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Key of the directory holding the attempt directories of a topic/partition
+    fn partition_key(&self, topic: &str, partition_number: i32) -> String {
+        join_key_prefix(&self.key_prefix, &format!("{topic}/{partition_number}"))
+    }
+
+    /// Checkpoint IDs of all completed attempts (those with a metadata.json)
+    /// for a topic/partition, sorted newest to oldest
+    async fn list_completed_attempts(
+        &self,
+        topic: &str,
+        partition_number: i32,
+    ) -> Result<Vec<String>> {
+        let partition_key = self.partition_key(topic, partition_number);
+        let ids = self.list_attempt_dirs(&partition_key).await?;
+        let mut completed = Vec::with_capacity(ids.len());
+        for id in ids {
+            let metadata_key = format!("{partition_key}/{id}/{METADATA_FILENAME}");
+            if tokio::fs::try_exists(self.key_path(&metadata_key)?).await? {
+                completed.push(id);
+            }
+        }
+        Ok(completed)
+    }
+
+    /// Names of the attempt directories directly below `partition_key`, sorted newest to oldest.
+    /// Checkpoint IDs are timestamp-formatted, so lexicographic order is chronological
+    async fn list_attempt_dirs(&self, partition_key: &str) -> Result<Vec<String>> {
+        let dir = self.key_path(partition_key)?;
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e).with_context(|| format!("Failed to list directory: {dir:?}")),
+        };
+
+        let mut ids = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                ids.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        Ok(ids)
+    }
+
+    async fn load_metadata(&self, metadata_key: &str) -> Result<CheckpointMetadata> {
+        let bytes = tokio::fs::read(self.key_path(metadata_key)?)
+            .await
+            .with_context(|| format!("Failed to read checkpoint metadata: {metadata_key}"))?;
+        CheckpointMetadata::from_json_bytes(&bytes)
+    }
+
+    /// Remove attempts beyond the newest `retained_checkpoints` for a topic/partition.
+    ///
+    /// Incremental attempts reference files uploaded by earlier attempts, so only files
+    /// no retained attempt references are deleted. Expired metadata.json files are removed
+    /// first so importers stop seeing those attempts before any of their files disappear.
+    /// Leftovers of incomplete attempts older than the oldest retained one are cleaned up
+    /// the same way. Returns the number of completed attempts pruned.
+    pub async fn prune_partition(&self, topic: &str, partition_number: i32) -> Result<usize> {
+        if self.retained_checkpoints == 0 {
+            return Ok(0);
+        }
+
+        let completed = self
+            .list_completed_attempts(topic, partition_number)
+            .await?;
+        if completed.len() <= self.retained_checkpoints {
+            return Ok(0);
+        }
+        let (retained, expired) = completed.split_at(self.retained_checkpoints);
+        let oldest_retained = retained.last().expect("retained_checkpoints > 0");
+
+        let partition_key = self.partition_key(topic, partition_number);
+        let mut referenced: HashSet<String> = HashSet::new();
+        for id in retained {
+            let metadata = self
+                .load_metadata(&format!("{partition_key}/{id}/{METADATA_FILENAME}"))
+                .await?;
+            referenced.extend(metadata.files.into_iter().map(|f| f.remote_filepath));
+        }
+
+        for id in expired {
+            let metadata_key = format!("{partition_key}/{id}/{METADATA_FILENAME}");
+            remove_file_if_exists(&self.key_path(&metadata_key)?).await?;
+        }
+
+        // Object files live under the hashed prefix, metadata.json under the unhashed one;
+        // an attempt that never completed may only exist in the hashed tree
+        let hashed_partition_key = format!(
+            "{}/{partition_key}",
+            hash_prefix_for_partition(topic, partition_number)
+        );
+        for base in [&partition_key, &hashed_partition_key] {
+            for id in self.list_attempt_dirs(base).await? {
+                if id.as_str() < oldest_retained.as_str() {
+                    self.remove_unreferenced_files(&format!("{base}/{id}"), &referenced)
+                        .await?;
+                }
+            }
+        }
+
+        info!(
+            topic,
+            partition = partition_number,
+            pruned = expired.len(),
+            retained = retained.len(),
+            "Pruned expired checkpoint attempts from filesystem storage"
+        );
+
+        Ok(expired.len())
+    }
+
+    /// Delete files in an attempt directory that aren't in `referenced`,
+    /// then the directory itself if nothing is left in it
+    async fn remove_unreferenced_files(
+        &self,
+        attempt_key: &str,
+        referenced: &HashSet<String>,
+    ) -> Result<()> {
+        let dir = self.key_path(attempt_key)?;
+        let mut entries = tokio::fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("Failed to list directory: {dir:?}"))?;
+        while let Some(entry) = entries.next_entry().await? {
+            let key = format!("{attempt_key}/{}", entry.file_name().to_string_lossy());
+            if !referenced.contains(&key) && entry.file_type().await?.is_file() {
+                remove_file_if_exists(&entry.path()).await?;
+            }
+        }
+        // Fails while referenced files remain, which is expected
+        let _ = tokio::fs::remove_dir(&dir).await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CheckpointUploader for FilesystemCheckpointStorage {
+    async fn upload_checkpoint_with_plan_cancellable(
+        &self,
+        plan: &CheckpointPlan,
+        cancel_token: Option<&CancellationToken>,
+    ) -> Result<Vec<String>> {
+        if cancel_token.is_some_and(|t| t.is_cancelled()) {
+            warn!("Upload cancelled before starting batch");
+            return Err(UploadCancelledError {
+                reason: "before starting batch".to_string(),
+            }
+            .into());
+        }
+
+        // Child token cancels sibling copies on first error without cancelling the caller
+        let upload_token = cancel_token
+            .map(|parent| parent.child_token())
+            .unwrap_or_default();
+
+        let mut copies = stream::iter(plan.files_to_upload.iter().map(|local_file| {
+            let dest_key = plan.info.get_file_key(&local_file.filename);
+            let token = upload_token.clone();
+            async move {
+                let dest = self.key_path(&dest_key)?;
+                match copy_file_cancellable(&local_file.local_path, &dest, Some(&token)).await {
+                    Ok(CopyOutcome::Copied(_)) => {
+                        metrics::counter!(CHECKPOINT_FILE_UPLOADS_COUNTER, "status" => "success")
+                            .increment(1);
+                        Ok(dest_key)
+                    }
+                    Ok(CopyOutcome::Cancelled) => {
+                        metrics::counter!(CHECKPOINT_FILE_UPLOADS_COUNTER, "status" => "cancelled")
+                            .increment(1);
+                        Err(UploadCancelledError {
+                            reason: format!("mid-copy: {dest_key}"),
+                        }
+                        .into())
+                    }
+                    Err(e) => {
+                        metrics::counter!(CHECKPOINT_FILE_UPLOADS_COUNTER, "status" => "error")
+                            .increment(1);
+                        Err(e.context(format!("Failed to store checkpoint file: {dest_key}")))
+                    }
+                }
+            }
+        }))
+        .buffer_unordered(self.max_concurrent_uploads);
+
+        let mut uploaded_keys = Vec::with_capacity(plan.files_to_upload.len());
+        let mut first_error: Option<anyhow::Error> = None;
+        while let Some(result) = copies.next().await {
+            match result {
+                Ok(key) => uploaded_keys.push(key),
+                Err(e) => {
+                    first_error = Some(e);
+                    upload_token.cancel();
+                    break;
+                }
+            }
+        }
+        // Drain remaining copies - they exit quickly due to cancellation
+        while copies.next().await.is_some() {}
+
+        // DO NOT promote the attempt unless every file landed
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+        if cancel_token.is_some_and(|t| t.is_cancelled()) {
+            warn!("Upload cancelled before metadata upload");
+            return Err(UploadCancelledError {
+                reason: "before metadata upload".to_string(),
+            }
+            .into());
+        }
+
+        // Promote the attempt: metadata.json appears atomically via rename
+        let metadata_key = plan.info.get_metadata_key();
+        let metadata_json = plan.info.metadata.to_json()?;
+        write_file_atomic(&self.key_path(&metadata_key)?, metadata_json.as_bytes())
+            .await
+            .with_context(|| format!("Failed to store checkpoint metadata: {metadata_key}"))?;
+
+        info!(
+            "Stored {} files and metadata file under {}/{}",
+            plan.files_to_upload.len(),
+            self.root.display(),
+            plan.info.get_remote_attempt_path(),
+        );
+
+        // Retention failures leave extra attempts behind but never fail the upload
+        let metadata = &plan.info.metadata;
+        if let Err(e) = self
+            .prune_partition(&metadata.topic, metadata.partition)
+            .await
+        {
+            error!(
+                topic = metadata.topic,
+                partition = metadata.partition,
+                error = ?e,
+                "Failed to prune old checkpoint attempts"
+            );
+        }
+
+        uploaded_keys.push(metadata_key);
+        Ok(uploaded_keys)
+    }
+
+    async fn is_available(&self) -> bool {
+        tokio::fs::metadata(&self.root)
+            .await
+            .is_ok_and(|m| m.is_dir())
+    }
+}
+
+#[async_trait]
+impl CheckpointDownloader for FilesystemCheckpointStorage {
+    async fn list_recent_checkpoints(
+        &self,
+        topic: &str,
+        partition_number: i32,
+    ) -> Result<Vec<String>> {
+        let start_time = Instant::now();
+        let cutoff = (Utc::now() - Duration::hours(self.checkpoint_import_window_hours as i64))
+            .format(DATE_PLUS_HOURS_ONLY_FORMAT)
+            .to_string();
+        let partition_key = self.partition_key(topic, partition_number);
+
+        // Same window semantics as the S3 listing's lexicographic start_after bound
+        let keys: Vec<String> = self
+            .list_completed_attempts(topic, partition_number)
+            .await?
+            .into_iter()
+            .filter(|id| id.as_str() >= cutoff.as_str())
+            .map(|id| format!("{partition_key}/{id}/{METADATA_FILENAME}"))
+            .collect();
+
+        metrics::histogram!(CHECKPOINT_LIST_METADATA_HISTOGRAM)
+            .record(start_time.elapsed().as_secs_f64());
+        info!(
+            "Found {} metadata.json files under {}/{partition_key} at or after: {cutoff}",
+            keys.len(),
+            self.root.display(),
+        );
+
+        Ok(keys)
+    }
+
+    async fn download_file(&self, remote_key: &str) -> Result<Vec<u8>> {
+        let path = self.key_path(remote_key)?;
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => {
+                metrics::counter!(CHECKPOINT_FILE_DOWNLOADS_COUNTER, "status" => "success")
+                    .increment(1);
+                Ok(bytes)
+            }
+            Err(e) => {
+                metrics::counter!(CHECKPOINT_FILE_DOWNLOADS_COUNTER, "status" => "error")
+                    .increment(1);
+                Err(e).with_context(|| format!("Failed to read checkpoint file: {path:?}"))
+            }
+        }
+    }
+
+    async fn download_and_store_file_cancellable(
+        &self,
+        remote_key: &str,
+        local_filepath: &Path,
+        cancel_token: Option<&CancellationToken>,
+    ) -> Result<()> {
+        if cancel_token.is_some_and(|t| t.is_cancelled()) {
+            warn!("Download cancelled before starting: {remote_key}");
+            return Err(DownloadCancelledError {
+                reason: format!("before starting: {remote_key}"),
+            }
+            .into());
+        }
+
+        let src = self.key_path(remote_key)?;
+        match copy_file_cancellable(&src, local_filepath, cancel_token).await {
+            Ok(CopyOutcome::Copied(bytes)) => {
+                metrics::counter!(CHECKPOINT_FILE_DOWNLOADS_COUNTER, "status" => "success")
+                    .increment(1);
+                info!("Copied checkpoint file {remote_key} ({bytes} bytes) to {local_filepath:?}");
+                Ok(())
+            }
+            Ok(CopyOutcome::Cancelled) => {
+                metrics::counter!(CHECKPOINT_FILE_DOWNLOADS_COUNTER, "status" => "cancelled")
+                    .increment(1);
+                warn!("Download of {remote_key} cancelled mid-copy");
+                Err(DownloadCancelledError {
+                    reason: format!("mid-copy: {remote_key}"),
+                }
+                .into())
+            }
+            Err(e) => {
+                metrics::counter!(CHECKPOINT_FILE_DOWNLOADS_COUNTER, "status" => "error")
+                    .increment(1);
+                Err(e.context(format!("Failed to copy checkpoint file: {remote_key}")))
+            }
+        }
+    }
+
+    async fn download_files_cancellable(
+        &self,
+        remote_keys: &[String],
+        local_base_path: &Path,
+        cancel_token: Option<&CancellationToken>,
+    ) -> Result<()> {
+        let start_time = Instant::now();
+        if cancel_token.is_some_and(|t| t.is_cancelled()) {
+            warn!("Download cancelled before starting");
+            return Err(DownloadCancelledError {
+                reason: "before starting batch".to_string(),
+            }
+            .into());
+        }
+
+        let mut copies = stream::iter(remote_keys.iter().map(|remote_key| {
+            let filename = remote_key.rsplit('/').next().unwrap_or(remote_key);
+            let local_filepath = local_base_path.join(filename);
+            async move {
+                self.download_and_store_file_cancellable(remote_key, &local_filepath, cancel_token)
+                    .await
+            }
+        }))
+        .buffer_unordered(self.max_concurrent_downloads);
+
+        let mut first_error: Option<anyhow::Error> = None;
+        while let Some(result) = copies.next().await {
+            if let Err(e) = result {
+                first_error = Some(e);
+                // Cancel siblings via the attempt token
+                if let Some(token) = cancel_token {
+                    token.cancel();
+                }
+                break;
+            }
+        }
+        if first_error.is_some() {
+            while copies.next().await.is_some() {}
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+
+        metrics::histogram!(CHECKPOINT_BATCH_FETCH_STORE_HISTOGRAM)
+            .record(start_time.elapsed().as_secs_f64());
+        info!(
+            "Successfully copied checkpoint with {} files to local path: {local_base_path:?}",
+            remote_keys.len()
+        );
+        Ok(())
+    }
+
+    async fn is_available(&self) -> bool {
+        tokio::fs::metadata(&self.root)
+            .await
+            .is_ok_and(|m| m.is_dir())
+    }
+}
+
+/// In-progress path for `dest` in the same directory, so the final rename is atomic
+fn in_progress_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}{IN_PROGRESS_SUFFIX}", uuid::Uuid::new_v4()));
+    dest.with_file_name(name)
+}
+
+/// Copy `src` to `dest` via an in-progress file renamed into place on completion.
+/// On cancellation or error the in-progress file is removed and `dest` is untouched.
+async fn copy_file_cancellable(
+    src: &Path,
+    dest: &Path,
+    cancel_token: Option<&CancellationToken>,
+) -> Result<CopyOutcome> {
+    if let Some(parent) = dest.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory: {parent:?}"))?;
+    }
+    let tmp = in_progress_path(dest);
+
+    let result = async {
+        let mut reader = tokio::fs::File::open(src)
+            .await
+            .with_context(|| format!("Failed to open file: {src:?}"))?;
+        let mut writer = tokio::fs::File::create(&tmp)
+            .await
+            .with_context(|| format!("Failed to create file: {tmp:?}"))?;
+
+        let copied = match cancel_token {
+            Some(token) => tokio::select! {
+                biased;
+
+                _ = token.cancelled() => return Ok(CopyOutcome::Cancelled),
+
+                result = tokio::io::copy(&mut reader, &mut writer) => result,
+            },
+            None => tokio::io::copy(&mut reader, &mut writer).await,
+        }
+        .with_context(|| format!("Failed to copy {src:?} to {tmp:?}"))?;
+
+        writer.flush().await?;
+        writer
+            .sync_all()
+            .await
+            .with_context(|| format!("Failed to sync file: {tmp:?}"))?;
+        tokio::fs::rename(&tmp, dest)
+            .await
+            .with_context(|| format!("Failed to rename {tmp:?} to {dest:?}"))?;
+        Ok(CopyOutcome::Copied(copied))
+    }
+    .await;
+
+    if !matches!(result, Ok(CopyOutcome::Copied(_))) {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+/// Write `data` to `path` via an in-progress file renamed into place
+async fn write_file_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .with_context(|| format!("Failed to create directory: {parent:?}"))?;
+    }
+    let tmp = in_progress_path(path);
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&tmp).await?;
+        file.write_all(data).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    result.with_context(|| format!("Failed to write file: {path:?}"))
+}
+
+async fn remove_file_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to remove file: {path:?}"))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::{plan_checkpoint, CheckpointImporter, CheckpointStorageBackend};
+    use crate::kafka::types::Partition;
+    use chrono::{DateTime, TimeDelta};
+    use tempfile::TempDir;
+
+    const TOPIC: &str = "events";
+    const PARTITION: i32 = 3;
+
+    fn test_storage(root: &Path, retained: usize) -> FilesystemCheckpointStorage {
+        FilesystemCheckpointStorage::new(&CheckpointConfig {
+            storage_backend: CheckpointStorageBackend::Filesystem,
+            filesystem_storage_dir: root.to_string_lossy().to_string(),
+            filesystem_retained_checkpoints: retained,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// Plan a checkpoint attempt from a local directory holding the given files
+    fn plan_attempt(
+        local_dir: &Path,
+        files: &[(&str, &str)],
+        attempt_timestamp: DateTime<Utc>,
+        previous: Option<&CheckpointMetadata>,
+    ) -> CheckpointPlan {
+        std::fs::create_dir_all(local_dir).unwrap();
+        for (name, content) in files {
+            std::fs::write(local_dir.join(name), content).unwrap();
+        }
+        plan_checkpoint(
+            local_dir,
+            CheckpointConfig::default().s3_key_prefix,
+            Partition::new(TOPIC.to_string(), PARTITION),
+            attempt_timestamp,
+            1,
+            100,
+            100,
+            previous,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upload_then_import_roundtrip() {
+        let root = TempDir::new().unwrap();
+        let local = TempDir::new().unwrap();
+        let storage = test_storage(root.path(), 10);
+
+        let plan = plan_attempt(
+            &local.path().join("attempt"),
+            &[("000001.sst", "sst data"), ("CURRENT", "MANIFEST-000001")],
+            Utc::now(),
+            None,
+        );
+        let keys = storage.upload_checkpoint_with_plan(&plan).await.unwrap();
+        assert_eq!(keys.len(), 3);
+        assert!(keys.last().unwrap().ends_with(METADATA_FILENAME));
+
+        let listed = storage
+            .list_recent_checkpoints(TOPIC, PARTITION)
+            .await
+            .unwrap();
+        assert_eq!(listed, vec![plan.info.get_metadata_key()]);
+
+        let store_dir = TempDir::new().unwrap();
+        let importer = CheckpointImporter::new(
+            Box::new(storage.clone()),
+            store_dir.path().to_path_buf(),
+            3,
+            std::time::Duration::from_secs(30),
+        );
+        let imported = importer
+            .import_checkpoint_for_topic_partition(TOPIC, PARTITION)
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(imported.join("000001.sst")).unwrap(),
+            "sst data"
+        );
+        assert_eq!(
+            std::fs::read_to_string(imported.join("CURRENT")).unwrap(),
+            "MANIFEST-000001"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_with_empty_key_prefix() {
+        let root = TempDir::new().unwrap();
+        let local = TempDir::new().unwrap();
+        let storage = FilesystemCheckpointStorage::new(&CheckpointConfig {
+            storage_backend: CheckpointStorageBackend::Filesystem,
+            filesystem_storage_dir: root.path().to_string_lossy().to_string(),
+            s3_key_prefix: String::new(),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let local_dir = local.path().join("attempt");
+        std::fs::create_dir_all(&local_dir).unwrap();
+        std::fs::write(local_dir.join("000001.sst"), "sst data").unwrap();
+        let plan = plan_checkpoint(
+            &local_dir,
+            String::new(),
+            Partition::new(TOPIC.to_string(), PARTITION),
+            Utc::now(),
+            1,
+            100,
+            100,
+            None,
+        )
+        .unwrap();
+        storage.upload_checkpoint_with_plan(&plan).await.unwrap();
+
+        // Keys keep their leading '/', but the files land below the root
+        let metadata_key = plan.info.get_metadata_key();
+        assert!(metadata_key.starts_with('/'));
+        assert!(root.path().join(&metadata_key[1..]).exists());
+        assert_eq!(
+            storage
+                .list_recent_checkpoints(TOPIC, PARTITION)
+                .await
+                .unwrap(),
+            vec![metadata_key]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_upload_is_not_promoted() {
+        let root = TempDir::new().unwrap();
+        let local = TempDir::new().unwrap();
+        let storage = test_storage(root.path(), 10);
+        let plan = plan_attempt(
+            &local.path().join("attempt"),
+            &[("000001.sst", "sst data")],
+            Utc::now(),
+            None,
+        );
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let err = storage
+            .upload_checkpoint_with_plan_cancellable(&plan, Some(&token))
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<UploadCancelledError>().is_some());
+
+        assert!(storage
+            .list_recent_checkpoints(TOPIC, PARTITION)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_download_leaves_no_file() {
+        let root = TempDir::new().unwrap();
+        let local = TempDir::new().unwrap();
+        let storage = test_storage(root.path(), 10);
+        let plan = plan_attempt(
+            &local.path().join("attempt"),
+            &[("000001.sst", "sst data")],
+            Utc::now(),
+            None,
+        );
+        storage.upload_checkpoint_with_plan(&plan).await.unwrap();
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let dest = local.path().join("download").join("000001.sst");
+        let err = storage
+            .download_and_store_file_cancellable(
+                &plan.info.get_file_key("000001.sst"),
+                &dest,
+                Some(&token),
+            )
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<DownloadCancelledError>().is_some());
+        assert!(!dest.exists());
+    }
+
+    #[tokio::test]
+    async fn test_retention_keeps_files_referenced_by_retained_attempts() {
+        let root = TempDir::new().unwrap();
+        let local = TempDir::new().unwrap();
+        let storage = test_storage(root.path(), 2);
+        let start = Utc::now() - TimeDelta::hours(1);
+
+        // First attempt uploads the shared SST; later attempts reference it
+        let first = plan_attempt(
+            &local.path().join("a1"),
+            &[("000001.sst", "shared"), ("OPTIONS", "v1")],
+            start,
+            None,
+        );
+        storage.upload_checkpoint_with_plan(&first).await.unwrap();
+        let mut previous = first.info.metadata.clone();
+        let mut plans = vec![first];
+
+        for i in 1..4 {
+            let plan = plan_attempt(
+                &local.path().join(format!("a{}", i + 1)),
+                &[
+                    ("000001.sst", "shared"),
+                    ("OPTIONS", &format!("v{}", i + 1)),
+                ],
+                start + TimeDelta::minutes(i),
+                Some(&previous),
+            );
+            storage.upload_checkpoint_with_plan(&plan).await.unwrap();
+            previous = plan.info.metadata.clone();
+            plans.push(plan);
+        }
+
+        let listed = storage
+            .list_recent_checkpoints(TOPIC, PARTITION)
+            .await
+            .unwrap();
+        assert_eq!(
+            listed,
+            vec![
+                plans[3].info.get_metadata_key(),
+                plans[2].info.get_metadata_key(),
+            ]
+        );
+
+        // The shared SST from the first attempt survives, its OPTIONS file doesn't
+        let shared_key = plans[0].info.get_file_key("000001.sst");
+        assert!(plans[3]
+            .info
+            .metadata
+            .files
+            .iter()
+            .any(|f| f.remote_filepath == shared_key));
+        assert!(storage.key_path(&shared_key).unwrap().exists());
+        assert!(!storage
+            .key_path(&plans[0].info.get_file_key("OPTIONS"))
+            .unwrap()
+            .exists());
+    }
+
+    #[test]
+    fn test_key_path_rejects_escaping_keys() {
+        let root = TempDir::new().unwrap();
+        let storage = test_storage(root.path(), 1);
+        assert!(storage.key_path("../outside").is_err());
+        assert!(storage.key_path("/etc/passwd").is_err());
+        assert!(storage.key_path("prefix/events/0/id/metadata.json").is_ok());
+    }
+}
@@ -0,0 +1,688 @@
+//! Offline inspection of deduplication checkpoints.
+//!
+//! These helpers back the `checkpoint_inspector` binary. They fetch a remote checkpoint
+//! attempt into a local directory, open a copy of it as a [`DeduplicationStore`] and
+//! summarize, dump, diff or replay events against its contents without running the service.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use common_types::{CapturedEvent, ClickHouseEvent, RawEvent};
+use serde::Serialize;
+use tempfile::TempDir;
+use tracing::info;
+
+use super::{CheckpointImporter, CheckpointMetadata, METADATA_FILENAME};
+use crate::config::PipelineType;
+use crate::kafka::batch_message::KafkaMessage;
+use crate::kafka::types::Partition;
+use crate::pipelines::clickhouse_events::{ClickHouseEventMetadata, ClickHouseEventParser};
+use crate::pipelines::ingestion_events::{IngestionEventParser, TimestampMetadata};
+use crate::pipelines::traits::{DeduplicationMetadata, EventParser};
+use crate::pipelines::{
+    get_result_labels, DeduplicatableEvent, DeduplicationResult, TimestampDeduplicator,
+    TimestampDeduplicatorConfig,
+};
+use crate::rebalance_tracker::RebalanceTracker;
+use crate::store::{DeduplicationStore, DeduplicationStoreConfig, TimestampKey};
+use crate::store_manager::StoreManager;
+
+/// Topic and partition used to open a local store that has no metadata.json
+const UNKNOWN_TOPIC: &str = "unknown";
+const UNKNOWN_PARTITION: i32 = -1;
+
+/// Download a single checkpoint attempt for a topic/partition into a fresh store directory
+/// under `local_base_path`. When `checkpoint_id` is None, the most recent attempt within
+/// the importer's listing window is used. Returns the attempt metadata and the store path.
+pub async fn fetch_checkpoint(
+    importer: &CheckpointImporter,
+    topic: &str,
+    partition: i32,
+    checkpoint_id: Option<&str>,
+    local_base_path: &Path,
+) -> Result<(CheckpointMetadata, PathBuf)> {
+    let attempts = importer
+        .fetch_checkpoint_metadata(topic, partition)
+        .await
+        .context("In fetch_checkpoint")?;
+
+    let mut metadata = match checkpoint_id {
+        Some(id) => attempts.into_iter().find(|m| m.id == id).with_context(|| {
+            format!("Checkpoint {id} not found for topic:{topic} partition:{partition}")
+        })?,
+        None => attempts.into_iter().next().with_context(|| {
+            format!("No checkpoints found for topic:{topic} partition:{partition}")
+        })?,
+    };
+
+    let store_path = metadata.get_store_path(local_base_path, Utc::now());
+    tokio::fs::create_dir_all(&store_path)
+        .await
+        .with_context(|| format!("Failed to create local directory: {store_path:?}"))?;
+    importer
+        .fetch_checkpoint_files(&metadata, &store_path)
+        .await
+        .with_context(|| format!("Failed to fetch checkpoint {}", metadata.id))?;
+    metadata.write_to_dir(&store_path).await?;
+
+    info!(
+        checkpoint = metadata.get_attempt_path(),
+        local_store_path = %store_path.display(),
+        "Fetched checkpoint for inspection"
+    );
+
+    Ok((metadata, store_path))
+}
+
+/// A checkpoint directory opened as a deduplication store.
+///
+/// RocksDB writes LOCK, LOG and MANIFEST files and may compact whatever it opens, so the
+/// store is a scratch copy of the checkpoint, and the directory itself is never modified.
+pub struct LocalCheckpoint {
+    pub path: PathBuf,
+    /// Present when the directory was produced by an import or `fetch_checkpoint`
+    pub metadata: Option<CheckpointMetadata>,
+    pub store: DeduplicationStore,
+    // Declared after the store, so the store is closed before its files are removed
+    _scratch_dir: TempDir,
+}
+
+impl LocalCheckpoint {
+    /// Open a copy of the checkpoint at `path`. Fails rather than creating an empty
+    /// store when the directory does not exist.
+    pub async fn open(path: &Path) -> Result<Self> {
+        if !path.is_dir() {
+            anyhow::bail!("Checkpoint directory does not exist: {}", path.display());
+        }
+
+        let metadata = if path.join(METADATA_FILENAME).exists() {
+            Some(CheckpointMetadata::load_from_dir(path).await?)
+        } else {
+            None
+        };
+        let (topic, partition) = match &metadata {
+            Some(m) => (m.topic.clone(), m.partition),
+            None => (UNKNOWN_TOPIC.to_string(), UNKNOWN_PARTITION),
+        };
+
+        let scratch_dir = TempDir::new().context("Failed to create scratch directory")?;
+        copy_checkpoint_dir(path, scratch_dir.path()).await?;
+
+        let store = DeduplicationStore::new(
+            DeduplicationStoreConfig {
+                path: scratch_dir.path().to_path_buf(),
+                max_capacity: 0,
+            },
+            topic,
+            partition,
+        )
+        .with_context(|| format!("Failed to open checkpoint store at {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            metadata,
+            store,
+            _scratch_dir: scratch_dir,
+        })
+    }
+
+    /// Count keys and compute the range of key timestamps (millis) held in the store
+    pub fn summarize(&self) -> Result<CheckpointSummary> {
+        let mut key_count = 0;
+        let mut min_timestamp: Option<u64> = None;
+        let mut max_timestamp: Option<u64> = None;
+
+        for record in self.store.iter_timestamp_records()? {
+            let (key, _) = record?;
+            let key = TimestampKey::try_from(key.as_ref())?;
+            key_count += 1;
+            min_timestamp = Some(min_timestamp.map_or(key.timestamp, |t| t.min(key.timestamp)));
+            max_timestamp = Some(max_timestamp.map_or(key.timestamp, |t| t.max(key.timestamp)));
+        }
+
+        Ok(CheckpointSummary {
+            key_count,
+            min_timestamp,
+            max_timestamp,
+            min_time: min_timestamp.and_then(millis_to_datetime),
+            max_time: max_timestamp.and_then(millis_to_datetime),
+            size_bytes: self.store.get_total_size()?,
+        })
+    }
+
+    /// Decode every record in key order, interpreting values with the given pipeline's metadata format
+    pub fn entries(
+        &self,
+        pipeline: PipelineType,
+    ) -> Result<impl Iterator<Item = Result<CheckpointEntry>> + '_> {
+        Ok(self.store.iter_timestamp_records()?.map(move |record| {
+            let (key, value) = record?;
+            Ok(CheckpointEntry {
+                key: TimestampKey::try_from(key.as_ref())?,
+                metadata: decode_metadata(pipeline, &value)?,
+            })
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointSummary {
+    pub key_count: u64,
+    pub min_timestamp: Option<u64>,
+    pub max_timestamp: Option<u64>,
+    pub min_time: Option<DateTime<Utc>>,
+    pub max_time: Option<DateTime<Utc>>,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckpointEntry {
+    pub key: TimestampKey,
+    pub metadata: serde_json::Value,
+}
+
+fn millis_to_datetime(millis: u64) -> Option<DateTime<Utc>> {
+    DateTime::from_timestamp_millis(millis as i64)
+}
+
+fn decode_metadata(pipeline: PipelineType, bytes: &[u8]) -> Result<serde_json::Value> {
+    let value = match pipeline {
+        PipelineType::IngestionEvents => {
+            let metadata: TimestampMetadata = DeduplicationMetadata::<RawEvent>::from_bytes(bytes)?;
+            serde_json::to_value(metadata)?
+        }
+        PipelineType::ClickhouseEvents => {
+            let metadata: ClickHouseEventMetadata =
+                DeduplicationMetadata::<ClickHouseEvent>::from_bytes(bytes)?;
+            serde_json::to_value(metadata)?
+        }
+    };
+    Ok(value)
+}
+
+/// Key-level differences between two checkpoints. Counts are exact; key lists are
+/// capped at the sample size passed to [`diff_checkpoints`].
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CheckpointDiff {
+    pub only_in_left_count: u64,
+    pub only_in_right_count: u64,
+    pub changed_count: u64,
+    pub unchanged_count: u64,
+    pub only_in_left: Vec<TimestampKey>,
+    pub only_in_right: Vec<TimestampKey>,
+    pub changed: Vec<TimestampKey>,
+}
+
+/// Walk both stores in key order and classify every key as left-only, right-only,
+/// changed (same key, different value bytes) or unchanged
+pub fn diff_checkpoints(
+    left: &LocalCheckpoint,
+    right: &LocalCheckpoint,
+    max_samples: usize,
+) -> Result<CheckpointDiff> {
+    fn sample(keys: &mut Vec<TimestampKey>, key: &[u8], max_samples: usize) -> Result<()> {
+        if keys.len() < max_samples {
+            keys.push(TimestampKey::try_from(key)?);
+        }
+        Ok(())
+    }
+
+    let mut diff = CheckpointDiff::default();
+    let mut left_iter = left.store.iter_timestamp_records()?.peekable();
+    let mut right_iter = right.store.iter_timestamp_records()?.peekable();
+
+    loop {
+        let ordering = match (left_iter.peek(), right_iter.peek()) {
+            (None, None) => break,
+            (Some(Err(_)), _) => return Err(left_iter.next().unwrap().unwrap_err()),
+            (_, Some(Err(_))) => return Err(right_iter.next().unwrap().unwrap_err()),
+            (Some(Ok(_)), None) => std::cmp::Ordering::Less,
+            (None, Some(Ok(_))) => std::cmp::Ordering::Greater,
+            (Some(Ok((l, _))), Some(Ok((r, _)))) => l.cmp(r),
+        };
+
+        match ordering {
+            std::cmp::Ordering::Less => {
+                let (key, _) = left_iter.next().unwrap()?;
+                diff.only_in_left_count += 1;
+                sample(&mut diff.only_in_left, &key, max_samples)?;
+            }
+            std::cmp::Ordering::Greater => {
+                let (key, _) = right_iter.next().unwrap()?;
+                diff.only_in_right_count += 1;
+                sample(&mut diff.only_in_right, &key, max_samples)?;
+            }
+            std::cmp::Ordering::Equal => {
+                let (key, left_value) = left_iter.next().unwrap()?;
+                let (_, right_value) = right_iter.next().unwrap()?;
+                if left_value == right_value {
+                    diff.unchanged_count += 1;
+                } else {
+                    diff.changed_count += 1;
+                    sample(&mut diff.changed, &key, max_samples)?;
+                }
+            }
+        }
+    }
+
+    Ok(diff)
+}
+
+/// Predicted deduplication outcome for one line of a replayed dump file
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayOutcome {
+    /// 1-based line number in the dump file
+    pub line: usize,
+    pub key: Option<TimestampKey>,
+    pub result: &'static str,
+    pub reason: Option<&'static str>,
+    pub similarity: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ReplayReport {
+    /// Count of outcomes per result type ("new", "confirmed_duplicate", ...)
+    pub totals: BTreeMap<String, u64>,
+    pub outcomes: Vec<ReplayOutcome>,
+}
+
+/// Replay a Kafka dump file (one JSON message payload per line, in the pipeline's wire
+/// format) through the pipeline's deduplicator against a copy of the checkpoint at
+/// `checkpoint_path`. The checkpoint itself is never modified.
+pub async fn replay_dump(
+    checkpoint_path: &Path,
+    topic: &str,
+    partition: i32,
+    pipeline: PipelineType,
+    dump_path: &Path,
+    batch_size: usize,
+) -> Result<ReplayReport> {
+    let lines = tokio::fs::read_to_string(dump_path)
+        .await
+        .with_context(|| format!("Failed to read dump file: {}", dump_path.display()))?;
+
+    match pipeline {
+        PipelineType::IngestionEvents => {
+            replay_lines::<RawEvent, _>(
+                checkpoint_path,
+                topic,
+                partition,
+                pipeline,
+                &lines,
+                batch_size,
+                |line, message_offset| {
+                    let captured: CapturedEvent = serde_json::from_str(line)?;
+                    IngestionEventParser::parse(&replay_message(
+                        topic,
+                        partition,
+                        message_offset,
+                        captured,
+                    ))
+                },
+            )
+            .await
+        }
+        PipelineType::ClickhouseEvents => {
+            replay_lines::<ClickHouseEvent, _>(
+                checkpoint_path,
+                topic,
+                partition,
+                pipeline,
+                &lines,
+                batch_size,
+                |line, message_offset| {
+                    let event: ClickHouseEvent = serde_json::from_str(line)?;
+                    ClickHouseEventParser::parse(&replay_message(
+                        topic,
+                        partition,
+                        message_offset,
+                        event,
+                    ))
+                },
+            )
+            .await
+        }
+    }
+}
+
+fn replay_message<T>(topic: &str, partition: i32, offset: i64, message: T) -> KafkaMessage<T> {
+    KafkaMessage::new(
+        Partition::new(topic.to_string(), partition),
+        offset,
+        None,
+        Some(message),
+        SystemTime::now(),
+        None,
+        None,
+    )
+}
+
+async fn replay_lines<E, P>(
+    checkpoint_path: &Path,
+    topic: &str,
+    partition: i32,
+    pipeline: PipelineType,
+    lines: &str,
+    batch_size: usize,
+    parse: P,
+) -> Result<ReplayReport>
+where
+    E: DeduplicatableEvent,
+    P: Fn(&str, i64) -> Result<E>,
+{
+    // Replay writes to the store, so run against a scratch copy. The scratch dir
+    // is declared first so it outlives the store manager holding the DB open.
+    let scratch = TempDir::new().context("Failed to create scratch directory for replay")?;
+    let replay_store_path = scratch.path().join("store");
+    copy_checkpoint_dir(checkpoint_path, &replay_store_path).await?;
+
+    let store_manager = Arc::new(StoreManager::new(
+        DeduplicationStoreConfig {
+            path: scratch.path().to_path_buf(),
+            max_capacity: 0,
+        },
+        Arc::new(RebalanceTracker::new()),
+    ));
+    store_manager.restore_imported_store(topic, partition, &replay_store_path)?;
+
+    let deduplicator = TimestampDeduplicator::<E>::new(
+        TimestampDeduplicatorConfig {
+            pipeline_name: pipeline_name(pipeline).to_string(),
+            publisher: None,
+            offset_tracker: None,
+        },
+        store_manager,
+    );
+
+    let mut report = ReplayReport::default();
+    let mut pending: Vec<(usize, E)> = Vec::with_capacity(batch_size);
+
+    for (idx, line) in lines.lines().enumerate() {
+        let line_number = idx + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse(line, idx as i64) {
+            Ok(event) => pending.push((line_number, event)),
+            Err(e) => report.push(ReplayOutcome {
+                line: line_number,
+                key: None,
+                result: "parse_error",
+                reason: None,
+                similarity: None,
+                error: Some(format!("{e:#}")),
+            }),
+        }
+
+        if pending.len() >= batch_size {
+            replay_batch(&deduplicator, topic, partition, &mut pending, &mut report).await?;
+        }
+    }
+    replay_batch(&deduplicator, topic, partition, &mut pending, &mut report).await?;
+
+    report.outcomes.sort_by_key(|o| o.line);
+    Ok(report)
+}
+
+async fn replay_batch<E: DeduplicatableEvent>(
+    deduplicator: &TimestampDeduplicator<E>,
+    topic: &str,
+    partition: i32,
+    pending: &mut Vec<(usize, E)>,
+    report: &mut ReplayReport,
+) -> Result<()> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let events: Vec<&E> = pending.iter().map(|(_, event)| event).collect();
+    let results = deduplicator
+        .deduplicate_batch(topic, partition, events)
+        .await?;
+    if results.len() != pending.len() {
+        anyhow::bail!("Replay store for {topic}:{partition} is not registered");
+    }
+
+    for ((line, event), result) in pending.drain(..).zip(results) {
+        let labels = get_result_labels(&result);
+        let similarity = match &result {
+            DeduplicationResult::ConfirmedDuplicate(info)
+            | DeduplicationResult::PotentialDuplicate(info) => Some(info.similarity.overall_score),
+            _ => None,
+        };
+        report.push(ReplayOutcome {
+            line,
+            key: TimestampKey::try_from(event.extract_dedup_key().as_slice()).ok(),
+            result: labels.result_type,
+            reason: labels.reason,
+            similarity,
+            error: None,
+        });
+    }
+
+    Ok(())
+}
+
+impl ReplayReport {
+    fn push(&mut self, outcome: ReplayOutcome) {
+        *self.totals.entry(outcome.result.to_string()).or_default() += 1;
+        self.outcomes.push(outcome);
+    }
+}
+
+fn pipeline_name(pipeline: PipelineType) -> &'static str {
+    match pipeline {
+        PipelineType::IngestionEvents => "ingestion_events",
+        PipelineType::ClickhouseEvents => "clickhouse_events",
+    }
+}
+
+/// Copy the flat file layout of a RocksDB checkpoint directory
+async fn copy_checkpoint_dir(src: &Path, dst: &Path) -> Result<()> {
+    tokio::fs::create_dir_all(dst)
+        .await
+        .with_context(|| format!("Failed to create directory: {}", dst.display()))?;
+    let mut entries = tokio::fs::read_dir(src)
+        .await
+        .with_context(|| format!("Failed to read checkpoint directory: {}", src.display()))?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            tokio::fs::copy(entry.path(), dst.join(entry.file_name()))
+                .await
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn raw_event(id: u128, timestamp: &str, distinct_id: &str, event: &str) -> RawEvent {
+        RawEvent {
+            uuid: Some(uuid::Uuid::from_u128(id)),
+            event: event.to_string(),
+            distinct_id: Some(serde_json::Value::String(distinct_id.to_string())),
+            token: Some("token".to_string()),
+            properties: HashMap::new(),
+            timestamp: Some(timestamp.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn write_events(path: &Path, events: &[RawEvent]) {
+        let store = DeduplicationStore::new(
+            DeduplicationStoreConfig {
+                path: path.to_path_buf(),
+                max_capacity: 0,
+            },
+            "events".to_string(),
+            0,
+        )
+        .unwrap();
+        for event in events {
+            store
+                .put_timestamp_record(&TimestampKey::from(event), &TimestampMetadata::new(event))
+                .unwrap();
+        }
+        store.flush().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_summarize_and_entries() {
+        let dir = TempDir::new().unwrap();
+        write_events(
+            dir.path(),
+            &[
+                raw_event(1, "2024-01-01T00:00:00Z", "user1", "pageview"),
+                raw_event(2, "2024-01-01T01:00:00Z", "user2", "click"),
+            ],
+        );
+
+        let checkpoint = LocalCheckpoint::open(dir.path()).await.unwrap();
+        assert!(checkpoint.metadata.is_none());
+
+        let summary = checkpoint.summarize().unwrap();
+        assert_eq!(summary.key_count, 2);
+        assert_eq!(summary.min_timestamp, Some(1704067200000));
+        assert_eq!(summary.max_timestamp, Some(1704070800000));
+
+        let entries: Vec<CheckpointEntry> = checkpoint
+            .entries(PipelineType::IngestionEvents)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key.distinct_id, "user1");
+        assert_eq!(entries[0].metadata["original_event"]["event"], "pageview");
+    }
+
+    fn read_dir_contents(path: &Path) -> BTreeMap<std::ffi::OsString, Vec<u8>> {
+        std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                (entry.file_name(), std::fs::read(entry.path()).unwrap())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_inspection_leaves_checkpoint_untouched() {
+        let left_dir = TempDir::new().unwrap();
+        let right_dir = TempDir::new().unwrap();
+        write_events(
+            left_dir.path(),
+            &[raw_event(1, "2024-01-01T00:00:00Z", "user1", "pageview")],
+        );
+        write_events(
+            right_dir.path(),
+            &[raw_event(2, "2024-01-01T01:00:00Z", "user2", "click")],
+        );
+        let before = read_dir_contents(left_dir.path());
+
+        {
+            let left = LocalCheckpoint::open(left_dir.path()).await.unwrap();
+            let right = LocalCheckpoint::open(right_dir.path()).await.unwrap();
+            left.summarize().unwrap();
+            left.entries(PipelineType::IngestionEvents)
+                .unwrap()
+                .collect::<Result<Vec<_>>>()
+                .unwrap();
+            diff_checkpoints(&left, &right, 10).unwrap();
+        }
+
+        assert_eq!(read_dir_contents(left_dir.path()), before);
+    }
+
+    #[tokio::test]
+    async fn test_open_missing_directory_fails() {
+        let dir = TempDir::new().unwrap();
+        assert!(LocalCheckpoint::open(&dir.path().join("missing"))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_diff_checkpoints() {
+        let shared = || raw_event(1, "2024-01-01T00:00:00Z", "user1", "pageview");
+        let left_only = raw_event(2, "2024-01-01T01:00:00Z", "user2", "click");
+        let right_only = raw_event(3, "2024-01-01T02:00:00Z", "user3", "click");
+
+        let left_dir = TempDir::new().unwrap();
+        let right_dir = TempDir::new().unwrap();
+        write_events(left_dir.path(), &[shared(), left_only]);
+        write_events(right_dir.path(), &[shared(), right_only]);
+
+        let left = LocalCheckpoint::open(left_dir.path()).await.unwrap();
+        let right = LocalCheckpoint::open(right_dir.path()).await.unwrap();
+        let diff = diff_checkpoints(&left, &right, 10).unwrap();
+
+        assert_eq!(diff.unchanged_count, 1);
+        assert_eq!(diff.only_in_left_count, 1);
+        assert_eq!(diff.only_in_right_count, 1);
+        assert_eq!(diff.changed_count, 0);
+        assert_eq!(diff.only_in_left[0].distinct_id, "user2");
+        assert_eq!(diff.only_in_right[0].distinct_id, "user3");
+    }
+
+    #[tokio::test]
+    async fn test_replay_dump_predicts_duplicates() {
+        let stored = raw_event(1, "2024-01-01T00:00:00Z", "user1", "pageview");
+        let checkpoint_dir = TempDir::new().unwrap();
+        write_events(checkpoint_dir.path(), std::slice::from_ref(&stored));
+
+        let captured = |event: &RawEvent| CapturedEvent {
+            uuid: event.uuid.unwrap(),
+            distinct_id: "user1".to_string(),
+            session_id: None,
+            ip: "127.0.0.1".to_string(),
+            data: serde_json::to_string(event).unwrap(),
+            now: "2024-01-01T00:00:00Z".to_string(),
+            sent_at: None,
+            token: "token".to_string(),
+            event: event.event.clone(),
+            timestamp: chrono::Utc::now(),
+            is_cookieless_mode: false,
+            historical_migration: false,
+        };
+        let fresh = raw_event(2, "2024-01-02T00:00:00Z", "user1", "pageview");
+        let dump = [
+            serde_json::to_string(&captured(&stored)).unwrap(),
+            serde_json::to_string(&captured(&fresh)).unwrap(),
+            "not json".to_string(),
+        ]
+        .join("\n");
+        let dump_dir = TempDir::new().unwrap();
+        let dump_path = dump_dir.path().join("dump.jsonl");
+        std::fs::write(&dump_path, dump).unwrap();
+
+        let report = replay_dump(
+            checkpoint_dir.path(),
+            "events",
+            0,
+            PipelineType::IngestionEvents,
+            &dump_path,
+            100,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.outcomes.len(), 3);
+        assert_eq!(report.outcomes[0].result, "confirmed_duplicate");
+        assert_eq!(report.outcomes[1].result, "new");
+        assert_eq!(report.outcomes[2].result, "parse_error");
+        assert_eq!(report.totals.get("new"), Some(&1));
+
+        // The source checkpoint is left untouched by the replay
+        let checkpoint = LocalCheckpoint::open(checkpoint_dir.path()).await.unwrap();
+        assert_eq!(checkpoint.summarize().unwrap().key_count, 1);
+    }
+}
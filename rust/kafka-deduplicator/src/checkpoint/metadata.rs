@@ -9,6 +9,13 @@ use tracing::info;
 
 use crate::utils::format_store_path;
 
+/// Joins a remote key onto the app-level key prefix. An empty prefix still gets its
+/// separator, giving a leading '/', as that's the layout existing checkpoints were
+/// written with.
+pub fn join_key_prefix(s3_key_prefix: &str, key: &str) -> String {
+    format!("{s3_key_prefix}/{key}")
+}
+
 /// Deterministic 8-hex-char prefix for spreading S3 object keys across internal partitions.
 /// Applied ONLY to checkpoint object file paths, NEVER to metadata.json paths.
 pub fn hash_prefix_for_partition(topic: &str, partition: i32) -> String {
@@ -188,11 +195,7 @@ impl CheckpointInfo {
 
     /// Fully-qualified remote path for metadata.json (unhashed; used for list/discovery).
     pub fn get_metadata_key(&self) -> String {
-        format!(
-            "{}/{}",
-            self.s3_key_prefix,
-            self.metadata.get_metadata_filepath()
-        )
+        join_key_prefix(&self.s3_key_prefix, &self.metadata.get_metadata_filepath())
     }
 
     /// Fully-qualified remote path for a file in this attempt (relative_file_path = filename only).
@@ -201,10 +204,9 @@ impl CheckpointInfo {
     pub fn get_file_key(&self, relative_file_path: &str) -> String {
         match &self.hash_prefix {
             Some(h) => format!(
-                "{}/{}/{}/{}",
+                "{}/{}/{}",
                 h,
-                self.s3_key_prefix,
-                self.metadata.get_attempt_path(),
+                self.get_remote_attempt_path(),
                 relative_file_path
             ),
             None => format!("{}/{}", self.get_remote_attempt_path(), relative_file_path),
@@ -213,11 +215,7 @@ impl CheckpointInfo {
 
     // The fully qualified remote base path for this checkpoint attempt
     pub fn get_remote_attempt_path(&self) -> String {
-        format!(
-            "{}/{}",
-            self.s3_key_prefix,
-            self.metadata.get_attempt_path(),
-        )
+        join_key_prefix(&self.s3_key_prefix, &self.metadata.get_attempt_path())
     }
 }
 
@@ -476,6 +474,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_checkpoint_info_with_empty_key_prefix() {
+        let attempt_timestamp = Utc::now();
+        let topic = "events";
+        let partition = 3;
+        let checkpoint_id = CheckpointMetadata::generate_id(attempt_timestamp);
+        let metadata = CheckpointMetadata::new(
+            topic.to_string(),
+            partition,
+            attempt_timestamp,
+            1234567890,
+            100,
+            50,
+        );
+        let hash = hash_prefix_for_partition(topic, partition);
+
+        // Same layout as with a prefix, so checkpoints written before keep resolving
+        let info = CheckpointInfo::new(metadata.clone(), String::new(), None);
+        assert_eq!(
+            info.get_metadata_key(),
+            format!("/{topic}/{partition}/{checkpoint_id}/{METADATA_FILENAME}")
+        );
+        assert_eq!(
+            info.get_file_key("000001.sst"),
+            format!("/{topic}/{partition}/{checkpoint_id}/000001.sst")
+        );
+
+        let info = CheckpointInfo::new(metadata, String::new(), Some(hash.clone()));
+        assert_eq!(
+            info.get_file_key("000001.sst"),
+            format!("{hash}//{topic}/{partition}/{checkpoint_id}/000001.sst")
+        );
+    }
+
     #[test]
     fn test_hash_prefix_deterministic() {
         let h1 = hash_prefix_for_partition("events", 0);
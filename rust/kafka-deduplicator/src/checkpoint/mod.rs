@@ -3,7 +3,9 @@ pub mod config;
 pub mod downloader;
 pub mod error;
 pub mod export;
+pub mod filesystem;
 pub mod import;
+pub mod inspect;
 pub mod metadata;
 pub mod planner;
 pub mod s3_client;
@@ -13,9 +15,10 @@ pub mod uploader;
 pub mod worker;
 
 pub use client::CheckpointClient;
-pub use config::CheckpointConfig;
+pub use config::{CheckpointConfig, CheckpointStorageBackend};
 pub use downloader::CheckpointDownloader;
 pub use export::CheckpointExporter;
+pub use filesystem::FilesystemCheckpointStorage;
 pub use import::CheckpointImporter;
 pub use metadata::{
     hash_prefix_for_partition, CheckpointFile, CheckpointInfo, CheckpointMetadata,
@@ -7,7 +7,7 @@ use tokio_util::bytes::Bytes;
 use super::config::CheckpointConfig;
 use super::downloader::CheckpointDownloader;
 use super::error::DownloadCancelledError;
-use super::metadata::{DATE_PLUS_HOURS_ONLY_FORMAT, METADATA_FILENAME};
+use super::metadata::{join_key_prefix, DATE_PLUS_HOURS_ONLY_FORMAT, METADATA_FILENAME};
 use super::s3_client::create_s3_client;
 use crate::metrics_const::{
     CHECKPOINT_BATCH_FETCH_STORE_HISTOGRAM, CHECKPOINT_FILE_DOWNLOADS_COUNTER,
@@ -74,7 +74,7 @@ fn format_checkpoint_list_prefix(
     topic: &str,
     partition_number: i32,
 ) -> String {
-    format!("{s3_key_prefix}/{topic}/{partition_number}/")
+    join_key_prefix(s3_key_prefix, &format!("{topic}/{partition_number}/"))
 }
 
 /// Build the S3 key used as lexicographic lower bound for listing recent checkpoints.
@@ -419,6 +419,12 @@ mod tests {
         assert_eq!(prefix, "checkpoints/events/0/");
     }
 
+    #[test]
+    fn test_format_checkpoint_list_prefix_empty_key_prefix() {
+        let prefix = format_checkpoint_list_prefix("", "events", 0);
+        assert_eq!(prefix, "/events/0/");
+    }
+
     #[test]
     fn test_format_checkpoint_list_prefix_trailing_slash_prevents_prefix_collision() {
         // This test documents the bug fix: partition 41 must NOT match partition 419
@@ -5,6 +5,8 @@ use bytesize::ByteSize;
 use common_continuous_profiling::ContinuousProfilingConfig;
 use envconfig::Envconfig;
 
+use crate::checkpoint::{CheckpointConfig, CheckpointStorageBackend};
+
 /// Pipeline type for the deduplicator service.
 ///
 /// Each pipeline type handles a different event format:
@@ -182,6 +184,18 @@ pub struct Config {
 
     //// Checkpoint configuration ////
 
+    // Remote storage backend for checkpoint export and import: "s3" (default) or "filesystem"
+    #[envconfig(default = "s3")]
+    pub checkpoint_storage_backend: CheckpointStorageBackend,
+
+    // Root directory (local disk or NFS mount) for the "filesystem" checkpoint storage backend
+    pub checkpoint_filesystem_dir: Option<String>,
+
+    // Number of most recent checkpoint attempts to retain per partition
+    // in the "filesystem" backend. If 0, old attempts are never pruned
+    #[envconfig(default = "10")]
+    pub checkpoint_filesystem_retained_checkpoints: usize,
+
     // Checkpoint S3 remote storage bucket. If set, this also
     // enables local successful checkpoints to be exported to S3
     pub s3_bucket: Option<String>,
@@ -426,16 +440,23 @@ impl Config {
 
     // Check multiple conditions for safe checkpoint export enablement
     pub fn checkpoint_export_enabled(&self) -> bool {
-        self.checkpoint_export_enabled
-            && self.s3_bucket.is_some()
-            && (self.s3_endpoint.is_some() || self.aws_region.is_some())
+        self.checkpoint_export_enabled && self.checkpoint_storage_configured()
     }
 
     // Check multiple conditions for safe checkpoint import enablement
     pub fn checkpoint_import_enabled(&self) -> bool {
-        self.checkpoint_import_enabled
-            && self.s3_bucket.is_some()
-            && (self.s3_endpoint.is_some() || self.aws_region.is_some())
+        self.checkpoint_import_enabled && self.checkpoint_storage_configured()
+    }
+
+    // Whether the selected checkpoint storage backend has the settings it requires
+    fn checkpoint_storage_configured(&self) -> bool {
+        match self.checkpoint_storage_backend {
+            CheckpointStorageBackend::S3 => {
+                self.s3_bucket.is_some()
+                    && (self.s3_endpoint.is_some() || self.aws_region.is_some())
+            }
+            CheckpointStorageBackend::Filesystem => self.checkpoint_filesystem_dir.is_some(),
+        }
     }
 
     /// Get checkpoint interval as Duration
@@ -466,6 +487,36 @@ impl Config {
         Duration::from_secs(self.checkpoint_partition_import_timeout_secs)
     }
 
+    /// Build the checkpoint subsystem configuration from the env-configured settings
+    pub fn build_checkpoint_config(&self) -> CheckpointConfig {
+        CheckpointConfig {
+            checkpoint_interval: self.checkpoint_interval(),
+            checkpoint_full_upload_interval: self.checkpoint_full_upload_interval,
+            local_checkpoint_dir: self.local_checkpoint_dir.clone(),
+            storage_backend: self.checkpoint_storage_backend,
+            filesystem_storage_dir: self.checkpoint_filesystem_dir.clone().unwrap_or_default(),
+            filesystem_retained_checkpoints: self.checkpoint_filesystem_retained_checkpoints,
+            s3_bucket: self.s3_bucket.clone().unwrap_or_default(),
+            s3_key_prefix: self.s3_key_prefix.clone(),
+            aws_region: self.aws_region.clone(),
+            s3_endpoint: self.s3_endpoint.clone(),
+            s3_access_key_id: self.s3_access_key_id.clone(),
+            s3_secret_access_key: self.s3_secret_access_key.clone(),
+            s3_force_path_style: self.s3_force_path_style,
+            max_concurrent_checkpoints: self.max_concurrent_checkpoints,
+            checkpoint_gate_interval: self.checkpoint_gate_interval(),
+            checkpoint_worker_shutdown_timeout: self.checkpoint_worker_shutdown_timeout(),
+            checkpoint_import_window_hours: self.checkpoint_import_window_hours,
+            s3_operation_timeout: self.s3_operation_timeout(),
+            s3_attempt_timeout: self.s3_attempt_timeout(),
+            s3_max_retries: self.s3_max_retries,
+            checkpoint_import_attempt_depth: self.checkpoint_import_attempt_depth,
+            max_concurrent_checkpoint_file_downloads: self.max_concurrent_checkpoint_file_downloads,
+            max_concurrent_checkpoint_file_uploads: self.max_concurrent_checkpoint_file_uploads,
+            checkpoint_partition_import_timeout: self.checkpoint_partition_import_timeout(),
+        }
+    }
+
     /// Build Kafka consumer configuration for the group-based batch consumer.
     /// Applies all relevant env-configured settings (connection, TLS, fetch/queued,
     /// group membership, sticky assignment, offset reset).
@@ -742,4 +793,33 @@ mod tests {
         config.aws_region = None;
         assert!(!config.checkpoint_import_enabled());
     }
+
+    #[test]
+    fn test_checkpoint_filesystem_backend_enabled() {
+        let mut config = Config::init_with_defaults().unwrap();
+        config.checkpoint_export_enabled = true;
+        config.checkpoint_import_enabled = true;
+        config.checkpoint_storage_backend = CheckpointStorageBackend::Filesystem;
+
+        // Missing directory - should be false
+        assert!(!config.checkpoint_export_enabled());
+        assert!(!config.checkpoint_import_enabled());
+
+        // S3 settings don't enable the filesystem backend
+        config.s3_bucket = Some("test-bucket".to_string());
+        config.aws_region = Some("us-east-1".to_string());
+        assert!(!config.checkpoint_export_enabled());
+
+        config.checkpoint_filesystem_dir = Some("/mnt/checkpoints".to_string());
+        assert!(config.checkpoint_export_enabled());
+        assert!(config.checkpoint_import_enabled());
+
+        let checkpoint_config = config.build_checkpoint_config();
+        assert_eq!(
+            checkpoint_config.storage_backend,
+            CheckpointStorageBackend::Filesystem
+        );
+        assert_eq!(checkpoint_config.filesystem_storage_dir, "/mnt/checkpoints");
+        assert_eq!(checkpoint_config.filesystem_retained_checkpoints, 10);
+    }
 }
@@ -16,8 +16,14 @@ use crate::pipelines::ingestion_events::{DeduplicationConfig, DuplicateEventProd
 use crate::pipelines::{PipelineBuilder, PipelineConsumer};
 use crate::{
     checkpoint::{
-        config::CheckpointConfig, export::CheckpointExporter, import::CheckpointImporter,
-        s3_downloader::S3Downloader, s3_uploader::S3Uploader,
+        config::{CheckpointConfig, CheckpointStorageBackend},
+        downloader::CheckpointDownloader,
+        export::CheckpointExporter,
+        filesystem::FilesystemCheckpointStorage,
+        import::CheckpointImporter,
+        s3_downloader::S3Downloader,
+        s3_uploader::S3Uploader,
+        uploader::CheckpointUploader,
     },
     checkpoint_manager::CheckpointManager,
     config::Config,
@@ -120,47 +126,32 @@ impl KafkaDeduplicatorService {
         };
 
         // Create checkpoint manager and inject an exporter to enable uploads
-        let checkpoint_config = CheckpointConfig {
-            checkpoint_interval: config.checkpoint_interval(),
-            checkpoint_full_upload_interval: config.checkpoint_full_upload_interval,
-            local_checkpoint_dir: config.local_checkpoint_dir.clone(),
-            s3_bucket: config.s3_bucket.clone().unwrap_or_default(),
-            s3_key_prefix: config.s3_key_prefix.clone(),
-            aws_region: config.aws_region.clone(),
-            s3_endpoint: config.s3_endpoint.clone(),
-            s3_access_key_id: config.s3_access_key_id.clone(),
-            s3_secret_access_key: config.s3_secret_access_key.clone(),
-            s3_force_path_style: config.s3_force_path_style,
-            max_concurrent_checkpoints: config.max_concurrent_checkpoints,
-            checkpoint_gate_interval: config.checkpoint_gate_interval(),
-            checkpoint_worker_shutdown_timeout: config.checkpoint_worker_shutdown_timeout(),
-            checkpoint_import_window_hours: config.checkpoint_import_window_hours,
-            s3_operation_timeout: config.s3_operation_timeout(),
-            s3_attempt_timeout: config.s3_attempt_timeout(),
-            s3_max_retries: config.s3_max_retries,
-            checkpoint_import_attempt_depth: config.checkpoint_import_attempt_depth,
-            max_concurrent_checkpoint_file_downloads: config
-                .max_concurrent_checkpoint_file_downloads,
-            max_concurrent_checkpoint_file_uploads: config.max_concurrent_checkpoint_file_uploads,
-            checkpoint_partition_import_timeout: config.checkpoint_partition_import_timeout(),
-        };
+        let checkpoint_config = config.build_checkpoint_config();
 
         // Reset local checkpoint directory on startup (it's temporary storage)
         Self::reset_checkpoint_directory(&checkpoint_config)?;
 
-        // create exporter conditionally if S3 config is populated
+        // create exporter conditionally if the checkpoint storage backend is configured
         let exporter = if config.checkpoint_export_enabled() {
-            let uploader = match S3Uploader::new(checkpoint_config.clone()).await {
-                Ok(uploader) => Box::new(uploader),
-                Err(e) => {
-                    error!(
-                        error = ?e,
-                        bucket = %config.s3_bucket.as_deref().unwrap_or(""),
-                        region = %config.aws_region.as_deref().unwrap_or(""),
-                        "Failed to initialize S3 client for checkpoint uploads"
-                    );
-                    return Err(e.context("S3 uploader: client initialization failed"));
+            let uploader: Box<dyn CheckpointUploader> = match checkpoint_config.storage_backend {
+                CheckpointStorageBackend::S3 => {
+                    match S3Uploader::new(checkpoint_config.clone()).await {
+                        Ok(uploader) => Box::new(uploader),
+                        Err(e) => {
+                            error!(
+                                error = ?e,
+                                bucket = %config.s3_bucket.as_deref().unwrap_or(""),
+                                region = %config.aws_region.as_deref().unwrap_or(""),
+                                "Failed to initialize S3 client for checkpoint uploads"
+                            );
+                            return Err(e.context("S3 uploader: client initialization failed"));
+                        }
+                    }
                 }
+                CheckpointStorageBackend::Filesystem => Box::new(
+                    FilesystemCheckpointStorage::new(&checkpoint_config)
+                        .context("Filesystem checkpoint storage: initialization failed")?,
+                ),
             };
             Some(Arc::new(CheckpointExporter::new(uploader)))
         } else {
@@ -169,17 +160,24 @@ impl KafkaDeduplicatorService {
 
         // if checkpoint import is enabled, create and configure the importer
         let importer = if config.checkpoint_import_enabled() {
-            let downloader = match S3Downloader::new(&checkpoint_config).await {
-                Ok(downloader) => Box::new(downloader),
-                Err(e) => {
-                    error!(
-                        error = ?e,
-                        bucket = %config.s3_bucket.as_deref().unwrap_or(""),
-                        region = %config.aws_region.as_deref().unwrap_or(""),
-                        "Failed to initialize S3 client for checkpoint downloads"
-                    );
-                    return Err(e.context("S3 downloader: client initialization failed"));
-                }
+            let downloader: Box<dyn CheckpointDownloader> = match checkpoint_config.storage_backend
+            {
+                CheckpointStorageBackend::S3 => match S3Downloader::new(&checkpoint_config).await {
+                    Ok(downloader) => Box::new(downloader),
+                    Err(e) => {
+                        error!(
+                            error = ?e,
+                            bucket = %config.s3_bucket.as_deref().unwrap_or(""),
+                            region = %config.aws_region.as_deref().unwrap_or(""),
+                            "Failed to initialize S3 client for checkpoint downloads"
+                        );
+                        return Err(e.context("S3 downloader: client initialization failed"));
+                    }
+                },
+                CheckpointStorageBackend::Filesystem => Box::new(
+                    FilesystemCheckpointStorage::new(&checkpoint_config)
+                        .context("Filesystem checkpoint storage: initialization failed")?,
+                ),
             };
             Some(Arc::new(CheckpointImporter::new(
                 downloader,
@@ -263,6 +263,19 @@ impl DeduplicationStore {
         Ok(bytes_freed)
     }
 
+    /// Iterate over all timestamp records in key order, yielding raw (key, value) bytes.
+    /// Intended for offline tooling such as checkpoint inspection, not the hot path.
+    pub fn iter_timestamp_records(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<(Box<[u8]>, Box<[u8]>)>> + '_> {
+        let cf = self.store.get_cf_handle(Self::TIMESTAMP_CF)?;
+        Ok(self
+            .store
+            .db
+            .iterator_cf(&cf, rocksdb::IteratorMode::Start)
+            .map(|item| item.context("Failed to read timestamp record")))
+    }
+
     pub fn get_store(&self) -> &RocksDbStore {
         &self.store
     }
@@ -0,0 +1,30 @@
+/*
+Failed jobs moved out of job_queue by the janitor.
+
+The janitor deletes finished jobs from job_queue once it has reported metrics for them, so failed
+jobs are kept here instead, for a retention window, so they can be inspected and retried. Rows keep
+the id they had in job_queue, and are moved back under it when retried.
+*/
+CREATE TABLE job_queue_dead_letter(
+    id BIGINT PRIMARY KEY,
+    attempt INT NOT NULL,
+    attempted_at TIMESTAMPTZ,
+    attempted_by TEXT [],
+    created_at TIMESTAMPTZ NOT NULL,
+    errors JSONB [],
+    max_attempts INT NOT NULL,
+    metadata JSONB,
+    last_attempt_finished_at TIMESTAMPTZ,
+    parameters JSONB,
+    queue TEXT NOT NULL,
+    scheduled_at TIMESTAMPTZ NOT NULL,
+    status job_status NOT NULL,
+    target TEXT NOT NULL,
+    dead_lettered_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Needed for listing the failed jobs of a queue, newest first
+CREATE INDEX idx_dead_letter_queue_id ON job_queue_dead_letter(queue, id);
+
+-- Needed for pruning jobs past the retention window
+CREATE INDEX idx_dead_letter_dead_lettered_at ON job_queue_dead_letter(dead_lettered_at);
@@ -0,0 +1,496 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{RwLock, RwLockReadGuard};
+
+use uuid::Uuid;
+
+use crate::storage::{DistinctIdWithVersion, Person};
+
+const CACHE_EVICTIONS: &str = "personhog_leader_cache_evictions_total";
+
+/// In-memory cache of person and distinct ID data, split into independently locked
+/// partitions by team.
+///
+/// The cache never stores negative results - anything missing is read through from
+/// Postgres by the caller and inserted here. Every insert is versioned, so an older copy
+/// of a row (from a lagging Kafka message, or a read racing a write) never replaces a
+/// newer one. Deleted persons leave a tombstone behind, so a read that raced the delete
+/// can't bring them back.
+///
+/// Everything cached about a person lives in one slot, and each partition holds a bounded
+/// number of slots. Once a partition is full, its oldest slot is evicted to make room.
+pub struct PersonCache {
+    partitions: Vec<RwLock<CachePartition>>,
+    partition_capacity: usize,
+    // Kept alongside the partitions, so reporting it doesn't need to lock all of them
+    person_count: AtomicUsize,
+}
+
+type PersonKey = (i64, i64);
+
+#[derive(Default)]
+struct CachePartition {
+    slots: HashMap<PersonKey, PersonSlot>,
+    person_ids_by_uuid: HashMap<(i64, Uuid), i64>,
+    distinct_ids: HashMap<(i64, String), CachedDistinctId>,
+    // Slots in the order they were created, for eviction. Entries for slots that have since
+    // been removed are skipped, and pruned once they outnumber the live ones.
+    insertion_order: VecDeque<(PersonKey, u64)>,
+    next_seq: u64,
+    person_count: usize,
+}
+
+#[derive(Default)]
+struct PersonSlot {
+    seq: u64,
+    person: Option<Person>,
+    // Every cached distinct ID pointing at the person
+    distinct_ids: BTreeMap<String, Option<i64>>,
+    // Whether distinct_ids is the person's full list, loaded from Postgres, since Kafka
+    // updates alone can't tell us we've seen all of them
+    distinct_ids_loaded: bool,
+    // Person IDs are never reused, so nothing should be cached for a deleted person again.
+    // The tombstone lasts until the slot is evicted.
+    deleted: bool,
+}
+
+impl PersonSlot {
+    fn is_empty(&self) -> bool {
+        self.person.is_none()
+            && self.distinct_ids.is_empty()
+            && !self.distinct_ids_loaded
+            && !self.deleted
+    }
+}
+
+#[derive(Clone, Copy)]
+struct CachedDistinctId {
+    person_id: i64,
+    version: Option<i64>,
+}
+
+impl CachePartition {
+    // Returns the person's slot, creating it if needed, which may evict the oldest slot
+    fn slot_mut(&mut self, key: PersonKey, capacity: usize) -> &mut PersonSlot {
+        if !self.slots.contains_key(&key) {
+            while self.slots.len() >= capacity && self.evict_oldest() {}
+
+            self.next_seq += 1;
+            self.slots.insert(
+                key,
+                PersonSlot {
+                    seq: self.next_seq,
+                    ..Default::default()
+                },
+            );
+            self.insertion_order.push_back((key, self.next_seq));
+            if self.insertion_order.len() > 2 * self.slots.len().max(capacity) {
+                let slots = &self.slots;
+                self.insertion_order
+                    .retain(|(key, seq)| slots.get(key).is_some_and(|s| s.seq == *seq));
+            }
+        }
+        self.slots.entry(key).or_default()
+    }
+
+    fn is_deleted(&self, key: PersonKey) -> bool {
+        self.slots.get(&key).is_some_and(|s| s.deleted)
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        while let Some((key, seq)) = self.insertion_order.pop_front() {
+            if self.slots.get(&key).is_some_and(|s| s.seq == seq) {
+                self.remove_slot(key);
+                metrics::counter!(CACHE_EVICTIONS).increment(1);
+                return true;
+            }
+        }
+        false
+    }
+
+    fn remove_slot(&mut self, key: PersonKey) {
+        let Some(slot) = self.slots.remove(&key) else {
+            return;
+        };
+        if let Some(person) = slot.person {
+            self.person_ids_by_uuid.remove(&(key.0, person.uuid));
+            self.person_count -= 1;
+        }
+        for distinct_id in slot.distinct_ids.into_keys() {
+            self.distinct_ids.remove(&(key.0, distinct_id));
+        }
+    }
+
+    // Points a distinct ID at a person, moving it off any person it pointed at before
+    fn point_distinct_id(
+        &mut self,
+        team_id: i64,
+        distinct_id: &str,
+        person_id: i64,
+        version: Option<i64>,
+        capacity: usize,
+    ) {
+        let key = (team_id, distinct_id.to_string());
+        if let Some(existing) = self.distinct_ids.get(&key).copied() {
+            if existing.person_id != person_id {
+                self.remove_distinct_id_from_person(team_id, existing.person_id, distinct_id);
+            }
+        }
+        // Creating the slot first means an eviction can't take the mapping with it
+        self.slot_mut((team_id, person_id), capacity)
+            .distinct_ids
+            .insert(distinct_id.to_string(), version);
+        self.distinct_ids
+            .insert(key, CachedDistinctId { person_id, version });
+    }
+
+    fn remove_distinct_id_from_person(&mut self, team_id: i64, person_id: i64, distinct_id: &str) {
+        let key = (team_id, person_id);
+        if let Some(slot) = self.slots.get_mut(&key) {
+            slot.distinct_ids.remove(distinct_id);
+            if slot.is_empty() {
+                self.slots.remove(&key);
+            }
+        }
+    }
+}
+
+impl PersonCache {
+    /// Create a cache that holds about `max_persons` persons, spread across `partitions`
+    pub fn new(partitions: usize, max_persons: usize) -> Self {
+        let partitions = partitions.max(1);
+        Self {
+            partitions: (0..partitions)
+                .map(|_| RwLock::new(CachePartition::default()))
+                .collect(),
+            partition_capacity: max_persons.div_ceil(partitions).max(1),
+            person_count: AtomicUsize::new(0),
+        }
+    }
+
+    fn partition_index(&self, team_id: i64) -> usize {
+        team_id.rem_euclid(self.partitions.len() as i64) as usize
+    }
+
+    // A panic while holding a partition lock can't leave it half-updated in a way that
+    // matters (worst case a missing entry, which is read through), so we ignore poisoning
+    fn read(&self, team_id: i64) -> RwLockReadGuard<'_, CachePartition> {
+        let partition = &self.partitions[self.partition_index(team_id)];
+        partition.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Runs a change against a team's partition, keeping the total person count in step
+    fn update<R>(&self, team_id: i64, f: impl FnOnce(&mut CachePartition) -> R) -> R {
+        let partition = &self.partitions[self.partition_index(team_id)];
+        let mut partition = partition.write().unwrap_or_else(|e| e.into_inner());
+        let before = partition.person_count;
+        let result = f(&mut *partition);
+        let after = partition.person_count;
+        if after > before {
+            self.person_count
+                .fetch_add(after - before, Ordering::Relaxed);
+        } else {
+            self.person_count
+                .fetch_sub(before - after, Ordering::Relaxed);
+        }
+        result
+    }
+
+    pub fn get_person(&self, team_id: i64, person_id: i64) -> Option<Person> {
+        self.read(team_id)
+            .slots
+            .get(&(team_id, person_id))?
+            .person
+            .clone()
+    }
+
+    pub fn get_person_by_uuid(&self, team_id: i64, uuid: Uuid) -> Option<Person> {
+        let partition = self.read(team_id);
+        let person_id = partition.person_ids_by_uuid.get(&(team_id, uuid))?;
+        partition.slots.get(&(team_id, *person_id))?.person.clone()
+    }
+
+    pub fn get_person_by_distinct_id(&self, team_id: i64, distinct_id: &str) -> Option<Person> {
+        let partition = self.read(team_id);
+        let entry = partition
+            .distinct_ids
+            .get(&(team_id, distinct_id.to_string()))?;
+        partition
+            .slots
+            .get(&(team_id, entry.person_id))?
+            .person
+            .clone()
+    }
+
+    /// Returns the distinct IDs for a person, if the full list has been loaded.
+    pub fn get_distinct_ids(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> Option<Vec<DistinctIdWithVersion>> {
+        let partition = self.read(team_id);
+        let slot = partition.slots.get(&(team_id, person_id))?;
+        if !slot.distinct_ids_loaded {
+            return None;
+        }
+        Some(
+            slot.distinct_ids
+                .iter()
+                .map(|(distinct_id, version)| DistinctIdWithVersion {
+                    distinct_id: distinct_id.clone(),
+                    version: *version,
+                })
+                .collect(),
+        )
+    }
+
+    /// Insert or replace a person, unless the cache already holds a newer version.
+    /// Returns whether the cache was updated.
+    pub fn upsert_person(&self, person: Person) -> bool {
+        let capacity = self.partition_capacity;
+        self.update(person.team_id, |partition| {
+            let key = (person.team_id, person.id);
+            let slot = partition.slot_mut(key, capacity);
+            if slot.deleted {
+                return false;
+            }
+            if let Some(existing) = &slot.person {
+                if existing.version > person.version {
+                    return false;
+                }
+            }
+            let uuid = person.uuid;
+            if slot.person.replace(person).is_none() {
+                partition.person_count += 1;
+            }
+            partition.person_ids_by_uuid.insert((key.0, uuid), key.1);
+            true
+        })
+    }
+
+    pub fn remove_person(&self, team_id: i64, person_id: i64) {
+        let capacity = self.partition_capacity;
+        self.update(team_id, |partition| {
+            partition.remove_slot((team_id, person_id));
+            partition.slot_mut((team_id, person_id), capacity).deleted = true;
+        });
+    }
+
+    /// Point a distinct ID at a person, unless the cache already holds a newer mapping.
+    /// Returns whether the cache was updated.
+    pub fn set_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+        person_id: i64,
+        version: Option<i64>,
+    ) -> bool {
+        let capacity = self.partition_capacity;
+        self.update(team_id, |partition| {
+            let key = (team_id, distinct_id.to_string());
+            if let Some(existing) = partition.distinct_ids.get(&key) {
+                if existing.version > version {
+                    return false;
+                }
+            }
+            if partition.is_deleted((team_id, person_id)) {
+                return false;
+            }
+            partition.point_distinct_id(team_id, distinct_id, person_id, version, capacity);
+            true
+        })
+    }
+
+    /// Record the complete list of distinct IDs for a person, as loaded from Postgres.
+    pub fn set_distinct_ids_for_person(
+        &self,
+        team_id: i64,
+        person_id: i64,
+        distinct_ids: &[DistinctIdWithVersion],
+    ) {
+        let capacity = self.partition_capacity;
+        self.update(team_id, |partition| {
+            if partition.is_deleted((team_id, person_id)) {
+                return;
+            }
+            for id in distinct_ids {
+                let key = (team_id, id.distinct_id.clone());
+                // Anything newer we already knew about wins
+                let newer_elsewhere = partition
+                    .distinct_ids
+                    .get(&key)
+                    .is_some_and(|e| e.person_id != person_id && e.version > id.version);
+                if !newer_elsewhere {
+                    partition.point_distinct_id(
+                        team_id,
+                        &id.distinct_id,
+                        person_id,
+                        id.version,
+                        capacity,
+                    );
+                }
+            }
+            partition
+                .slot_mut((team_id, person_id), capacity)
+                .distinct_ids_loaded = true;
+        });
+    }
+
+    /// The number of persons cached, across all partitions
+    pub fn person_count(&self) -> usize {
+        self.person_count.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::*;
+
+    fn person(team_id: i64, id: i64, version: i64) -> Person {
+        Person {
+            id,
+            uuid: Uuid::from_u128(id as u128),
+            team_id,
+            properties: json!({"version": version}),
+            properties_last_updated_at: None,
+            properties_last_operation: None,
+            created_at: Utc::now(),
+            version: Some(version),
+            is_identified: false,
+            is_user_id: None,
+        }
+    }
+
+    fn distinct_ids(ids: &[(&str, i64)]) -> Vec<DistinctIdWithVersion> {
+        ids.iter()
+            .map(|(distinct_id, version)| DistinctIdWithVersion {
+                distinct_id: distinct_id.to_string(),
+                version: Some(*version),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_older_person_versions_are_ignored() {
+        let cache = PersonCache::new(4, 1000);
+        assert!(cache.upsert_person(person(1, 10, 2)));
+        assert!(!cache.upsert_person(person(1, 10, 1)));
+        assert_eq!(cache.get_person(1, 10).unwrap().version, Some(2));
+
+        assert!(cache.upsert_person(person(1, 10, 3)));
+        assert_eq!(cache.get_person(1, 10).unwrap().version, Some(3));
+    }
+
+    #[test]
+    fn test_lookup_by_uuid_and_distinct_id() {
+        let cache = PersonCache::new(4, 1000);
+        cache.upsert_person(person(1, 10, 0));
+        cache.set_distinct_id(1, "user-a", 10, Some(0));
+
+        assert_eq!(
+            cache.get_person_by_uuid(1, Uuid::from_u128(10)).unwrap().id,
+            10
+        );
+        assert_eq!(cache.get_person_by_distinct_id(1, "user-a").unwrap().id, 10);
+        // Lookups are scoped to a team
+        assert!(cache.get_person_by_distinct_id(2, "user-a").is_none());
+        assert!(cache.get_person(2, 10).is_none());
+    }
+
+    #[test]
+    fn test_distinct_id_moves_between_persons() {
+        let cache = PersonCache::new(4, 1000);
+        cache.upsert_person(person(1, 10, 0));
+        cache.upsert_person(person(1, 20, 0));
+        cache.set_distinct_ids_for_person(1, 10, &distinct_ids(&[("a", 0), ("b", 0)]));
+        cache.set_distinct_ids_for_person(1, 20, &distinct_ids(&[("c", 0)]));
+
+        assert!(cache.set_distinct_id(1, "b", 20, Some(1)));
+        assert_eq!(
+            cache.get_distinct_ids(1, 10).unwrap(),
+            distinct_ids(&[("a", 0)])
+        );
+        assert_eq!(
+            cache.get_distinct_ids(1, 20).unwrap(),
+            distinct_ids(&[("b", 1), ("c", 0)])
+        );
+
+        // A stale mapping doesn't move it back
+        assert!(!cache.set_distinct_id(1, "b", 10, Some(0)));
+        assert_eq!(cache.get_person_by_distinct_id(1, "b").unwrap().id, 20);
+    }
+
+    #[test]
+    fn test_distinct_id_list_is_only_known_once_loaded() {
+        let cache = PersonCache::new(4, 1000);
+        cache.set_distinct_id(1, "a", 10, Some(0));
+        assert!(cache.get_distinct_ids(1, 10).is_none());
+
+        // Loading a stale list keeps the newer mapping we already had
+        cache.set_distinct_id(1, "b", 20, Some(3));
+        cache.set_distinct_ids_for_person(1, 10, &distinct_ids(&[("a", 0), ("b", 2)]));
+        assert_eq!(
+            cache.get_distinct_ids(1, 10).unwrap(),
+            distinct_ids(&[("a", 0)])
+        );
+    }
+
+    #[test]
+    fn test_remove_person_drops_its_distinct_ids() {
+        let cache = PersonCache::new(4, 1000);
+        cache.upsert_person(person(1, 10, 0));
+        cache.set_distinct_ids_for_person(1, 10, &distinct_ids(&[("a", 0)]));
+
+        cache.remove_person(1, 10);
+        assert!(cache.get_person(1, 10).is_none());
+        assert!(cache.get_person_by_uuid(1, Uuid::from_u128(10)).is_none());
+        assert!(cache.get_person_by_distinct_id(1, "a").is_none());
+        assert_eq!(cache.person_count(), 0);
+    }
+
+    #[test]
+    fn test_reads_racing_a_delete_dont_bring_the_person_back() {
+        let cache = PersonCache::new(4, 1000);
+        cache.upsert_person(person(1, 10, 0));
+        cache.remove_person(1, 10);
+
+        // What a read-through started before the delete would insert once it finished
+        assert!(!cache.upsert_person(person(1, 10, 0)));
+        assert!(!cache.set_distinct_id(1, "a", 10, None));
+        cache.set_distinct_ids_for_person(1, 10, &distinct_ids(&[("a", 0)]));
+
+        assert!(cache.get_person(1, 10).is_none());
+        assert!(cache.get_person_by_distinct_id(1, "a").is_none());
+        assert!(cache.get_distinct_ids(1, 10).is_none());
+        assert_eq!(cache.person_count(), 0);
+    }
+
+    #[test]
+    fn test_oldest_persons_are_evicted_at_capacity() {
+        // A single partition of three slots
+        let cache = PersonCache::new(1, 3);
+        for id in 1..=3 {
+            cache.upsert_person(person(1, id, 0));
+        }
+        cache.set_distinct_ids_for_person(1, 1, &distinct_ids(&[("a", 0)]));
+        assert_eq!(cache.person_count(), 3);
+
+        cache.upsert_person(person(1, 4, 0));
+        assert_eq!(cache.person_count(), 3);
+        assert!(cache.get_person(1, 1).is_none());
+        assert!(cache.get_person_by_uuid(1, Uuid::from_u128(1)).is_none());
+        assert!(cache.get_person_by_distinct_id(1, "a").is_none());
+        assert!(cache.get_distinct_ids(1, 1).is_none());
+        assert!(cache.get_person(1, 4).is_some());
+
+        // Distinct IDs of persons we don't hold count towards the bound too
+        for (i, distinct_id) in ["b", "c", "d"].iter().enumerate() {
+            cache.set_distinct_id(1, distinct_id, 10 + i as i64, Some(0));
+        }
+        assert_eq!(cache.person_count(), 0);
+        assert!(cache.get_person(1, 4).is_none());
+    }
+}
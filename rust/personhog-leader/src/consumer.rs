@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use common_kafka::kafka_consumer::{RecvErr, SingleTopicConsumer};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::cache::PersonCache;
+use crate::storage::Person;
+
+const UPDATES_RECEIVED: &str = "personhog_leader_updates_received_total";
+const UPDATES_PARSE_ERROR: &str = "personhog_leader_updates_parse_error_total";
+const UPDATES_OFFSET_STORE_FAILED: &str = "personhog_leader_updates_offset_store_failed_total";
+const CACHED_PERSONS: &str = "personhog_leader_cached_persons";
+
+/// A change to person data made outside of this leader, e.g. by the ingestion pipeline,
+/// published so the leader's cache stays in line with Postgres.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PersonUpdate {
+    Person(Person),
+    PersonDeleted {
+        team_id: i64,
+        person_id: i64,
+    },
+    DistinctId {
+        team_id: i64,
+        distinct_id: String,
+        person_id: i64,
+        version: Option<i64>,
+    },
+}
+
+impl PersonUpdate {
+    fn kind(&self) -> &'static str {
+        match self {
+            PersonUpdate::Person(_) => "person",
+            PersonUpdate::PersonDeleted { .. } => "person_deleted",
+            PersonUpdate::DistinctId { .. } => "distinct_id",
+        }
+    }
+
+    /// Apply the update to the cache. Returns whether the cache changed, which it won't if
+    /// it already held a newer version.
+    pub fn apply(self, cache: &PersonCache) -> bool {
+        match self {
+            PersonUpdate::Person(person) => cache.upsert_person(person),
+            PersonUpdate::PersonDeleted { team_id, person_id } => {
+                cache.remove_person(team_id, person_id);
+                true
+            }
+            PersonUpdate::DistinctId {
+                team_id,
+                distinct_id,
+                person_id,
+                version,
+            } => cache.set_distinct_id(team_id, &distinct_id, person_id, version),
+        }
+    }
+}
+
+/// Apply person updates from Kafka to the cache until the consumer fails.
+///
+/// Returns the Kafka error that stopped it. Without its update stream the leader would
+/// keep serving whatever it has cached, so callers should treat this as fatal.
+pub async fn consume_person_updates(
+    consumer: SingleTopicConsumer,
+    cache: Arc<PersonCache>,
+) -> RecvErr {
+    loop {
+        let (update, offset): (PersonUpdate, _) = match consumer.json_recv().await {
+            Ok(r) => r,
+            Err(RecvErr::Empty) => {
+                warn!("Received empty person update");
+                metrics::counter!(UPDATES_PARSE_ERROR).increment(1);
+                continue;
+            }
+            Err(RecvErr::Serde(e)) => {
+                warn!("Failed to parse person update: {:?}", e);
+                metrics::counter!(UPDATES_PARSE_ERROR).increment(1);
+                continue;
+            }
+            Err(e @ RecvErr::Kafka(_)) => return e,
+        };
+
+        let kind = update.kind();
+        let applied = update.apply(&cache);
+        metrics::counter!(
+            UPDATES_RECEIVED,
+            &[
+                ("type", kind),
+                ("applied", if applied { "true" } else { "false" })
+            ]
+        )
+        .increment(1);
+        metrics::gauge!(CACHED_PERSONS).set(cache.person_count() as f64);
+
+        let curr_offset = offset.get_value();
+        if let Err(e) = offset.store() {
+            metrics::counter!(UPDATES_OFFSET_STORE_FAILED).increment(1);
+            error!("Failed to store person update offset {curr_offset}: {e}");
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod cache;
+pub mod config;
+pub mod consumer;
+pub mod service;
+pub mod storage;
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use axum::{routing::get, Router};
+use common_database::{get_pool_with_config, PoolConfig};
+use common_kafka::kafka_consumer::SingleTopicConsumer;
+use common_metrics::setup_metrics_routes;
+use personhog_proto::personhog::leader::v1::person_hog_leader_server::PersonHogLeaderServer;
+use tokio::signal;
+use tonic::transport::Server;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::fmt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+use health::readiness_handler;
+use personhog_leader::cache::PersonCache;
+use personhog_leader::config::Config;
+use personhog_leader::consumer::consume_person_updates;
+use personhog_leader::service::PersonHogLeaderService;
+use personhog_leader::storage::postgres::PostgresStore;
+
+common_alloc::used!();
+
+async fn shutdown_signal() {
+    let mut term = signal::unix::signal(signal::unix::SignalKind::terminate())
+        .expect("failed to register SIGTERM handler");
+
+    let mut interrupt = signal::unix::signal(signal::unix::SignalKind::interrupt())
+        .expect("failed to register SIGINT handler");
+
+    tokio::select! {
+        _ = term.recv() => {},
+        _ = interrupt.recv() => {},
+    };
+
+    tracing::info!("Shutting down gracefully...");
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::init_with_defaults().expect("Invalid configuration");
+
+    // Initialize tracing
+    let log_layer = fmt::layer()
+        .with_target(true)
+        .with_thread_ids(true)
+        .with_level(true);
+
+    tracing_subscriber::registry()
+        .with(log_layer)
+        .with(
+            EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+
+    tracing::info!("Starting personhog-leader service");
+    tracing::info!("gRPC address: {}", config.grpc_address);
+    tracing::info!("Metrics port: {}", config.metrics_port);
+    tracing::info!(
+        "Person updates topic: {}",
+        config.consumer.kafka_consumer_topic
+    );
+
+    // Start HTTP server for metrics and health checks
+    let metrics_port = config.metrics_port;
+    let health_router = Router::new()
+        .route("/_readiness", get(readiness_handler))
+        .route("/_liveness", get(|| async { "ok" }));
+    let metrics_router = setup_metrics_routes(health_router);
+
+    tokio::spawn(async move {
+        let bind = format!("0.0.0.0:{metrics_port}");
+        let listener = tokio::net::TcpListener::bind(&bind)
+            .await
+            .expect("Failed to bind metrics port");
+        tracing::info!("Metrics server listening on {}", bind);
+        axum::serve(listener, metrics_router)
+            .await
+            .expect("Metrics server error");
+    });
+
+    let pool_config = PoolConfig {
+        min_connections: config.min_pg_connections,
+        max_connections: config.max_pg_connections,
+        acquire_timeout: config.acquire_timeout(),
+        idle_timeout: config.idle_timeout(),
+        test_before_acquire: true,
+        statement_timeout_ms: config.statement_timeout(),
+    };
+    let pool = get_pool_with_config(&config.database_url, pool_config)
+        .expect("Failed to create database pool");
+    let store = Arc::new(PostgresStore::new(pool));
+
+    let cache = Arc::new(PersonCache::new(
+        config.cache_partitions,
+        config.cache_max_persons,
+    ));
+
+    let consumer = SingleTopicConsumer::new(config.kafka.clone(), config.consumer.clone())
+        .expect("Failed to create person updates consumer");
+    let updates = tokio::spawn(consume_person_updates(consumer, cache.clone()));
+
+    let service = PersonHogLeaderService::new(cache, store);
+
+    tracing::info!("Starting gRPC server on {}", config.grpc_address);
+
+    let server = Server::builder()
+        .add_service(PersonHogLeaderServer::new(service))
+        .serve_with_shutdown(config.grpc_address, shutdown_signal());
+
+    // If the update stream stops, the cache can no longer be trusted, so stop serving
+    tokio::select! {
+        result = server => result?,
+        result = updates => {
+            let err = result?;
+            tracing::error!("Person updates consumer stopped: {}", err);
+            return Err(err.into());
+        }
+    }
+
+    Ok(())
+}
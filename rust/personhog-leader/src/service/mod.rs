@@ -0,0 +1,453 @@
+mod error;
+mod types;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use personhog_proto::personhog::leader::v1::person_hog_leader_server::PersonHogLeader;
+use personhog_proto::personhog::types::v1::{
+    GetDistinctIdsForPersonRequest, GetDistinctIdsForPersonResponse,
+    GetDistinctIdsForPersonsRequest, GetDistinctIdsForPersonsResponse,
+    GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
+    GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
+    GetPersonsRequest, MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonDistinctIds,
+    PersonWithDistinctIds, PersonWithTeamDistinctId, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, TeamDistinctId, UpdatePersonPropertiesRequest,
+    UpdatePersonPropertiesResponse,
+};
+use serde_json::{Map, Value};
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+use crate::cache::PersonCache;
+use crate::storage::{self, PersonStore, PropertyUpdate};
+
+use error::log_and_convert_error;
+
+const CACHE_LOOKUPS: &str = "personhog_leader_cache_lookups_total";
+
+fn record_lookup(lookup: &'static str, hit: bool) {
+    metrics::counter!(
+        CACHE_LOOKUPS,
+        &[
+            ("lookup", lookup),
+            ("result", if hit { "hit" } else { "miss" })
+        ]
+    )
+    .increment(1);
+}
+
+/// Parse a JSON object of properties from request bytes. Empty bytes mean no properties.
+#[allow(clippy::result_large_err)] // tonic::Status is large but we can't change it
+fn parse_properties(bytes: &[u8], field: &str) -> Result<Map<String, Value>, Status> {
+    if bytes.is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_slice(bytes) {
+        Ok(Value::Object(properties)) => Ok(properties),
+        Ok(_) => Err(Status::invalid_argument(format!(
+            "{field} must be a JSON object"
+        ))),
+        Err(e) => Err(Status::invalid_argument(format!(
+            "Invalid JSON in {field}: {e}"
+        ))),
+    }
+}
+
+/// Serves person reads from the in-memory cache, reading through to Postgres on a miss,
+/// and applies person writes to Postgres before updating the cache.
+///
+/// Read options are ignored: everything the leader serves is strongly consistent.
+pub struct PersonHogLeaderService {
+    cache: Arc<PersonCache>,
+    store: Arc<dyn PersonStore>,
+}
+
+impl PersonHogLeaderService {
+    pub fn new(cache: Arc<PersonCache>, store: Arc<dyn PersonStore>) -> Self {
+        Self { cache, store }
+    }
+
+    async fn person_by_id(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> Result<Option<storage::Person>, storage::StorageError> {
+        if let Some(person) = self.cache.get_person(team_id, person_id) {
+            record_lookup("person_id", true);
+            return Ok(Some(person));
+        }
+        record_lookup("person_id", false);
+
+        let person = self.store.get_person_by_id(team_id, person_id).await?;
+        if let Some(person) = &person {
+            self.cache.upsert_person(person.clone());
+        }
+        Ok(person)
+    }
+
+    async fn person_by_uuid(
+        &self,
+        team_id: i64,
+        uuid: Uuid,
+    ) -> Result<Option<storage::Person>, storage::StorageError> {
+        if let Some(person) = self.cache.get_person_by_uuid(team_id, uuid) {
+            record_lookup("uuid", true);
+            return Ok(Some(person));
+        }
+        record_lookup("uuid", false);
+
+        let person = self.store.get_person_by_uuid(team_id, uuid).await?;
+        if let Some(person) = &person {
+            self.cache.upsert_person(person.clone());
+        }
+        Ok(person)
+    }
+
+    async fn person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> Result<Option<storage::Person>, storage::StorageError> {
+        if let Some(person) = self.cache.get_person_by_distinct_id(team_id, distinct_id) {
+            record_lookup("distinct_id", true);
+            return Ok(Some(person));
+        }
+        record_lookup("distinct_id", false);
+
+        let person = self
+            .store
+            .get_person_by_distinct_id(team_id, distinct_id)
+            .await?;
+        if let Some(person) = &person {
+            // The join doesn't give us the mapping's version, so this loses to any
+            // versioned mapping the cache already holds
+            self.cache
+                .set_distinct_id(team_id, distinct_id, person.id, None);
+            self.cache.upsert_person(person.clone());
+        }
+        Ok(person)
+    }
+
+    async fn distinct_ids_for_person(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> Result<Vec<storage::DistinctIdWithVersion>, storage::StorageError> {
+        if let Some(distinct_ids) = self.cache.get_distinct_ids(team_id, person_id) {
+            record_lookup("distinct_ids_for_person", true);
+            return Ok(distinct_ids);
+        }
+        record_lookup("distinct_ids_for_person", false);
+
+        let distinct_ids = self
+            .store
+            .get_distinct_ids_for_person(team_id, person_id)
+            .await?;
+        self.cache
+            .set_distinct_ids_for_person(team_id, person_id, &distinct_ids);
+        Ok(distinct_ids)
+    }
+}
+
+#[tonic::async_trait]
+impl PersonHogLeader for PersonHogLeaderService {
+    // ============================================================
+    // Person lookups by ID/UUID
+    // ============================================================
+
+    async fn get_person(
+        &self,
+        request: Request<GetPersonRequest>,
+    ) -> Result<Response<GetPersonResponse>, Status> {
+        let req = request.into_inner();
+
+        let person = self
+            .person_by_id(req.team_id, req.person_id)
+            .await
+            .map_err(|e| log_and_convert_error(e, "get_person"))?;
+
+        Ok(Response::new(GetPersonResponse {
+            person: person.map(Into::into),
+        }))
+    }
+
+    async fn get_persons(
+        &self,
+        request: Request<GetPersonsRequest>,
+    ) -> Result<Response<PersonsResponse>, Status> {
+        let req = request.into_inner();
+
+        let mut persons = Vec::with_capacity(req.person_ids.len());
+        let mut missing_ids = Vec::new();
+        let mut seen = HashSet::new();
+        for person_id in req.person_ids {
+            if !seen.insert(person_id) {
+                continue;
+            }
+            match self
+                .person_by_id(req.team_id, person_id)
+                .await
+                .map_err(|e| log_and_convert_error(e, "get_persons"))?
+            {
+                Some(person) => persons.push(person.into()),
+                None => missing_ids.push(person_id),
+            }
+        }
+
+        Ok(Response::new(PersonsResponse {
+            persons,
+            missing_ids,
+        }))
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        request: Request<GetPersonByUuidRequest>,
+    ) -> Result<Response<GetPersonResponse>, Status> {
+        let req = request.into_inner();
+
+        let uuid = Uuid::parse_str(&req.uuid)
+            .map_err(|e| Status::invalid_argument(format!("Invalid UUID: {e}")))?;
+
+        let person = self
+            .person_by_uuid(req.team_id, uuid)
+            .await
+            .map_err(|e| log_and_convert_error(e, "get_person_by_uuid"))?;
+
+        Ok(Response::new(GetPersonResponse {
+            person: person.map(Into::into),
+        }))
+    }
+
+    async fn get_persons_by_uuids(
+        &self,
+        request: Request<GetPersonsByUuidsRequest>,
+    ) -> Result<Response<PersonsResponse>, Status> {
+        let req = request.into_inner();
+
+        let uuids: Vec<Uuid> = req
+            .uuids
+            .iter()
+            .map(|s| Uuid::parse_str(s))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Status::invalid_argument(format!("Invalid UUID: {e}")))?;
+
+        let mut persons = Vec::with_capacity(uuids.len());
+        let mut seen = HashSet::new();
+        for uuid in uuids {
+            if !seen.insert(uuid) {
+                continue;
+            }
+            if let Some(person) = self
+                .person_by_uuid(req.team_id, uuid)
+                .await
+                .map_err(|e| log_and_convert_error(e, "get_persons_by_uuids"))?
+            {
+                persons.push(person.into());
+            }
+        }
+
+        Ok(Response::new(PersonsResponse {
+            persons,
+            missing_ids: Vec::new(),
+        }))
+    }
+
+    // ============================================================
+    // Person lookups by Distinct ID
+    // ============================================================
+
+    async fn get_person_by_distinct_id(
+        &self,
+        request: Request<GetPersonByDistinctIdRequest>,
+    ) -> Result<Response<GetPersonResponse>, Status> {
+        let req = request.into_inner();
+
+        let person = self
+            .person_by_distinct_id(req.team_id, &req.distinct_id)
+            .await
+            .map_err(|e| log_and_convert_error(e, "get_person_by_distinct_id"))?;
+
+        Ok(Response::new(GetPersonResponse {
+            person: person.map(Into::into),
+        }))
+    }
+
+    async fn get_persons_by_distinct_ids_in_team(
+        &self,
+        request: Request<GetPersonsByDistinctIdsInTeamRequest>,
+    ) -> Result<Response<PersonsByDistinctIdsInTeamResponse>, Status> {
+        let req = request.into_inner();
+
+        let mut results = Vec::with_capacity(req.distinct_ids.len());
+        for distinct_id in req.distinct_ids {
+            let person = self
+                .person_by_distinct_id(req.team_id, &distinct_id)
+                .await
+                .map_err(|e| log_and_convert_error(e, "get_persons_by_distinct_ids_in_team"))?;
+            results.push(PersonWithDistinctIds {
+                distinct_id,
+                person: person.map(Into::into),
+            });
+        }
+
+        Ok(Response::new(PersonsByDistinctIdsInTeamResponse {
+            results,
+        }))
+    }
+
+    async fn get_persons_by_distinct_ids(
+        &self,
+        request: Request<GetPersonsByDistinctIdsRequest>,
+    ) -> Result<Response<PersonsByDistinctIdsResponse>, Status> {
+        let req = request.into_inner();
+
+        let mut results = Vec::with_capacity(req.team_distinct_ids.len());
+        for TeamDistinctId {
+            team_id,
+            distinct_id,
+        } in req.team_distinct_ids
+        {
+            let person = self
+                .person_by_distinct_id(team_id, &distinct_id)
+                .await
+                .map_err(|e| log_and_convert_error(e, "get_persons_by_distinct_ids"))?;
+            results.push(PersonWithTeamDistinctId {
+                key: Some(TeamDistinctId {
+                    team_id,
+                    distinct_id,
+                }),
+                person: person.map(Into::into),
+            });
+        }
+
+        Ok(Response::new(PersonsByDistinctIdsResponse { results }))
+    }
+
+    // ============================================================
+    // Distinct ID operations
+    // ============================================================
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        request: Request<GetDistinctIdsForPersonRequest>,
+    ) -> Result<Response<GetDistinctIdsForPersonResponse>, Status> {
+        let req = request.into_inner();
+
+        let distinct_ids = self
+            .distinct_ids_for_person(req.team_id, req.person_id)
+            .await
+            .map_err(|e| log_and_convert_error(e, "get_distinct_ids_for_person"))?;
+
+        Ok(Response::new(GetDistinctIdsForPersonResponse {
+            distinct_ids: distinct_ids.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_distinct_ids_for_persons(
+        &self,
+        request: Request<GetDistinctIdsForPersonsRequest>,
+    ) -> Result<Response<GetDistinctIdsForPersonsResponse>, Status> {
+        let req = request.into_inner();
+
+        let mut person_distinct_ids = Vec::with_capacity(req.person_ids.len());
+        let mut seen = HashSet::new();
+        for person_id in req.person_ids {
+            if !seen.insert(person_id) {
+                continue;
+            }
+            let distinct_ids = self
+                .distinct_ids_for_person(req.team_id, person_id)
+                .await
+                .map_err(|e| log_and_convert_error(e, "get_distinct_ids_for_persons"))?;
+            if distinct_ids.is_empty() {
+                continue;
+            }
+            person_distinct_ids.push(PersonDistinctIds {
+                person_id,
+                distinct_ids: distinct_ids.into_iter().map(Into::into).collect(),
+            });
+        }
+
+        Ok(Response::new(GetDistinctIdsForPersonsResponse {
+            person_distinct_ids,
+        }))
+    }
+
+    // ============================================================
+    // Person writes
+    // ============================================================
+
+    async fn update_person_properties(
+        &self,
+        request: Request<UpdatePersonPropertiesRequest>,
+    ) -> Result<Response<UpdatePersonPropertiesResponse>, Status> {
+        let req = request.into_inner();
+
+        let update = PropertyUpdate {
+            set: parse_properties(&req.set_properties, "set_properties")?,
+            set_once: parse_properties(&req.set_once_properties, "set_once_properties")?,
+            unset: req.unset_properties,
+        };
+
+        let person = self
+            .store
+            .update_person_properties(req.team_id, req.person_id, &update)
+            .await
+            .map_err(|e| log_and_convert_error(e, "update_person_properties"))?
+            .ok_or_else(|| {
+                Status::not_found(format!(
+                    "Person {} not found in team {}",
+                    req.person_id, req.team_id
+                ))
+            })?;
+
+        self.cache.upsert_person(person.clone());
+
+        Ok(Response::new(UpdatePersonPropertiesResponse {
+            person: Some(person.into()),
+        }))
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        request: Request<MergeDistinctIdsRequest>,
+    ) -> Result<Response<MergeDistinctIdsResponse>, Status> {
+        let req = request.into_inner();
+
+        let result = self
+            .store
+            .merge_distinct_ids(
+                req.team_id,
+                &req.target_distinct_id,
+                &req.source_distinct_ids,
+            )
+            .await
+            .map_err(|e| log_and_convert_error(e, "merge_distinct_ids"))?
+            .ok_or_else(|| {
+                Status::not_found(format!(
+                    "Distinct ID {} not found in team {}",
+                    req.target_distinct_id, req.team_id
+                ))
+            })?;
+
+        let person_id = result.person.id;
+        for person_id in &result.deleted_person_ids {
+            self.cache.remove_person(req.team_id, *person_id);
+        }
+        for moved in &result.moved_distinct_ids {
+            self.cache
+                .set_distinct_id(req.team_id, &moved.distinct_id, person_id, moved.version);
+        }
+        self.cache.upsert_person(result.person.clone());
+
+        Ok(Response::new(MergeDistinctIdsResponse {
+            person: Some(result.person.into()),
+            deleted_person_ids: result.deleted_person_ids,
+        }))
+    }
+}
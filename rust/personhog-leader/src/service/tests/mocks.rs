@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::storage::{
+    DistinctIdWithVersion, MergeResult, Person, PersonStore, PropertyUpdate, StorageError,
+    StorageResult,
+};
+
+pub fn person(team_id: i64, id: i64) -> Person {
+    Person {
+        id,
+        uuid: Uuid::from_u128(id as u128),
+        team_id,
+        properties: json!({}),
+        properties_last_updated_at: None,
+        properties_last_operation: None,
+        created_at: Utc::now(),
+        version: Some(0),
+        is_identified: false,
+        is_user_id: None,
+    }
+}
+
+#[derive(Default)]
+struct State {
+    persons: HashMap<(i64, i64), Person>,
+    distinct_ids: HashMap<(i64, String), (i64, Option<i64>)>,
+}
+
+/// In-memory person store that counts reads, so tests can tell cache hits from misses
+#[derive(Default)]
+pub struct InMemoryStore {
+    state: Mutex<State>,
+    reads: AtomicUsize,
+}
+
+impl InMemoryStore {
+    pub fn insert_person(&self, person: Person, distinct_ids: &[&str]) {
+        let mut state = self.state.lock().unwrap();
+        for distinct_id in distinct_ids {
+            state.distinct_ids.insert(
+                (person.team_id, distinct_id.to_string()),
+                (person.id, Some(0)),
+            );
+        }
+        state.persons.insert((person.team_id, person.id), person);
+    }
+
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+
+    fn read(&self) -> std::sync::MutexGuard<'_, State> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        self.state.lock().unwrap()
+    }
+}
+
+fn bump(version: Option<i64>) -> Option<i64> {
+    Some(version.unwrap_or(0) + 1)
+}
+
+#[async_trait]
+impl PersonStore for InMemoryStore {
+    async fn get_person_by_id(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> StorageResult<Option<Person>> {
+        Ok(self.read().persons.get(&(team_id, person_id)).cloned())
+    }
+
+    async fn get_person_by_uuid(&self, team_id: i64, uuid: Uuid) -> StorageResult<Option<Person>> {
+        Ok(self
+            .read()
+            .persons
+            .values()
+            .find(|p| p.team_id == team_id && p.uuid == uuid)
+            .cloned())
+    }
+
+    async fn get_person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> StorageResult<Option<Person>> {
+        let state = self.read();
+        Ok(state
+            .distinct_ids
+            .get(&(team_id, distinct_id.to_string()))
+            .and_then(|(person_id, _)| state.persons.get(&(team_id, *person_id)))
+            .cloned())
+    }
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> StorageResult<Vec<DistinctIdWithVersion>> {
+        let state = self.read();
+        let mut distinct_ids: Vec<_> = state
+            .distinct_ids
+            .iter()
+            .filter(|((team, _), (person, _))| *team == team_id && *person == person_id)
+            .map(|((_, distinct_id), (_, version))| DistinctIdWithVersion {
+                distinct_id: distinct_id.clone(),
+                version: *version,
+            })
+            .collect();
+        distinct_ids.sort_by(|a, b| a.distinct_id.cmp(&b.distinct_id));
+        Ok(distinct_ids)
+    }
+
+    async fn update_person_properties(
+        &self,
+        team_id: i64,
+        person_id: i64,
+        update: &PropertyUpdate,
+    ) -> StorageResult<Option<Person>> {
+        let mut state = self.state.lock().unwrap();
+        let Some(person) = state.persons.get_mut(&(team_id, person_id)) else {
+            return Ok(None);
+        };
+        if update.apply(&mut person.properties) {
+            person.version = bump(person.version);
+        }
+        Ok(Some(person.clone()))
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        team_id: i64,
+        target_distinct_id: &str,
+        source_distinct_ids: &[String],
+    ) -> StorageResult<Option<MergeResult>> {
+        let mut state = self.state.lock().unwrap();
+        let Some((target_id, _)) = state
+            .distinct_ids
+            .get(&(team_id, target_distinct_id.to_string()))
+            .copied()
+        else {
+            return Ok(None);
+        };
+
+        let mut moved_distinct_ids = Vec::new();
+        let mut previous_person_ids = Vec::new();
+        for distinct_id in source_distinct_ids {
+            let key = (team_id, distinct_id.clone());
+            let version = match state.distinct_ids.get(&key).copied() {
+                Some((person_id, _)) if person_id == target_id => continue,
+                Some((person_id, version)) => {
+                    previous_person_ids.push(person_id);
+                    bump(version)
+                }
+                None => Some(0),
+            };
+            state.distinct_ids.insert(key, (target_id, version));
+            moved_distinct_ids.push(DistinctIdWithVersion {
+                distinct_id: distinct_id.clone(),
+                version,
+            });
+        }
+
+        let mut deleted_person_ids = Vec::new();
+        for person_id in previous_person_ids {
+            let orphaned = !state
+                .distinct_ids
+                .values()
+                .any(|(person, _)| *person == person_id);
+            if orphaned && state.persons.remove(&(team_id, person_id)).is_some() {
+                deleted_person_ids.push(person_id);
+            }
+        }
+
+        let person = state.persons.get_mut(&(team_id, target_id)).unwrap();
+        person.version = bump(person.version);
+        Ok(Some(MergeResult {
+            person: person.clone(),
+            moved_distinct_ids,
+            deleted_person_ids,
+        }))
+    }
+}
+
+/// Mock store that fails every call with the configured error
+pub struct FailingStore {
+    error: StorageError,
+}
+
+impl FailingStore {
+    pub fn with_connection_error() -> Self {
+        Self {
+            error: StorageError::Connection("connection refused".to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl PersonStore for FailingStore {
+    async fn get_person_by_id(
+        &self,
+        _team_id: i64,
+        _person_id: i64,
+    ) -> StorageResult<Option<Person>> {
+        Err(self.error.clone())
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        _team_id: i64,
+        _uuid: Uuid,
+    ) -> StorageResult<Option<Person>> {
+        Err(self.error.clone())
+    }
+
+    async fn get_person_by_distinct_id(
+        &self,
+        _team_id: i64,
+        _distinct_id: &str,
+    ) -> StorageResult<Option<Person>> {
+        Err(self.error.clone())
+    }
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        _team_id: i64,
+        _person_id: i64,
+    ) -> StorageResult<Vec<DistinctIdWithVersion>> {
+        Err(self.error.clone())
+    }
+
+    async fn update_person_properties(
+        &self,
+        _team_id: i64,
+        _person_id: i64,
+        _update: &PropertyUpdate,
+    ) -> StorageResult<Option<Person>> {
+        Err(self.error.clone())
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        _team_id: i64,
+        _target_distinct_id: &str,
+        _source_distinct_ids: &[String],
+    ) -> StorageResult<Option<MergeResult>> {
+        Err(self.error.clone())
+    }
+}
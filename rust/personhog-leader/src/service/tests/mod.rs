@@ -0,0 +1,268 @@
+mod mocks;
+
+use std::sync::Arc;
+
+use personhog_proto::personhog::leader::v1::person_hog_leader_server::PersonHogLeader;
+use personhog_proto::personhog::types::v1::{
+    GetDistinctIdsForPersonRequest, GetPersonByDistinctIdRequest, GetPersonByUuidRequest,
+    GetPersonRequest, GetPersonsRequest, MergeDistinctIdsRequest, UpdatePersonPropertiesRequest,
+};
+use serde_json::{json, Value};
+use tonic::{Code, Request};
+
+use mocks::{person, FailingStore, InMemoryStore};
+
+use super::PersonHogLeaderService;
+use crate::cache::PersonCache;
+
+fn setup() -> (PersonHogLeaderService, Arc<InMemoryStore>, Arc<PersonCache>) {
+    let store = Arc::new(InMemoryStore::default());
+    let cache = Arc::new(PersonCache::new(4, 1000));
+    let service = PersonHogLeaderService::new(cache.clone(), store.clone());
+    (service, store, cache)
+}
+
+fn get_person_request(team_id: i64, person_id: i64) -> Request<GetPersonRequest> {
+    Request::new(GetPersonRequest {
+        team_id,
+        person_id,
+        read_options: None,
+    })
+}
+
+fn get_by_distinct_id_request(
+    team_id: i64,
+    distinct_id: &str,
+) -> Request<GetPersonByDistinctIdRequest> {
+    Request::new(GetPersonByDistinctIdRequest {
+        team_id,
+        distinct_id: distinct_id.to_string(),
+        read_options: None,
+    })
+}
+
+#[tokio::test]
+async fn test_reads_go_through_to_the_store_once() {
+    let (service, store, _) = setup();
+    store.insert_person(person(1, 10), &["user-a"]);
+
+    for _ in 0..3 {
+        let response = service.get_person(get_person_request(1, 10)).await.unwrap();
+        assert_eq!(response.into_inner().person.unwrap().id, 10);
+    }
+    assert_eq!(store.reads(), 1);
+
+    // The person is now cached, and so is its UUID
+    let response = service
+        .get_person_by_uuid(Request::new(GetPersonByUuidRequest {
+            team_id: 1,
+            uuid: uuid::Uuid::from_u128(10).to_string(),
+            read_options: None,
+        }))
+        .await
+        .unwrap();
+    assert_eq!(response.into_inner().person.unwrap().id, 10);
+    assert_eq!(store.reads(), 1);
+}
+
+#[tokio::test]
+async fn test_missing_persons_are_not_cached() {
+    let (service, store, _) = setup();
+
+    let response = service.get_person(get_person_request(1, 10)).await.unwrap();
+    assert!(response.into_inner().person.is_none());
+
+    store.insert_person(person(1, 10), &[]);
+    let response = service.get_person(get_person_request(1, 10)).await.unwrap();
+    assert_eq!(response.into_inner().person.unwrap().id, 10);
+    assert_eq!(store.reads(), 2);
+}
+
+#[tokio::test]
+async fn test_get_persons_reports_missing_ids() {
+    let (service, store, _) = setup();
+    store.insert_person(person(1, 10), &[]);
+
+    let response = service
+        .get_persons(Request::new(GetPersonsRequest {
+            team_id: 1,
+            person_ids: vec![10, 20, 10],
+            read_options: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.persons.len(), 1);
+    assert_eq!(response.missing_ids, vec![20]);
+}
+
+#[tokio::test]
+async fn test_distinct_id_lookups_are_cached() {
+    let (service, store, _) = setup();
+    store.insert_person(person(1, 10), &["user-a", "user-b"]);
+
+    service
+        .get_person_by_distinct_id(get_by_distinct_id_request(1, "user-a"))
+        .await
+        .unwrap();
+    service
+        .get_person_by_distinct_id(get_by_distinct_id_request(1, "user-a"))
+        .await
+        .unwrap();
+    // The person was cached by the distinct ID lookup
+    service.get_person(get_person_request(1, 10)).await.unwrap();
+    assert_eq!(store.reads(), 1);
+
+    let request = || {
+        Request::new(GetDistinctIdsForPersonRequest {
+            team_id: 1,
+            person_id: 10,
+            read_options: None,
+        })
+    };
+    let first = service
+        .get_distinct_ids_for_person(request())
+        .await
+        .unwrap();
+    let second = service
+        .get_distinct_ids_for_person(request())
+        .await
+        .unwrap();
+    assert_eq!(first.into_inner(), second.into_inner());
+    assert_eq!(store.reads(), 2);
+}
+
+#[tokio::test]
+async fn test_update_properties_writes_through_to_the_cache() {
+    let (service, store, cache) = setup();
+    store.insert_person(person(1, 10), &[]);
+    service.get_person(get_person_request(1, 10)).await.unwrap();
+
+    let response = service
+        .update_person_properties(Request::new(UpdatePersonPropertiesRequest {
+            team_id: 1,
+            person_id: 10,
+            set_properties: br#"{"plan": "scale"}"#.to_vec(),
+            set_once_properties: Vec::new(),
+            unset_properties: Vec::new(),
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let person = response.person.unwrap();
+    assert_eq!(person.version, 1);
+    let properties: Value = serde_json::from_slice(&person.properties).unwrap();
+    assert_eq!(properties, json!({"plan": "scale"}));
+
+    let cached = cache.get_person(1, 10).unwrap();
+    assert_eq!(cached.version, Some(1));
+    assert_eq!(cached.properties, json!({"plan": "scale"}));
+}
+
+#[tokio::test]
+async fn test_update_properties_validates_input() {
+    let (service, store, _) = setup();
+    store.insert_person(person(1, 10), &[]);
+
+    let request = |team_id, set_properties: &[u8]| {
+        Request::new(UpdatePersonPropertiesRequest {
+            team_id,
+            person_id: 10,
+            set_properties: set_properties.to_vec(),
+            set_once_properties: Vec::new(),
+            unset_properties: Vec::new(),
+        })
+    };
+
+    let status = service
+        .update_person_properties(request(1, b"[1, 2]"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = service
+        .update_person_properties(request(1, b"{not json"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::InvalidArgument);
+
+    let status = service
+        .update_person_properties(request(2, b"{}"))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_merge_updates_cached_distinct_ids_and_drops_deleted_persons() {
+    let (service, store, cache) = setup();
+    store.insert_person(person(1, 10), &["target"]);
+    store.insert_person(person(1, 20), &["source"]);
+
+    // Warm the cache with the pre-merge state
+    for distinct_id in ["target", "source"] {
+        service
+            .get_person_by_distinct_id(get_by_distinct_id_request(1, distinct_id))
+            .await
+            .unwrap();
+    }
+
+    let response = service
+        .merge_distinct_ids(Request::new(MergeDistinctIdsRequest {
+            team_id: 1,
+            target_distinct_id: "target".to_string(),
+            source_distinct_ids: vec!["source".to_string(), "new".to_string()],
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert_eq!(response.person.unwrap().id, 10);
+    assert_eq!(response.deleted_person_ids, vec![20]);
+
+    assert!(cache.get_person(1, 20).is_none());
+    let reads = store.reads();
+    for distinct_id in ["target", "source", "new"] {
+        let response = service
+            .get_person_by_distinct_id(get_by_distinct_id_request(1, distinct_id))
+            .await
+            .unwrap();
+        assert_eq!(response.into_inner().person.unwrap().id, 10);
+    }
+    assert_eq!(store.reads(), reads);
+}
+
+#[tokio::test]
+async fn test_merge_with_unknown_target_returns_not_found() {
+    let (service, _, _) = setup();
+
+    let status = service
+        .merge_distinct_ids(Request::new(MergeDistinctIdsRequest {
+            team_id: 1,
+            target_distinct_id: "missing".to_string(),
+            source_distinct_ids: vec!["source".to_string()],
+        }))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_store_errors_on_cache_miss_return_unavailable() {
+    let cache = Arc::new(PersonCache::new(4, 1000));
+    cache.upsert_person(person(1, 10));
+    let service =
+        PersonHogLeaderService::new(cache, Arc::new(FailingStore::with_connection_error()));
+
+    // Cached reads don't touch the store
+    service.get_person(get_person_request(1, 10)).await.unwrap();
+
+    let status = service
+        .get_person(get_person_request(1, 20))
+        .await
+        .unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert!(status.message().contains("connection refused"));
+}
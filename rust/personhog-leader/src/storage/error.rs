@@ -0,0 +1,30 @@
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+pub enum StorageError {
+    /// Connection-level errors (network, TLS, authentication)
+    #[error("Database connection error: {0}")]
+    Connection(String),
+
+    /// Query execution errors (SQL errors, constraint violations)
+    #[error("Database query error: {0}")]
+    Query(String),
+
+    /// Connection pool exhausted or closed
+    #[error("Database pool exhausted")]
+    PoolExhausted,
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+impl From<sqlx::Error> for StorageError {
+    fn from(err: sqlx::Error) -> Self {
+        match &err {
+            sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed => StorageError::PoolExhausted,
+
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) => StorageError::Connection(err.to_string()),
+
+            _ => StorageError::Query(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+pub mod error;
+pub mod postgres;
+pub mod types;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+pub use error::{StorageError, StorageResult};
+pub use types::{DistinctIdWithVersion, MergeResult, Person, PropertyUpdate};
+
+/// Durable storage for person data. The leader reads through to this on cache misses,
+/// and writes through to it before updating the cache.
+#[async_trait]
+pub trait PersonStore: Send + Sync {
+    // Lookups
+
+    async fn get_person_by_id(&self, team_id: i64, person_id: i64)
+        -> StorageResult<Option<Person>>;
+
+    async fn get_person_by_uuid(&self, team_id: i64, uuid: Uuid) -> StorageResult<Option<Person>>;
+
+    async fn get_person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> StorageResult<Option<Person>>;
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> StorageResult<Vec<DistinctIdWithVersion>>;
+
+    // Writes
+
+    /// Apply a property update to a person, bumping its version if anything changed.
+    /// Returns None if the person doesn't exist.
+    async fn update_person_properties(
+        &self,
+        team_id: i64,
+        person_id: i64,
+        update: &PropertyUpdate,
+    ) -> StorageResult<Option<Person>>;
+
+    /// Point each source distinct ID at the person owning the target distinct ID, creating
+    /// any source distinct IDs that don't exist yet. Returns None if the target distinct ID
+    /// doesn't exist.
+    async fn merge_distinct_ids(
+        &self,
+        team_id: i64,
+        target_distinct_id: &str,
+        source_distinct_ids: &[String],
+    ) -> StorageResult<Option<MergeResult>>;
+}
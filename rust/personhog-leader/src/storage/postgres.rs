@@ -0,0 +1,441 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use serde_json::Value;
+use sqlx::postgres::PgPool;
+use sqlx::{FromRow, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::error::StorageResult;
+use super::types::{DistinctIdWithVersion, MergeResult, Person, PropertyUpdate};
+use super::PersonStore;
+
+pub(crate) const DB_QUERY_DURATION: &str = "personhog_leader_db_query_duration_ms";
+
+const PERSON_COLUMNS: &str =
+    "p.id, p.uuid, p.team_id, p.properties, p.properties_last_updated_at, \
+     p.properties_last_operation, p.created_at, p.version, p.is_identified, p.is_user_id";
+
+#[derive(Debug, Clone, FromRow)]
+struct PersonRow {
+    id: i64,
+    uuid: Uuid,
+    team_id: i32,
+    properties: Value,
+    properties_last_updated_at: Option<Value>,
+    properties_last_operation: Option<Value>,
+    created_at: chrono::DateTime<chrono::Utc>,
+    version: Option<i64>,
+    is_identified: bool,
+    is_user_id: Option<i32>,
+}
+
+impl From<PersonRow> for Person {
+    fn from(row: PersonRow) -> Self {
+        Person {
+            id: row.id,
+            uuid: row.uuid,
+            team_id: row.team_id.into(),
+            properties: row.properties,
+            properties_last_updated_at: row.properties_last_updated_at,
+            properties_last_operation: row.properties_last_operation,
+            created_at: row.created_at,
+            version: row.version,
+            is_identified: row.is_identified,
+            is_user_id: row.is_user_id.map(|v| v != 0),
+        }
+    }
+}
+
+/// Postgres implementation of the person store. All reads and writes go to the primary,
+/// since the leader is the source of truth for strongly consistent person data.
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+async fn lock_person(
+    tx: &mut Transaction<'_, Postgres>,
+    team_id: i64,
+    person_id: i64,
+) -> StorageResult<Option<Person>> {
+    let row = sqlx::query_as::<_, PersonRow>(&format!(
+        "SELECT {PERSON_COLUMNS} FROM posthog_person p WHERE p.team_id = $1 AND p.id = $2 FOR UPDATE"
+    ))
+    .bind(team_id)
+    .bind(person_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(row.map(Person::from))
+}
+
+// Locks persons in ID order, so transactions locking overlapping sets of persons can't deadlock
+async fn lock_persons(
+    tx: &mut Transaction<'_, Postgres>,
+    team_id: i64,
+    person_ids: &[i64],
+) -> StorageResult<Vec<Person>> {
+    let rows = sqlx::query_as::<_, PersonRow>(&format!(
+        "SELECT {PERSON_COLUMNS} FROM posthog_person p WHERE p.team_id = $1 AND p.id = ANY($2) \
+         ORDER BY p.id FOR UPDATE"
+    ))
+    .bind(team_id)
+    .bind(person_ids)
+    .fetch_all(&mut **tx)
+    .await?;
+    Ok(rows.into_iter().map(Person::from).collect())
+}
+
+async fn save_person_properties(
+    tx: &mut Transaction<'_, Postgres>,
+    person: &Person,
+) -> StorageResult<Person> {
+    let row = sqlx::query_as::<_, PersonRow>(&format!(
+        "UPDATE posthog_person p SET properties = $3, version = COALESCE(p.version, 0) + 1 \
+         WHERE p.team_id = $1 AND p.id = $2 RETURNING {PERSON_COLUMNS}"
+    ))
+    .bind(person.team_id)
+    .bind(person.id)
+    .bind(&person.properties)
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(row.into())
+}
+
+/// Delete a person that has been merged away, moving its feature flag hash key overrides
+/// to the target person where the target doesn't already have one for that flag.
+async fn delete_merged_person(
+    tx: &mut Transaction<'_, Postgres>,
+    team_id: i64,
+    person_id: i64,
+    target_person_id: i64,
+) -> StorageResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE posthog_featureflaghashkeyoverride
+        SET person_id = $3
+        WHERE team_id = $1 AND person_id = $2
+          AND feature_flag_key NOT IN (
+            SELECT feature_flag_key FROM posthog_featureflaghashkeyoverride
+            WHERE team_id = $1 AND person_id = $3
+          )
+        "#,
+    )
+    .bind(team_id)
+    .bind(person_id)
+    .bind(target_person_id)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query(
+        "DELETE FROM posthog_featureflaghashkeyoverride WHERE team_id = $1 AND person_id = $2",
+    )
+    .bind(team_id)
+    .bind(person_id)
+    .execute(&mut **tx)
+    .await?;
+
+    // Cohort membership is recalculated for the merged person, so stale rows are just dropped
+    sqlx::query("DELETE FROM posthog_cohortpeople WHERE person_id = $1")
+        .bind(person_id)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query("DELETE FROM posthog_person WHERE team_id = $1 AND id = $2")
+        .bind(team_id)
+        .bind(person_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl PersonStore for PostgresStore {
+    async fn get_person_by_id(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> StorageResult<Option<Person>> {
+        let labels = [("operation".to_string(), "get_person_by_id".to_string())];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let row = sqlx::query_as::<_, PersonRow>(&format!(
+            "SELECT {PERSON_COLUMNS} FROM posthog_person p WHERE p.team_id = $1 AND p.id = $2"
+        ))
+        .bind(team_id)
+        .bind(person_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Person::from))
+    }
+
+    async fn get_person_by_uuid(&self, team_id: i64, uuid: Uuid) -> StorageResult<Option<Person>> {
+        let labels = [("operation".to_string(), "get_person_by_uuid".to_string())];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let row = sqlx::query_as::<_, PersonRow>(&format!(
+            "SELECT {PERSON_COLUMNS} FROM posthog_person p WHERE p.team_id = $1 AND p.uuid = $2"
+        ))
+        .bind(team_id)
+        .bind(uuid)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Person::from))
+    }
+
+    async fn get_person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> StorageResult<Option<Person>> {
+        let labels = [(
+            "operation".to_string(),
+            "get_person_by_distinct_id".to_string(),
+        )];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let row = sqlx::query_as::<_, PersonRow>(&format!(
+            r#"
+            SELECT {PERSON_COLUMNS}
+            FROM posthog_person p
+            JOIN posthog_persondistinctid d ON d.person_id = p.id AND d.team_id = p.team_id
+            WHERE d.team_id = $1 AND d.distinct_id = $2
+            "#
+        ))
+        .bind(team_id)
+        .bind(distinct_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(Person::from))
+    }
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> StorageResult<Vec<DistinctIdWithVersion>> {
+        let labels = [(
+            "operation".to_string(),
+            "get_distinct_ids_for_person".to_string(),
+        )];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let rows = sqlx::query_as::<_, (String, Option<i64>)>(
+            r#"
+            SELECT distinct_id, version
+            FROM posthog_persondistinctid
+            WHERE team_id = $1 AND person_id = $2
+            "#,
+        )
+        .bind(team_id)
+        .bind(person_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(distinct_id, version)| DistinctIdWithVersion {
+                distinct_id,
+                version,
+            })
+            .collect())
+    }
+
+    async fn update_person_properties(
+        &self,
+        team_id: i64,
+        person_id: i64,
+        update: &PropertyUpdate,
+    ) -> StorageResult<Option<Person>> {
+        let labels = [(
+            "operation".to_string(),
+            "update_person_properties".to_string(),
+        )];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let mut tx = self.pool.begin().await?;
+        let Some(mut person) = lock_person(&mut tx, team_id, person_id).await? else {
+            return Ok(None);
+        };
+
+        if !update.apply(&mut person.properties) {
+            // Nothing changed, so there's no new version to write
+            tx.commit().await?;
+            return Ok(Some(person));
+        }
+
+        let person = save_person_properties(&mut tx, &person).await?;
+        tx.commit().await?;
+        Ok(Some(person))
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        team_id: i64,
+        target_distinct_id: &str,
+        source_distinct_ids: &[String],
+    ) -> StorageResult<Option<MergeResult>> {
+        let labels = [("operation".to_string(), "merge_distinct_ids".to_string())];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let mut tx = self.pool.begin().await?;
+
+        // Rows are locked in a stable order - distinct IDs, then persons, each sorted - so
+        // concurrent merges over the same rows wait on each other rather than deadlocking
+        let mut locked_distinct_ids: Vec<&str> = source_distinct_ids
+            .iter()
+            .map(String::as_str)
+            .chain([target_distinct_id])
+            .collect();
+        locked_distinct_ids.sort_unstable();
+        locked_distinct_ids.dedup();
+
+        let owners: HashMap<String, i64> = sqlx::query_as::<_, (String, i64)>(
+            r#"
+            SELECT distinct_id, person_id FROM posthog_persondistinctid
+            WHERE team_id = $1 AND distinct_id = ANY($2)
+            ORDER BY distinct_id
+            FOR UPDATE
+            "#,
+        )
+        .bind(team_id)
+        .bind(&locked_distinct_ids)
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .collect();
+        let Some(&target_person_id) = owners.get(target_distinct_id) else {
+            return Ok(None);
+        };
+
+        let mut to_move = Vec::new();
+        let mut to_insert = Vec::new();
+        let mut moved = Vec::new();
+        let mut previous_person_ids = Vec::new();
+        let mut seen = HashSet::new();
+        for distinct_id in source_distinct_ids {
+            if distinct_id == target_distinct_id || !seen.insert(distinct_id.as_str()) {
+                continue;
+            }
+            match owners.get(distinct_id) {
+                Some(&person_id) if person_id == target_person_id => continue,
+                Some(&person_id) => {
+                    if !previous_person_ids.contains(&person_id) {
+                        previous_person_ids.push(person_id);
+                    }
+                    to_move.push(distinct_id.as_str());
+                }
+                None => to_insert.push(distinct_id.as_str()),
+            }
+            moved.push(distinct_id.as_str());
+        }
+
+        let person_ids: Vec<i64> = previous_person_ids
+            .iter()
+            .copied()
+            .chain([target_person_id])
+            .collect();
+        let mut persons: HashMap<i64, Person> = lock_persons(&mut tx, team_id, &person_ids)
+            .await?
+            .into_iter()
+            .map(|person| (person.id, person))
+            .collect();
+        let Some(mut target) = persons.remove(&target_person_id) else {
+            return Ok(None);
+        };
+
+        let mut versions: HashMap<String, Option<i64>> = HashMap::new();
+        if !to_move.is_empty() {
+            versions.extend(
+                sqlx::query_as::<_, (String, Option<i64>)>(
+                    r#"
+                    UPDATE posthog_persondistinctid
+                    SET person_id = $3, version = COALESCE(version, 0) + 1
+                    WHERE team_id = $1 AND distinct_id = ANY($2)
+                    RETURNING distinct_id, version
+                    "#,
+                )
+                .bind(team_id)
+                .bind(&to_move)
+                .bind(target.id)
+                .fetch_all(&mut *tx)
+                .await?,
+            );
+        }
+        if !to_insert.is_empty() {
+            sqlx::query(
+                r#"
+                INSERT INTO posthog_persondistinctid (distinct_id, person_id, team_id, version)
+                SELECT distinct_id, $2, $3, 0 FROM UNNEST($1::varchar[]) AS distinct_id
+                "#,
+            )
+            .bind(&to_insert)
+            .bind(target.id)
+            .bind(team_id)
+            .execute(&mut *tx)
+            .await?;
+            versions.extend(to_insert.iter().map(|d| (d.to_string(), Some(0))));
+        }
+
+        let moved_distinct_ids: Vec<DistinctIdWithVersion> = moved
+            .into_iter()
+            .map(|distinct_id| DistinctIdWithVersion {
+                distinct_id: distinct_id.to_string(),
+                version: versions.get(distinct_id).copied().flatten(),
+            })
+            .collect();
+
+        // Persons left without any distinct IDs are merged into the target and deleted
+        let mut deleted_person_ids = Vec::new();
+        let mut merged_properties = PropertyUpdate::default();
+        let remaining: HashSet<i64> = if previous_person_ids.is_empty() {
+            HashSet::new()
+        } else {
+            sqlx::query_scalar(
+                "SELECT DISTINCT person_id FROM posthog_persondistinctid WHERE team_id = $1 AND person_id = ANY($2)",
+            )
+            .bind(team_id)
+            .bind(&previous_person_ids)
+            .fetch_all(&mut *tx)
+            .await?
+            .into_iter()
+            .collect()
+        };
+        for person_id in previous_person_ids {
+            if remaining.contains(&person_id) {
+                continue;
+            }
+
+            if let Some(person) = persons.remove(&person_id) {
+                if let Value::Object(properties) = person.properties {
+                    for (key, value) in properties {
+                        merged_properties.set_once.entry(key).or_insert(value);
+                    }
+                }
+            }
+            delete_merged_person(&mut tx, team_id, person_id, target.id).await?;
+            deleted_person_ids.push(person_id);
+        }
+
+        let properties_changed = merged_properties.apply(&mut target.properties);
+        if properties_changed || !moved_distinct_ids.is_empty() {
+            target = save_person_properties(&mut tx, &target).await?;
+        }
+
+        tx.commit().await?;
+        Ok(Some(MergeResult {
+            person: target,
+            moved_distinct_ids,
+            deleted_person_ids,
+        }))
+    }
+}
@@ -0,0 +1,135 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Person {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub team_id: i64,
+    pub properties: Value,
+    pub properties_last_updated_at: Option<Value>,
+    pub properties_last_operation: Option<Value>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub version: Option<i64>,
+    pub is_identified: bool,
+    pub is_user_id: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DistinctIdWithVersion {
+    pub distinct_id: String,
+    pub version: Option<i64>,
+}
+
+/// A set of $set, $set_once and $unset style changes to a person's properties.
+#[derive(Debug, Clone, Default)]
+pub struct PropertyUpdate {
+    pub set: Map<String, Value>,
+    pub set_once: Map<String, Value>,
+    pub unset: Vec<String>,
+}
+
+impl PropertyUpdate {
+    /// Apply the update to a properties object, returning whether anything changed.
+    /// Non-object properties are replaced with an empty object first.
+    pub fn apply(&self, properties: &mut Value) -> bool {
+        if !properties.is_object() {
+            *properties = Value::Object(Map::new());
+        }
+        let Value::Object(properties) = properties else {
+            unreachable!("properties was just made an object");
+        };
+
+        let mut changed = false;
+        for (key, value) in &self.set_once {
+            if !properties.contains_key(key) {
+                properties.insert(key.clone(), value.clone());
+                changed = true;
+            }
+        }
+        for (key, value) in &self.set {
+            if properties.get(key) != Some(value) {
+                properties.insert(key.clone(), value.clone());
+                changed = true;
+            }
+        }
+        for key in &self.unset {
+            changed |= properties.remove(key).is_some();
+        }
+        changed
+    }
+}
+
+/// The outcome of merging distinct IDs into a target person.
+#[derive(Debug, Clone)]
+pub struct MergeResult {
+    /// The target person, after any properties were merged in from deleted persons
+    pub person: Person,
+    /// Distinct IDs that now point at the target person, with their new versions
+    pub moved_distinct_ids: Vec<DistinctIdWithVersion>,
+    /// Persons that were left without distinct IDs, and so were deleted
+    pub deleted_person_ids: Vec<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn update(set: Value, set_once: Value, unset: &[&str]) -> PropertyUpdate {
+        PropertyUpdate {
+            set: set.as_object().unwrap().clone(),
+            set_once: set_once.as_object().unwrap().clone(),
+            unset: unset.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_set_overwrites_and_set_once_does_not() {
+        let mut properties = json!({"plan": "free", "signup": "web"});
+        let changed = update(
+            json!({"plan": "scale"}),
+            json!({"signup": "mobile", "referrer": "ad"}),
+            &[],
+        )
+        .apply(&mut properties);
+
+        assert!(changed);
+        assert_eq!(
+            properties,
+            json!({"plan": "scale", "signup": "web", "referrer": "ad"})
+        );
+    }
+
+    #[test]
+    fn test_unset_removes_properties() {
+        let mut properties = json!({"plan": "free", "email": "a@b.com"});
+        let changed = update(json!({}), json!({}), &["email", "missing"]).apply(&mut properties);
+
+        assert!(changed);
+        assert_eq!(properties, json!({"plan": "free"}));
+    }
+
+    #[test]
+    fn test_no_op_update_reports_unchanged() {
+        let mut properties = json!({"plan": "free"});
+        let changed = update(
+            json!({"plan": "free"}),
+            json!({"plan": "scale"}),
+            &["missing"],
+        )
+        .apply(&mut properties);
+
+        assert!(!changed);
+        assert_eq!(properties, json!({"plan": "free"}));
+    }
+
+    #[test]
+    fn test_non_object_properties_are_replaced() {
+        let mut properties = Value::Null;
+        assert!(update(json!({"plan": "free"}), json!({}), &[]).apply(&mut properties));
+        assert_eq!(properties, json!({"plan": "free"}));
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use common_kafka::config::{ConsumerConfig, KafkaConfig};
+use common_kafka::kafka_consumer::SingleTopicConsumer;
+use common_kafka::test::create_mock_kafka;
+use personhog_leader::cache::PersonCache;
+use personhog_leader::consumer::{consume_person_updates, PersonUpdate};
+use personhog_leader::storage::Person;
+use rdkafka::producer::FutureRecord;
+use serde_json::json;
+use uuid::Uuid;
+
+const TOPIC: &str = "personhog_person_updates";
+
+fn person(id: i64, version: i64) -> Person {
+    Person {
+        id,
+        uuid: Uuid::now_v7(),
+        team_id: 1,
+        properties: json!({"version": version}),
+        properties_last_updated_at: None,
+        properties_last_operation: None,
+        created_at: Utc::now(),
+        version: Some(version),
+        is_identified: false,
+        is_user_id: None,
+    }
+}
+
+#[tokio::test]
+async fn test_person_updates_are_applied_to_the_cache() {
+    let (cluster, producer) = create_mock_kafka().await;
+    cluster
+        .create_topic(TOPIC, 1, 1)
+        .expect("failed to create mock topic");
+
+    let updates = [
+        PersonUpdate::Person(person(10, 2)),
+        // Stale, so ignored
+        PersonUpdate::Person(person(10, 1)),
+        PersonUpdate::DistinctId {
+            team_id: 1,
+            distinct_id: "user-a".to_string(),
+            person_id: 10,
+            version: Some(0),
+        },
+        PersonUpdate::Person(person(20, 0)),
+        PersonUpdate::PersonDeleted {
+            team_id: 1,
+            person_id: 20,
+        },
+        PersonUpdate::Person(person(30, 0)),
+    ];
+    for update in &updates {
+        let payload = serde_json::to_string(update).unwrap();
+        producer
+            .send(
+                FutureRecord::<(), str>::to(TOPIC).payload(&payload),
+                Duration::from_secs(5),
+            )
+            .await
+            .expect("failed to produce update");
+    }
+
+    let kafka_config = KafkaConfig {
+        kafka_producer_linger_ms: 0,
+        kafka_producer_queue_mib: 50,
+        kafka_message_timeout_ms: 5000,
+        kafka_compression_codec: "none".to_string(),
+        kafka_hosts: cluster.bootstrap_servers(),
+        kafka_tls: false,
+        kafka_producer_queue_messages: 1000,
+    };
+    let consumer_config = ConsumerConfig {
+        kafka_consumer_group: "personhog-leader-test".to_string(),
+        kafka_consumer_topic: TOPIC.to_string(),
+        kafka_consumer_offset_reset: "earliest".to_string(),
+        kafka_consumer_auto_commit: true,
+        kafka_consumer_auto_commit_interval_ms: 5000,
+    };
+    let consumer =
+        SingleTopicConsumer::new(kafka_config, consumer_config).expect("failed to create consumer");
+
+    let cache = Arc::new(PersonCache::new(4, 1000));
+    let handle = tokio::spawn(consume_person_updates(consumer, cache.clone()));
+
+    // Updates are applied in order, so once the last one is in so is everything else
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while cache.get_person(1, 30).is_none() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "timed out waiting for updates"
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    handle.abort();
+
+    let cached = cache.get_person(1, 10).unwrap();
+    assert_eq!(cached.version, Some(2));
+    assert_eq!(cache.get_person_by_distinct_id(1, "user-a").unwrap().id, 10);
+    assert!(cache.get_person(1, 20).is_none());
+}
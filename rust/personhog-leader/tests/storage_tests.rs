@@ -0,0 +1,206 @@
+mod common;
+
+use common::TestContext;
+use personhog_leader::storage::PropertyUpdate;
+use serde_json::json;
+
+#[tokio::test]
+async fn test_lookups_by_id_uuid_and_distinct_id() {
+    let ctx = TestContext::new().await;
+    let person = ctx
+        .insert_person(&["user-a", "user-b"], json!({"plan": "free"}))
+        .await
+        .expect("Failed to insert person");
+
+    let by_id = ctx
+        .store
+        .get_person_by_id(ctx.team_id, person.id)
+        .await
+        .expect("Failed to get person")
+        .expect("Person not found");
+    assert_eq!(by_id.uuid, person.uuid);
+    assert_eq!(by_id.properties, json!({"plan": "free"}));
+
+    let by_uuid = ctx
+        .store
+        .get_person_by_uuid(ctx.team_id, person.uuid)
+        .await
+        .expect("Failed to get person")
+        .expect("Person not found");
+    assert_eq!(by_uuid.id, person.id);
+
+    let by_distinct_id = ctx
+        .store
+        .get_person_by_distinct_id(ctx.team_id, "user-b")
+        .await
+        .expect("Failed to get person")
+        .expect("Person not found");
+    assert_eq!(by_distinct_id.id, person.id);
+
+    let mut distinct_ids: Vec<_> = ctx
+        .store
+        .get_distinct_ids_for_person(ctx.team_id, person.id)
+        .await
+        .expect("Failed to get distinct ids")
+        .into_iter()
+        .map(|d| d.distinct_id)
+        .collect();
+    distinct_ids.sort();
+    assert_eq!(distinct_ids, vec!["user-a", "user-b"]);
+
+    ctx.cleanup().await.ok();
+}
+
+#[tokio::test]
+async fn test_update_person_properties_bumps_version() {
+    let ctx = TestContext::new().await;
+    let person = ctx
+        .insert_person(&["user-a"], json!({"plan": "free", "email": "a@b.com"}))
+        .await
+        .expect("Failed to insert person");
+
+    let update = PropertyUpdate {
+        set: json!({"plan": "scale"}).as_object().unwrap().clone(),
+        set_once: json!({"plan": "ignored", "signup": "web"})
+            .as_object()
+            .unwrap()
+            .clone(),
+        unset: vec!["email".to_string()],
+    };
+    let updated = ctx
+        .store
+        .update_person_properties(ctx.team_id, person.id, &update)
+        .await
+        .expect("Failed to update person")
+        .expect("Person not found");
+
+    assert_eq!(updated.version, Some(1));
+    assert_eq!(
+        updated.properties,
+        json!({"plan": "scale", "signup": "web"})
+    );
+
+    // Re-applying the same update changes nothing, so the version stays put
+    let unchanged = ctx
+        .store
+        .update_person_properties(ctx.team_id, person.id, &update)
+        .await
+        .expect("Failed to update person")
+        .expect("Person not found");
+    assert_eq!(unchanged.version, Some(1));
+
+    let missing = ctx
+        .store
+        .update_person_properties(ctx.team_id, 999_999_999, &update)
+        .await
+        .expect("Failed to update person");
+    assert!(missing.is_none());
+
+    ctx.cleanup().await.ok();
+}
+
+#[tokio::test]
+async fn test_merge_distinct_ids_deletes_orphaned_persons() {
+    let ctx = TestContext::new().await;
+    let target = ctx
+        .insert_person(&["target"], json!({"plan": "scale"}))
+        .await
+        .expect("Failed to insert person");
+    let source = ctx
+        .insert_person(&["source"], json!({"plan": "free", "signup": "web"}))
+        .await
+        .expect("Failed to insert person");
+    let shared = ctx
+        .insert_person(&["shared-a", "shared-b"], json!({}))
+        .await
+        .expect("Failed to insert person");
+
+    let result = ctx
+        .store
+        .merge_distinct_ids(
+            ctx.team_id,
+            "target",
+            &[
+                "source".to_string(),
+                "shared-a".to_string(),
+                "brand-new".to_string(),
+            ],
+        )
+        .await
+        .expect("Failed to merge")
+        .expect("Target not found");
+
+    assert_eq!(result.person.id, target.id);
+    assert_eq!(result.deleted_person_ids, vec![source.id]);
+    // Merged properties never overwrite the target's own
+    assert_eq!(
+        result.person.properties,
+        json!({"plan": "scale", "signup": "web"})
+    );
+
+    let mut moved: Vec<_> = result
+        .moved_distinct_ids
+        .iter()
+        .map(|d| (d.distinct_id.as_str(), d.version))
+        .collect();
+    moved.sort();
+    assert_eq!(
+        moved,
+        vec![
+            ("brand-new", Some(0)),
+            ("shared-a", Some(1)),
+            ("source", Some(1))
+        ]
+    );
+
+    let deleted = ctx
+        .store
+        .get_person_by_id(ctx.team_id, source.id)
+        .await
+        .expect("Failed to get person");
+    assert!(deleted.is_none());
+
+    // The person that kept a distinct ID survives
+    let remaining = ctx
+        .store
+        .get_person_by_distinct_id(ctx.team_id, "shared-b")
+        .await
+        .expect("Failed to get person")
+        .expect("Person not found");
+    assert_eq!(remaining.id, shared.id);
+
+    let missing = ctx
+        .store
+        .merge_distinct_ids(ctx.team_id, "missing", &["source".to_string()])
+        .await
+        .expect("Failed to merge");
+    assert!(missing.is_none());
+
+    ctx.cleanup().await.ok();
+}
+
+#[tokio::test]
+async fn test_opposite_concurrent_merges_dont_deadlock() {
+    let ctx = TestContext::new().await;
+    for round in 0..10 {
+        let (a, b) = (format!("a-{round}"), format!("b-{round}"));
+        ctx.insert_person(&[&a, &format!("{a}-other")], json!({}))
+            .await
+            .expect("Failed to insert person");
+        ctx.insert_person(&[&b, &format!("{b}-other")], json!({}))
+            .await
+            .expect("Failed to insert person");
+
+        // Each merge locks the other's target, so locking in argument order would deadlock
+        let (first, second) = tokio::join!(
+            ctx.store
+                .merge_distinct_ids(ctx.team_id, &a, &[b.clone(), format!("{b}-other")]),
+            ctx.store
+                .merge_distinct_ids(ctx.team_id, &b, &[a.clone(), format!("{a}-other")]),
+        );
+        first.expect("First merge failed");
+        second.expect("Second merge failed");
+    }
+
+    ctx.cleanup().await.ok();
+}
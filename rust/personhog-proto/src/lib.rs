@@ -9,6 +9,11 @@ pub mod personhog {
             tonic::include_proto!("personhog.replica.v1");
         }
     }
+    pub mod leader {
+        pub mod v1 {
+            tonic::include_proto!("personhog.leader.v1");
+        }
+    }
     pub mod service {
         pub mod v1 {
             tonic::include_proto!("personhog.service.v1");
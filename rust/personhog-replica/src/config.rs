@@ -1,8 +1,12 @@
+use common_kafka::config::{ConsumerConfig, KafkaConfig};
 use envconfig::Envconfig;
 use std::net::SocketAddr;
 use std::time::Duration;
 
-#[derive(Envconfig, Clone, Debug)]
+use crate::service::ScanLimits;
+use crate::storage::cache::{CacheConfig, CacheLimits};
+
+#[derive(Envconfig, Clone)]
 pub struct Config {
     #[envconfig(default = "127.0.0.1:50051")]
     pub grpc_address: SocketAddr,
@@ -37,9 +41,117 @@ pub struct Config {
 
     #[envconfig(default = "9100")]
     pub metrics_port: u16,
+
+    /// Page size for person scans that don't request one
+    #[envconfig(default = "500")]
+    pub scan_default_page_size: i64,
+
+    #[envconfig(default = "1000")]
+    pub scan_max_page_size: i64,
+
+    /// Rows each scan stream may read per second, so batch jobs can't starve point
+    /// lookups. 0 disables the limit.
+    #[envconfig(default = "5000")]
+    pub scan_max_rows_per_second: u32,
+
+    /// Cache persons, groups and group type mappings in memory, invalidated from change events
+    #[envconfig(default = "false")]
+    pub cache_enabled: bool,
+
+    /// Upper bound on the entries any one team can hold in each cache, so a single large
+    /// team can't evict everyone else's hot data
+    #[envconfig(default = "10000")]
+    pub cache_max_entries_per_team: u64,
+
+    #[envconfig(default = "200000")]
+    pub cache_person_max_entries: u64,
+
+    #[envconfig(default = "60")]
+    pub cache_person_ttl_secs: u64,
+
+    #[envconfig(default = "100000")]
+    pub cache_group_max_entries: u64,
+
+    #[envconfig(default = "300")]
+    pub cache_group_ttl_secs: u64,
+
+    #[envconfig(default = "50000")]
+    pub cache_group_type_mapping_max_entries: u64,
+
+    /// No change events cover group type mappings, so this is the only thing bounding how
+    /// long a newly created group type stays invisible
+    #[envconfig(default = "60")]
+    pub cache_group_type_mapping_ttl_secs: u64,
+
+    /// Topic carrying group change events. Person change events are read from the
+    /// consumer topic (KAFKA_CONSUMER_TOPIC).
+    #[envconfig(default = "personhog_group_updates")]
+    pub cache_group_updates_topic: String,
+
+    /// Stable identity of this replica, naming its cache invalidation consumer group.
+    /// Defaults to the StatefulSet ordinal at the end of HOSTNAME.
+    #[envconfig(from = "REPLICA_ID")]
+    pub replica_id: Option<String>,
+
+    // supplied by k8s deploy env
+    #[envconfig(from = "HOSTNAME")]
+    pub pod_hostname: Option<String>,
+
+    #[envconfig(nested = true)]
+    pub kafka: KafkaConfig,
+
+    #[envconfig(nested = true)]
+    pub consumer: ConsumerConfig,
 }
 
 impl Config {
+    pub fn init_with_defaults() -> Result<Self, envconfig::Error> {
+        ConsumerConfig::set_defaults("personhog-replica-cache", "personhog_person_updates", true);
+        // The cache starts empty, so there are no older invalidations worth replaying
+        if std::env::var("KAFKA_CONSUMER_OFFSET_RESET").is_err() {
+            std::env::set_var("KAFKA_CONSUMER_OFFSET_RESET", "latest");
+        }
+        Config::init_from_env()
+    }
+
+    pub fn cache_config(&self) -> CacheConfig {
+        let limits = |max_entries, ttl_secs| CacheLimits {
+            max_entries,
+            max_entries_per_team: self.cache_max_entries_per_team,
+            ttl: Duration::from_secs(ttl_secs),
+        };
+        CacheConfig {
+            person: limits(self.cache_person_max_entries, self.cache_person_ttl_secs),
+            group: limits(self.cache_group_max_entries, self.cache_group_ttl_secs),
+            group_type_mapping: limits(
+                self.cache_group_type_mapping_max_entries,
+                self.cache_group_type_mapping_ttl_secs,
+            ),
+        }
+    }
+
+    /// Consumer group for this replica's cache invalidations. Every replica caches
+    /// independently, so each needs its own group to see every event. The group is reused
+    /// across restarts, rather than every pod leaving an orphaned group behind.
+    pub fn cache_consumer_group(&self) -> Option<String> {
+        let replica_id = match &self.replica_id {
+            Some(replica_id) => replica_id.as_str(),
+            None => statefulset_ordinal(self.pod_hostname.as_deref()?)?,
+        };
+        Some(format!(
+            "{}-{replica_id}",
+            self.consumer.kafka_consumer_group
+        ))
+    }
+
+    pub fn scan_limits(&self) -> ScanLimits {
+        ScanLimits {
+            default_page_size: self.scan_default_page_size,
+            max_page_size: self.scan_max_page_size,
+            max_rows_per_second: self.scan_max_rows_per_second,
+        }
+    }
+
     pub fn acquire_timeout(&self) -> Duration {
         Duration::from_secs(self.acquire_timeout_secs)
     }
@@ -69,3 +181,26 @@ impl Config {
         }
     }
 }
+
+/// The ordinal of a StatefulSet pod, e.g. "2" for "personhog-replica-2"
+fn statefulset_ordinal(hostname: &str) -> Option<&str> {
+    let (_, ordinal) = hostname.rsplit_once('-')?;
+    (!ordinal.is_empty() && ordinal.bytes().all(|b| b.is_ascii_digit())).then_some(ordinal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statefulset_ordinal() {
+        assert_eq!(statefulset_ordinal("personhog-replica-2"), Some("2"));
+        assert_eq!(statefulset_ordinal("personhog-replica-12"), Some("12"));
+        assert_eq!(
+            statefulset_ordinal("personhog-replica-7d9f8b6c4-x2x7q"),
+            None
+        );
+        assert_eq!(statefulset_ordinal("personhog-replica-"), None);
+        assert_eq!(statefulset_ordinal("localhost"), None);
+    }
+}
@@ -2,10 +2,11 @@ use std::sync::Arc;
 
 use axum::{routing::get, Router};
 use common_database::{get_pool_with_config, PoolConfig};
+use common_kafka::kafka_consumer::{RecvErr, SingleTopicConsumer};
 use common_metrics::setup_metrics_routes;
-use envconfig::Envconfig;
 use personhog_proto::personhog::replica::v1::person_hog_replica_server::PersonHogReplicaServer;
 use tokio::signal;
+use tokio::task::JoinSet;
 use tonic::transport::Server;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::fmt;
@@ -16,7 +17,10 @@ use tracing_subscriber::EnvFilter;
 use health::readiness_handler;
 use personhog_replica::config::Config;
 use personhog_replica::service::PersonHogReplicaService;
+use personhog_replica::storage::cache::invalidation::consume_invalidations;
+use personhog_replica::storage::cache::CachedStorage;
 use personhog_replica::storage::postgres::PostgresStorage;
+use personhog_replica::storage::FullStorage;
 
 common_alloc::used!();
 
@@ -73,9 +77,39 @@ async fn create_storage(config: &Config) -> Arc<PostgresStorage> {
     }
 }
 
+/// Wrap the storage in a cache, with a consumer per change event topic invalidating it
+fn create_cache(
+    config: &Config,
+    storage: Arc<dyn FullStorage>,
+) -> (Arc<CachedStorage>, JoinSet<RecvErr>) {
+    let cache = Arc::new(CachedStorage::new(storage, config.cache_config()));
+
+    let mut consumer_config = config.consumer.clone();
+    consumer_config.kafka_consumer_group = config
+        .cache_consumer_group()
+        .expect("Caching needs a stable replica ID: run as a StatefulSet or set REPLICA_ID");
+
+    let mut group_consumer_config = consumer_config.clone();
+    group_consumer_config.kafka_consumer_topic = config.cache_group_updates_topic.clone();
+
+    let mut consumers = JoinSet::new();
+    for consumer_config in [consumer_config, group_consumer_config] {
+        tracing::info!(
+            "Invalidating cache from topic {} as consumer group {}",
+            consumer_config.kafka_consumer_topic,
+            consumer_config.kafka_consumer_group
+        );
+        let consumer = SingleTopicConsumer::new(config.kafka.clone(), consumer_config)
+            .expect("Failed to create cache invalidation consumer");
+        consumers.spawn(consume_invalidations(consumer, cache.clone()));
+    }
+
+    (cache, consumers)
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::init_from_env().expect("Invalid configuration");
+    let config = Config::init_with_defaults().expect("Invalid configuration");
 
     // Initialize tracing
     let log_layer = fmt::layer()
@@ -96,6 +130,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("gRPC address: {}", config.grpc_address);
     tracing::info!("Metrics port: {}", config.metrics_port);
     tracing::info!("Storage backend: {}", config.storage_backend);
+    tracing::info!("Cache enabled: {}", config.cache_enabled);
 
     // Start HTTP server for metrics and health checks
     let metrics_port = config.metrics_port;
@@ -115,15 +150,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .expect("Metrics server error");
     });
 
-    let storage = create_storage(&config).await;
-    let service = PersonHogReplicaService::new(storage);
+    let storage: Arc<dyn FullStorage> = create_storage(&config).await;
+    let (storage, mut invalidation_consumers): (Arc<dyn FullStorage>, _) = if config.cache_enabled {
+        let (cache, consumers) = create_cache(&config, storage);
+        (cache, consumers)
+    } else {
+        (storage, JoinSet::new())
+    };
+    let service = PersonHogReplicaService::new(storage).with_scan_limits(config.scan_limits());
+
+    // Routers probe this to take unhealthy replicas out of rotation
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_serving::<PersonHogReplicaServer<PersonHogReplicaService>>()
+        .await;
 
     tracing::info!("Starting gRPC server on {}", config.grpc_address);
 
-    Server::builder()
+    let server = Server::builder()
+        .add_service(health_service)
         .add_service(PersonHogReplicaServer::new(service))
-        .serve_with_shutdown(config.grpc_address, shutdown_signal())
-        .await?;
+        .serve_with_shutdown(config.grpc_address, shutdown_signal());
+
+    if invalidation_consumers.is_empty() {
+        server.await?;
+        return Ok(());
+    }
+
+    // Without invalidations the cache would serve stale data, so stop serving if they stop
+    tokio::select! {
+        result = server => result?,
+        Some(result) = invalidation_consumers.join_next() => {
+            let err = result?;
+            tracing::error!("Cache invalidation consumer stopped: {}", err);
+            return Err(err.into());
+        }
+    }
 
     Ok(())
 }
@@ -1,5 +1,6 @@
 mod consistency;
 mod error;
+mod scan;
 mod types;
 
 #[cfg(test)]
@@ -24,8 +25,8 @@ use personhog_proto::personhog::types::v1::{
     GroupTypeMappingsByKey, GroupTypeMappingsResponse, GroupWithKey, GroupsResponse,
     HashKeyOverride, HashKeyOverrideContext as ProtoHashKeyOverrideContext, PersonDistinctIds,
     PersonWithDistinctIds, PersonWithTeamDistinctId, PersonsByDistinctIdsInTeamResponse,
-    PersonsByDistinctIdsResponse, PersonsResponse, TeamDistinctId, UpsertHashKeyOverridesRequest,
-    UpsertHashKeyOverridesResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    TeamDistinctId, UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
 };
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
@@ -34,19 +35,35 @@ use crate::storage::{self, FullStorage};
 
 use consistency::{reject_strong_consistency, to_storage_consistency};
 use error::log_and_convert_error;
+use scan::{scan_pages, ScanStream};
+
+pub use scan::ScanLimits;
 
 pub struct PersonHogReplicaService {
     storage: Arc<dyn FullStorage>,
+    scan_limits: ScanLimits,
 }
 
 impl PersonHogReplicaService {
     pub fn new(storage: Arc<dyn FullStorage>) -> Self {
-        Self { storage }
+        Self {
+            storage,
+            scan_limits: ScanLimits::default(),
+        }
+    }
+
+    /// Override the default page sizes and per-stream rate limit of the scan RPCs.
+    pub fn with_scan_limits(mut self, scan_limits: ScanLimits) -> Self {
+        self.scan_limits = scan_limits;
+        self
     }
 }
 
 #[tonic::async_trait]
 impl PersonHogReplica for PersonHogReplicaService {
+    type ScanPersonsStream = ScanStream;
+    type ScanCohortMembersStream = ScanStream;
+
     // ============================================================
     // Person lookups by ID/UUID
     // ============================================================
@@ -289,6 +306,35 @@ impl PersonHogReplica for PersonHogReplicaService {
         }))
     }
 
+    // ============================================================
+    // Scans
+    // ============================================================
+
+    async fn scan_persons(
+        &self,
+        request: Request<ScanPersonsRequest>,
+    ) -> Result<Response<Self::ScanPersonsStream>, Status> {
+        let req = request.into_inner();
+        reject_strong_consistency(&req.read_options)?;
+        let page_size = self.scan_limits.page_size(req.cursor, req.page_size)?;
+
+        let storage = self.storage.clone();
+        let team_id = req.team_id;
+        let stream = scan_pages(
+            "scan_persons",
+            move |cursor, limit| {
+                let storage = storage.clone();
+                async move { storage.scan_persons(team_id, cursor, limit).await }
+            },
+            req.cursor,
+            page_size,
+            req.property_keys,
+            self.scan_limits.max_rows_per_second,
+        );
+
+        Ok(Response::new(stream))
+    }
+
     // ============================================================
     // Feature Flag support
     // ============================================================
@@ -304,11 +350,10 @@ impl PersonHogReplica for PersonHogReplicaService {
         // 2. The caller needs the latest person existence state (e.g., for write validation)
         //
         // Note: This implementation queries person data on the primary database directly to attain strong consistency.
-        // When personhog-leader is implemented, person table data will be cached on leader pods.
-        // At that point, strong consistency for person data will require routing to the leader
-        // service rather than the primary database.
-        // Once that service is implemented and in use, using personhog-replica for this query will have to be
-        // re-assessed as its consistency guarantee will be broken
+        // Person table data is now cached on personhog-leader pods, which apply writes to the primary
+        // before updating their cache, so the primary is never behind the leader for person existence.
+        // If the leader ever defers its Postgres writes, using personhog-replica for this query will have
+        // to be re-assessed as its consistency guarantee will be broken
         let consistency = to_storage_consistency(&req.read_options);
 
         let results = self
@@ -413,6 +458,35 @@ impl PersonHogReplica for PersonHogReplicaService {
         }))
     }
 
+    async fn scan_cohort_members(
+        &self,
+        request: Request<ScanCohortMembersRequest>,
+    ) -> Result<Response<Self::ScanCohortMembersStream>, Status> {
+        let req = request.into_inner();
+        reject_strong_consistency(&req.read_options)?;
+        let page_size = self.scan_limits.page_size(req.cursor, req.page_size)?;
+
+        let storage = self.storage.clone();
+        let (team_id, cohort_id) = (req.team_id, req.cohort_id);
+        let stream = scan_pages(
+            "scan_cohort_members",
+            move |cursor, limit| {
+                let storage = storage.clone();
+                async move {
+                    storage
+                        .scan_cohort_members(team_id, cohort_id, cursor, limit)
+                        .await
+                }
+            },
+            req.cursor,
+            page_size,
+            req.property_keys,
+            self.scan_limits.max_rows_per_second,
+        );
+
+        Ok(Response::new(stream))
+    }
+
     // ============================================================
     // Groups
     // ============================================================
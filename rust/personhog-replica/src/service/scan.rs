@@ -0,0 +1,169 @@
+use std::future::Future;
+use std::time::Duration;
+
+use personhog_proto::personhog::types::v1::ScanPersonsResponse;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::Status;
+
+use super::error::log_and_convert_error;
+use crate::storage::{self, StorageResult};
+
+const SCAN_ROWS: &str = "personhog_replica_scan_rows_total";
+const SCANS_STARTED: &str = "personhog_replica_scans_started_total";
+
+pub type ScanStream = ReceiverStream<Result<ScanPersonsResponse, Status>>;
+
+/// Bounds on the scan RPCs, so batch jobs can't starve point lookups of database capacity
+#[derive(Debug, Clone, Copy)]
+pub struct ScanLimits {
+    /// Page size used when the request doesn't set one
+    pub default_page_size: i64,
+    /// Requested page sizes above this are capped
+    pub max_page_size: i64,
+    /// Rows each scan stream may read per second. 0 disables the limit.
+    pub max_rows_per_second: u32,
+}
+
+impl Default for ScanLimits {
+    fn default() -> Self {
+        Self {
+            default_page_size: 500,
+            max_page_size: 1000,
+            max_rows_per_second: 5000,
+        }
+    }
+}
+
+impl ScanLimits {
+    /// Validate a requested cursor and page size, returning the page size to use
+    #[allow(clippy::result_large_err)] // tonic::Status is large but we can't change it
+    pub fn page_size(&self, cursor: i64, requested: i32) -> Result<i64, Status> {
+        if cursor < 0 {
+            return Err(Status::invalid_argument("cursor must not be negative"));
+        }
+        match requested {
+            0 => Ok(self.default_page_size),
+            n if n < 0 => Err(Status::invalid_argument("page_size must not be negative")),
+            n => Ok(i64::from(n).min(self.max_page_size)),
+        }
+    }
+}
+
+/// Spaces out page fetches so a stream reads at most a fixed number of rows per second.
+///
+/// Time a slow client spends consuming a page isn't banked, so it can't later burst
+/// above the limit.
+struct RowPacer {
+    rows_per_second: u32,
+    next_fetch: Instant,
+}
+
+impl RowPacer {
+    fn new(rows_per_second: u32) -> Self {
+        Self {
+            rows_per_second,
+            next_fetch: Instant::now(),
+        }
+    }
+
+    async fn wait(&self) {
+        tokio::time::sleep_until(self.next_fetch).await;
+    }
+
+    fn record(&mut self, rows: usize) {
+        if self.rows_per_second == 0 {
+            return;
+        }
+        let cost = Duration::from_secs_f64(rows as f64 / f64::from(self.rows_per_second));
+        self.next_fetch = self.next_fetch.max(Instant::now()) + cost;
+    }
+}
+
+/// Stream pages of persons in ascending id order, starting after `cursor`.
+///
+/// `fetch_page(after_person_id, limit)` reads one page from storage. Pages are fetched
+/// one at a time as the client consumes them, and the stream ends after the first short
+/// page or on the first storage error, which is sent to the client as the final item.
+pub fn scan_pages<F, Fut>(
+    operation: &'static str,
+    fetch_page: F,
+    cursor: i64,
+    page_size: i64,
+    property_keys: Vec<String>,
+    max_rows_per_second: u32,
+) -> ScanStream
+where
+    F: Fn(i64, i64) -> Fut + Send + 'static,
+    Fut: Future<Output = StorageResult<Vec<storage::Person>>> + Send,
+{
+    metrics::counter!(SCANS_STARTED, &[("operation", operation)]).increment(1);
+
+    // A single slot means the next page is only read once the client has taken this one
+    let (tx, rx) = mpsc::channel(1);
+
+    tokio::spawn(async move {
+        let mut pacer = RowPacer::new(max_rows_per_second);
+        let mut cursor = cursor;
+
+        loop {
+            pacer.wait().await;
+
+            let persons = match fetch_page(cursor, page_size).await {
+                Ok(persons) => persons,
+                Err(e) => {
+                    tx.send(Err(log_and_convert_error(e, operation))).await.ok();
+                    return;
+                }
+            };
+
+            let Some(last) = persons.last() else {
+                return;
+            };
+            cursor = last.id;
+            let is_last_page = (persons.len() as i64) < page_size;
+
+            pacer.record(persons.len());
+            metrics::counter!(SCAN_ROWS, &[("operation", operation)])
+                .increment(persons.len() as u64);
+
+            let page = ScanPersonsResponse {
+                persons: persons
+                    .into_iter()
+                    .map(|person| project_properties(person, &property_keys).into())
+                    .collect(),
+                next_cursor: cursor,
+            };
+
+            // Stop once the client goes away or the team has been read to the end
+            if tx.send(Ok(page)).await.is_err() || is_last_page {
+                return;
+            }
+        }
+    });
+
+    ReceiverStream::new(rx)
+}
+
+/// Keep only the given property keys, along with their last updated and last operation
+/// metadata. An empty key list keeps everything.
+fn project_properties(mut person: storage::Person, keys: &[String]) -> storage::Person {
+    if keys.is_empty() {
+        return person;
+    }
+
+    let retain = |value: &mut serde_json::Value| {
+        if let Some(map) = value.as_object_mut() {
+            map.retain(|key, _| keys.contains(key));
+        }
+    };
+    retain(&mut person.properties);
+    if let Some(value) = person.properties_last_updated_at.as_mut() {
+        retain(value);
+    }
+    if let Some(value) = person.properties_last_operation.as_mut() {
+        retain(value);
+    }
+    person
+}
@@ -86,6 +86,15 @@ impl storage::PersonLookup for FailingStorage {
     ) -> storage::StorageResult<Vec<((i64, String), Option<storage::Person>)>> {
         Err(self.error.clone())
     }
+
+    async fn scan_persons(
+        &self,
+        _team_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<storage::Person>> {
+        Err(self.error.clone())
+    }
 }
 
 #[async_trait]
@@ -148,6 +157,16 @@ impl storage::CohortStorage for FailingStorage {
     ) -> storage::StorageResult<Vec<storage::CohortMembership>> {
         Err(self.error.clone())
     }
+
+    async fn scan_cohort_members(
+        &self,
+        _team_id: i64,
+        _cohort_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<storage::Person>> {
+        Err(self.error.clone())
+    }
 }
 
 #[async_trait]
@@ -274,6 +293,15 @@ impl storage::PersonLookup for SuccessStorage {
             .map(|(t, d)| ((*t, d.clone()), None))
             .collect())
     }
+
+    async fn scan_persons(
+        &self,
+        _team_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<storage::Person>> {
+        Ok(Vec::new())
+    }
 }
 
 #[async_trait]
@@ -342,6 +370,16 @@ impl storage::CohortStorage for SuccessStorage {
             })
             .collect())
     }
+
+    async fn scan_cohort_members(
+        &self,
+        _team_id: i64,
+        _cohort_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<storage::Person>> {
+        Ok(Vec::new())
+    }
 }
 
 #[async_trait]
@@ -487,6 +525,15 @@ impl storage::PersonLookup for ConsistencyTrackingStorage {
             .map(|(t, d)| ((*t, d.clone()), None))
             .collect())
     }
+
+    async fn scan_persons(
+        &self,
+        _team_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<storage::Person>> {
+        Ok(Vec::new())
+    }
 }
 
 #[async_trait]
@@ -559,6 +606,16 @@ impl storage::CohortStorage for ConsistencyTrackingStorage {
             })
             .collect())
     }
+
+    async fn scan_cohort_members(
+        &self,
+        _team_id: i64,
+        _cohort_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<storage::Person>> {
+        Ok(Vec::new())
+    }
 }
 
 #[async_trait]
@@ -1,5 +1,6 @@
 mod mocks;
 mod routing;
+mod scan;
 
 use std::sync::Arc;
 
@@ -32,7 +32,8 @@ use personhog_proto::personhog::types::v1::{
     GetGroupTypeMappingsByTeamIdsRequest, GetGroupsBatchRequest, GetGroupsRequest,
     GetHashKeyOverrideContextRequest, GetPersonByDistinctIdRequest, GetPersonByUuidRequest,
     GetPersonRequest, GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest,
-    GetPersonsByUuidsRequest, GetPersonsRequest, TeamDistinctId,
+    GetPersonsByUuidsRequest, GetPersonsRequest, ScanCohortMembersRequest, ScanPersonsRequest,
+    TeamDistinctId,
 };
 use tonic::Request;
 
@@ -201,6 +202,44 @@ async fn test_get_persons_by_distinct_ids_rejects_strong_consistency() {
     assert_eq!(status.code(), tonic::Code::FailedPrecondition);
 }
 
+#[tokio::test]
+async fn test_scan_persons_rejects_strong_consistency() {
+    let service = PersonHogReplicaService::new(Arc::new(SuccessStorage));
+
+    let result = service
+        .scan_persons(Request::new(ScanPersonsRequest {
+            team_id: 1,
+            cursor: 0,
+            page_size: 0,
+            property_keys: vec![],
+            read_options: strong_consistency(),
+        }))
+        .await;
+
+    let status = result.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+}
+
+#[tokio::test]
+async fn test_scan_cohort_members_rejects_strong_consistency() {
+    // Cohort membership itself accepts strong consistency, but scans return person data
+    let service = PersonHogReplicaService::new(Arc::new(SuccessStorage));
+
+    let result = service
+        .scan_cohort_members(Request::new(ScanCohortMembersRequest {
+            team_id: 1,
+            cohort_id: 1,
+            cursor: 0,
+            page_size: 0,
+            property_keys: vec![],
+            read_options: strong_consistency(),
+        }))
+        .await;
+
+    let status = result.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+}
+
 // ============================================================
 // Non-person endpoints: accepts both EVENTUAL and STRONG consistency reads
 // ============================================================
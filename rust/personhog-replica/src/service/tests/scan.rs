@@ -0,0 +1,202 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use personhog_proto::personhog::replica::v1::person_hog_replica_server::PersonHogReplica;
+use personhog_proto::personhog::types::v1::{ScanPersonsRequest, ScanPersonsResponse};
+use serde_json::json;
+use tokio_stream::StreamExt;
+use tonic::{Request, Status};
+use uuid::Uuid;
+
+use super::mocks::SuccessStorage;
+use crate::service::scan::{scan_pages, ScanLimits, ScanStream};
+use crate::service::PersonHogReplicaService;
+use crate::storage;
+
+fn person(id: i64) -> storage::Person {
+    storage::Person {
+        id,
+        uuid: Uuid::now_v7(),
+        team_id: 1,
+        properties: json!({"email": format!("{id}@example.com"), "plan": "free"}),
+        properties_last_updated_at: Some(json!({"email": "2024-01-01", "plan": "2024-01-01"})),
+        properties_last_operation: Some(json!({"email": "set", "plan": "set_once"})),
+        created_at: chrono::Utc::now(),
+        version: Some(0),
+        is_identified: false,
+        is_user_id: None,
+    }
+}
+
+/// Scan `count` persons with ids 1..=count, recording the cursor each page was fetched after
+fn scan(
+    count: i64,
+    cursor: i64,
+    page_size: i64,
+    property_keys: Vec<String>,
+    max_rows_per_second: u32,
+) -> (ScanStream, Arc<Mutex<Vec<i64>>>) {
+    let fetched_after = Arc::new(Mutex::new(Vec::new()));
+    let recorder = fetched_after.clone();
+    let stream = scan_pages(
+        "test_scan",
+        move |after, limit| {
+            recorder.lock().unwrap().push(after);
+            async move {
+                Ok(((after + 1)..=count)
+                    .take(limit as usize)
+                    .map(person)
+                    .collect())
+            }
+        },
+        cursor,
+        page_size,
+        property_keys,
+        max_rows_per_second,
+    );
+    (stream, fetched_after)
+}
+
+async fn collect(stream: ScanStream) -> Vec<Result<ScanPersonsResponse, Status>> {
+    stream.collect().await
+}
+
+fn page_ids(page: &ScanPersonsResponse) -> Vec<i64> {
+    page.persons.iter().map(|p| p.id).collect()
+}
+
+#[tokio::test]
+async fn test_scan_pages_through_all_persons() {
+    let (stream, fetched_after) = scan(5, 0, 2, vec![], 0);
+
+    let pages: Vec<_> = collect(stream)
+        .await
+        .into_iter()
+        .map(|page| page.unwrap())
+        .collect();
+
+    assert_eq!(
+        pages.iter().map(page_ids).collect::<Vec<_>>(),
+        vec![vec![1, 2], vec![3, 4], vec![5]]
+    );
+    assert_eq!(
+        pages.iter().map(|p| p.next_cursor).collect::<Vec<_>>(),
+        vec![2, 4, 5]
+    );
+    // The short last page ends the scan without another round trip
+    assert_eq!(*fetched_after.lock().unwrap(), vec![0, 2, 4]);
+}
+
+#[tokio::test]
+async fn test_scan_resumes_from_cursor() {
+    let (stream, _) = scan(5, 3, 10, vec![], 0);
+
+    let pages = collect(stream).await;
+
+    assert_eq!(pages.len(), 1);
+    assert_eq!(page_ids(pages[0].as_ref().unwrap()), vec![4, 5]);
+}
+
+#[tokio::test]
+async fn test_scan_of_exact_page_multiple_ends_on_empty_page() {
+    let (stream, fetched_after) = scan(4, 0, 2, vec![], 0);
+
+    let pages = collect(stream).await;
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!(*fetched_after.lock().unwrap(), vec![0, 2, 4]);
+}
+
+#[tokio::test]
+async fn test_scan_projects_properties() {
+    let (stream, _) = scan(1, 0, 10, vec!["email".to_string()], 0);
+
+    let pages = collect(stream).await;
+    let person = &pages[0].as_ref().unwrap().persons[0];
+
+    let properties: serde_json::Value = serde_json::from_slice(&person.properties).unwrap();
+    assert_eq!(properties, json!({"email": "1@example.com"}));
+    let last_operation: serde_json::Value =
+        serde_json::from_slice(&person.properties_last_operation).unwrap();
+    assert_eq!(last_operation, json!({"email": "set"}));
+}
+
+#[tokio::test]
+async fn test_scan_ends_with_storage_error() {
+    let stream = scan_pages(
+        "test_scan",
+        |after, _limit| async move {
+            if after == 0 {
+                Ok(vec![person(1), person(2)])
+            } else {
+                Err(storage::StorageError::PoolExhausted)
+            }
+        },
+        0,
+        2,
+        vec![],
+        0,
+    );
+
+    let pages = collect(stream).await;
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!(page_ids(pages[0].as_ref().unwrap()), vec![1, 2]);
+    assert_eq!(
+        pages[1].as_ref().unwrap_err().code(),
+        tonic::Code::Unavailable
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_scan_is_rate_limited() {
+    let start = tokio::time::Instant::now();
+    let (stream, _) = scan(25, 0, 10, vec![], 10);
+
+    let pages = collect(stream).await;
+
+    assert_eq!(pages.len(), 3);
+    // The first page is free, then each page of 10 rows costs a second at 10 rows/s
+    assert!(start.elapsed() >= Duration::from_secs(2));
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn test_scan_page_size_limits() {
+    let limits = ScanLimits {
+        default_page_size: 100,
+        max_page_size: 500,
+        max_rows_per_second: 0,
+    };
+
+    assert_eq!(limits.page_size(0, 0).unwrap(), 100);
+    assert_eq!(limits.page_size(0, 50).unwrap(), 50);
+    assert_eq!(limits.page_size(0, 10_000).unwrap(), 500);
+    assert_eq!(
+        limits.page_size(0, -1).unwrap_err().code(),
+        tonic::Code::InvalidArgument
+    );
+    assert_eq!(
+        limits.page_size(-1, 0).unwrap_err().code(),
+        tonic::Code::InvalidArgument
+    );
+}
+
+#[tokio::test]
+async fn test_scan_persons_of_empty_team_yields_no_pages() {
+    let service = PersonHogReplicaService::new(Arc::new(SuccessStorage));
+
+    let stream = service
+        .scan_persons(Request::new(ScanPersonsRequest {
+            team_id: 1,
+            cursor: 0,
+            page_size: 0,
+            property_keys: vec![],
+            read_options: None,
+        }))
+        .await
+        .unwrap()
+        .into_inner();
+
+    assert!(collect(stream).await.is_empty());
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use common_kafka::kafka_consumer::{RecvErr, SingleTopicConsumer};
+use serde::Deserialize;
+use tracing::{error, warn};
+
+use super::CachedStorage;
+use crate::storage::GroupKey;
+
+const INVALIDATIONS: &str = "personhog_replica_cache_invalidations_total";
+const INVALIDATION_PARSE_ERROR: &str = "personhog_replica_cache_invalidation_parse_error_total";
+
+/// A change to person or group data. Only the fields needed to find the affected cache
+/// entries are read; anything else in the message (e.g. the full person row published
+/// to the person updates topic) is ignored.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeEvent {
+    Person {
+        team_id: i64,
+        id: i64,
+    },
+    PersonDeleted {
+        team_id: i64,
+        person_id: i64,
+    },
+    DistinctId {
+        team_id: i64,
+        distinct_id: String,
+    },
+    Group {
+        team_id: i64,
+        group_type_index: i32,
+        group_key: String,
+    },
+}
+
+impl ChangeEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            ChangeEvent::Person { .. } => "person",
+            ChangeEvent::PersonDeleted { .. } => "person_deleted",
+            ChangeEvent::DistinctId { .. } => "distinct_id",
+            ChangeEvent::Group { .. } => "group",
+        }
+    }
+
+    pub async fn invalidate(self, cache: &CachedStorage) {
+        match self {
+            ChangeEvent::Person { team_id, id } => cache.invalidate_person(team_id, id).await,
+            ChangeEvent::PersonDeleted { team_id, person_id } => {
+                cache.invalidate_person(team_id, person_id).await
+            }
+            ChangeEvent::DistinctId {
+                team_id,
+                distinct_id,
+            } => cache.invalidate_distinct_id(team_id, &distinct_id).await,
+            ChangeEvent::Group {
+                team_id,
+                group_type_index,
+                group_key,
+            } => {
+                cache
+                    .invalidate_group(&GroupKey {
+                        team_id,
+                        group_type_index,
+                        group_key,
+                    })
+                    .await
+            }
+        }
+    }
+}
+
+/// Invalidate cache entries from change events until the consumer fails.
+///
+/// Returns the Kafka error that stopped it. Without invalidations the cache would serve
+/// stale data for up to a full TTL, so callers should treat this as fatal.
+pub async fn consume_invalidations(
+    consumer: SingleTopicConsumer,
+    cache: Arc<CachedStorage>,
+) -> RecvErr {
+    loop {
+        let (event, offset): (ChangeEvent, _) = match consumer.json_recv().await {
+            Ok(r) => r,
+            Err(RecvErr::Empty) => {
+                warn!("Received empty change event");
+                metrics::counter!(INVALIDATION_PARSE_ERROR).increment(1);
+                continue;
+            }
+            Err(RecvErr::Serde(e)) => {
+                warn!("Failed to parse change event: {:?}", e);
+                metrics::counter!(INVALIDATION_PARSE_ERROR).increment(1);
+                continue;
+            }
+            Err(e @ RecvErr::Kafka(_)) => return e,
+        };
+
+        metrics::counter!(INVALIDATIONS, &[("type", event.kind())]).increment(1);
+        event.invalidate(&cache).await;
+
+        let curr_offset = offset.get_value();
+        if let Err(e) = offset.store() {
+            error!("Failed to store change event offset {curr_offset}: {e}");
+        }
+    }
+}
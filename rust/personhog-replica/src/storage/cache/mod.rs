@@ -0,0 +1,617 @@
+pub mod invalidation;
+mod team_fair;
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::storage::error::StorageResult;
+use crate::storage::postgres::ConsistencyLevel;
+use crate::storage::traits::{
+    CohortStorage, DistinctIdLookup, FeatureFlagStorage, GroupStorage, PersonLookup,
+};
+use crate::storage::types::{
+    CohortMembership, DistinctIdMapping, DistinctIdWithVersion, Group, GroupIdentifier, GroupKey,
+    GroupTypeMapping, HashKeyOverrideContext, HashKeyOverrideInput, Person,
+};
+use crate::storage::FullStorage;
+
+pub use team_fair::CacheLimits;
+use team_fair::TeamFairCache;
+
+/// Limits for each cached data type
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    pub person: CacheLimits,
+    pub group: CacheLimits,
+    pub group_type_mapping: CacheLimits,
+}
+
+/// Read-through caching layer over another storage implementation.
+///
+/// Persons (by ID, UUID and distinct ID), groups and group type mappings are cached.
+/// Everything else passes straight through. Entries are invalidated from person and
+/// group change events (see [`invalidation`]) and otherwise expire after their TTL, which
+/// also bounds how long a read racing an invalidation can keep a stale value around.
+///
+/// Strong consistency reads never touch the cache, in either direction.
+pub struct CachedStorage {
+    inner: Arc<dyn FullStorage>,
+    persons: TeamFairCache<(i64, i64), Person>,
+    person_ids_by_uuid: TeamFairCache<(i64, Uuid), i64>,
+    person_ids_by_distinct_id: TeamFairCache<(i64, String), i64>,
+    groups: TeamFairCache<GroupKey, Group>,
+    group_type_mappings_by_team: TeamFairCache<i64, Arc<Vec<GroupTypeMapping>>>,
+    group_type_mappings_by_project: TeamFairCache<i64, Arc<Vec<GroupTypeMapping>>>,
+}
+
+impl CachedStorage {
+    pub fn new(inner: Arc<dyn FullStorage>, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            persons: TeamFairCache::new("person", config.person),
+            person_ids_by_uuid: TeamFairCache::new("person_uuid", config.person),
+            person_ids_by_distinct_id: TeamFairCache::new("person_distinct_id", config.person),
+            groups: TeamFairCache::new("group", config.group),
+            group_type_mappings_by_team: TeamFairCache::new(
+                "group_type_mappings_by_team",
+                config.group_type_mapping,
+            ),
+            group_type_mappings_by_project: TeamFairCache::new(
+                "group_type_mappings_by_project",
+                config.group_type_mapping,
+            ),
+        }
+    }
+
+    // Invalidation
+
+    pub async fn invalidate_person(&self, team_id: i64, person_id: i64) {
+        self.persons.invalidate(&(team_id, person_id)).await;
+    }
+
+    pub async fn invalidate_distinct_id(&self, team_id: i64, distinct_id: &str) {
+        self.person_ids_by_distinct_id
+            .invalidate(&(team_id, distinct_id.to_string()))
+            .await;
+    }
+
+    pub async fn invalidate_group(&self, key: &GroupKey) {
+        self.groups.invalidate(key).await;
+    }
+
+    // Cache helpers
+
+    async fn cached_person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> Option<Person> {
+        let person_id = self
+            .person_ids_by_distinct_id
+            .get(&(team_id, distinct_id.to_string()))
+            .await?;
+        // A mapping to an invalidated person is a miss, not a stale hit
+        self.persons.get(&(team_id, person_id)).await
+    }
+
+    async fn cache_person(&self, person: &Person) {
+        self.person_ids_by_uuid
+            .insert((person.team_id, person.uuid), person.id)
+            .await;
+        self.persons
+            .insert((person.team_id, person.id), person.clone())
+            .await;
+    }
+
+    async fn cache_person_for_distinct_id(&self, team_id: i64, distinct_id: &str, person: &Person) {
+        self.person_ids_by_distinct_id
+            .insert((team_id, distinct_id.to_string()), person.id)
+            .await;
+        self.cache_person(person).await;
+    }
+}
+
+#[async_trait]
+impl PersonLookup for CachedStorage {
+    async fn get_person_by_id(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> StorageResult<Option<Person>> {
+        if let Some(person) = self.persons.get(&(team_id, person_id)).await {
+            return Ok(Some(person));
+        }
+
+        let person = self.inner.get_person_by_id(team_id, person_id).await?;
+        if let Some(person) = &person {
+            self.cache_person(person).await;
+        }
+        Ok(person)
+    }
+
+    async fn get_person_by_uuid(&self, team_id: i64, uuid: Uuid) -> StorageResult<Option<Person>> {
+        if let Some(person_id) = self.person_ids_by_uuid.get(&(team_id, uuid)).await {
+            if let Some(person) = self.persons.get(&(team_id, person_id)).await {
+                return Ok(Some(person));
+            }
+        }
+
+        let person = self.inner.get_person_by_uuid(team_id, uuid).await?;
+        if let Some(person) = &person {
+            self.cache_person(person).await;
+        }
+        Ok(person)
+    }
+
+    async fn get_persons_by_ids(
+        &self,
+        team_id: i64,
+        person_ids: &[i64],
+    ) -> StorageResult<Vec<Person>> {
+        let mut found = HashMap::new();
+        let mut misses = Vec::new();
+        for person_id in person_ids {
+            match self.persons.get(&(team_id, *person_id)).await {
+                Some(person) => {
+                    found.insert(*person_id, person);
+                }
+                None => misses.push(*person_id),
+            }
+        }
+
+        if !misses.is_empty() {
+            for person in self.inner.get_persons_by_ids(team_id, &misses).await? {
+                self.cache_person(&person).await;
+                found.insert(person.id, person);
+            }
+        }
+
+        // Preserve the request order, rather than returning hits before misses
+        Ok(person_ids
+            .iter()
+            .filter_map(|person_id| found.remove(person_id))
+            .collect())
+    }
+
+    async fn get_persons_by_uuids(
+        &self,
+        team_id: i64,
+        uuids: &[Uuid],
+    ) -> StorageResult<Vec<Person>> {
+        let mut found = HashMap::new();
+        let mut misses = Vec::new();
+        for uuid in uuids {
+            let cached = match self.person_ids_by_uuid.get(&(team_id, *uuid)).await {
+                Some(person_id) => self.persons.get(&(team_id, person_id)).await,
+                None => None,
+            };
+            match cached {
+                Some(person) => {
+                    found.insert(*uuid, person);
+                }
+                None => misses.push(*uuid),
+            }
+        }
+
+        if !misses.is_empty() {
+            for person in self.inner.get_persons_by_uuids(team_id, &misses).await? {
+                self.cache_person(&person).await;
+                found.insert(person.uuid, person);
+            }
+        }
+
+        Ok(uuids.iter().filter_map(|uuid| found.remove(uuid)).collect())
+    }
+
+    async fn get_person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> StorageResult<Option<Person>> {
+        if let Some(person) = self
+            .cached_person_by_distinct_id(team_id, distinct_id)
+            .await
+        {
+            return Ok(Some(person));
+        }
+
+        let person = self
+            .inner
+            .get_person_by_distinct_id(team_id, distinct_id)
+            .await?;
+        if let Some(person) = &person {
+            self.cache_person_for_distinct_id(team_id, distinct_id, person)
+                .await;
+        }
+        Ok(person)
+    }
+
+    async fn get_persons_by_distinct_ids_in_team(
+        &self,
+        team_id: i64,
+        distinct_ids: &[String],
+    ) -> StorageResult<Vec<(String, Option<Person>)>> {
+        let mut found = HashMap::new();
+        let mut misses = Vec::new();
+        for distinct_id in distinct_ids {
+            match self
+                .cached_person_by_distinct_id(team_id, distinct_id)
+                .await
+            {
+                Some(person) => {
+                    found.insert(distinct_id.clone(), person);
+                }
+                None => misses.push(distinct_id.clone()),
+            }
+        }
+
+        if !misses.is_empty() {
+            let results = self
+                .inner
+                .get_persons_by_distinct_ids_in_team(team_id, &misses)
+                .await?;
+            for (distinct_id, person) in results {
+                if let Some(person) = person {
+                    self.cache_person_for_distinct_id(team_id, &distinct_id, &person)
+                        .await;
+                    found.insert(distinct_id, person);
+                }
+            }
+        }
+
+        // Preserve the request order, as the underlying storage does
+        Ok(distinct_ids
+            .iter()
+            .map(|distinct_id| (distinct_id.clone(), found.get(distinct_id).cloned()))
+            .collect())
+    }
+
+    async fn get_persons_by_distinct_ids_cross_team(
+        &self,
+        team_distinct_ids: &[(i64, String)],
+    ) -> StorageResult<Vec<((i64, String), Option<Person>)>> {
+        let mut found = HashMap::new();
+        let mut misses = Vec::new();
+        for (team_id, distinct_id) in team_distinct_ids {
+            match self
+                .cached_person_by_distinct_id(*team_id, distinct_id)
+                .await
+            {
+                Some(person) => {
+                    found.insert((*team_id, distinct_id.clone()), person);
+                }
+                None => misses.push((*team_id, distinct_id.clone())),
+            }
+        }
+
+        if !misses.is_empty() {
+            let results = self
+                .inner
+                .get_persons_by_distinct_ids_cross_team(&misses)
+                .await?;
+            for ((team_id, distinct_id), person) in results {
+                if let Some(person) = person {
+                    self.cache_person_for_distinct_id(team_id, &distinct_id, &person)
+                        .await;
+                    found.insert((team_id, distinct_id), person);
+                }
+            }
+        }
+
+        Ok(team_distinct_ids
+            .iter()
+            .map(|key| (key.clone(), found.get(key).cloned()))
+            .collect())
+    }
+
+    // Scans read every person once, so caching them would only evict hot entries
+    async fn scan_persons(
+        &self,
+        team_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<Person>> {
+        self.inner
+            .scan_persons(team_id, after_person_id, limit)
+            .await
+    }
+}
+
+#[async_trait]
+impl GroupStorage for CachedStorage {
+    async fn get_group(
+        &self,
+        team_id: i64,
+        group_type_index: i32,
+        group_key: &str,
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Option<Group>> {
+        if consistency == ConsistencyLevel::Strong {
+            self.groups.record_bypass();
+            return self
+                .inner
+                .get_group(team_id, group_type_index, group_key, consistency)
+                .await;
+        }
+
+        let key = GroupKey {
+            team_id,
+            group_type_index,
+            group_key: group_key.to_string(),
+        };
+        if let Some(group) = self.groups.get(&key).await {
+            return Ok(Some(group));
+        }
+
+        let group = self
+            .inner
+            .get_group(team_id, group_type_index, group_key, consistency)
+            .await?;
+        if let Some(group) = &group {
+            self.groups.insert(key, group.clone()).await;
+        }
+        Ok(group)
+    }
+
+    async fn get_groups(
+        &self,
+        team_id: i64,
+        identifiers: &[GroupIdentifier],
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<Group>> {
+        if consistency == ConsistencyLevel::Strong {
+            self.groups.record_bypass();
+            return self
+                .inner
+                .get_groups(team_id, identifiers, consistency)
+                .await;
+        }
+
+        let mut groups = Vec::with_capacity(identifiers.len());
+        let mut misses = Vec::new();
+        for identifier in identifiers {
+            let key = GroupKey {
+                team_id,
+                group_type_index: identifier.group_type_index,
+                group_key: identifier.group_key.clone(),
+            };
+            match self.groups.get(&key).await {
+                Some(group) => groups.push(group),
+                None => misses.push(identifier.clone()),
+            }
+        }
+
+        if !misses.is_empty() {
+            for group in self.inner.get_groups(team_id, &misses, consistency).await? {
+                let key = GroupKey {
+                    team_id: group.team_id,
+                    group_type_index: group.group_type_index,
+                    group_key: group.group_key.clone(),
+                };
+                self.groups.insert(key, group.clone()).await;
+                groups.push(group);
+            }
+        }
+        Ok(groups)
+    }
+
+    async fn get_groups_batch(
+        &self,
+        keys: &[GroupKey],
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<(GroupKey, Group)>> {
+        if consistency == ConsistencyLevel::Strong {
+            self.groups.record_bypass();
+            return self.inner.get_groups_batch(keys, consistency).await;
+        }
+
+        let mut results = Vec::with_capacity(keys.len());
+        let mut misses = Vec::new();
+        for key in keys {
+            match self.groups.get(key).await {
+                Some(group) => results.push((key.clone(), group)),
+                None => misses.push(key.clone()),
+            }
+        }
+
+        if !misses.is_empty() {
+            for (key, group) in self.inner.get_groups_batch(&misses, consistency).await? {
+                self.groups.insert(key.clone(), group.clone()).await;
+                results.push((key, group));
+            }
+        }
+        Ok(results)
+    }
+
+    async fn get_group_type_mappings_by_team_id(
+        &self,
+        team_id: i64,
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<GroupTypeMapping>> {
+        self.get_group_type_mappings_by_team_ids(&[team_id], consistency)
+            .await
+    }
+
+    async fn get_group_type_mappings_by_team_ids(
+        &self,
+        team_ids: &[i64],
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<GroupTypeMapping>> {
+        cached_group_type_mappings(
+            &self.group_type_mappings_by_team,
+            team_ids,
+            consistency,
+            |m| m.team_id,
+            |ids| async move {
+                self.inner
+                    .get_group_type_mappings_by_team_ids(&ids, consistency)
+                    .await
+            },
+        )
+        .await
+    }
+
+    async fn get_group_type_mappings_by_project_id(
+        &self,
+        project_id: i64,
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<GroupTypeMapping>> {
+        self.get_group_type_mappings_by_project_ids(&[project_id], consistency)
+            .await
+    }
+
+    async fn get_group_type_mappings_by_project_ids(
+        &self,
+        project_ids: &[i64],
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<GroupTypeMapping>> {
+        cached_group_type_mappings(
+            &self.group_type_mappings_by_project,
+            project_ids,
+            consistency,
+            |m| m.project_id,
+            |ids| async move {
+                self.inner
+                    .get_group_type_mappings_by_project_ids(&ids, consistency)
+                    .await
+            },
+        )
+        .await
+    }
+}
+
+/// Group type mappings are cached as the full list per team (or project), including
+/// empty lists, since "this team has no group types" is a common and stable answer.
+/// No change events cover them, so they're only refreshed when their (short) TTL expires.
+async fn cached_group_type_mappings<F, Fut>(
+    cache: &TeamFairCache<i64, Arc<Vec<GroupTypeMapping>>>,
+    ids: &[i64],
+    consistency: ConsistencyLevel,
+    id_of: impl Fn(&GroupTypeMapping) -> i64,
+    fetch: F,
+) -> StorageResult<Vec<GroupTypeMapping>>
+where
+    F: FnOnce(Vec<i64>) -> Fut,
+    Fut: std::future::Future<Output = StorageResult<Vec<GroupTypeMapping>>>,
+{
+    if consistency == ConsistencyLevel::Strong {
+        cache.record_bypass();
+        return fetch(ids.to_vec()).await;
+    }
+
+    let mut mappings = Vec::new();
+    let mut misses = Vec::new();
+    for id in ids {
+        match cache.get(id).await {
+            Some(cached) => mappings.extend(cached.iter().cloned()),
+            None => misses.push(*id),
+        }
+    }
+    if misses.is_empty() {
+        return Ok(mappings);
+    }
+
+    let mut by_id: HashMap<i64, Vec<GroupTypeMapping>> =
+        misses.iter().map(|id| (*id, Vec::new())).collect();
+    for mapping in fetch(misses).await? {
+        if let Some(list) = by_id.get_mut(&id_of(&mapping)) {
+            list.push(mapping);
+        }
+    }
+    for (id, list) in by_id {
+        mappings.extend(list.iter().cloned());
+        cache.insert(id, Arc::new(list)).await;
+    }
+    Ok(mappings)
+}
+
+// Distinct ID lists, cohort membership and hash key overrides aren't cached
+
+#[async_trait]
+impl DistinctIdLookup for CachedStorage {
+    async fn get_distinct_ids_for_person(
+        &self,
+        team_id: i64,
+        person_id: i64,
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<DistinctIdWithVersion>> {
+        self.inner
+            .get_distinct_ids_for_person(team_id, person_id, consistency)
+            .await
+    }
+
+    async fn get_distinct_ids_for_persons(
+        &self,
+        team_id: i64,
+        person_ids: &[i64],
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<DistinctIdMapping>> {
+        self.inner
+            .get_distinct_ids_for_persons(team_id, person_ids, consistency)
+            .await
+    }
+}
+
+#[async_trait]
+impl CohortStorage for CachedStorage {
+    async fn check_cohort_membership(
+        &self,
+        person_id: i64,
+        cohort_ids: &[i64],
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<CohortMembership>> {
+        self.inner
+            .check_cohort_membership(person_id, cohort_ids, consistency)
+            .await
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        team_id: i64,
+        cohort_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<Person>> {
+        self.inner
+            .scan_cohort_members(team_id, cohort_id, after_person_id, limit)
+            .await
+    }
+}
+
+#[async_trait]
+impl FeatureFlagStorage for CachedStorage {
+    async fn get_hash_key_override_context(
+        &self,
+        team_id: i64,
+        distinct_ids: &[String],
+        check_person_exists: bool,
+        consistency: ConsistencyLevel,
+    ) -> StorageResult<Vec<HashKeyOverrideContext>> {
+        self.inner
+            .get_hash_key_override_context(team_id, distinct_ids, check_person_exists, consistency)
+            .await
+    }
+
+    async fn upsert_hash_key_overrides(
+        &self,
+        team_id: i64,
+        overrides: &[HashKeyOverrideInput],
+        hash_key: &str,
+    ) -> StorageResult<i64> {
+        self.inner
+            .upsert_hash_key_overrides(team_id, overrides, hash_key)
+            .await
+    }
+
+    async fn delete_hash_key_overrides_by_teams(&self, team_ids: &[i64]) -> StorageResult<i64> {
+        self.inner
+            .delete_hash_key_overrides_by_teams(team_ids)
+            .await
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use moka::future::Cache;
+
+const CACHE_LOOKUPS: &str = "personhog_replica_cache_lookups_total";
+const CACHE_INSERTS_SKIPPED: &str = "personhog_replica_cache_inserts_skipped_total";
+const CACHE_ENTRIES: &str = "personhog_replica_cache_entries";
+
+/// Cache keys that belong to a single team (or project), for per-team accounting
+pub trait TeamScoped {
+    fn team_id(&self) -> i64;
+}
+
+impl TeamScoped for i64 {
+    fn team_id(&self) -> i64 {
+        *self
+    }
+}
+
+impl<T> TeamScoped for (i64, T) {
+    fn team_id(&self) -> i64 {
+        self.0
+    }
+}
+
+impl TeamScoped for crate::storage::GroupKey {
+    fn team_id(&self) -> i64 {
+        self.team_id
+    }
+}
+
+/// Limits for one cached data type
+#[derive(Debug, Clone, Copy)]
+pub struct CacheLimits {
+    pub max_entries: u64,
+    pub max_entries_per_team: u64,
+    pub ttl: Duration,
+}
+
+/// A bounded, TTL'd cache in which no single team can hold more than a fixed number of
+/// entries, so one very large team can't evict everybody else's hot data.
+///
+/// Per-team counts are maintained from moka's eviction listener, which runs lazily, so
+/// they can briefly lag behind the real entry counts. That makes the per-team bound soft,
+/// which is fine for fairness purposes.
+pub struct TeamFairCache<K, V> {
+    name: &'static str,
+    inner: Cache<K, V>,
+    entries_per_team: Arc<Mutex<HashMap<i64, u64>>>,
+    max_entries_per_team: u64,
+}
+
+impl<K, V> TeamFairCache<K, V>
+where
+    K: TeamScoped + Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    pub fn new(name: &'static str, limits: CacheLimits) -> Self {
+        let entries_per_team: Arc<Mutex<HashMap<i64, u64>>> = Arc::default();
+        let listener_counts = entries_per_team.clone();
+        let inner = Cache::builder()
+            .max_capacity(limits.max_entries)
+            .time_to_live(limits.ttl)
+            .eviction_listener(move |key: Arc<K>, _value, _cause| {
+                let mut counts = listener_counts.lock().unwrap_or_else(|e| e.into_inner());
+                let team_id = key.team_id();
+                if let Some(count) = counts.get_mut(&team_id) {
+                    *count = count.saturating_sub(1);
+                    if *count == 0 {
+                        counts.remove(&team_id);
+                    }
+                }
+            })
+            .build();
+
+        Self {
+            name,
+            inner,
+            entries_per_team,
+            max_entries_per_team: limits.max_entries_per_team,
+        }
+    }
+
+    pub async fn get(&self, key: &K) -> Option<V> {
+        let value = self.inner.get(key).await;
+        let result = if value.is_some() { "hit" } else { "miss" };
+        metrics::counter!(CACHE_LOOKUPS, &[("cache", self.name), ("result", result)]).increment(1);
+        value
+    }
+
+    /// Record a read that skipped the cache entirely, e.g. for strong consistency
+    pub fn record_bypass(&self) {
+        metrics::counter!(CACHE_LOOKUPS, &[("cache", self.name), ("result", "bypass")])
+            .increment(1);
+    }
+
+    /// Insert a value, unless it's a new key and its team already holds its share of the
+    /// cache. Existing entries can always be refreshed.
+    pub async fn insert(&self, key: K, value: V) {
+        let replacing = self.inner.contains_key(&key);
+        {
+            let mut counts = self
+                .entries_per_team
+                .lock()
+                .unwrap_or_else(|e| e.into_inner());
+            let count = counts.entry(key.team_id()).or_insert(0);
+            if !replacing && *count >= self.max_entries_per_team {
+                metrics::counter!(CACHE_INSERTS_SKIPPED, &[("cache", self.name)]).increment(1);
+                return;
+            }
+            // Replacing an existing entry fires the eviction listener for the old value,
+            // so counting every insert keeps the books balanced
+            *count += 1;
+        }
+        self.inner.insert(key, value).await;
+        metrics::gauge!(CACHE_ENTRIES, &[("cache", self.name)])
+            .set(self.inner.entry_count() as f64);
+    }
+
+    pub async fn invalidate(&self, key: &K) {
+        self.inner.invalidate(key).await;
+    }
+
+    #[cfg(test)]
+    pub async fn entries_for_team(&self, team_id: i64) -> u64 {
+        self.inner.run_pending_tasks().await;
+        let counts = self
+            .entries_per_team
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        counts.get(&team_id).copied().unwrap_or(0)
+    }
+}
@@ -0,0 +1,735 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use uuid::Uuid;
+
+use super::invalidation::ChangeEvent;
+use super::team_fair::{CacheLimits, TeamFairCache};
+use super::{CacheConfig, CachedStorage};
+use crate::storage::postgres::ConsistencyLevel;
+use crate::storage::{
+    self, CohortMembership, DistinctIdMapping, DistinctIdWithVersion, Group, GroupIdentifier,
+    GroupKey, GroupStorage, GroupTypeMapping, HashKeyOverrideContext, HashKeyOverrideInput, Person,
+    PersonLookup,
+};
+
+fn person(team_id: i64, id: i64, plan: &str) -> Person {
+    Person {
+        id,
+        uuid: Uuid::from_u128(id as u128),
+        team_id,
+        properties: json!({"plan": plan}),
+        properties_last_updated_at: None,
+        properties_last_operation: None,
+        created_at: Utc::now(),
+        version: Some(0),
+        is_identified: false,
+        is_user_id: None,
+    }
+}
+
+fn group(team_id: i64, group_key: &str) -> Group {
+    Group {
+        id: 1,
+        team_id,
+        group_type_index: 0,
+        group_key: group_key.to_string(),
+        group_properties: json!({}),
+        created_at: Utc::now(),
+        properties_last_updated_at: None,
+        properties_last_operation: None,
+        version: 0,
+    }
+}
+
+fn group_type_mapping(team_id: i64, group_type_index: i32) -> GroupTypeMapping {
+    GroupTypeMapping {
+        id: group_type_index as i64,
+        team_id,
+        project_id: team_id,
+        group_type: format!("type-{group_type_index}"),
+        group_type_index,
+        name_singular: None,
+        name_plural: None,
+        default_columns: None,
+        detail_dashboard_id: None,
+        created_at: None,
+    }
+}
+
+/// In-memory storage that counts calls, so tests can tell cache hits from misses
+#[derive(Default)]
+struct CountingStorage {
+    persons: Mutex<HashMap<(i64, i64), Person>>,
+    distinct_ids: Mutex<HashMap<(i64, String), i64>>,
+    groups: Mutex<HashMap<GroupKey, Group>>,
+    group_type_mappings: Mutex<Vec<GroupTypeMapping>>,
+    calls: AtomicUsize,
+}
+
+impl CountingStorage {
+    fn insert_person(&self, person: Person, distinct_ids: &[&str]) {
+        let mut mappings = self.distinct_ids.lock().unwrap();
+        for distinct_id in distinct_ids {
+            mappings.insert((person.team_id, distinct_id.to_string()), person.id);
+        }
+        self.persons
+            .lock()
+            .unwrap()
+            .insert((person.team_id, person.id), person);
+    }
+
+    fn insert_group(&self, group: Group) {
+        let key = GroupKey {
+            team_id: group.team_id,
+            group_type_index: group.group_type_index,
+            group_key: group.group_key.clone(),
+        };
+        self.groups.lock().unwrap().insert(key, group);
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn call(&self) {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn person_for_distinct_id(&self, team_id: i64, distinct_id: &str) -> Option<Person> {
+        let person_id = *self
+            .distinct_ids
+            .lock()
+            .unwrap()
+            .get(&(team_id, distinct_id.to_string()))?;
+        self.persons
+            .lock()
+            .unwrap()
+            .get(&(team_id, person_id))
+            .cloned()
+    }
+}
+
+#[async_trait]
+impl storage::PersonLookup for CountingStorage {
+    async fn get_person_by_id(
+        &self,
+        team_id: i64,
+        person_id: i64,
+    ) -> storage::StorageResult<Option<Person>> {
+        self.call();
+        Ok(self
+            .persons
+            .lock()
+            .unwrap()
+            .get(&(team_id, person_id))
+            .cloned())
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        team_id: i64,
+        uuid: Uuid,
+    ) -> storage::StorageResult<Option<Person>> {
+        self.call();
+        Ok(self
+            .persons
+            .lock()
+            .unwrap()
+            .values()
+            .find(|p| p.team_id == team_id && p.uuid == uuid)
+            .cloned())
+    }
+
+    async fn get_persons_by_ids(
+        &self,
+        team_id: i64,
+        person_ids: &[i64],
+    ) -> storage::StorageResult<Vec<Person>> {
+        self.call();
+        let persons = self.persons.lock().unwrap();
+        Ok(person_ids
+            .iter()
+            .filter_map(|id| persons.get(&(team_id, *id)).cloned())
+            .collect())
+    }
+
+    async fn get_persons_by_uuids(
+        &self,
+        team_id: i64,
+        uuids: &[Uuid],
+    ) -> storage::StorageResult<Vec<Person>> {
+        self.call();
+        Ok(self
+            .persons
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|p| p.team_id == team_id && uuids.contains(&p.uuid))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_person_by_distinct_id(
+        &self,
+        team_id: i64,
+        distinct_id: &str,
+    ) -> storage::StorageResult<Option<Person>> {
+        self.call();
+        Ok(self.person_for_distinct_id(team_id, distinct_id))
+    }
+
+    async fn get_persons_by_distinct_ids_in_team(
+        &self,
+        team_id: i64,
+        distinct_ids: &[String],
+    ) -> storage::StorageResult<Vec<(String, Option<Person>)>> {
+        self.call();
+        Ok(distinct_ids
+            .iter()
+            .map(|d| (d.clone(), self.person_for_distinct_id(team_id, d)))
+            .collect())
+    }
+
+    async fn get_persons_by_distinct_ids_cross_team(
+        &self,
+        team_distinct_ids: &[(i64, String)],
+    ) -> storage::StorageResult<Vec<((i64, String), Option<Person>)>> {
+        self.call();
+        Ok(team_distinct_ids
+            .iter()
+            .map(|(team_id, d)| {
+                (
+                    (*team_id, d.clone()),
+                    self.person_for_distinct_id(*team_id, d),
+                )
+            })
+            .collect())
+    }
+
+    async fn scan_persons(
+        &self,
+        team_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> storage::StorageResult<Vec<Person>> {
+        self.call();
+        let mut persons: Vec<Person> = self
+            .persons
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|p| p.team_id == team_id && p.id > after_person_id)
+            .cloned()
+            .collect();
+        persons.sort_by_key(|p| p.id);
+        persons.truncate(limit as usize);
+        Ok(persons)
+    }
+}
+
+#[async_trait]
+impl storage::DistinctIdLookup for CountingStorage {
+    async fn get_distinct_ids_for_person(
+        &self,
+        _team_id: i64,
+        _person_id: i64,
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<DistinctIdWithVersion>> {
+        self.call();
+        Ok(Vec::new())
+    }
+
+    async fn get_distinct_ids_for_persons(
+        &self,
+        _team_id: i64,
+        _person_ids: &[i64],
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<DistinctIdMapping>> {
+        self.call();
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl storage::FeatureFlagStorage for CountingStorage {
+    async fn get_hash_key_override_context(
+        &self,
+        _team_id: i64,
+        _distinct_ids: &[String],
+        _check_person_exists: bool,
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<HashKeyOverrideContext>> {
+        self.call();
+        Ok(Vec::new())
+    }
+
+    async fn upsert_hash_key_overrides(
+        &self,
+        _team_id: i64,
+        _overrides: &[HashKeyOverrideInput],
+        _hash_key: &str,
+    ) -> storage::StorageResult<i64> {
+        self.call();
+        Ok(0)
+    }
+
+    async fn delete_hash_key_overrides_by_teams(
+        &self,
+        _team_ids: &[i64],
+    ) -> storage::StorageResult<i64> {
+        self.call();
+        Ok(0)
+    }
+}
+
+#[async_trait]
+impl storage::CohortStorage for CountingStorage {
+    async fn check_cohort_membership(
+        &self,
+        _person_id: i64,
+        _cohort_ids: &[i64],
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<CohortMembership>> {
+        self.call();
+        Ok(Vec::new())
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        _team_id: i64,
+        _cohort_id: i64,
+        _after_person_id: i64,
+        _limit: i64,
+    ) -> storage::StorageResult<Vec<Person>> {
+        self.call();
+        Ok(Vec::new())
+    }
+}
+
+#[async_trait]
+impl storage::GroupStorage for CountingStorage {
+    async fn get_group(
+        &self,
+        team_id: i64,
+        group_type_index: i32,
+        group_key: &str,
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Option<Group>> {
+        self.call();
+        Ok(self
+            .groups
+            .lock()
+            .unwrap()
+            .get(&GroupKey {
+                team_id,
+                group_type_index,
+                group_key: group_key.to_string(),
+            })
+            .cloned())
+    }
+
+    async fn get_groups(
+        &self,
+        team_id: i64,
+        identifiers: &[GroupIdentifier],
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<Group>> {
+        self.call();
+        let groups = self.groups.lock().unwrap();
+        Ok(identifiers
+            .iter()
+            .filter_map(|i| {
+                groups
+                    .get(&GroupKey {
+                        team_id,
+                        group_type_index: i.group_type_index,
+                        group_key: i.group_key.clone(),
+                    })
+                    .cloned()
+            })
+            .collect())
+    }
+
+    async fn get_groups_batch(
+        &self,
+        keys: &[GroupKey],
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<(GroupKey, Group)>> {
+        self.call();
+        let groups = self.groups.lock().unwrap();
+        Ok(keys
+            .iter()
+            .filter_map(|k| groups.get(k).map(|g| (k.clone(), g.clone())))
+            .collect())
+    }
+
+    async fn get_group_type_mappings_by_team_id(
+        &self,
+        team_id: i64,
+        consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<GroupTypeMapping>> {
+        self.get_group_type_mappings_by_team_ids(&[team_id], consistency)
+            .await
+    }
+
+    async fn get_group_type_mappings_by_team_ids(
+        &self,
+        team_ids: &[i64],
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<GroupTypeMapping>> {
+        self.call();
+        Ok(self
+            .group_type_mappings
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| team_ids.contains(&m.team_id))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_group_type_mappings_by_project_id(
+        &self,
+        project_id: i64,
+        consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<GroupTypeMapping>> {
+        self.get_group_type_mappings_by_project_ids(&[project_id], consistency)
+            .await
+    }
+
+    async fn get_group_type_mappings_by_project_ids(
+        &self,
+        project_ids: &[i64],
+        _consistency: ConsistencyLevel,
+    ) -> storage::StorageResult<Vec<GroupTypeMapping>> {
+        self.call();
+        Ok(self
+            .group_type_mappings
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| project_ids.contains(&m.project_id))
+            .cloned()
+            .collect())
+    }
+}
+
+fn limits() -> CacheLimits {
+    CacheLimits {
+        max_entries: 1000,
+        max_entries_per_team: 100,
+        ttl: Duration::from_secs(60),
+    }
+}
+
+fn setup() -> (CachedStorage, Arc<CountingStorage>) {
+    let inner = Arc::new(CountingStorage::default());
+    let config = CacheConfig {
+        person: limits(),
+        group: limits(),
+        group_type_mapping: limits(),
+    };
+    (CachedStorage::new(inner.clone(), config), inner)
+}
+
+#[tokio::test]
+async fn test_person_lookups_are_cached_across_keys() {
+    let (cache, inner) = setup();
+    inner.insert_person(person(1, 10, "free"), &["user-a"]);
+
+    for _ in 0..3 {
+        let found = cache.get_person_by_distinct_id(1, "user-a").await.unwrap();
+        assert_eq!(found.unwrap().id, 10);
+    }
+    // The same person is then served by ID and UUID without another query
+    cache.get_person_by_id(1, 10).await.unwrap().unwrap();
+    cache
+        .get_person_by_uuid(1, Uuid::from_u128(10))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(inner.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_missing_persons_are_not_cached() {
+    let (cache, inner) = setup();
+
+    assert!(cache.get_person_by_id(1, 10).await.unwrap().is_none());
+    inner.insert_person(person(1, 10, "free"), &[]);
+    assert!(cache.get_person_by_id(1, 10).await.unwrap().is_some());
+    assert_eq!(inner.calls(), 2);
+}
+
+#[tokio::test]
+async fn test_scans_do_not_fill_the_cache() {
+    let (cache, inner) = setup();
+    inner.insert_person(person(1, 10, "free"), &[]);
+    inner.insert_person(person(1, 11, "free"), &[]);
+
+    let scanned = cache.scan_persons(1, 0, 10).await.unwrap();
+    assert_eq!(
+        scanned.iter().map(|p| p.id).collect::<Vec<_>>(),
+        vec![10, 11]
+    );
+
+    cache.get_person_by_id(1, 10).await.unwrap();
+    assert_eq!(inner.calls(), 2);
+}
+
+#[tokio::test]
+async fn test_batch_lookups_only_fetch_misses_and_keep_order() {
+    let (cache, inner) = setup();
+    inner.insert_person(person(1, 10, "free"), &["a"]);
+    inner.insert_person(person(1, 20, "free"), &["b"]);
+    cache.get_person_by_distinct_id(1, "b").await.unwrap();
+
+    let distinct_ids = vec!["b".to_string(), "missing".to_string(), "a".to_string()];
+    let results = cache
+        .get_persons_by_distinct_ids_in_team(1, &distinct_ids)
+        .await
+        .unwrap();
+    let ids: Vec<_> = results
+        .iter()
+        .map(|(d, p)| (d.as_str(), p.as_ref().map(|p| p.id)))
+        .collect();
+    assert_eq!(
+        ids,
+        vec![("b", Some(20)), ("missing", None), ("a", Some(10))]
+    );
+    assert_eq!(inner.calls(), 2);
+
+    // Everything found is now cached; only the miss goes back to storage
+    cache
+        .get_persons_by_distinct_ids_in_team(1, &distinct_ids)
+        .await
+        .unwrap();
+    assert_eq!(inner.calls(), 3);
+}
+
+#[tokio::test]
+async fn test_batch_person_lookups_keep_order() {
+    let (cache, inner) = setup();
+    for id in [10, 20, 30] {
+        inner.insert_person(person(1, id, "free"), &[]);
+    }
+    cache.get_person_by_id(1, 30).await.unwrap();
+
+    // The cached person comes last in the request, not first in the results
+    let persons = cache
+        .get_persons_by_ids(1, &[20, 99, 10, 30])
+        .await
+        .unwrap();
+    assert_eq!(
+        persons.iter().map(|p| p.id).collect::<Vec<_>>(),
+        vec![20, 10, 30]
+    );
+
+    let uuids = [30u128, 10, 20].map(Uuid::from_u128);
+    let persons = cache.get_persons_by_uuids(1, &uuids).await.unwrap();
+    assert_eq!(
+        persons.iter().map(|p| p.id).collect::<Vec<_>>(),
+        vec![30, 10, 20]
+    );
+}
+
+#[tokio::test]
+async fn test_invalidation_events_drop_cached_entries() {
+    let (cache, inner) = setup();
+    inner.insert_person(person(1, 10, "free"), &["user-a"]);
+    inner.insert_group(group(1, "acme"));
+    cache.get_person_by_distinct_id(1, "user-a").await.unwrap();
+    cache
+        .get_group(1, 0, "acme", ConsistencyLevel::Eventual)
+        .await
+        .unwrap();
+
+    inner.insert_person(person(1, 10, "scale"), &["user-a"]);
+    ChangeEvent::Person { team_id: 1, id: 10 }
+        .invalidate(&cache)
+        .await;
+    let found = cache.get_person_by_distinct_id(1, "user-a").await.unwrap();
+    assert_eq!(found.unwrap().properties, json!({"plan": "scale"}));
+
+    // Moving the distinct ID to another person
+    inner.insert_person(person(1, 20, "free"), &["user-a"]);
+    ChangeEvent::DistinctId {
+        team_id: 1,
+        distinct_id: "user-a".to_string(),
+    }
+    .invalidate(&cache)
+    .await;
+    let found = cache.get_person_by_distinct_id(1, "user-a").await.unwrap();
+    assert_eq!(found.unwrap().id, 20);
+
+    let calls = inner.calls();
+    ChangeEvent::Group {
+        team_id: 1,
+        group_type_index: 0,
+        group_key: "acme".to_string(),
+    }
+    .invalidate(&cache)
+    .await;
+    cache
+        .get_group(1, 0, "acme", ConsistencyLevel::Eventual)
+        .await
+        .unwrap();
+    assert_eq!(inner.calls(), calls + 1);
+}
+
+#[tokio::test]
+async fn test_strong_consistency_bypasses_the_cache() {
+    let (cache, inner) = setup();
+    inner.insert_group(group(1, "acme"));
+
+    for _ in 0..2 {
+        cache
+            .get_group(1, 0, "acme", ConsistencyLevel::Strong)
+            .await
+            .unwrap();
+    }
+    assert_eq!(inner.calls(), 2);
+
+    // Strong reads didn't populate the cache either
+    cache
+        .get_group(1, 0, "acme", ConsistencyLevel::Eventual)
+        .await
+        .unwrap();
+    assert_eq!(inner.calls(), 3);
+}
+
+#[tokio::test]
+async fn test_group_type_mappings_cache_empty_results() {
+    let (cache, inner) = setup();
+    inner
+        .group_type_mappings
+        .lock()
+        .unwrap()
+        .extend([group_type_mapping(1, 0), group_type_mapping(1, 1)]);
+
+    for _ in 0..2 {
+        let mappings = cache
+            .get_group_type_mappings_by_team_ids(&[1, 2], ConsistencyLevel::Eventual)
+            .await
+            .unwrap();
+        assert_eq!(mappings.len(), 2);
+    }
+    cache
+        .get_group_type_mappings_by_team_id(2, ConsistencyLevel::Eventual)
+        .await
+        .unwrap();
+    assert_eq!(inner.calls(), 1);
+}
+
+#[tokio::test]
+async fn test_team_fair_cache_caps_entries_per_team() {
+    let cache: TeamFairCache<(i64, i64), i64> = TeamFairCache::new(
+        "test",
+        CacheLimits {
+            max_entries: 1000,
+            max_entries_per_team: 2,
+            ttl: Duration::from_secs(60),
+        },
+    );
+
+    for id in 0..5 {
+        cache.insert((1, id), id).await;
+    }
+    cache.insert((2, 0), 0).await;
+
+    assert_eq!(cache.entries_for_team(1).await, 2);
+    assert!(cache.get(&(1, 4)).await.is_none());
+    assert_eq!(cache.get(&(2, 0)).await, Some(0));
+
+    // Invalidating frees up room for the team
+    cache.invalidate(&(1, 0)).await;
+    assert_eq!(cache.entries_for_team(1).await, 1);
+    cache.insert((1, 4), 4).await;
+    assert_eq!(cache.get(&(1, 4)).await, Some(4));
+}
+
+#[tokio::test]
+async fn test_team_fair_cache_updates_existing_entries_at_the_cap() {
+    let cache: TeamFairCache<(i64, i64), i64> = TeamFairCache::new(
+        "test",
+        CacheLimits {
+            max_entries: 1000,
+            max_entries_per_team: 2,
+            ttl: Duration::from_secs(60),
+        },
+    );
+
+    cache.insert((1, 0), 0).await;
+    cache.insert((1, 1), 1).await;
+    cache.insert((1, 1), 10).await;
+
+    assert_eq!(cache.get(&(1, 1)).await, Some(10));
+    assert_eq!(cache.entries_for_team(1).await, 2);
+}
+
+#[tokio::test]
+async fn test_group_type_mappings_expire() {
+    let inner = Arc::new(CountingStorage::default());
+    let config = CacheConfig {
+        person: limits(),
+        group: limits(),
+        group_type_mapping: CacheLimits {
+            ttl: Duration::from_millis(50),
+            ..limits()
+        },
+    };
+    let cache = CachedStorage::new(inner.clone(), config);
+
+    cache
+        .get_group_type_mappings_by_team_id(1, ConsistencyLevel::Eventual)
+        .await
+        .unwrap();
+    inner
+        .group_type_mappings
+        .lock()
+        .unwrap()
+        .push(group_type_mapping(1, 0));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mappings = cache
+        .get_group_type_mappings_by_team_id(1, ConsistencyLevel::Eventual)
+        .await
+        .unwrap();
+    assert_eq!(mappings.len(), 1);
+}
+
+#[test]
+fn test_change_events_parse_ignoring_extra_fields() {
+    let event: ChangeEvent = serde_json::from_value(json!({
+        "type": "person",
+        "id": 10,
+        "team_id": 1,
+        "uuid": "0190e1a4-0000-7000-8000-000000000000",
+        "properties": {"plan": "free"},
+        "version": 3,
+    }))
+    .unwrap();
+    assert_eq!(event, ChangeEvent::Person { team_id: 1, id: 10 });
+
+    let event: ChangeEvent = serde_json::from_value(json!({
+        "type": "distinct_id",
+        "team_id": 1,
+        "distinct_id": "user-a",
+        "person_id": 10,
+        "version": 1,
+    }))
+    .unwrap();
+    assert_eq!(
+        event,
+        ChangeEvent::DistinctId {
+            team_id: 1,
+            distinct_id: "user-a".to_string()
+        }
+    );
+}
@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod error;
 pub mod postgres;
 pub mod traits;
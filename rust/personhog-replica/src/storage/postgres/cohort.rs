@@ -2,10 +2,11 @@ use std::collections::HashSet;
 
 use async_trait::async_trait;
 
+use super::person::PersonRow;
 use super::{ConsistencyLevel, PostgresStorage, DB_QUERY_DURATION};
 use crate::storage::error::StorageResult;
 use crate::storage::traits::CohortStorage;
-use crate::storage::types::CohortMembership;
+use crate::storage::types::{CohortMembership, Person};
 
 #[async_trait]
 impl CohortStorage for PostgresStorage {
@@ -51,4 +52,37 @@ impl CohortStorage for PostgresStorage {
             })
             .collect())
     }
+
+    async fn scan_cohort_members(
+        &self,
+        team_id: i64,
+        cohort_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<Person>> {
+        let labels = [("operation".to_string(), "scan_cohort_members".to_string())];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        // A person can have a row per cohort version, so dedupe on person_id
+        let rows = sqlx::query_as::<_, PersonRow>(
+            r#"
+            SELECT DISTINCT ON (c.person_id)
+                   p.id, p.uuid, p.team_id, p.properties, p.properties_last_updated_at,
+                   p.properties_last_operation, p.created_at, p.version, p.is_identified, p.is_user_id
+            FROM posthog_cohortpeople c
+            INNER JOIN posthog_person p ON p.id = c.person_id
+            WHERE c.cohort_id = $1 AND p.team_id = $2 AND c.person_id > $3
+            ORDER BY c.person_id
+            LIMIT $4
+            "#,
+        )
+        .bind(cohort_id as i32)
+        .bind(team_id)
+        .bind(after_person_id)
+        .bind(limit)
+        .fetch_all(&self.replica_pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Person::from).collect())
+    }
 }
@@ -10,7 +10,7 @@ use crate::storage::traits::PersonLookup;
 use crate::storage::types::Person;
 
 #[derive(Debug, Clone, FromRow)]
-struct PersonRow {
+pub(super) struct PersonRow {
     id: i64,
     uuid: Uuid,
     team_id: i32,
@@ -304,4 +304,32 @@ impl PersonLookup for PostgresStorage {
             })
             .collect())
     }
+
+    async fn scan_persons(
+        &self,
+        team_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<Person>> {
+        let labels = [("operation".to_string(), "scan_persons".to_string())];
+        let _timer = common_metrics::timing_guard(DB_QUERY_DURATION, &labels);
+
+        let rows = sqlx::query_as::<_, PersonRow>(
+            r#"
+            SELECT id, uuid, team_id, properties, properties_last_updated_at,
+                   properties_last_operation, created_at, version, is_identified, is_user_id
+            FROM posthog_person
+            WHERE team_id = $1 AND id > $2
+            ORDER BY id
+            LIMIT $3
+            "#,
+        )
+        .bind(team_id)
+        .bind(after_person_id)
+        .bind(limit)
+        .fetch_all(&self.replica_pool)
+        .await?;
+
+        Ok(rows.into_iter().map(Person::from).collect())
+    }
 }
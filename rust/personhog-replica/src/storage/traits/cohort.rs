@@ -2,7 +2,7 @@ use async_trait::async_trait;
 
 use crate::storage::error::StorageResult;
 use crate::storage::postgres::ConsistencyLevel;
-use crate::storage::types::CohortMembership;
+use crate::storage::types::{CohortMembership, Person};
 
 /// Cohort membership operations
 #[async_trait]
@@ -13,4 +13,14 @@ pub trait CohortStorage: Send + Sync {
         cohort_ids: &[i64],
         consistency: ConsistencyLevel,
     ) -> StorageResult<Vec<CohortMembership>>;
+
+    /// Fetch up to `limit` members of a cohort with person ids greater than
+    /// `after_person_id`, in ascending person id order
+    async fn scan_cohort_members(
+        &self,
+        team_id: i64,
+        cohort_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<Person>>;
 }
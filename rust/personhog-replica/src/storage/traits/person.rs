@@ -44,4 +44,15 @@ pub trait PersonLookup: Send + Sync {
         &self,
         team_distinct_ids: &[(i64, String)],
     ) -> StorageResult<Vec<((i64, String), Option<Person>)>>;
+
+    // Scans
+
+    /// Fetch up to `limit` persons of a team with ids greater than `after_person_id`,
+    /// in ascending id order. Callers page through a team by passing the last id seen.
+    async fn scan_persons(
+        &self,
+        team_id: i64,
+        after_person_id: i64,
+        limit: i64,
+    ) -> StorageResult<Vec<Person>>;
 }
@@ -220,6 +220,78 @@ async fn test_check_cohort_membership() {
     ctx.cleanup().await.ok();
 }
 
+#[tokio::test]
+async fn test_scan_persons_pages_in_id_order() {
+    let ctx = TestContext::new().await;
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        let person = ctx
+            .insert_person(&format!("scan_user_{i}"), None)
+            .await
+            .expect("Failed to insert person");
+        ids.push(person.id);
+    }
+    ids.sort();
+
+    let first_page = ctx
+        .storage
+        .scan_persons(ctx.team_id, 0, 2)
+        .await
+        .expect("Failed to scan persons");
+    assert_eq!(
+        first_page.iter().map(|p| p.id).collect::<Vec<_>>(),
+        ids[..2]
+    );
+
+    let second_page = ctx
+        .storage
+        .scan_persons(ctx.team_id, first_page[1].id, 2)
+        .await
+        .expect("Failed to scan persons");
+    assert_eq!(
+        second_page.iter().map(|p| p.id).collect::<Vec<_>>(),
+        ids[2..]
+    );
+
+    ctx.cleanup().await.ok();
+}
+
+#[tokio::test]
+async fn test_scan_cohort_members() {
+    let ctx = TestContext::new().await;
+    let cohort_id: i64 = 2001;
+    let member = ctx
+        .insert_person("scan_cohort_member", None)
+        .await
+        .expect("Failed to insert person");
+    ctx.insert_person("scan_cohort_non_member", None)
+        .await
+        .expect("Failed to insert person");
+
+    ctx.add_person_to_cohort(member.id, cohort_id)
+        .await
+        .expect("Failed to add person to cohort");
+
+    let members = ctx
+        .storage
+        .scan_cohort_members(ctx.team_id, cohort_id, 0, 10)
+        .await
+        .expect("Failed to scan cohort members");
+    assert_eq!(
+        members.iter().map(|p| p.id).collect::<Vec<_>>(),
+        vec![member.id]
+    );
+
+    let after_member = ctx
+        .storage
+        .scan_cohort_members(ctx.team_id, cohort_id, member.id, 10)
+        .await
+        .expect("Failed to scan cohort members");
+    assert!(after_member.is_empty());
+
+    ctx.cleanup().await.ok();
+}
+
 #[tokio::test]
 async fn test_person_properties() {
     let ctx = TestContext::new().await;
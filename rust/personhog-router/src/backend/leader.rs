@@ -0,0 +1,268 @@
+use async_trait::async_trait;
+use personhog_proto::personhog::leader::v1::person_hog_leader_client::PersonHogLeaderClient;
+use personhog_proto::personhog::types::v1::{
+    CheckCohortMembershipRequest, CohortMembershipResponse, DeleteHashKeyOverridesByTeamsRequest,
+    DeleteHashKeyOverridesByTeamsResponse, GetDistinctIdsForPersonRequest,
+    GetDistinctIdsForPersonResponse, GetDistinctIdsForPersonsRequest,
+    GetDistinctIdsForPersonsResponse, GetGroupRequest, GetGroupResponse,
+    GetGroupTypeMappingsByProjectIdRequest, GetGroupTypeMappingsByProjectIdsRequest,
+    GetGroupTypeMappingsByTeamIdRequest, GetGroupTypeMappingsByTeamIdsRequest,
+    GetGroupsBatchRequest, GetGroupsBatchResponse, GetGroupsRequest,
+    GetHashKeyOverrideContextRequest, GetHashKeyOverrideContextResponse,
+    GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
+    GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
+    GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse, UpsertHashKeyOverridesRequest,
+    UpsertHashKeyOverridesResponse,
+};
+use std::time::Duration;
+use tonic::transport::Channel;
+use tonic::{Request, Status};
+
+use super::{PersonHogBackend, PersonPageStream};
+
+/// Backend implementation that forwards requests to a personhog-leader service.
+///
+/// The leader only owns person data (person and persondistinctid), so the router never
+/// sends it non-person requests. Those methods return `Unimplemented` if called.
+pub struct LeaderBackend {
+    client: PersonHogLeaderClient<Channel>,
+}
+
+impl LeaderBackend {
+    /// Create a new leader backend with a lazy connection to the given URL.
+    pub fn new(
+        url: &str,
+        timeout: Duration,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let channel = Channel::from_shared(url.to_string())?
+            .timeout(timeout)
+            .connect_lazy();
+
+        Ok(Self {
+            client: PersonHogLeaderClient::new(channel),
+        })
+    }
+}
+
+fn not_person_data() -> Status {
+    Status::unimplemented("personhog-leader only serves person data")
+}
+
+#[async_trait]
+impl PersonHogBackend for LeaderBackend {
+    // Person lookups by ID
+
+    async fn get_person(&self, request: GetPersonRequest) -> Result<GetPersonResponse, Status> {
+        self.client
+            .clone()
+            .get_person(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn get_persons(&self, request: GetPersonsRequest) -> Result<PersonsResponse, Status> {
+        self.client
+            .clone()
+            .get_persons(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        request: GetPersonByUuidRequest,
+    ) -> Result<GetPersonResponse, Status> {
+        self.client
+            .clone()
+            .get_person_by_uuid(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn get_persons_by_uuids(
+        &self,
+        request: GetPersonsByUuidsRequest,
+    ) -> Result<PersonsResponse, Status> {
+        self.client
+            .clone()
+            .get_persons_by_uuids(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    // Person lookups by distinct ID
+
+    async fn get_person_by_distinct_id(
+        &self,
+        request: GetPersonByDistinctIdRequest,
+    ) -> Result<GetPersonResponse, Status> {
+        self.client
+            .clone()
+            .get_person_by_distinct_id(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn get_persons_by_distinct_ids_in_team(
+        &self,
+        request: GetPersonsByDistinctIdsInTeamRequest,
+    ) -> Result<PersonsByDistinctIdsInTeamResponse, Status> {
+        self.client
+            .clone()
+            .get_persons_by_distinct_ids_in_team(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn get_persons_by_distinct_ids(
+        &self,
+        request: GetPersonsByDistinctIdsRequest,
+    ) -> Result<PersonsByDistinctIdsResponse, Status> {
+        self.client
+            .clone()
+            .get_persons_by_distinct_ids(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    // Distinct ID operations
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        request: GetDistinctIdsForPersonRequest,
+    ) -> Result<GetDistinctIdsForPersonResponse, Status> {
+        self.client
+            .clone()
+            .get_distinct_ids_for_person(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn get_distinct_ids_for_persons(
+        &self,
+        request: GetDistinctIdsForPersonsRequest,
+    ) -> Result<GetDistinctIdsForPersonsResponse, Status> {
+        self.client
+            .clone()
+            .get_distinct_ids_for_persons(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    // Person scans - batch reads are served from Postgres replicas, never the leader's cache
+
+    async fn scan_persons(&self, _request: ScanPersonsRequest) -> Result<PersonPageStream, Status> {
+        Err(Status::unimplemented(
+            "Person scans are only served with eventual consistency by personhog-replica",
+        ))
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        _request: ScanCohortMembersRequest,
+    ) -> Result<PersonPageStream, Status> {
+        Err(Status::unimplemented(
+            "Person scans are only served with eventual consistency by personhog-replica",
+        ))
+    }
+
+    // Person writes
+
+    async fn update_person_properties(
+        &self,
+        request: UpdatePersonPropertiesRequest,
+    ) -> Result<UpdatePersonPropertiesResponse, Status> {
+        self.client
+            .clone()
+            .update_person_properties(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        request: MergeDistinctIdsRequest,
+    ) -> Result<MergeDistinctIdsResponse, Status> {
+        self.client
+            .clone()
+            .merge_distinct_ids(Request::new(request))
+            .await
+            .map(|r| r.into_inner())
+    }
+
+    // Non-person data is always routed to the replica
+
+    async fn get_hash_key_override_context(
+        &self,
+        _request: GetHashKeyOverrideContextRequest,
+    ) -> Result<GetHashKeyOverrideContextResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn upsert_hash_key_overrides(
+        &self,
+        _request: UpsertHashKeyOverridesRequest,
+    ) -> Result<UpsertHashKeyOverridesResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn delete_hash_key_overrides_by_teams(
+        &self,
+        _request: DeleteHashKeyOverridesByTeamsRequest,
+    ) -> Result<DeleteHashKeyOverridesByTeamsResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn check_cohort_membership(
+        &self,
+        _request: CheckCohortMembershipRequest,
+    ) -> Result<CohortMembershipResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_group(&self, _request: GetGroupRequest) -> Result<GetGroupResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_groups(&self, _request: GetGroupsRequest) -> Result<GroupsResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_groups_batch(
+        &self,
+        _request: GetGroupsBatchRequest,
+    ) -> Result<GetGroupsBatchResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_group_type_mappings_by_team_id(
+        &self,
+        _request: GetGroupTypeMappingsByTeamIdRequest,
+    ) -> Result<GroupTypeMappingsResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_group_type_mappings_by_team_ids(
+        &self,
+        _request: GetGroupTypeMappingsByTeamIdsRequest,
+    ) -> Result<GroupTypeMappingsBatchResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_group_type_mappings_by_project_id(
+        &self,
+        _request: GetGroupTypeMappingsByProjectIdRequest,
+    ) -> Result<GroupTypeMappingsResponse, Status> {
+        Err(not_person_data())
+    }
+
+    async fn get_group_type_mappings_by_project_ids(
+        &self,
+        _request: GetGroupTypeMappingsByProjectIdsRequest,
+    ) -> Result<GroupTypeMappingsBatchResponse, Status> {
+        Err(not_person_data())
+    }
+}
@@ -1,7 +1,13 @@
+mod leader;
+mod pool;
 mod replica;
 
+pub use leader::LeaderBackend;
+pub use pool::{Discovery, HedgeConfig, ReplicaPool, ReplicaPoolConfig};
 pub use replica::ReplicaBackend;
 
+use std::pin::Pin;
+
 use async_trait::async_trait;
 use personhog_proto::personhog::types::v1::{
     CheckCohortMembershipRequest, CohortMembershipResponse, DeleteHashKeyOverridesByTeamsRequest,
@@ -15,15 +21,28 @@ use personhog_proto::personhog::types::v1::{
     GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
     GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
     GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
-    PersonsByDistinctIdsInTeamResponse, PersonsByDistinctIdsResponse, PersonsResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    ScanPersonsResponse, UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse,
     UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
 };
+use tokio_stream::Stream;
 use tonic::Status;
 
+/// A server stream of person pages, as returned by the scan RPCs.
+pub type PersonPageStream =
+    Pin<Box<dyn Stream<Item = Result<ScanPersonsResponse, Status>> + Send + 'static>>;
+
 /// Trait defining the backend interface for person-related operations.
 /// Implementations provide the actual data access (e.g., replica, leader).
 #[async_trait]
 pub trait PersonHogBackend: Send + Sync {
+    /// Check whether the backend is able to serve requests. Backends without a health
+    /// check are assumed to always be healthy.
+    async fn check_health(&self) -> Result<(), Status> {
+        Ok(())
+    }
+
     // Person lookups by ID
     async fn get_person(&self, request: GetPersonRequest) -> Result<GetPersonResponse, Status>;
     async fn get_persons(&self, request: GetPersonsRequest) -> Result<PersonsResponse, Status>;
@@ -60,6 +79,23 @@ pub trait PersonHogBackend: Send + Sync {
         request: GetDistinctIdsForPersonsRequest,
     ) -> Result<GetDistinctIdsForPersonsResponse, Status>;
 
+    // Person scans
+    async fn scan_persons(&self, request: ScanPersonsRequest) -> Result<PersonPageStream, Status>;
+    async fn scan_cohort_members(
+        &self,
+        request: ScanCohortMembersRequest,
+    ) -> Result<PersonPageStream, Status>;
+
+    // Person writes
+    async fn update_person_properties(
+        &self,
+        request: UpdatePersonPropertiesRequest,
+    ) -> Result<UpdatePersonPropertiesResponse, Status>;
+    async fn merge_distinct_ids(
+        &self,
+        request: MergeDistinctIdsRequest,
+    ) -> Result<MergeDistinctIdsResponse, Status>;
+
     // Feature flag hash key override support
     async fn get_hash_key_override_context(
         &self,
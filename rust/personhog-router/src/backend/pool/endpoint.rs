@@ -0,0 +1,178 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::time::Instant;
+use tonic::{Code, Status};
+
+use super::ReplicaPoolConfig;
+use crate::backend::PersonHogBackend;
+use crate::middleware::{
+    clear_backend_endpoint, record_backend_ejection, set_backend_available, set_backend_outstanding,
+};
+
+/// A single personhog-replica instance in the pool, along with the state used to
+/// balance requests across instances and take unhealthy ones out of rotation.
+pub(super) struct Endpoint {
+    pub(super) url: String,
+    pub(super) backend: Arc<dyn PersonHogBackend>,
+    outstanding: AtomicUsize,
+    /// Result of the last health check. Endpoints start out healthy so they get traffic
+    /// before their first check.
+    healthy: AtomicBool,
+    /// Set once the endpoint leaves the pool, after which it stops reporting its gauges
+    removed: AtomicBool,
+    errors: Mutex<ErrorRate>,
+}
+
+/// Request outcomes over the current error rate window
+struct ErrorRate {
+    window_started: Instant,
+    requests: u64,
+    errors: u64,
+    ejected_until: Option<Instant>,
+}
+
+impl ErrorRate {
+    fn reset(&mut self, now: Instant) {
+        self.window_started = now;
+        self.requests = 0;
+        self.errors = 0;
+    }
+}
+
+impl Endpoint {
+    pub(super) fn new(url: String, backend: Arc<dyn PersonHogBackend>) -> Self {
+        set_backend_available(&url, true);
+        Self {
+            url,
+            backend,
+            outstanding: AtomicUsize::new(0),
+            healthy: AtomicBool::new(true),
+            removed: AtomicBool::new(false),
+            errors: Mutex::new(ErrorRate {
+                window_started: Instant::now(),
+                requests: 0,
+                errors: 0,
+                ejected_until: None,
+            }),
+        }
+    }
+
+    pub(super) fn outstanding(&self) -> usize {
+        self.outstanding.load(Ordering::Relaxed)
+    }
+
+    /// Whether the endpoint passed its last health check and isn't ejected for errors
+    pub(super) fn is_available(&self, now: Instant) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+            && self
+                .errors
+                .lock()
+                .unwrap()
+                .ejected_until
+                .is_none_or(|until| until <= now)
+    }
+
+    /// Take the endpoint out of the pool's metrics. Requests still in flight to it
+    /// finish, but no longer update its gauges.
+    pub(super) fn remove(&self) {
+        self.removed.store(true, Ordering::Relaxed);
+        clear_backend_endpoint(&self.url);
+    }
+
+    fn set_available_gauge(&self, available: bool) {
+        if !self.removed.load(Ordering::Relaxed) {
+            set_backend_available(&self.url, available);
+        }
+    }
+
+    fn set_outstanding_gauge(&self, outstanding: usize) {
+        if !self.removed.load(Ordering::Relaxed) {
+            set_backend_outstanding(&self.url, outstanding);
+        }
+    }
+
+    /// Count a request as outstanding until the returned guard is dropped
+    pub(super) fn start_request(self: &Arc<Self>) -> InFlight {
+        let outstanding = self.outstanding.fetch_add(1, Ordering::Relaxed) + 1;
+        self.set_outstanding_gauge(outstanding);
+        InFlight {
+            endpoint: self.clone(),
+        }
+    }
+
+    /// Record the result of a health check
+    pub(super) fn set_health(&self, result: Result<(), Status>) {
+        let healthy = result.is_ok();
+        let was_healthy = self.healthy.swap(healthy, Ordering::Relaxed);
+        match result {
+            Err(status) if was_healthy => {
+                tracing::warn!(
+                    endpoint = %self.url,
+                    error = %status,
+                    "Replica failed its health check, taking it out of rotation"
+                );
+                record_backend_ejection(&self.url, "health_check");
+            }
+            Ok(()) if !was_healthy => {
+                tracing::info!(endpoint = %self.url, "Replica is healthy again");
+            }
+            _ => {}
+        }
+        self.set_available_gauge(self.is_available(Instant::now()));
+    }
+
+    /// Record the outcome of a request, ejecting the endpoint if too many requests in
+    /// the current window failed
+    pub(super) fn record_result(&self, code: Code, config: &ReplicaPoolConfig) {
+        let now = Instant::now();
+        let mut errors = self.errors.lock().unwrap();
+
+        if now.duration_since(errors.window_started) >= config.error_rate_window {
+            errors.reset(now);
+        }
+        errors.requests += 1;
+        if is_backend_failure(code) {
+            errors.errors += 1;
+        }
+
+        let error_rate = errors.errors as f64 / errors.requests as f64;
+        let is_ejected = errors.ejected_until.is_some_and(|until| until > now);
+        if errors.requests >= config.ejection_min_requests
+            && error_rate >= config.ejection_error_rate
+            && !is_ejected
+        {
+            tracing::warn!(
+                endpoint = %self.url,
+                error_rate,
+                "Replica error rate is too high, taking it out of rotation for {:?}",
+                config.ejection_duration
+            );
+            errors.ejected_until = Some(now + config.ejection_duration);
+            errors.reset(now);
+            record_backend_ejection(&self.url, "error_rate");
+            self.set_available_gauge(false);
+        }
+    }
+}
+
+/// Guard keeping a request counted as outstanding on its endpoint
+pub(super) struct InFlight {
+    endpoint: Arc<Endpoint>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let outstanding = self.endpoint.outstanding.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.endpoint.set_outstanding_gauge(outstanding);
+    }
+}
+
+/// Whether a status code points at a problem with the replica itself, rather than with
+/// the request
+fn is_backend_failure(code: Code) -> bool {
+    matches!(
+        code,
+        Code::Unavailable | Code::DeadlineExceeded | Code::Internal | Code::Unknown
+    )
+}
@@ -0,0 +1,68 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::HedgeConfig;
+
+/// Latency samples kept per method
+const MAX_SAMPLES: usize = 1000;
+/// Samples recorded between recomputing a method's hedge delay, so the percentile isn't
+/// sorted on every request
+const RECOMPUTE_EVERY: usize = 100;
+
+/// Tracks recent per-method latencies to decide how long to wait before hedging
+pub(super) struct LatencyTracker {
+    config: HedgeConfig,
+    methods: Mutex<HashMap<&'static str, Latencies>>,
+}
+
+#[derive(Default)]
+struct Latencies {
+    samples: VecDeque<Duration>,
+    since_recompute: usize,
+    hedge_after: Option<Duration>,
+}
+
+impl LatencyTracker {
+    pub(super) fn new(config: HedgeConfig) -> Self {
+        Self {
+            config,
+            methods: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(super) fn record(&self, method: &'static str, latency: Duration) {
+        let mut methods = self.methods.lock().unwrap();
+        let latencies = methods.entry(method).or_default();
+
+        if latencies.samples.len() == MAX_SAMPLES {
+            latencies.samples.pop_front();
+        }
+        latencies.samples.push_back(latency);
+        latencies.since_recompute += 1;
+
+        let is_stale =
+            latencies.hedge_after.is_none() || latencies.since_recompute >= RECOMPUTE_EVERY;
+        if is_stale && latencies.samples.len() >= self.config.min_samples {
+            latencies.hedge_after = Some(self.percentile(&latencies.samples));
+            latencies.since_recompute = 0;
+        }
+    }
+
+    /// How long to wait for a request before hedging it, or `None` until enough
+    /// latencies have been recorded for the method
+    pub(super) fn hedge_delay(&self, method: &'static str) -> Option<Duration> {
+        self.methods
+            .lock()
+            .unwrap()
+            .get(method)
+            .and_then(|latencies| latencies.hedge_after)
+    }
+
+    fn percentile(&self, samples: &VecDeque<Duration>) -> Duration {
+        let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let index = ((sorted.len() - 1) as f64 * self.config.percentile).round() as usize;
+        sorted[index.min(sorted.len() - 1)].max(self.config.min_delay)
+    }
+}
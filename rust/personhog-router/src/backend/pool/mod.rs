@@ -0,0 +1,578 @@
+#[cfg(test)]
+mod tests;
+
+mod endpoint;
+mod hedge;
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, Weak};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use personhog_proto::personhog::types::v1::{
+    CheckCohortMembershipRequest, CohortMembershipResponse, DeleteHashKeyOverridesByTeamsRequest,
+    DeleteHashKeyOverridesByTeamsResponse, GetDistinctIdsForPersonRequest,
+    GetDistinctIdsForPersonResponse, GetDistinctIdsForPersonsRequest,
+    GetDistinctIdsForPersonsResponse, GetGroupRequest, GetGroupResponse,
+    GetGroupTypeMappingsByProjectIdRequest, GetGroupTypeMappingsByProjectIdsRequest,
+    GetGroupTypeMappingsByTeamIdRequest, GetGroupTypeMappingsByTeamIdsRequest,
+    GetGroupsBatchRequest, GetGroupsBatchResponse, GetGroupsRequest,
+    GetHashKeyOverrideContextRequest, GetHashKeyOverrideContextResponse,
+    GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
+    GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
+    GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse, UpsertHashKeyOverridesRequest,
+    UpsertHashKeyOverridesResponse,
+};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tonic::{Code, Status};
+
+use super::{PersonHogBackend, PersonPageStream};
+use crate::middleware::{record_backend_request, record_hedged_request, set_backend_endpoints};
+use endpoint::Endpoint;
+use hedge::LatencyTracker;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Creates the backend for a replica URL
+type Connect = Box<dyn Fn(&str) -> Result<Arc<dyn PersonHogBackend>, BoxError> + Send + Sync>;
+
+/// How the pool finds its replicas
+#[derive(Debug, Clone)]
+pub enum Discovery {
+    /// A fixed list of replica URLs
+    Static(Vec<String>),
+    /// Every address a host resolves to, e.g. a headless Kubernetes service. The host is
+    /// re-resolved periodically to follow replicas being added and removed.
+    Dns {
+        host: String,
+        port: u16,
+        refresh_interval: Duration,
+    },
+}
+
+/// Hedging sends a slow read to a second replica and uses whichever answers first
+#[derive(Debug, Clone)]
+pub struct HedgeConfig {
+    /// Latency percentile of a method after which its requests are hedged, e.g. 0.95
+    pub percentile: f64,
+    /// Never hedge requests sooner than this
+    pub min_delay: Duration,
+    /// Latencies a method needs before its requests are hedged
+    pub min_samples: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplicaPoolConfig {
+    pub health_check_interval: Duration,
+    pub health_check_timeout: Duration,
+    /// Window over which each replica's error rate is measured
+    pub error_rate_window: Duration,
+    /// Replicas with at least this fraction of failed requests in a window are ejected
+    pub ejection_error_rate: f64,
+    /// Requests a replica needs in a window before its error rate is considered
+    pub ejection_min_requests: u64,
+    /// How long a replica is ejected for when its error rate is too high
+    pub ejection_duration: Duration,
+    /// Hedging of idempotent reads, disabled if `None`
+    pub hedge: Option<HedgeConfig>,
+}
+
+impl Default for ReplicaPoolConfig {
+    fn default() -> Self {
+        Self {
+            health_check_interval: Duration::from_secs(5),
+            health_check_timeout: Duration::from_secs(1),
+            error_rate_window: Duration::from_secs(10),
+            ejection_error_rate: 0.5,
+            ejection_min_requests: 20,
+            ejection_duration: Duration::from_secs(30),
+            hedge: None,
+        }
+    }
+}
+
+/// Backend that balances requests across a set of personhog-replica instances.
+///
+/// Each request goes to the replica with the fewest outstanding requests. Replicas are
+/// taken out of rotation while they fail gRPC health checks, or for a while after too many
+/// of their requests fail. If no replica is available, requests are sent to all of them
+/// anyway, since failing every request is never better than trying an ejected replica.
+///
+/// Idempotent reads can optionally be hedged, see [`HedgeConfig`]. Writes and scans never
+/// are.
+pub struct ReplicaPool {
+    config: ReplicaPoolConfig,
+    connect: Connect,
+    endpoints: RwLock<Arc<Vec<Arc<Endpoint>>>>,
+    /// Rotates where the search for the least loaded replica starts, to spread ties
+    next: AtomicUsize,
+    latencies: Option<LatencyTracker>,
+}
+
+impl ReplicaPool {
+    /// Create an empty pool, using `connect` to create the backend for each replica URL
+    pub fn new<F>(config: ReplicaPoolConfig, connect: F) -> Self
+    where
+        F: Fn(&str) -> Result<Arc<dyn PersonHogBackend>, BoxError> + Send + Sync + 'static,
+    {
+        Self {
+            latencies: config.hedge.clone().map(LatencyTracker::new),
+            config,
+            connect: Box::new(connect),
+            endpoints: RwLock::new(Arc::new(Vec::new())),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// Discover the initial replicas, then keep them up to date and health checked in
+    /// the background for as long as the pool is alive
+    pub async fn start(self, discovery: Discovery) -> Result<Arc<Self>, BoxError> {
+        let pool = Arc::new(self);
+
+        match discovery {
+            Discovery::Static(urls) => pool.set_endpoints(urls)?,
+            Discovery::Dns {
+                host,
+                port,
+                refresh_interval,
+            } => {
+                pool.set_endpoints(resolve_urls(&host, port).await?)?;
+                tokio::spawn(refresh_dns(
+                    Arc::downgrade(&pool),
+                    host,
+                    port,
+                    refresh_interval,
+                ));
+            }
+        }
+        tokio::spawn(check_health_periodically(
+            Arc::downgrade(&pool),
+            pool.config.health_check_interval,
+        ));
+
+        Ok(pool)
+    }
+
+    /// Replace the set of replicas. Replicas that were already in the pool keep their
+    /// outstanding requests, health and error rate, and those that left it have their
+    /// gauges zeroed.
+    pub fn set_endpoints(&self, urls: Vec<String>) -> Result<(), BoxError> {
+        let current = self.endpoints();
+        let mut updated: Vec<Arc<Endpoint>> = Vec::with_capacity(urls.len());
+
+        for url in urls {
+            if updated.iter().any(|endpoint| endpoint.url == url) {
+                continue;
+            }
+            match current.iter().find(|endpoint| endpoint.url == url) {
+                Some(endpoint) => updated.push(endpoint.clone()),
+                None => {
+                    let backend = (self.connect)(&url)?;
+                    tracing::info!(endpoint = %url, "Added replica to the pool");
+                    updated.push(Arc::new(Endpoint::new(url, backend)));
+                }
+            }
+        }
+        let removed: Vec<Arc<Endpoint>> = current
+            .iter()
+            .filter(|endpoint| !updated.iter().any(|e| e.url == endpoint.url))
+            .cloned()
+            .collect();
+
+        set_backend_endpoints(updated.len());
+        *self.endpoints.write().unwrap() = Arc::new(updated);
+
+        for endpoint in removed {
+            tracing::info!(endpoint = %endpoint.url, "Removed replica from the pool");
+            endpoint.remove();
+        }
+        Ok(())
+    }
+
+    /// Health check every replica once
+    pub async fn check_health(&self) {
+        let timeout = self.config.health_check_timeout;
+        let mut checks = JoinSet::new();
+
+        for endpoint in self.endpoints().iter().cloned() {
+            checks.spawn(async move {
+                let result =
+                    match tokio::time::timeout(timeout, endpoint.backend.check_health()).await {
+                        Ok(result) => result,
+                        Err(_) => Err(Status::deadline_exceeded("health check timed out")),
+                    };
+                endpoint.set_health(result);
+            });
+        }
+        while checks.join_next().await.is_some() {}
+    }
+
+    fn endpoints(&self) -> Arc<Vec<Arc<Endpoint>>> {
+        self.endpoints.read().unwrap().clone()
+    }
+
+    /// Pick the available replica with the fewest outstanding requests, other than
+    /// `exclude`. Without an excluded replica, falls back to unavailable ones.
+    #[allow(clippy::result_large_err)] // tonic::Status is large but we can't change it
+    fn pick(&self, exclude: Option<&Arc<Endpoint>>) -> Result<Arc<Endpoint>, Status> {
+        let endpoints = self.endpoints();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let now = Instant::now();
+
+        let least_loaded = |available_only: bool| {
+            (0..endpoints.len())
+                .map(|i| &endpoints[(start + i) % endpoints.len()])
+                .filter(|endpoint| exclude.is_none_or(|excluded| !Arc::ptr_eq(endpoint, excluded)))
+                .filter(|endpoint| !available_only || endpoint.is_available(now))
+                .min_by_key(|endpoint| endpoint.outstanding())
+                .cloned()
+        };
+
+        least_loaded(true)
+            .or_else(|| exclude.is_none().then(|| least_loaded(false)).flatten())
+            .ok_or_else(|| Status::unavailable("No personhog-replica instances are available"))
+    }
+
+    /// Send a request to a single replica
+    async fn call<Req, Resp, F, Fut>(
+        &self,
+        method: &'static str,
+        request: Req,
+        send: F,
+    ) -> Result<Resp, Status>
+    where
+        F: Fn(Arc<dyn PersonHogBackend>, Req) -> Fut,
+        Fut: Future<Output = Result<Resp, Status>>,
+    {
+        let endpoint = self.pick(None)?;
+        self.attempt(&endpoint, method, send(endpoint.backend.clone(), request))
+            .await
+    }
+
+    /// Send an idempotent request to a replica, and to a second one as well if the first
+    /// is slower than the method's hedge delay
+    async fn call_hedged<Req, Resp, F, Fut>(
+        &self,
+        method: &'static str,
+        request: Req,
+        send: F,
+    ) -> Result<Resp, Status>
+    where
+        Req: Clone,
+        F: Fn(Arc<dyn PersonHogBackend>, Req) -> Fut,
+        Fut: Future<Output = Result<Resp, Status>>,
+    {
+        let Some(delay) = self
+            .latencies
+            .as_ref()
+            .and_then(|latencies| latencies.hedge_delay(method))
+        else {
+            return self.call(method, request, send).await;
+        };
+
+        let primary = self.pick(None)?;
+        let first = self.attempt(
+            &primary,
+            method,
+            send(primary.backend.clone(), request.clone()),
+        );
+        tokio::pin!(first);
+
+        tokio::select! {
+            result = &mut first => return result,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        let Ok(secondary) = self.pick(Some(&primary)) else {
+            return first.await;
+        };
+        let second = self.attempt(&secondary, method, send(secondary.backend.clone(), request));
+        tokio::pin!(second);
+
+        // Take the first success, only falling back to the other attempt if one fails
+        let (result, winner) = tokio::select! {
+            result = &mut first => match result {
+                Ok(response) => (Ok(response), "primary"),
+                Err(_) => (second.await, "hedge"),
+            },
+            result = &mut second => match result {
+                Ok(response) => (Ok(response), "hedge"),
+                Err(_) => (first.await, "primary"),
+            },
+        };
+        record_hedged_request(method, winner);
+        result
+    }
+
+    /// Send a request to the given replica, recording its outcome
+    async fn attempt<Resp>(
+        &self,
+        endpoint: &Arc<Endpoint>,
+        method: &'static str,
+        request: impl Future<Output = Result<Resp, Status>>,
+    ) -> Result<Resp, Status> {
+        let _in_flight = endpoint.start_request();
+        let start = Instant::now();
+        let result = request.await;
+        let elapsed = start.elapsed();
+
+        let code = result.as_ref().map_or_else(Status::code, |_| Code::Ok);
+        record_backend_request(&endpoint.url, method, code, elapsed);
+        endpoint.record_result(code, &self.config);
+        if let (Ok(_), Some(latencies)) = (&result, &self.latencies) {
+            latencies.record(method, elapsed);
+        }
+
+        result
+    }
+}
+
+/// Resolve a host to one replica URL per address
+async fn resolve_urls(host: &str, port: u16) -> Result<Vec<String>, BoxError> {
+    let addrs = common_dns::resolve_all(host, port).await?;
+    Ok(addrs.iter().map(|addr| format!("http://{addr}")).collect())
+}
+
+async fn refresh_dns(pool: Weak<ReplicaPool>, host: String, port: u16, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+        let Some(pool) = pool.upgrade() else {
+            return;
+        };
+
+        // Keep the current replicas rather than emptying the pool on a bad lookup
+        match resolve_urls(&host, port).await {
+            Ok(urls) if urls.is_empty() => {
+                tracing::warn!(%host, "Replica host resolved to no addresses");
+            }
+            Ok(urls) => {
+                if let Err(e) = pool.set_endpoints(urls) {
+                    tracing::warn!(%host, error = %e, "Failed to update replicas");
+                }
+            }
+            Err(e) => {
+                tracing::warn!(%host, error = %e, "Failed to resolve replica host");
+            }
+        }
+    }
+}
+
+async fn check_health_periodically(pool: Weak<ReplicaPool>, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+
+    loop {
+        ticker.tick().await;
+        let Some(pool) = pool.upgrade() else {
+            return;
+        };
+        pool.check_health().await;
+    }
+}
+
+/// Send a request to a single replica
+macro_rules! balanced {
+    ($self:ident, $method:ident, $request:expr) => {
+        $self
+            .call(
+                stringify!($method),
+                $request,
+                |backend, request| async move { backend.$method(request).await },
+            )
+            .await
+    };
+}
+
+/// Send an idempotent read, hedging it if it's slow
+macro_rules! hedged {
+    ($self:ident, $method:ident, $request:expr) => {
+        $self
+            .call_hedged(
+                stringify!($method),
+                $request,
+                |backend, request| async move { backend.$method(request).await },
+            )
+            .await
+    };
+}
+
+#[async_trait]
+impl PersonHogBackend for ReplicaPool {
+    // Person lookups by ID
+
+    async fn get_person(&self, request: GetPersonRequest) -> Result<GetPersonResponse, Status> {
+        hedged!(self, get_person, request)
+    }
+
+    async fn get_persons(&self, request: GetPersonsRequest) -> Result<PersonsResponse, Status> {
+        hedged!(self, get_persons, request)
+    }
+
+    async fn get_person_by_uuid(
+        &self,
+        request: GetPersonByUuidRequest,
+    ) -> Result<GetPersonResponse, Status> {
+        hedged!(self, get_person_by_uuid, request)
+    }
+
+    async fn get_persons_by_uuids(
+        &self,
+        request: GetPersonsByUuidsRequest,
+    ) -> Result<PersonsResponse, Status> {
+        hedged!(self, get_persons_by_uuids, request)
+    }
+
+    // Person lookups by distinct ID
+
+    async fn get_person_by_distinct_id(
+        &self,
+        request: GetPersonByDistinctIdRequest,
+    ) -> Result<GetPersonResponse, Status> {
+        hedged!(self, get_person_by_distinct_id, request)
+    }
+
+    async fn get_persons_by_distinct_ids_in_team(
+        &self,
+        request: GetPersonsByDistinctIdsInTeamRequest,
+    ) -> Result<PersonsByDistinctIdsInTeamResponse, Status> {
+        hedged!(self, get_persons_by_distinct_ids_in_team, request)
+    }
+
+    async fn get_persons_by_distinct_ids(
+        &self,
+        request: GetPersonsByDistinctIdsRequest,
+    ) -> Result<PersonsByDistinctIdsResponse, Status> {
+        hedged!(self, get_persons_by_distinct_ids, request)
+    }
+
+    // Distinct ID operations
+
+    async fn get_distinct_ids_for_person(
+        &self,
+        request: GetDistinctIdsForPersonRequest,
+    ) -> Result<GetDistinctIdsForPersonResponse, Status> {
+        hedged!(self, get_distinct_ids_for_person, request)
+    }
+
+    async fn get_distinct_ids_for_persons(
+        &self,
+        request: GetDistinctIdsForPersonsRequest,
+    ) -> Result<GetDistinctIdsForPersonsResponse, Status> {
+        hedged!(self, get_distinct_ids_for_persons, request)
+    }
+
+    // Person scans, balanced when the stream is opened
+
+    async fn scan_persons(&self, request: ScanPersonsRequest) -> Result<PersonPageStream, Status> {
+        balanced!(self, scan_persons, request)
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        request: ScanCohortMembersRequest,
+    ) -> Result<PersonPageStream, Status> {
+        balanced!(self, scan_cohort_members, request)
+    }
+
+    // Person writes
+
+    async fn update_person_properties(
+        &self,
+        request: UpdatePersonPropertiesRequest,
+    ) -> Result<UpdatePersonPropertiesResponse, Status> {
+        balanced!(self, update_person_properties, request)
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        request: MergeDistinctIdsRequest,
+    ) -> Result<MergeDistinctIdsResponse, Status> {
+        balanced!(self, merge_distinct_ids, request)
+    }
+
+    // Feature flag hash key override support
+
+    async fn get_hash_key_override_context(
+        &self,
+        request: GetHashKeyOverrideContextRequest,
+    ) -> Result<GetHashKeyOverrideContextResponse, Status> {
+        hedged!(self, get_hash_key_override_context, request)
+    }
+
+    async fn upsert_hash_key_overrides(
+        &self,
+        request: UpsertHashKeyOverridesRequest,
+    ) -> Result<UpsertHashKeyOverridesResponse, Status> {
+        balanced!(self, upsert_hash_key_overrides, request)
+    }
+
+    async fn delete_hash_key_overrides_by_teams(
+        &self,
+        request: DeleteHashKeyOverridesByTeamsRequest,
+    ) -> Result<DeleteHashKeyOverridesByTeamsResponse, Status> {
+        balanced!(self, delete_hash_key_overrides_by_teams, request)
+    }
+
+    // Cohort membership
+
+    async fn check_cohort_membership(
+        &self,
+        request: CheckCohortMembershipRequest,
+    ) -> Result<CohortMembershipResponse, Status> {
+        hedged!(self, check_cohort_membership, request)
+    }
+
+    // Groups
+
+    async fn get_group(&self, request: GetGroupRequest) -> Result<GetGroupResponse, Status> {
+        hedged!(self, get_group, request)
+    }
+
+    async fn get_groups(&self, request: GetGroupsRequest) -> Result<GroupsResponse, Status> {
+        hedged!(self, get_groups, request)
+    }
+
+    async fn get_groups_batch(
+        &self,
+        request: GetGroupsBatchRequest,
+    ) -> Result<GetGroupsBatchResponse, Status> {
+        hedged!(self, get_groups_batch, request)
+    }
+
+    // Group type mappings
+
+    async fn get_group_type_mappings_by_team_id(
+        &self,
+        request: GetGroupTypeMappingsByTeamIdRequest,
+    ) -> Result<GroupTypeMappingsResponse, Status> {
+        hedged!(self, get_group_type_mappings_by_team_id, request)
+    }
+
+    async fn get_group_type_mappings_by_team_ids(
+        &self,
+        request: GetGroupTypeMappingsByTeamIdsRequest,
+    ) -> Result<GroupTypeMappingsBatchResponse, Status> {
+        hedged!(self, get_group_type_mappings_by_team_ids, request)
+    }
+
+    async fn get_group_type_mappings_by_project_id(
+        &self,
+        request: GetGroupTypeMappingsByProjectIdRequest,
+    ) -> Result<GroupTypeMappingsResponse, Status> {
+        hedged!(self, get_group_type_mappings_by_project_id, request)
+    }
+
+    async fn get_group_type_mappings_by_project_ids(
+        &self,
+        request: GetGroupTypeMappingsByProjectIdsRequest,
+    ) -> Result<GroupTypeMappingsBatchResponse, Status> {
+        hedged!(self, get_group_type_mappings_by_project_ids, request)
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use personhog_proto::personhog::types::v1::{GetPersonRequest, UpsertHashKeyOverridesRequest};
+use tonic::{Code, Status};
+
+use super::{HedgeConfig, ReplicaPool, ReplicaPoolConfig};
+use crate::backend::PersonHogBackend;
+use crate::service::tests::mocks::MockBackend;
+
+fn url(index: usize) -> String {
+    format!("http://replica-{index}:50051")
+}
+
+/// A pool of the given replicas, which are reachable at `url(index)`
+fn pool_of(replicas: &[Arc<MockBackend>], config: ReplicaPoolConfig) -> ReplicaPool {
+    let by_url: HashMap<String, Arc<MockBackend>> = replicas
+        .iter()
+        .enumerate()
+        .map(|(i, replica)| (url(i), replica.clone()))
+        .collect();
+    let pool = ReplicaPool::new(config, move |url| {
+        let replica: Arc<dyn PersonHogBackend> = by_url
+            .get(url)
+            .cloned()
+            .ok_or_else(|| format!("unknown replica {url}"))?;
+        Ok(replica)
+    });
+    pool.set_endpoints((0..replicas.len()).map(url).collect())
+        .unwrap();
+    pool
+}
+
+fn replicas(count: usize) -> Vec<Arc<MockBackend>> {
+    (0..count).map(|_| Arc::new(MockBackend::new())).collect()
+}
+
+fn ejection_config() -> ReplicaPoolConfig {
+    ReplicaPoolConfig {
+        ejection_error_rate: 0.5,
+        ejection_min_requests: 4,
+        ejection_duration: Duration::from_secs(30),
+        ..Default::default()
+    }
+}
+
+fn hedge_config() -> ReplicaPoolConfig {
+    ReplicaPoolConfig {
+        hedge: Some(HedgeConfig {
+            percentile: 0.9,
+            min_delay: Duration::from_millis(1),
+            min_samples: 1,
+        }),
+        ..Default::default()
+    }
+}
+
+async fn get_person(pool: &ReplicaPool) -> Result<(), Status> {
+    pool.get_person(GetPersonRequest::default())
+        .await
+        .map(|_| ())
+}
+
+async fn send(pool: &ReplicaPool, requests: usize) -> Vec<Result<(), Status>> {
+    let mut results = Vec::with_capacity(requests);
+    for _ in 0..requests {
+        results.push(get_person(pool).await);
+    }
+    results
+}
+
+// ============================================================
+// Balancing
+// ============================================================
+
+#[tokio::test]
+async fn test_requests_spread_across_idle_replicas() {
+    let replicas = replicas(2);
+    let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+
+    send(&pool, 10).await;
+
+    assert_eq!(replicas[0].calls(), 5);
+    assert_eq!(replicas[1].calls(), 5);
+}
+
+#[tokio::test]
+async fn test_requests_go_to_replica_with_fewest_outstanding_requests() {
+    let replicas = replicas(3);
+    let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+    let endpoints = pool.endpoints();
+    let _busy = [
+        endpoints[0].start_request(),
+        endpoints[0].start_request(),
+        endpoints[1].start_request(),
+    ];
+
+    send(&pool, 4).await;
+
+    assert_eq!(replicas[0].calls(), 0);
+    assert_eq!(replicas[1].calls(), 0);
+    assert_eq!(replicas[2].calls(), 4);
+}
+
+#[tokio::test]
+async fn test_empty_pool_is_unavailable() {
+    let pool = pool_of(&[], ReplicaPoolConfig::default());
+
+    let status = get_person(&pool).await.unwrap_err();
+
+    assert_eq!(status.code(), Code::Unavailable);
+}
+
+// ============================================================
+// Ejection
+// ============================================================
+
+#[tokio::test(start_paused = true)]
+async fn test_replica_with_high_error_rate_is_ejected() {
+    let replicas = replicas(2);
+    replicas[0].set_error(Status::unavailable("replica is overloaded"));
+    let pool = pool_of(&replicas, ejection_config());
+
+    let results = send(&pool, 20).await;
+
+    // Ejected once it has failed the minimum number of requests
+    assert_eq!(replicas[0].calls(), 4);
+    assert_eq!(results.iter().filter(|r| r.is_err()).count(), 4);
+
+    // And back in rotation once the ejection expires
+    replicas[0].clear_error();
+    tokio::time::advance(Duration::from_secs(31)).await;
+    send(&pool, 10).await;
+    assert_eq!(replicas[0].calls(), 9);
+}
+
+#[tokio::test]
+async fn test_request_errors_do_not_eject_replica() {
+    let replicas = replicas(2);
+    replicas[0].set_error(Status::not_found("no such person"));
+    let pool = pool_of(&replicas, ejection_config());
+
+    send(&pool, 20).await;
+
+    assert_eq!(replicas[0].calls(), 10);
+}
+
+#[tokio::test]
+async fn test_unhealthy_replica_is_taken_out_of_rotation() {
+    let replicas = replicas(2);
+    let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+
+    replicas[0].set_healthy(false);
+    pool.check_health().await;
+    send(&pool, 4).await;
+
+    assert_eq!(replicas[0].calls(), 0);
+    assert_eq!(replicas[1].calls(), 4);
+
+    replicas[0].set_healthy(true);
+    pool.check_health().await;
+    send(&pool, 4).await;
+
+    assert_eq!(replicas[0].calls(), 2);
+}
+
+#[tokio::test]
+async fn test_requests_still_sent_when_no_replica_is_available() {
+    let replicas = replicas(2);
+    let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+
+    for replica in &replicas {
+        replica.set_healthy(false);
+    }
+    pool.check_health().await;
+
+    assert!(send(&pool, 4).await.iter().all(Result::is_ok));
+}
+
+#[tokio::test]
+async fn test_updating_endpoints_keeps_state_of_remaining_replicas() {
+    let replicas = replicas(3);
+    let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+    replicas[0].set_healthy(false);
+    pool.check_health().await;
+    replicas[0].set_healthy(true);
+
+    pool.set_endpoints(vec![url(0), url(2)]).unwrap();
+    send(&pool, 4).await;
+
+    // replica 0 stays out of rotation until its next health check
+    assert_eq!(replicas[0].calls(), 0);
+    assert_eq!(replicas[1].calls(), 0);
+    assert_eq!(replicas[2].calls(), 4);
+}
+
+#[test]
+fn test_removed_replica_gauges_are_zeroed() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let replicas = replicas(2);
+
+    metrics::with_local_recorder(&recorder, || {
+        let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+        let endpoint = pool.endpoints()[1].clone();
+        let in_flight = endpoint.start_request();
+
+        pool.set_endpoints(vec![url(0)]).unwrap();
+        // A request finishing after the replica left doesn't bring its gauges back
+        drop(in_flight);
+    });
+
+    let snapshot = snapshotter.snapshot().into_vec();
+    let gauge = |name: &str, endpoint: &str| {
+        snapshot.iter().find_map(|(key, _, _, value)| {
+            let key = key.key();
+            let matches = key.name() == name
+                && key
+                    .labels()
+                    .any(|label| label.key() == "endpoint" && label.value() == endpoint);
+            match value {
+                DebugValue::Gauge(value) if matches => Some(value.into_inner()),
+                _ => None,
+            }
+        })
+    };
+
+    assert_eq!(
+        gauge("personhog_router_replica_available", &url(0)),
+        Some(1.0)
+    );
+    assert_eq!(
+        gauge("personhog_router_replica_available", &url(1)),
+        Some(0.0)
+    );
+    assert_eq!(
+        gauge("personhog_router_replica_outstanding_requests", &url(1)),
+        Some(0.0)
+    );
+}
+
+#[tokio::test]
+async fn test_updating_endpoints_rejects_unknown_replica() {
+    let replicas = replicas(1);
+    let pool = pool_of(&replicas, ReplicaPoolConfig::default());
+
+    assert!(pool.set_endpoints(vec![url(0), url(5)]).is_err());
+
+    // The pool is left as it was
+    send(&pool, 2).await;
+    assert_eq!(replicas[0].calls(), 2);
+}
+
+// ============================================================
+// Hedging
+// ============================================================
+
+#[tokio::test(start_paused = true)]
+async fn test_slow_read_is_hedged_to_another_replica() {
+    let replicas = replicas(2);
+    replicas[0].set_delay(Duration::from_secs(10));
+    let pool = pool_of(&replicas, hedge_config());
+    pool.latencies
+        .as_ref()
+        .unwrap()
+        .record("get_person", Duration::from_millis(10));
+
+    let start = tokio::time::Instant::now();
+    get_person(&pool).await.unwrap();
+
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(replicas[0].calls(), 1);
+    assert_eq!(replicas[1].calls(), 1);
+    // The abandoned request no longer counts against the slow replica
+    assert_eq!(pool.endpoints()[0].outstanding(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_reads_are_not_hedged_until_latencies_are_known() {
+    let replicas = replicas(2);
+    replicas[0].set_delay(Duration::from_secs(10));
+    let pool = pool_of(&replicas, hedge_config());
+
+    get_person(&pool).await.unwrap();
+
+    assert_eq!(replicas[1].calls(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_failed_hedge_waits_for_primary() {
+    let replicas = replicas(2);
+    replicas[0].set_delay(Duration::from_millis(100));
+    replicas[1].set_error(Status::unavailable("replica is overloaded"));
+    let pool = pool_of(&replicas, hedge_config());
+    pool.latencies
+        .as_ref()
+        .unwrap()
+        .record("get_person", Duration::from_millis(10));
+
+    get_person(&pool).await.unwrap();
+
+    assert_eq!(replicas[1].calls(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_writes_are_never_hedged() {
+    let replicas = replicas(2);
+    replicas[0].set_delay(Duration::from_secs(10));
+    let pool = pool_of(&replicas, hedge_config());
+    pool.latencies
+        .as_ref()
+        .unwrap()
+        .record("upsert_hash_key_overrides", Duration::from_millis(10));
+
+    pool.upsert_hash_key_overrides(UpsertHashKeyOverridesRequest::default())
+        .await
+        .unwrap();
+
+    assert_eq!(replicas[1].calls(), 0);
+}
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use personhog_proto::personhog::replica::v1::person_hog_replica_client::PersonHogReplicaClient;
+use personhog_proto::personhog::replica::v1::person_hog_replica_server::SERVICE_NAME;
 use personhog_proto::personhog::types::v1::{
     CheckCohortMembershipRequest, CohortMembershipResponse, DeleteHashKeyOverridesByTeamsRequest,
     DeleteHashKeyOverridesByTeamsResponse, GetDistinctIdsForPersonRequest,
@@ -12,18 +13,24 @@ use personhog_proto::personhog::types::v1::{
     GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
     GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
     GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
-    PersonsByDistinctIdsInTeamResponse, PersonsByDistinctIdsResponse, PersonsResponse,
-    UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse, UpsertHashKeyOverridesRequest,
+    UpsertHashKeyOverridesResponse,
 };
 use std::time::Duration;
 use tonic::transport::Channel;
 use tonic::{Request, Status};
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
 
-use super::PersonHogBackend;
+use super::{PersonHogBackend, PersonPageStream};
 
 /// Backend implementation that forwards requests to a personhog-replica service.
 pub struct ReplicaBackend {
     client: PersonHogReplicaClient<Channel>,
+    health_client: HealthClient<Channel>,
 }
 
 impl ReplicaBackend {
@@ -37,13 +44,34 @@ impl ReplicaBackend {
             .connect_lazy();
 
         Ok(Self {
-            client: PersonHogReplicaClient::new(channel),
+            client: PersonHogReplicaClient::new(channel.clone()),
+            health_client: HealthClient::new(channel),
         })
     }
 }
 
 #[async_trait]
 impl PersonHogBackend for ReplicaBackend {
+    async fn check_health(&self) -> Result<(), Status> {
+        let response = self
+            .health_client
+            .clone()
+            .check(Request::new(HealthCheckRequest {
+                service: SERVICE_NAME.to_string(),
+            }))
+            .await?
+            .into_inner();
+
+        if response.status() == ServingStatus::Serving {
+            Ok(())
+        } else {
+            Err(Status::unavailable(format!(
+                "replica is {}",
+                response.status().as_str_name()
+            )))
+        }
+    }
+
     // Person lookups by ID
 
     async fn get_person(&self, request: GetPersonRequest) -> Result<GetPersonResponse, Status> {
@@ -143,6 +171,47 @@ impl PersonHogBackend for ReplicaBackend {
             .map(|r| r.into_inner())
     }
 
+    // Person scans
+
+    async fn scan_persons(&self, request: ScanPersonsRequest) -> Result<PersonPageStream, Status> {
+        self.client
+            .clone()
+            .scan_persons(Request::new(request))
+            .await
+            .map(|r| -> PersonPageStream { Box::pin(r.into_inner()) })
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        request: ScanCohortMembersRequest,
+    ) -> Result<PersonPageStream, Status> {
+        self.client
+            .clone()
+            .scan_cohort_members(Request::new(request))
+            .await
+            .map(|r| -> PersonPageStream { Box::pin(r.into_inner()) })
+    }
+
+    // Person writes - the replica never owns person data, so these are only served by the leader
+
+    async fn update_person_properties(
+        &self,
+        _request: UpdatePersonPropertiesRequest,
+    ) -> Result<UpdatePersonPropertiesResponse, Status> {
+        Err(Status::unimplemented(
+            "Person writes are served by personhog-leader, not personhog-replica",
+        ))
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        _request: MergeDistinctIdsRequest,
+    ) -> Result<MergeDistinctIdsResponse, Status> {
+        Err(Status::unimplemented(
+            "Person writes are served by personhog-leader, not personhog-replica",
+        ))
+    }
+
     // Feature flag hash key override support
 
     async fn get_hash_key_override_context(
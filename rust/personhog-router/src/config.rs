@@ -2,15 +2,73 @@ use envconfig::Envconfig;
 use std::net::SocketAddr;
 use std::time::Duration;
 
+use crate::backend::{Discovery, HedgeConfig, ReplicaPoolConfig};
+
 #[derive(Envconfig, Clone, Debug)]
 pub struct Config {
     #[envconfig(default = "127.0.0.1:50052")]
     pub grpc_address: SocketAddr,
 
-    /// URL of the personhog-replica backend
+    /// Comma-separated URLs of the personhog-replica backends. Ignored if
+    /// `replica_dns_host` is set.
     #[envconfig(default = "http://127.0.0.1:50051")]
     pub replica_url: String,
 
+    /// Host resolving to every personhog-replica instance, e.g. a headless service.
+    /// If set, replicas are discovered through DNS instead of `replica_url`.
+    #[envconfig(default = "")]
+    pub replica_dns_host: String,
+
+    /// gRPC port of the replicas discovered through `replica_dns_host`
+    #[envconfig(default = "50051")]
+    pub replica_dns_port: u16,
+
+    /// How often to re-resolve `replica_dns_host`, in milliseconds
+    #[envconfig(default = "10000")]
+    pub replica_dns_refresh_ms: u64,
+
+    /// How often to health check each replica, in milliseconds
+    #[envconfig(default = "5000")]
+    pub replica_health_check_interval_ms: u64,
+
+    #[envconfig(default = "1000")]
+    pub replica_health_check_timeout_ms: u64,
+
+    /// Replicas failing at least this fraction of requests are taken out of rotation
+    #[envconfig(default = "0.5")]
+    pub replica_ejection_error_rate: f64,
+
+    /// Requests a replica needs within the error rate window before it can be ejected
+    #[envconfig(default = "20")]
+    pub replica_ejection_min_requests: u64,
+
+    #[envconfig(default = "10000")]
+    pub replica_error_rate_window_ms: u64,
+
+    /// How long a replica with a high error rate is out of rotation, in milliseconds
+    #[envconfig(default = "30000")]
+    pub replica_ejection_duration_ms: u64,
+
+    /// Whether to hedge slow idempotent reads to a second replica
+    #[envconfig(default = "false")]
+    pub hedge_enabled: bool,
+
+    /// Latency percentile of a method after which its reads are hedged
+    #[envconfig(default = "0.95")]
+    pub hedge_percentile: f64,
+
+    #[envconfig(default = "5")]
+    pub hedge_min_delay_ms: u64,
+
+    /// Latencies a method needs before its reads are hedged
+    #[envconfig(default = "100")]
+    pub hedge_min_samples: usize,
+
+    /// URL of the personhog-leader backend. If empty, person data writes and strong
+    /// consistency reads are rejected.
+    #[envconfig(default = "")]
+    pub leader_url: String,
+
     /// Timeout for backend requests in milliseconds
     #[envconfig(default = "5000")]
     pub backend_timeout_ms: u64,
@@ -23,4 +81,49 @@ impl Config {
     pub fn backend_timeout(&self) -> Duration {
         Duration::from_millis(self.backend_timeout_ms)
     }
+
+    /// Returns the leader URL, if one is configured
+    pub fn leader_url(&self) -> Option<&str> {
+        if self.leader_url.is_empty() {
+            None
+        } else {
+            Some(&self.leader_url)
+        }
+    }
+
+    /// Returns how to find the replicas
+    pub fn replica_discovery(&self) -> Discovery {
+        if self.replica_dns_host.is_empty() {
+            Discovery::Static(
+                self.replica_url
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(String::from)
+                    .collect(),
+            )
+        } else {
+            Discovery::Dns {
+                host: self.replica_dns_host.clone(),
+                port: self.replica_dns_port,
+                refresh_interval: Duration::from_millis(self.replica_dns_refresh_ms),
+            }
+        }
+    }
+
+    pub fn replica_pool_config(&self) -> ReplicaPoolConfig {
+        ReplicaPoolConfig {
+            health_check_interval: Duration::from_millis(self.replica_health_check_interval_ms),
+            health_check_timeout: Duration::from_millis(self.replica_health_check_timeout_ms),
+            error_rate_window: Duration::from_millis(self.replica_error_rate_window_ms),
+            ejection_error_rate: self.replica_ejection_error_rate,
+            ejection_min_requests: self.replica_ejection_min_requests,
+            ejection_duration: Duration::from_millis(self.replica_ejection_duration_ms),
+            hedge: self.hedge_enabled.then(|| HedgeConfig {
+                percentile: self.hedge_percentile,
+                min_delay: Duration::from_millis(self.hedge_min_delay_ms),
+                min_samples: self.hedge_min_samples,
+            }),
+        }
+    }
 }
@@ -1,13 +1,13 @@
 use std::sync::Arc;
 
 use axum::{routing::get, Router};
-use common_metrics::setup_metrics_routes;
+use common_metrics::track_metrics;
 use envconfig::Envconfig;
 use health::readiness_handler;
 use personhog_proto::personhog::service::v1::person_hog_service_server::PersonHogServiceServer;
-use personhog_router::backend::ReplicaBackend;
+use personhog_router::backend::{LeaderBackend, PersonHogBackend, ReplicaBackend, ReplicaPool};
 use personhog_router::config::Config;
-use personhog_router::middleware::GrpcMetricsLayer;
+use personhog_router::middleware::{setup_metrics_recorder, GrpcMetricsLayer};
 use personhog_router::router::PersonHogRouter;
 use personhog_router::service::PersonHogRouterService;
 use tokio::signal;
@@ -56,7 +56,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!("Starting personhog-router service");
     tracing::info!("gRPC address: {}", config.grpc_address);
-    tracing::info!("Replica URL: {}", config.replica_url);
+    tracing::info!("Replica discovery: {:?}", config.replica_discovery());
+    tracing::info!("Leader URL: {:?}", config.leader_url());
     tracing::info!("Backend timeout: {}ms", config.backend_timeout_ms);
     tracing::info!("Metrics port: {}", config.metrics_port);
 
@@ -65,7 +66,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let health_router = Router::new()
         .route("/_readiness", get(readiness_handler))
         .route("/_liveness", get(|| async { "ok" }));
-    let metrics_router = setup_metrics_routes(health_router);
+    // Our own recorder rather than common_metrics', so the series of departed replicas expire
+    let recorder_handle = setup_metrics_recorder();
+    let metrics_router = health_router
+        .route(
+            "/metrics",
+            get(move || std::future::ready(recorder_handle.render())),
+        )
+        .layer(axum::middleware::from_fn(track_metrics));
 
     tokio::spawn(async move {
         let bind = format!("0.0.0.0:{metrics_port}");
@@ -78,12 +86,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             .expect("Metrics server error");
     });
 
-    // Create backend connection to personhog-replica
-    let replica_backend = ReplicaBackend::new(&config.replica_url, config.backend_timeout())
-        .expect("Failed to create replica backend");
-
-    // Create the router with the replica backend
-    let router = PersonHogRouter::new(Arc::new(replica_backend));
+    // Balance requests across the personhog-replica instances
+    let backend_timeout = config.backend_timeout();
+    let replica_pool = ReplicaPool::new(config.replica_pool_config(), move |url| {
+        let backend: Arc<dyn PersonHogBackend> =
+            Arc::new(ReplicaBackend::new(url, backend_timeout)?);
+        Ok(backend)
+    })
+    .start(config.replica_discovery())
+    .await
+    .expect("Failed to discover replicas");
+
+    // Create the router with the replica pool, and the leader backend if configured
+    let mut router = PersonHogRouter::new(replica_pool);
+    if let Some(leader_url) = config.leader_url() {
+        let leader_backend = LeaderBackend::new(leader_url, config.backend_timeout())
+            .expect("Failed to create leader backend");
+        router = router.with_leader_backend(Arc::new(leader_backend));
+    }
     let service = PersonHogRouterService::new(Arc::new(router));
 
     tracing::info!("Starting gRPC server on {}", config.grpc_address);
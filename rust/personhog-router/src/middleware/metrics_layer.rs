@@ -1,10 +1,12 @@
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use http::{Request, Response};
-use metrics::{counter, histogram};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use metrics_util::MetricKindMask;
 use pin_project::pin_project;
 use tower::{Layer, Service};
 
@@ -108,3 +110,106 @@ fn extract_grpc_method(path: &str) -> String {
         .unwrap_or("unknown")
         .to_string()
 }
+
+// ============================================================
+// Per-backend metrics, recorded by the replica pool for each replica endpoint
+// ============================================================
+
+const BACKEND_ENDPOINT_REQUESTS: &str = "personhog_router_replica_requests_total";
+const BACKEND_ENDPOINT_DURATION: &str = "personhog_router_replica_duration_ms";
+const BACKEND_ENDPOINT_OUTSTANDING: &str = "personhog_router_replica_outstanding_requests";
+const BACKEND_ENDPOINT_AVAILABLE: &str = "personhog_router_replica_available";
+const BACKEND_ENDPOINT_EJECTIONS: &str = "personhog_router_replica_ejections_total";
+const BACKEND_ENDPOINTS: &str = "personhog_router_replica_endpoints";
+const HEDGED_REQUESTS: &str = "personhog_router_hedged_requests_total";
+
+/// How long a counter or histogram can go without being updated before the recorder drops it.
+/// The per-endpoint series of replicas that left the pool stop being updated, so this is
+/// what removes them.
+const IDLE_SERIES_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Install the Prometheus recorder. This matches `common_metrics::setup_metrics_recorder`,
+/// except that idle counters and histograms are dropped, see [`IDLE_SERIES_TIMEOUT`].
+pub fn setup_metrics_recorder() -> PrometheusHandle {
+    const BUCKETS: &[f64] = &[
+        1.0, 5.0, 10.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0,
+    ];
+
+    PrometheusBuilder::new()
+        .set_buckets(BUCKETS)
+        .unwrap()
+        .idle_timeout(
+            MetricKindMask::COUNTER | MetricKindMask::HISTOGRAM,
+            Some(IDLE_SERIES_TIMEOUT),
+        )
+        .install_recorder()
+        .unwrap()
+}
+
+/// Record a request served by a single replica endpoint, labelled with its status code.
+///
+/// Cardinality is bounded by the number of replicas, which the pool keeps in sync
+/// with discovery. Series of replicas that left the pool are dropped once idle.
+pub fn record_backend_request(
+    endpoint: &str,
+    method: &'static str,
+    code: tonic::Code,
+    duration: Duration,
+) {
+    counter!(
+        BACKEND_ENDPOINT_REQUESTS,
+        "endpoint" => endpoint.to_string(),
+        "method" => method,
+        "code" => format!("{code:?}")
+    )
+    .increment(1);
+    histogram!(
+        BACKEND_ENDPOINT_DURATION,
+        "endpoint" => endpoint.to_string(),
+        "method" => method
+    )
+    .record(duration.as_secs_f64() * 1000.0);
+}
+
+/// Record the number of requests currently in flight to a replica endpoint
+pub fn set_backend_outstanding(endpoint: &str, outstanding: usize) {
+    gauge!(BACKEND_ENDPOINT_OUTSTANDING, "endpoint" => endpoint.to_string())
+        .set(outstanding as f64);
+}
+
+/// Record whether a replica endpoint is currently receiving traffic
+pub fn set_backend_available(endpoint: &str, available: bool) {
+    gauge!(BACKEND_ENDPOINT_AVAILABLE, "endpoint" => endpoint.to_string()).set(if available {
+        1.0
+    } else {
+        0.0
+    });
+}
+
+/// Zero the gauges of a replica endpoint that left the pool, so it doesn't keep
+/// reporting its last state
+pub fn clear_backend_endpoint(endpoint: &str) {
+    set_backend_outstanding(endpoint, 0);
+    set_backend_available(endpoint, false);
+}
+
+/// Record a replica endpoint being taken out of rotation, e.g. for a failed health
+/// check or a high error rate
+pub fn record_backend_ejection(endpoint: &str, reason: &'static str) {
+    counter!(
+        BACKEND_ENDPOINT_EJECTIONS,
+        "endpoint" => endpoint.to_string(),
+        "reason" => reason
+    )
+    .increment(1);
+}
+
+/// Record the number of replica endpoints known to the pool
+pub fn set_backend_endpoints(count: usize) {
+    gauge!(BACKEND_ENDPOINTS).set(count as f64);
+}
+
+/// Record a hedged request, labelled with which attempt answered first
+pub fn record_hedged_request(method: &'static str, winner: &'static str) {
+    counter!(HEDGED_REQUESTS, "method" => method, "winner" => winner).increment(1);
+}
@@ -1,3 +1,7 @@
 mod metrics_layer;
 
-pub use metrics_layer::{GrpcMetricsLayer, GrpcMetricsService};
+pub use metrics_layer::{
+    clear_backend_endpoint, record_backend_ejection, record_backend_request, record_hedged_request,
+    set_backend_available, set_backend_endpoints, set_backend_outstanding, setup_metrics_recorder,
+    GrpcMetricsLayer, GrpcMetricsService,
+};
@@ -18,12 +18,14 @@ use personhog_proto::personhog::types::v1::{
     GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
     GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
     GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
-    PersonsByDistinctIdsInTeamResponse, PersonsByDistinctIdsResponse, PersonsResponse,
-    UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse, UpsertHashKeyOverridesRequest,
+    UpsertHashKeyOverridesResponse,
 };
 use tonic::Status;
 
-use crate::backend::PersonHogBackend;
+use crate::backend::{PersonHogBackend, PersonPageStream};
 use routing::{get_consistency, route_request};
 
 /// Macro to call a backend method with timing instrumentation.
@@ -78,14 +80,17 @@ macro_rules! call_backend {
 /// | Data Type       | Operation | Consistency | Target Backend           |
 /// |-----------------|-----------|-------------|--------------------------|
 /// | Person data     | Read      | EVENTUAL    | personhog-replica        |
-/// | Person data     | Read      | STRONG      | personhog-leader (Phase 2) |
-/// | Person data     | Write     | -           | personhog-leader (Phase 2) |
+/// | Person data     | Read      | STRONG      | personhog-leader         |
+/// | Person data     | Write     | -           | personhog-leader         |
 /// | Non-person data | Read      | EVENTUAL    | personhog-replica        |
 /// | Non-person data | Read      | STRONG      | personhog-replica (primary) |
 /// | Non-person data | Write     | -           | personhog-replica        |
 ///
 /// Non-person data includes: hash key overrides, cohort membership, groups, group type mappings
 ///
+/// The leader backend is optional. Without one, requests routed to the leader fail with
+/// `Unimplemented`.
+///
 /// # Metrics
 ///
 /// Records backend-specific metrics:
@@ -94,13 +99,21 @@ macro_rules! call_backend {
 /// - `personhog_router_backend_errors_total` - counter by method and backend
 pub struct PersonHogRouter {
     replica_backend: Arc<dyn PersonHogBackend>,
-    // Phase 2: Add leader_backend for person data writes and strong consistency reads
-    // leader_backend: Option<Arc<dyn PersonHogBackend>>,
+    leader_backend: Option<Arc<dyn PersonHogBackend>>,
 }
 
 impl PersonHogRouter {
     pub fn new(replica_backend: Arc<dyn PersonHogBackend>) -> Self {
-        Self { replica_backend }
+        Self {
+            replica_backend,
+            leader_backend: None,
+        }
+    }
+
+    /// Route person data writes and strong consistency reads to the given leader backend.
+    pub fn with_leader_backend(mut self, leader_backend: Arc<dyn PersonHogBackend>) -> Self {
+        self.leader_backend = Some(leader_backend);
+        self
     }
 
     /// Get the appropriate backend for a request based on routing decision.
@@ -108,12 +121,15 @@ impl PersonHogRouter {
     fn get_backend(&self, decision: RouteDecision) -> Result<&dyn PersonHogBackend, Status> {
         match decision {
             RouteDecision::Replica => Ok(self.replica_backend.as_ref()),
-            RouteDecision::Leader => {
-                // Phase 2: Return leader backend when available
-                Err(Status::unimplemented(
-                    "Strong consistency for person data requires personhog-leader (not yet implemented)",
-                ))
-            }
+            RouteDecision::Leader => match &self.leader_backend {
+                Some(backend) => Ok(backend.as_ref()),
+                None => Err(RoutingError::BackendNotAvailable {
+                    message: "Person data writes and strong consistency reads require \
+                              personhog-leader, which is not configured for this router."
+                        .to_string(),
+                }
+                .into()),
+            },
         }
     }
 
@@ -273,6 +289,72 @@ impl PersonHogRouter {
         )
     }
 
+    // ============================================================
+    // Person scans - Person data, read operations
+    // ============================================================
+
+    pub async fn scan_persons(
+        &self,
+        request: ScanPersonsRequest,
+    ) -> Result<PersonPageStream, Status> {
+        let decision = route_request(
+            DataCategory::PersonData,
+            OperationType::Read,
+            get_consistency(&request.read_options),
+        )?;
+        call_backend!(self, decision, "ScanPersons", scan_persons, request)
+    }
+
+    pub async fn scan_cohort_members(
+        &self,
+        request: ScanCohortMembersRequest,
+    ) -> Result<PersonPageStream, Status> {
+        let decision = route_request(
+            DataCategory::PersonData,
+            OperationType::Read,
+            get_consistency(&request.read_options),
+        )?;
+        call_backend!(
+            self,
+            decision,
+            "ScanCohortMembers",
+            scan_cohort_members,
+            request
+        )
+    }
+
+    // ============================================================
+    // Person writes - Person data, write operations
+    // ============================================================
+
+    pub async fn update_person_properties(
+        &self,
+        request: UpdatePersonPropertiesRequest,
+    ) -> Result<UpdatePersonPropertiesResponse, Status> {
+        let decision = route_request(DataCategory::PersonData, OperationType::Write, None)?;
+        call_backend!(
+            self,
+            decision,
+            "UpdatePersonProperties",
+            update_person_properties,
+            request
+        )
+    }
+
+    pub async fn merge_distinct_ids(
+        &self,
+        request: MergeDistinctIdsRequest,
+    ) -> Result<MergeDistinctIdsResponse, Status> {
+        let decision = route_request(DataCategory::PersonData, OperationType::Write, None)?;
+        call_backend!(
+            self,
+            decision,
+            "MergeDistinctIds",
+            merge_distinct_ids,
+            request
+        )
+    }
+
     // ============================================================
     // Feature flag hash key overrides - Non-person data
     // ============================================================
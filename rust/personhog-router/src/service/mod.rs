@@ -1,5 +1,5 @@
 #[cfg(test)]
-mod tests;
+pub(crate) mod tests;
 
 use std::sync::Arc;
 
@@ -16,11 +16,14 @@ use personhog_proto::personhog::types::v1::{
     GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
     GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
     GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
-    PersonsByDistinctIdsInTeamResponse, PersonsByDistinctIdsResponse, PersonsResponse,
-    UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse, UpsertHashKeyOverridesRequest,
+    UpsertHashKeyOverridesResponse,
 };
 use tonic::{Request, Response, Status};
 
+use crate::backend::PersonPageStream;
 use crate::router::PersonHogRouter;
 
 pub struct PersonHogRouterService {
@@ -44,6 +47,9 @@ macro_rules! route_request {
 
 #[tonic::async_trait]
 impl PersonHogService for PersonHogRouterService {
+    type ScanPersonsStream = PersonPageStream;
+    type ScanCohortMembersStream = PersonPageStream;
+
     // Person lookups by ID
 
     async fn get_person(
@@ -113,6 +119,38 @@ impl PersonHogService for PersonHogRouterService {
         route_request!(self, get_distinct_ids_for_persons, request)
     }
 
+    // Person scans
+
+    async fn scan_persons(
+        &self,
+        request: Request<ScanPersonsRequest>,
+    ) -> Result<Response<Self::ScanPersonsStream>, Status> {
+        route_request!(self, scan_persons, request)
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        request: Request<ScanCohortMembersRequest>,
+    ) -> Result<Response<Self::ScanCohortMembersStream>, Status> {
+        route_request!(self, scan_cohort_members, request)
+    }
+
+    // Person writes
+
+    async fn update_person_properties(
+        &self,
+        request: Request<UpdatePersonPropertiesRequest>,
+    ) -> Result<Response<UpdatePersonPropertiesResponse>, Status> {
+        route_request!(self, update_person_properties, request)
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        request: Request<MergeDistinctIdsRequest>,
+    ) -> Result<Response<MergeDistinctIdsResponse>, Status> {
+        route_request!(self, merge_distinct_ids, request)
+    }
+
     // Feature flag hash key override support
 
     async fn get_hash_key_override_context(
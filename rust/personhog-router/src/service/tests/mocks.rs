@@ -11,17 +11,24 @@ use personhog_proto::personhog::types::v1::{
     GetPersonByDistinctIdRequest, GetPersonByUuidRequest, GetPersonRequest, GetPersonResponse,
     GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
     GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
-    Person, PersonsByDistinctIdsInTeamResponse, PersonsByDistinctIdsResponse, PersonsResponse,
+    MergeDistinctIdsRequest, MergeDistinctIdsResponse, Person, PersonsByDistinctIdsInTeamResponse,
+    PersonsByDistinctIdsResponse, PersonsResponse, ScanCohortMembersRequest, ScanPersonsRequest,
+    ScanPersonsResponse, UpdatePersonPropertiesRequest, UpdatePersonPropertiesResponse,
     UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
 };
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
+use std::time::Duration;
 use tonic::Status;
 
-use crate::backend::PersonHogBackend;
+use crate::backend::{PersonHogBackend, PersonPageStream};
 
 pub struct MockBackend {
     person_response: Mutex<Option<Person>>,
     error: Mutex<Option<Status>>,
+    delay: Mutex<Option<Duration>>,
+    healthy: AtomicBool,
+    calls: AtomicUsize,
 }
 
 impl MockBackend {
@@ -29,6 +36,9 @@ impl MockBackend {
         Self {
             person_response: Mutex::new(None),
             error: Mutex::new(None),
+            delay: Mutex::new(None),
+            healthy: AtomicBool::new(true),
+            calls: AtomicUsize::new(0),
         }
     }
 
@@ -40,8 +50,46 @@ impl MockBackend {
         *self.error.lock().unwrap() = Some(status);
     }
 
-    #[allow(clippy::result_large_err)] // tonic::Status is large but we can't change it
-    fn check_error(&self) -> Result<(), Status> {
+    pub fn clear_error(&self) {
+        *self.error.lock().unwrap() = None;
+    }
+
+    /// Delay every response by the given duration
+    pub fn set_delay(&self, delay: Duration) {
+        *self.delay.lock().unwrap() = Some(delay);
+    }
+
+    pub fn set_healthy(&self, healthy: bool) {
+        self.healthy.store(healthy, Ordering::Relaxed);
+    }
+
+    /// Number of requests this backend has received
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+
+    /// A scan yields a single page holding the configured person, if any
+    fn scan_page(&self) -> PersonPageStream {
+        let persons: Vec<Person> = self
+            .person_response
+            .lock()
+            .unwrap()
+            .clone()
+            .into_iter()
+            .collect();
+        let next_cursor = persons.last().map(|p| p.id).unwrap_or_default();
+        Box::pin(tokio_stream::iter(vec![Ok(ScanPersonsResponse {
+            persons,
+            next_cursor,
+        })]))
+    }
+
+    async fn respond(&self) -> Result<(), Status> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        let delay = *self.delay.lock().unwrap();
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
         if let Some(status) = self.error.lock().unwrap().clone() {
             return Err(status);
         }
@@ -51,15 +99,23 @@ impl MockBackend {
 
 #[async_trait]
 impl PersonHogBackend for MockBackend {
+    async fn check_health(&self) -> Result<(), Status> {
+        if self.healthy.load(Ordering::Relaxed) {
+            Ok(())
+        } else {
+            Err(Status::unavailable("not serving"))
+        }
+    }
+
     async fn get_person(&self, _request: GetPersonRequest) -> Result<GetPersonResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetPersonResponse {
             person: self.person_response.lock().unwrap().clone(),
         })
     }
 
     async fn get_persons(&self, _request: GetPersonsRequest) -> Result<PersonsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(PersonsResponse {
             persons: vec![],
             missing_ids: vec![],
@@ -70,7 +126,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetPersonByUuidRequest,
     ) -> Result<GetPersonResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetPersonResponse {
             person: self.person_response.lock().unwrap().clone(),
         })
@@ -80,7 +136,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetPersonsByUuidsRequest,
     ) -> Result<PersonsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(PersonsResponse {
             persons: vec![],
             missing_ids: vec![],
@@ -91,7 +147,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetPersonByDistinctIdRequest,
     ) -> Result<GetPersonResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetPersonResponse {
             person: self.person_response.lock().unwrap().clone(),
         })
@@ -101,7 +157,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetPersonsByDistinctIdsInTeamRequest,
     ) -> Result<PersonsByDistinctIdsInTeamResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(PersonsByDistinctIdsInTeamResponse { results: vec![] })
     }
 
@@ -109,7 +165,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetPersonsByDistinctIdsRequest,
     ) -> Result<PersonsByDistinctIdsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(PersonsByDistinctIdsResponse { results: vec![] })
     }
 
@@ -117,7 +173,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetDistinctIdsForPersonRequest,
     ) -> Result<GetDistinctIdsForPersonResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetDistinctIdsForPersonResponse {
             distinct_ids: vec![],
         })
@@ -127,17 +183,51 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetDistinctIdsForPersonsRequest,
     ) -> Result<GetDistinctIdsForPersonsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetDistinctIdsForPersonsResponse {
             person_distinct_ids: vec![],
         })
     }
 
+    async fn scan_persons(&self, _request: ScanPersonsRequest) -> Result<PersonPageStream, Status> {
+        self.respond().await?;
+        Ok(self.scan_page())
+    }
+
+    async fn scan_cohort_members(
+        &self,
+        _request: ScanCohortMembersRequest,
+    ) -> Result<PersonPageStream, Status> {
+        self.respond().await?;
+        Ok(self.scan_page())
+    }
+
+    async fn update_person_properties(
+        &self,
+        _request: UpdatePersonPropertiesRequest,
+    ) -> Result<UpdatePersonPropertiesResponse, Status> {
+        self.respond().await?;
+        Ok(UpdatePersonPropertiesResponse {
+            person: self.person_response.lock().unwrap().clone(),
+        })
+    }
+
+    async fn merge_distinct_ids(
+        &self,
+        _request: MergeDistinctIdsRequest,
+    ) -> Result<MergeDistinctIdsResponse, Status> {
+        self.respond().await?;
+        Ok(MergeDistinctIdsResponse {
+            person: self.person_response.lock().unwrap().clone(),
+            deleted_person_ids: vec![],
+        })
+    }
+
     async fn get_hash_key_override_context(
         &self,
         _request: GetHashKeyOverrideContextRequest,
     ) -> Result<GetHashKeyOverrideContextResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetHashKeyOverrideContextResponse { results: vec![] })
     }
 
@@ -145,7 +235,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: UpsertHashKeyOverridesRequest,
     ) -> Result<UpsertHashKeyOverridesResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(UpsertHashKeyOverridesResponse { inserted_count: 0 })
     }
 
@@ -153,7 +243,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: DeleteHashKeyOverridesByTeamsRequest,
     ) -> Result<DeleteHashKeyOverridesByTeamsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(DeleteHashKeyOverridesByTeamsResponse { deleted_count: 0 })
     }
 
@@ -161,19 +251,19 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: CheckCohortMembershipRequest,
     ) -> Result<CohortMembershipResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(CohortMembershipResponse {
             memberships: vec![],
         })
     }
 
     async fn get_group(&self, _request: GetGroupRequest) -> Result<GetGroupResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetGroupResponse { group: None })
     }
 
     async fn get_groups(&self, _request: GetGroupsRequest) -> Result<GroupsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GroupsResponse {
             groups: vec![],
             missing_groups: vec![],
@@ -184,7 +274,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetGroupsBatchRequest,
     ) -> Result<GetGroupsBatchResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GetGroupsBatchResponse { results: vec![] })
     }
 
@@ -192,7 +282,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetGroupTypeMappingsByTeamIdRequest,
     ) -> Result<GroupTypeMappingsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GroupTypeMappingsResponse { mappings: vec![] })
     }
 
@@ -200,7 +290,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetGroupTypeMappingsByTeamIdsRequest,
     ) -> Result<GroupTypeMappingsBatchResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GroupTypeMappingsBatchResponse { results: vec![] })
     }
 
@@ -208,7 +298,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetGroupTypeMappingsByProjectIdRequest,
     ) -> Result<GroupTypeMappingsResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GroupTypeMappingsResponse { mappings: vec![] })
     }
 
@@ -216,7 +306,7 @@ impl PersonHogBackend for MockBackend {
         &self,
         _request: GetGroupTypeMappingsByProjectIdsRequest,
     ) -> Result<GroupTypeMappingsBatchResponse, Status> {
-        self.check_error()?;
+        self.respond().await?;
         Ok(GroupTypeMappingsBatchResponse { results: vec![] })
     }
 }
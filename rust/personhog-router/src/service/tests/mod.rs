@@ -1,4 +1,4 @@
-mod mocks;
+pub(crate) mod mocks;
 
 use std::sync::Arc;
 
@@ -6,7 +6,9 @@ use mocks::MockBackend;
 use personhog_proto::personhog::service::v1::person_hog_service_server::PersonHogService;
 use personhog_proto::personhog::types::v1::{
     ConsistencyLevel, GetPersonByDistinctIdRequest, GetPersonRequest, Person, ReadOptions,
+    ScanCohortMembersRequest, ScanPersonsRequest, UpdatePersonPropertiesRequest,
 };
+use tokio_stream::StreamExt;
 use tonic::{Request, Status};
 
 use crate::router::PersonHogRouter;
@@ -33,6 +35,11 @@ fn create_service_with_mock(mock: MockBackend) -> PersonHogRouterService {
     PersonHogRouterService::new(Arc::new(router))
 }
 
+fn create_service_with_leader(replica: MockBackend, leader: MockBackend) -> PersonHogRouterService {
+    let router = PersonHogRouter::new(Arc::new(replica)).with_leader_backend(Arc::new(leader));
+    PersonHogRouterService::new(Arc::new(router))
+}
+
 #[tokio::test]
 async fn test_get_person_routes_to_replica_with_eventual_consistency() {
     let mock = MockBackend::new();
@@ -144,3 +151,135 @@ async fn test_get_person_with_explicit_eventual_consistency_succeeds() {
     let response = service.get_person(request).await.unwrap();
     assert!(response.get_ref().person.is_some());
 }
+
+#[tokio::test]
+async fn test_get_person_with_strong_consistency_routes_to_leader() {
+    let replica = MockBackend::new();
+    replica.set_error(Status::failed_precondition(
+        "replica cannot serve strong reads",
+    ));
+    let leader = MockBackend::new();
+    leader.set_person_response(Some(create_test_person()));
+
+    let service = create_service_with_leader(replica, leader);
+
+    let request = Request::new(GetPersonRequest {
+        team_id: 1,
+        person_id: 1,
+        read_options: Some(ReadOptions {
+            consistency: ConsistencyLevel::Strong.into(),
+        }),
+    });
+
+    let response = service.get_person(request).await.unwrap();
+    assert_eq!(response.get_ref().person.as_ref().unwrap().id, 1);
+}
+
+#[tokio::test]
+async fn test_get_person_with_eventual_consistency_skips_leader() {
+    let replica = MockBackend::new();
+    replica.set_person_response(Some(create_test_person()));
+    let leader = MockBackend::new();
+    leader.set_error(Status::unavailable("leader should not be called"));
+
+    let service = create_service_with_leader(replica, leader);
+
+    let request = Request::new(GetPersonRequest {
+        team_id: 1,
+        person_id: 1,
+        read_options: None,
+    });
+
+    let response = service.get_person(request).await.unwrap();
+    assert!(response.get_ref().person.is_some());
+}
+
+#[tokio::test]
+async fn test_person_write_routes_to_leader() {
+    let replica = MockBackend::new();
+    replica.set_error(Status::unimplemented("replica cannot serve person writes"));
+    let leader = MockBackend::new();
+    leader.set_person_response(Some(create_test_person()));
+
+    let service = create_service_with_leader(replica, leader);
+
+    let request = Request::new(UpdatePersonPropertiesRequest {
+        team_id: 1,
+        person_id: 1,
+        set_properties: br#"{"plan": "scale"}"#.to_vec(),
+        set_once_properties: vec![],
+        unset_properties: vec![],
+    });
+
+    let response = service.update_person_properties(request).await.unwrap();
+    assert!(response.get_ref().person.is_some());
+}
+
+#[tokio::test]
+async fn test_person_write_without_leader_returns_unimplemented() {
+    let mock = MockBackend::new();
+    mock.set_person_response(Some(create_test_person()));
+
+    let service = create_service_with_mock(mock);
+
+    let request = Request::new(UpdatePersonPropertiesRequest {
+        team_id: 1,
+        person_id: 1,
+        set_properties: br#"{"plan": "scale"}"#.to_vec(),
+        set_once_properties: vec![],
+        unset_properties: vec![],
+    });
+
+    let status = service.update_person_properties(request).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::Unimplemented);
+    assert!(status.message().contains("personhog-leader"));
+}
+
+#[tokio::test]
+async fn test_scan_persons_streams_pages_from_replica() {
+    let replica = MockBackend::new();
+    replica.set_person_response(Some(create_test_person()));
+    let leader = MockBackend::new();
+    leader.set_error(Status::unavailable("leader should not be called"));
+
+    let service = create_service_with_leader(replica, leader);
+
+    let request = Request::new(ScanPersonsRequest {
+        team_id: 1,
+        cursor: 0,
+        page_size: 100,
+        property_keys: vec![],
+        read_options: None,
+    });
+
+    let pages: Vec<_> = service
+        .scan_persons(request)
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+    assert_eq!(pages.len(), 1);
+    let page = pages[0].as_ref().unwrap();
+    assert_eq!(page.persons[0].id, 1);
+    assert_eq!(page.next_cursor, 1);
+}
+
+#[tokio::test]
+async fn test_scan_cohort_members_with_strong_consistency_without_leader_returns_unimplemented() {
+    let service = create_service_with_mock(MockBackend::new());
+
+    let request = Request::new(ScanCohortMembersRequest {
+        team_id: 1,
+        cohort_id: 1,
+        cursor: 0,
+        page_size: 0,
+        property_keys: vec![],
+        read_options: Some(ReadOptions {
+            consistency: ConsistencyLevel::Strong.into(),
+        }),
+    });
+
+    let status = service.scan_cohort_members(request).await.err().unwrap();
+    assert_eq!(status.code(), tonic::Code::Unimplemented);
+}
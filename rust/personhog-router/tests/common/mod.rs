@@ -20,9 +20,12 @@ use personhog_proto::personhog::types::v1::{
     GetPersonsByDistinctIdsInTeamRequest, GetPersonsByDistinctIdsRequest, GetPersonsByUuidsRequest,
     GetPersonsRequest, GroupTypeMappingsBatchResponse, GroupTypeMappingsResponse, GroupsResponse,
     Person, PersonsByDistinctIdsInTeamResponse, PersonsByDistinctIdsResponse, PersonsResponse,
+    ScanCohortMembersRequest, ScanPersonsRequest, ScanPersonsResponse,
     UpsertHashKeyOverridesRequest, UpsertHashKeyOverridesResponse,
 };
-use personhog_router::backend::ReplicaBackend;
+use personhog_router::backend::{
+    Discovery, PersonHogBackend, ReplicaBackend, ReplicaPool, ReplicaPoolConfig,
+};
 use personhog_router::router::PersonHogRouter;
 use personhog_router::service::PersonHogRouterService;
 use tokio::net::TcpListener;
@@ -33,6 +36,8 @@ use personhog_proto::personhog::types::v1::{
     CohortMembership, Group, GroupTypeMapping, HashKeyOverrideContext, PersonWithDistinctIds,
 };
 
+type ScanStream = tokio_stream::Iter<std::vec::IntoIter<Result<ScanPersonsResponse, Status>>>;
+
 /// A configurable replica service implementation for integration tests.
 /// Supports setting up responses for different RPC methods.
 pub struct TestReplicaService {
@@ -97,10 +102,29 @@ impl TestReplicaService {
         self.group_type_mappings = mappings;
         self
     }
+
+    /// Scans yield the configured person as a single page, if it's past the cursor
+    #[allow(clippy::result_large_err)] // tonic::Status is large but we can't change it
+    fn scan_pages(&self, cursor: i64) -> ScanStream {
+        let pages = self
+            .person
+            .clone()
+            .filter(|person| person.id > cursor)
+            .map(|person| {
+                Ok(ScanPersonsResponse {
+                    next_cursor: person.id,
+                    persons: vec![person],
+                })
+            });
+        tokio_stream::iter(pages.into_iter().collect::<Vec<_>>())
+    }
 }
 
 #[tonic::async_trait]
 impl PersonHogReplica for TestReplicaService {
+    type ScanPersonsStream = ScanStream;
+    type ScanCohortMembersStream = ScanStream;
+
     async fn get_person(
         &self,
         _request: Request<GetPersonRequest>,
@@ -184,6 +208,13 @@ impl PersonHogReplica for TestReplicaService {
         }))
     }
 
+    async fn scan_persons(
+        &self,
+        request: Request<ScanPersonsRequest>,
+    ) -> Result<Response<Self::ScanPersonsStream>, Status> {
+        Ok(Response::new(self.scan_pages(request.into_inner().cursor)))
+    }
+
     async fn get_hash_key_override_context(
         &self,
         _request: Request<GetHashKeyOverrideContextRequest>,
@@ -220,6 +251,13 @@ impl PersonHogReplica for TestReplicaService {
         }))
     }
 
+    async fn scan_cohort_members(
+        &self,
+        request: Request<ScanCohortMembersRequest>,
+    ) -> Result<Response<Self::ScanCohortMembersStream>, Status> {
+        Ok(Response::new(self.scan_pages(request.into_inner().cursor)))
+    }
+
     async fn get_group(
         &self,
         _request: Request<GetGroupRequest>,
@@ -286,8 +324,14 @@ pub async fn start_test_replica(service: TestReplicaService) -> SocketAddr {
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let addr = listener.local_addr().unwrap();
 
+    let (mut health_reporter, health_service) = tonic_health::server::health_reporter();
+    health_reporter
+        .set_serving::<PersonHogReplicaServer<TestReplicaService>>()
+        .await;
+
     tokio::spawn(async move {
         Server::builder()
+            .add_service(health_service)
             .add_service(PersonHogReplicaServer::new(service))
             .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener))
             .await
@@ -307,7 +351,34 @@ pub async fn start_test_router(replica_addr: SocketAddr) -> SocketAddr {
 
     let replica_url = format!("http://{}", replica_addr);
     let backend = ReplicaBackend::new(&replica_url, Duration::from_secs(5)).unwrap();
-    let router = PersonHogRouter::new(Arc::new(backend));
+    serve_router(listener, Arc::new(backend)).await;
+
+    addr
+}
+
+/// Start a test router balancing across the given replica URLs, returning its address
+/// and the replica pool
+pub async fn start_test_router_with_pool(
+    replica_urls: Vec<String>,
+) -> (SocketAddr, Arc<ReplicaPool>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let pool = ReplicaPool::new(ReplicaPoolConfig::default(), |url| {
+        let backend: Arc<dyn PersonHogBackend> =
+            Arc::new(ReplicaBackend::new(url, Duration::from_secs(5))?);
+        Ok(backend)
+    })
+    .start(Discovery::Static(replica_urls))
+    .await
+    .unwrap();
+    serve_router(listener, pool.clone()).await;
+
+    (addr, pool)
+}
+
+async fn serve_router(listener: TcpListener, replica_backend: Arc<dyn PersonHogBackend>) {
+    let router = PersonHogRouter::new(replica_backend);
     let service = PersonHogRouterService::new(Arc::new(router));
 
     tokio::spawn(async move {
@@ -320,8 +391,6 @@ pub async fn start_test_router(replica_addr: SocketAddr) -> SocketAddr {
 
     // Give the server a moment to start
     tokio::time::sleep(Duration::from_millis(10)).await;
-
-    addr
 }
 
 /// Create a client connected to the router
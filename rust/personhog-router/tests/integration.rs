@@ -1,7 +1,8 @@
 mod common;
 
 use common::{
-    create_client, create_test_person, start_test_replica, start_test_router, TestReplicaService,
+    create_client, create_test_person, start_test_replica, start_test_router,
+    start_test_router_with_pool, TestReplicaService,
 };
 use personhog_proto::personhog::types::v1::{
     CheckCohortMembershipRequest, CohortMembership, ConsistencyLevel,
@@ -9,8 +10,10 @@ use personhog_proto::personhog::types::v1::{
     GetHashKeyOverrideContextRequest, GetPersonByDistinctIdRequest, GetPersonRequest,
     GetPersonsByDistinctIdsInTeamRequest, Group, GroupIdentifier, GroupTypeMapping,
     HashKeyOverride, HashKeyOverrideContext, HashKeyOverrideInput, Person, PersonWithDistinctIds,
-    ReadOptions, UpsertHashKeyOverridesRequest,
+    ReadOptions, ScanPersonsRequest, UpsertHashKeyOverridesRequest,
 };
+use tokio::net::TcpListener;
+use tokio_stream::StreamExt;
 
 #[tokio::test]
 async fn test_get_person_roundtrip() {
@@ -136,6 +139,51 @@ async fn test_get_persons_by_distinct_ids_in_team() {
 /// Tests cohort membership check - a batch operation that checks if a person
 /// belongs to multiple cohorts at once.
 /// Feature-flags uses this for cohort-based targeting.
+#[tokio::test]
+async fn test_scan_persons_streams_through_router() {
+    let test_person = create_test_person();
+    let replica_service = TestReplicaService::with_person(test_person.clone());
+
+    let replica_addr = start_test_replica(replica_service).await;
+    let router_addr = start_test_router(replica_addr).await;
+    let mut client = create_client(router_addr).await;
+
+    let pages: Vec<_> = client
+        .scan_persons(ScanPersonsRequest {
+            team_id: 1,
+            cursor: 0,
+            page_size: 100,
+            property_keys: vec![],
+            read_options: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+
+    assert_eq!(pages.len(), 1);
+    let page = pages[0].as_ref().unwrap();
+    assert_eq!(page.persons[0].id, test_person.id);
+    assert_eq!(page.next_cursor, test_person.id);
+
+    // Resuming from the last cursor finds nothing left
+    let remaining: Vec<_> = client
+        .scan_persons(ScanPersonsRequest {
+            team_id: 1,
+            cursor: page.next_cursor,
+            page_size: 100,
+            property_keys: vec![],
+            read_options: None,
+        })
+        .await
+        .unwrap()
+        .into_inner()
+        .collect()
+        .await;
+    assert!(remaining.is_empty());
+}
+
 #[tokio::test]
 async fn test_check_cohort_membership() {
     let memberships = vec![
@@ -507,3 +555,35 @@ async fn test_get_group_type_mappings_by_project_id() {
     assert_eq!(org_mapping.unwrap().group_type_index, 0);
     assert_eq!(org_mapping.unwrap().project_id, 100);
 }
+
+#[tokio::test]
+async fn test_pool_routes_around_unreachable_replica() {
+    let replica_addr =
+        start_test_replica(TestReplicaService::with_person(create_test_person())).await;
+    // Reserve a port, then free it so nothing is listening there
+    let unreachable_addr = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let (router_addr, pool) = start_test_router_with_pool(vec![
+        format!("http://{replica_addr}"),
+        format!("http://{unreachable_addr}"),
+    ])
+    .await;
+    pool.check_health().await;
+    let mut client = create_client(router_addr).await;
+
+    for _ in 0..10 {
+        let response = client
+            .get_person(GetPersonRequest {
+                team_id: 1,
+                person_id: 42,
+                read_options: None,
+            })
+            .await
+            .unwrap();
+        assert!(response.into_inner().person.is_some());
+    }
+}
@@ -6,6 +6,14 @@ pub const GROUP_TYPE_LIMIT: i32 = 5;
 pub const DEFAULT_QUERY_LIMIT: i64 = 100;
 pub const DEFAULT_QUERY_OFFSET: i64 = 0;
 
+pub const DEFAULT_PROPERTY_VALUES_LIMIT: usize = 20;
+pub const MAX_PROPERTY_VALUES_LIMIT: usize = 100;
+
+pub const DEFAULT_TYPE_CONFLICTS_LIMIT: usize = 100;
+pub const MAX_TYPE_CONFLICTS_LIMIT: usize = 1000;
+// how many sample values we return per conflicting type
+pub const TYPE_CONFLICT_SAMPLE_SIZE: usize = 10;
+
 pub const SEARCH_TRIGGER_WORD: &str = "latest";
 pub const SEARCH_SCREEN_WORD: &str = "initial";
 
@@ -37,6 +45,45 @@ pub const ENTERPRISE_PROP_DEFS_TABLE_COLUMNS: [&str; 7] = [
     // "deprecated_tags",
 ];
 
+pub const ENTERPRISE_EVENT_DEFS_TABLE: &str = "ee_enterpriseeventdefinition";
+pub const EVENT_DEFS_TABLE: &str = "posthog_eventdefinition";
+
+pub const EVENT_DEFS_TABLE_COLUMNS: [&str; 7] = [
+    "id",
+    "project_id",
+    "team_id",
+    "name",
+    "created_at",
+    "last_seen_at",
+    "volume_30_day",
+    // "query_usage_30_day"
+];
+
+pub const ENTERPRISE_EVENT_DEFS_TABLE_COLUMNS: [&str; 9] = [
+    "owner_id",
+    "description",
+    "verified",
+    "verified_at",
+    "verified_by_id",
+    "updated_at",
+    "updated_by_id",
+    "hidden",
+    "tags",
+    // "deprecated_tags", "default_columns"
+];
+
+// event definitions can be ordered by these, optionally prefixed with "-" for descending order
+// https://github.com/PostHog/posthog/blob/master/posthog/api/event_definition.py#L241-L264
+pub const EVENT_DEFS_ORDERING_FIELDS: [&str; 4] = [
+    "name",
+    "last_seen_at",
+    "last_seen_at::date",
+    "volume_30_day",
+];
+
+// events named with this prefix are sent by PostHog itself, rather than customers
+pub const POSTHOG_EVENT_NAME_PREFIX: &str = "$";
+
 // property definitions we don't want customers querying
 // https://github.com/PostHog/posthog/blob/master/posthog/taxonomy/property_definition_api.py#L343-L361
 pub const EVENTS_HIDDEN_PROPERTY_DEFINITIONS: [&str; 14] = [
@@ -1,11 +1,13 @@
 use crate::{
     api::v1::{
         constants::{
-            extract_aliases, ENTERPRISE_PROP_DEFS_TABLE, ENTERPRISE_PROP_DEFS_TABLE_COLUMNS,
-            EVENTS_HIDDEN_PROPERTY_DEFINITIONS, EVENT_PROPERTY_TABLE, EVENT_PROPERTY_TABLE_ALIAS,
+            extract_aliases, ENTERPRISE_EVENT_DEFS_TABLE, ENTERPRISE_EVENT_DEFS_TABLE_COLUMNS,
+            ENTERPRISE_PROP_DEFS_TABLE, ENTERPRISE_PROP_DEFS_TABLE_COLUMNS,
+            EVENTS_HIDDEN_PROPERTY_DEFINITIONS, EVENT_DEFS_TABLE, EVENT_DEFS_TABLE_COLUMNS,
+            EVENT_PROPERTY_TABLE, EVENT_PROPERTY_TABLE_ALIAS, POSTHOG_EVENT_NAME_PREFIX,
             PROPERTY_DEFS_TABLE, PROPERTY_DEFS_TABLE_COLUMNS, SEARCH_SCREEN_WORD,
         },
-        routing::Params,
+        routing::{EventDefinitionParams, EventDefinitionType, EventPropertyParams, Params},
     },
     //metrics_consts::{},
     types::PropertyParentType,
@@ -32,7 +34,7 @@ impl Manager {
     pub fn count_query<'args, 'builder: 'args>(
         &self,
         qb: &'builder mut QueryBuilder<'args, Postgres>,
-        project_id: i32,
+        project_id: i64,
         params: &'args Params,
     ) -> Query<'args, Postgres, PgArguments> {
         /* The original Django query formulation we're duplicating
@@ -104,7 +106,7 @@ impl Manager {
     pub fn property_definitions_query<'args, 'builder: 'args>(
         &self,
         qb: &'builder mut QueryBuilder<'args, Postgres>,
-        project_id: i32,
+        project_id: i64,
         params: &'args Params,
     ) -> Query<'args, Postgres, PgArguments> {
         /* The original Django query we're duplicating
@@ -201,6 +203,263 @@ impl Manager {
         qb.build()
     }
 
+    pub fn event_definitions_count_query<'args, 'builder: 'args>(
+        &self,
+        qb: &'builder mut QueryBuilder<'args, Postgres>,
+        project_id: i64,
+        params: &'args EventDefinitionParams,
+    ) -> Query<'args, Postgres, PgArguments> {
+        // the Django API counts by paginating over the query below, so we apply the same filters
+        qb.push("SELECT count(*) AS full_count ");
+
+        self.gen_event_defs_from_clause(qb, params.use_enterprise_taxonomy);
+        self.apply_event_defs_where_clause(qb, project_id, params);
+
+        // NOTE: count query is global per project_id, so no LIMIT/OFFSET handling is applied
+
+        qb.build()
+    }
+
+    pub fn event_definitions_query<'args, 'builder: 'args>(
+        &self,
+        qb: &'builder mut QueryBuilder<'args, Postgres>,
+        project_id: i64,
+        params: &'args EventDefinitionParams,
+    ) -> Query<'args, Postgres, PgArguments> {
+        /* The original Django query we're duplicating
+                 * https://github.com/PostHog/posthog/blob/master/posthog/api/event_definition.py#L34-L83
+
+        SELECT {",".join(event_definition_fields)}
+        FROM posthog_eventdefinition
+        {enterprise_join}
+        WHERE (project_id = %(project_id)s OR (project_id IS NULL AND team_id = %(project_id)s))
+        {conditions}
+        ORDER BY {",".join(additional_ordering)}
+
+                * where conditions are the search, event type, excluded events and hidden filters
+                */
+
+        self.gen_event_defs_select_clause(qb, params.use_enterprise_taxonomy);
+        self.gen_event_defs_from_clause(qb, params.use_enterprise_taxonomy);
+        self.apply_event_defs_where_clause(qb, project_id, params);
+
+        // ORDER BY clauses: as in Django, NULLs go first when ascending and last when descending
+        let orderings: Vec<String> = params
+            .ordering
+            .iter()
+            .map(|ordering| {
+                let column = match ordering.field {
+                    "last_seen_at::date" => format!("{EVENT_DEFS_TABLE}.\"last_seen_at\"::date"),
+                    field => format!("{EVENT_DEFS_TABLE}.\"{field}\""),
+                };
+                if ordering.descending {
+                    format!("{column} DESC NULLS LAST")
+                } else {
+                    format!("{column} ASC NULLS FIRST")
+                }
+            })
+            .collect();
+        qb.push(format!(" ORDER BY {} ", orderings.join(", ")));
+
+        // LIMIT and OFFSET clauses
+        qb.push(" LIMIT ");
+        qb.push_bind(params.limit);
+        qb.push(" OFFSET ");
+        qb.push_bind(params.offset);
+        qb.push(" ");
+
+        qb.build()
+    }
+
+    pub fn event_properties_count_query<'args, 'builder: 'args>(
+        &self,
+        qb: &'builder mut QueryBuilder<'args, Postgres>,
+        project_id: i64,
+        params: &'args EventPropertyParams,
+    ) -> Query<'args, Postgres, PgArguments> {
+        qb.push("SELECT count(*) AS full_count ");
+
+        self.gen_event_props_from_clause(qb);
+        self.apply_event_props_where_clause(qb, project_id, params);
+
+        // NOTE: count query is global per project_id, so no LIMIT/OFFSET handling is applied
+
+        qb.build()
+    }
+
+    // the properties seen with the requested events, along with the type
+    // their property definition has, if one has been written yet
+    pub fn event_properties_query<'args, 'builder: 'args>(
+        &self,
+        qb: &'builder mut QueryBuilder<'args, Postgres>,
+        project_id: i64,
+        params: &'args EventPropertyParams,
+    ) -> Query<'args, Postgres, PgArguments> {
+        qb.push(format!(
+            " SELECT {EVENT_PROPERTY_TABLE}.\"event\", {EVENT_PROPERTY_TABLE}.\"property\", {PROPERTY_DEFS_TABLE}.\"property_type\", {PROPERTY_DEFS_TABLE}.\"is_numerical\" "
+        ));
+
+        self.gen_event_props_from_clause(qb);
+        self.apply_event_props_where_clause(qb, project_id, params);
+
+        // ORDER BY clauses
+        qb.push(format!(
+            " ORDER BY {EVENT_PROPERTY_TABLE}.\"property\" ASC, {EVENT_PROPERTY_TABLE}.\"event\" ASC "
+        ));
+
+        // LIMIT and OFFSET clauses
+        qb.push(" LIMIT ");
+        qb.push_bind(params.limit);
+        qb.push(" OFFSET ");
+        qb.push_bind(params.offset);
+        qb.push(" ");
+
+        qb.build()
+    }
+
+    fn gen_event_defs_select_clause(
+        &self,
+        qb: &mut QueryBuilder<Postgres>,
+        use_enterprise_taxonomy: bool,
+    ) {
+        let mut selections = vec![];
+
+        for col_name in EVENT_DEFS_TABLE_COLUMNS {
+            selections.push(format!("{EVENT_DEFS_TABLE}.\"{col_name}\""));
+        }
+
+        // if we're JOINing in the enterprise event def, select ee-specific cols too
+        if use_enterprise_taxonomy {
+            for col_name in ENTERPRISE_EVENT_DEFS_TABLE_COLUMNS {
+                selections.push(format!("{ENTERPRISE_EVENT_DEFS_TABLE}.\"{col_name}\""));
+            }
+        }
+
+        qb.push(format!(" SELECT {}", selections.join(", ")));
+    }
+
+    fn gen_event_defs_from_clause(
+        &self,
+        qb: &mut QueryBuilder<Postgres>,
+        use_enterprise_taxonomy: bool,
+    ) {
+        let from_clause = if use_enterprise_taxonomy {
+            format!(
+                " FROM {EVENT_DEFS_TABLE} FULL OUTER JOIN {ENTERPRISE_EVENT_DEFS_TABLE} ON {EVENT_DEFS_TABLE}.\"id\"={ENTERPRISE_EVENT_DEFS_TABLE}.\"eventdefinition_ptr_id\" "
+            )
+        } else {
+            format!(" FROM {EVENT_DEFS_TABLE} ")
+        };
+        qb.push(from_clause);
+        qb.push(" ");
+    }
+
+    fn apply_event_defs_where_clause<'args>(
+        &self,
+        qb: &mut QueryBuilder<'args, Postgres>,
+        project_id: i64,
+        params: &'args EventDefinitionParams,
+    ) {
+        qb.push(format!(
+            "WHERE COALESCE({EVENT_DEFS_TABLE}.\"project_id\", {EVENT_DEFS_TABLE}.\"team_id\") = "
+        ));
+        qb.push_bind(project_id);
+        qb.push(" ");
+
+        // narrow to events sent by PostHog or by the customer, if requested
+        match params.event_type {
+            EventDefinitionType::Event => {}
+            EventDefinitionType::EventPostHog => {
+                qb.push(format!(
+                    " AND {EVENT_DEFS_TABLE}.\"name\" LIKE '{POSTHOG_EVENT_NAME_PREFIX}%' "
+                ));
+            }
+            EventDefinitionType::EventCustom => {
+                qb.push(format!(
+                    " AND {EVENT_DEFS_TABLE}.\"name\" NOT LIKE '{POSTHOG_EVENT_NAME_PREFIX}%' "
+                ));
+            }
+        }
+
+        if !params.excluded_events.is_empty() {
+            qb.push(format!(" AND NOT {EVENT_DEFS_TABLE}.\"name\" = ANY("));
+            qb.push_bind(&params.excluded_events);
+            qb.push(") ");
+        }
+
+        // "hidden" col only exists on the enterprise event defs table!
+        if params.exclude_hidden && params.use_enterprise_taxonomy {
+            qb.push(format!(
+                " AND ({ENTERPRISE_EVENT_DEFS_TABLE}.\"hidden\" IS NULL OR {ENTERPRISE_EVENT_DEFS_TABLE}.\"hidden\" = false) "
+            ));
+        }
+
+        self.conditionally_apply_term_search(
+            qb,
+            &format!("{EVENT_DEFS_TABLE}.\"name\""),
+            &params.search_terms,
+        );
+    }
+
+    fn gen_event_props_from_clause(&self, qb: &mut QueryBuilder<Postgres>) {
+        // LEFT JOIN, since a property can be seen with an event before its definition is written
+        qb.push(format!(
+            " FROM {EVENT_PROPERTY_TABLE} LEFT JOIN {PROPERTY_DEFS_TABLE} ON COALESCE({PROPERTY_DEFS_TABLE}.\"project_id\", {PROPERTY_DEFS_TABLE}.\"team_id\") = COALESCE({EVENT_PROPERTY_TABLE}.\"project_id\", {EVENT_PROPERTY_TABLE}.\"team_id\") AND {PROPERTY_DEFS_TABLE}.\"name\" = {EVENT_PROPERTY_TABLE}.\"property\" AND {PROPERTY_DEFS_TABLE}.\"type\" = {} ",
+            PropertyParentType::Event as i32
+        ));
+    }
+
+    fn apply_event_props_where_clause<'args>(
+        &self,
+        qb: &mut QueryBuilder<'args, Postgres>,
+        project_id: i64,
+        params: &'args EventPropertyParams,
+    ) {
+        qb.push(format!(
+            "WHERE COALESCE({EVENT_PROPERTY_TABLE}.\"project_id\", {EVENT_PROPERTY_TABLE}.\"team_id\") = "
+        ));
+        qb.push_bind(project_id);
+        qb.push(format!(" AND {EVENT_PROPERTY_TABLE}.\"event\" = ANY("));
+        qb.push_bind(&params.event_names);
+        qb.push(") ");
+
+        // as with event property definitions, we never return the hidden ones
+        qb.push(format!(
+            " AND NOT {EVENT_PROPERTY_TABLE}.\"property\" = ANY("
+        ));
+        let mut buf: Vec<&str> = vec![];
+        for entry in EVENTS_HIDDEN_PROPERTY_DEFINITIONS {
+            buf.push(entry);
+        }
+        for entry in params.excluded_properties.iter() {
+            buf.push(entry);
+        }
+        qb.push_bind(buf);
+        qb.push(") ");
+
+        self.conditionally_apply_term_search(
+            qb,
+            &format!("{EVENT_PROPERTY_TABLE}.\"property\""),
+            &params.search_terms,
+        );
+    }
+
+    // every search term must fuzzy-match the column. Unlike conditionally_apply_search_clause
+    // below, terms are bound rather than inlined, so callers don't need to restrict them
+    // https://github.com/PostHog/posthog/blob/master/posthog/filters.py#L62-L85
+    fn conditionally_apply_term_search(
+        &self,
+        qb: &mut QueryBuilder<Postgres>,
+        column: &str,
+        search_terms: &[String],
+    ) {
+        for term in search_terms {
+            qb.push(format!(" AND {column} ILIKE "));
+            qb.push_bind(format!("%{}%", escape_like(term)));
+            qb.push(" ");
+        }
+    }
+
     fn gen_prop_defs_select_clause(
         &self,
         qb: &mut QueryBuilder<Postgres>,
@@ -240,7 +499,7 @@ impl Manager {
     fn conditionally_join_event_properties<'args>(
         &self,
         qb: &mut QueryBuilder<'args, Postgres>,
-        project_id: i32,
+        project_id: i64,
         parent_type: PropertyParentType,
         event_names: &'args [String],
     ) {
@@ -272,7 +531,7 @@ impl Manager {
         }
     }
 
-    fn init_where_clause(&self, qb: &mut QueryBuilder<Postgres>, project_id: i32) {
+    fn init_where_clause(&self, qb: &mut QueryBuilder<Postgres>, project_id: i64) {
         qb.push(format!(
             "WHERE COALESCE({PROPERTY_DEFS_TABLE}.\"project_id\", {PROPERTY_DEFS_TABLE}.\"team_id\") = "
         ));
@@ -477,3 +736,29 @@ impl Manager {
         parent_type == PropertyParentType::Event
     }
 }
+
+// escapes LIKE wildcards in a user supplied search term, so "_" and "%" match themselves.
+// Backslash is the default escape character in Postgres LIKE patterns
+fn escape_like(term: &str) -> String {
+    let mut escaped = String::with_capacity(term.len());
+    for c in term.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape_like;
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("signed_up"), "signed\\_up");
+        assert_eq!(escape_like("100%"), "100\\%");
+        assert_eq!(escape_like("a\\b"), "a\\\\b");
+        assert_eq!(escape_like("plain"), "plain");
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{
     api::v1::{constants::*, errors::ApiError, query::Manager},
+    type_tracking::{read_type_conflicts, TypeObservation},
     types::PropertyParentType,
+    value_sampling::{read_value_summary, TopValue},
     //metrics_consts::{},
     AppContext,
 };
@@ -26,6 +28,22 @@ pub fn apply_routes(parent: Router, app_ctx: Arc<AppContext>) -> Router {
             "/projects/:project_id/property_definitions",
             get(project_property_definitions_handler),
         )
+        .route(
+            "/projects/:project_id/event_definitions",
+            get(project_event_definitions_handler),
+        )
+        .route(
+            "/projects/:project_id/event_properties",
+            get(project_event_properties_handler),
+        )
+        .route(
+            "/projects/:project_id/property_values",
+            get(project_property_values_handler),
+        )
+        .route(
+            "/projects/:project_id/property_type_conflicts",
+            get(project_property_type_conflicts_handler),
+        )
         .with_state(app_ctx);
 
     parent.nest("/api/v1", api_router)
@@ -33,7 +51,7 @@ pub fn apply_routes(parent: Router, app_ctx: Arc<AppContext>) -> Router {
 
 async fn project_property_definitions_handler(
     State(app_ctx): State<Arc<AppContext>>,
-    Path(project_id): Path<i32>,
+    Path(project_id): Path<i64>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<PropertyDefinitionResponse>, ApiError> {
     // parse and validate request's query params
@@ -87,9 +105,241 @@ async fn project_property_definitions_handler(
     }))
 }
 
-fn parse_request(params: HashMap<String, String>) -> Params {
-    // which category of properties do we filter for? default is "event"
-    let parent_type = params
+async fn project_event_definitions_handler(
+    State(app_ctx): State<Arc<AppContext>>,
+    Path(project_id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<EventDefinitionResponse>, ApiError> {
+    // parse and validate request's query params
+    let params = parse_event_definitions_request(params);
+    params.valid()?;
+    debug!(
+        "Request for event definitions for project_id({}) w/params: {:?}",
+        project_id, &params
+    );
+
+    let qmgr: &Manager = &app_ctx.query_manager;
+
+    let mut count_bldr = QueryBuilder::<Postgres>::new("");
+    let count_query = qmgr.event_definitions_count_query(&mut count_bldr, project_id, &params);
+    let count_dbg: String = count_query.sql().into();
+    debug!("Event defs count query: {:?}", &count_dbg);
+
+    let mut defs_bldr = QueryBuilder::<Postgres>::new("");
+    let defs_query = qmgr.event_definitions_query(&mut defs_bldr, project_id, &params);
+    let defs_dbg: String = defs_query.sql().into();
+    debug!("Event defs query: {:?}", &defs_dbg);
+
+    let total_count: i64 = match qmgr.pool.fetch_one(count_query).await {
+        Ok(row) => row.get(0),
+        Err(e) => {
+            return Err(ApiError::QueryError(format!(
+                "executing event defs count query: {e}"
+            )))
+        }
+    };
+
+    let mut event_defs: Vec<EventDefinition> = vec![];
+    match qmgr.pool.fetch_all(defs_query).await {
+        Ok(result) => {
+            for row in result {
+                let ed = EventDefinition::from_row(&row).map_err(|e| {
+                    ApiError::QueryError(format!("deserializing event defs row: {e}"))
+                })?;
+                event_defs.push(ed);
+            }
+        }
+        Err(e) => {
+            return Err(ApiError::QueryError(format!(
+                "executing event defs query: {e}"
+            )))
+        }
+    }
+
+    Ok(Json(EventDefinitionResponse {
+        count: total_count,
+        results: event_defs,
+    }))
+}
+
+async fn project_event_properties_handler(
+    State(app_ctx): State<Arc<AppContext>>,
+    Path(project_id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<EventPropertyResponse>, ApiError> {
+    // parse and validate request's query params
+    let params = parse_event_properties_request(params);
+    params.valid()?;
+    debug!(
+        "Request for event properties for project_id({}) w/params: {:?}",
+        project_id, &params
+    );
+
+    let qmgr: &Manager = &app_ctx.query_manager;
+
+    let mut count_bldr = QueryBuilder::<Postgres>::new("");
+    let count_query = qmgr.event_properties_count_query(&mut count_bldr, project_id, &params);
+    let count_dbg: String = count_query.sql().into();
+    debug!("Event props count query: {:?}", &count_dbg);
+
+    let mut props_bldr = QueryBuilder::<Postgres>::new("");
+    let props_query = qmgr.event_properties_query(&mut props_bldr, project_id, &params);
+    let props_dbg: String = props_query.sql().into();
+    debug!("Event props query: {:?}", &props_dbg);
+
+    let total_count: i64 = match qmgr.pool.fetch_one(count_query).await {
+        Ok(row) => row.get(0),
+        Err(e) => {
+            return Err(ApiError::QueryError(format!(
+                "executing event props count query: {e}"
+            )))
+        }
+    };
+
+    let mut event_props: Vec<EventProperty> = vec![];
+    match qmgr.pool.fetch_all(props_query).await {
+        Ok(result) => {
+            for row in result {
+                let ep = EventProperty::from_row(&row).map_err(|e| {
+                    ApiError::QueryError(format!("deserializing event props row: {e}"))
+                })?;
+                event_props.push(ep);
+            }
+        }
+        Err(e) => {
+            return Err(ApiError::QueryError(format!(
+                "executing event props query: {e}"
+            )))
+        }
+    }
+
+    Ok(Json(EventPropertyResponse {
+        count: total_count,
+        results: event_props,
+    }))
+}
+
+async fn project_property_values_handler(
+    State(app_ctx): State<Arc<AppContext>>,
+    Path(project_id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<PropertyValuesResponse>, ApiError> {
+    let key = params
+        .get("key")
+        .filter(|k| !k.is_empty())
+        .ok_or_else(|| ApiError::InvalidRequestParam("parameter 'key' is required".to_string()))?;
+    let parent_type = parse_parent_type(&params);
+    let group_type_index = require_group_type_index(&params, parent_type)?;
+    // unlike the property definitions API, search here is a plain substring match on the values
+    let search = params.get("search").map(|s| s.to_lowercase());
+    let limit = params
+        .get("limit")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_PROPERTY_VALUES_LIMIT)
+        .min(MAX_PROPERTY_VALUES_LIMIT);
+    debug!(
+        "Request for property values of {} for project_id({}) w/params: {:?}",
+        key, project_id, &params
+    );
+
+    let qmgr: &Manager = &app_ctx.query_manager;
+    let summary = read_value_summary(
+        &qmgr.pool,
+        project_id,
+        parent_type,
+        group_type_index,
+        key,
+        limit,
+        limit,
+    )
+    .await
+    .map_err(|e| ApiError::QueryError(format!("reading property value stats: {e}")))?;
+    let (summary, updated_at) = match summary {
+        Some((summary, updated_at)) => (summary, Some(updated_at)),
+        None => Default::default(),
+    };
+
+    let matches = |value: &str| {
+        search
+            .as_ref()
+            .is_none_or(|search| value.to_lowercase().contains(search))
+    };
+
+    Ok(Json(PropertyValuesResponse {
+        key: key.clone(),
+        sample_values: summary
+            .sample_values
+            .into_iter()
+            .filter(|v| matches(v))
+            .collect(),
+        top_values: summary
+            .top_values
+            .into_iter()
+            .filter(|tv| matches(&tv.value))
+            .collect(),
+        updated_at,
+    }))
+}
+
+async fn project_property_type_conflicts_handler(
+    State(app_ctx): State<Arc<AppContext>>,
+    Path(project_id): Path<i64>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<PropertyTypeConflictsResponse>, ApiError> {
+    let parent_type = parse_parent_type(&params);
+    let group_type_index = require_group_type_index(&params, parent_type)?;
+    // optional: look up a single property rather than listing all of them
+    let key = params.get("key").filter(|k| !k.is_empty());
+    let limit = params
+        .get("limit")
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_TYPE_CONFLICTS_LIMIT)
+        .min(MAX_TYPE_CONFLICTS_LIMIT);
+    debug!(
+        "Request for property type conflicts for project_id({}) w/params: {:?}",
+        project_id, &params
+    );
+
+    let qmgr: &Manager = &app_ctx.query_manager;
+    let conflicts = read_type_conflicts(
+        &qmgr.pool,
+        project_id,
+        parent_type,
+        group_type_index,
+        key.map(String::as_str),
+        TYPE_CONFLICT_SAMPLE_SIZE,
+    )
+    .await
+    .map_err(|e| ApiError::QueryError(format!("reading property type stats: {e}")))?;
+
+    let count = conflicts.len();
+    let results = conflicts
+        .into_iter()
+        .take(limit)
+        .map(|conflict| {
+            let total: u64 = conflict.observations.iter().map(|o| o.count).sum();
+            PropertyTypeConflict {
+                name: conflict.name,
+                defined_type: conflict.defined_type,
+                observed_types: conflict
+                    .observations
+                    .into_iter()
+                    .map(|observation| ObservedType {
+                        share: observation.count as f64 / total.max(1) as f64,
+                        observation,
+                    })
+                    .collect(),
+                updated_at: conflict.updated_at,
+            }
+        })
+        .collect();
+
+    Ok(Json(PropertyTypeConflictsResponse { count, results }))
+}
+
+// which category of properties do we filter for? default is "event"
+fn parse_parent_type(params: &HashMap<String, String>) -> PropertyParentType {
+    params
         .get("type")
         .map_or(PropertyParentType::Event, |s| match s.as_str() {
             "event" => PropertyParentType::Event,
@@ -97,8 +347,173 @@ fn parse_request(params: HashMap<String, String>) -> Params {
             "group" => PropertyParentType::Group,
             "session" => PropertyParentType::Session,
             _ => PropertyParentType::Event,
+        })
+}
+
+// defaults to "-1" if the caller didn't supply the group_type_index, or the parent_type != "group"
+fn parse_group_type_index(
+    params: &HashMap<String, String>,
+    parent_type: PropertyParentType,
+) -> i32 {
+    params.get("group_type_index").map_or(-1, |s| {
+        s.parse::<i32>().ok().map_or(-1, |gti| {
+            if parent_type == PropertyParentType::Group {
+                gti
+            } else {
+                -1
+            }
+        })
+    })
+}
+
+// like parse_group_type_index, but group properties' stats are kept per group type, so we can't
+// look them up without a valid one
+fn require_group_type_index(
+    params: &HashMap<String, String>,
+    parent_type: PropertyParentType,
+) -> Result<i32, ApiError> {
+    let group_type_index = parse_group_type_index(params, parent_type);
+    if parent_type == PropertyParentType::Group
+        && !(0..GROUP_TYPE_LIMIT).contains(&group_type_index)
+    {
+        return Err(ApiError::InvalidRequestParam(
+            "property_type 'group' requires valid 'group_type_index' parameter".to_string(),
+        ));
+    }
+    Ok(group_type_index)
+}
+
+// search terms: optional - each term must fuzzy-match (ILIKE '%term%') for a row to be returned.
+// Unlike the property definitions API, terms are bound as query params, so aren't restricted,
+// and any LIKE wildcards in them are escaped to match literally
+// DIVERGES FROM DJANGO API: the new Rust API will accept lists as space-separated query param values
+fn parse_search_terms(params: &HashMap<String, String>) -> Vec<String> {
+    params
+        .get("search")
+        .map(|raw| {
+            raw.replace('\0', "")
+                .split(" ")
+                .map(|s| s.trim().to_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// DIVERGES FROM DJANGO API: the new Rust API will accept lists as space-separated query param values
+fn parse_list(params: &HashMap<String, String>, key: &str) -> Vec<String> {
+    params
+        .get(key)
+        .map(|raw| {
+            raw.split(" ")
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_limit_and_offset(params: &HashMap<String, String>) -> (i64, i64) {
+    let limit: i64 = params.get("limit").map_or(DEFAULT_QUERY_LIMIT, |s| {
+        s.parse::<i64>().unwrap_or(DEFAULT_QUERY_LIMIT)
+    });
+
+    let offset: i64 = params.get("offset").map_or(DEFAULT_QUERY_OFFSET, |s| {
+        s.parse::<i64>().unwrap_or(DEFAULT_QUERY_OFFSET)
+    });
+
+    (limit, offset)
+}
+
+fn parse_event_definitions_request(params: HashMap<String, String>) -> EventDefinitionParams {
+    let search_terms = parse_search_terms(&params);
+
+    // which events do we list? default is all of them
+    // https://github.com/PostHog/posthog/blob/master/posthog/constants.py#L297-L303
+    let event_type = params
+        .get("event_type")
+        .map_or(EventDefinitionType::Event, |s| match s.as_str() {
+            "event_posthog" => EventDefinitionType::EventPostHog,
+            "event_custom" => EventDefinitionType::EventCustom,
+            _ => EventDefinitionType::Event,
         });
 
+    // DIVERGES FROM DJANGO API: the Django API calls this list "excluded_properties"
+    let excluded_events = parse_list(&params, "excluded_events");
+
+    // hidden status only exists on the enterprise event defs table, validated downstream
+    let exclude_hidden = params
+        .get("exclude_hidden")
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(false);
+
+    // unknown fields are ignored, as the Django API does. When none are
+    // supplied, we default to most recently seen (by day) first, then by name
+    // DIVERGES FROM DJANGO API: the new Rust API will accept lists as space-separated query param values
+    let mut ordering: Vec<EventDefinitionOrdering> = parse_list(&params, "ordering")
+        .iter()
+        .filter_map(|raw| {
+            let (field, descending) = match raw.strip_prefix('-') {
+                Some(field) => (field, true),
+                None => (raw.as_str(), false),
+            };
+            EVENT_DEFS_ORDERING_FIELDS
+                .iter()
+                .find(|f| **f == field)
+                .map(|&field| EventDefinitionOrdering { field, descending })
+        })
+        .collect();
+    if ordering.is_empty() {
+        ordering = vec![
+            EventDefinitionOrdering {
+                field: "last_seen_at::date",
+                descending: true,
+            },
+            EventDefinitionOrdering {
+                field: "name",
+                descending: false,
+            },
+        ];
+    }
+
+    // see the note on this param in parse_request below
+    let use_enterprise_taxonomy = params
+        .get("use_enterprise_taxonomy")
+        .and_then(|s| s.parse::<bool>().ok())
+        .unwrap_or(true);
+
+    let (limit, offset) = parse_limit_and_offset(&params);
+
+    EventDefinitionParams {
+        search_terms,
+        event_type,
+        excluded_events,
+        exclude_hidden,
+        ordering,
+        use_enterprise_taxonomy,
+        limit,
+        offset,
+    }
+}
+
+fn parse_event_properties_request(params: HashMap<String, String>) -> EventPropertyParams {
+    let search_terms = parse_search_terms(&params);
+    let event_names = parse_list(&params, "event_names");
+    let excluded_properties = parse_list(&params, "excluded_properties");
+    let (limit, offset) = parse_limit_and_offset(&params);
+
+    EventPropertyParams {
+        search_terms,
+        event_names,
+        excluded_properties,
+        limit,
+        offset,
+    }
+}
+
+fn parse_request(params: HashMap<String, String>) -> Params {
+    let parent_type = parse_parent_type(&params);
+
     // search terms: optional - each term is a fragment that will be
     // fuzzy-searched in Postgres against the specified search fields
     // DIVERGES FROM DJANGO API: the new Rust API will accept lists as space-separated query param values
@@ -137,17 +552,8 @@ fn parse_request(params: HashMap<String, String>) -> Params {
         }
     }
 
-    // defaults to "-1" if the caller didn't supply the group_type_index, or the parent_type != "group"
-    let group_type_index: i32 = params.get("group_type_index").map_or(-1, |s| {
-        s.parse::<i32>().ok().map_or(-1, |gti| {
-            if parent_type == PropertyParentType::Group {
-                // group_type_index value on "group" type query is validated downstream
-                gti
-            } else {
-                -1
-            }
-        })
-    });
+    // group_type_index value on "group" type query is validated downstream
+    let group_type_index = parse_group_type_index(&params, parent_type);
 
     // DIVERGES FROM DJANGO API: the new Rust API will accept lists as space-separated query param values
     let properties: Vec<String> = params
@@ -189,13 +595,7 @@ fn parse_request(params: HashMap<String, String>) -> Params {
         .map(|raw| raw.split(" ").map(|s| s.trim().to_string()).collect())
         .unwrap_or_default();
 
-    let limit: i64 = params.get("limit").map_or(DEFAULT_QUERY_LIMIT, |s| {
-        s.parse::<i64>().unwrap_or(DEFAULT_QUERY_LIMIT)
-    });
-
-    let offset: i64 = params.get("offset").map_or(DEFAULT_QUERY_OFFSET, |s| {
-        s.parse::<i64>().unwrap_or(DEFAULT_QUERY_OFFSET)
-    });
+    let (limit, offset) = parse_limit_and_offset(&params);
 
     Params {
         search_terms,
@@ -259,6 +659,77 @@ impl Params {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventDefinitionType {
+    Event,
+    EventPostHog,
+    EventCustom,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventDefinitionOrdering {
+    // always one of EVENT_DEFS_ORDERING_FIELDS
+    pub field: &'static str,
+    pub descending: bool,
+}
+
+#[derive(Debug)]
+pub struct EventDefinitionParams {
+    pub search_terms: Vec<String>,
+    pub event_type: EventDefinitionType,
+    pub excluded_events: Vec<String>,
+    pub exclude_hidden: bool,
+    pub ordering: Vec<EventDefinitionOrdering>,
+    pub use_enterprise_taxonomy: bool,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl EventDefinitionParams {
+    pub fn valid(&self) -> Result<(), ApiError> {
+        if self.exclude_hidden && !self.use_enterprise_taxonomy {
+            return Err(ApiError::InvalidRequestParam(
+                "parameter 'exclude_hidden' requires 'use_enterprise_taxonomy'".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for EventDefinitionParams {
+    fn default() -> Self {
+        parse_event_definitions_request(HashMap::new())
+    }
+}
+
+#[derive(Debug)]
+pub struct EventPropertyParams {
+    pub search_terms: Vec<String>,
+    pub event_names: Vec<String>,
+    pub excluded_properties: Vec<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl EventPropertyParams {
+    pub fn valid(&self) -> Result<(), ApiError> {
+        if self.event_names.is_empty() {
+            return Err(ApiError::InvalidRequestParam(
+                "parameter 'event_names' is required".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for EventPropertyParams {
+    fn default() -> Self {
+        parse_event_properties_request(HashMap::new())
+    }
+}
+
 impl Default for Params {
     fn default() -> Self {
         Params {
@@ -304,3 +775,88 @@ pub struct PropertyDefinition {
     verified_by_id: Option<i64>,
     tags: Option<Vec<String>>,
 }
+
+#[derive(Serialize)]
+pub struct EventDefinitionResponse {
+    count: i64,
+    results: Vec<EventDefinition>,
+}
+
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct EventDefinition {
+    id: uuid::Uuid,
+    name: String,
+    created_at: Option<DateTime<Utc>>,
+    last_seen_at: Option<DateTime<Utc>>,
+    volume_30_day: Option<i32>,
+    // enterprise taxonomy columns, unset if not requested
+    #[sqlx(default)]
+    owner_id: Option<i32>,
+    #[sqlx(default)]
+    description: Option<String>,
+    #[sqlx(default)]
+    verified: Option<bool>,
+    #[sqlx(default)]
+    verified_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    verified_by_id: Option<i32>,
+    #[sqlx(default)]
+    updated_at: Option<DateTime<Utc>>,
+    #[sqlx(default)]
+    updated_by_id: Option<i32>,
+    #[sqlx(default)]
+    hidden: Option<bool>,
+    #[sqlx(default)]
+    tags: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+pub struct EventPropertyResponse {
+    count: i64,
+    results: Vec<EventProperty>,
+}
+
+#[derive(Serialize, Deserialize, FromRow)]
+pub struct EventProperty {
+    event: String,
+    property: String,
+    // from the property's definition, unset if it doesn't have one yet
+    property_type: Option<String>,
+    is_numerical: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct PropertyValuesResponse {
+    key: String,
+    // most recently seen first
+    sample_values: Vec<String>,
+    // most common first, with approximate counts
+    top_values: Vec<TopValue>,
+    // unset if we've never sampled values for this property
+    updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct PropertyTypeConflictsResponse {
+    count: usize,
+    // most recently updated first
+    results: Vec<PropertyTypeConflict>,
+}
+
+#[derive(Serialize)]
+pub struct PropertyTypeConflict {
+    name: String,
+    // the type on the property definition, which is what insights treat every value as
+    defined_type: Option<String>,
+    // most common first
+    observed_types: Vec<ObservedType>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+pub struct ObservedType {
+    #[serde(flatten)]
+    observation: TypeObservation,
+    // fraction of all the property's observed values that had this type
+    share: f64,
+}
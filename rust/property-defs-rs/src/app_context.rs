@@ -88,6 +88,22 @@ impl AppContext {
     pub async fn resolve_group_types_indexes(
         &self,
         updates: &mut [Update],
+    ) -> Result<(), sqlx::Error> {
+        let group_types = updates
+            .iter_mut()
+            .filter_map(|update| match update {
+                Update::Property(update) => Some((update.team_id, &mut update.group_type_index)),
+                _ => None,
+            })
+            .collect();
+        self.resolve_group_types(group_types).await
+    }
+
+    // Resolves the given teams' group types in place, from the cache or the DB. Group types
+    // that don't exist for their team are cleared.
+    pub async fn resolve_group_types(
+        &self,
+        mut group_types: Vec<(i32, &mut Option<GroupType>)>,
     ) -> Result<(), sqlx::Error> {
         if self.skip_reads {
             return Ok(());
@@ -97,22 +113,18 @@ impl AppContext {
         let mut to_resolve: Vec<(usize, String, i32)> = Vec::new();
 
         // First pass: check cache and collect uncached items
-        for (idx, update) in updates.iter_mut().enumerate() {
-            let Update::Property(update) = update else {
-                continue;
-            };
-            let Some(GroupType::Unresolved(group_name)) = &update.group_type_index else {
+        for (idx, (team_id, group_type)) in group_types.iter_mut().enumerate() {
+            let Some(GroupType::Unresolved(group_name)) = &**group_type else {
                 continue;
             };
 
-            let cache_key = format!("{}:{}", update.team_id, group_name);
+            let cache_key = format!("{}:{}", team_id, group_name);
 
             if let Some(index) = self.group_type_cache.get(&cache_key) {
                 metrics::counter!(GROUP_TYPE_CACHE, &[("action", "hit")]).increment(1);
-                update.group_type_index =
-                    update.group_type_index.take().map(|gti| gti.resolve(index));
+                **group_type = group_type.take().map(|gti| gti.resolve(index));
             } else {
-                to_resolve.push((idx, group_name.clone(), update.team_id));
+                to_resolve.push((idx, group_name.clone(), *team_id));
             }
         }
 
@@ -153,27 +165,23 @@ impl AppContext {
                 resolved_map.insert((result.group_type, result.team_id), result.group_type_index);
             }
 
-            // Second pass: apply resolved group types to updates
+            // Second pass: apply resolved group types
             for (idx, group_name, team_id) in to_resolve {
                 let cache_key = format!("{team_id}:{group_name}");
+                let (_, group_type) = &mut group_types[idx];
 
                 if let Some(&index) = resolved_map.get(&(group_name.clone(), team_id)) {
                     metrics::counter!(GROUP_TYPE_CACHE, &[("action", "miss")]).increment(1);
                     self.group_type_cache.insert(cache_key, index);
 
-                    if let Update::Property(update) = &mut updates[idx] {
-                        update.group_type_index =
-                            update.group_type_index.take().map(|gti| gti.resolve(index));
-                    }
+                    **group_type = group_type.take().map(|gti| gti.resolve(index));
                 } else {
                     metrics::counter!(GROUP_TYPE_CACHE, &[("action", "fail")]).increment(1);
                     warn!(
                         "Failed to resolve group type index for group name: {group_name} and team id: {team_id}"
                     );
 
-                    if let Update::Property(update) = &mut updates[idx] {
-                        update.group_type_index = None;
-                    }
+                    **group_type = None;
                 }
             }
         }
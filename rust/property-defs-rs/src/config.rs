@@ -114,6 +114,53 @@ pub struct Config {
     // TODO: rename deploy cfg var to "write_batch_size" and update this after to complete the cutover!
     #[envconfig(default = "100")]
     pub write_batch_size: usize,
+
+    // Sample recent distinct values and track the most common values of each property, so filter
+    // autocomplete can be served without querying ClickHouse
+    #[envconfig(default = "false")]
+    pub enable_value_sampling: bool,
+
+    // Teams whose property values are never sampled
+    #[envconfig(default = "")]
+    pub value_sampling_opt_out_teams: TeamList,
+
+    // Longer values are never sampled. Short values are far less likely to be personal data, and
+    // far more likely to be useful for autocomplete
+    #[envconfig(default = "64")]
+    pub value_sampling_max_value_length: usize,
+
+    // How many recent distinct values, and how many of the most common values, we keep per property
+    #[envconfig(default = "20")]
+    pub value_sampling_sample_size: usize,
+    #[envconfig(default = "10")]
+    pub value_sampling_top_k: usize,
+
+    // Bounds memory use - once this many properties are being tracked, values of properties we
+    // aren't tracking yet are dropped until the next flush
+    #[envconfig(default = "20000")]
+    pub value_sampling_max_properties: usize,
+
+    // How often sampled values are merged into Postgres
+    #[envconfig(default = "60")]
+    pub value_sampling_flush_interval_secs: u64,
+
+    // Track which types each property's values are detected as, and record properties seen with
+    // more than one, since only the first type seen ends up on the property definition. Samples of
+    // the conflicting values follow the value sampling length limit and opt-outs above.
+    #[envconfig(default = "false")]
+    pub enable_type_tracking: bool,
+
+    // How many recent values we keep per property and type
+    #[envconfig(default = "5")]
+    pub type_tracking_sample_size: usize,
+
+    // Bounds memory use, as for value sampling
+    #[envconfig(default = "50000")]
+    pub type_tracking_max_properties: usize,
+
+    // How often conflicting properties are merged into Postgres
+    #[envconfig(default = "60")]
+    pub type_tracking_flush_interval_secs: u64,
 }
 
 #[derive(Clone)]
@@ -10,12 +10,14 @@ use metrics_consts::{
     RECV_DEQUEUED, SKIPPED_DUE_TO_TEAM_FILTER, UPDATES_FILTERED_BY_CACHE, UPDATES_PER_EVENT,
     UPDATES_SEEN, UPDATE_PRODUCER_OFFSET, WORKER_BLOCKED,
 };
-use types::{Event, Update};
+use types::{Event, PropertyObservers, Update};
 
 use ahash::AHashSet;
 use tokio::sync::mpsc::error::TrySendError;
 use tracing::{error, warn};
+use type_tracking::TypeTracker;
 use update_cache::Cache;
+use value_sampling::ValueSampler;
 
 use crate::{
     measuring_channel::{MeasuringReceiver, MeasuringSender},
@@ -28,8 +30,10 @@ pub mod batch_ingestion;
 pub mod config;
 pub mod measuring_channel;
 pub mod metrics_consts;
+pub mod type_tracking;
 pub mod types;
 pub mod update_cache;
+pub mod value_sampling;
 
 pub async fn update_consumer_loop(
     config: Config,
@@ -145,6 +149,8 @@ pub async fn update_producer_loop(
     consumer: SingleTopicConsumer,
     shared_cache: Arc<Cache>,
     channel: MeasuringSender<Update>,
+    value_sampler: Option<Arc<ValueSampler>>,
+    type_tracker: Option<Arc<TypeTracker>>,
 ) {
     let mut batch = AHashSet::with_capacity(config.compaction_batch_size);
     let mut last_send = tokio::time::Instant::now();
@@ -191,7 +197,21 @@ pub async fn update_producer_loop(
             continue;
         }
 
-        let updates = event.into_updates(config.update_count_skip_threshold);
+        let (team_id, project_id) = (event.team_id, event.project_id);
+        let observers = PropertyObservers {
+            value_sampler: value_sampler
+                .as_deref()
+                .filter(|sampler| sampler.is_enabled_for(team_id)),
+            type_tracker: type_tracker.as_deref(),
+        };
+        let (updates, observations) =
+            event.into_updates_with_observations(config.update_count_skip_threshold, observers);
+        if let Some(sampler) = observers.value_sampler {
+            sampler.observe(team_id, project_id, observations.values);
+        }
+        if let Some(tracker) = observers.type_tracker {
+            tracker.observe(team_id, project_id, observations.types);
+        }
 
         metrics::counter!(EVENTS_RECEIVED).increment(1);
         metrics::counter!(UPDATES_SEEN).increment(updates.len() as u64);
@@ -10,8 +10,10 @@ use property_defs_rs::{
     config::Config,
     measuring_channel::measuring_channel,
     metrics_consts::CHANNEL_CAPACITY,
+    type_tracking::{type_tracking_flush_loop, TypeTracker},
     update_cache::Cache,
     update_consumer_loop, update_producer_loop,
+    value_sampling::{value_sampling_flush_loop, ValueSampler},
 };
 
 use serve_metrics::{serve, setup_metrics_routes};
@@ -105,12 +107,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut handles = Vec::new();
 
+    let value_sampler = if config.enable_value_sampling {
+        let sampler = Arc::new(ValueSampler::new(&config));
+        handles.push(tokio::spawn(value_sampling_flush_loop(
+            config.clone(),
+            sampler.clone(),
+            context.clone(),
+        )));
+        Some(sampler)
+    } else {
+        None
+    };
+
+    let type_tracker = if config.enable_type_tracking {
+        let tracker = Arc::new(TypeTracker::new(&config));
+        handles.push(tokio::spawn(type_tracking_flush_loop(
+            config.clone(),
+            tracker.clone(),
+            context.clone(),
+        )));
+        Some(tracker)
+    } else {
+        None
+    };
+
     for _ in 0..config.worker_loop_count {
         let handle = tokio::spawn(update_producer_loop(
             config.clone(),
             consumer.clone(),
             cache.clone(),
             tx.clone(),
+            value_sampler.clone(),
+            type_tracker.clone(),
         ));
 
         handles.push(handle);
@@ -32,6 +32,17 @@ pub const DUPLICATES_IN_BATCH: &str = "prop_defs_duplicates_in_batch";
 pub const SINGLE_UPDATE_ISSUE_TIME: &str = "prop_defs_single_update_issue_time_ms";
 pub const CHANNEL_MESSAGES_IN_FLIGHT: &str = "prop_defs_channel_messages_in_flight";
 pub const CHANNEL_CAPACITY: &str = "prop_defs_channel_capacity";
+pub const VALUE_SAMPLING_TRACKED: &str = "prop_defs_value_sampling_tracked_properties";
+pub const VALUE_SAMPLING_DROPPED: &str = "prop_defs_value_sampling_dropped";
+pub const VALUE_SAMPLING_WRITE_TIME: &str = "prop_defs_value_sampling_write_time_ms";
+pub const VALUE_SAMPLING_ROWS_WRITTEN: &str = "prop_defs_value_sampling_rows_written";
+pub const VALUE_SAMPLING_WRITE_FAILED: &str = "prop_defs_value_sampling_write_failed";
+pub const TYPE_TRACKING_TRACKED: &str = "prop_defs_type_tracking_tracked_properties";
+pub const TYPE_TRACKING_DROPPED: &str = "prop_defs_type_tracking_dropped";
+pub const TYPE_TRACKING_CONFLICTS_FOUND: &str = "prop_defs_type_tracking_conflicts_found";
+pub const TYPE_TRACKING_WRITE_TIME: &str = "prop_defs_type_tracking_write_time_ms";
+pub const TYPE_TRACKING_ROWS_WRITTEN: &str = "prop_defs_type_tracking_rows_written";
+pub const TYPE_TRACKING_WRITE_FAILED: &str = "prop_defs_type_tracking_write_failed";
 
 pub const ISOLATED_PROPDEFS_DB_SELECTED: &str = "isolated_propdefs_db_selected";
 
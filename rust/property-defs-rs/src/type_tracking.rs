@@ -0,0 +1,526 @@
+use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use ahash::{AHashMap, RandomState};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    app_context::AppContext,
+    config::Config,
+    metrics_consts::{
+        TYPE_TRACKING_CONFLICTS_FOUND, TYPE_TRACKING_DROPPED, TYPE_TRACKING_ROWS_WRITTEN,
+        TYPE_TRACKING_TRACKED, TYPE_TRACKING_WRITE_FAILED, TYPE_TRACKING_WRITE_TIME,
+    },
+    types::{GroupType, PropertyParentType, PropertyValueType},
+    value_sampling::{resolve_group_types, safe_scalar_value, PropertyKey, SHARDS},
+};
+
+// The detected type of a property value pulled out of an event, and the value itself if it's one
+// we're allowed to keep as a sample
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObservedType {
+    pub parent_type: PropertyParentType,
+    pub group_type_index: Option<GroupType>,
+    pub property: String,
+    pub property_type: PropertyValueType,
+    pub value: Option<String>,
+}
+
+// How often a property was seen with one type. This is what we persist, one per type seen.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TypeObservation {
+    pub property_type: PropertyValueType,
+    pub count: u64,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    // Most recently seen first
+    pub sample_values: Vec<String>,
+}
+
+impl TypeObservation {
+    fn new(property_type: PropertyValueType, seen_at: DateTime<Utc>) -> Self {
+        Self {
+            property_type,
+            count: 0,
+            first_seen_at: seen_at,
+            last_seen_at: seen_at,
+            sample_values: vec![],
+        }
+    }
+}
+
+// Merges newer observations into older ones, summing the counts and widening the seen-at range of
+// each type. Newer samples push out older ones. The result is ordered most common type first.
+pub fn merge_type_observations(
+    older: Vec<TypeObservation>,
+    newer: Vec<TypeObservation>,
+    sample_size: usize,
+) -> Vec<TypeObservation> {
+    let mut merged = older;
+    for newer in newer {
+        let Some(existing) = merged
+            .iter_mut()
+            .find(|o| o.property_type == newer.property_type)
+        else {
+            merged.push(newer);
+            continue;
+        };
+
+        existing.count += newer.count;
+        existing.first_seen_at = existing.first_seen_at.min(newer.first_seen_at);
+        existing.last_seen_at = existing.last_seen_at.max(newer.last_seen_at);
+
+        let mut sample_values = newer.sample_values;
+        for value in existing.sample_values.drain(..) {
+            if !sample_values.contains(&value) {
+                sample_values.push(value);
+            }
+        }
+        sample_values.truncate(sample_size);
+        existing.sample_values = sample_values;
+    }
+    sort_type_observations(&mut merged);
+    merged
+}
+
+fn sort_type_observations(observations: &mut [TypeObservation]) {
+    observations.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.property_type.cmp(&b.property_type))
+    });
+}
+
+// A property is conflicted if it's been seen with more than one type, or with a type other than the
+// one its definition was created with - which is the one insights will treat every value as
+pub fn is_conflicting(observations: &[TypeObservation], defined_type: Option<&str>) -> bool {
+    match observations {
+        [] => false,
+        [only] => defined_type.is_some_and(|defined| defined != only.property_type.to_string()),
+        _ => true,
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TypeStats {
+    observations: Vec<TypeObservation>,
+    // Per observation, most recently seen first
+    recent: Vec<VecDeque<String>>,
+}
+
+impl TypeStats {
+    pub fn observe(
+        &mut self,
+        property_type: PropertyValueType,
+        value: Option<String>,
+        seen_at: DateTime<Utc>,
+        sample_size: usize,
+    ) {
+        let index = match self
+            .observations
+            .iter()
+            .position(|o| o.property_type == property_type)
+        {
+            Some(index) => index,
+            None => {
+                self.observations
+                    .push(TypeObservation::new(property_type, seen_at));
+                self.recent.push(VecDeque::new());
+                self.observations.len() - 1
+            }
+        };
+
+        let observation = &mut self.observations[index];
+        observation.count += 1;
+        observation.last_seen_at = seen_at;
+
+        let Some(value) = value else {
+            return;
+        };
+        let recent = &mut self.recent[index];
+        if let Some(pos) = recent.iter().position(|v| *v == value) {
+            recent.remove(pos);
+        } else if recent.len() >= sample_size {
+            recent.pop_back();
+        }
+        recent.push_front(value);
+    }
+
+    pub fn observations(&self) -> Vec<TypeObservation> {
+        let mut observations: Vec<TypeObservation> = self
+            .observations
+            .iter()
+            .zip(&self.recent)
+            .map(|(observation, recent)| TypeObservation {
+                sample_values: recent.iter().cloned().collect(),
+                ..observation.clone()
+            })
+            .collect();
+        sort_type_observations(&mut observations);
+        observations
+    }
+}
+
+// Tracks the types every property is seen with between flushes. Most properties only ever have
+// one type, so the per-property cost is a single observation and a handful of short samples.
+pub struct TypeTracker {
+    opt_out_teams: Vec<i32>,
+    max_value_length: usize,
+    sample_size: usize,
+    max_properties: usize,
+    tracked: AtomicUsize,
+    hasher: RandomState,
+    shards: Vec<Mutex<AHashMap<PropertyKey, TypeStats>>>,
+}
+
+impl TypeTracker {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            opt_out_teams: config.value_sampling_opt_out_teams.teams.clone(),
+            max_value_length: config.value_sampling_max_value_length,
+            sample_size: config.type_tracking_sample_size,
+            max_properties: config.type_tracking_max_properties,
+            tracked: AtomicUsize::new(0),
+            hasher: RandomState::new(),
+            shards: (0..SHARDS).map(|_| Mutex::new(AHashMap::new())).collect(),
+        }
+    }
+
+    // Returns the value as we'd keep it as a sample. Unlike value sampling we keep timestamps,
+    // since a timestamp showing up in a numeric property is exactly the kind of thing we're after.
+    pub fn sample_value(&self, property: &str, value: &Value) -> Option<String> {
+        safe_scalar_value(property, value, self.max_value_length)
+    }
+
+    pub fn observe(&self, team_id: i32, project_id: i64, types: Vec<ObservedType>) {
+        // Teams that opted out of value sampling still get their types tracked, just without samples
+        let keep_samples = !self.opt_out_teams.contains(&team_id);
+        let seen_at = Utc::now();
+
+        for observed in types {
+            let key = PropertyKey {
+                team_id,
+                project_id,
+                parent_type: observed.parent_type,
+                group_type_index: observed.group_type_index,
+                property: observed.property,
+            };
+            let shard = &self.shards[self.hasher.hash_one(&key) as usize % SHARDS];
+            let mut shard = shard.lock().unwrap();
+
+            let stats = match shard.get_mut(&key) {
+                Some(stats) => stats,
+                None => {
+                    if self.tracked.load(Ordering::Relaxed) >= self.max_properties {
+                        metrics::counter!(
+                            TYPE_TRACKING_DROPPED,
+                            &[("reason", "too_many_properties")]
+                        )
+                        .increment(1);
+                        continue;
+                    }
+                    self.tracked.fetch_add(1, Ordering::Relaxed);
+                    shard.entry(key).or_default()
+                }
+            };
+            let value = observed.value.filter(|_| keep_samples);
+            stats.observe(observed.property_type, value, seen_at, self.sample_size);
+        }
+    }
+
+    pub fn tracked_properties(&self) -> usize {
+        self.tracked.load(Ordering::Relaxed)
+    }
+
+    // Takes everything tracked since the last call, leaving the tracker empty
+    pub fn take(&self) -> Vec<(PropertyKey, Vec<TypeObservation>)> {
+        let mut taken = Vec::with_capacity(self.tracked_properties());
+        for shard in &self.shards {
+            let stats = std::mem::take(&mut *shard.lock().unwrap());
+            self.tracked.fetch_sub(stats.len(), Ordering::Relaxed);
+            taken.extend(
+                stats
+                    .into_iter()
+                    .map(|(key, stats)| (key, stats.observations())),
+            );
+        }
+        taken
+    }
+
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+}
+
+// Periodically records conflicting properties in Postgres. As with value sampling, pods read, merge
+// and write back their own observations, so racing flushes can lose one pod's window of counts.
+pub async fn type_tracking_flush_loop(
+    config: Config,
+    tracker: Arc<TypeTracker>,
+    context: Arc<AppContext>,
+) {
+    let interval = Duration::from_secs(config.type_tracking_flush_interval_secs);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let mut observations = tracker.take();
+        metrics::gauge!(TYPE_TRACKING_TRACKED).set(observations.len() as f64);
+        resolve_group_types(&context, &mut observations, TYPE_TRACKING_DROPPED).await;
+
+        for chunk in observations.chunks(config.write_batch_size.max(1)) {
+            match write_type_observations(&context.pool, chunk, tracker.sample_size()).await {
+                Ok(count) => {
+                    metrics::counter!(TYPE_TRACKING_ROWS_WRITTEN).increment(count);
+                }
+                Err(e) => {
+                    metrics::counter!(TYPE_TRACKING_WRITE_FAILED).increment(1);
+                    error!("Failed to write property type stats batch: {:?}", e);
+                }
+            }
+        }
+        info!("Flushed type stats for {} properties", observations.len());
+    }
+}
+
+// Merges observations into Postgres, but only for properties that are conflicted: ones seen with
+// more than one type, ones whose definition has a different type, and ones already recorded as
+// conflicted, whose counts we keep up to date. Everything else is dropped.
+pub async fn write_type_observations(
+    pool: &PgPool,
+    observations: &[(PropertyKey, Vec<TypeObservation>)],
+    sample_size: usize,
+) -> Result<u64, sqlx::Error> {
+    if observations.is_empty() {
+        return Ok(0);
+    }
+    let timer = common_metrics::timing_guard(TYPE_TRACKING_WRITE_TIME, &[]);
+
+    let team_ids: Vec<i32> = observations.iter().map(|(k, _)| k.team_id).collect();
+    let types: Vec<i16> = observations
+        .iter()
+        .map(|(k, _)| k.parent_type as i16)
+        .collect();
+    let group_type_indexes: Vec<i16> = observations
+        .iter()
+        .map(|(k, _)| k.group_type_index())
+        .collect();
+    let names: Vec<String> = observations
+        .iter()
+        .map(|(k, _)| k.property.clone())
+        .collect();
+
+    let rows = sqlx::query(
+        r#"
+        SELECT team_id, type, coalesce(group_type_index, -1)::smallint AS group_type_index, name, observations
+            FROM posthog_propertytypestats
+            WHERE (team_id, type, coalesce(group_type_index, -1), name)
+                IN (SELECT * FROM UNNEST($1::int[], $2::smallint[], $3::smallint[], $4::varchar[]))"#,
+    )
+    .bind(&team_ids)
+    .bind(&types)
+    .bind(&group_type_indexes)
+    .bind(&names)
+    .fetch_all(pool)
+    .await?;
+
+    let mut existing: AHashMap<(i32, i16, i16, String), Vec<TypeObservation>> =
+        AHashMap::with_capacity(rows.len());
+    for row in rows {
+        let observations = serde_json::from_value(row.get("observations")).unwrap_or_default();
+        existing.insert(
+            (
+                row.get("team_id"),
+                row.get("type"),
+                row.get("group_type_index"),
+                row.get("name"),
+            ),
+            observations,
+        );
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT team_id, type, coalesce(group_type_index, -1)::smallint AS group_type_index, name, property_type
+            FROM posthog_propertydefinition
+            WHERE (team_id, type, coalesce(group_type_index, -1), name)
+                IN (SELECT * FROM UNNEST($1::int[], $2::smallint[], $3::smallint[], $4::varchar[]))
+            AND property_type IS NOT NULL"#,
+    )
+    .bind(&team_ids)
+    .bind(&types)
+    .bind(&group_type_indexes)
+    .bind(&names)
+    .fetch_all(pool)
+    .await?;
+
+    let mut defined: AHashMap<(i32, i16, i16, String), String> =
+        AHashMap::with_capacity(rows.len());
+    for row in rows {
+        defined.insert(
+            (
+                row.get("team_id"),
+                row.get("type"),
+                row.get("group_type_index"),
+                row.get("name"),
+            ),
+            row.get("property_type"),
+        );
+    }
+
+    let mut ids = Vec::new();
+    let mut conflict_team_ids = Vec::new();
+    let mut project_ids = Vec::new();
+    let mut conflict_types = Vec::new();
+    let mut conflict_group_type_indexes = Vec::new();
+    let mut conflict_names = Vec::new();
+    let mut merged_observations = Vec::new();
+    for (i, (key, observed)) in observations.iter().enumerate() {
+        let key_parts = (
+            team_ids[i],
+            types[i],
+            group_type_indexes[i],
+            names[i].clone(),
+        );
+        let previous = existing.remove(&key_parts);
+        let defined_type = defined.get(&key_parts).map(String::as_str);
+        if previous.is_none()
+            && !is_conflicting(observed, None)
+            && !defined_type.is_some_and(|defined| is_conflicting(observed, Some(defined)))
+        {
+            continue;
+        }
+        if previous.is_none() {
+            metrics::counter!(TYPE_TRACKING_CONFLICTS_FOUND).increment(1);
+        }
+
+        let merged =
+            merge_type_observations(previous.unwrap_or_default(), observed.clone(), sample_size);
+        ids.push(Uuid::now_v7());
+        conflict_team_ids.push(key.team_id);
+        project_ids.push(key.project_id);
+        conflict_types.push(types[i]);
+        conflict_group_type_indexes.push(group_type_indexes[i]);
+        conflict_names.push(key.property.clone());
+        merged_observations.push(serde_json::to_value(merged).unwrap_or_default());
+    }
+
+    if ids.is_empty() {
+        timer.fin();
+        return Ok(0);
+    }
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO posthog_propertytypestats (id, team_id, project_id, type, group_type_index, name, observations, updated_at)
+            (SELECT id, team_id, project_id, type, NULLIF(group_type_index, -1), name, observations, NOW() FROM UNNEST(
+                $1::uuid[],
+                $2::int[],
+                $3::bigint[],
+                $4::smallint[],
+                $5::smallint[],
+                $6::varchar[],
+                $7::jsonb[]) AS t(id, team_id, project_id, type, group_type_index, name, observations))
+            ON CONFLICT (team_id, type, coalesce(group_type_index, -1), name) DO UPDATE SET
+                observations = EXCLUDED.observations,
+                updated_at = EXCLUDED.updated_at"#,
+    )
+    .bind(&ids)
+    .bind(&conflict_team_ids)
+    .bind(&project_ids)
+    .bind(&conflict_types)
+    .bind(&conflict_group_type_indexes)
+    .bind(&conflict_names)
+    .bind(&merged_observations)
+    .execute(pool)
+    .await;
+    timer.fin();
+
+    Ok(result?.rows_affected())
+}
+
+// A property seen with conflicting types, across all of a project's environments
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyTypeConflict {
+    pub name: String,
+    // The type the property definition was created with, if it has one
+    pub defined_type: Option<String>,
+    // Most common type first
+    pub observations: Vec<TypeObservation>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Reads the conflicted properties of a project, most recently updated first. If a name is given,
+// only that property is returned. The group type index is -1 for properties that aren't a group's.
+pub async fn read_type_conflicts(
+    pool: &PgPool,
+    project_id: i64,
+    parent_type: PropertyParentType,
+    group_type_index: i32,
+    name: Option<&str>,
+    sample_size: usize,
+) -> Result<Vec<PropertyTypeConflict>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.name, s.observations, s.updated_at,
+            (SELECT pd.property_type FROM posthog_propertydefinition pd
+                WHERE coalesce(pd.project_id, pd.team_id::bigint) = $1 AND pd.type = s.type
+                AND coalesce(pd.group_type_index, -1) = coalesce(s.group_type_index, -1) AND pd.name = s.name
+                AND pd.property_type IS NOT NULL
+                LIMIT 1) AS defined_type
+            FROM posthog_propertytypestats s
+            WHERE coalesce(s.project_id, s.team_id::bigint) = $1 AND s.type = $2
+            AND coalesce(s.group_type_index, -1) = $3
+            AND ($4::varchar IS NULL OR s.name = $4)
+            ORDER BY s.updated_at ASC"#,
+    )
+    .bind(project_id)
+    .bind(parent_type as i16)
+    .bind(group_type_index as i16)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    // A project's environments each have their own row, which we merge
+    let mut conflicts: Vec<PropertyTypeConflict> = Vec::with_capacity(rows.len());
+    let mut by_name: AHashMap<String, usize> = AHashMap::with_capacity(rows.len());
+    for row in rows {
+        let name: String = row.get("name");
+        let observations = serde_json::from_value(row.get("observations")).unwrap_or_default();
+        let updated_at: DateTime<Utc> = row.get("updated_at");
+
+        match by_name.get(&name) {
+            Some(&index) => {
+                let conflict = &mut conflicts[index];
+                let existing = std::mem::take(&mut conflict.observations);
+                conflict.observations =
+                    merge_type_observations(existing, observations, sample_size);
+                conflict.updated_at = updated_at;
+            }
+            None => {
+                by_name.insert(name.clone(), conflicts.len());
+                conflicts.push(PropertyTypeConflict {
+                    name,
+                    defined_type: row.get("defined_type"),
+                    observations,
+                    updated_at,
+                });
+            }
+        }
+    }
+
+    conflicts.retain(|c| is_conflicting(&c.observations, c.defined_type.as_deref()));
+    conflicts.sort_by_key(|c| Reverse(c.updated_at));
+    Ok(conflicts)
+}
@@ -8,7 +8,11 @@ use sqlx::{Executor, Postgres};
 use tracing::warn;
 use uuid::Uuid;
 
-use crate::metrics_consts::{EVENTS_SKIPPED, UPDATES_ISSUED, UPDATES_SKIPPED};
+use crate::{
+    metrics_consts::{EVENTS_SKIPPED, UPDATES_ISSUED, UPDATES_SKIPPED},
+    type_tracking::{ObservedType, TypeTracker},
+    value_sampling::{ObservedValue, ValueSampler},
+};
 
 // Custom deserializer that can handle both string and integer values
 fn deserialize_string_or_i32<'de, D>(deserializer: D) -> Result<i32, D::Error>
@@ -205,6 +209,54 @@ pub struct Event {
     pub properties: Option<String>,
 }
 
+// The optional trackers we feed with each property pulled out of an event
+#[derive(Clone, Copy, Default)]
+pub struct PropertyObservers<'a> {
+    pub value_sampler: Option<&'a ValueSampler>,
+    pub type_tracker: Option<&'a TypeTracker>,
+}
+
+impl PropertyObservers<'_> {
+    fn observe(
+        &self,
+        parent_type: PropertyParentType,
+        group_type: Option<&GroupType>,
+        key: &str,
+        property_type: Option<&PropertyValueType>,
+        value: &Value,
+        observations: &mut PropertyObservations,
+    ) {
+        if let Some(value) = self
+            .value_sampler
+            .and_then(|s| s.sampleable_value(key, property_type, value))
+        {
+            observations.values.push(ObservedValue {
+                parent_type,
+                group_type_index: group_type.cloned(),
+                property: key.to_string(),
+                value,
+            });
+        }
+
+        if let (Some(tracker), Some(property_type)) = (self.type_tracker, property_type) {
+            observations.types.push(ObservedType {
+                parent_type,
+                group_type_index: group_type.cloned(),
+                property: key.to_string(),
+                property_type: property_type.clone(),
+                value: tracker.sample_value(key, value),
+            });
+        }
+    }
+}
+
+// What the observers took from an event's properties
+#[derive(Debug, Default)]
+pub struct PropertyObservations {
+    pub values: Vec<ObservedValue>,
+    pub types: Vec<ObservedType>,
+}
+
 impl From<&Event> for EventDefinition {
     fn from(event: &Event) -> Self {
         EventDefinition {
@@ -218,34 +270,49 @@ impl From<&Event> for EventDefinition {
 
 impl Event {
     pub fn into_updates(self, skip_threshold: usize) -> Vec<Update> {
+        self.into_updates_with_observations(skip_threshold, PropertyObservers::default())
+            .0
+    }
+
+    // As into_updates, but also returns what the given observers want from the event's properties
+    pub fn into_updates_with_observations(
+        self,
+        skip_threshold: usize,
+        observers: PropertyObservers<'_>,
+    ) -> (Vec<Update>, PropertyObservations) {
         if EVENTS_WITHOUT_PROPERTIES.contains(&self.event.as_str()) {
             metrics::counter!(EVENTS_SKIPPED, &[("reason", "no_properties_event")]).increment(1);
-            return vec![];
+            return (vec![], PropertyObservations::default());
         }
 
         if !will_fit_in_postgres_column(&self.event) {
             metrics::counter!(EVENTS_SKIPPED, &[("reason", "name_wont_fit_in_postgres")])
                 .increment(1);
-            return vec![];
+            return (vec![], PropertyObservations::default());
         }
 
         let team_id = self.team_id;
         let event = self.event.clone();
 
-        let updates = self.into_updates_inner();
+        let mut observations = PropertyObservations::default();
+        let updates = self.into_updates_inner(observers, &mut observations);
         if updates.len() > skip_threshold {
             warn!(
                 "Event {} for team {} has more than {} properties, skipping",
                 event, team_id, skip_threshold
             );
             metrics::counter!(EVENTS_SKIPPED, &[("reason", "too_many_properties")]).increment(1);
-            return vec![];
+            return (vec![], PropertyObservations::default());
         }
 
-        updates
+        (updates, observations)
     }
 
-    fn into_updates_inner(self) -> Vec<Update> {
+    fn into_updates_inner(
+        self,
+        observers: PropertyObservers<'_>,
+        observations: &mut PropertyObservations,
+    ) -> Vec<Update> {
         let mut updates = vec![Update::Event(EventDefinition::from(&self))];
         let Some(props) = &self.properties else {
             return updates;
@@ -279,16 +346,32 @@ impl Event {
                 group_properties,
                 PropertyParentType::Group,
                 Some(group_type),
+                observers,
+                observations,
             );
             return updates;
         }
 
         // Grab the "ordinary" (non-person) event properties
-        self.get_props_from_object(&mut updates, &props, PropertyParentType::Event, None);
+        self.get_props_from_object(
+            &mut updates,
+            &props,
+            PropertyParentType::Event,
+            None,
+            observers,
+            observations,
+        );
 
         // If there are any person properties, also push those into the flat property map.
         if let Some(Value::Object(set_props)) = props.get("$set") {
-            self.get_props_from_object(&mut updates, set_props, PropertyParentType::Person, None)
+            self.get_props_from_object(
+                &mut updates,
+                set_props,
+                PropertyParentType::Person,
+                None,
+                observers,
+                observations,
+            )
         }
         if let Some(Value::Object(set_once_props)) = props.get("$set_once") {
             self.get_props_from_object(
@@ -296,6 +379,8 @@ impl Event {
                 set_once_props,
                 PropertyParentType::Person,
                 None,
+                observers,
+                observations,
             )
         }
 
@@ -308,6 +393,8 @@ impl Event {
         set: &Map<String, Value>,
         parent_type: PropertyParentType,
         group_type: Option<GroupType>,
+        observers: PropertyObservers<'_>,
+        observations: &mut PropertyObservations,
     ) {
         updates.reserve(set.len() * 2);
         for (key, value) in set {
@@ -334,6 +421,15 @@ impl Event {
             let property_type = detect_property_type(key, value);
             let is_numerical = matches!(property_type, Some(PropertyValueType::Numeric));
 
+            observers.observe(
+                parent_type,
+                group_type.as_ref(),
+                key,
+                property_type.as_ref(),
+                value,
+                observations,
+            );
+
             updates.push(Update::Property(PropertyDefinition {
                 team_id: self.team_id,
                 project_id: self.project_id,
@@ -0,0 +1,581 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    time::Duration,
+};
+
+use ahash::{AHashMap, RandomState};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    app_context::AppContext,
+    config::Config,
+    metrics_consts::{
+        VALUE_SAMPLING_DROPPED, VALUE_SAMPLING_ROWS_WRITTEN, VALUE_SAMPLING_TRACKED,
+        VALUE_SAMPLING_WRITE_FAILED, VALUE_SAMPLING_WRITE_TIME,
+    },
+    types::{GroupType, PropertyParentType, PropertyValueType},
+};
+
+// Sketches are only allocated once a property has more distinct values than we keep top values for,
+// below that we count values exactly. Each sketch is SKETCH_WIDTH * SKETCH_DEPTH u32 counters.
+const SKETCH_WIDTH: usize = 128;
+const SKETCH_DEPTH: usize = 4;
+
+// We spread tracked properties across shards, so producer workers rarely contend for the same lock
+pub(crate) const SHARDS: usize = 64;
+
+// Values of properties with names containing these are likely to be personal data, and are never sampled
+const SENSITIVE_PROPERTY_FRAGMENTS: [&str; 8] = [
+    "email", "password", "token", "secret", "phone", "address", "$ip", "ssn",
+];
+
+static SKETCH_HASHER: LazyLock<RandomState> =
+    LazyLock::new(|| RandomState::with_seeds(0x9e37_79b9, 0x85eb_ca6b, 0xc2b2_ae35, 0x27d4_eb2f));
+
+// An approximate counter of how often values are seen. Estimates never undercount, and overcount
+// by a fraction of the total count that shrinks as the sketch gets wider.
+#[derive(Clone, Debug)]
+pub struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<u32>,
+}
+
+impl CountMinSketch {
+    pub fn new(width: usize, depth: usize) -> Self {
+        Self {
+            width,
+            depth,
+            counters: vec![0; width * depth],
+        }
+    }
+
+    // Adds count sightings of value, returning the new estimated count for it
+    pub fn add(&mut self, value: &str, count: u32) -> u32 {
+        let mut estimate = u32::MAX;
+        for index in self.indexes(value) {
+            let counter = &mut self.counters[index];
+            *counter = counter.saturating_add(count);
+            estimate = estimate.min(*counter);
+        }
+        estimate
+    }
+
+    pub fn estimate(&self, value: &str) -> u32 {
+        self.indexes(value)
+            .map(|index| self.counters[index])
+            .min()
+            .unwrap_or_default()
+    }
+
+    // Each row gets its own hash, derived from two halves of a single one
+    fn indexes(&self, value: &str) -> impl Iterator<Item = usize> {
+        let hash = SKETCH_HASHER.hash_one(value);
+        let (h1, h2) = (hash as u32 as usize, (hash >> 32) as usize | 1);
+        let width = self.width;
+        (0..self.depth).map(move |row| row * width + h1.wrapping_add(row.wrapping_mul(h2)) % width)
+    }
+}
+
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub struct PropertyKey {
+    pub team_id: i32,
+    pub project_id: i64,
+    pub parent_type: PropertyParentType,
+    // Only set for group properties, which are tracked per group type. Resolved before writing.
+    pub group_type_index: Option<GroupType>,
+    pub property: String,
+}
+
+impl PropertyKey {
+    // The group type index as stored, where -1 stands in for properties that aren't a group's
+    pub fn group_type_index(&self) -> i16 {
+        match &self.group_type_index {
+            Some(GroupType::Resolved(_, index)) => *index as i16,
+            _ => -1,
+        }
+    }
+
+    // Group properties whose group type we couldn't resolve can't be written
+    fn is_resolved(&self) -> bool {
+        self.parent_type != PropertyParentType::Group
+            || matches!(self.group_type_index, Some(GroupType::Resolved(..)))
+    }
+}
+
+// A property value pulled out of an event, that's passed our PII checks
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ObservedValue {
+    pub parent_type: PropertyParentType,
+    pub group_type_index: Option<GroupType>,
+    pub property: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TopValue {
+    pub value: String,
+    pub count: u64,
+}
+
+// What we persist for each property, and serve from the API
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PropertyValueSummary {
+    // Most recently seen first
+    pub sample_values: Vec<String>,
+    // Highest count first
+    pub top_values: Vec<TopValue>,
+}
+
+impl PropertyValueSummary {
+    // Merges a newer summary into this one. Newer samples push out older ones, and counts for the same
+    // value are summed, which keeps the top values approximately right across flushes and pods.
+    pub fn merge(self, newer: PropertyValueSummary, sample_size: usize, top_k: usize) -> Self {
+        let mut sample_values = newer.sample_values;
+        for value in self.sample_values {
+            if sample_values.len() >= sample_size {
+                break;
+            }
+            if !sample_values.contains(&value) {
+                sample_values.push(value);
+            }
+        }
+        sample_values.truncate(sample_size);
+
+        let mut top_values = self.top_values;
+        for newer in newer.top_values {
+            match top_values.iter_mut().find(|t| t.value == newer.value) {
+                Some(existing) => existing.count += newer.count,
+                None => top_values.push(newer),
+            }
+        }
+        sort_top_values(&mut top_values);
+        top_values.truncate(top_k);
+
+        Self {
+            sample_values,
+            top_values,
+        }
+    }
+}
+
+fn sort_top_values(top_values: &mut [TopValue]) {
+    top_values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ValueStats {
+    recent: VecDeque<String>,
+    top: Vec<TopValue>,
+    sketch: Option<CountMinSketch>,
+}
+
+impl ValueStats {
+    pub fn observe(&mut self, value: String, sample_size: usize, top_k: usize) {
+        self.count(&value, top_k);
+
+        if let Some(pos) = self.recent.iter().position(|v| *v == value) {
+            self.recent.remove(pos);
+        } else if self.recent.len() >= sample_size {
+            self.recent.pop_back();
+        }
+        self.recent.push_front(value);
+    }
+
+    fn count(&mut self, value: &str, top_k: usize) {
+        if let Some(sketch) = &mut self.sketch {
+            let estimate = sketch.add(value, 1) as u64;
+            self.offer(value, estimate);
+            return;
+        }
+
+        if let Some(top) = self.top.iter_mut().find(|t| t.value == value) {
+            top.count += 1;
+        } else if self.top.len() < top_k {
+            self.top.push(TopValue {
+                value: value.to_string(),
+                count: 1,
+            });
+        } else {
+            // Too many distinct values to count them all exactly, so switch to estimating
+            let mut sketch = CountMinSketch::new(SKETCH_WIDTH, SKETCH_DEPTH);
+            for top in &self.top {
+                sketch.add(&top.value, top.count.min(u32::MAX as u64) as u32);
+            }
+            let estimate = sketch.add(value, 1) as u64;
+            self.sketch = Some(sketch);
+            self.offer(value, estimate);
+        }
+    }
+
+    // Updates the count of a top value, or has it replace the least common one if it's now more common
+    fn offer(&mut self, value: &str, estimate: u64) {
+        if let Some(top) = self.top.iter_mut().find(|t| t.value == value) {
+            top.count = estimate;
+            return;
+        }
+
+        let Some(min) = self.top.iter_mut().min_by_key(|t| t.count) else {
+            return;
+        };
+        if estimate > min.count {
+            *min = TopValue {
+                value: value.to_string(),
+                count: estimate,
+            };
+        }
+    }
+
+    pub fn summary(&self) -> PropertyValueSummary {
+        let mut top_values = self.top.clone();
+        sort_top_values(&mut top_values);
+        PropertyValueSummary {
+            sample_values: self.recent.iter().cloned().collect(),
+            top_values,
+        }
+    }
+}
+
+// Renders short scalar values of properties that don't look like they hold personal data
+pub(crate) fn safe_scalar_value(
+    property: &str,
+    value: &Value,
+    max_value_length: usize,
+) -> Option<String> {
+    let property = property.to_lowercase();
+    if SENSITIVE_PROPERTY_FRAGMENTS
+        .iter()
+        .any(|fragment| property.contains(fragment))
+    {
+        return None;
+    }
+
+    let value = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => return None,
+    };
+
+    if value.is_empty() || value.chars().count() > max_value_length {
+        return None;
+    }
+
+    Some(value)
+}
+
+// Tracks values for every property we see between flushes. Memory is bounded by the number of
+// properties we track, each of which holds at most sample_size + top_k short values and one sketch.
+pub struct ValueSampler {
+    opt_out_teams: Vec<i32>,
+    max_value_length: usize,
+    sample_size: usize,
+    top_k: usize,
+    max_properties: usize,
+    tracked: AtomicUsize,
+    shards: Vec<Mutex<AHashMap<PropertyKey, ValueStats>>>,
+}
+
+impl ValueSampler {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            opt_out_teams: config.value_sampling_opt_out_teams.teams.clone(),
+            max_value_length: config.value_sampling_max_value_length,
+            sample_size: config.value_sampling_sample_size,
+            top_k: config.value_sampling_top_k,
+            max_properties: config.value_sampling_max_properties,
+            tracked: AtomicUsize::new(0),
+            shards: (0..SHARDS).map(|_| Mutex::new(AHashMap::new())).collect(),
+        }
+    }
+
+    pub fn is_enabled_for(&self, team_id: i32) -> bool {
+        !self.opt_out_teams.contains(&team_id)
+    }
+
+    // Returns the value as we'd store it, if it's one we're allowed to sample. We only sample short
+    // scalar values, never timestamps (which are unique enough to be useless for autocomplete), and
+    // never values of properties that look like they hold personal data.
+    pub fn sampleable_value(
+        &self,
+        property: &str,
+        property_type: Option<&PropertyValueType>,
+        value: &Value,
+    ) -> Option<String> {
+        if matches!(property_type, Some(PropertyValueType::DateTime)) {
+            return None;
+        }
+        safe_scalar_value(property, value, self.max_value_length)
+    }
+
+    pub fn observe(&self, team_id: i32, project_id: i64, values: Vec<ObservedValue>) {
+        for observed in values {
+            let key = PropertyKey {
+                team_id,
+                project_id,
+                parent_type: observed.parent_type,
+                group_type_index: observed.group_type_index,
+                property: observed.property,
+            };
+            let shard = &self.shards[SKETCH_HASHER.hash_one(&key) as usize % SHARDS];
+            let mut shard = shard.lock().unwrap();
+
+            let stats = match shard.get_mut(&key) {
+                Some(stats) => stats,
+                None => {
+                    if self.tracked.load(Ordering::Relaxed) >= self.max_properties {
+                        metrics::counter!(
+                            VALUE_SAMPLING_DROPPED,
+                            &[("reason", "too_many_properties")]
+                        )
+                        .increment(1);
+                        continue;
+                    }
+                    self.tracked.fetch_add(1, Ordering::Relaxed);
+                    shard.entry(key).or_default()
+                }
+            };
+            stats.observe(observed.value, self.sample_size, self.top_k);
+        }
+    }
+
+    pub fn tracked_properties(&self) -> usize {
+        self.tracked.load(Ordering::Relaxed)
+    }
+
+    // Takes everything tracked since the last call, leaving the sampler empty
+    pub fn take(&self) -> Vec<(PropertyKey, PropertyValueSummary)> {
+        let mut taken = Vec::with_capacity(self.tracked_properties());
+        for shard in &self.shards {
+            let stats = std::mem::take(&mut *shard.lock().unwrap());
+            self.tracked.fetch_sub(stats.len(), Ordering::Relaxed);
+            taken.extend(stats.into_iter().map(|(key, stats)| (key, stats.summary())));
+        }
+        taken
+    }
+
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+
+    pub fn top_k(&self) -> usize {
+        self.top_k
+    }
+}
+
+// Periodically merges what we've sampled into Postgres. Pods read, merge and write back their
+// own summaries, so concurrent flushes of the same property from different pods can lose one pod's
+// window of values, which is fine for autocomplete.
+pub async fn value_sampling_flush_loop(
+    config: Config,
+    sampler: Arc<ValueSampler>,
+    context: Arc<AppContext>,
+) {
+    let interval = Duration::from_secs(config.value_sampling_flush_interval_secs);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        let mut summaries = sampler.take();
+        metrics::gauge!(VALUE_SAMPLING_TRACKED).set(summaries.len() as f64);
+        resolve_group_types(&context, &mut summaries, VALUE_SAMPLING_DROPPED).await;
+
+        for chunk in summaries.chunks(config.write_batch_size.max(1)) {
+            match write_value_summaries(
+                &context.pool,
+                chunk,
+                sampler.sample_size(),
+                sampler.top_k(),
+            )
+            .await
+            {
+                Ok(count) => {
+                    metrics::counter!(VALUE_SAMPLING_ROWS_WRITTEN).increment(count);
+                }
+                Err(e) => {
+                    metrics::counter!(VALUE_SAMPLING_WRITE_FAILED).increment(1);
+                    error!("Failed to write property value stats batch: {:?}", e);
+                }
+            }
+        }
+        info!("Flushed value stats for {} properties", summaries.len());
+    }
+}
+
+// Resolves the group types of tracked group properties, dropping those we can't resolve
+pub(crate) async fn resolve_group_types<T>(
+    context: &AppContext,
+    tracked: &mut Vec<(PropertyKey, T)>,
+    dropped_metric: &'static str,
+) {
+    let group_types = tracked
+        .iter_mut()
+        .map(|(key, _)| (key.team_id, &mut key.group_type_index))
+        .collect();
+    if let Err(e) = context.resolve_group_types(group_types).await {
+        warn!(
+            "Failed resolving group type indices for tracked properties, got: {:?}",
+            e
+        );
+    }
+
+    let before = tracked.len();
+    tracked.retain(|(key, _)| key.is_resolved());
+    metrics::counter!(dropped_metric, &[("reason", "unresolved_group_type")])
+        .increment((before - tracked.len()) as u64);
+}
+
+pub async fn write_value_summaries(
+    pool: &PgPool,
+    summaries: &[(PropertyKey, PropertyValueSummary)],
+    sample_size: usize,
+    top_k: usize,
+) -> Result<u64, sqlx::Error> {
+    if summaries.is_empty() {
+        return Ok(0);
+    }
+    let timer = common_metrics::timing_guard(VALUE_SAMPLING_WRITE_TIME, &[]);
+
+    let team_ids: Vec<i32> = summaries.iter().map(|(k, _)| k.team_id).collect();
+    let types: Vec<i16> = summaries
+        .iter()
+        .map(|(k, _)| k.parent_type as i16)
+        .collect();
+    let group_type_indexes: Vec<i16> = summaries
+        .iter()
+        .map(|(k, _)| k.group_type_index())
+        .collect();
+    let names: Vec<String> = summaries.iter().map(|(k, _)| k.property.clone()).collect();
+
+    let rows = sqlx::query(
+        r#"
+        SELECT team_id, type, coalesce(group_type_index, -1)::smallint AS group_type_index, name, sample_values, top_values
+            FROM posthog_propertyvaluestats
+            WHERE (team_id, type, coalesce(group_type_index, -1), name)
+                IN (SELECT * FROM UNNEST($1::int[], $2::smallint[], $3::smallint[], $4::varchar[]))"#,
+    )
+    .bind(&team_ids)
+    .bind(&types)
+    .bind(&group_type_indexes)
+    .bind(&names)
+    .fetch_all(pool)
+    .await?;
+
+    let mut existing: AHashMap<(i32, i16, i16, String), PropertyValueSummary> =
+        AHashMap::with_capacity(rows.len());
+    for row in rows {
+        let summary = PropertyValueSummary {
+            sample_values: serde_json::from_value(row.get("sample_values")).unwrap_or_default(),
+            top_values: serde_json::from_value(row.get("top_values")).unwrap_or_default(),
+        };
+        existing.insert(
+            (
+                row.get("team_id"),
+                row.get("type"),
+                row.get("group_type_index"),
+                row.get("name"),
+            ),
+            summary,
+        );
+    }
+
+    let mut ids = Vec::with_capacity(summaries.len());
+    let mut project_ids = Vec::with_capacity(summaries.len());
+    let mut sample_values = Vec::with_capacity(summaries.len());
+    let mut top_values = Vec::with_capacity(summaries.len());
+    for (i, (key, summary)) in summaries.iter().enumerate() {
+        let merged = existing
+            .remove(&(
+                team_ids[i],
+                types[i],
+                group_type_indexes[i],
+                names[i].clone(),
+            ))
+            .unwrap_or_default()
+            .merge(summary.clone(), sample_size, top_k);
+        ids.push(Uuid::now_v7());
+        project_ids.push(key.project_id);
+        sample_values.push(serde_json::to_value(merged.sample_values).unwrap_or_default());
+        top_values.push(serde_json::to_value(merged.top_values).unwrap_or_default());
+    }
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO posthog_propertyvaluestats (id, team_id, project_id, type, group_type_index, name, sample_values, top_values, updated_at)
+            (SELECT id, team_id, project_id, type, NULLIF(group_type_index, -1), name, sample_values, top_values, NOW() FROM UNNEST(
+                $1::uuid[],
+                $2::int[],
+                $3::bigint[],
+                $4::smallint[],
+                $5::smallint[],
+                $6::varchar[],
+                $7::jsonb[],
+                $8::jsonb[]) AS t(id, team_id, project_id, type, group_type_index, name, sample_values, top_values))
+            ON CONFLICT (team_id, type, coalesce(group_type_index, -1), name) DO UPDATE SET
+                sample_values = EXCLUDED.sample_values,
+                top_values = EXCLUDED.top_values,
+                updated_at = EXCLUDED.updated_at"#,
+    )
+    .bind(&ids)
+    .bind(&team_ids)
+    .bind(&project_ids)
+    .bind(&types)
+    .bind(&group_type_indexes)
+    .bind(&names)
+    .bind(&sample_values)
+    .bind(&top_values)
+    .execute(pool)
+    .await;
+    timer.fin();
+
+    Ok(result?.rows_affected())
+}
+
+// Reads the value stats for a property across all of a project's environments. The group type
+// index is -1 for properties that aren't a group's.
+pub async fn read_value_summary(
+    pool: &PgPool,
+    project_id: i64,
+    parent_type: PropertyParentType,
+    group_type_index: i32,
+    name: &str,
+    sample_size: usize,
+    top_k: usize,
+) -> Result<Option<(PropertyValueSummary, DateTime<Utc>)>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT sample_values, top_values, updated_at FROM posthog_propertyvaluestats
+            WHERE coalesce(project_id, team_id::bigint) = $1 AND type = $2
+            AND coalesce(group_type_index, -1) = $3 AND name = $4
+            ORDER BY updated_at ASC"#,
+    )
+    .bind(project_id)
+    .bind(parent_type as i16)
+    .bind(group_type_index as i16)
+    .bind(name)
+    .fetch_all(pool)
+    .await?;
+
+    let mut result: Option<(PropertyValueSummary, DateTime<Utc>)> = None;
+    for row in rows {
+        let summary = PropertyValueSummary {
+            sample_values: serde_json::from_value(row.get("sample_values")).unwrap_or_default(),
+            top_values: serde_json::from_value(row.get("top_values")).unwrap_or_default(),
+        };
+        let updated_at: DateTime<Utc> = row.get("updated_at");
+        result = Some(match result {
+            Some((merged, _)) => (merged.merge(summary, sample_size, top_k), updated_at),
+            None => (summary, updated_at),
+        });
+    }
+
+    Ok(result)
+}
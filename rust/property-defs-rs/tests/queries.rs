@@ -1,10 +1,16 @@
 use property_defs_rs::{
     api::v1::errors::ApiError,
-    api::v1::{query::Manager, routing::Params},
+    api::v1::{
+        query::Manager,
+        routing::{
+            EventDefinitionOrdering, EventDefinitionParams, EventDefinitionType,
+            EventPropertyParams, Params,
+        },
+    },
     types::PropertyParentType,
 };
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use sqlx::{postgres::PgArguments, Arguments, Executor, PgPool, Row};
 use uuid::Uuid;
 
@@ -61,8 +67,299 @@ async fn test_group_property_definitions_queries(test_pool: PgPool) {
     query_with_illegal_group_type_index_fails().await;
 }
 
+#[sqlx::test(migrations = "./tests/test_migrations")]
+async fn test_event_definitions_queries(test_pool: PgPool) {
+    // seed the test DB
+    bootstrap_event_definitions_seed_data(test_pool.clone())
+        .await
+        .unwrap();
+
+    // plumbing that won't change during the test suite exec
+    let qmgr = Manager::new(test_pool.clone()).await.unwrap();
+    let project_id = 1;
+
+    query_event_defs_no_filters(&qmgr, project_id).await;
+    query_event_defs_event_type_filter(&qmgr, project_id).await;
+    query_event_defs_search_filter(&qmgr, project_id).await;
+    query_event_defs_excluded_and_hidden_filters(&qmgr, project_id).await;
+    query_event_defs_ordering_and_pagination(&qmgr, project_id).await;
+    query_event_defs_without_enterprise_taxonomy(&qmgr, project_id).await;
+    query_event_defs_exclude_hidden_requires_enterprise_taxonomy().await;
+}
+
+#[sqlx::test(migrations = "./tests/test_migrations")]
+async fn test_event_properties_queries(test_pool: PgPool) {
+    // seed the test DB
+    bootstrap_seed_data(test_pool.clone()).await.unwrap();
+
+    // plumbing that won't change during the test suite exec
+    let qmgr = Manager::new(test_pool.clone()).await.unwrap();
+    let project_id = 1;
+
+    // a property without a definition yet, and one that's always hidden
+    for (id, property) in [(110, "new_prop"), (111, "distinct_id")] {
+        sqlx::query(
+            r#"
+            INSERT INTO posthog_eventproperty (id, event, property, team_id, project_id)
+                VALUES ($1, '$pageview', $2, 1, 1)
+        "#,
+        )
+        .bind(id)
+        .bind(property)
+        .execute(&test_pool)
+        .await
+        .unwrap();
+    }
+
+    query_event_props_for_event(&qmgr, project_id).await;
+    query_event_props_search_and_excluded_filters(&qmgr, project_id).await;
+    query_event_props_requires_event_names().await;
+}
+
+// fetch the names of event definitions matching the params, along with the total count
+async fn fetch_event_defs(
+    qmgr: &Manager,
+    project_id: i64,
+    params: &EventDefinitionParams,
+) -> (i64, Vec<String>) {
+    let mut qb = sqlx::QueryBuilder::new("");
+    let count_query = qmgr.event_definitions_count_query(&mut qb, project_id, params);
+    let total_count: i64 = qmgr.pool.fetch_one(count_query).await.unwrap().get(0);
+
+    let mut qb = sqlx::QueryBuilder::new("");
+    let defs_query = qmgr.event_definitions_query(&mut qb, project_id, params);
+    let names = qmgr
+        .pool
+        .fetch_all(defs_query)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("name"))
+        .collect();
+
+    (total_count, names)
+}
+
+// fetch all of the project's event definitions, most recently seen first
+async fn query_event_defs_no_filters(qmgr: &Manager, project_id: i64) {
+    let params = EventDefinitionParams::default();
+
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(4, total_count);
+    // seen today, by name, then seen yesterday and two days ago
+    assert_eq!(
+        names,
+        ["$pageview", "signed_up", "purchase", "$autocapture"]
+    );
+}
+
+// fetch only events sent by PostHog, or only those sent by the customer
+async fn query_event_defs_event_type_filter(qmgr: &Manager, project_id: i64) {
+    let params = EventDefinitionParams {
+        event_type: EventDefinitionType::EventPostHog,
+        ..Default::default()
+    };
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(2, total_count);
+    assert_eq!(names, ["$pageview", "$autocapture"]);
+
+    let params = EventDefinitionParams {
+        event_type: EventDefinitionType::EventCustom,
+        ..Default::default()
+    };
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(2, total_count);
+    assert_eq!(names, ["signed_up", "purchase"]);
+}
+
+// every search term must match somewhere in the event name
+async fn query_event_defs_search_filter(qmgr: &Manager, project_id: i64) {
+    let params = EventDefinitionParams {
+        search_terms: vec!["view".to_string()],
+        ..Default::default()
+    };
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(1, total_count);
+    assert_eq!(names, ["$pageview"]);
+
+    let params = EventDefinitionParams {
+        search_terms: vec!["u".to_string(), "p".to_string()],
+        ..Default::default()
+    };
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(3, total_count);
+    assert_eq!(names, ["signed_up", "purchase", "$autocapture"]);
+
+    // LIKE wildcards in terms match literally
+    let params = EventDefinitionParams {
+        search_terms: vec!["_".to_string()],
+        ..Default::default()
+    };
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(1, total_count);
+    assert_eq!(names, ["signed_up"]);
+}
+
+async fn query_event_defs_excluded_and_hidden_filters(qmgr: &Manager, project_id: i64) {
+    let params = EventDefinitionParams {
+        excluded_events: vec!["signed_up".to_string()],
+        exclude_hidden: true,
+        ..Default::default()
+    };
+    assert_eq!(Ok(()), params.valid());
+
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(2, total_count);
+    assert_eq!(names, ["$pageview", "purchase"]);
+}
+
+async fn query_event_defs_ordering_and_pagination(qmgr: &Manager, project_id: i64) {
+    // events we don't know the volume of yet sort last
+    let params = EventDefinitionParams {
+        ordering: vec![EventDefinitionOrdering {
+            field: "volume_30_day",
+            descending: true,
+        }],
+        ..Default::default()
+    };
+    let (_, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(
+        names,
+        ["$pageview", "$autocapture", "signed_up", "purchase"]
+    );
+
+    let params = EventDefinitionParams {
+        ordering: vec![EventDefinitionOrdering {
+            field: "name",
+            descending: false,
+        }],
+        limit: 2,
+        offset: 1,
+        ..Default::default()
+    };
+    let (total_count, names) = fetch_event_defs(qmgr, project_id, &params).await;
+    assert_eq!(4, total_count);
+    assert_eq!(names, ["$pageview", "purchase"]);
+}
+
+async fn query_event_defs_without_enterprise_taxonomy(qmgr: &Manager, project_id: i64) {
+    let params = EventDefinitionParams {
+        use_enterprise_taxonomy: false,
+        ..Default::default()
+    };
+    let mut qb = sqlx::QueryBuilder::new("");
+    let defs_query = qmgr.event_definitions_query(&mut qb, project_id, &params);
+    let rows = qmgr.pool.fetch_all(defs_query).await.unwrap();
+    assert_eq!(4, rows.len());
+    assert!(rows[0].try_get::<Option<bool>, _>("hidden").is_err());
+
+    // and with it, the enterprise columns are joined in
+    let params = EventDefinitionParams::default();
+    let mut qb = sqlx::QueryBuilder::new("");
+    let defs_query = qmgr.event_definitions_query(&mut qb, project_id, &params);
+    let rows = qmgr.pool.fetch_all(defs_query).await.unwrap();
+    let hidden: Vec<Option<bool>> = rows.iter().map(|row| row.get("hidden")).collect();
+    assert_eq!(hidden, [Some(false), None, None, Some(true)]);
+}
+
+async fn query_event_defs_exclude_hidden_requires_enterprise_taxonomy() {
+    let params = EventDefinitionParams {
+        exclude_hidden: true,
+        use_enterprise_taxonomy: false,
+        ..Default::default()
+    };
+    assert_eq!(
+        Err(ApiError::InvalidRequestParam(
+            "parameter 'exclude_hidden' requires 'use_enterprise_taxonomy'".to_string()
+        )),
+        params.valid()
+    );
+}
+
+// fetch the (property, property_type) pairs matching the params, along with the total count
+async fn fetch_event_props(
+    qmgr: &Manager,
+    project_id: i64,
+    params: &EventPropertyParams,
+) -> (i64, Vec<(String, Option<String>)>) {
+    let mut qb = sqlx::QueryBuilder::new("");
+    let count_query = qmgr.event_properties_count_query(&mut qb, project_id, params);
+    let total_count: i64 = qmgr.pool.fetch_one(count_query).await.unwrap().get(0);
+
+    let mut qb = sqlx::QueryBuilder::new("");
+    let props_query = qmgr.event_properties_query(&mut qb, project_id, params);
+    let props = qmgr
+        .pool
+        .fetch_all(props_query)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| (row.get("property"), row.get("property_type")))
+        .collect();
+
+    (total_count, props)
+}
+
+// fetch the properties seen with an event, with the types of their definitions
+async fn query_event_props_for_event(qmgr: &Manager, project_id: i64) {
+    let params = EventPropertyParams {
+        event_names: vec!["$other_event".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(Ok(()), params.valid());
+
+    let (total_count, props) = fetch_event_props(qmgr, project_id, &params).await;
+    assert_eq!(2, total_count);
+    assert_eq!(
+        props,
+        [
+            ("$screen_width".to_string(), Some("Numeric".to_string())),
+            (
+                "attempted_event_type".to_string(),
+                Some("Numeric".to_string())
+            ),
+        ]
+    );
+
+    // hidden properties are never returned, and ones without a definition have no type
+    let params = EventPropertyParams {
+        event_names: vec!["$pageview".to_string()],
+        ..Default::default()
+    };
+    let (total_count, props) = fetch_event_props(qmgr, project_id, &params).await;
+    assert_eq!(8, total_count);
+    assert!(!props.iter().any(|(property, _)| property == "distinct_id"));
+    assert!(props.contains(&("new_prop".to_string(), None)));
+}
+
+async fn query_event_props_search_and_excluded_filters(qmgr: &Manager, project_id: i64) {
+    let params = EventPropertyParams {
+        event_names: vec!["$pageview".to_string(), "$other_event".to_string()],
+        search_terms: vec!["s".to_string(), "m".to_string()],
+        excluded_properties: vec!["user_email".to_string()],
+        ..Default::default()
+    };
+    let (total_count, props) = fetch_event_props(qmgr, project_id, &params).await;
+    assert_eq!(2, total_count);
+    let names: Vec<&str> = props
+        .iter()
+        .map(|(property, _)| property.as_str())
+        .collect();
+    assert_eq!(names, ["session_timeout_ms", "utm_source"]);
+}
+
+async fn query_event_props_requires_event_names() {
+    let params = EventPropertyParams::default();
+    assert_eq!(
+        Err(ApiError::InvalidRequestParam(
+            "parameter 'event_names' is required".to_string()
+        )),
+        params.valid()
+    );
+}
+
 // fetch all PropertyParentType::Event records without filtering
-async fn query_type_event_no_filters(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_no_filters(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params::default();
 
@@ -85,7 +382,7 @@ async fn query_type_event_no_filters(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Event records with a "properties" (prop name) filter
-async fn query_type_event_properties_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_properties_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
 
     let mut params = Params::default();
@@ -113,7 +410,7 @@ async fn query_type_event_properties_filter(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Event records with a "excluded_properties" (prop name) filter
-async fn query_type_event_excluded_props_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_excluded_props_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
 
     let params = Params {
@@ -148,7 +445,7 @@ async fn query_type_event_excluded_props_filter(qmgr: &Manager, project_id: i32)
 
 // fetch all PropertyParentType::Event records (properties) matching the "event_names"
 // (particular event) filter. This is determined by a JOIN on the posthog_eventproperty table
-async fn query_type_event_names_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_names_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
 
     let params = Params {
@@ -181,7 +478,7 @@ async fn query_type_event_names_filter(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch only PropertyParentType::Event records of type "Numeric" (where is_numerical column == true)
-async fn query_type_event_is_numerical_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_is_numerical_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
 
     let params = Params {
@@ -211,7 +508,7 @@ async fn query_type_event_is_numerical_filter(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch only PropertyParentType::Event records where the property is a feature flag
-async fn query_type_event_is_feature_flag_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_is_feature_flag_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
 
     let params = Params {
@@ -241,7 +538,7 @@ async fn query_type_event_is_feature_flag_filter(qmgr: &Manager, project_id: i32
 }
 
 // fetch all PropertyParentType::Event records that are *not* feature flag props
-async fn query_type_event_is_not_feature_flag_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_event_is_not_feature_flag_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
 
     let params = Params {
@@ -338,7 +635,7 @@ fn expected_event_props_all() -> [&'static str; 9] {
 }
 
 // fetch all PropertyParentType::Person records without filtering
-async fn query_type_person_no_filters(qmgr: &Manager, project_id: i32) {
+async fn query_type_person_no_filters(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Person,
@@ -364,7 +661,7 @@ async fn query_type_person_no_filters(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Person records where props or description contain search term
-async fn query_type_person_simple_search_filter(qmgr: &Manager, project_id: i32) {
+async fn query_type_person_simple_search_filter(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Person,
@@ -407,7 +704,7 @@ fn expected_person_props_all() -> [&'static str; 8] {
 }
 
 // fetch all PropertyParentType::Group records of group_type_index = 0
-async fn query_type_group_index_zero(qmgr: &Manager, project_id: i32) {
+async fn query_type_group_index_zero(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Group,
@@ -441,7 +738,7 @@ async fn query_type_group_index_zero(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Group records of group_type_index = 1
-async fn query_type_group_index_one(qmgr: &Manager, project_id: i32) {
+async fn query_type_group_index_one(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Group,
@@ -475,7 +772,7 @@ async fn query_type_group_index_one(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Group records of group_type_index = 2
-async fn query_type_group_index_two(qmgr: &Manager, project_id: i32) {
+async fn query_type_group_index_two(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Group,
@@ -509,7 +806,7 @@ async fn query_type_group_index_two(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Group records of group_type_index = 3
-async fn query_type_group_index_three(qmgr: &Manager, project_id: i32) {
+async fn query_type_group_index_three(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Group,
@@ -539,7 +836,7 @@ async fn query_type_group_index_three(qmgr: &Manager, project_id: i32) {
 }
 
 // fetch all PropertyParentType::Group records of group_type_index = 4
-async fn query_type_group_index_four(qmgr: &Manager, project_id: i32) {
+async fn query_type_group_index_four(qmgr: &Manager, project_id: i64) {
     let mut qb = sqlx::QueryBuilder::new("");
     let params = Params {
         parent_type: PropertyParentType::Group,
@@ -950,3 +1247,83 @@ async fn bootstrap_seed_data(test_pool: PgPool) -> Result<(), sqlx::Error> {
 
     Ok(())
 }
+
+async fn bootstrap_event_definitions_seed_data(test_pool: PgPool) -> Result<(), sqlx::Error> {
+    let now = Utc::now();
+
+    // posthog_eventdefinition: (id, name, project_id, team_id, last_seen_at, volume_30_day, hidden)
+    // where hidden is None for events without an enterprise event definition
+    let ed_rows = [
+        (
+            Uuid::now_v7(),
+            "$pageview",
+            1,
+            1,
+            now,
+            Some(1000),
+            Some(false),
+        ),
+        (
+            Uuid::now_v7(),
+            "$autocapture",
+            1,
+            1,
+            now - Duration::days(2),
+            Some(500),
+            Some(true),
+        ),
+        (Uuid::now_v7(), "signed_up", 1, 1, now, Some(10), None),
+        (
+            Uuid::now_v7(),
+            "purchase",
+            1,
+            1,
+            now - Duration::days(1),
+            None,
+            None,
+        ),
+        // another project's, so never returned
+        (Uuid::now_v7(), "$pageview", 2, 2, now, Some(1), None),
+    ];
+
+    for row in ed_rows.iter() {
+        let mut args = PgArguments::default();
+        args.add(row.0).unwrap();
+        args.add(row.1).unwrap();
+        args.add(row.2).unwrap();
+        args.add(row.3).unwrap();
+        args.add(row.4).unwrap();
+        args.add(row.5).unwrap();
+
+        sqlx::query_with(
+            r#"
+            INSERT INTO posthog_eventdefinition
+                (id, name, project_id, team_id, last_seen_at, volume_30_day, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, NOW())
+        "#,
+            args,
+        )
+        .execute(&test_pool)
+        .await?;
+
+        let Some(hidden) = row.6 else {
+            continue;
+        };
+        let mut args = PgArguments::default();
+        args.add(row.0).unwrap();
+        args.add(hidden).unwrap();
+
+        sqlx::query_with(
+            r#"
+            INSERT INTO ee_enterpriseeventdefinition
+                (eventdefinition_ptr_id, description, updated_at, verified, hidden)
+                VALUES ($1, 'a fine event indeed', NOW(), false, $2)
+        "#,
+            args,
+        )
+        .execute(&test_pool)
+        .await?;
+    }
+
+    Ok(())
+}
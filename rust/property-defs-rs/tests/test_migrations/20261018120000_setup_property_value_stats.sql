@@ -0,0 +1,16 @@
+-- This mimics the posthog main-db property value stats table, and is only used for testing
+
+CREATE TABLE IF NOT EXISTS posthog_propertyvaluestats (
+    id UUID PRIMARY KEY,
+    name VARCHAR(400) NOT NULL,
+    type SMALLINT NOT NULL DEFAULT 1,
+    group_type_index SMALLINT NULL,
+    sample_values JSONB NOT NULL,
+    top_values JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    project_id BIGINT NULL,
+    team_id INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS posthog_propvaluestats_uniq ON posthog_propertyvaluestats (team_id, type, coalesce(group_type_index, -1), name);
+CREATE INDEX IF NOT EXISTS posthog_propvaluestats_proj ON posthog_propertyvaluestats (coalesce(project_id, team_id), type, coalesce(group_type_index, -1), name);
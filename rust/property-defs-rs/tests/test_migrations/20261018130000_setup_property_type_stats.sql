@@ -0,0 +1,15 @@
+-- This mimics the posthog main-db property type stats table, and is only used for testing
+
+CREATE TABLE IF NOT EXISTS posthog_propertytypestats (
+    id UUID PRIMARY KEY,
+    name VARCHAR(400) NOT NULL,
+    type SMALLINT NOT NULL DEFAULT 1,
+    group_type_index SMALLINT NULL,
+    observations JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    project_id BIGINT NULL,
+    team_id INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS posthog_proptypestats_uniq ON posthog_propertytypestats (team_id, type, coalesce(group_type_index, -1), name);
+CREATE INDEX IF NOT EXISTS posthog_proptypestats_proj ON posthog_propertytypestats (coalesce(project_id, team_id), type, coalesce(group_type_index, -1), name);
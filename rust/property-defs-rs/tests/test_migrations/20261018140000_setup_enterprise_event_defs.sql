@@ -0,0 +1,16 @@
+-- This mimics the posthog main-db enterprise event definitions table, and is only used for testing
+
+CREATE TABLE IF NOT EXISTS ee_enterpriseeventdefinition (
+    eventdefinition_ptr_id UUID PRIMARY KEY,
+    owner_id integer,
+    description text,
+    updated_at timestamp with time zone NOT NULL,
+    updated_by_id integer,
+    verified boolean NOT NULL,
+    verified_at timestamp with time zone,
+    verified_by_id integer,
+    hidden boolean,
+    default_columns text[],
+    deprecated_tags character varying(32)[],
+    tags character varying(32)[]
+);
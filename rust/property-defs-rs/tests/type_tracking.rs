@@ -0,0 +1,310 @@
+use chrono::{DateTime, Duration, Utc};
+use property_defs_rs::{
+    config::{Config, TeamList},
+    type_tracking::{
+        is_conflicting, merge_type_observations, read_type_conflicts, write_type_observations,
+        ObservedType, TypeObservation, TypeStats, TypeTracker,
+    },
+    types::{Event, GroupType, PropertyObservers, PropertyParentType, PropertyValueType},
+    value_sampling::PropertyKey,
+};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn test_config() -> Config {
+    let mut config = Config::init_with_defaults().unwrap();
+    config.enable_type_tracking = true;
+    config.value_sampling_opt_out_teams = TeamList { teams: vec![2] };
+    config.type_tracking_sample_size = 2;
+    config
+}
+
+fn observation(
+    property_type: PropertyValueType,
+    count: u64,
+    seen_at: DateTime<Utc>,
+    sample_values: &[&str],
+) -> TypeObservation {
+    TypeObservation {
+        property_type,
+        count,
+        first_seen_at: seen_at,
+        last_seen_at: seen_at,
+        sample_values: sample_values.iter().map(|v| v.to_string()).collect(),
+    }
+}
+
+fn key(property: &str) -> PropertyKey {
+    PropertyKey {
+        team_id: 1,
+        project_id: 1,
+        parent_type: PropertyParentType::Event,
+        group_type_index: None,
+        property: property.to_string(),
+    }
+}
+
+#[test]
+fn test_type_stats_counts_each_type() {
+    let now = Utc::now();
+    let mut stats = TypeStats::default();
+    for (property_type, value) in [
+        (PropertyValueType::Numeric, "1"),
+        (PropertyValueType::Numeric, "2"),
+        (PropertyValueType::String, "two"),
+        (PropertyValueType::Numeric, "3"),
+        (PropertyValueType::Numeric, "1"),
+    ] {
+        stats.observe(property_type, Some(value.to_string()), now, 2);
+    }
+    stats.observe(PropertyValueType::String, None, now, 2);
+
+    let observations = stats.observations();
+    assert_eq!(
+        observations,
+        vec![
+            observation(PropertyValueType::Numeric, 4, now, &["1", "3"]),
+            observation(PropertyValueType::String, 2, now, &["two"]),
+        ]
+    );
+}
+
+#[test]
+fn test_type_observations_merge() {
+    let earlier = Utc::now() - Duration::hours(1);
+    let later = Utc::now();
+    let older = vec![
+        observation(PropertyValueType::Numeric, 8, earlier, &["1", "2"]),
+        observation(PropertyValueType::String, 1, earlier, &["one"]),
+    ];
+    let newer = vec![
+        observation(PropertyValueType::String, 10, later, &["two", "one"]),
+        observation(PropertyValueType::Boolean, 1, later, &["true"]),
+    ];
+
+    let merged = merge_type_observations(older, newer, 2);
+
+    assert_eq!(
+        merged,
+        vec![
+            TypeObservation {
+                property_type: PropertyValueType::String,
+                count: 11,
+                first_seen_at: earlier,
+                last_seen_at: later,
+                sample_values: vec!["two".to_string(), "one".to_string()],
+            },
+            observation(PropertyValueType::Numeric, 8, earlier, &["1", "2"]),
+            observation(PropertyValueType::Boolean, 1, later, &["true"]),
+        ]
+    );
+}
+
+#[test]
+fn test_conflicts() {
+    let now = Utc::now();
+    let numeric = observation(PropertyValueType::Numeric, 8, now, &[]);
+    let string = observation(PropertyValueType::String, 2, now, &[]);
+
+    assert!(!is_conflicting(&[], None));
+    assert!(!is_conflicting(std::slice::from_ref(&numeric), None));
+    assert!(!is_conflicting(
+        std::slice::from_ref(&numeric),
+        Some("Numeric")
+    ));
+    assert!(is_conflicting(
+        std::slice::from_ref(&string),
+        Some("Numeric")
+    ));
+    assert!(is_conflicting(&[numeric, string], None));
+}
+
+#[test]
+fn test_event_types_are_observed() {
+    let config = test_config();
+    let tracker = TypeTracker::new(&config);
+    let observers = PropertyObservers {
+        value_sampler: None,
+        type_tracker: Some(&tracker),
+    };
+
+    let event = Event {
+        team_id: 1,
+        project_id: 1,
+        event: "purchase".to_string(),
+        properties: Some(
+            json!({"amount": "12.50", "user_email": "a@b.co", "items": [1, 2], "coupon": null})
+                .to_string(),
+        ),
+    };
+    let (_, observations) =
+        event.into_updates_with_observations(config.update_count_skip_threshold, observers);
+
+    // values without a type aren't tracked, and values of likely personal data aren't sampled
+    assert!(observations.values.is_empty());
+    assert_eq!(
+        observations.types,
+        vec![
+            ObservedType {
+                parent_type: PropertyParentType::Event,
+                group_type_index: None,
+                property: "amount".to_string(),
+                property_type: PropertyValueType::String,
+                value: Some("12.50".to_string()),
+            },
+            ObservedType {
+                parent_type: PropertyParentType::Event,
+                group_type_index: None,
+                property: "user_email".to_string(),
+                property_type: PropertyValueType::String,
+                value: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_tracker_drops_samples_of_opted_out_teams() {
+    let tracker = TypeTracker::new(&test_config());
+    let observed = |property: &str| ObservedType {
+        parent_type: PropertyParentType::Event,
+        group_type_index: None,
+        property: property.to_string(),
+        property_type: PropertyValueType::Numeric,
+        value: Some("42".to_string()),
+    };
+    tracker.observe(1, 1, vec![observed("amount")]);
+    tracker.observe(2, 2, vec![observed("amount")]);
+    assert_eq!(tracker.tracked_properties(), 2);
+
+    let mut taken = tracker.take();
+    taken.sort_by_key(|(key, _)| key.team_id);
+    assert_eq!(taken.len(), 2);
+    assert_eq!(taken[0].1[0].sample_values, vec!["42"]);
+    assert!(taken[1].1[0].sample_values.is_empty());
+    assert_eq!(tracker.tracked_properties(), 0);
+}
+
+#[sqlx::test(migrations = "./tests/test_migrations")]
+async fn test_only_conflicts_are_written(db: PgPool) {
+    sqlx::query(
+        r#"
+        INSERT INTO posthog_propertydefinition (id, name, is_numerical, property_type, team_id, project_id, type)
+            VALUES ($1, 'plan', false, 'String', 1, 1, 1)"#,
+    )
+    .bind(Uuid::now_v7())
+    .execute(&db)
+    .await
+    .unwrap();
+
+    let now = Utc::now();
+    let numeric = |count| observation(PropertyValueType::Numeric, count, now, &["42"]);
+    let string = |count| observation(PropertyValueType::String, count, now, &["forty-two"]);
+
+    let written = write_type_observations(
+        &db,
+        &[
+            // consistently typed, so not a conflict
+            (key("$browser"), vec![string(5)]),
+            // seen with two types in the same window
+            (key("amount"), vec![numeric(8), string(2)]),
+            // seen with a type other than its definition's
+            (key("plan"), vec![numeric(3)]),
+        ],
+        5,
+    )
+    .await
+    .unwrap();
+    assert_eq!(written, 2);
+
+    // conflicts already recorded keep counting, even if a window only sees one type
+    let written = write_type_observations(&db, &[(key("amount"), vec![numeric(10)])], 5)
+        .await
+        .unwrap();
+    assert_eq!(written, 1);
+
+    let conflicts = read_type_conflicts(&db, 1, PropertyParentType::Event, -1, None, 5)
+        .await
+        .unwrap();
+    assert_eq!(conflicts.len(), 2);
+
+    let amount = conflicts.iter().find(|c| c.name == "amount").unwrap();
+    assert_eq!(amount.defined_type, None);
+    assert_eq!(amount.observations, vec![numeric(18), string(2)]);
+
+    let plan = conflicts.iter().find(|c| c.name == "plan").unwrap();
+    assert_eq!(plan.defined_type, Some("String".to_string()));
+    assert_eq!(plan.observations, vec![numeric(3)]);
+
+    let single = read_type_conflicts(&db, 1, PropertyParentType::Event, -1, Some("plan"), 5)
+        .await
+        .unwrap();
+    assert_eq!(single.len(), 1);
+    assert!(
+        read_type_conflicts(&db, 1, PropertyParentType::Person, -1, None, 5)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[sqlx::test(migrations = "./tests/test_migrations")]
+async fn test_group_properties_are_tracked_per_group_type(db: PgPool) {
+    // the same property is numeric on one group type and a string on another
+    for (group_type_index, property_type) in [(0, "Numeric"), (1, "String")] {
+        sqlx::query(
+            r#"
+            INSERT INTO posthog_propertydefinition (id, name, is_numerical, property_type, team_id, project_id, type, group_type_index)
+                VALUES ($1, 'size', false, $2, 1, 1, 3, $3)"#,
+        )
+        .bind(Uuid::now_v7())
+        .bind(property_type)
+        .bind(group_type_index as i16)
+        .execute(&db)
+        .await
+        .unwrap();
+    }
+
+    let now = Utc::now();
+    let group_key = |index| PropertyKey {
+        team_id: 1,
+        project_id: 1,
+        parent_type: PropertyParentType::Group,
+        group_type_index: Some(GroupType::Resolved(format!("group-{index}"), index)),
+        property: "size".to_string(),
+    };
+    let numeric = observation(PropertyValueType::Numeric, 4, now, &["42"]);
+    let string = observation(PropertyValueType::String, 2, now, &["large"]);
+
+    // each group type's values match its own definition, so neither is a conflict
+    let written = write_type_observations(
+        &db,
+        &[
+            (group_key(0), vec![numeric.clone()]),
+            (group_key(1), vec![string]),
+        ],
+        5,
+    )
+    .await
+    .unwrap();
+    assert_eq!(written, 0);
+
+    let written = write_type_observations(&db, &[(group_key(1), vec![numeric.clone()])], 5)
+        .await
+        .unwrap();
+    assert_eq!(written, 1);
+
+    let conflicts = read_type_conflicts(&db, 1, PropertyParentType::Group, 1, None, 5)
+        .await
+        .unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].defined_type, Some("String".to_string()));
+    assert_eq!(conflicts[0].observations, vec![numeric]);
+    assert!(
+        read_type_conflicts(&db, 1, PropertyParentType::Group, 0, None, 5)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,308 @@
+use property_defs_rs::{
+    config::{Config, TeamList},
+    types::{Event, GroupType, PropertyObservers, PropertyParentType, PropertyValueType},
+    value_sampling::{
+        read_value_summary, write_value_summaries, CountMinSketch, ObservedValue, PropertyKey,
+        PropertyValueSummary, TopValue, ValueSampler, ValueStats,
+    },
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+fn test_config() -> Config {
+    let mut config = Config::init_with_defaults().unwrap();
+    config.enable_value_sampling = true;
+    config.value_sampling_opt_out_teams = TeamList { teams: vec![2] };
+    config.value_sampling_max_value_length = 16;
+    config.value_sampling_sample_size = 3;
+    config.value_sampling_top_k = 2;
+    config.value_sampling_max_properties = 3;
+    config
+}
+
+fn top(value: &str, count: u64) -> TopValue {
+    TopValue {
+        value: value.to_string(),
+        count,
+    }
+}
+
+fn event(team_id: i32, properties: Value) -> Event {
+    Event {
+        team_id,
+        project_id: team_id as i64,
+        event: "$pageview".to_string(),
+        properties: Some(properties.to_string()),
+    }
+}
+
+#[test]
+fn test_count_min_sketch_never_undercounts() {
+    let mut sketch = CountMinSketch::new(16, 4);
+    for i in 0..1000 {
+        sketch.add(&format!("value-{}", i % 100), 1);
+    }
+    sketch.add("common", 500);
+
+    for i in 0..100 {
+        assert!(sketch.estimate(&format!("value-{i}")) >= 10);
+    }
+    assert!(sketch.estimate("common") >= 500);
+}
+
+#[test]
+fn test_value_stats_keeps_recent_distinct_values() {
+    let mut stats = ValueStats::default();
+    for value in ["a", "b", "a", "c", "d"] {
+        stats.observe(value.to_string(), 3, 10);
+    }
+
+    let summary = stats.summary();
+    assert_eq!(summary.sample_values, vec!["d", "c", "a"]);
+    assert_eq!(
+        summary.top_values,
+        vec![top("a", 2), top("b", 1), top("c", 1), top("d", 1)]
+    );
+}
+
+#[test]
+fn test_value_stats_tracks_top_values_past_top_k() {
+    let mut stats = ValueStats::default();
+    // "Chrome" and "Safari" dominate a long tail of values far larger than top-k
+    for i in 0..2000 {
+        stats.observe("Chrome".to_string(), 5, 2);
+        if i % 2 == 0 {
+            stats.observe("Safari".to_string(), 5, 2);
+        }
+        stats.observe(format!("rare-{i}"), 5, 2);
+    }
+
+    let top_values = stats.summary().top_values;
+    assert_eq!(top_values.len(), 2);
+    assert_eq!(top_values[0].value, "Chrome");
+    assert!(top_values[0].count >= 2000);
+    assert_eq!(top_values[1].value, "Safari");
+    assert!(top_values[1].count >= 1000);
+}
+
+#[test]
+fn test_only_safe_values_are_sampled() {
+    let sampler = ValueSampler::new(&test_config());
+
+    assert_eq!(
+        sampler.sampleable_value(
+            "$browser",
+            Some(&PropertyValueType::String),
+            &json!("Chrome")
+        ),
+        Some("Chrome".to_string())
+    );
+    assert_eq!(
+        sampler.sampleable_value("count", Some(&PropertyValueType::Numeric), &json!(42)),
+        Some("42".to_string())
+    );
+    assert_eq!(
+        sampler.sampleable_value("flag", Some(&PropertyValueType::Boolean), &json!(true)),
+        Some("true".to_string())
+    );
+
+    // too long
+    assert_eq!(
+        sampler.sampleable_value(
+            "$current_url",
+            Some(&PropertyValueType::String),
+            &json!("https://example.com/some/long/path")
+        ),
+        None
+    );
+    // likely personal data
+    assert_eq!(
+        sampler.sampleable_value("Email", Some(&PropertyValueType::String), &json!("a@b.co")),
+        None
+    );
+    assert_eq!(
+        sampler.sampleable_value("$ip", Some(&PropertyValueType::String), &json!("127.0.0.1")),
+        None
+    );
+    // timestamps, non-scalars and empty strings
+    assert_eq!(
+        sampler.sampleable_value(
+            "signed_up",
+            Some(&PropertyValueType::DateTime),
+            &json!("2024-01-01")
+        ),
+        None
+    );
+    assert_eq!(
+        sampler.sampleable_value("list", None, &json!(["a", "b"])),
+        None
+    );
+    assert_eq!(
+        sampler.sampleable_value("blank", Some(&PropertyValueType::String), &json!("")),
+        None
+    );
+}
+
+#[test]
+fn test_event_values_are_sampled_by_parent_type() {
+    let config = test_config();
+    let sampler = ValueSampler::new(&config);
+
+    let observers = PropertyObservers {
+        value_sampler: Some(&sampler),
+        type_tracker: None,
+    };
+    let (updates, observations) = event(
+        1,
+        json!({"$browser": "Chrome", "$set": {"plan": "free"}, "email": "a@b.co"}),
+    )
+    .into_updates_with_observations(config.update_count_skip_threshold, observers);
+
+    assert!(!updates.is_empty());
+    assert!(observations.types.is_empty());
+    assert_eq!(
+        observations.values,
+        vec![
+            ObservedValue {
+                parent_type: PropertyParentType::Event,
+                group_type_index: None,
+                property: "$browser".to_string(),
+                value: "Chrome".to_string(),
+            },
+            ObservedValue {
+                parent_type: PropertyParentType::Person,
+                group_type_index: None,
+                property: "plan".to_string(),
+                value: "free".to_string(),
+            },
+        ]
+    );
+
+    // without a sampler, nothing is sampled
+    let (_, observations) = event(1, json!({"$browser": "Chrome"})).into_updates_with_observations(
+        config.update_count_skip_threshold,
+        PropertyObservers::default(),
+    );
+    assert!(observations.values.is_empty());
+}
+
+#[test]
+fn test_sampler_respects_opt_out_and_property_limit() {
+    let sampler = ValueSampler::new(&test_config());
+    assert!(sampler.is_enabled_for(1));
+    assert!(!sampler.is_enabled_for(2));
+
+    let observed = (0..5)
+        .map(|i| ObservedValue {
+            parent_type: PropertyParentType::Event,
+            group_type_index: None,
+            property: format!("prop-{i}"),
+            value: "x".to_string(),
+        })
+        .collect();
+    sampler.observe(1, 1, observed);
+    assert_eq!(sampler.tracked_properties(), 3);
+
+    let taken = sampler.take();
+    assert_eq!(taken.len(), 3);
+    assert_eq!(sampler.tracked_properties(), 0);
+    assert!(sampler.take().is_empty());
+}
+
+#[test]
+fn test_summaries_merge() {
+    let older = PropertyValueSummary {
+        sample_values: vec!["a".to_string(), "b".to_string()],
+        top_values: vec![top("a", 5), top("b", 4)],
+    };
+    let newer = PropertyValueSummary {
+        sample_values: vec!["c".to_string(), "b".to_string()],
+        top_values: vec![top("b", 3), top("c", 1)],
+    };
+
+    let merged = older.merge(newer, 3, 2);
+
+    assert_eq!(merged.sample_values, vec!["c", "b", "a"]);
+    assert_eq!(merged.top_values, vec![top("b", 7), top("a", 5)]);
+}
+
+#[sqlx::test(migrations = "./tests/test_migrations")]
+async fn test_value_summaries_are_merged_into_postgres(db: PgPool) {
+    let key = PropertyKey {
+        team_id: 1,
+        project_id: 1,
+        parent_type: PropertyParentType::Event,
+        group_type_index: None,
+        property: "$browser".to_string(),
+    };
+    let first = PropertyValueSummary {
+        sample_values: vec!["Chrome".to_string(), "Safari".to_string()],
+        top_values: vec![top("Chrome", 10), top("Safari", 2)],
+    };
+    let second = PropertyValueSummary {
+        sample_values: vec!["Firefox".to_string()],
+        top_values: vec![top("Firefox", 3), top("Safari", 2)],
+    };
+
+    let written = write_value_summaries(&db, &[(key.clone(), first)], 3, 2)
+        .await
+        .unwrap();
+    assert_eq!(written, 1);
+    write_value_summaries(&db, &[(key, second)], 3, 2)
+        .await
+        .unwrap();
+
+    let (summary, _) = read_value_summary(&db, 1, PropertyParentType::Event, -1, "$browser", 3, 2)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(summary.sample_values, vec!["Firefox", "Chrome", "Safari"]);
+    assert_eq!(
+        summary.top_values,
+        vec![top("Chrome", 10), top("Safari", 4)]
+    );
+
+    let missing = read_value_summary(&db, 1, PropertyParentType::Person, -1, "$browser", 3, 2)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[sqlx::test(migrations = "./tests/test_migrations")]
+async fn test_group_value_summaries_are_kept_per_group_type(db: PgPool) {
+    let key = |index| PropertyKey {
+        team_id: 1,
+        project_id: 1,
+        parent_type: PropertyParentType::Group,
+        group_type_index: Some(GroupType::Resolved("organization".to_string(), index)),
+        property: "name".to_string(),
+    };
+    let summary = |value: &str| PropertyValueSummary {
+        sample_values: vec![value.to_string()],
+        top_values: vec![top(value, 1)],
+    };
+
+    let written = write_value_summaries(
+        &db,
+        &[(key(0), summary("Acme")), (key(1), summary("Globex"))],
+        3,
+        2,
+    )
+    .await
+    .unwrap();
+    assert_eq!(written, 2);
+
+    for (index, value) in [(0, "Acme"), (1, "Globex")] {
+        let (summary, _) =
+            read_value_summary(&db, 1, PropertyParentType::Group, index, "name", 3, 2)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(summary.sample_values, vec![value]);
+    }
+
+    let missing = read_value_summary(&db, 1, PropertyParentType::Group, 2, "name", 3, 2)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}